@@ -0,0 +1,181 @@
+//! Compare building and rendering the random scene of the binary from boxed
+//! objects with `Arc` materials, from `Object`s, and from a `SceneArena`.
+//!
+//! Run with `cargo run --release --example arena`.
+
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use raytracer::camera::Camera;
+use raytracer::materials::{Dielectric, Lambertian, MaterialExt, Metal};
+use raytracer::objects::arena::SceneArena;
+use raytracer::objects::bvh::{BvhBuildStrategy, BvhNode};
+use raytracer::objects::object::Object;
+use raytracer::objects::sphere::Sphere;
+use raytracer::objects::Hitable;
+use raytracer::render::{render_with_stats, RenderSettings};
+use raytracer::vec3::Vec3;
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::Instant;
+
+const BUILDS: usize = 200;
+
+#[derive(Clone, Copy)]
+enum Material {
+    Lambertian(Vec3),
+    Metal(Vec3, f32),
+    Dielectric(f32),
+}
+
+impl Material {
+    fn to_arc(self) -> Arc<dyn MaterialExt> {
+        match self {
+            Material::Lambertian(albedo) => Arc::new(Lambertian::new(albedo)),
+            Material::Metal(albedo, fuzz) => Arc::new(Metal::new(albedo, fuzz)),
+            Material::Dielectric(index) => Arc::new(Dielectric::new(index)),
+        }
+    }
+}
+
+// The spheres of the random scene of the binary, without the fog.
+fn spheres() -> Vec<(Vec3, f32, Material)> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut spheres = vec![(
+        Vec3(0., -1000., 0.),
+        1000.,
+        Material::Lambertian(Vec3(0.5, 0.5, 0.5)),
+    )];
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat = rng.gen::<f32>();
+            let center = Vec3(
+                a as f32 + 0.6 * rng.gen::<f32>(),
+                0.2,
+                b as f32 + 0.6 * rng.gen::<f32>(),
+            );
+            let big = [Vec3(4., 1., 0.), Vec3(-4., 1., 0.), Vec3(0., 1., 0.)];
+            if big.iter().all(|&c| (center - c).length() > 1.2) {
+                let material = if choose_mat < 0.8 {
+                    Material::Lambertian(Vec3(rng.gen(), rng.gen(), rng.gen()))
+                } else if choose_mat < 0.95 {
+                    let albedo = Vec3(
+                        0.5 * (1. + rng.gen::<f32>()),
+                        0.5 * (1. + rng.gen::<f32>()),
+                        0.5 * (1. + rng.gen::<f32>()),
+                    );
+                    Material::Metal(albedo, 0.5 * rng.gen::<f32>())
+                } else {
+                    Material::Dielectric(1.5)
+                };
+                spheres.push((center, 0.2, material));
+            }
+        }
+    }
+    spheres.push((Vec3(0., 1., 0.), 1., Material::Dielectric(1.5)));
+    spheres.push((
+        Vec3(-4., 1., 0.),
+        1.,
+        Material::Lambertian(Vec3(0.1, 0.8, 0.1)),
+    ));
+    spheres.push((
+        Vec3(4., 1., 0.),
+        1.,
+        Material::Metal(Vec3(0.7, 0.6, 0.5), 0.),
+    ));
+    spheres
+}
+
+fn boxed(spheres: &[(Vec3, f32, Material)]) -> BvhNode {
+    let objects: Vec<Box<dyn Hitable>> = spheres
+        .iter()
+        .map(|&(center, radius, material)| {
+            Box::new(Sphere::new(center, radius, material.to_arc())) as Box<dyn Hitable>
+        })
+        .collect();
+    BvhNode::with_strategy(objects, BvhBuildStrategy::Median)
+}
+
+fn objects(spheres: &[(Vec3, f32, Material)]) -> BvhNode<Object> {
+    let objects: Vec<Object> = spheres
+        .iter()
+        .map(|&(center, radius, material)| Sphere::new(center, radius, material.to_arc()).into())
+        .collect();
+    BvhNode::from_objects(objects, BvhBuildStrategy::Median)
+}
+
+fn arena(spheres: &[(Vec3, f32, Material)]) -> SceneArena {
+    let mut arena = SceneArena::new();
+    for &(center, radius, material) in spheres {
+        let id = match material {
+            Material::Lambertian(albedo) => arena.add_material(Lambertian::new(albedo)),
+            Material::Metal(albedo, fuzz) => arena.add_material(Metal::new(albedo, fuzz)),
+            Material::Dielectric(index) => arena.add_material(Dielectric::new(index)),
+        };
+        arena.add_sphere(center, radius, id);
+    }
+    arena
+}
+
+// Print the time per build of the scene.
+fn time_build<T>(name: &str, build: impl Fn() -> T) {
+    let start = Instant::now();
+    for _ in 0..BUILDS {
+        black_box(build());
+    }
+    println!(
+        "{:>7} build:  {:>8.1} us",
+        name,
+        start.elapsed().as_secs_f64() * 1e6 / BUILDS as f64
+    );
+}
+
+// Render the scene and print the rays traced per second.
+fn time_render(name: &str, world: &dyn Hitable, camera: &Camera, settings: &RenderSettings) {
+    let (_, stats) = render_with_stats(world, camera, settings);
+    println!(
+        "{:>7} render: {:>8.0} rays/s ({:.2?})",
+        name,
+        stats.rays_per_second(),
+        stats.time
+    );
+}
+
+fn main() {
+    let spheres = spheres();
+    println!("{} spheres", spheres.len());
+
+    time_build("boxed", || boxed(&spheres));
+    time_build("objects", || objects(&spheres));
+    time_build("arena", || {
+        let arena = arena(&spheres);
+        black_box(arena.bvh(BvhBuildStrategy::Median));
+        arena
+    });
+
+    let settings = RenderSettings {
+        width: 300,
+        height: 200,
+        samples: 8,
+        ..RenderSettings::default()
+    };
+    let camera = Camera::new(
+        Vec3(13., 2., 3.),
+        Vec3(0., 0., 0.),
+        Vec3(0., 1., 0.),
+        20.,
+        1.5,
+        0.1,
+        10.,
+    );
+    let arena = arena(&spheres);
+    let worlds: [(&str, Box<dyn Hitable + '_>); 3] = [
+        ("boxed", Box::new(boxed(&spheres))),
+        ("objects", Box::new(objects(&spheres))),
+        ("arena", Box::new(arena.bvh(BvhBuildStrategy::Median))),
+    ];
+    for _ in 0..2 {
+        for (name, world) in &worlds {
+            time_render(name, world.as_ref(), &camera, &settings);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Compare rendering the cover scene with its objects stored as trait objects
+//! and as the `Object` enum, in a bounding volume hierarchy either way.
+//!
+//! Run with `cargo run --release --example dispatch`.
+
+use rand::prelude::*;
+use rand::rngs::{SmallRng, StdRng};
+use raytracer::camera::Camera;
+use raytracer::materials::{Dielectric, Lambertian, MaterialExt, Metal};
+use raytracer::objects::bvh::{BvhBuildStrategy, BvhNode};
+use raytracer::objects::object::Object;
+use raytracer::objects::sphere::Sphere;
+use raytracer::objects::Hitable;
+use raytracer::render::color;
+use raytracer::vec3::Vec3;
+use std::sync::Arc;
+use std::time::Instant;
+
+// The spheres of the cover scene, with a fixed seed.
+fn random_spheres() -> Vec<Sphere> {
+    let mut rng = StdRng::seed_from_u64(2018);
+    let mut spheres = vec![Sphere::new(
+        Vec3(0., -1000., 0.),
+        1000.,
+        Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))),
+    )];
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat = rng.gen::<f32>();
+            let center = Vec3(
+                a as f32 + 0.6 * rng.gen::<f32>(),
+                0.2,
+                b as f32 + 0.6 * rng.gen::<f32>(),
+            );
+            let material: Arc<dyn MaterialExt> = if choose_mat < 0.8 {
+                Arc::new(Lambertian::new(Vec3(rng.gen(), rng.gen(), rng.gen())))
+            } else if choose_mat < 0.95 {
+                let albedo = Vec3(
+                    0.5 * (1. + rng.gen::<f32>()),
+                    0.5 * (1. + rng.gen::<f32>()),
+                    0.5 * (1. + rng.gen::<f32>()),
+                );
+                Arc::new(Metal::new(albedo, 0.5 * rng.gen::<f32>()))
+            } else {
+                Arc::new(Dielectric::new(1.5))
+            };
+            spheres.push(Sphere::new(center, 0.2, material));
+        }
+    }
+    spheres.push(Sphere::new(
+        Vec3(0., 1., 0.),
+        1.,
+        Arc::new(Dielectric::new(1.5)),
+    ));
+    spheres.push(Sphere::new(
+        Vec3(-4., 1., 0.),
+        1.,
+        Arc::new(Lambertian::new(Vec3(0.1, 0.8, 0.1))),
+    ));
+    spheres.push(Sphere::new(
+        Vec3(4., 1., 0.),
+        1.,
+        Arc::new(Metal::new(Vec3(0.7, 0.6, 0.5), 0.)),
+    ));
+    spheres
+}
+
+// Render a 300x200 image with 4 samples per pixel from the camera of the
+// cover scene, returning the average color.
+fn render(world: &dyn Hitable) -> Vec3 {
+    let (nx, ny, ns) = (300, 200, 4);
+    let camera = Camera::new(
+        Vec3(13., 2., 3.),
+        Vec3(0., 0., 0.),
+        Vec3(0., 1., 0.),
+        20.,
+        nx as f32 / ny as f32,
+        0.1,
+        10.,
+    );
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut sum = Vec3(0., 0., 0.);
+    for j in 0..ny {
+        for i in 0..nx {
+            for _ in 0..ns {
+                let u = (i as f32 + rng.gen::<f32>()) / nx as f32;
+                let v = (j as f32 + rng.gen::<f32>()) / ny as f32;
+                let ray = camera.get_ray(u, v, &mut rng);
+                sum += color(&ray, world, 0, &mut rng);
+            }
+        }
+    }
+    sum / (nx * ny * ns) as f32
+}
+
+fn main() {
+    let boxed = BvhNode::new(
+        random_spheres()
+            .into_iter()
+            .map(|sphere| Box::new(sphere) as Box<dyn Hitable>)
+            .collect(),
+    );
+    let objects = BvhNode::from_objects(
+        random_spheres().into_iter().map(Object::from).collect(),
+        BvhBuildStrategy::Median,
+    );
+
+    for (name, world) in [
+        ("trait objects", &boxed as &dyn Hitable),
+        ("enum", &objects as &dyn Hitable),
+    ] {
+        let start = Instant::now();
+        let average = render(world);
+        println!(
+            "{:>13}: {:>8.1} ms ({:?})",
+            name,
+            start.elapsed().as_secs_f64() * 1000.,
+            average
+        );
+    }
+}
@@ -0,0 +1,71 @@
+//! Render glass spheres of increasing roughness in front of a striped wall,
+//! from perfectly smooth to heavily frosted, into `output/frosted_glass.png`.
+//!
+//! Run with `cargo run --release --example frosted_glass`.
+
+use raytracer::camera::Camera;
+use raytracer::materials::{Dielectric, Lambertian, MaterialExt};
+use raytracer::objects::bvh::BvhNode;
+use raytracer::objects::cuboid::Cuboid;
+use raytracer::objects::sphere::Sphere;
+use raytracer::objects::Hitable;
+use raytracer::render::{render_with_stats, RenderSettings};
+use raytracer::vec3::Vec3;
+use std::sync::Arc;
+
+const ROUGHNESS: [f32; 4] = [0., 0.05, 0.2, 0.5];
+
+fn scene() -> BvhNode {
+    let mut objects: Vec<Box<dyn Hitable>> = Vec::new();
+    let ground: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+    objects.push(Box::new(Sphere::new(Vec3(0., -1000., 0.), 1000., ground)));
+    // Thin stripes behind the spheres show how much the glass blurs.
+    for i in 0..24 {
+        let color = if i % 2 == 0 {
+            Vec3(0.8, 0.1, 0.1)
+        } else {
+            Vec3(0.9, 0.9, 0.9)
+        };
+        let x = -6. + 0.5 * i as f32;
+        objects.push(Box::new(Cuboid::new(
+            Vec3(x, 0., -3.),
+            Vec3(x + 0.5, 3., -2.8),
+            Arc::new(Lambertian::new(color)),
+        )));
+    }
+    for (i, &roughness) in ROUGHNESS.iter().enumerate() {
+        let glass = Dielectric::new(1.5).with_roughness(roughness);
+        objects.push(Box::new(Sphere::new(
+            Vec3(-3.3 + 2.2 * i as f32, 1., 0.),
+            1.,
+            Arc::new(glass),
+        )));
+    }
+    BvhNode::new(objects)
+}
+
+fn main() {
+    let settings = RenderSettings {
+        width: 800,
+        height: 300,
+        samples: 64,
+        ..RenderSettings::default()
+    };
+    let camera = Camera::new(
+        Vec3(0., 1.5, 9.),
+        Vec3(0., 1., 0.),
+        Vec3(0., 1., 0.),
+        30.,
+        settings.width as f32 / settings.height as f32,
+        0.,
+        9.,
+    );
+    let (buffer, stats) = render_with_stats(&scene(), &camera, &settings);
+    println!("Rendered in {:.2?}", stats.time);
+    let path = std::path::Path::new("output/frosted_glass.png");
+    let (width, height) = (settings.width as u32, settings.height as u32);
+    match image::save_buffer(path, &buffer, width, height, image::ColorType::Rgb8) {
+        Ok(_) => println!("Image written to {:?}!", &path),
+        Err(e) => eprintln!("There was a problem in writing the image: {}", e),
+    }
+}
@@ -0,0 +1,161 @@
+//! Compare tracing the primary rays of the cover scene one by one with
+//! tracing them in packets of 2x2 pixels, in a BVH and in a `SphereList`,
+//! and rendering with and without packets.
+//!
+//! Run with `cargo run --release --example packets`.
+
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use raytracer::camera::Camera;
+use raytracer::materials::{Dielectric, Lambertian, MaterialExt, Metal};
+use raytracer::objects::bvh::BvhNode;
+use raytracer::objects::sphere::Sphere;
+use raytracer::objects::sphere_list::SphereList;
+use raytracer::objects::Hitable;
+use raytracer::ray::{Ray, PACKET_SIZE};
+use raytracer::render::{render_with_stats, RenderSettings};
+use raytracer::vec3::Vec3;
+use std::sync::Arc;
+use std::time::Instant;
+
+// The spheres of the cover scene, with a fixed seed.
+fn random_spheres() -> Vec<(Vec3, f32, Arc<dyn MaterialExt>)> {
+    let mut rng = StdRng::seed_from_u64(2018);
+    let mut spheres: Vec<(Vec3, f32, Arc<dyn MaterialExt>)> = vec![(
+        Vec3(0., -1000., 0.),
+        1000.,
+        Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))),
+    )];
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat = rng.gen::<f32>();
+            let center = Vec3(
+                a as f32 + 0.6 * rng.gen::<f32>(),
+                0.2,
+                b as f32 + 0.6 * rng.gen::<f32>(),
+            );
+            let material: Arc<dyn MaterialExt> = if choose_mat < 0.8 {
+                Arc::new(Lambertian::new(Vec3(rng.gen(), rng.gen(), rng.gen())))
+            } else if choose_mat < 0.95 {
+                let albedo = Vec3(
+                    0.5 * (1. + rng.gen::<f32>()),
+                    0.5 * (1. + rng.gen::<f32>()),
+                    0.5 * (1. + rng.gen::<f32>()),
+                );
+                Arc::new(Metal::new(albedo, 0.5 * rng.gen::<f32>()))
+            } else {
+                Arc::new(Dielectric::new(1.5))
+            };
+            spheres.push((center, 0.2, material));
+        }
+    }
+    spheres.push((Vec3(0., 1., 0.), 1., Arc::new(Dielectric::new(1.5))));
+    spheres.push((
+        Vec3(-4., 1., 0.),
+        1.,
+        Arc::new(Lambertian::new(Vec3(0.1, 0.8, 0.1))),
+    ));
+    spheres.push((
+        Vec3(4., 1., 0.),
+        1.,
+        Arc::new(Metal::new(Vec3(0.7, 0.6, 0.5), 0.)),
+    ));
+    spheres
+}
+
+// The primary rays through the centers of the pixels of a 600x400 image,
+// grouped into 2x2 blocks.
+fn primary_rays(camera: &Camera) -> Vec<[Ray; PACKET_SIZE]> {
+    let (nx, ny) = (600, 400);
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut packets = Vec::new();
+    for j in (0..ny).step_by(2) {
+        for i in (0..nx).step_by(2) {
+            packets.push(std::array::from_fn(|lane| {
+                let u = (i + lane % 2) as f32 / nx as f32;
+                let v = (j + lane / 2) as f32 / ny as f32;
+                camera.get_ray(u, v, &mut rng)
+            }));
+        }
+    }
+    packets
+}
+
+// Print the time to trace all rays, one by one and in packets.
+fn time_trace(name: &str, world: &dyn Hitable, packets: &[[Ray; PACKET_SIZE]]) {
+    let start = Instant::now();
+    let mut hits = 0;
+    for rays in packets {
+        for ray in rays {
+            hits += world.intersect(ray, 0.001, f32::MAX).is_some() as usize;
+        }
+    }
+    let scalar = start.elapsed();
+    let start = Instant::now();
+    let mut packet_hits = 0;
+    for rays in packets {
+        for hit in world.intersect_packet(rays, 0.001, f32::MAX).iter() {
+            packet_hits += hit.is_some() as usize;
+        }
+    }
+    let packet = start.elapsed();
+    assert_eq!(hits, packet_hits);
+    println!(
+        "{:>12} trace:  {:>8.2?} scalar, {:>8.2?} packets",
+        name, scalar, packet
+    );
+}
+
+// Print the time to render, with and without packets.
+fn time_render(name: &str, world: &dyn Hitable, camera: &Camera) {
+    let settings = RenderSettings {
+        width: 300,
+        height: 200,
+        samples: 8,
+        ..RenderSettings::default()
+    };
+    let (scalar_image, scalar) = render_with_stats(world, camera, &settings);
+    let packet_settings = RenderSettings {
+        packets: true,
+        ..settings
+    };
+    let (packet_image, packet) = render_with_stats(world, camera, &packet_settings);
+    assert!(scalar_image == packet_image);
+    println!(
+        "{:>12} render: {:>8.2?} scalar, {:>8.2?} packets",
+        name, scalar.time, packet.time
+    );
+}
+
+fn main() {
+    let spheres = random_spheres();
+    let bvh = BvhNode::new(
+        spheres
+            .iter()
+            .map(|(center, radius, material)| {
+                Box::new(Sphere::new(*center, *radius, material.clone())) as Box<dyn Hitable>
+            })
+            .collect(),
+    );
+    let list = SphereList::new(spheres);
+    let camera = Camera::new(
+        Vec3(13., 2., 3.),
+        Vec3(0., 0., 0.),
+        Vec3(0., 1., 0.),
+        20.,
+        1.5,
+        0.,
+        10.,
+    );
+
+    let packets = primary_rays(&camera);
+    let worlds: [(&str, &dyn Hitable); 2] = [("bvh", &bvh), ("sphere list", &list)];
+    for _ in 0..2 {
+        for (name, world) in worlds.iter() {
+            time_trace(name, *world, &packets);
+        }
+    }
+    for (name, world) in worlds.iter() {
+        time_render(name, *world, &camera);
+    }
+}
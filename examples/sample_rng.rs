@@ -0,0 +1,76 @@
+//! Time the samples of a small scene with the random number generators that
+//! can be passed to the camera and the materials.
+//!
+//! Run with `cargo run --release --example sample_rng`.
+
+use rand::prelude::*;
+use rand::rngs::SmallRng;
+use raytracer::camera::Camera;
+use raytracer::materials::{Dielectric, Lambertian, Metal};
+use raytracer::objects::sphere::Sphere;
+use raytracer::objects::{Hitable, HitableList};
+use raytracer::render::color;
+use raytracer::vec3::Vec3;
+use std::sync::Arc;
+use std::time::Instant;
+
+const SAMPLES: usize = 200_000;
+
+// A ground with a diffuse, a metal and a glass sphere, so that every material
+// drawing random numbers is hit.
+fn scene() -> HitableList {
+    HitableList::new(vec![
+        Box::new(Sphere::new(
+            Vec3(0., -1000., 0.),
+            1000.,
+            Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))),
+        )),
+        Box::new(Sphere::new(
+            Vec3(-2., 1., 0.),
+            1.,
+            Arc::new(Lambertian::new(Vec3(0.1, 0.8, 0.1))),
+        )),
+        Box::new(Sphere::new(
+            Vec3(0., 1., 0.),
+            1.,
+            Arc::new(Dielectric::new(1.5)),
+        )),
+        Box::new(Sphere::new(
+            Vec3(2., 1., 0.),
+            1.,
+            Arc::new(Metal::new(Vec3(0.7, 0.6, 0.5), 0.3)),
+        )),
+    ])
+}
+
+// Trace `SAMPLES` samples through random points of the image, returning the
+// time per sample in nanoseconds and the average color.
+fn trace(world: &dyn Hitable, camera: &Camera, rng: &mut dyn RngCore) -> (f64, Vec3) {
+    let start = Instant::now();
+    let mut sum = Vec3(0., 0., 0.);
+    for _ in 0..SAMPLES {
+        let ray = camera.get_ray(rng.gen(), rng.gen(), rng);
+        sum += color(&ray, world, 0, rng);
+    }
+    let nanoseconds = start.elapsed().as_secs_f64() * 1e9 / SAMPLES as f64;
+    (nanoseconds, sum / SAMPLES as f32)
+}
+
+fn main() {
+    let world = scene();
+    let camera = Camera::new(
+        Vec3(0., 2., 8.),
+        Vec3(0., 1., 0.),
+        Vec3(0., 1., 0.),
+        40.,
+        1.5,
+        0.1,
+        8.,
+    );
+
+    let (thread_rng, average) = trace(&world, &camera, &mut rand::thread_rng());
+    println!("thread_rng: {:>6.0} ns/sample ({:?})", thread_rng, average);
+    let (small_rng, average) = trace(&world, &camera, &mut SmallRng::seed_from_u64(0));
+    println!("SmallRng:   {:>6.0} ns/sample ({:?})", small_rng, average);
+    println!("speedup:    {:.2}x", thread_rng / small_rng);
+}
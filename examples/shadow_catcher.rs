@@ -0,0 +1,66 @@
+//! Render spheres standing on a shadow catcher, which shows the sky behind
+//! it except for their shadows, into `output/shadow_catcher.png`.
+//!
+//! Run with `cargo run --release --example shadow_catcher`.
+
+use raytracer::camera::Camera;
+use raytracer::materials::{Dielectric, Lambertian, Metal, ShadowCatcher};
+use raytracer::objects::rect::Rect;
+use raytracer::objects::sphere::Sphere;
+use raytracer::objects::{Hitable, HitableList};
+use raytracer::render::{render_with_stats, RenderSettings};
+use raytracer::vec3::Vec3;
+use std::sync::Arc;
+
+fn scene() -> HitableList {
+    let ground = Rect::new(
+        Vec3(-100., 0., -100.),
+        Vec3(0., 0., 200.),
+        Vec3(200., 0., 0.),
+        Arc::new(ShadowCatcher::new(Vec3(0.5, 0.5, 0.5))),
+    );
+    HitableList::new(vec![
+        Box::new(ground) as Box<dyn Hitable>,
+        Box::new(Sphere::new(
+            Vec3(-2.2, 1., 0.),
+            1.,
+            Arc::new(Lambertian::new(Vec3(0.8, 0.2, 0.1))),
+        )),
+        Box::new(Sphere::new(
+            Vec3(0., 1., 0.),
+            1.,
+            Arc::new(Dielectric::new(1.5)),
+        )),
+        Box::new(Sphere::new(
+            Vec3(2.2, 1., 0.),
+            1.,
+            Arc::new(Metal::new(Vec3(0.8, 0.8, 0.9), 0.05)),
+        )),
+    ])
+}
+
+fn main() {
+    let settings = RenderSettings {
+        width: 800,
+        height: 400,
+        samples: 64,
+        ..RenderSettings::default()
+    };
+    let camera = Camera::new(
+        Vec3(0., 3., 9.),
+        Vec3(0., 0.8, 0.),
+        Vec3(0., 1., 0.),
+        35.,
+        settings.width as f32 / settings.height as f32,
+        0.,
+        9.,
+    );
+    let (buffer, stats) = render_with_stats(&scene(), &camera, &settings);
+    println!("Rendered in {:.2?}", stats.time);
+    let path = std::path::Path::new("output/shadow_catcher.png");
+    let (width, height) = (settings.width as u32, settings.height as u32);
+    match image::save_buffer(path, &buffer, width, height, image::ColorType::Rgb8) {
+        Ok(_) => println!("Image written to {:?}!", &path),
+        Err(e) => eprintln!("There was a problem in writing the image: {}", e),
+    }
+}
@@ -0,0 +1,106 @@
+//! Compare tracing the spheres of the cover scene stored as a list of boxed
+//! spheres with storing them in a `SphereList`.
+//!
+//! Run with `cargo run --release --example sphere_list`.
+
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use raytracer::materials::{Dielectric, Lambertian, MaterialExt, Metal};
+use raytracer::objects::sphere::Sphere;
+use raytracer::objects::sphere_list::SphereList;
+use raytracer::objects::{Hitable, HitableList};
+use raytracer::ray::Ray;
+use raytracer::vec3::{cross, unit_vector, Vec3};
+use std::sync::Arc;
+use std::time::Instant;
+
+// The spheres of the cover scene, with a fixed seed.
+fn random_spheres() -> Vec<(Vec3, f32, Arc<dyn MaterialExt>)> {
+    let mut rng = StdRng::seed_from_u64(2018);
+    let mut spheres: Vec<(Vec3, f32, Arc<dyn MaterialExt>)> = vec![(
+        Vec3(0., -1000., 0.),
+        1000.,
+        Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))),
+    )];
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat = rng.gen::<f32>();
+            let center = Vec3(
+                a as f32 + 0.6 * rng.gen::<f32>(),
+                0.2,
+                b as f32 + 0.6 * rng.gen::<f32>(),
+            );
+            let material: Arc<dyn MaterialExt> = if choose_mat < 0.8 {
+                Arc::new(Lambertian::new(Vec3(rng.gen(), rng.gen(), rng.gen())))
+            } else if choose_mat < 0.95 {
+                let albedo = Vec3(
+                    0.5 * (1. + rng.gen::<f32>()),
+                    0.5 * (1. + rng.gen::<f32>()),
+                    0.5 * (1. + rng.gen::<f32>()),
+                );
+                Arc::new(Metal::new(albedo, 0.5 * rng.gen::<f32>()))
+            } else {
+                Arc::new(Dielectric::new(1.5))
+            };
+            spheres.push((center, 0.2, material));
+        }
+    }
+    spheres.push((Vec3(0., 1., 0.), 1., Arc::new(Dielectric::new(1.5))));
+    spheres.push((
+        Vec3(-4., 1., 0.),
+        1.,
+        Arc::new(Lambertian::new(Vec3(0.1, 0.8, 0.1))),
+    ));
+    spheres.push((
+        Vec3(4., 1., 0.),
+        1.,
+        Arc::new(Metal::new(Vec3(0.7, 0.6, 0.5), 0.)),
+    ));
+    spheres
+}
+
+// Trace primary rays through a 600x400 image from the camera position of
+// the cover scene, returning the sum of the hit parameters.
+fn trace(world: &dyn Hitable) -> f32 {
+    let (nx, ny) = (600, 400);
+    let origin = Vec3(13., 2., 3.);
+    let w = unit_vector(&origin);
+    let u = unit_vector(&Vec3(w.z(), 0., -w.x()));
+    let v = cross(&w, &u);
+    let mut sum = 0.;
+    for j in 0..ny {
+        for i in 0..nx {
+            let s = (i as f32 / nx as f32 - 0.5) * 0.52;
+            let t = (j as f32 / ny as f32 - 0.5) * 0.35;
+            let ray = Ray::new(origin, s * u + t * v - w);
+            if let Some(hit) = world.intersect(&ray, 0.001, f32::MAX) {
+                sum += hit.parameter;
+            }
+        }
+    }
+    sum
+}
+
+fn main() {
+    let spheres = random_spheres();
+    let boxed = HitableList::new(
+        spheres
+            .iter()
+            .map(|(center, radius, material)| {
+                Box::new(Sphere::new(*center, *radius, material.clone())) as Box<dyn Hitable>
+            })
+            .collect(),
+    );
+    let list = HitableList::new(vec![Box::new(SphereList::new(spheres))]);
+
+    for (name, world) in [("boxed spheres", &boxed), ("sphere list", &list)] {
+        let start = Instant::now();
+        let sum = trace(world);
+        println!(
+            "{:>14}: {:>8.1} ms (checksum {})",
+            name,
+            start.elapsed().as_secs_f64() * 1000.,
+            sum
+        );
+    }
+}
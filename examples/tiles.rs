@@ -0,0 +1,103 @@
+//! Compare rendering the pixels of an image with a parallel `flat_map`, one
+//! small vector per pixel, with rendering it in tiles.
+//!
+//! Run with `cargo run --release --example tiles`.
+
+use rand::prelude::*;
+use rand::rngs::SmallRng;
+use rayon::prelude::*;
+use raytracer::camera::Camera;
+use raytracer::materials::{Dielectric, Lambertian, Metal};
+use raytracer::objects::sphere::Sphere;
+use raytracer::objects::{Hitable, HitableList};
+use raytracer::render::{color, render, RenderSettings};
+use raytracer::vec3::Vec3;
+use std::sync::Arc;
+use std::time::Instant;
+
+// Render the image pixel by pixel, as the binary used to.
+fn render_pixels(world: &dyn Hitable, camera: &Camera, settings: &RenderSettings) -> Vec<u8> {
+    let (nx, ny, ns) = (settings.width, settings.height, settings.samples);
+    (0..ny)
+        .into_par_iter()
+        .flat_map(|y| (0..nx).into_par_iter().map(move |x| (x, y)))
+        .map_init(SmallRng::from_entropy, |rng, (x, y)| {
+            let y = ny - y - 1;
+            let mut col = Vec3(0., 0., 0.);
+            for _ in 0..ns {
+                let u = (x as f32 + rng.gen::<f32>()) / nx as f32;
+                let v = (y as f32 + rng.gen::<f32>()) / ny as f32;
+                let r = camera.get_ray(u, v, rng);
+                col += color(&r, world, 0, rng);
+            }
+            col /= ns as f32;
+            vec![
+                (col.r().sqrt() * 254.99) as u8,
+                (col.g().sqrt() * 254.99) as u8,
+                (col.b().sqrt() * 254.99) as u8,
+            ]
+        })
+        .flatten()
+        .collect()
+}
+
+fn main() {
+    let world = HitableList::new(vec![
+        Box::new(Sphere::new(
+            Vec3(0., -1000., 0.),
+            1000.,
+            Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))),
+        )),
+        Box::new(Sphere::new(
+            Vec3(-2., 1., 0.),
+            1.,
+            Arc::new(Lambertian::new(Vec3(0.1, 0.8, 0.1))),
+        )),
+        Box::new(Sphere::new(
+            Vec3(0., 1., 0.),
+            1.,
+            Arc::new(Dielectric::new(1.5)),
+        )),
+        Box::new(Sphere::new(
+            Vec3(2., 1., 0.),
+            1.,
+            Arc::new(Metal::new(Vec3(0.7, 0.6, 0.5), 0.3)),
+        )),
+    ]);
+    let settings = RenderSettings {
+        width: 600,
+        height: 400,
+        samples: 4,
+        ..RenderSettings::default()
+    };
+    let camera = Camera::new(
+        Vec3(0., 2., 8.),
+        Vec3(0., 1., 0.),
+        Vec3(0., 1., 0.),
+        40.,
+        settings.width as f32 / settings.height as f32,
+        0.1,
+        8.,
+    );
+
+    let start = Instant::now();
+    let pixels = render_pixels(&world, &camera, &settings);
+    println!(
+        "per pixel: {:>8.1} ms",
+        start.elapsed().as_secs_f64() * 1000.
+    );
+    for tile_size in [8, 32, 128] {
+        let settings = RenderSettings {
+            tile_size,
+            ..settings.clone()
+        };
+        let start = Instant::now();
+        let tiles = render(&world, &camera, &settings);
+        println!(
+            "tiles {:>3}: {:>8.1} ms",
+            tile_size,
+            start.elapsed().as_secs_f64() * 1000.
+        );
+        assert_eq!(tiles.len(), pixels.len());
+    }
+}
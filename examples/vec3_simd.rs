@@ -0,0 +1,89 @@
+//! Time `dot`, `cross` and the intersection of rays with spheres, to compare
+//! the representations of `Vec3` with and without the `simd` feature.
+//!
+//! Run with `cargo run --release --example vec3_simd` and
+//! `cargo run --release --example vec3_simd --features simd`.
+
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use raytracer::materials::{Lambertian, MaterialExt};
+use raytracer::objects::sphere::Sphere;
+use raytracer::objects::Hitable;
+use raytracer::ray::Ray;
+use raytracer::vec3::{cross, dot, Vec3};
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::Instant;
+
+const ROUNDS: usize = 200;
+
+fn random_vectors(rng: &mut StdRng, n: usize) -> Vec<Vec3> {
+    (0..n)
+        .map(|_| {
+            Vec3(
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+            )
+        })
+        .collect()
+}
+
+// Run `f` and print the time it took.
+fn time<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = black_box(f());
+    println!(
+        "{:>7}: {:>8.1} ms",
+        name,
+        start.elapsed().as_secs_f64() * 1000.
+    );
+    result
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(44);
+    let a = random_vectors(&mut rng, 10_000);
+    let b = random_vectors(&mut rng, 10_000);
+
+    time("dot", || {
+        let mut sum = 0.;
+        for _ in 0..ROUNDS {
+            for (v1, v2) in black_box(&a).iter().zip(&b) {
+                sum += dot(v1, v2);
+            }
+        }
+        sum
+    });
+
+    time("cross", || {
+        let mut sum = Vec3::default();
+        for _ in 0..ROUNDS {
+            for (v1, v2) in black_box(&a).iter().zip(&b) {
+                sum += cross(v1, v2);
+            }
+        }
+        sum
+    });
+
+    let material: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+    let spheres: Vec<Sphere> = random_vectors(&mut rng, 1000)
+        .into_iter()
+        .map(|center| Sphere::new(center * 10., 0.5, material.clone()))
+        .collect();
+    let rays: Vec<Ray> = random_vectors(&mut rng, 1000)
+        .into_iter()
+        .map(|direction| Ray::new(Vec3(0., 0., 0.), direction))
+        .collect();
+    time("spheres", || {
+        let mut hits = 0;
+        for ray in black_box(&rays) {
+            for sphere in &spheres {
+                if sphere.intersect(ray, 0.001, f32::MAX).is_some() {
+                    hits += 1;
+                }
+            }
+        }
+        hits
+    });
+}
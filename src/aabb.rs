@@ -0,0 +1,395 @@
+//! Axis-aligned bounding boxes.
+//!
+//! A bounding box encloses an object, such that a ray missing the box is
+//! known to miss the object as well. Testing a ray against a box is much
+//! cheaper than against most objects, which acceleration structures use to
+//! skip whole groups of objects at once.
+
+use crate::ray::Ray;
+use crate::ray::PACKET_SIZE;
+use crate::transform::Mat4;
+use crate::vec3::Vec3;
+
+/// A box whose faces are perpendicular to the coordinate axes.
+///
+/// The box may have zero extent along some axes, e.g. to enclose a flat
+/// rectangle lying in a coordinate plane.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    min: Vec3,
+    max: Vec3,
+}
+
+impl Aabb {
+    /// Create the `Aabb` spanned by two opposite corners `a` and `b`.
+    ///
+    /// ```
+    /// use raytracer::aabb::Aabb;
+    /// use raytracer::vec3::Vec3;
+    /// let aabb = Aabb::new(Vec3(1., 0., 1.), Vec3(0., 1., 0.));
+    /// assert_eq!(aabb.min(), &Vec3(0., 0., 0.));
+    /// assert_eq!(aabb.max(), &Vec3(1., 1., 1.));
+    /// ```
+    pub fn new(a: Vec3, b: Vec3) -> Aabb {
+        Aabb {
+            min: Vec3(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z())),
+            max: Vec3(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z())),
+        }
+    }
+
+    /// Create the smallest `Aabb` containing all `points`, or `None` if there are none.
+    ///
+    /// ```
+    /// # use raytracer::aabb::Aabb;
+    /// # use raytracer::vec3::Vec3;
+    /// let points = [Vec3(0., 2., 0.), Vec3(1., 0., -1.), Vec3(-1., 1., 0.)];
+    /// let aabb = Aabb::from_points(&points).unwrap();
+    /// assert_eq!(aabb, Aabb::new(Vec3(-1., 0., -1.), Vec3(1., 2., 0.)));
+    /// assert!(Aabb::from_points(&[]).is_none());
+    /// ```
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Vec3>) -> Option<Aabb> {
+        let mut points = points.into_iter();
+        let first = *points.next()?;
+        Some(points.fold(Aabb::new(first, first), |aabb, p| {
+            surrounding_box(&aabb, &Aabb::new(*p, *p))
+        }))
+    }
+
+    /// Access the corner of an `Aabb` with the smallest coordinates.
+    pub fn min(&self) -> &Vec3 {
+        &self.min
+    }
+
+    /// Access the corner of an `Aabb` with the largest coordinates.
+    pub fn max(&self) -> &Vec3 {
+        &self.max
+    }
+
+    /// Return the surface area of an `Aabb`.
+    ///
+    /// The chance that a random ray hitting a box also hits a smaller box
+    /// inside it is proportional to the ratio of their surface areas, which
+    /// is used to judge the quality of acceleration structures.
+    ///
+    /// ```
+    /// # use raytracer::aabb::Aabb;
+    /// # use raytracer::vec3::Vec3;
+    /// let aabb = Aabb::new(Vec3(0., 0., 0.), Vec3(1., 2., 3.));
+    /// assert_eq!(aabb.surface_area(), 22.);
+    /// ```
+    pub fn surface_area(&self) -> f32 {
+        let d = self.max - self.min;
+        2. * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
+    /// Return the eight corners of an `Aabb`.
+    pub fn corners(&self) -> [Vec3; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            Vec3(a.x(), a.y(), a.z()),
+            Vec3(b.x(), a.y(), a.z()),
+            Vec3(a.x(), b.y(), a.z()),
+            Vec3(b.x(), b.y(), a.z()),
+            Vec3(a.x(), a.y(), b.z()),
+            Vec3(b.x(), a.y(), b.z()),
+            Vec3(a.x(), b.y(), b.z()),
+            Vec3(b.x(), b.y(), b.z()),
+        ]
+    }
+
+    /// Return the `Aabb` enclosing this box after the affine transformation `matrix`.
+    ///
+    /// ```
+    /// # use raytracer::aabb::Aabb;
+    /// # use raytracer::transform::Mat4;
+    /// # use raytracer::vec3::Vec3;
+    /// let aabb = Aabb::new(Vec3(0., 0., 0.), Vec3(1., 1., 1.));
+    /// let moved = aabb.transformed(&Mat4::translation(Vec3(1., 2., 3.)));
+    /// assert_eq!(moved, Aabb::new(Vec3(1., 2., 3.), Vec3(2., 3., 4.)));
+    /// ```
+    pub fn transformed(&self, matrix: &Mat4) -> Aabb {
+        let corners = self.corners();
+        let transformed: Vec<Vec3> = corners.iter().map(|c| matrix.transform_point(c)).collect();
+        Aabb::from_points(&transformed).unwrap()
+    }
+
+    /// Check whether the ray hits the box for a parameter between `t_min` and `t_max`.
+    ///
+    /// Each pair of parallel faces limits the ray to an interval of
+    /// parameters, and the ray hits the box if these intervals overlap. The
+    /// face entered first along each axis is known from the sign of the
+    /// direction, and the reciprocal direction cached by the ray saves the
+    /// divisions. For a ray parallel to a pair of faces, the reciprocal of
+    /// the zero direction component is infinite, which makes the interval
+    /// either unbounded or empty without having to test for it. Rays which
+    /// lie exactly in the plane of a face count as hitting the box.
+    ///
+    /// ```
+    /// # use raytracer::aabb::Aabb;
+    /// # use raytracer::ray::Ray;
+    /// # use raytracer::vec3::Vec3;
+    /// let aabb = Aabb::new(Vec3(-1., -1., -3.), Vec3(1., 1., -2.));
+    /// let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., -1.));
+    /// assert!(aabb.hit(&ray, 0., f32::MAX));
+    /// assert!(!aabb.hit(&ray, 0., 1.));
+    /// let ray = Ray::new(Vec3(2., 0., 0.), Vec3(0., 0., -1.));
+    /// assert!(!aabb.hit(&ray, 0., f32::MAX));
+    /// ```
+    pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.clip(ray, t_min, t_max).is_some()
+    }
+
+    /// Return the parameters where the ray enters and leaves the box, limited
+    /// to the range from `t_min` to `t_max`, or `None` if it misses the box
+    /// within that range.
+    ///
+    /// The box is hit under the same conditions as for `hit`.
+    ///
+    /// ```
+    /// # use raytracer::aabb::Aabb;
+    /// # use raytracer::ray::Ray;
+    /// # use raytracer::vec3::Vec3;
+    /// let aabb = Aabb::new(Vec3(-1., -1., -3.), Vec3(1., 1., -2.));
+    /// let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., -1.));
+    /// assert_eq!(aabb.clip(&ray, 0., f32::MAX), Some((2., 3.)));
+    /// assert_eq!(aabb.clip(&ray, 2.5, 10.), Some((2.5, 3.)));
+    /// assert_eq!(aabb.clip(&ray, 0., 1.), None);
+    /// ```
+    pub fn clip(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, f32)> {
+        let origin = ray.origin();
+        let inv_direction = ray.inv_direction();
+        let sign = ray.sign();
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+        for (o, inv_d, negative, min, max) in [
+            (
+                origin.x(),
+                inv_direction.x(),
+                sign[0],
+                self.min.x(),
+                self.max.x(),
+            ),
+            (
+                origin.y(),
+                inv_direction.y(),
+                sign[1],
+                self.min.y(),
+                self.max.y(),
+            ),
+            (
+                origin.z(),
+                inv_direction.z(),
+                sign[2],
+                self.min.z(),
+                self.max.z(),
+            ),
+        ] {
+            let (near, far) = if negative { (max, min) } else { (min, max) };
+            // A ray lying in a face gives `0 * inf`, whose NaN is ignored by
+            // `max` and `min`, such that the interval is unbounded.
+            t_min = t_min.max((near - o) * inv_d);
+            t_max = t_max.min((far - o) * inv_d);
+            // Equal bounds still hit boxes of zero extent.
+            if t_max < t_min {
+                return None;
+            }
+        }
+        Some((t_min, t_max))
+    }
+
+    /// Check for each of the `rays` whether it hits the box within `t_min`
+    /// and its own limit in `t_max`.
+    ///
+    /// The rays are tested together, one axis after another, but each gives
+    /// exactly the result of `hit`.
+    ///
+    /// ```
+    /// # use raytracer::aabb::Aabb;
+    /// # use raytracer::ray::Ray;
+    /// # use raytracer::vec3::Vec3;
+    /// let aabb = Aabb::new(Vec3(-1., -1., -3.), Vec3(1., 1., -2.));
+    /// let rays = [
+    ///     Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., -1.)),
+    ///     Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., -1.)),
+    ///     Ray::new(Vec3(2., 0., 0.), Vec3(0., 0., -1.)),
+    ///     Ray::new(Vec3(0., 0., -5.), Vec3(0., 0.2, 1.)),
+    /// ];
+    /// let hits = aabb.hit_packet(&rays, 0., &[f32::MAX, 1., f32::MAX, f32::MAX]);
+    /// assert_eq!(hits, [true, false, false, true]);
+    /// ```
+    pub fn hit_packet(
+        &self,
+        rays: &[Ray; PACKET_SIZE],
+        t_min: f32,
+        t_max: &[f32; PACKET_SIZE],
+    ) -> [bool; PACKET_SIZE] {
+        let components = |v: &Vec3| [v.x(), v.y(), v.z()];
+        let (min, max) = (components(&self.min), components(&self.max));
+        let origins = rays.each_ref().map(|ray| components(ray.origin()));
+        let inv_directions = rays.each_ref().map(|ray| components(ray.inv_direction()));
+        let mut t_min = [t_min; PACKET_SIZE];
+        let mut t_max = *t_max;
+        for axis in 0..3 {
+            for i in 0..PACKET_SIZE {
+                let (near, far) = if rays[i].sign()[axis] {
+                    (max[axis], min[axis])
+                } else {
+                    (min[axis], max[axis])
+                };
+                // The same arithmetic as `clip`, whose early exit does not
+                // change the result, as the interval only shrinks.
+                let (o, inv_d) = (origins[i][axis], inv_directions[i][axis]);
+                t_min[i] = t_min[i].max((near - o) * inv_d);
+                t_max[i] = t_max[i].min((far - o) * inv_d);
+            }
+        }
+        std::array::from_fn(|i| t_max[i] >= t_min[i])
+    }
+}
+
+/// Return the smallest `Aabb` containing both `a` and `b`.
+///
+/// ```
+/// # use raytracer::aabb::{surrounding_box, Aabb};
+/// # use raytracer::vec3::Vec3;
+/// let a = Aabb::new(Vec3(0., 0., 0.), Vec3(1., 1., 1.));
+/// let b = Aabb::new(Vec3(2., -1., 0.), Vec3(3., 0., 0.5));
+/// let both = surrounding_box(&a, &b);
+/// assert_eq!(both, Aabb::new(Vec3(0., -1., 0.), Vec3(3., 1., 1.)));
+/// ```
+pub fn surrounding_box(a: &Aabb, b: &Aabb) -> Aabb {
+    Aabb {
+        min: Vec3(
+            a.min.x().min(b.min.x()),
+            a.min.y().min(b.min.y()),
+            a.min.z().min(b.min.z()),
+        ),
+        max: Vec3(
+            a.max.x().max(b.max.x()),
+            a.max.y().max(b.max.y()),
+            a.max.z().max(b.max.z()),
+        ),
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> Aabb {
+        Aabb::new(Vec3(0., 0., 0.), Vec3(1., 1., 1.))
+    }
+
+    #[test]
+    // Test rays hitting and missing a box from all sides.
+    fn test_aabb_hit() {
+        let aabb = unit_box();
+        let directions = [
+            Vec3(1., 0., 0.),
+            Vec3(-1., 0., 0.),
+            Vec3(0., 1., 0.),
+            Vec3(0., -1., 0.),
+            Vec3(0., 0., 1.),
+            Vec3(0., 0., -1.),
+        ];
+        for d in directions.iter() {
+            let ray = Ray::new(Vec3(0.5, 0.5, 0.5) - 3. * *d, *d);
+            assert!(aabb.hit(&ray, 0., f32::MAX));
+            // The box lies between the parameters 2.5 and 3.5.
+            assert!(aabb.hit(&ray, 3.4, 5.));
+            assert!(!aabb.hit(&ray, 0., 2.4));
+            assert!(!aabb.hit(&ray, 3.6, 5.));
+            // Pointing away from the box.
+            let ray = Ray::new(Vec3(0.5, 0.5, 0.5) - 3. * *d, -*d);
+            assert!(!aabb.hit(&ray, 0., f32::MAX));
+        }
+        // A diagonal ray passing a corner.
+        let ray = Ray::new(Vec3(2., 2., 2.), Vec3(-1., -1., 0.));
+        assert!(!aabb.hit(&ray, 0., f32::MAX));
+        let ray = Ray::new(Vec3(2., 2., 0.5), Vec3(-1., -1., 0.));
+        assert!(aabb.hit(&ray, 0., f32::MAX));
+    }
+
+    #[test]
+    // Test that rays starting inside the box hit it, in any direction.
+    fn test_aabb_ray_inside() {
+        let aabb = unit_box();
+        for d in [
+            Vec3(1., 0., 0.),
+            Vec3(0., -1., 0.),
+            Vec3(0.3, -0.2, 0.9),
+            Vec3(-1., -1., -1.),
+        ] {
+            let ray = Ray::new(Vec3(0.2, 0.7, 0.4), d);
+            assert!(aabb.hit(&ray, 0., f32::MAX));
+            assert!(aabb.hit(&ray, 0.001, f32::MAX));
+        }
+    }
+
+    #[test]
+    // Test rays parallel to the faces of a box, which have zero direction components.
+    fn test_aabb_parallel_rays() {
+        let aabb = unit_box();
+        let ray = Ray::new(Vec3(0.5, 0.5, -2.), Vec3(0., 0., 1.));
+        assert!(aabb.hit(&ray, 0., f32::MAX));
+        let ray = Ray::new(Vec3(1.5, 0.5, -2.), Vec3(0., 0., 1.));
+        assert!(!aabb.hit(&ray, 0., f32::MAX));
+        let ray = Ray::new(Vec3(-0.5, 0.5, -2.), Vec3(0., 0., 1.));
+        assert!(!aabb.hit(&ray, 0., f32::MAX));
+        // Negative zero components are handled like positive ones.
+        let ray = Ray::new(Vec3(0.5, 0.5, -2.), Vec3(-0., -0., 1.));
+        assert!(aabb.hit(&ray, 0., f32::MAX));
+        for d in [Vec3(0., 0., 1.), Vec3(-0., 0., 1.), Vec3(0., -0., -1.)] {
+            let ray = Ray::new(Vec3(1.5, 0.5, -2.), d);
+            assert!(!aabb.hit(&ray, f32::MIN, f32::MAX));
+            let ray = Ray::new(Vec3(0.5, -0.5, -2.), d);
+            assert!(!aabb.hit(&ray, f32::MIN, f32::MAX));
+            let ray = Ray::new(Vec3(0.5, 0.5, -2.), d);
+            assert!(aabb.hit(&ray, f32::MIN, f32::MAX));
+        }
+    }
+
+    #[test]
+    // Test rays whose origin lies on a face of the box.
+    fn test_aabb_origin_on_face() {
+        let aabb = unit_box();
+        // Entering and leaving the box through the face.
+        let ray = Ray::new(Vec3(0., 0.5, 0.5), Vec3(1., 0.2, 0.));
+        assert!(aabb.hit(&ray, 0., f32::MAX));
+        let ray = Ray::new(Vec3(0., 0.5, 0.5), Vec3(-1., 0.2, 0.));
+        assert!(aabb.hit(&ray, 0., f32::MAX));
+        assert!(!aabb.hit(&ray, 0.001, f32::MAX));
+        // Lying in the plane of the face, with positive and negative zeros.
+        for d in [Vec3(0., 1., 0.), Vec3(-0., 1., 0.), Vec3(0., -0.5, 1.)] {
+            let ray = Ray::new(Vec3(0., 0.5, 0.5), d);
+            assert!(aabb.hit(&ray, 0., f32::MAX));
+            let ray = Ray::new(Vec3(1., 0.5, 0.5), -d);
+            assert!(aabb.hit(&ray, 0., f32::MAX));
+        }
+        // In the plane of a face, but beside the box.
+        let ray = Ray::new(Vec3(0., 2., 0.5), Vec3(-0., 0., 1.));
+        assert!(!aabb.hit(&ray, 0., f32::MAX));
+    }
+
+    #[test]
+    // Test boxes of zero extent along one or all axes.
+    fn test_aabb_zero_extent() {
+        let flat = Aabb::new(Vec3(0., 0., -2.), Vec3(1., 1., -2.));
+        let ray = Ray::new(Vec3(0.5, 0.5, 0.), Vec3(0., 0., -1.));
+        assert!(flat.hit(&ray, 0., f32::MAX));
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0.25, 0.25, -1.));
+        assert!(flat.hit(&ray, 0., f32::MAX));
+        let ray = Ray::new(Vec3(1.5, 0.5, 0.), Vec3(0., 0., -1.));
+        assert!(!flat.hit(&ray, 0., f32::MAX));
+
+        let point = Aabb::new(Vec3(1., 1., 1.), Vec3(1., 1., 1.));
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(1., 1., 1.));
+        assert!(point.hit(&ray, 0., f32::MAX));
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(1., 1., 0.9));
+        assert!(!point.hit(&ray, 0., f32::MAX));
+    }
+}
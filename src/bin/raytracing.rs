@@ -1,48 +1,36 @@
 use rand::prelude::*;
-use rayon::prelude::*;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::time::Duration;
 
 use raytracer::camera::Camera;
+use raytracer::film::AnimationPipeline;
 use raytracer::materials::Dielectric;
+use raytracer::materials::Isotropic;
 use raytracer::materials::Lambertian;
 use raytracer::materials::Metal;
+use raytracer::objects::arena::SceneArena;
+use raytracer::objects::bvh::BvhBuildStrategy;
+use raytracer::objects::constant_medium::ConstantMedium;
+use raytracer::objects::object::Object;
+use raytracer::objects::scene::Scene;
 use raytracer::objects::sphere::Sphere;
 use raytracer::objects::Hitable;
-use raytracer::objects::HitableList;
-use raytracer::ray::Ray;
+use raytracer::render::{
+    render_into, render_with_progress, Parallelism, Progress, RenderMode, RenderSettings,
+    RussianRoulette, TileOrder,
+};
 use raytracer::vec3::*;
 
-// For now the color is a simple gradient background.
-fn color(r: &Ray, world: &Box<dyn Hitable>, depth: i32) -> Vec3 {
-    match world.intersect(&r, 0.001, std::f32::MAX) {
-        Some(hit) => {
-            let scatter_info = hit.material.scatter(&r, &hit);
-            match scatter_info {
-                Some((scattered, attenuation)) => {
-                    if depth < 50 {
-                        attenuation * color(&scattered, world, depth + 1)
-                    } else {
-                        Default::default()
-                    }
-                }
-                None => Default::default(),
-            }
-        }
-        None => {
-            let unit_direction = unit_vector(&r.direction());
-            let t = 0.5 * (unit_direction.y() + 1.);
-            (1. - t) * Vec3(1., 1., 1.) + t * Vec3(0.5, 0.7, 1.0)
-        }
-    }
-}
-
-fn random_scene() -> Box<dyn Hitable> {
-    let mut list: Vec<Box<dyn Hitable>> = vec![];
-    list.push(Box::new(Sphere::new(
-        Vec3(0., -1000., 0.),
-        1000.,
-        Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))),
-    )));
+// The scene is stored in an arena, which holds the spheres in a single vector
+// and their materials by index.
+fn random_scene() -> SceneArena {
+    let mut arena = SceneArena::new();
+    let ground = arena.add_material(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+    arena.add_sphere(Vec3(0., -1000., 0.), 1000., ground);
 
     // Random number generator
     let mut rng = rand::thread_rng();
@@ -64,69 +52,100 @@ fn random_scene() -> Box<dyn Hitable> {
             {
                 if choose_mat < 0.8 {
                     // diffuse
-                    list.push(Box::new(Sphere::new(
-                        center,
-                        0.2,
-                        Arc::new(Lambertian::new(Vec3(
-                            rng.gen::<f32>(),
-                            rng.gen::<f32>(),
-                            rng.gen::<f32>(),
-                        ))),
+                    let material = arena.add_material(Lambertian::new(Vec3(
+                        rng.gen::<f32>(),
+                        rng.gen::<f32>(),
+                        rng.gen::<f32>(),
                     )));
+                    arena.add_sphere(center, 0.2, material);
                 } else if choose_mat < 0.95 {
                     // metal
-                    list.push(Box::new(Sphere::new(
-                        center,
-                        0.2,
-                        Arc::new(Metal::new(
-                            Vec3(
-                                0.5 * (1. + rng.gen::<f32>()),
-                                0.5 * (1. + rng.gen::<f32>()),
-                                0.5 * (1. + rng.gen::<f32>()),
-                            ),
-                            0.5 * rng.gen::<f32>(),
-                        )),
-                    )));
+                    let material = arena.add_material(Metal::new(
+                        Vec3(
+                            0.5 * (1. + rng.gen::<f32>()),
+                            0.5 * (1. + rng.gen::<f32>()),
+                            0.5 * (1. + rng.gen::<f32>()),
+                        ),
+                        0.5 * rng.gen::<f32>(),
+                    ));
+                    arena.add_sphere(center, 0.2, material);
                 } else {
                     // glass
-                    list.push(Box::new(Sphere::new(
-                        center,
-                        0.2,
-                        Arc::new(Dielectric::new(1.5)),
-                    )));
+                    let material = arena.add_material(Dielectric::new(1.5));
+                    arena.add_sphere(center, 0.2, material);
                 }
             }
         }
     }
 
-    list.push(Box::new(Sphere::new(
-        Vec3(0., 1., 0.),
-        1.0,
-        Arc::new(Dielectric::new(1.5)),
-    )));
-    list.push(Box::new(Sphere::new(
-        Vec3(-4., 1., 0.),
-        1.0,
-        Arc::new(Lambertian::new(Vec3(0.1, 0.8, 0.1))),
-    )));
-    list.push(Box::new(Sphere::new(
-        Vec3(4., 1., 0.),
-        1.0,
-        Arc::new(Metal::new(Vec3(0.7, 0.6, 0.5), 0.0)),
-    )));
+    let glass = arena.add_material(Dielectric::new(1.5));
+    arena.add_sphere(Vec3(0., 1., 0.), 1.0, glass);
+    let green = arena.add_material(Lambertian::new(Vec3(0.1, 0.8, 0.1)));
+    arena.add_sphere(Vec3(-4., 1., 0.), 1.0, green);
+    let metal = arena.add_material(Metal::new(Vec3(0.7, 0.6, 0.5), 0.0));
+    arena.add_sphere(Vec3(4., 1., 0.), 1.0, metal);
+    // A thin fog surrounding the metal sphere.
+    let fog = Arc::new(Isotropic::new(Vec3(0.9, 0.9, 0.9)));
+    arena.add_object(Object::Dyn(Box::new(ConstantMedium::new(
+        Box::new(Sphere::new(Vec3(4., 1., 0.), 1.3, fog.clone())),
+        0.3,
+        fog,
+    ))));
+    arena
+}
 
-    Box::new(HitableList::new(list)) as Box<dyn Hitable>
+// Render the frames of an animation seen by the cameras `camera` returns
+// for the frame indices, writing each one to an image file while the next
+// one is rendered.
+fn animate(
+    world: &dyn Hitable,
+    settings: &RenderSettings,
+    frames: usize,
+    camera: impl Fn(usize) -> Camera,
+) {
+    let pipeline = AnimationPipeline::new(settings.width, settings.height, 2);
+    let cancel = AtomicBool::new(false);
+    let written = pipeline.run(
+        frames,
+        &cancel,
+        |frame, film| {
+            print!("\rRendering frame {} of {}   ", frame + 1, frames);
+            io::stdout().flush().ok();
+            render_into(film, world, &camera(frame), settings);
+        },
+        |frame, film| film.save(Path::new(&format!("output/frame_{:04}.png", frame))),
+    );
+    println!();
+    match written {
+        Ok(frames) => println!("{} frames written to \"output\"!", frames),
+        Err(e) => eprintln!("There was a problem in writing the animation: {}", e),
+    }
 }
 
 fn main() {
     println!("Raytracer in Rust!");
 
-    let nx = 1200;
-    let ny = 800;
-    let ns = 150;
+    let settings = RenderSettings {
+        width: 1200,
+        height: 800,
+        samples: 150,
+        tile_size: 32,
+        tile_order: TileOrder::Scanline,
+        threads: None,
+        seed: 0,
+        parallelism: Parallelism::Tiles,
+        mode: RenderMode::Shaded,
+        roulette: Some(RussianRoulette { min_depth: 3 }),
+        min_throughput: 0.,
+        packets: false,
+    };
+    let (nx, ny) = (settings.width, settings.height);
 
     // Setup the scene.
-    let world = random_scene();
+    let arena = random_scene();
+    // Building the hierarchy once makes every ray much cheaper to trace, and
+    // the lights collected from it are sampled at every diffuse bounce.
+    let world = Scene::new(arena.bvh(BvhBuildStrategy::Median));
 
     // Set up the camera
     let look_from = Vec3(13., 2., 3.);
@@ -143,31 +162,55 @@ fn main() {
         dist_to_focus,
     );
 
-    let buffer = (0..ny)
-        .into_par_iter()
-        .flat_map(|y| (0..nx).into_par_iter().map(move |x| (x, y)))
-        .flat_map(|(x, y)| {
-            let y = ny - y - 1;
-
-            // Random number generator
-            let mut rng = rand::thread_rng();
-
-            let mut col = Vec3(0., 0., 0.);
-            for _ in 0..ns {
-                let u = (x as f32 + rng.gen::<f32>()) / nx as f32;
-                let v = (y as f32 + rng.gen::<f32>()) as f32 / ny as f32;
-
-                let r = cam.get_ray(u, v);
-                col += color(&r, &world, 0);
-            }
-            col /= ns as f32;
-            let r = (col.r().sqrt() * 254.99) as u8;
-            let g = (col.g().sqrt() * 254.99) as u8;
-            let b = (col.b().sqrt() * 254.99) as u8;
+    // With `--animate <frames>`, the camera circles the scene instead.
+    let mut args = std::env::args().skip_while(|arg| arg != "--animate");
+    if args.next().is_some() {
+        let frames = args
+            .next()
+            .and_then(|frames| frames.parse().ok())
+            .unwrap_or(24);
+        animate(&world, &settings, frames, |frame| {
+            let angle = 2. * std::f32::consts::PI * frame as f32 / frames as f32;
+            let (sin, cos) = angle.sin_cos();
+            let look_from = Vec3(
+                cos * look_from.x() - sin * look_from.z(),
+                look_from.y(),
+                sin * look_from.x() + cos * look_from.z(),
+            );
+            Camera::new(
+                look_from,
+                look_at,
+                Vec3(0., 1., 0.),
+                20.,
+                nx as f32 / ny as f32,
+                aperture,
+                dist_to_focus,
+            )
+        });
+        return;
+    }
 
-            vec![r, g, b]
-        })
-        .collect::<Vec<_>>();
+    // Keep a single line with the progress up to date.
+    let report = |progress: Progress| {
+        let eta = progress
+            .eta
+            .map_or_else(|| "?".to_string(), |eta| format!("{}s", eta.as_secs()));
+        print!(
+            "\rRendering: {:5.1}%, {} left   ",
+            100. * progress.fraction(),
+            eta
+        );
+        io::stdout().flush().ok();
+    };
+    let interval = Duration::from_millis(250);
+    let (buffer, stats) = render_with_progress(&world, &cam, &settings, interval, report);
+    println!();
+    println!(
+        "Rendered {} primary rays in {:.2?} ({:.0} rays/s)",
+        stats.primary_rays,
+        stats.time,
+        stats.rays_per_second()
+    );
 
     let path = std::path::Path::new("output/image.png");
 
@@ -175,4 +218,20 @@ fn main() {
         Ok(_) => println!("Image written to {:?}!", &path),
         Err(e) => eprintln!("There was a problem in writing the image: {}", e),
     }
+
+    // Show where the traversal of the scene is expensive.
+    #[cfg(feature = "stats")]
+    {
+        let heatmap_settings = RenderSettings {
+            samples: 4,
+            mode: RenderMode::TraversalHeatmap,
+            ..settings
+        };
+        let heatmap = raytracer::render::render(&world, &cam, &heatmap_settings);
+        let path = std::path::Path::new("output/heatmap.png");
+        match image::save_buffer(path, &heatmap, nx as u32, ny as u32, image::ColorType::Rgb8) {
+            Ok(_) => println!("Heatmap written to {:?}!", &path),
+            Err(e) => eprintln!("There was a problem in writing the heatmap: {}", e),
+        }
+    }
 }
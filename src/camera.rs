@@ -1,15 +1,16 @@
 use rand::prelude::*;
+use std::error::Error;
+use std::f32::consts::PI;
+use std::fmt;
 
 use crate::ray::Ray;
 use crate::vec3::cross;
+use crate::vec3::dot;
 use crate::vec3::unit_vector;
 use crate::vec3::Vec3;
 
 // Choose a vector on the unit disk.
-fn random_in_unit_disk() -> Vec3 {
-    // Random number generator
-    let mut rng = rand::thread_rng();
-
+fn random_in_unit_disk(rng: &mut dyn RngCore) -> Vec3 {
     loop {
         let p = 2.0 * Vec3(rng.gen::<f32>(), rng.gen::<f32>(), 0.0) - Vec3(1., 1., 0.);
         if p.squared_length() < 1.0 {
@@ -18,6 +19,93 @@ fn random_in_unit_disk() -> Vec3 {
     }
 }
 
+// Choose a vector uniformly on the regular polygon of `blades` corners
+// inscribed in the unit disk, the first one rotated by `rotation` degrees
+// from the x axis, by choosing one of the equal triangles between the center
+// and two consecutive corners, and a point on it.
+fn random_in_polygon(blades: u8, rotation: f32, rng: &mut dyn RngCore) -> Vec3 {
+    let corner = |i: f32| {
+        let angle = rotation.to_radians() + 2. * PI * i / blades as f32;
+        Vec3(angle.cos(), angle.sin(), 0.)
+    };
+    let i = rng.gen_range(0..blades) as f32;
+    // The square root spreads the points evenly from the center to the edge.
+    let distance = rng.gen::<f32>().sqrt();
+    let along = rng.gen::<f32>();
+    distance * ((1. - along) * corner(i) + along * corner(i + 1.))
+}
+
+/// The shape of the aperture of a camera, which is the shape defocused
+/// highlights take.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ApertureShape {
+    /// A round aperture.
+    #[default]
+    Circle,
+    /// A regular polygon of `blades` corners, at least 3, inscribed in the
+    /// round aperture, with the first corner rotated by `rotation` degrees
+    /// counter-clockwise from the horizontal.
+    Polygon { blades: u8, rotation: f32 },
+}
+
+/// Error returned when a camera is created from invalid parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CameraError {
+    /// A polygonal aperture has fewer than 3 blades.
+    TooFewBlades { blades: u8 },
+}
+
+impl fmt::Display for CameraError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CameraError::TooFewBlades { blades } => write!(
+                f,
+                "invalid aperture of {} blades: a polygon needs at least 3",
+                blades
+            ),
+        }
+    }
+}
+
+impl Error for CameraError {}
+
+/// A model of projection, which maps the points of the image to the rays
+/// leaving the camera through them.
+///
+/// The renderer traces the scene through any camera model, such that other
+/// projections than the perspective of `Camera` can be added outside of it.
+///
+/// ```
+/// use raytracer::camera::CameraModel;
+/// use raytracer::ray::Ray;
+/// use raytracer::vec3::Vec3;
+/// use rand::RngCore;
+///
+/// /// A camera looking down the z axis, whose rays leave the image plane in parallel.
+/// struct Parallel;
+///
+/// impl CameraModel for Parallel {
+///     fn get_ray(&self, x_frac: f32, y_frac: f32, _rng: &mut dyn RngCore) -> Option<Ray> {
+///         Some(Ray::new(Vec3(x_frac - 0.5, y_frac - 0.5, 0.), Vec3(0., 0., -1.)))
+///     }
+/// }
+///
+/// let ray = Parallel.get_ray(0.5, 1., &mut rand::thread_rng()).unwrap();
+/// assert_eq!(*ray.origin(), Vec3(0., 0.5, 0.));
+/// ```
+pub trait CameraModel: Send + Sync {
+    // Subtraiting `Send` & `Sync` such that cameras can be shared between the
+    // `rayon` threads of the renderer.
+
+    /// Return the ray leaving the camera through the point `(x_frac, y_frac)`
+    /// of the image, where `(0, 0)` is the lower left and `(1, 1)` the upper
+    /// right corner, drawing any random numbers from `rng`.
+    ///
+    /// Return `None` for points of the image the camera does not see, e.g.
+    /// outside the image circle of a fisheye lens, which are rendered black.
+    fn get_ray(&self, x_frac: f32, y_frac: f32, rng: &mut dyn RngCore) -> Option<Ray>;
+}
+
 /// A simple camera.
 #[derive(Debug)]
 pub struct Camera {
@@ -27,8 +115,13 @@ pub struct Camera {
     vertical: Vec3,
     u: Vec3,
     v: Vec3,
-    //w: Vec3,
+    w: Vec3,
     lens_radius: f32,
+    look_at_dist: f32,
+    tilt: (f32, f32),
+    focus_normal: Vec3,
+    aperture_shape: ApertureShape,
+    shutter: (f32, f32),
 }
 
 impl Camera {
@@ -53,12 +146,12 @@ impl Camera {
     /// let look_at = Vec3(0., 0., -1.);
     /// let view_up = Vec3(0., 1., 0.);
     /// let aperture = 2.0;
-    ///// let dist_to_focus = 3.0;
+    /// let dist_to_focus = 3.0;
     /// let cam = Camera::new(look_from,
     ///                       look_at,
     ///                       view_up,
     ///                       vertical_field_of_view_in_degrees, aspect_x_over_y,
-    ///                       aperture);//, dist_to_focus);
+    ///                       aperture, dist_to_focus);
     /// ```
     pub fn new(
         look_from: Vec3,
@@ -89,7 +182,195 @@ impl Camera {
             lens_radius,
             u,
             v,
-            //w,
+            w,
+            look_at_dist: (look_at - look_from).length(),
+            tilt: (0., 0.),
+            focus_normal: w,
+            aperture_shape: ApertureShape::default(),
+            shutter: (0., 0.),
+        }
+    }
+
+    /// Move the plane in focus to the distance `focus_dist` from the camera,
+    /// keeping the field of view, e.g. to the distance found by
+    /// `render::autofocus`.
+    ///
+    /// ```
+    /// # use raytracer::camera::Camera;
+    /// # use raytracer::vec3::Vec3;
+    /// let camera = Camera::new(Vec3(0., 0., 0.), Vec3(0., 0., -1.), Vec3(0., 1., 0.), 40., 1.5, 0.2, 1.)
+    ///     .with_focus_dist(5.);
+    /// assert!((camera.focus_dist() - 5.).abs() < 1e-5);
+    /// ```
+    pub fn with_focus_dist(mut self, focus_dist: f32) -> Camera {
+        let scale = focus_dist / self.focus_dist();
+        self.lower_left_corner = self.origin + scale * (self.lower_left_corner - self.origin);
+        self.horizontal = scale * self.horizontal;
+        self.vertical = scale * self.vertical;
+        self
+    }
+
+    /// Move the plane in focus to the point the camera is looking at, such
+    /// that the `focus_dist` given when creating the camera does not matter.
+    ///
+    /// ```
+    /// # use raytracer::camera::Camera;
+    /// # use raytracer::vec3::Vec3;
+    /// let look_from = Vec3(0., 3., 4.);
+    /// let camera = Camera::new(look_from, Vec3(0., 0., 0.), Vec3(0., 1., 0.), 40., 1.5, 0.2, 1.)
+    ///     .focus_at_look_at();
+    /// assert!((camera.focus_dist() - 5.).abs() < 1e-5);
+    /// ```
+    pub fn focus_at_look_at(self) -> Camera {
+        let look_at_dist = self.look_at_dist;
+        self.with_focus_dist(look_at_dist)
+    }
+
+    /// Access the distance from the camera to the plane in focus, through the
+    /// center of the image.
+    pub fn focus_dist(&self) -> f32 {
+        let center = self.lower_left_corner + 0.5 * (self.horizontal + self.vertical);
+        (center - self.origin).length()
+    }
+
+    /// Access the distance from the camera to the point it is looking at.
+    pub fn look_at_dist(&self) -> f32 {
+        self.look_at_dist
+    }
+
+    /// Set the shape of the aperture, which is round by default.
+    ///
+    /// Return an error if the aperture is a polygon of fewer than 3 blades.
+    ///
+    /// ```
+    /// # use raytracer::camera::{ApertureShape, Camera};
+    /// # use raytracer::vec3::Vec3;
+    /// let camera = || {
+    ///     let look_from = Vec3(0., 0., 0.);
+    ///     Camera::new(look_from, Vec3(0., 0., -1.), Vec3(0., 1., 0.), 40., 1.5, 0.2, 1.)
+    /// };
+    /// // Hexagonal bokeh, with a flat edge at the bottom.
+    /// let hexagon = ApertureShape::Polygon { blades: 6, rotation: 30. };
+    /// assert!(camera().with_aperture_shape(hexagon).is_ok());
+    /// let line = ApertureShape::Polygon { blades: 2, rotation: 0. };
+    /// assert!(camera().with_aperture_shape(line).is_err());
+    /// ```
+    pub fn with_aperture_shape(mut self, shape: ApertureShape) -> Result<Camera, CameraError> {
+        if let ApertureShape::Polygon { blades, .. } = shape {
+            if blades < 3 {
+                return Err(CameraError::TooFewBlades { blades });
+            }
+        }
+        self.aperture_shape = shape;
+        Ok(self)
+    }
+
+    /// Access the shape of the aperture.
+    pub fn aperture_shape(&self) -> ApertureShape {
+        self.aperture_shape
+    }
+
+    /// Set the times at which the shutter opens and closes, such that the
+    /// rays are emitted at random times in between, which blurs moving
+    /// objects, see `MovingSphere`.
+    ///
+    /// By default, the shutter opens and closes at time zero, and all rays
+    /// are emitted at that time.
+    ///
+    /// ```
+    /// # use raytracer::camera::Camera;
+    /// # use raytracer::vec3::Vec3;
+    /// let look_from = Vec3(0., 0., 0.);
+    /// let look_at = Vec3(0., 0., -1.);
+    /// let camera = Camera::new(look_from, look_at, Vec3(0., 1., 0.), 90., 2., 0., 1.)
+    ///     .with_shutter(0., 0.5);
+    /// let ray = camera.get_ray(0.5, 0.5, &mut rand::thread_rng());
+    /// assert!(ray.time() >= 0. && ray.time() <= 0.5);
+    /// ```
+    pub fn with_shutter(mut self, open: f32, close: f32) -> Camera {
+        self.shutter = (open, close);
+        self
+    }
+
+    /// Access the times at which the shutter opens and closes.
+    pub fn shutter(&self) -> (f32, f32) {
+        self.shutter
+    }
+
+    /// Tilt the plane in focus by `x` degrees around the horizontal and `y`
+    /// degrees around the vertical axis of the image, as a tilt-shift lens
+    /// does, such that e.g. a floor or a facade running away from the camera
+    /// is in focus along its whole depth.
+    ///
+    /// The plane keeps going through the center of the image at `focus_dist`.
+    /// A positive `x` moves its upper part and a positive `y` its right part
+    /// away from the camera: the depth of the plane grows by `tan(x)` for every
+    /// unit upwards, and by `tan(y)` for every unit to the right. Each ray is
+    /// then focused where the ray through the center of the lens meets the
+    /// plane, following the Scheimpflug principle. Where it does not meet the
+    /// plane in front of the camera, the ray is focused at infinity.
+    ///
+    /// The angles are expected within `(-90, 90)`. Without tilt, which is the
+    /// default, the whole image is focused at `focus_dist`.
+    ///
+    /// ```
+    /// # use raytracer::camera::Camera;
+    /// # use raytracer::vec3::Vec3;
+    /// # use rand::SeedableRng;
+    /// let (look_from, look_at) = (Vec3(0., 1., 0.), Vec3(0., 0., -4.));
+    /// // Looking down at 1 / 4, the floor is tilted by atan(4) from the view.
+    /// let tilt = 4f32.atan().to_degrees();
+    /// let distance = (look_at - look_from).length();
+    /// let floor = Camera::new(look_from, look_at, Vec3(0., 1., 0.), 40., 1.5, 0.5, distance)
+    ///     .tilt_degrees(tilt, 0.);
+    /// assert_eq!(floor.tilt(), (tilt, 0.));
+    /// // The rays through a point of the image all meet on the floor.
+    /// let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    /// let mut on_floor = |x, y| {
+    ///     let ray = floor.get_ray(x, y, &mut rng);
+    ///     ray.point_at_parameter(-ray.origin().y() / ray.direction().y())
+    /// };
+    /// for &(x, y) in &[(0.5, 0.1), (0.2, 0.4), (0.9, 0.7)] {
+    ///     assert!((on_floor(x, y) - on_floor(x, y)).length() < 1e-3);
+    /// }
+    /// ```
+    pub fn tilt_degrees(mut self, x: f32, y: f32) -> Camera {
+        self.tilt = (x, y);
+        self.focus_normal =
+            unit_vector(&(self.w + x.to_radians().tan() * self.v + y.to_radians().tan() * self.u));
+        self
+    }
+
+    /// Access the tilt of the plane in focus in degrees, around the horizontal
+    /// and the vertical axis of the image.
+    pub fn tilt(&self) -> (f32, f32) {
+        self.tilt
+    }
+
+    // Return the point of the untilted plane in focus seen at the point
+    // `(x_frac, y_frac)` of the image.
+    fn target(&self, x_frac: f32, y_frac: f32) -> Vec3 {
+        self.lower_left_corner + x_frac * self.horizontal + y_frac * self.vertical
+    }
+
+    // Return the ray from the center of the lens through the point
+    // `(x_frac, y_frac)` of the image, which reaches the untilted plane in
+    // focus at the parameter 1.
+    pub(crate) fn chief_ray(&self, x_frac: f32, y_frac: f32) -> Ray {
+        Ray::new(self.origin, self.target(x_frac, y_frac) - self.origin)
+    }
+
+    // Return the point where the ray from the center of the lens towards the
+    // `target` on the untilted plane in focus meets the tilted one, if it
+    // does so in front of the camera.
+    fn focal_point(&self, target: &Vec3) -> Option<Vec3> {
+        let center = self.lower_left_corner + 0.5 * (self.horizontal + self.vertical);
+        let chief = *target - self.origin;
+        let t = dot(&(center - self.origin), &self.focus_normal) / dot(&chief, &self.focus_normal);
+        if t > 0. && t.is_finite() {
+            Some(self.origin + t * chief)
+        } else {
+            None
         }
     }
 
@@ -100,14 +381,566 @@ impl Camera {
     /// x_frac * x_dim + y_frac * y_dim
     ///
     /// is returned.
-    pub fn get_ray(&self, x_frac: f32, y_frac: f32) -> Ray {
-        let rd = self.lens_radius * random_in_unit_disk();
-        let offset = self.u * rd.x() + self.v * rd.y();
-        Ray::new(
-            self.origin + offset,
-            self.lower_left_corner + x_frac * self.horizontal + y_frac * self.vertical
-                - self.origin
-                - offset,
+    ///
+    /// The origin of the ray is drawn at random on the aperture, unless the
+    /// aperture is zero, in which case the camera is a pinhole, and the time
+    /// of the ray is drawn at random while the shutter is open. A pinhole
+    /// camera whose shutter opens and closes at once does not use `rng`.
+    pub fn get_ray(&self, x_frac: f32, y_frac: f32, rng: &mut dyn RngCore) -> Ray {
+        let offset = if self.lens_radius == 0. {
+            Vec3(0., 0., 0.)
+        } else {
+            let rd = self.lens_radius
+                * match self.aperture_shape {
+                    ApertureShape::Circle => random_in_unit_disk(rng),
+                    ApertureShape::Polygon { blades, rotation } => {
+                        random_in_polygon(blades, rotation, rng)
+                    }
+                };
+            self.u * rd.x() + self.v * rd.y()
+        };
+        let (open, close) = self.shutter;
+        let time = if open == close {
+            open
+        } else {
+            open + rng.gen::<f32>() * (close - open)
+        };
+        let target = self.target(x_frac, y_frac);
+        let direction = if self.tilt == (0., 0.) {
+            target - self.origin - offset
+        } else {
+            match self.focal_point(&target) {
+                Some(focus) => focus - self.origin - offset,
+                None => target - self.origin,
+            }
+        };
+        Ray::new_with_time(self.origin + offset, direction, time)
+    }
+}
+
+impl CameraModel for Camera {
+    fn get_ray(&self, x_frac: f32, y_frac: f32, rng: &mut dyn RngCore) -> Option<Ray> {
+        Some(Camera::get_ray(self, x_frac, y_frac, rng))
+    }
+}
+
+/// A camera of orthographic projection, whose rays leave the image plane in
+/// parallel, e.g. for technical or isometric renders.
+///
+/// The rays start on the plane through the camera position perpendicular to
+/// the view direction, such that only objects in front of it are seen, and
+/// their directions have unit length, such that the parameters of hits are
+/// the distances from the image plane. There is no defocus blur.
+///
+/// ```
+/// use raytracer::camera::{CameraModel, OrthographicCamera};
+/// use raytracer::vec3::Vec3;
+/// // An isometric view of a region 8 units wide and 4 units high.
+/// let camera = OrthographicCamera::new(
+///     Vec3(10., 10., 10.),
+///     Vec3(0., 0., 0.),
+///     Vec3(0., 1., 0.),
+///     8.,
+///     4.,
+/// );
+/// let ray = camera.get_ray(0.5, 0.5, &mut rand::thread_rng()).unwrap();
+/// assert!((*ray.origin() - Vec3(10., 10., 10.)).length() < 1e-5);
+/// ```
+#[derive(Debug)]
+pub struct OrthographicCamera {
+    lower_left_corner: Vec3,
+    horizontal: Vec3,
+    vertical: Vec3,
+    direction: Vec3,
+}
+
+impl OrthographicCamera {
+    /// Create a new orthographic camera.
+    ///
+    /// The camera is located at `look_from`, points at `look_at` and is
+    /// tilted by `view_up`, like `Camera`, and sees a region of the world
+    /// `width` wide and `height` high, centered on the line of sight. The
+    /// ratio of `width` over `height` should be the one of the image.
+    pub fn new(
+        look_from: Vec3,
+        look_at: Vec3,
+        view_up: Vec3,
+        width: f32,
+        height: f32,
+    ) -> OrthographicCamera {
+        let w = unit_vector(&(look_from - look_at));
+        let u = unit_vector(&cross(&view_up, &w));
+        let v = cross(&w, &u);
+        OrthographicCamera {
+            lower_left_corner: look_from - 0.5 * width * u - 0.5 * height * v,
+            horizontal: width * u,
+            vertical: height * v,
+            direction: -w,
+        }
+    }
+}
+
+impl CameraModel for OrthographicCamera {
+    fn get_ray(&self, x_frac: f32, y_frac: f32, _rng: &mut dyn RngCore) -> Option<Ray> {
+        Some(Ray::new(
+            self.lower_left_corner + x_frac * self.horizontal + y_frac * self.vertical,
+            self.direction,
+        ))
+    }
+}
+
+/// How a `FisheyeCamera` maps the angle of a ray from the view direction to
+/// the distance from the center of the image.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FisheyeProjection {
+    /// The distance is proportional to the angle.
+    #[default]
+    Equidistant,
+    /// The distance is proportional to the sine of half the angle, such that
+    /// equal solid angles cover equal areas of the image.
+    Equisolid,
+}
+
+/// A camera with a circular fisheye lens, which sees up to all directions
+/// around it.
+///
+/// The image circle touches the top and the bottom of the image, and its
+/// edge sees the directions at half the field of view from the view
+/// direction, which may exceed 180 degrees. The points outside of the image
+/// circle are black. There is no defocus blur.
+///
+/// ```
+/// use raytracer::camera::{CameraModel, FisheyeCamera, FisheyeProjection};
+/// use raytracer::vec3::Vec3;
+/// let camera = FisheyeCamera::new(
+///     Vec3(0., 1., 0.),
+///     Vec3(0., 1., -1.),
+///     Vec3(0., 1., 0.),
+///     220.,
+///     1.5,
+/// )
+/// .with_projection(FisheyeProjection::Equisolid);
+/// let mut rng = rand::thread_rng();
+/// let center = camera.get_ray(0.5, 0.5, &mut rng).unwrap();
+/// assert_eq!(*center.direction(), Vec3(0., 0., -1.));
+/// // The corners lie outside of the image circle.
+/// assert!(camera.get_ray(0., 0., &mut rng).is_none());
+/// ```
+#[derive(Debug)]
+pub struct FisheyeCamera {
+    origin: Vec3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    half_fov: f32,
+    aspect: f32,
+    projection: FisheyeProjection,
+}
+
+impl FisheyeCamera {
+    /// Create a new fisheye camera of equidistant projection.
+    ///
+    /// The camera is located at `look_from`, points at `look_at` and is
+    /// tilted by `view_up`, like `Camera`. The field of view `fov` in degrees
+    /// is the angle across the image circle, at most 360 degrees, and
+    /// `aspect` is the ratio of the width over the height of the image.
+    pub fn new(
+        look_from: Vec3,
+        look_at: Vec3,
+        view_up: Vec3,
+        fov: f32,
+        aspect: f32,
+    ) -> FisheyeCamera {
+        let w = unit_vector(&(look_from - look_at));
+        let u = unit_vector(&cross(&view_up, &w));
+        let v = cross(&w, &u);
+        FisheyeCamera {
+            origin: look_from,
+            u,
+            v,
+            w,
+            half_fov: 0.5 * fov.clamp(0., 360.).to_radians(),
+            aspect,
+            projection: FisheyeProjection::default(),
+        }
+    }
+
+    /// Set how angles from the view direction map to the image.
+    pub fn with_projection(mut self, projection: FisheyeProjection) -> FisheyeCamera {
+        self.projection = projection;
+        self
+    }
+
+    /// Access how angles from the view direction map to the image.
+    pub fn projection(&self) -> FisheyeProjection {
+        self.projection
+    }
+}
+
+impl CameraModel for FisheyeCamera {
+    fn get_ray(&self, x_frac: f32, y_frac: f32, _rng: &mut dyn RngCore) -> Option<Ray> {
+        // The offset from the center, relative to the radius of the image circle.
+        let (x, y) = ((2. * x_frac - 1.) * self.aspect, 2. * y_frac - 1.);
+        let radius = (x * x + y * y).sqrt();
+        if radius > 1. {
+            return None;
+        }
+        if radius == 0. {
+            return Some(Ray::new(self.origin, -self.w));
+        }
+        let theta = match self.projection {
+            FisheyeProjection::Equidistant => radius * self.half_fov,
+            FisheyeProjection::Equisolid => {
+                2. * (radius * (0.5 * self.half_fov).sin()).clamp(-1., 1.).asin()
+            }
+        };
+        let (sin, cos) = theta.sin_cos();
+        let direction = sin / radius * (x * self.u + y * self.v) - cos * self.w;
+        Some(Ray::new(self.origin, direction))
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::Hitable;
+    use crate::vec3::dot;
+    use rand::rngs::SmallRng;
+    use std::sync::Arc;
+
+    fn camera(aperture: f32) -> Camera {
+        Camera::new(
+            Vec3(-2., 2., 1.),
+            Vec3(0., 0., -1.),
+            Vec3(0., 1., 0.),
+            20.,
+            2.,
+            aperture,
+            3.,
+        )
+    }
+
+    #[test]
+    // Test that a camera without aperture returns the same ray without drawing
+    // random numbers, and that equally seeded generators give the same rays.
+    fn test_get_ray_pinhole() {
+        let cam = camera(0.);
+        let mut rng = SmallRng::seed_from_u64(54);
+        let first = cam.get_ray(0.3, 0.7, &mut rng);
+        let second = cam.get_ray(0.3, 0.7, &mut rng);
+        assert_eq!(first.origin(), second.origin());
+        assert_eq!(first.direction(), second.direction());
+        assert_eq!(*first.origin(), Vec3(-2., 2., 1.));
+        assert_eq!(rng.gen::<u64>(), SmallRng::seed_from_u64(54).gen::<u64>());
+
+        // With an aperture, the rays start at different points of the lens.
+        let cam = camera(2.);
+        let first = cam.get_ray(0.3, 0.7, &mut rng);
+        let second = cam.get_ray(0.3, 0.7, &mut rng);
+        assert!(first.origin() != second.origin());
+
+        // Equally seeded generators give the same rays.
+        let (mut a, mut b) = (SmallRng::seed_from_u64(55), SmallRng::seed_from_u64(55));
+        let first = cam.get_ray(0.3, 0.7, &mut a);
+        let second = cam.get_ray(0.3, 0.7, &mut b);
+        assert_eq!(first.origin(), second.origin());
+        assert_eq!(first.direction(), second.direction());
+    }
+
+    #[test]
+    // Test that the camera casts the same rays through the camera model.
+    fn test_camera_model() {
+        let cam = camera(2.);
+        let model: &dyn CameraModel = &cam;
+        let (mut first, mut second) = (SmallRng::seed_from_u64(55), SmallRng::seed_from_u64(55));
+        for &(x, y) in &[(0., 0.), (0.3, 0.7), (1., 0.5)] {
+            let concrete = cam.get_ray(x, y, &mut first);
+            let dynamic = model.get_ray(x, y, &mut second).unwrap();
+            assert_eq!(concrete.origin(), dynamic.origin());
+            assert_eq!(concrete.direction(), dynamic.direction());
+        }
+    }
+
+    #[test]
+    // Test that the rays of an orthographic camera are parallel and start
+    // offset by the size of the view over the image.
+    fn test_orthographic_rays() {
+        let cam =
+            OrthographicCamera::new(Vec3(0., 0., 5.), Vec3(0., 0., 0.), Vec3(0., 1., 0.), 8., 4.);
+        let mut rng = SmallRng::seed_from_u64(56);
+        let (width, height) = (80., 40.);
+        let first = cam.get_ray(10. / width, 30. / height, &mut rng).unwrap();
+        let second = cam.get_ray(13. / width, 28. / height, &mut rng).unwrap();
+        assert_eq!(first.direction(), second.direction());
+        assert_eq!(*first.direction(), Vec3(0., 0., -1.));
+        // Each pixel spans 0.1 units.
+        let offset = *second.origin() - *first.origin();
+        assert!((offset - Vec3(0.3, -0.2, 0.)).length() < 1e-5);
+        assert!((*first.origin() - Vec3(-3., 1., 5.)).length() < 1e-5);
+        assert_eq!(rng.gen::<u64>(), SmallRng::seed_from_u64(56).gen::<u64>());
+    }
+
+    #[test]
+    // Test that a sphere on the line of sight projects to a circle of its
+    // radius, whatever its distance.
+    fn test_orthographic_sphere() {
+        let material = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        let look_from = Vec3(1., 2., 3.);
+        let axis = unit_vector(&Vec3(-1., -1., -2.));
+        let size = 64;
+        // The view is 4 units wide, such that a pixel spans 1/16 units.
+        let cam = OrthographicCamera::new(look_from, look_from + axis, Vec3(0., 1., 0.), 4., 4.);
+        let mut rng = SmallRng::seed_from_u64(57);
+        for &distance in &[3., 30.] {
+            let sphere = Sphere::new(look_from + distance * axis, 1., material.clone());
+            for y in 0..size {
+                for x in 0..size {
+                    let center = |i: usize| (i as f32 + 0.5) / size as f32;
+                    let ray = cam.get_ray(center(x), center(y), &mut rng).unwrap();
+                    let hit = sphere.intersect(&ray, 0.001, f32::MAX);
+                    // The distance of the pixel center from the image center, in pixels.
+                    let radius =
+                        ((x as f32 + 0.5 - 32.).powi(2) + (y as f32 + 0.5 - 32.).powi(2)).sqrt();
+                    if (radius - 16.).abs() > 1e-2 {
+                        assert_eq!(hit.is_some(), radius < 16., "pixel ({}, {})", x, y);
+                    }
+                    if let Some(hit) = hit {
+                        assert!(hit.parameter > distance - 1. - 1e-3);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Test that the edge of the image circle of a fisheye camera sees at half
+    // the field of view from the view direction, and its center along it.
+    fn test_fisheye_edge() {
+        let mut rng = SmallRng::seed_from_u64(58);
+        let look_from = Vec3(1., 2., 3.);
+        let view = unit_vector(&Vec3(1., -0.5, -2.));
+        for &projection in &[FisheyeProjection::Equidistant, FisheyeProjection::Equisolid] {
+            for &fov in &[90., 180., 220.] {
+                let cam =
+                    FisheyeCamera::new(look_from, look_from + view, Vec3(0., 1., 0.), fov, 2.)
+                        .with_projection(projection);
+                let center = cam.get_ray(0.5, 0.5, &mut rng).unwrap();
+                assert_eq!(*center.origin(), look_from);
+                assert!((*center.direction() - view).length() < 1e-6);
+                // The top, bottom, left and right of the image circle.
+                for &(x, y) in &[(0.5, 1.), (0.5, 0.), (0.25, 0.5), (0.75, 0.5)] {
+                    let ray = cam.get_ray(x, y, &mut rng).unwrap();
+                    let angle = dot(&unit_vector(ray.direction()), &view)
+                        .acos()
+                        .to_degrees();
+                    assert!(
+                        (angle - 0.5 * fov).abs() < 1e-2,
+                        "{} at {:?}",
+                        angle,
+                        (x, y)
+                    );
+                }
+                assert!(cam.get_ray(0.2, 0.5, &mut rng).is_none());
+                assert!(cam.get_ray(0.75, 0.9, &mut rng).is_none());
+            }
+        }
+    }
+
+    #[test]
+    // Test that a fisheye camera matches a perspective camera of the same
+    // focal length close to the center of the image.
+    fn test_fisheye_center() {
+        let mut rng = SmallRng::seed_from_u64(59);
+        let (look_from, look_at) = (Vec3(0., 0., 0.), Vec3(0., 0., -1.));
+        let fisheye = FisheyeCamera::new(look_from, look_at, Vec3(0., 1., 0.), 90., 1.);
+        // The equidistant projection of 90 degrees maps the edge of the
+        // image, at a distance of 1 from the center, to 45 degrees, which is
+        // the field of view of a perspective camera of focal length 4 / π.
+        let vfov = 2. * (std::f32::consts::PI / 4.).atan().to_degrees();
+        let perspective = Camera::new(look_from, look_at, Vec3(0., 1., 0.), vfov, 1., 0., 1.);
+        for &(x, y) in &[(0.5, 0.5), (0.52, 0.5), (0.5, 0.47), (0.53, 0.54)] {
+            let a = unit_vector(fisheye.get_ray(x, y, &mut rng).unwrap().direction());
+            let b = unit_vector(perspective.get_ray(x, y, &mut rng).direction());
+            assert!(
+                (a - b).length() < 1e-3,
+                "{:?} and {:?} at {:?}",
+                a,
+                b,
+                (x, y)
+            );
+        }
+    }
+
+    #[test]
+    // Test that the samples of a hexagonal aperture fall inside the hexagon
+    // and spread over it like samples drawn by rejection from a square.
+    fn test_polygon_aperture() {
+        let mut rng = SmallRng::seed_from_u64(61);
+        let rotation = 10f32;
+        let apothem = (PI / 6.).cos();
+        let inside = |p: &Vec3| {
+            (0..6).all(|i| {
+                let angle = (rotation + 30. + 60. * i as f32).to_radians();
+                p.x() * angle.cos() + p.y() * angle.sin() <= apothem + 1e-5
+            })
+        };
+        // Count the samples per sixth of a turn, split into those within the
+        // inscribed circle of the hexagon and those in its corners.
+        let histogram = |sample: &mut dyn FnMut() -> Vec3| {
+            let mut counts = [0usize; 12];
+            let samples = 60_000;
+            for _ in 0..samples {
+                let p = sample();
+                let sector = (p.y().atan2(p.x()) / (PI / 3.)).floor().rem_euclid(6.) as usize;
+                let corner = p.length() > apothem;
+                counts[2 * sector + corner as usize] += 1;
+            }
+            counts.map(|c| c as f32 / samples as f32)
+        };
+
+        let polygon = histogram(&mut || {
+            let p = random_in_polygon(6, rotation, &mut rng);
+            assert!(inside(&p), "{:?} outside of the hexagon", p);
+            p
+        });
+        let mut reference_rng = SmallRng::seed_from_u64(62);
+        let rejection = histogram(&mut || loop {
+            let p = 2.0 * Vec3(reference_rng.gen::<f32>(), reference_rng.gen::<f32>(), 0.0)
+                - Vec3(1., 1., 0.);
+            if inside(&p) {
+                break p;
+            }
+        });
+        for (a, b) in polygon.iter().zip(rejection.iter()) {
+            assert!(
+                (a - b).abs() < 0.006,
+                "{:?} against {:?}",
+                polygon,
+                rejection
+            );
+        }
+        // The corners beyond the inscribed circle hold about 7% of the area.
+        let corners: f32 = polygon.iter().skip(1).step_by(2).sum();
+        let expected = 1. - PI * apothem * apothem / (3. * 3f32.sqrt() / 2.);
+        assert!((corners - expected).abs() < 0.005, "{}", corners);
+    }
+
+    #[test]
+    // Test that fewer than 3 blades are rejected, and that a round aperture
+    // gives the same rays as a camera without an aperture shape.
+    fn test_aperture_shape() {
+        for blades in 0..3 {
+            let shape = ApertureShape::Polygon {
+                blades,
+                rotation: 0.,
+            };
+            assert_eq!(
+                camera(0.5).with_aperture_shape(shape).unwrap_err(),
+                CameraError::TooFewBlades { blades }
+            );
+        }
+        let round = camera(0.5)
+            .with_aperture_shape(ApertureShape::Circle)
+            .unwrap();
+        let (mut a, mut b) = (SmallRng::seed_from_u64(63), SmallRng::seed_from_u64(63));
+        for i in 0..100 {
+            let (x, y) = (i as f32 / 100., 1. - i as f32 / 100.);
+            let (first, second) = (
+                camera(0.5).get_ray(x, y, &mut a),
+                round.get_ray(x, y, &mut b),
+            );
+            assert_eq!(first.origin(), second.origin());
+            assert_eq!(first.direction(), second.direction());
+        }
+
+        let triangle = ApertureShape::Polygon {
+            blades: 3,
+            rotation: 90.,
+        };
+        let cam = camera(0.5).with_aperture_shape(triangle).unwrap();
+        assert_eq!(cam.aperture_shape(), triangle);
+        let mut rng = SmallRng::seed_from_u64(64);
+        for _ in 0..100 {
+            let offset = *cam.get_ray(0.5, 0.5, &mut rng).origin() - Vec3(-2., 2., 1.);
+            assert!(offset.length() <= 0.25 + 1e-5);
+        }
+    }
+
+    #[test]
+    // Test that a camera without tilt gives the same rays as an untilted one.
+    fn test_tilt_zero() {
+        let tilted = camera(0.5).tilt_degrees(0., 0.);
+        let (mut a, mut b) = (SmallRng::seed_from_u64(65), SmallRng::seed_from_u64(65));
+        for i in 0..100 {
+            let (x, y) = (i as f32 / 100., (i * 37 % 100) as f32 / 100.);
+            let (first, second) = (
+                camera(0.5).get_ray(x, y, &mut a),
+                tilted.get_ray(x, y, &mut b),
+            );
+            assert_eq!(first.origin(), second.origin());
+            assert_eq!(first.direction(), second.direction());
+        }
+    }
+
+    #[test]
+    // Test that two spheres at different depths on a tilted plane in focus are
+    // sharp, i.e. all the rays through their pixels hit them, while a sphere
+    // off the plane at the same distance as one of them is blurred.
+    fn test_tilt_focus() {
+        let vfov = 60f32;
+        let cam = Camera::new(
+            Vec3(0., 0., 0.),
+            Vec3(0., 0., -1.),
+            Vec3(0., 1., 0.),
+            vfov,
+            1.,
+            0.5,
+            4.,
         )
+        .tilt_degrees(45., 0.);
+        // The plane in focus goes through (0, 0, -4), at a depth of 4 + y.
+        let half = (vfov.to_radians() / 2.).tan();
+        let hits = |center: Vec3| {
+            let sphere = Sphere::new(center, 0.05, Arc::new(Lambertian::new(Vec3(1., 1., 1.))));
+            let (x, y) = (
+                0.5 + center.x() / -center.z() / (2. * half),
+                0.5 + center.y() / -center.z() / (2. * half),
+            );
+            let mut rng = SmallRng::seed_from_u64(66);
+            let samples = 1000;
+            let hits = (0..samples)
+                .filter(|_| {
+                    let ray = cam.get_ray(x, y, &mut rng);
+                    sphere.intersect_any(&ray, 0.001, f32::MAX)
+                })
+                .count();
+            hits as f32 / samples as f32
+        };
+        assert_eq!(hits(Vec3(0.5, -1., -3.)), 1.);
+        assert_eq!(hits(Vec3(0., 1., -5.)), 1.);
+        // The pixel of this sphere is focused at a depth of 10 / 3.
+        let blurred = hits(Vec3(0., -1., -5.));
+        assert!(blurred < 0.3, "{}", blurred);
+
+        let untilted = Camera::new(
+            Vec3(0., 0., 0.),
+            Vec3(0., 0., -1.),
+            Vec3(0., 1., 0.),
+            vfov,
+            1.,
+            0.5,
+            4.,
+        );
+        let mut rng = SmallRng::seed_from_u64(67);
+        let center = untilted.get_ray(0.5, 0.5, &mut rng);
+        let tilted_center = cam.get_ray(0.5, 0.5, &mut rng);
+        assert!((center.point_at_parameter(1.) - Vec3(0., 0., -4.)).length() < 1e-5);
+        assert!((tilted_center.point_at_parameter(1.) - Vec3(0., 0., -4.)).length() < 1e-5);
     }
 }
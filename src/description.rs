@@ -0,0 +1,645 @@
+//! Descriptions of materials and textures, which can be written to and read
+//! from files.
+//!
+//! This module is only available with the `serde` feature. A description is
+//! a plain value mapping the kind of a material or texture to its named
+//! parameters, see `MaterialDesc`, and colors are written as described by
+//! `ColorDesc`. Materials are built from their descriptions with `build`,
+//! which reports what cannot be built and warns about colors outside the
+//! 0–1 range, and materials of a scene built with a shared `TextureCache`
+//! load every image they use only once. Built materials describe themselves
+//! again with `MaterialExt::describe`.
+//!
+//! ```
+//! use raytracer::description::MaterialDesc;
+//! use raytracer::textures::TextureCache;
+//! let text = r##"{
+//!     "Lambertian": { "albedo": { "Solid": { "color": "#c83232" } } }
+//! }"##;
+//! let desc = MaterialDesc::from_json(text).unwrap();
+//! let built = desc.build(&TextureCache::new()).unwrap();
+//! assert!(built.warning.is_none());
+//! let material = built.material;
+//! ```
+
+use crate::hit_record::HitRecord;
+use crate::materials::Dielectric;
+use crate::materials::Lambertian;
+use crate::materials::MaterialExt;
+use crate::materials::Metal;
+use crate::perlin::Perlin;
+use crate::textures::ImageOptions;
+use crate::textures::ImageTexture;
+use crate::textures::MarbleTexture;
+use crate::textures::NoiseTexture;
+use crate::textures::SolidColor;
+use crate::textures::Texture;
+use crate::textures::TextureCache;
+use crate::textures::WoodTexture;
+use crate::vec3::ParseColorError;
+use crate::vec3::Vec3;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::de;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+/// The description of a material.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum MaterialDesc {
+    /// A `Lambertian` of the `albedo` texture.
+    Lambertian { albedo: TextureDesc },
+    /// A `Metal` of the `albedo` texture and the fuzzyness `fuzz`, which the
+    /// `fuzz_texture` replaces if given.
+    Metal {
+        albedo: TextureDesc,
+        fuzz: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fuzz_texture: Option<TextureDesc>,
+    },
+    /// A `Dielectric` of the refractive index `ref_idx`.
+    Dielectric { ref_idx: f32 },
+}
+
+/// The description of a texture.
+///
+/// Procedural textures are described by the seed of their noise, and keep
+/// the defaults of their other parameters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum TextureDesc {
+    /// A `SolidColor` of the `color`.
+    Solid { color: ColorDesc },
+    /// A `NoiseTexture` of the turbulence over `octaves` octaves of noise
+    /// seeded by `seed`, at the `scale`.
+    Noise { seed: u64, scale: f32, octaves: u32 },
+    /// A `MarbleTexture` of noise seeded by `seed`.
+    Marble { seed: u64 },
+    /// A `WoodTexture` of noise seeded by `seed`.
+    Wood { seed: u64 },
+    /// An `ImageTexture` of the image at `path`, which holds data rather than
+    /// colors if `linear` is true, false by default.
+    Image {
+        path: String,
+        #[serde(default)]
+        linear: bool,
+    },
+}
+
+/// The description of a color.
+///
+/// A color is written as an array of its linear red, green and blue
+/// components, e.g. `[0.8, 0.03, 0.03]`, as an array of its 8-bit sRGB
+/// components, e.g. `[200, 50, 50]`, or as a hexadecimal sRGB string, e.g.
+/// `"#c83232"`. Arrays of integers only are read as 8-bit components, and
+/// strings which are not hexadecimal colors are rejected when read.
+///
+/// ```
+/// # use raytracer::description::ColorDesc;
+/// # use raytracer::vec3::Vec3;
+/// let color: ColorDesc = serde_json::from_str("[200, 50, 50]").unwrap();
+/// assert_eq!(color, ColorDesc::Srgb8([200, 50, 50]));
+/// assert_eq!(color.to_vec3(), Ok(Vec3::from_u8(200, 50, 50)));
+/// assert!(serde_json::from_str::<ColorDesc>(r##""#c8323""##).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ColorDesc {
+    /// The 8-bit sRGB components of the color, see `Vec3::from_u8`.
+    Srgb8([u8; 3]),
+    /// The linear components of the color.
+    Linear([f32; 3]),
+    /// The hexadecimal sRGB form of the color, see `Vec3::from_hex`.
+    Hex(String),
+}
+
+// The forms of a color as read, before hexadecimal strings are checked.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawColor {
+    Srgb8([u8; 3]),
+    Linear([f32; 3]),
+    Hex(String),
+}
+
+impl<'de> Deserialize<'de> for ColorDesc {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<ColorDesc, D::Error> {
+        Ok(match RawColor::deserialize(deserializer)? {
+            RawColor::Srgb8(components) => ColorDesc::Srgb8(components),
+            RawColor::Linear(components) => ColorDesc::Linear(components),
+            RawColor::Hex(hex) => {
+                Vec3::from_hex(&hex).map_err(de::Error::custom)?;
+                ColorDesc::Hex(hex)
+            }
+        })
+    }
+}
+
+impl ColorDesc {
+    /// Return the linear color described.
+    ///
+    /// Return an error for a string which is not a hexadecimal color.
+    pub fn to_vec3(&self) -> Result<Vec3, ParseColorError> {
+        match self {
+            ColorDesc::Srgb8([r, g, b]) => Ok(Vec3::from_u8(*r, *g, *b)),
+            ColorDesc::Linear([r, g, b]) => Ok(Vec3(*r, *g, *b)),
+            ColorDesc::Hex(hex) => Vec3::from_hex(hex),
+        }
+    }
+}
+
+impl From<Vec3> for ColorDesc {
+    fn from(color: Vec3) -> ColorDesc {
+        ColorDesc::Linear([color.x(), color.y(), color.z()])
+    }
+}
+
+/// Error returned when a description cannot be read or built.
+#[derive(Debug)]
+pub struct DescriptionError {
+    path: String,
+    message: String,
+}
+
+impl DescriptionError {
+    /// Access the path of the field at which reading or building failed, e.g.
+    /// `Lambertian.albedo`, which is `.` for the description itself.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    // The error of the field at `path`.
+    fn at(path: &str, error: impl fmt::Display) -> DescriptionError {
+        DescriptionError {
+            path: path.to_string(),
+            message: error.to_string(),
+        }
+    }
+
+    // The error of a description nested at the field `field`.
+    fn within(mut self, field: &str) -> DescriptionError {
+        self.path = format!("{}.{}", field, self.path);
+        self
+    }
+}
+
+impl fmt::Display for DescriptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid description at {}: {}", self.path, self.message)
+    }
+}
+
+impl Error for DescriptionError {}
+
+/// A material built from its description by `MaterialDesc::build`.
+pub struct BuiltMaterial {
+    /// The material described.
+    pub material: Arc<dyn MaterialExt>,
+    /// The warning of the material, e.g. that its albedo is outside the 0–1
+    /// color range, see `Lambertian::warning`.
+    pub warning: Option<String>,
+}
+
+impl MaterialDesc {
+    /// Read the description of a material from JSON.
+    ///
+    /// ```
+    /// # use raytracer::description::MaterialDesc;
+    /// let desc = MaterialDesc::from_json(r#"{ "Dielectric": { "ref_idx": 1.5 } }"#);
+    /// assert_eq!(desc.unwrap(), MaterialDesc::Dielectric { ref_idx: 1.5 });
+    ///
+    /// let text = r#"{ "Lambertian": { "albedo": { "Plaid": {} } } }"#;
+    /// let error = MaterialDesc::from_json(text).unwrap_err();
+    /// assert_eq!(error.path(), "Lambertian.albedo");
+    /// ```
+    pub fn from_json(text: &str) -> Result<MaterialDesc, DescriptionError> {
+        let mut deserializer = serde_json::Deserializer::from_str(text);
+        serde_path_to_error::deserialize(&mut deserializer).map_err(|error| DescriptionError {
+            path: error.path().to_string(),
+            message: error.inner().to_string(),
+        })
+    }
+
+    /// Write the description as JSON.
+    pub fn to_json(&self) -> String {
+        // Descriptions only hold numbers and strings, which always serialize.
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// Build the material, loading its images through the `cache`, such that
+    /// materials built with the same cache share their images.
+    ///
+    /// Return an error if an image cannot be loaded or a color cannot be
+    /// parsed, whereas materials built with `From` use
+    /// `ImageTexture::magenta` in place of the texture.
+    ///
+    /// ```
+    /// # use raytracer::description::MaterialDesc;
+    /// # use raytracer::textures::TextureCache;
+    /// let text = r#"{ "Lambertian": { "albedo": { "Solid": { "color": [200.0, 50.0, 50.0] } } } }"#;
+    /// let desc = MaterialDesc::from_json(text).unwrap();
+    /// assert!(desc.build(&TextureCache::new()).unwrap().warning.is_some());
+    /// ```
+    pub fn build(self, cache: &TextureCache) -> Result<BuiltMaterial, DescriptionError> {
+        Ok(match self {
+            MaterialDesc::Lambertian { albedo } => {
+                let albedo = albedo
+                    .build(cache)
+                    .map_err(|error| error.within("Lambertian.albedo"))?;
+                let material = Lambertian::with_texture(albedo);
+                BuiltMaterial {
+                    warning: material.warning(),
+                    material: Arc::new(material),
+                }
+            }
+            MaterialDesc::Metal {
+                albedo,
+                fuzz,
+                fuzz_texture,
+            } => {
+                let albedo = albedo
+                    .build(cache)
+                    .map_err(|error| error.within("Metal.albedo"))?;
+                let mut material = Metal::with_texture(albedo, fuzz);
+                if let Some(texture) = fuzz_texture {
+                    let texture = texture
+                        .build(cache)
+                        .map_err(|error| error.within("Metal.fuzz_texture"))?;
+                    material = material.with_fuzzy_texture(texture);
+                }
+                BuiltMaterial {
+                    warning: material.warning(),
+                    material: Arc::new(material),
+                }
+            }
+            MaterialDesc::Dielectric { ref_idx } => BuiltMaterial {
+                material: Arc::new(Dielectric::new(ref_idx)),
+                warning: None,
+            },
+        })
+    }
+}
+
+impl TextureDesc {
+    /// Build the texture, loading its image through the `cache`.
+    ///
+    /// Return an error if the image cannot be loaded or the color cannot be
+    /// parsed.
+    pub fn build(self, cache: &TextureCache) -> Result<Arc<dyn Texture>, DescriptionError> {
+        let texture: Arc<dyn Texture> = match &self {
+            TextureDesc::Solid { color } => {
+                let color = color
+                    .to_vec3()
+                    .map_err(|error| DescriptionError::at("Solid.color", error))?;
+                // Solid colors describe themselves, see `SolidColor::describe`.
+                return Ok(Arc::new(SolidColor::new(color)));
+            }
+            TextureDesc::Noise {
+                seed,
+                scale,
+                octaves,
+            } => Arc::new(NoiseTexture::new(noise(*seed), *scale, *octaves)),
+            TextureDesc::Marble { seed } => Arc::new(MarbleTexture::new(noise(*seed))),
+            TextureDesc::Wood { seed } => Arc::new(WoodTexture::new(noise(*seed))),
+            TextureDesc::Image { path, linear } => cache
+                .open(
+                    path,
+                    ImageOptions {
+                        linear: *linear,
+                        ..ImageOptions::default()
+                    },
+                )
+                .map_err(|error| DescriptionError::at("Image.path", error))?,
+        };
+        Ok(Arc::new(Described {
+            desc: self,
+            texture,
+        }))
+    }
+}
+
+// A noise generator seeded by `seed`.
+fn noise(seed: u64) -> Perlin {
+    Perlin::new(&mut StdRng::seed_from_u64(seed))
+}
+
+// A texture built from a description, which it keeps to describe itself, as
+// the seeds of procedural textures and the paths of images are not kept.
+struct Described {
+    desc: TextureDesc,
+    texture: Arc<dyn Texture>,
+}
+
+impl Texture for Described {
+    fn value(&self, u: f32, v: f32, p: &Vec3) -> Vec3 {
+        self.texture.value(u, v, p)
+    }
+
+    fn value_at_hit(&self, hit: &HitRecord) -> Vec3 {
+        self.texture.value_at_hit(hit)
+    }
+
+    fn warning(&self) -> Option<String> {
+        self.texture.warning()
+    }
+
+    fn describe(&self) -> Option<TextureDesc> {
+        Some(self.desc.clone())
+    }
+}
+
+impl From<TextureDesc> for Arc<dyn Texture> {
+    fn from(desc: TextureDesc) -> Arc<dyn Texture> {
+        desc.build(&TextureCache::new())
+            .unwrap_or_else(|_| Arc::new(ImageTexture::magenta()))
+    }
+}
+
+impl From<MaterialDesc> for Arc<dyn MaterialExt> {
+    fn from(desc: MaterialDesc) -> Arc<dyn MaterialExt> {
+        match desc {
+            MaterialDesc::Lambertian { albedo } => {
+                Arc::new(Lambertian::with_texture(albedo.into()))
+            }
+            MaterialDesc::Metal {
+                albedo,
+                fuzz,
+                fuzz_texture,
+            } => {
+                let metal = Metal::with_texture(albedo.into(), fuzz);
+                match fuzz_texture {
+                    Some(texture) => Arc::new(metal.with_fuzzy_texture(texture.into())),
+                    None => Arc::new(metal),
+                }
+            }
+            MaterialDesc::Dielectric { ref_idx } => Arc::new(Dielectric::new(ref_idx)),
+        }
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Fresnel;
+    use crate::ray::Ray;
+
+    // Descriptions of every kind of material, texture and color.
+    fn every_variant() -> Vec<MaterialDesc> {
+        let textures = vec![
+            TextureDesc::Solid {
+                color: ColorDesc::Linear([0.8, 0.3, 0.3]),
+            },
+            TextureDesc::Solid {
+                color: ColorDesc::Srgb8([200, 50, 50]),
+            },
+            TextureDesc::Solid {
+                color: ColorDesc::Hex("#c83232".to_string()),
+            },
+            TextureDesc::Noise {
+                seed: 1,
+                scale: 4.,
+                octaves: 7,
+            },
+            TextureDesc::Marble { seed: 2 },
+            TextureDesc::Wood { seed: 3 },
+        ];
+        let mut descs: Vec<MaterialDesc> = textures
+            .into_iter()
+            .map(|albedo| MaterialDesc::Lambertian { albedo })
+            .collect();
+        descs.push(MaterialDesc::Metal {
+            albedo: TextureDesc::Solid {
+                color: ColorDesc::Linear([0.8, 0.6, 0.2]),
+            },
+            fuzz: 0.3,
+            fuzz_texture: None,
+        });
+        descs.push(MaterialDesc::Metal {
+            albedo: TextureDesc::Marble { seed: 4 },
+            fuzz: 0.,
+            fuzz_texture: Some(TextureDesc::Noise {
+                seed: 5,
+                scale: 2.,
+                octaves: 3,
+            }),
+        });
+        descs.push(MaterialDesc::Dielectric { ref_idx: 1.5 });
+        descs
+    }
+
+    #[test]
+    // Test that descriptions are read back from the JSON they are written to.
+    fn test_description_round_trip() {
+        let image = MaterialDesc::Lambertian {
+            albedo: TextureDesc::Image {
+                path: "textures/earth.jpg".to_string(),
+                linear: false,
+            },
+        };
+        for desc in every_variant().into_iter().chain(Some(image)) {
+            let text = desc.to_json();
+            assert_eq!(MaterialDesc::from_json(&text).unwrap(), desc, "{}", text);
+        }
+    }
+
+    #[test]
+    // Test that built materials describe themselves as the descriptions they
+    // are built from, with solid colors in their linear form, and build the
+    // same material again.
+    fn test_description_describe() {
+        let cache = TextureCache::new();
+        for desc in every_variant() {
+            let expected = match &desc {
+                MaterialDesc::Lambertian {
+                    albedo: TextureDesc::Solid { color },
+                } => MaterialDesc::Lambertian {
+                    albedo: TextureDesc::Solid {
+                        color: color.to_vec3().unwrap().into(),
+                    },
+                },
+                desc => desc.clone(),
+            };
+            let described = desc.build(&cache).unwrap().material.describe().unwrap();
+            assert_eq!(described, expected);
+            let rebuilt = described.clone().build(&cache).unwrap().material;
+            assert_eq!(rebuilt.describe().unwrap(), described);
+        }
+
+        let metal = Metal::new(Vec3(0.8, 0.6, 0.2), 0.3);
+        let expected = MaterialDesc::Metal {
+            albedo: TextureDesc::Solid {
+                color: ColorDesc::Linear([0.8, 0.6, 0.2]),
+            },
+            fuzz: 0.3,
+            fuzz_texture: None,
+        };
+        assert_eq!(metal.describe(), Some(expected));
+        let marble = Lambertian::with_texture(Arc::new(MarbleTexture::new(noise(2))));
+        assert_eq!(marble.describe(), None);
+        let glass = Dielectric::new(1.5).with_fresnel(Fresnel::Exact);
+        assert_eq!(glass.describe(), None);
+    }
+
+    #[test]
+    // Test that the fixture of every variant is read, and that the materials
+    // built from it scatter like the materials they describe.
+    fn test_description_fixture() {
+        let text = include_str!("../tests/fixtures/materials.json");
+        let descs: Vec<MaterialDesc> = serde_json::from_str(text).unwrap();
+        assert_eq!(descs, every_variant());
+
+        let built: Vec<Arc<dyn MaterialExt>> = descs.into_iter().map(Arc::from).collect();
+        let red = Vec3::from_u8(200, 50, 50);
+        let expected: Vec<Arc<dyn MaterialExt>> = vec![
+            Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3))),
+            Arc::new(Lambertian::new(red)),
+            Arc::new(Lambertian::new(red)),
+            Arc::new(Lambertian::with_texture(Arc::new(NoiseTexture::new(
+                noise(1),
+                4.,
+                7,
+            )))),
+            Arc::new(Lambertian::with_texture(Arc::new(MarbleTexture::new(
+                noise(2),
+            )))),
+            Arc::new(Lambertian::with_texture(Arc::new(WoodTexture::new(noise(
+                3,
+            ))))),
+            Arc::new(Metal::new(Vec3(0.8, 0.6, 0.2), 0.3)),
+            Arc::new(
+                Metal::with_texture(Arc::new(MarbleTexture::new(noise(4))), 0.)
+                    .with_fuzzy_texture(Arc::new(NoiseTexture::new(noise(5), 2., 3))),
+            ),
+            Arc::new(Dielectric::new(1.5)),
+        ];
+        assert_eq!(built.len(), expected.len());
+        let ray = Ray::new(Vec3(0.3, 1., 0.2), Vec3(0.1, -1., 0.));
+        for (built, expected) in built.iter().zip(&expected) {
+            let hit = |material| HitRecord {
+                parameter: 1.,
+                point_at_parameter: Vec3(0.3, 0., 0.2),
+                normal: Vec3(0., 1., 0.),
+                u: 0.,
+                v: 0.,
+                tangent: Vec3(0., 0., 0.),
+                bitangent: Vec3(0., 0., 0.),
+                front_face: true,
+                material,
+            };
+            let (mut first, mut second) = (StdRng::seed_from_u64(100), StdRng::seed_from_u64(100));
+            for _ in 0..10 {
+                let a = built.scatter(&ray, &hit(built.as_ref()), &mut first);
+                let b = expected.scatter(&ray, &hit(expected.as_ref()), &mut second);
+                assert_eq!(a.is_some(), b.is_some());
+                if let (Some(a), Some(b)) = (a, b) {
+                    assert_eq!(a.0.direction(), b.0.direction());
+                    assert_eq!(a.1, b.1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Test that errors name the field at which reading or building failed.
+    fn test_description_errors() {
+        let unknown = r#"{ "Lambertian": { "albedo": { "Plaid": {} } } }"#;
+        let error = MaterialDesc::from_json(unknown).unwrap_err();
+        assert_eq!(error.path(), "Lambertian.albedo");
+        assert!(error.to_string().contains("Plaid"), "{}", error);
+
+        let unknown = r#"{ "Velvet": {} }"#;
+        let error = MaterialDesc::from_json(unknown).unwrap_err();
+        assert!(error.to_string().contains("Velvet"), "{}", error);
+
+        let missing = r#"{ "Metal": { "albedo": { "Wood": { "seed": 3 } } } }"#;
+        let error = MaterialDesc::from_json(missing).unwrap_err();
+        assert!(error.to_string().contains("fuzz"), "{}", error);
+
+        let wrong = r#"{ "Metal": { "albedo": { "Solid": { "color": [0.8, 0.6] } }, "fuzz": 0 } }"#;
+        let error = MaterialDesc::from_json(wrong).unwrap_err();
+        assert_eq!(error.path(), "Metal.albedo.Solid.color");
+
+        let hex = r##"{ "Lambertian": { "albedo": { "Solid": { "color": "#c8323" } } } }"##;
+        let error = MaterialDesc::from_json(hex).unwrap_err();
+        assert_eq!(error.path(), "Lambertian.albedo.Solid.color");
+        assert!(error.to_string().contains("#c8323"), "{}", error);
+
+        let hex = MaterialDesc::Metal {
+            albedo: TextureDesc::Wood { seed: 3 },
+            fuzz: 0.,
+            fuzz_texture: Some(TextureDesc::Solid {
+                color: ColorDesc::Hex("red".to_string()),
+            }),
+        };
+        let error = hex.clone().build(&TextureCache::new()).err().unwrap();
+        assert_eq!(error.path(), "Metal.fuzz_texture.Solid.color");
+        let _: Arc<dyn MaterialExt> = hex.into();
+    }
+
+    #[test]
+    // Test that materials built of colors outside the 0–1 range are warned
+    // about.
+    fn test_description_warnings() {
+        let cache = TextureCache::new();
+        for desc in every_variant() {
+            assert!(desc.build(&cache).unwrap().warning.is_none());
+        }
+        let bright =
+            r#"{ "Lambertian": { "albedo": { "Solid": { "color": [200.0, 50.0, 50.0] } } } }"#;
+        let built = MaterialDesc::from_json(bright)
+            .unwrap()
+            .build(&cache)
+            .unwrap();
+        assert!(built.warning.is_some());
+        let bright =
+            r#"{ "Metal": { "albedo": { "Solid": { "color": [0.5, 1.5, 0.5] } }, "fuzz": 0 } }"#;
+        let built = MaterialDesc::from_json(bright)
+            .unwrap()
+            .build(&cache)
+            .unwrap();
+        assert!(built.warning.is_some());
+    }
+
+    #[test]
+    // Test that materials built with a cache share their images, and that
+    // images which fail to load are reported, or magenta when converted.
+    fn test_description_images() {
+        let path = std::env::temp_dir().join("raytracer_test_description_image.png");
+        image::save_buffer(&path, &[0, 128, 255], 1, 1, image::ColorType::Rgb8).unwrap();
+        let text = format!(
+            r#"{{ "Lambertian": {{ "albedo": {{ "Image": {{ "path": {:?} }} }} }} }}"#,
+            path.to_str().unwrap()
+        );
+        let desc = MaterialDesc::from_json(&text).unwrap();
+        let cache = TextureCache::new();
+        for _ in 0..3 {
+            let material = desc.clone().build(&cache).unwrap().material;
+            assert_eq!(material.describe().as_ref(), Some(&desc));
+        }
+        assert_eq!(cache.len(), 1);
+        let texture = TextureDesc::Image {
+            path: path.to_str().unwrap().to_string(),
+            linear: true,
+        };
+        let p = Vec3(0., 0., 0.);
+        let data = texture.clone().build(&cache).unwrap();
+        assert_eq!(data.value(0.5, 0.5, &p), Vec3(0., 128. / 255., 1.));
+        assert_eq!(cache.len(), 2);
+        std::fs::remove_file(&path).unwrap();
+
+        let error = desc.clone().build(&TextureCache::new()).err().unwrap();
+        assert_eq!(error.path(), "Lambertian.albedo.Image.path");
+        let magenta: Arc<dyn Texture> = texture.into();
+        assert_eq!(magenta.value(0.5, 0.5, &p), Vec3(1., 0., 1.));
+        let _: Arc<dyn MaterialExt> = desc.into();
+    }
+}
@@ -0,0 +1,375 @@
+//! Film buffers and pipelined rendering of animations.
+//!
+//! A `FilmBuffer` collects the linear radiance of every pixel of a frame.
+//! It is tone-mapped (gamma 2) into 8-bit RGB only when the frame is encoded.
+//!
+//! When rendering an animation, an `AnimationPipeline` overlaps the rendering
+//! of one frame with the encoding of the previous ones. A fixed number of film
+//! buffers is cycled between the rendering thread and a dedicated encoder
+//! thread through bounded channels, such that the memory use stays bounded
+//! even if the encoder is slower than the renderer.
+//!
+//! ```
+//! use raytracer::film::AnimationPipeline;
+//! use raytracer::vec3::Vec3;
+//! use std::sync::atomic::AtomicBool;
+//! let pipeline = AnimationPipeline::new(4, 2, 2);
+//! let cancel = AtomicBool::new(false);
+//! let mut encoded = vec![];
+//! let written = pipeline.run(
+//!     3,
+//!     &cancel,
+//!     |frame, film| film.set(0, 0, Vec3(frame as f32, 0., 0.)),
+//!     |frame, film| -> Result<(), ()> {
+//!         encoded.push((frame, film.get(0, 0).r()));
+//!         Ok(())
+//!     },
+//! );
+//! assert_eq!(written, Ok(3));
+//! assert_eq!(encoded, vec![(0, 0.), (1, 1.), (2, 2.)]);
+//! ```
+
+use crate::render::to_rgb;
+use crate::vec3::Vec3;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::thread;
+
+/// Linear radiance of every pixel of an image.
+///
+/// Pixels are stored row by row, starting with the top row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilmBuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+}
+
+impl FilmBuffer {
+    /// Create a black film of the given dimensions.
+    ///
+    /// ```
+    /// # use raytracer::film::FilmBuffer;
+    /// # use raytracer::vec3::Vec3;
+    /// let film = FilmBuffer::new(4, 2);
+    /// assert_eq!(film.get(3, 1), Vec3(0., 0., 0.));
+    /// ```
+    pub fn new(width: usize, height: usize) -> FilmBuffer {
+        FilmBuffer {
+            width,
+            height,
+            pixels: vec![Vec3(0., 0., 0.); width * height],
+        }
+    }
+
+    /// Access the width of the film in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Access the height of the film in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Access the color of the pixel in column `x` and row `y` (counted from the top).
+    pub fn get(&self, x: usize, y: usize) -> Vec3 {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Set the color of the pixel in column `x` and row `y` (counted from the top).
+    ///
+    /// ```
+    /// # use raytracer::film::FilmBuffer;
+    /// # use raytracer::vec3::Vec3;
+    /// let mut film = FilmBuffer::new(4, 2);
+    /// film.set(1, 0, Vec3(0.5, 0.5, 0.5));
+    /// assert_eq!(film.get(1, 0), Vec3(0.5, 0.5, 0.5));
+    /// ```
+    pub fn set(&mut self, x: usize, y: usize, color: Vec3) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    /// Access the pixels row by row, starting with the top row.
+    pub fn pixels(&self) -> &[Vec3] {
+        &self.pixels
+    }
+
+    /// Mutably access the pixels row by row, starting with the top row.
+    pub fn pixels_mut(&mut self) -> &mut [Vec3] {
+        &mut self.pixels
+    }
+
+    /// Tone-map the film into 8-bit RGB values using a gamma of 2.
+    ///
+    /// ```
+    /// # use raytracer::film::FilmBuffer;
+    /// # use raytracer::vec3::Vec3;
+    /// let mut film = FilmBuffer::new(1, 1);
+    /// film.set(0, 0, Vec3(1., 0.25, 0.));
+    /// assert_eq!(film.to_rgb8(), vec![254, 127, 0]);
+    /// ```
+    pub fn to_rgb8(&self) -> Vec<u8> {
+        let mut rgb = vec![0; self.pixels.len() * 3];
+        for (values, col) in rgb.chunks_exact_mut(3).zip(&self.pixels) {
+            values.copy_from_slice(&to_rgb(*col));
+        }
+        rgb
+    }
+
+    /// Tone-map the film and write it to an image file.
+    pub fn save(&self, path: &Path) -> image::ImageResult<()> {
+        image::save_buffer(
+            path,
+            &self.to_rgb8(),
+            self.width as u32,
+            self.height as u32,
+            image::ColorType::Rgb8,
+        )
+    }
+}
+
+/// Error returned when rendering an animation through an `AnimationPipeline` fails.
+#[derive(Debug, PartialEq)]
+pub enum AnimationError<E> {
+    /// The encoder failed on the given frame. No further frames are rendered.
+    Encode { frame: usize, error: E },
+    /// The encoder thread panicked.
+    EncoderPanicked,
+    /// The animation was cancelled after the given number of frames was written.
+    Cancelled { frames_written: usize },
+}
+
+impl<E: fmt::Display> fmt::Display for AnimationError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AnimationError::Encode { frame, error } => {
+                write!(f, "encoding frame {} failed: {}", frame, error)
+            }
+            AnimationError::EncoderPanicked => write!(f, "the encoder thread panicked"),
+            AnimationError::Cancelled { frames_written } => write!(
+                f,
+                "the animation was cancelled after {} frames",
+                frames_written
+            ),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> Error for AnimationError<E> {}
+
+/// Renders the frames of an animation while encoding the previous ones.
+#[derive(Debug, Clone, Copy)]
+pub struct AnimationPipeline {
+    width: usize,
+    height: usize,
+    buffers: usize,
+}
+
+impl AnimationPipeline {
+    /// Create a pipeline for frames of the given dimensions cycling `buffers` film buffers.
+    ///
+    /// At least two buffers are used, such that one frame can be rendered while
+    /// another one is encoded. More buffers allow the renderer to run further
+    /// ahead of a slow encoder at the cost of memory.
+    pub fn new(width: usize, height: usize, buffers: usize) -> AnimationPipeline {
+        AnimationPipeline {
+            width,
+            height,
+            buffers: buffers.max(2),
+        }
+    }
+
+    /// Render and encode `frames` frames and return the number of frames written.
+    ///
+    /// `render` is called on the calling thread with the frame index and a film
+    /// buffer to fill, while `encode` is called in order for every rendered frame
+    /// on a dedicated encoder thread. The first encoder error stops the rendering
+    /// and is returned to the caller.
+    ///
+    /// Setting `cancel` stops the rendering of new frames. The frames that were
+    /// already rendered are still encoded before `AnimationError::Cancelled` is
+    /// returned, such that no thread outlives the call.
+    pub fn run<R, W, E>(
+        &self,
+        frames: usize,
+        cancel: &AtomicBool,
+        mut render: R,
+        mut encode: W,
+    ) -> Result<usize, AnimationError<E>>
+    where
+        R: FnMut(usize, &mut FilmBuffer),
+        W: FnMut(usize, &FilmBuffer) -> Result<(), E> + Send,
+        E: Send,
+    {
+        // Rendered frames travel to the encoder and the buffers travel back,
+        // so the number of buffers bounds the number of frames in flight.
+        let (rendered_sender, rendered_receiver) =
+            mpsc::sync_channel::<(usize, FilmBuffer)>(self.buffers);
+        let (free_sender, free_receiver) = mpsc::sync_channel::<FilmBuffer>(self.buffers);
+        for _ in 0..self.buffers {
+            free_sender
+                .send(FilmBuffer::new(self.width, self.height))
+                .expect("the receiver is alive");
+        }
+
+        thread::scope(|scope| {
+            let encoder = scope.spawn(move || {
+                let mut written = 0;
+                for (frame, film) in rendered_receiver {
+                    encode(frame, &film).map_err(|error| (frame, error))?;
+                    written += 1;
+                    // The renderer may already have stopped, which is fine.
+                    let _ = free_sender.send(film);
+                }
+                Ok(written)
+            });
+
+            let mut cancelled = false;
+            for frame in 0..frames {
+                if cancel.load(Ordering::Relaxed) {
+                    cancelled = true;
+                    break;
+                }
+                // Waiting for a free buffer applies the back-pressure. If the
+                // encoder stopped, its result is collected below.
+                let mut film = match free_receiver.recv() {
+                    Ok(film) => film,
+                    Err(_) => break,
+                };
+                render(frame, &mut film);
+                if rendered_sender.send((frame, film)).is_err() {
+                    break;
+                }
+            }
+            // Closing the channel lets the encoder drain the remaining frames.
+            drop(rendered_sender);
+            drop(free_receiver);
+
+            match encoder.join() {
+                Err(_) => Err(AnimationError::EncoderPanicked),
+                Ok(Err((frame, error))) => Err(AnimationError::Encode { frame, error }),
+                Ok(Ok(frames_written)) if cancelled => {
+                    Err(AnimationError::Cancelled { frames_written })
+                }
+                Ok(Ok(frames_written)) => Ok(frames_written),
+            }
+        })
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    // Test that rendering and encoding overlap and frames are written in order.
+    fn test_pipeline_overlaps_render_and_encode() {
+        let pipeline = AnimationPipeline::new(8, 8, 2);
+        let cancel = AtomicBool::new(false);
+        let frames = 4;
+        // The renderer reports every frame it starts to the encoder.
+        let (started_sender, started_receiver) = mpsc::channel();
+        let mut written = vec![];
+        let encoded = &mut written;
+        let result = pipeline.run(
+            frames,
+            &cancel,
+            |frame, film| {
+                started_sender.send(frame).unwrap();
+                film.set(0, 0, Vec3(frame as f32, 0., 0.));
+            },
+            move |frame, film| -> Result<(), ()> {
+                // The encoding of a frame only finishes once the next frame is
+                // being rendered. Without any overlap, the renderer would wait
+                // for this encoding instead, which the timeout turns into a
+                // failure rather than a hang.
+                if frame + 1 < frames {
+                    let mut started = frame;
+                    while started != frame + 1 {
+                        started = started_receiver
+                            .recv_timeout(Duration::from_secs(10))
+                            .expect("the next frame is rendered during the encoding");
+                    }
+                }
+                encoded.push((frame, film.get(0, 0).r() as usize));
+                Ok(())
+            },
+        );
+        assert_eq!(result, Ok(4));
+        assert_eq!(written, vec![(0, 0), (1, 1), (2, 2), (3, 3)]);
+    }
+
+    #[test]
+    // Test that encoder errors reach the caller and stop the rendering.
+    fn test_pipeline_encoder_error() {
+        let pipeline = AnimationPipeline::new(2, 2, 2);
+        let cancel = AtomicBool::new(false);
+        let mut rendered = 0;
+        let result = pipeline.run(
+            100,
+            &cancel,
+            |_, _| rendered += 1,
+            |frame, _| if frame == 2 { Err("disk full") } else { Ok(()) },
+        );
+        assert_eq!(
+            result,
+            Err(AnimationError::Encode {
+                frame: 2,
+                error: "disk full"
+            })
+        );
+        // The renderer can only get a few frames ahead of the failed encoder.
+        assert!(rendered < 10);
+    }
+
+    #[test]
+    // Test that cancelling drains the frames already rendered.
+    fn test_pipeline_cancel() {
+        let pipeline = AnimationPipeline::new(2, 2, 3);
+        let cancel = AtomicBool::new(false);
+        let mut rendered = 0;
+        let mut written = vec![];
+        let result = pipeline.run(
+            10,
+            &cancel,
+            |frame, _| {
+                rendered += 1;
+                if frame == 4 {
+                    cancel.store(true, Ordering::Relaxed);
+                }
+            },
+            |frame, _| -> Result<(), ()> {
+                thread::sleep(Duration::from_millis(10));
+                written.push(frame);
+                Ok(())
+            },
+        );
+        assert_eq!(rendered, 5);
+        assert_eq!(written, vec![0, 1, 2, 3, 4]);
+        assert_eq!(result, Err(AnimationError::Cancelled { frames_written: 5 }));
+    }
+
+    #[test]
+    // Test that an encoder panic is reported instead of propagated.
+    fn test_pipeline_encoder_panic() {
+        let pipeline = AnimationPipeline::new(2, 2, 2);
+        let cancel = AtomicBool::new(false);
+        let result = pipeline.run(
+            3,
+            &cancel,
+            |_, _| {},
+            |_, _| -> Result<(), ()> { panic!("encoder bug") },
+        );
+        assert_eq!(result, Err(AnimationError::EncoderPanicked));
+    }
+}
@@ -1,6 +1,5 @@
-use crate::materials::Material;
+use crate::materials::MaterialExt;
 use crate::vec3::Vec3;
-use std::sync::Arc;
 
 /// Collect information on the hit point between a ray and an object.
 ///
@@ -8,12 +7,47 @@ use std::sync::Arc;
 /// 1. The parameter `t` at which the ray intersects the object.
 /// 2. The intersection point itself, given by the ray at parameter `t`.
 /// 3. The surface normal of the object at the hit point
+/// 4. The surface coordinates `u` and `v` of the hit point, both between
+///    0 and 1, used for texturing. Objects without a natural
+///    parameterization set them to zero.
+/// 5. The tangent and the bitangent, the derivatives of the hit point along
+///    `u` and `v`, normalized, which span the tangent space of normal maps.
+///    Objects without surface derivatives set them to zero.
+/// 6. Whether the ray hits the front side of the surface, i.e. enters a
+///    closed object. Media hit inside their volume report the front side.
+/// 7. The material of the object at the hit point.
+///
+/// The material is borrowed from the object that was hit, such that no
+/// reference count has to be updated per hit, and the record cannot outlive
+/// the scene.
 // #[derive(Debug)]
-pub struct HitRecord {
+pub struct HitRecord<'a> {
     pub parameter: f32,
     pub point_at_parameter: Vec3,
     pub normal: Vec3,
-    // Use an `Arc` such that hit records can be shared across `rayon` threads
-    // in `Arc`s.
-    pub material: Arc<dyn Material>,
+    pub u: f32,
+    pub v: f32,
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
+    pub front_face: bool,
+    // Materials are `Send` and `Sync`, such that hit records can be used on
+    // `rayon` threads.
+    pub material: &'a dyn MaterialExt,
+}
+
+impl HitRecord<'_> {
+    // Move the tangent and the bitangent along with the surface, mapping
+    // them by the linear map `map` and renormalizing them. Missing ones stay
+    // zero.
+    pub(crate) fn map_tangents(&mut self, map: impl Fn(&Vec3) -> Vec3) {
+        for direction in [&mut self.tangent, &mut self.bitangent] {
+            let mapped = map(direction);
+            let length = mapped.length();
+            *direction = if length > 0. {
+                mapped / length
+            } else {
+                Vec3(0., 0., 0.)
+            };
+        }
+    }
 }
@@ -1,6 +1,18 @@
+pub mod aabb;
 pub mod camera;
+#[cfg(feature = "serde")]
+pub mod description;
+pub mod film;
 pub mod hit_record;
+pub mod lights;
 pub mod materials;
 pub mod objects;
+pub mod pdf;
+pub mod perlin;
 pub mod ray;
+pub mod render;
+pub mod stats;
+pub mod textures;
+pub mod transform;
 pub mod vec3;
+pub mod worley;
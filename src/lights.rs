@@ -0,0 +1,1041 @@
+//! Importance sampling of many light sources.
+//!
+//! Scenes with hundreds of emitters cannot be lit efficiently by picking a
+//! light uniformly at random, since most of the picked lights are far away
+//! and contribute almost nothing. A `LightTree` is a bounding volume hierarchy
+//! over the emitters where each node stores the total power and the spatial
+//! bounds of the emitters below it. A light is selected by descending the tree
+//! and choosing each child with a probability proportional to its importance
+//! estimate (power over squared distance) as seen from the shading point.
+//! Emitters which only emit to one side, e.g. rects, are weighted down by
+//! the angle at which they face the point, bounded per node by a cone of
+//! their normals.
+//!
+//! The emitting objects of a scene are collected into `Lights`, see
+//! `objects::scene::Scene`, which the renderer samples directions towards
+//! for next event estimation.
+//!
+//! ```
+//! use raytracer::lights::Emitter;
+//! use raytracer::lights::LightTree;
+//! use raytracer::vec3::Vec3;
+//! let emitters = vec![
+//!     Emitter::new(Vec3(0., 5., 0.), 0.5, 10.),
+//!     Emitter::new(Vec3(100., 5., 0.), 0.5, 10.),
+//! ];
+//! let tree = LightTree::new(emitters);
+//! let sample = tree.sample(&Vec3(0., 0., 0.), 0.3).unwrap();
+//! assert_eq!(sample.index, 0);
+//! assert!(sample.pdf > 0.99);
+//! ```
+
+use rand::Rng;
+use rand::RngCore;
+use std::f32::consts::PI;
+
+use crate::hit_record::HitRecord;
+use crate::materials::around_axis;
+use crate::materials::MaterialExt;
+use crate::objects::sphere::sphere_uv;
+use crate::objects::Hitable;
+use crate::vec3::cross;
+use crate::vec3::dot;
+use crate::vec3::unit_vector;
+use crate::vec3::Vec3;
+
+/// Summary of an emitting object as needed for light selection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Emitter {
+    /// Center of the emitter.
+    pub center: Vec3,
+    /// Radius of a sphere enclosing the emitter.
+    pub radius: f32,
+    /// Total emitted power.
+    pub power: f32,
+    /// The unit normal of an emitter which only emits to the side it points
+    /// to, e.g. a rect, or `None` if it emits in all directions.
+    pub normal: Option<Vec3>,
+}
+
+impl Emitter {
+    /// Create an emitter summary by specifying its `center`, enclosing `radius` and `power`.
+    ///
+    /// ```
+    /// # use raytracer::lights::Emitter;
+    /// # use raytracer::vec3::Vec3;
+    /// let emitter = Emitter::new(Vec3(0., 5., 0.), 0.5, 10.);
+    /// assert_eq!(emitter.power, 10.);
+    /// ```
+    pub fn new(center: Vec3, radius: f32, power: f32) -> Emitter {
+        Emitter {
+            center,
+            radius,
+            power,
+            normal: None,
+        }
+    }
+
+    /// Only emit to the side of `normal`, which need not be normalized.
+    ///
+    /// ```
+    /// # use raytracer::lights::Emitter;
+    /// # use raytracer::vec3::Vec3;
+    /// let emitter = Emitter::new(Vec3(0., 5., 0.), 0.5, 10.).with_normal(Vec3(0., -2., 0.));
+    /// assert_eq!(emitter.normal, Some(Vec3(0., -1., 0.)));
+    /// ```
+    pub fn with_normal(mut self, normal: Vec3) -> Emitter {
+        self.normal = Some(unit_vector(&normal));
+        self
+    }
+}
+
+/// A light chosen by the `LightTree` together with the probability of choosing it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightSample {
+    /// Index of the chosen emitter in the list the tree was built from.
+    pub index: usize,
+    /// Probability with which this emitter was selected.
+    pub pdf: f32,
+}
+
+#[derive(Debug)]
+enum NodeKind {
+    Leaf { emitter: usize },
+    Interior { left: usize, right: usize },
+}
+
+// A cone bounding the normals of emitters, given by its unit axis and the
+// largest angle between the axis and a normal. Emitters emitting in all
+// directions are bounded by the angle pi.
+#[derive(Debug, Clone, Copy)]
+struct NormalCone {
+    axis: Vec3,
+    angle: f32,
+}
+
+impl NormalCone {
+    // The cone of all directions.
+    fn all() -> NormalCone {
+        NormalCone {
+            axis: Vec3(0., 0., 1.),
+            angle: PI,
+        }
+    }
+
+    fn of(emitter: &Emitter) -> NormalCone {
+        match emitter.normal {
+            Some(normal) => NormalCone {
+                axis: normal,
+                angle: 0.,
+            },
+            None => NormalCone::all(),
+        }
+    }
+
+    // The smallest cone around the axis of the wider of both cones, turned
+    // towards the other one, that bounds both.
+    fn union(&self, other: &NormalCone) -> NormalCone {
+        let (wide, narrow) = if self.angle >= other.angle {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let between = dot(&wide.axis, &narrow.axis).clamp(-1., 1.).acos();
+        if between + narrow.angle <= wide.angle {
+            return *wide;
+        }
+        let angle = 0.5 * (wide.angle + between + narrow.angle);
+        // Opposite axes leave no direction to turn to.
+        let turn = narrow.axis - dot(&wide.axis, &narrow.axis) * wide.axis;
+        if angle >= PI || turn.squared_length() < 1e-12 {
+            return NormalCone::all();
+        }
+        let rotation = angle - wide.angle;
+        NormalCone {
+            axis: rotation.cos() * wide.axis + rotation.sin() * unit_vector(&turn),
+            angle,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Node {
+    min: Vec3,
+    max: Vec3,
+    // Power weighted center of the emitters.
+    centroid: Vec3,
+    power: f32,
+    normals: NormalCone,
+    parent: Option<usize>,
+    kind: NodeKind,
+}
+
+impl Node {
+    // Estimate how much light the emitters in this node send to `point`.
+    //
+    // Outside of the bounds the distance to the closest point of the bounds is
+    // used, which never underestimates the contribution of a nearby emitter.
+    // Inside the bounds that distance vanishes, so the distance to the power
+    // weighted center clamped by the size of the node is used instead.
+    fn importance(&self, point: &Vec3) -> f32 {
+        let closest = component_max(&self.min, &component_min(point, &self.max));
+        let mut distance_squared = (closest - *point).squared_length();
+        if distance_squared == 0. {
+            let radius_squared = 0.25 * (self.max - self.min).squared_length();
+            distance_squared = (self.centroid - *point)
+                .squared_length()
+                .max(radius_squared);
+        }
+        let power = self.power * self.orientation(point);
+        if distance_squared == 0. {
+            power
+        } else {
+            power / distance_squared
+        }
+    }
+
+    // Bound the cosine of the angle between the normal of any emitter in
+    // this node and the direction from it to `point`, which is zero if all
+    // of them face away from the point.
+    //
+    // The directions from the node to the point lie within the angle its
+    // bounding sphere subtends at the point, and the normals within their
+    // cone, such that the smallest angle between them is the angle between
+    // the axis and the direction to the center of the node less both.
+    fn orientation(&self, point: &Vec3) -> f32 {
+        if self.normals.angle >= PI {
+            return 1.;
+        }
+        let center = 0.5 * (self.min + self.max);
+        let radius = 0.5 * (self.max - self.min).length();
+        let to_point = *point - center;
+        let distance = to_point.length();
+        if distance <= radius {
+            return 1.;
+        }
+        let angle = (dot(&self.normals.axis, &to_point) / distance)
+            .clamp(-1., 1.)
+            .acos();
+        let bounds = (radius / distance).asin();
+        let smallest = angle - self.normals.angle - bounds;
+        if smallest <= 0. {
+            1.
+        } else if smallest >= 0.5 * PI {
+            0.
+        } else {
+            smallest.cos()
+        }
+    }
+}
+
+/// A bounding volume hierarchy over emitters for importance sampling of lights.
+#[derive(Debug)]
+pub struct LightTree {
+    emitters: Vec<Emitter>,
+    nodes: Vec<Node>,
+    // The leaf node holding each emitter.
+    leaves: Vec<usize>,
+}
+
+impl LightTree {
+    /// Build a light tree over a list of emitters.
+    ///
+    /// The emitters are recursively split at the median along the longest
+    /// axis of the bounds of their centers.
+    pub fn new(emitters: Vec<Emitter>) -> LightTree {
+        let mut tree = LightTree {
+            nodes: Vec::with_capacity(2 * emitters.len()),
+            leaves: vec![0; emitters.len()],
+            emitters,
+        };
+        if !tree.emitters.is_empty() {
+            let mut indices = (0..tree.emitters.len()).collect::<Vec<_>>();
+            tree.build(&mut indices, None);
+        }
+        tree
+    }
+
+    // Recursively build the subtree over `indices` and return its node index.
+    fn build(&mut self, indices: &mut [usize], parent: Option<usize>) -> usize {
+        let mut min = Vec3(f32::MAX, f32::MAX, f32::MAX);
+        let mut max = Vec3(f32::MIN, f32::MIN, f32::MIN);
+        let mut weighted_center = Vec3(0., 0., 0.);
+        let mut power = 0.;
+        let mut normals = NormalCone::of(&self.emitters[indices[0]]);
+        for &i in indices.iter() {
+            let e = &self.emitters[i];
+            let r = Vec3(e.radius, e.radius, e.radius);
+            min = component_min(&min, &(e.center - r));
+            max = component_max(&max, &(e.center + r));
+            weighted_center += e.power * e.center;
+            power += e.power;
+            normals = normals.union(&NormalCone::of(e));
+        }
+        let centroid = if power > 0. {
+            weighted_center / power
+        } else {
+            0.5 * (min + max)
+        };
+
+        let node = self.nodes.len();
+        if indices.len() == 1 {
+            self.leaves[indices[0]] = node;
+            self.nodes.push(Node {
+                min,
+                max,
+                centroid,
+                power,
+                normals,
+                parent,
+                kind: NodeKind::Leaf {
+                    emitter: indices[0],
+                },
+            });
+            return node;
+        }
+
+        // Placeholder that is completed once the children are built.
+        self.nodes.push(Node {
+            min,
+            max,
+            centroid,
+            power,
+            normals,
+            parent,
+            kind: NodeKind::Interior { left: 0, right: 0 },
+        });
+
+        let extent = max - min;
+        let axis = if extent.x() > extent.y() && extent.x() > extent.z() {
+            0
+        } else if extent.y() > extent.z() {
+            1
+        } else {
+            2
+        };
+        let coordinate = |e: &Emitter| match axis {
+            0 => e.center.x(),
+            1 => e.center.y(),
+            _ => e.center.z(),
+        };
+        let emitters = &self.emitters;
+        indices.sort_by(|&a, &b| {
+            coordinate(&emitters[a])
+                .partial_cmp(&coordinate(&emitters[b]))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let (left_indices, right_indices) = indices.split_at_mut(indices.len() / 2);
+        let left = self.build(left_indices, Some(node));
+        let right = self.build(right_indices, Some(node));
+        self.nodes[node].kind = NodeKind::Interior { left, right };
+        node
+    }
+
+    /// Access the emitters the tree was built from.
+    pub fn emitters(&self) -> &[Emitter] {
+        &self.emitters
+    }
+
+    // Probability of descending into `left` rather than `right` when shading `point`.
+    fn left_probability(&self, point: &Vec3, left: usize, right: usize) -> f32 {
+        let left_importance = self.nodes[left].importance(point);
+        let right_importance = self.nodes[right].importance(point);
+        let total = left_importance + right_importance;
+        if total > 0. {
+            left_importance / total
+        } else {
+            0.5
+        }
+    }
+
+    /// Choose an emitter for shading `point` using a uniform random number `u` in `[0, 1)`.
+    ///
+    /// `None` is returned if the tree contains no emitters.
+    pub fn sample(&self, point: &Vec3, u: f32) -> Option<LightSample> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let mut u = u;
+        let mut pdf = 1.;
+        let mut node = 0;
+        loop {
+            match self.nodes[node].kind {
+                NodeKind::Leaf { emitter } => {
+                    return Some(LightSample {
+                        index: emitter,
+                        pdf,
+                    })
+                }
+                NodeKind::Interior { left, right } => {
+                    let p_left = self.left_probability(point, left, right);
+                    // Reuse the random number by rescaling it to the chosen interval.
+                    if u < p_left {
+                        u /= p_left;
+                        pdf *= p_left;
+                        node = left;
+                    } else {
+                        u = (u - p_left) / (1. - p_left);
+                        pdf *= 1. - p_left;
+                        node = right;
+                    }
+                    u = u.min(1. - f32::EPSILON);
+                }
+            }
+        }
+    }
+
+    /// Return the probability that `sample` chooses the emitter `index` for shading `point`.
+    ///
+    /// This is needed to weight light samples found by other strategies,
+    /// e.g. by scattering, in multiple importance sampling.
+    pub fn pdf(&self, point: &Vec3, index: usize) -> f32 {
+        if index >= self.leaves.len() {
+            return 0.;
+        }
+        let mut pdf = 1.;
+        let mut node = self.leaves[index];
+        while let Some(parent) = self.nodes[node].parent {
+            if let NodeKind::Interior { left, right } = self.nodes[parent].kind {
+                let p_left = self.left_probability(point, left, right);
+                pdf *= if node == left { p_left } else { 1. - p_left };
+            }
+            node = parent;
+        }
+        pdf
+    }
+
+    // Call `visit` with the emitters whose bounds contain `point`, until it
+    // returns true, and return that emitter.
+    fn find(&self, point: &Vec3, mut visit: impl FnMut(usize) -> bool) -> Option<usize> {
+        // Allow for the rounding of hit points on the surface of emitters.
+        let contains = |node: &Node| {
+            let margin = 1e-3 * (1. + (node.max - node.min).length());
+            let margin = Vec3(margin, margin, margin);
+            component_max(point, &(node.min - margin)) == *point
+                && component_min(point, &(node.max + margin)) == *point
+        };
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let node = match self.nodes.get(node) {
+                Some(node) if contains(node) => node,
+                _ => continue,
+            };
+            match node.kind {
+                NodeKind::Leaf { emitter } => {
+                    if visit(emitter) {
+                        return Some(emitter);
+                    }
+                }
+                NodeKind::Interior { left, right } => stack.extend([right, left]),
+            }
+        }
+        None
+    }
+}
+
+/// The shape of an emitting object, which directions towards are sampled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightShape {
+    /// A sphere, see `objects::sphere::Sphere`.
+    Sphere { center: Vec3, radius: f32 },
+    /// A parallelogram, see `objects::rect::Rect`.
+    Rect {
+        corner: Vec3,
+        edge_u: Vec3,
+        edge_v: Vec3,
+    },
+}
+
+/// An emitting object of a scene.
+///
+/// Lights are created by the objects of a scene from their material, see
+/// `Hitable::collect_lights`, and only exist for materials which emit light.
+/// The light is told apart from other objects hit by the address of its
+/// material.
+#[derive(Debug, Clone)]
+pub struct Light {
+    shape: LightShape,
+    emitter: Emitter,
+    material: usize,
+}
+
+impl Light {
+    /// Create the light of a sphere of the `material`, or `None` if the
+    /// material does not emit light.
+    ///
+    /// The power is estimated from the light emitted at the top of the sphere.
+    ///
+    /// ```
+    /// # use raytracer::lights::Light;
+    /// # use raytracer::materials::Lambertian;
+    /// # use raytracer::vec3::Vec3;
+    /// let material = Lambertian::new(Vec3(0.8, 0.8, 0.8));
+    /// assert!(Light::sphere(Vec3(0., 2., 0.), 0.5, &material).is_none());
+    /// ```
+    pub fn sphere(center: Vec3, radius: f32, material: &dyn MaterialExt) -> Option<Light> {
+        let radius = radius.abs();
+        let normal = Vec3(0., 1., 0.);
+        let (u, v) = sphere_uv(&normal);
+        let radiance = mean(&material.emitted(&probe(
+            center + radius * normal,
+            normal,
+            u,
+            v,
+            true,
+            material,
+        )));
+        if radiance <= 0. {
+            return None;
+        }
+        let area = 4. * PI * radius * radius;
+        Some(Light {
+            shape: LightShape::Sphere { center, radius },
+            emitter: Emitter::new(center, radius, PI * radiance * area),
+            material: address(material),
+        })
+    }
+
+    /// Create the light of a rect of the `material`, or `None` if the
+    /// material does not emit light.
+    ///
+    /// The power is estimated from the light emitted at the center of either
+    /// side. A rect emitting from one side only is oriented towards it.
+    pub fn rect(
+        corner: Vec3,
+        edge_u: Vec3,
+        edge_v: Vec3,
+        material: &dyn MaterialExt,
+    ) -> Option<Light> {
+        let normal = unit_vector(&cross(&edge_u, &edge_v));
+        let center = corner + 0.5 * (edge_u + edge_v);
+        let front = mean(&material.emitted(&probe(center, normal, 0.5, 0.5, true, material)));
+        let back = mean(&material.emitted(&probe(center, normal, 0.5, 0.5, false, material)));
+        if front + back <= 0. {
+            return None;
+        }
+        let area = cross(&edge_u, &edge_v).length();
+        let radius = 0.5 * (edge_u + edge_v).length().max((edge_u - edge_v).length());
+        let mut emitter = Emitter::new(center, radius, PI * (front + back) * area);
+        if back <= 0. {
+            emitter = emitter.with_normal(normal);
+        } else if front <= 0. {
+            emitter = emitter.with_normal(-normal);
+        }
+        Some(Light {
+            shape: LightShape::Rect {
+                corner,
+                edge_u,
+                edge_v,
+            },
+            emitter,
+            material: address(material),
+        })
+    }
+
+    /// Access the shape of the light.
+    pub fn shape(&self) -> &LightShape {
+        &self.shape
+    }
+
+    /// Access the summary of the light used to select it.
+    pub fn emitter(&self) -> &Emitter {
+        &self.emitter
+    }
+
+    // Whether `hit` lies on the light.
+    fn contains(&self, hit: &HitRecord) -> bool {
+        if address(hit.material) != self.material {
+            return false;
+        }
+        let point = hit.point_at_parameter;
+        match self.shape {
+            LightShape::Sphere { center, radius } => {
+                ((point - center).length() - radius).abs() <= 1e-3 * (1. + radius)
+            }
+            LightShape::Rect {
+                corner,
+                edge_u,
+                edge_v,
+            } => {
+                let n = cross(&edge_u, &edge_v);
+                let p = point - corner;
+                let w = n / dot(&n, &n);
+                let u = dot(&w, &cross(&p, &edge_v));
+                let v = dot(&w, &cross(&edge_u, &p));
+                let tolerance = 1e-3;
+                dot(&w, &p).abs() * n.length() <= tolerance * (1. + n.length().sqrt())
+                    && (-tolerance..=1. + tolerance).contains(&u)
+                    && (-tolerance..=1. + tolerance).contains(&v)
+            }
+        }
+    }
+
+    // Draw a unit direction from `point` towards the light, and return it
+    // with its density per unit of solid angle, or `None` if there is none.
+    fn sample(&self, point: &Vec3, rng: &mut dyn RngCore) -> Option<(Vec3, f32)> {
+        match self.shape {
+            LightShape::Sphere { center, radius } => {
+                let to_center = center - *point;
+                let distance_squared = to_center.squared_length();
+                if distance_squared <= radius * radius {
+                    return None;
+                }
+                // Draw directions uniformly from the cone of the sphere.
+                let cone = cone_solid_angle(radius * radius / distance_squared);
+                let cos_theta = 1. - rng.gen::<f32>() * cone / (2. * PI);
+                let direction = around_axis(&unit_vector(&to_center), cos_theta, rng);
+                Some((direction, 1. / cone))
+            }
+            LightShape::Rect {
+                corner,
+                edge_u,
+                edge_v,
+            } => {
+                let target = corner + rng.gen::<f32>() * edge_u + rng.gen::<f32>() * edge_v;
+                let direction = unit_vector(&(target - *point));
+                let pdf = self.rect_pdf(point, &target, &direction);
+                if pdf > 0. {
+                    Some((direction, pdf))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    // The density per unit of solid angle with which `sample` draws the
+    // direction from `point` to the point `target` on the light.
+    fn pdf(&self, point: &Vec3, target: &Vec3) -> f32 {
+        match self.shape {
+            LightShape::Sphere { center, radius } => {
+                let distance_squared = (center - *point).squared_length();
+                if distance_squared <= radius * radius {
+                    0.
+                } else {
+                    1. / cone_solid_angle(radius * radius / distance_squared)
+                }
+            }
+            LightShape::Rect { .. } => {
+                let direction = unit_vector(&(*target - *point));
+                self.rect_pdf(point, target, &direction)
+            }
+        }
+    }
+
+    // The density of the unit `direction` from `point` to the point `target`
+    // on a rect, drawn uniformly over its area.
+    fn rect_pdf(&self, point: &Vec3, target: &Vec3, direction: &Vec3) -> f32 {
+        let normal = match self.shape {
+            LightShape::Rect { edge_u, edge_v, .. } => cross(&edge_u, &edge_v),
+            LightShape::Sphere { .. } => return 0.,
+        };
+        let area = normal.length();
+        let cosine = dot(direction, &normal).abs() / area;
+        if cosine < 1e-6 {
+            return 0.;
+        }
+        (*target - *point).squared_length() / (cosine * area)
+    }
+}
+
+// The solid angle of the cone around a sphere seen at the squared ratio
+// `x` of its radius to the distance of its center.
+fn cone_solid_angle(x: f32) -> f32 {
+    // One less the cosine of the opening angle, sqrt(1 - x), without
+    // cancellation for small spheres.
+    2. * PI * x / (1. + (1. - x).sqrt())
+}
+
+// The address of a material, which tells materials apart.
+fn address(material: &dyn MaterialExt) -> usize {
+    material as *const dyn MaterialExt as *const () as usize
+}
+
+// The mean of the components of a color.
+fn mean(color: &Vec3) -> f32 {
+    (color.x() + color.y() + color.z()) / 3.
+}
+
+// A hit at the `point` with the `normal`, at which the light emitted by a
+// material is probed.
+fn probe<'a>(
+    point: Vec3,
+    normal: Vec3,
+    u: f32,
+    v: f32,
+    front_face: bool,
+    material: &'a dyn MaterialExt,
+) -> HitRecord<'a> {
+    HitRecord {
+        parameter: 0.,
+        point_at_parameter: point,
+        normal,
+        u,
+        v,
+        tangent: Vec3(0., 0., 0.),
+        bitangent: Vec3(0., 0., 0.),
+        front_face,
+        material,
+    }
+}
+
+/// A direction towards a light drawn by `Lights::sample`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightDirection {
+    /// The index of the light among `Lights::lights`.
+    pub index: usize,
+    /// The unit direction towards the light.
+    pub direction: Vec3,
+    /// The density per unit of solid angle of the direction, including the
+    /// probability of selecting the light.
+    pub pdf: f32,
+}
+
+/// The emitting objects of a scene, with a light tree to select them.
+///
+/// ```
+/// # use raytracer::lights::Lights;
+/// # use raytracer::materials::Lambertian;
+/// # use raytracer::objects::sphere::Sphere;
+/// # use raytracer::objects::HitableList;
+/// # use raytracer::vec3::Vec3;
+/// # use std::sync::Arc;
+/// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.8, 0.8)));
+/// let world = HitableList::new(vec![Box::new(Sphere::new(Vec3(0., 0., -1.), 0.5, material))]);
+/// assert!(Lights::collect(&world).is_empty());
+/// ```
+#[derive(Debug)]
+pub struct Lights {
+    lights: Vec<Light>,
+    tree: LightTree,
+}
+
+impl Lights {
+    /// Build the light tree over the `lights`.
+    pub fn new(lights: Vec<Light>) -> Lights {
+        let tree = LightTree::new(lights.iter().map(|light| light.emitter).collect());
+        Lights { lights, tree }
+    }
+
+    /// Collect the lights of the objects of `world`, see
+    /// `Hitable::collect_lights`.
+    pub fn collect(world: &dyn Hitable) -> Lights {
+        let mut lights = Vec::new();
+        world.collect_lights(&mut lights);
+        Lights::new(lights)
+    }
+
+    /// Return whether there are no lights.
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    /// Access the lights.
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    /// Access the light tree selecting the lights.
+    pub fn tree(&self) -> &LightTree {
+        &self.tree
+    }
+
+    /// Select a light for shading `point` and draw a direction towards it,
+    /// or return `None` if there is no light to be seen from the point.
+    pub fn sample(&self, point: &Vec3, rng: &mut dyn RngCore) -> Option<LightDirection> {
+        let selected = self.tree.sample(point, rng.gen::<f32>())?;
+        let (direction, pdf) = self.lights[selected.index].sample(point, rng)?;
+        Some(LightDirection {
+            index: selected.index,
+            direction,
+            pdf: selected.pdf * pdf,
+        })
+    }
+
+    /// Return the index of the light the `hit` lies on, if any.
+    pub fn find(&self, hit: &HitRecord) -> Option<usize> {
+        self.tree.find(&hit.point_at_parameter, |index| {
+            self.lights[index].contains(hit)
+        })
+    }
+
+    /// Return the density per unit of solid angle with which `sample` draws
+    /// the direction from `point` to the `hit`, zero if it is not on a light.
+    ///
+    /// This weighs the light found by scattering a ray against the light
+    /// sampled directly in multiple importance sampling.
+    pub fn pdf(&self, point: &Vec3, hit: &HitRecord) -> f32 {
+        match self.find(hit) {
+            Some(index) => {
+                self.tree.pdf(point, index) * self.lights[index].pdf(point, &hit.point_at_parameter)
+            }
+            None => 0.,
+        }
+    }
+}
+
+fn component_min(a: &Vec3, b: &Vec3) -> Vec3 {
+    Vec3(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z()))
+}
+
+fn component_max(a: &Vec3, b: &Vec3) -> Vec3 {
+    Vec3(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z()))
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+    use crate::objects::rect::Rect;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::HitableList;
+    use crate::ray::Ray;
+    use rand::prelude::*;
+    use rand::rngs::StdRng;
+    use std::sync::Arc;
+
+    // One bright light close to the origin and 99 dim lights far away.
+    fn emitters() -> Vec<Emitter> {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut emitters = vec![Emitter::new(Vec3(0., 2., 0.), 0.1, 100.)];
+        for _ in 0..99 {
+            let center = Vec3(
+                50. + 50. * rng.gen::<f32>(),
+                2. + 10. * rng.gen::<f32>(),
+                -50. + 100. * rng.gen::<f32>(),
+            );
+            emitters.push(Emitter::new(center, 0.1, 1.));
+        }
+        emitters
+    }
+
+    // Irradiance-like contribution of an emitter at `point`.
+    fn contribution(emitter: &Emitter, point: &Vec3) -> f32 {
+        emitter.power / (emitter.center - *point).squared_length()
+    }
+
+    #[test]
+    // Test that the probabilities over all emitters sum up to one.
+    fn test_pdf_normalized() {
+        let tree = LightTree::new(emitters());
+        let point = Vec3(1., 0., 3.);
+        let total = (0..100).map(|i| tree.pdf(&point, i)).sum::<f32>();
+        assert!((total - 1.).abs() < 1e-4);
+    }
+
+    #[test]
+    // Test that the pdf reported by sample matches the pdf method.
+    fn test_sample_pdf_consistent() {
+        let tree = LightTree::new(emitters());
+        let point = Vec3(10., 0., 3.);
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..100 {
+            let sample = tree.sample(&point, rng.gen::<f32>()).unwrap();
+            assert!((sample.pdf - tree.pdf(&point, sample.index)).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    // Test that the bright close light is chosen with a high probability.
+    fn test_bright_light_preferred() {
+        let tree = LightTree::new(emitters());
+        let point = Vec3(0., 0., 0.);
+        assert!(tree.pdf(&point, 0) > 0.9);
+        let mut rng = StdRng::seed_from_u64(2);
+        let chosen = (0..1000)
+            .filter(|_| tree.sample(&point, rng.gen::<f32>()).unwrap().index == 0)
+            .count();
+        assert!(chosen > 900);
+    }
+
+    #[test]
+    // Test that the estimator is unbiased and has lower variance than uniform selection.
+    fn test_unbiased_and_lower_variance() {
+        let emitters = emitters();
+        let tree = LightTree::new(emitters.clone());
+        let point = Vec3(0., 0., 0.);
+        let exact = emitters
+            .iter()
+            .map(|e| contribution(e, &point) as f64)
+            .sum::<f64>();
+
+        let n = 20_000;
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut tree_estimates = vec![];
+        let mut uniform_estimates = vec![];
+        for _ in 0..n {
+            let sample = tree.sample(&point, rng.gen::<f32>()).unwrap();
+            let value = contribution(&emitters[sample.index], &point) / sample.pdf;
+            tree_estimates.push(value as f64);
+            let index = rng.gen_range(0..emitters.len());
+            let value = contribution(&emitters[index], &point) * emitters.len() as f32;
+            uniform_estimates.push(value as f64);
+        }
+
+        let mean = |v: &[f64]| v.iter().sum::<f64>() / v.len() as f64;
+        let variance = |v: &[f64]| {
+            let m = mean(v);
+            v.iter().map(|x| (x - m).powi(2)).sum::<f64>() / (v.len() - 1) as f64
+        };
+        let tree_mean = mean(&tree_estimates);
+        let uniform_mean = mean(&uniform_estimates);
+        // Agreement within a few standard errors.
+        let tree_error = (variance(&tree_estimates) / n as f64).sqrt();
+        let uniform_error = (variance(&uniform_estimates) / n as f64).sqrt();
+        assert!((tree_mean - exact).abs() < 4. * tree_error + 1e-3 * exact);
+        assert!((uniform_mean - exact).abs() < 4. * uniform_error + 1e-3 * exact);
+        assert!(variance(&tree_estimates) < variance(&uniform_estimates));
+    }
+
+    #[test]
+    // Test that a one-sided emitter facing away from the point is never chosen.
+    fn test_orientation() {
+        let facing = Emitter::new(Vec3(-2., 2., 0.), 0.5, 10.).with_normal(Vec3(0., -1., 0.));
+        let away = Emitter::new(Vec3(2., 2., 0.), 0.5, 10.).with_normal(Vec3(0., 1., 0.));
+        let tree = LightTree::new(vec![facing, away]);
+        let point = Vec3(0., 0., 0.);
+        assert_eq!(tree.pdf(&point, 0), 1.);
+        assert_eq!(tree.pdf(&point, 1), 0.);
+        // Seen from above, the other one faces the point.
+        let point = Vec3(0., 4., 0.);
+        assert_eq!(tree.pdf(&point, 0), 0.);
+        assert_eq!(tree.pdf(&point, 1), 1.);
+        // The cone of both normals bounds the emitters from all sides.
+        assert_eq!(tree.nodes[0].normals.angle, PI);
+    }
+
+    #[test]
+    // Test the cone bounding the normals of two emitters.
+    fn test_normal_cone_union() {
+        let up = NormalCone {
+            axis: Vec3(0., 1., 0.),
+            angle: 0.,
+        };
+        let side = NormalCone {
+            axis: Vec3(1., 0., 0.),
+            angle: 0.,
+        };
+        let both = up.union(&side);
+        assert!((both.angle - 0.25 * PI).abs() < 1e-6);
+        let diagonal = unit_vector(&Vec3(1., 1., 0.));
+        assert!((both.axis - diagonal).length() < 1e-6);
+        assert!((both.union(&up).angle - both.angle).abs() < 1e-6);
+        assert_eq!(NormalCone::all().union(&up).angle, PI);
+    }
+
+    // A material emitting light from the front faces of objects.
+    struct Glow;
+
+    impl MaterialExt for Glow {
+        fn scatter(
+            &self,
+            _ray: &Ray,
+            _hit: &HitRecord,
+            _rng: &mut dyn RngCore,
+        ) -> Option<(Ray, Vec3)> {
+            None
+        }
+
+        fn emitted(&self, hit: &HitRecord) -> Vec3 {
+            if hit.front_face {
+                Vec3(4., 4., 4.)
+            } else {
+                Vec3(0., 0., 0.)
+            }
+        }
+    }
+
+    // A floor with an emitting sphere and an emitting rect above it.
+    fn world() -> HitableList {
+        let glow = Arc::new(Glow);
+        let gray = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        HitableList::new(vec![
+            Box::new(Rect::new(
+                Vec3(-10., 0., 10.),
+                Vec3(20., 0., 0.),
+                Vec3(0., 0., -20.),
+                gray.clone(),
+            )),
+            Box::new(Sphere::new(Vec3(-1., 2., 0.), 0.5, glow.clone())),
+            Box::new(Rect::new(
+                Vec3(1., 3., 1.),
+                Vec3(0., 0., -2.),
+                Vec3(1., 0., 0.),
+                glow,
+            )),
+            Box::new(Sphere::new(Vec3(3., 1., 0.), 1., gray)),
+        ])
+    }
+
+    #[test]
+    // Test that the emitting objects of a scene are collected.
+    fn test_collect() {
+        let lights = Lights::collect(&world());
+        assert_eq!(lights.lights().len(), 2);
+        let sphere = lights.lights()[0].emitter();
+        assert_eq!(sphere.center, Vec3(-1., 2., 0.));
+        assert_eq!(sphere.normal, None);
+        assert!((sphere.power - 4. * PI * PI).abs() < 1e-4);
+        // The rect only emits downwards, from its front side.
+        let rect = lights.lights()[1].emitter();
+        assert_eq!(rect.normal, Some(Vec3(0., -1., 0.)));
+        assert!((rect.power - 8. * PI).abs() < 1e-4);
+    }
+
+    #[test]
+    // Test that the density of the sampled directions matches the density of
+    // the hits, and integrates the solid angle of the lights.
+    fn test_lights_sample_pdf_consistent() {
+        let world = world();
+        let lights = Lights::collect(&world);
+        let point = Vec3(0.5, 0., 0.5);
+        let mut rng = StdRng::seed_from_u64(4);
+        let n = 20_000;
+        let mut hits = [0; 2];
+        let mut inverse_pdfs = [0.; 2];
+        for _ in 0..n {
+            let sample = lights.sample(&point, &mut rng).unwrap();
+            let ray = Ray::new(point, sample.direction);
+            let hit = world.intersect(&ray, 0.001, f32::MAX).unwrap();
+            assert_eq!(lights.find(&hit), Some(sample.index));
+            let pdf = lights.pdf(&point, &hit);
+            assert!(
+                (pdf - sample.pdf).abs() <= 1e-3 * sample.pdf,
+                "{} {}",
+                pdf,
+                sample.pdf
+            );
+            hits[sample.index] += 1;
+            inverse_pdfs[sample.index] += 1. / sample.pdf;
+        }
+        assert!(hits.iter().all(|&hits| hits > n / 10), "{:?}", hits);
+        // The mean inverse density of the directions is the solid angle.
+        let sphere = 2. * PI * (1. - (1. - 0.25 / 6.5_f32).sqrt());
+        assert!((inverse_pdfs[0] / n as f32 - sphere).abs() < 0.02 * sphere);
+        // Misses are not on a light.
+        let miss = world
+            .intersect(
+                &Ray::new(Vec3(0., 5., 0.), Vec3(0., -1., 0.)),
+                0.001,
+                f32::MAX,
+            )
+            .unwrap();
+        assert_eq!(lights.find(&miss), None);
+        assert_eq!(lights.pdf(&point, &miss), 0.);
+    }
+
+    #[test]
+    // Test the degenerate trees.
+    fn test_empty_and_single() {
+        let tree = LightTree::new(vec![]);
+        assert!(tree.sample(&Vec3(0., 0., 0.), 0.5).is_none());
+        let tree = LightTree::new(vec![Emitter::new(Vec3(0., 1., 0.), 0.1, 1.)]);
+        let sample = tree.sample(&Vec3(0., 0., 0.), 0.5).unwrap();
+        assert_eq!(sample, LightSample { index: 0, pdf: 1. });
+    }
+}
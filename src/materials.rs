@@ -1,15 +1,32 @@
 use rand::prelude::*;
+use std::error::Error;
+use std::f32::consts::PI;
+use std::fmt;
+use std::sync::Arc;
 
+#[cfg(feature = "serde")]
+use crate::description::MaterialDesc;
 use crate::hit_record::HitRecord;
+use crate::pdf::CosinePdf;
+use crate::pdf::Pdf;
 use crate::ray::Ray;
+use crate::textures::SolidColor;
+use crate::textures::Texture;
+use crate::vec3::cross;
 use crate::vec3::dot;
 use crate::vec3::unit_vector;
 use crate::vec3::Vec3;
 
-/// Generate a random vector in the unit sphere.
-pub fn random_in_unit_sphere() -> Vec3 {
-    // TODO: CHECK whether it is efficient to regenerate a random number generator on every function call.
-    let mut rng = rand::thread_rng();
+/// Generate a random vector in the unit sphere, using the random number generator `rng`.
+///
+/// ```
+/// # use raytracer::materials::random_in_unit_sphere;
+/// # use rand::rngs::SmallRng;
+/// # use rand::SeedableRng;
+/// let mut rng = SmallRng::seed_from_u64(0);
+/// assert!(random_in_unit_sphere(&mut rng).length() < 1.);
+/// ```
+pub fn random_in_unit_sphere(rng: &mut dyn RngCore) -> Vec3 {
     // Start with a vector that has length larger than 1!
     let mut p = Vec3(10., 10., 10.);
     while p.squared_length() >= 1.0 {
@@ -18,6 +35,78 @@ pub fn random_in_unit_sphere() -> Vec3 {
     p
 }
 
+/// Check that an attenuation follows the linear color convention.
+///
+/// All materials expect attenuation components between 0 and 1. A component
+/// greater than 1 makes a surface reflect more light than it receives, which
+/// usually means that the color was given in 0–255 units by mistake (use
+/// `Vec3::from_u8` for those). In that case a warning message is returned.
+///
+/// ```
+/// # use raytracer::vec3::Vec3;
+/// # use raytracer::materials::validate_attenuation;
+/// assert!(validate_attenuation(&Vec3(0.8, 0.2, 0.2)).is_none());
+/// assert!(validate_attenuation(&Vec3(200., 50., 50.)).is_some());
+/// ```
+pub fn validate_attenuation(attenuation: &Vec3) -> Option<String> {
+    if attenuation.x() > 1. || attenuation.y() > 1. || attenuation.z() > 1. {
+        Some(format!(
+            "attenuation {:?} has components greater than 1, colors are expected \
+             to be linear values between 0 and 1",
+            attenuation
+        ))
+    } else {
+        None
+    }
+}
+
+/// Error returned when a material is created from invalid parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MaterialError {
+    /// A component of an attenuation is not between 0 and 1.
+    InvalidAttenuation { attenuation: Vec3 },
+    /// The fuzzyness of a metal is not between 0 and 1.
+    InvalidFuzzyness { fuzzy: f32 },
+    /// The refractive index of a dielectric is not positive.
+    InvalidRefractiveIndex { ref_idx: f32 },
+}
+
+impl fmt::Display for MaterialError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MaterialError::InvalidAttenuation { attenuation } => write!(
+                f,
+                "invalid attenuation ({}, {}, {}): all components must be between 0 and 1",
+                attenuation.x(),
+                attenuation.y(),
+                attenuation.z()
+            ),
+            MaterialError::InvalidFuzzyness { fuzzy } => {
+                write!(f, "invalid fuzzyness {}: must be between 0 and 1", fuzzy)
+            }
+            MaterialError::InvalidRefractiveIndex { ref_idx } => {
+                write!(f, "invalid refractive index {}: must be positive", ref_idx)
+            }
+        }
+    }
+}
+
+impl Error for MaterialError {}
+
+// Check that all components of the `attenuation` are between 0 and 1, which
+// rules out NaN as well.
+fn check_attenuation(attenuation: Vec3) -> Result<Vec3, MaterialError> {
+    let range = 0. ..=1.;
+    if range.contains(&attenuation.x())
+        && range.contains(&attenuation.y())
+        && range.contains(&attenuation.z())
+    {
+        Ok(attenuation)
+    } else {
+        Err(MaterialError::InvalidAttenuation { attenuation })
+    }
+}
+
 /// Schlick's approximation for the dependence of reflectivity of glass on the angle.
 fn schlick(cosine: f32, ref_idx: f32) -> f32 {
     let r0 = ((1. - ref_idx) / (1. + ref_idx)).powi(2);
@@ -37,7 +126,7 @@ fn schlick(cosine: f32, ref_idx: f32) -> f32 {
 /// assert_eq!(reflected.z(), 0.);
 /// ```
 pub fn reflect(v: &Vec3, n: &Vec3) -> Vec3 {
-    *v - 2. * dot(&v, &n) * *n
+    *v - 2. * dot(v, n) * *n
 }
 
 /// Compute the refracted ray vector.
@@ -49,7 +138,7 @@ pub fn reflect(v: &Vec3, n: &Vec3) -> Vec3 {
 /// Sometimes, refraction is not possible, if Snell's law has no solution
 /// and in this case None is returned.
 pub fn refract(v: &Vec3, n: &Vec3, ni_over_nt: f32) -> Option<Vec3> {
-    let uv = unit_vector(&v);
+    let uv = unit_vector(v);
     let dt = dot(&uv, n);
     let discriminant = 1.0 - ni_over_nt.powi(2) * (1. - dt.powi(2));
     if discriminant > 0. {
@@ -59,6 +148,28 @@ pub fn refract(v: &Vec3, n: &Vec3, ni_over_nt: f32) -> Option<Vec3> {
     }
 }
 
+// Return two unit vectors which form an orthonormal basis with the unit
+// vector `axis`, built from the coordinate axis least aligned with it.
+fn tangents(axis: &Vec3) -> (Vec3, Vec3) {
+    let helper = if axis.x().abs() < 0.9 {
+        Vec3(1., 0., 0.)
+    } else {
+        Vec3(0., 1., 0.)
+    };
+    let u = unit_vector(&cross(&helper, axis));
+    let v = cross(axis, &u);
+    (u, v)
+}
+
+// Return a unit vector at the angle with the cosine `cos_theta` to the unit
+// vector `axis`, at a random azimuth around it.
+pub(crate) fn around_axis(axis: &Vec3, cos_theta: f32, rng: &mut dyn RngCore) -> Vec3 {
+    let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+    let phi = 2. * PI * rng.gen::<f32>();
+    let (u, v) = tangents(axis);
+    sin_theta * phi.cos() * u + sin_theta * phi.sin() * v + cos_theta * *axis
+}
+
 /// A material trait.
 ///
 /// A material is characterized by the way a ray is scattered
@@ -67,18 +178,218 @@ pub fn refract(v: &Vec3, n: &Vec3, ni_over_nt: f32) -> Option<Vec3> {
 /// Given the incoming ray and hit record containing the information
 /// on the point where this ray hits the surface, the scattered ray,
 /// as well as the attenuation vector are returned.
+///
+/// This trait is kept stable for materials implemented outside of this
+/// crate. Every `Material` is also a `MaterialExt` through a blanket
+/// implementation, which is the trait the renderer works with. New materials
+/// should implement `MaterialExt`, see there for the migration of existing
+/// ones before this trait is deprecated in version 0.2.0.
 pub trait Material: Send + Sync {
     // Subtraiting `Send` & `Sync` in order to be able to use the material
     // objects in rayon threads using `Arc` without having to copy them.
 
     /// Return the scattered ray and the attenuation.
+    ///
+    /// Random decisions are drawn by the material itself, e.g. from
+    /// `rand::thread_rng()`. Implement `MaterialExt` instead to draw them from
+    /// the random number generator of the renderer.
     fn scatter(&self, ray: &Ray, hit: &HitRecord) -> Option<(Ray, Vec3)>;
 }
 
+/// The material trait used by the objects and the renderer.
+///
+/// New capabilities of materials are added to this trait rather than to
+/// `Material`, together with a default that keeps existing materials working.
+///
+/// There are two ways to provide a material:
+/// 1. Implement the minimal `Material` trait. The blanket implementation
+///    `impl<T: Material> MaterialExt for T` adapts it, making the following
+///    approximations:
+///    - `scatter` delegates to `Material::scatter`, ignoring the random
+///      number generator it is given.
+///    - `emitted` assumes that the material does not emit light.
+///    - `scatter_with_pdf` reports every scattering as specular.
+/// 2. Implement `MaterialExt` directly (and not `Material`) to get access to
+///    all capabilities.
+///
+/// New materials should implement `MaterialExt`. `Material` remains
+/// supported throughout the 0.1 releases and will be marked `#[deprecated]`
+/// in version 0.2.0. Migrating a material means renaming its `impl Material`
+/// to `impl MaterialExt` and adding the `rng: &mut dyn RngCore` parameter to
+/// `scatter`, from which the random numbers should then be drawn instead of
+/// `rand::thread_rng()`.
+///
+/// ```
+/// use rand::RngCore;
+/// use raytracer::hit_record::HitRecord;
+/// use raytracer::materials::MaterialExt;
+/// use raytracer::ray::Ray;
+/// use raytracer::vec3::Vec3;
+///
+/// /// A material which glows and absorbs all incoming light.
+/// struct Glow;
+///
+/// impl MaterialExt for Glow {
+///     fn scatter(
+///         &self,
+///         _ray: &Ray,
+///         _hit: &HitRecord,
+///         _rng: &mut dyn RngCore,
+///     ) -> Option<(Ray, Vec3)> {
+///         None
+///     }
+///
+///     fn emitted(&self, _hit: &HitRecord) -> Vec3 {
+///         Vec3(4., 4., 4.)
+///     }
+/// }
+/// ```
+pub trait MaterialExt: Send + Sync {
+    /// Return the scattered ray and the attenuation.
+    ///
+    /// Random decisions are drawn from `rng`, which the renderer keeps per
+    /// worker thread.
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<(Ray, Vec3)>;
+
+    /// Return the light emitted by the material at the hit point.
+    ///
+    /// Defaults to black, i.e. a non-emissive material.
+    fn emitted(&self, _hit: &HitRecord) -> Vec3 {
+        Vec3(0., 0., 0.)
+    }
+
+    /// Return whether the material only catches shadows, see `ShadowCatcher`.
+    ///
+    /// Defaults to false.
+    fn is_shadow_catcher(&self) -> bool {
+        false
+    }
+
+    /// Return the refractive index of the medium inside objects of the
+    /// material, if light passes through them.
+    ///
+    /// The renderer keeps track of the media a path has entered, see
+    /// `scatter_in`. Defaults to `None`, i.e. an opaque material.
+    fn refractive_index(&self) -> Option<f32> {
+        None
+    }
+
+    /// Return the absorption coefficients per unit of distance of the three
+    /// color components in the medium inside objects of the material, see
+    /// `refractive_index`.
+    ///
+    /// The renderer attenuates the light along every segment of a path inside
+    /// the medium by `exp(-absorption * d)`, where `d` is the length of the
+    /// segment, whichever surface it ends on. Defaults to zero, i.e. a clear
+    /// medium.
+    fn absorption(&self) -> Vec3 {
+        Vec3(0., 0., 0.)
+    }
+
+    /// Return the scattered ray and the attenuation like `scatter`, given the
+    /// refractive index of the medium `outside` the surface of the object.
+    ///
+    /// The renderer calls this instead of `scatter`, with the index of the
+    /// innermost medium the path has entered other than this material, or 1
+    /// for vacuum. Defaults to `scatter`, ignoring the medium.
+    fn scatter_in(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        _outside: f32,
+        rng: &mut dyn RngCore,
+    ) -> Option<(Ray, Vec3)> {
+        self.scatter(ray, hit, rng)
+    }
+
+    /// Return how the ray is scattered, telling specular scatterings apart
+    /// from diffuse ones, whose directions can be importance sampled.
+    ///
+    /// Defaults to `scatter`, reported as a specular scattering.
+    fn scatter_with_pdf(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        rng: &mut dyn RngCore,
+    ) -> Option<ScatterRecord> {
+        self.scatter(ray, hit, rng)
+            .map(|(ray, attenuation)| ScatterRecord::Specular { ray, attenuation })
+    }
+
+    /// Return the density per unit of solid angle with which the material
+    /// scatters the incoming `ray` at the hit into the direction of
+    /// `scattered`.
+    ///
+    /// Only meaningful for diffuse scatterings, see `ScatterRecord`.
+    /// Defaults to zero.
+    fn scattering_pdf(&self, _ray: &Ray, _hit: &HitRecord, _scattered: &Ray) -> f32 {
+        0.
+    }
+
+    /// Return the description the material is built from again, see
+    /// `MaterialDesc::build`.
+    ///
+    /// Defaults to `None`, for materials which cannot be described.
+    #[cfg(feature = "serde")]
+    fn describe(&self) -> Option<MaterialDesc> {
+        None
+    }
+}
+
+/// How a material scatters a ray, see `MaterialExt::scatter_with_pdf`.
+pub enum ScatterRecord {
+    /// A scattering into a single direction, e.g. off a mirror or through
+    /// glass, which cannot be importance sampled.
+    Specular {
+        /// The scattered ray.
+        ray: Ray,
+        /// The attenuation of the light along the scattered ray.
+        attenuation: Vec3,
+    },
+    /// A scattering into directions spread over a density. A direction drawn
+    /// from another density `q` is weighted by the attenuation times the
+    /// ratio of `MaterialExt::scattering_pdf` to `q`, which is the
+    /// attenuation itself for directions drawn from `pdf`.
+    Diffuse {
+        /// The attenuation of the light for directions drawn from `pdf`.
+        attenuation: Vec3,
+        /// The density the material draws the scattered directions from.
+        pdf: Box<dyn Pdf>,
+    },
+}
+
+// Densities need not be `Debug`, which is why only the variant is printed
+// for diffuse scatterings.
+impl fmt::Debug for ScatterRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScatterRecord::Specular { ray, attenuation } => f
+                .debug_struct("Specular")
+                .field("ray", ray)
+                .field("attenuation", attenuation)
+                .finish(),
+            ScatterRecord::Diffuse { attenuation, .. } => f
+                .debug_struct("Diffuse")
+                .field("attenuation", attenuation)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+impl<T: Material + ?Sized> MaterialExt for T {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, _rng: &mut dyn RngCore) -> Option<(Ray, Vec3)> {
+        Material::scatter(self, ray, hit)
+    }
+}
+
 /// A Lambertian (diffuse) material.
-#[derive(Default, Debug)]
+///
+/// The attenuation of a Lambertian is given by a texture, which is sampled at
+/// the surface coordinates and the position of the hit point.
 pub struct Lambertian {
+    // The color the material was created with by `new`, black for a texture.
     attenuation: Vec3,
+    albedo: Arc<dyn Texture>,
 }
 
 impl Lambertian {
@@ -92,11 +403,48 @@ impl Lambertian {
     /// let diffuse_material = Lambertian::new(attenuation);
     /// ```
     pub fn new(attenuation: Vec3) -> Lambertian {
-        Lambertian { attenuation }
+        Lambertian {
+            attenuation,
+            albedo: Arc::new(SolidColor::new(attenuation)),
+        }
+    }
+
+    /// Create a Lambertian material like `new`, checking that all components
+    /// of the attenuation are between 0 and 1.
+    ///
+    /// ```
+    /// # use raytracer::materials::Lambertian;
+    /// # use raytracer::vec3::Vec3;
+    /// assert!(Lambertian::try_new(Vec3(0.8, 0.7, 0.9)).is_ok());
+    /// // A color given in 0–255 units by mistake.
+    /// assert!(Lambertian::try_new(Vec3(200., 180., 230.)).is_err());
+    /// ```
+    pub fn try_new(attenuation: Vec3) -> Result<Lambertian, MaterialError> {
+        Ok(Lambertian::new(check_attenuation(attenuation)?))
+    }
+
+    /// Create a Lambertian material whose attenuation is given by a texture.
+    ///
+    /// ```
+    /// # use raytracer::materials::Lambertian;
+    /// # use raytracer::textures::SolidColor;
+    /// # use raytracer::vec3::Vec3;
+    /// # use std::sync::Arc;
+    /// let texture = Arc::new(SolidColor::new(Vec3(0.8, 0.7, 0.9)));
+    /// let diffuse_material = Lambertian::with_texture(texture);
+    /// ```
+    pub fn with_texture(albedo: Arc<dyn Texture>) -> Lambertian {
+        Lambertian {
+            attenuation: Vec3::default(),
+            albedo,
+        }
     }
 
     /// Extract the attenuation information from the Lambertian material.
     ///
+    /// A material created by `with_texture` has no single attenuation, and
+    /// returns black. Its colors are given by `texture`.
+    ///
     /// ```
     /// # use raytracer::materials::Lambertian;
     /// # use raytracer::vec3::Vec3;
@@ -110,21 +458,372 @@ impl Lambertian {
     pub fn attenuation(&self) -> &Vec3 {
         &self.attenuation
     }
+
+    /// Access the texture giving the attenuation of the Lambertian material.
+    ///
+    /// ```
+    /// # use raytracer::materials::Lambertian;
+    /// # use raytracer::vec3::Vec3;
+    /// // Define how each color component is attenuated by the diffuse material.
+    /// let attenuation = Vec3(0.8, 0.7, 0.9);
+    /// let diffuse_material = Lambertian::new(attenuation);
+    /// let p = Vec3(0., 0., 0.);
+    /// assert_eq!(diffuse_material.texture().value(0., 0., &p), attenuation);
+    /// ```
+    pub fn texture(&self) -> &dyn Texture {
+        self.albedo.as_ref()
+    }
+
+    /// Return a warning if the attenuation is outside the 0–1 color range.
+    ///
+    /// ```
+    /// # use raytracer::materials::Lambertian;
+    /// # use raytracer::vec3::Vec3;
+    /// assert!(Lambertian::new(Vec3(0.8, 0.2, 0.2)).warning().is_none());
+    /// assert!(Lambertian::new(Vec3(200., 50., 50.)).warning().is_some());
+    /// ```
+    pub fn warning(&self) -> Option<String> {
+        self.albedo.warning()
+    }
+}
+
+impl Default for Lambertian {
+    fn default() -> Lambertian {
+        Lambertian::new(Vec3::default())
+    }
+}
+
+// Textures need not be `Debug`, which is why only the attenuation is printed.
+impl fmt::Debug for Lambertian {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Lambertian")
+            .field("attenuation", &self.attenuation)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MaterialExt for Lambertian {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<(Ray, Vec3)> {
+        let scattered = diffuse_scatter(ray, hit, rng);
+        let attenuation = self.albedo.value_at_hit(hit);
+        Some((scattered, attenuation))
+    }
+
+    fn scatter_with_pdf(
+        &self,
+        _ray: &Ray,
+        hit: &HitRecord,
+        _rng: &mut dyn RngCore,
+    ) -> Option<ScatterRecord> {
+        Some(ScatterRecord::Diffuse {
+            attenuation: self.albedo.value_at_hit(hit),
+            pdf: Box::new(CosinePdf::new(&hit.normal)),
+        })
+    }
+
+    fn scattering_pdf(&self, _ray: &Ray, hit: &HitRecord, scattered: &Ray) -> f32 {
+        CosinePdf::new(&hit.normal).value(scattered.direction())
+    }
+
+    #[cfg(feature = "serde")]
+    fn describe(&self) -> Option<MaterialDesc> {
+        Some(MaterialDesc::Lambertian {
+            albedo: self.albedo.describe()?,
+        })
+    }
+}
+
+// Scatter a ray diffusely off the surface at the hit point, towards a random
+// point in the unit sphere touching the surface.
+fn diffuse_scatter(ray: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Ray {
+    let target = hit.point_at_parameter + hit.normal + random_in_unit_sphere(rng);
+    Ray::new_with_time(
+        hit.point_at_parameter,
+        target - hit.point_at_parameter,
+        ray.time(),
+    )
+}
+
+/// A rough diffuse material, following the qualitative Oren–Nayar model.
+///
+/// The surface is made of tiny Lambertian facets whose slopes have the
+/// standard deviation `sigma`, in radians. Compared to a `Lambertian`, the
+/// facets make the surface look flatter, e.g. clay, concrete or the moon,
+/// since it gets brighter when lit from the direction it is seen from. A
+/// `sigma` of 0 gives a `Lambertian`.
+pub struct OrenNayar {
+    albedo: Arc<dyn Texture>,
+    sigma: f32,
+}
+
+impl OrenNayar {
+    /// Create an Oren–Nayar material by specifying its albedo and the
+    /// roughness `sigma`.
+    ///
+    /// ```
+    /// # use raytracer::materials::OrenNayar;
+    /// # use raytracer::vec3::Vec3;
+    /// let clay = OrenNayar::new(Vec3(0.7, 0.4, 0.3), 0.5);
+    /// assert_eq!(clay.sigma(), 0.5);
+    /// ```
+    pub fn new(albedo: Vec3, sigma: f32) -> OrenNayar {
+        OrenNayar::with_texture(Arc::new(SolidColor::new(albedo)), sigma)
+    }
+
+    /// Create an Oren–Nayar material whose albedo is given by a texture.
+    pub fn with_texture(albedo: Arc<dyn Texture>, sigma: f32) -> OrenNayar {
+        OrenNayar { albedo, sigma }
+    }
+
+    /// Access the roughness of the material.
+    pub fn sigma(&self) -> f32 {
+        self.sigma
+    }
+
+    /// Access the texture giving the albedo of the material.
+    pub fn texture(&self) -> &dyn Texture {
+        self.albedo.as_ref()
+    }
+
+    // The factor by which the roughness changes the reflection of light
+    // coming from `light` towards `view`, both unit vectors pointing away
+    // from the surface with the unit normal `normal`.
+    fn weight(&self, view: &Vec3, light: &Vec3, normal: &Vec3) -> f32 {
+        let sigma2 = self.sigma * self.sigma;
+        let a = 1. - 0.5 * sigma2 / (sigma2 + 0.33);
+        let b = 0.45 * sigma2 / (sigma2 + 0.09);
+
+        let cos_view = dot(view, normal).clamp(0., 1.);
+        let cos_light = dot(light, normal).clamp(0., 1.);
+        // The cosine of the difference of the azimuths, from the projections
+        // onto the tangent plane.
+        let view_tangent = *view - cos_view * *normal;
+        let light_tangent = *light - cos_light * *normal;
+        let lengths = view_tangent.length() * light_tangent.length();
+        let cos_azimuth = if lengths > 1e-6 {
+            dot(&view_tangent, &light_tangent) / lengths
+        } else {
+            0.
+        };
+        // sin(alpha) tan(beta), with alpha the larger and beta the smaller
+        // of the angles to the normal.
+        let (cos_alpha, cos_beta) = if cos_view < cos_light {
+            (cos_view, cos_light)
+        } else {
+            (cos_light, cos_view)
+        };
+        if cos_beta <= 0. {
+            return a;
+        }
+        let sin_alpha = (1. - cos_alpha * cos_alpha).sqrt();
+        let tan_beta = (1. - cos_beta * cos_beta).sqrt() / cos_beta;
+        a + b * cos_azimuth.max(0.) * sin_alpha * tan_beta
+    }
+}
+
+// Textures need not be `Debug`, which is why only the roughness is printed.
+impl fmt::Debug for OrenNayar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OrenNayar")
+            .field("sigma", &self.sigma)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MaterialExt for OrenNayar {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<(Ray, Vec3)> {
+        // The direction is sampled as for a Lambertian, and the weight of
+        // the roughness follows from the angles to it.
+        let scattered = diffuse_scatter(ray, hit, rng);
+        let albedo = self.albedo.value_at_hit(hit);
+        if self.sigma == 0. {
+            return Some((scattered, albedo));
+        }
+        let view = -unit_vector(ray.direction());
+        let light = unit_vector(scattered.direction());
+        let weight = self.weight(&view, &light, &unit_vector(&hit.normal));
+        Some((scattered, weight * albedo))
+    }
+}
+
+// The number of cosines of the view angle at which the reflectance of the
+// sheen lobe is tabulated, from 0 to 1.
+const SHEEN_TABLE_SIZE: usize = 16;
+
+/// A cloth-like material, e.g. velvet, with a sheen over a diffuse base.
+///
+/// The sheen is a lobe of the "Charlie" microfiber distribution, whose fibers
+/// stand up from the surface and mostly reflect light arriving at grazing
+/// angles, giving cloth its bright rim. It is layered over a Lambertian
+/// base, which is weighted down by the fraction of the light the sheen
+/// reflects, such that no energy is created. Directions are drawn from the
+/// cosine distribution, and the sheen is folded into the attenuation.
+pub struct Sheen {
+    albedo: Arc<dyn Texture>,
+    sheen: Vec3,
+    roughness: f32,
+    // The reflectance of the sheen lobe of a white sheen at the cosines of
+    // the view angle from 0 to 1.
+    reflectance: [f32; SHEEN_TABLE_SIZE],
+}
+
+impl Sheen {
+    /// Create a sheen material by specifying the albedo of the base, the
+    /// color of the sheen and its roughness, which is forced to be between
+    /// 0 and 1.
+    ///
+    /// A black sheen gives a `Lambertian`.
+    ///
+    /// ```
+    /// # use raytracer::materials::Sheen;
+    /// # use raytracer::vec3::Vec3;
+    /// let velvet = Sheen::new(Vec3(0.3, 0.02, 0.05), Vec3(1., 0.6, 0.7), 0.4);
+    /// assert_eq!(velvet.roughness(), 0.4);
+    /// assert!(velvet.reflectance(0.1) > velvet.reflectance(0.9));
+    /// ```
+    pub fn new(albedo: Vec3, sheen: Vec3, roughness: f32) -> Sheen {
+        Sheen::with_texture(Arc::new(SolidColor::new(albedo)), sheen, roughness)
+    }
+
+    /// Create a sheen material like `new`, whose albedo is given by a texture.
+    pub fn with_texture(albedo: Arc<dyn Texture>, sheen: Vec3, roughness: f32) -> Sheen {
+        let mut material = Sheen {
+            albedo,
+            sheen,
+            roughness: roughness.clamp(0., 1.),
+            reflectance: [0.; SHEEN_TABLE_SIZE],
+        };
+        for i in 0..SHEEN_TABLE_SIZE {
+            let cos_view = i as f32 / (SHEEN_TABLE_SIZE - 1) as f32;
+            material.reflectance[i] = material.integrate_lobe(cos_view);
+        }
+        material
+    }
+
+    /// Access the texture giving the albedo of the base.
+    pub fn texture(&self) -> &dyn Texture {
+        self.albedo.as_ref()
+    }
+
+    /// Access the color of the sheen.
+    pub fn sheen(&self) -> &Vec3 {
+        &self.sheen
+    }
+
+    /// Access the roughness of the sheen.
+    pub fn roughness(&self) -> f32 {
+        self.roughness
+    }
+
+    /// Return the fraction of the light a white sheen reflects towards a
+    /// view at the angle with the cosine `cos_view` to the normal.
+    pub fn reflectance(&self, cos_view: f32) -> f32 {
+        self.lobe_reflectance(cos_view).min(1.)
+    }
+
+    /// Return a warning if the albedo or the sheen is outside the 0–1 color
+    /// range.
+    pub fn warning(&self) -> Option<String> {
+        self.albedo
+            .warning()
+            .or_else(|| validate_attenuation(&self.sheen))
+    }
+
+    // The reflectance of the lobe towards a view at the angle with the cosine
+    // `cos_view` to the normal, interpolated from the table, which exceeds 1
+    // at grazing angles for smooth sheens.
+    fn lobe_reflectance(&self, cos_view: f32) -> f32 {
+        let position = cos_view.clamp(0., 1.) * (SHEEN_TABLE_SIZE - 1) as f32;
+        let i = (position as usize).min(SHEEN_TABLE_SIZE - 2);
+        let t = position - i as f32;
+        (1. - t) * self.reflectance[i] + t * self.reflectance[i + 1]
+    }
+
+    // The BRDF of the sheen lobe of a white sheen, given the cosines of the
+    // view and the light directions and of the half vector to the normal.
+    // The visibility term is the one of Ashikhmin's velvet model.
+    fn lobe(&self, cos_view: f32, cos_light: f32, cos_half: f32) -> f32 {
+        let alpha = (self.roughness * self.roughness).max(1e-3);
+        let sin_half = (1. - cos_half * cos_half).max(0.).sqrt();
+        let distribution = (2. + 1. / alpha) * sin_half.powf(1. / alpha) / (2. * PI);
+        let visibility = 1. / (4. * (cos_light + cos_view - cos_light * cos_view));
+        distribution * visibility
+    }
+
+    // The reflectance of the lobe towards a view at the angle with the cosine
+    // `cos_view` to the normal, integrated over a grid of cosine distributed
+    // light directions around the z axis.
+    fn integrate_lobe(&self, cos_view: f32) -> f32 {
+        let n = 32;
+        let view = Vec3((1. - cos_view * cos_view).sqrt(), 0., cos_view);
+        let mut sum = 0.;
+        for i in 0..n {
+            for j in 0..n {
+                let cos_light = ((i as f32 + 0.5) / n as f32).sqrt();
+                let sin_light = (1. - cos_light * cos_light).sqrt();
+                let phi = 2. * PI * (j as f32 + 0.5) / n as f32;
+                let light = Vec3(sin_light * phi.cos(), sin_light * phi.sin(), cos_light);
+                let half = unit_vector(&(view + light));
+                sum += self.lobe(cos_view, cos_light, half.z());
+            }
+        }
+        PI * sum / (n * n) as f32
+    }
+}
+
+// Textures need not be `Debug`, which is why only the sheen is printed.
+impl fmt::Debug for Sheen {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Sheen")
+            .field("sheen", &self.sheen)
+            .field("roughness", &self.roughness)
+            .finish_non_exhaustive()
+    }
 }
 
-impl Material for Lambertian {
-    fn scatter(&self, _ray: &Ray, hit: &HitRecord) -> Option<(Ray, Vec3)> {
-        let target = hit.point_at_parameter + hit.normal + random_in_unit_sphere();
-        let scattered = Ray::new(hit.point_at_parameter, target - hit.point_at_parameter);
-        Some((scattered, self.attenuation))
+impl MaterialExt for Sheen {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<(Ray, Vec3)> {
+        let albedo = self.albedo.value_at_hit(hit);
+        if self.sheen == Vec3(0., 0., 0.) {
+            return Some((diffuse_scatter(ray, hit, rng), albedo));
+        }
+        // The sheen is weighted for exactly cosine distributed directions,
+        // unlike the ones of `diffuse_scatter`.
+        let direction = CosinePdf::new(&hit.normal).generate(rng);
+        let scattered = Ray::new_with_time(hit.point_at_parameter, direction, ray.time());
+        let normal = unit_vector(&hit.normal);
+        let view = -unit_vector(ray.direction());
+        let light = unit_vector(scattered.direction());
+        let cos_view = dot(&view, &normal).clamp(0., 1.);
+        let cos_light = dot(&light, &normal);
+        let halfway = view + light;
+        let lobe = if cos_light > 0. && halfway.squared_length() > 0. {
+            // The lobe is scaled down where it would reflect more light than
+            // arrives, which its visibility term allows at grazing angles.
+            let total = self.lobe_reflectance(cos_view).max(1.);
+            self.lobe(cos_view, cos_light, dot(&unit_vector(&halfway), &normal)) / total
+        } else {
+            0.
+        };
+        let strength = self.sheen.x().max(self.sheen.y()).max(self.sheen.z());
+        let base = (1. - strength * self.reflectance(cos_view)).max(0.);
+        // With cosine distributed directions, the BRDF is weighted by pi.
+        Some((scattered, base * albedo + PI * lobe * self.sheen))
     }
 }
 
 /// A metal (reflective) material.
-#[derive(Default, Debug)]
+///
+/// The attenuation of a metal is given by a texture, e.g. to paint a pattern
+/// on a mirror. Its fuzzyness may be given by a texture as well, such that
+/// parts of the surface are polished and others rough.
 pub struct Metal {
+    // The color the metal was created with by `new`, black for a texture.
     attenuation: Vec3,
+    attenuation_texture: Arc<dyn Texture>,
     fuzzy: f32,
+    // Replaces the constant fuzzyness by the mean of its components if given.
+    fuzzy_texture: Option<Arc<dyn Texture>>,
 }
 
 impl Metal {
@@ -132,7 +831,8 @@ impl Metal {
     ///
     /// The attenuation specifies how each color component in the scattered ray
     /// is attenuated while the `fuzzy` parameter indicates the randomness in
-    /// the direction of the reflection. `fuzzy` is forced to be between 0 and 1.
+    /// the direction of the reflection. `fuzzy` is forced to be between 0 and
+    /// 1, where NaN counts as 0.
     ///
     /// A fuzzyness of 0 indicates perfect reflection.
     ///
@@ -142,20 +842,80 @@ impl Metal {
     /// // Define how each color component is attenuated by the reflective material.
     /// let attenuation = Vec3(0.8, 0.7, 0.9);
     /// let metal = Metal::new(attenuation, 0.0);
+    /// assert_eq!(Metal::new(attenuation, -0.5).fuzzy(), 0.);
     /// ```
     pub fn new(attenuation: Vec3, fuzzy: f32) -> Metal {
-        if fuzzy > 1. {
-            Metal {
-                attenuation,
-                fuzzy: 1.,
-            }
+        Metal {
+            attenuation,
+            ..Metal::with_texture(Arc::new(SolidColor::new(attenuation)), fuzzy)
+        }
+    }
+
+    /// Create a new metal like `new`, whose attenuation is given by a texture.
+    ///
+    /// ```
+    /// # use raytracer::materials::Metal;
+    /// # use raytracer::textures::{SolidColor, Texture};
+    /// # use raytracer::vec3::Vec3;
+    /// # use std::sync::Arc;
+    /// let gold = Arc::new(SolidColor::new(Vec3(1., 0.8, 0.3)));
+    /// let metal = Metal::with_texture(gold, 0.1);
+    /// let p = Vec3(0., 0., 0.);
+    /// assert_eq!(metal.attenuation_texture().value(0., 0., &p), Vec3(1., 0.8, 0.3));
+    /// ```
+    pub fn with_texture(attenuation: Arc<dyn Texture>, fuzzy: f32) -> Metal {
+        let fuzzy = if fuzzy.is_nan() {
+            0.
         } else {
-            Metal { attenuation, fuzzy }
+            fuzzy.clamp(0., 1.)
+        };
+        Metal {
+            attenuation: Vec3::default(),
+            attenuation_texture: attenuation,
+            fuzzy,
+            fuzzy_texture: None,
+        }
+    }
+
+    /// Take the fuzzyness from the mean of the components of a texture,
+    /// forced to be between 0 and 1, instead of the constant fuzzyness.
+    ///
+    /// ```
+    /// # use raytracer::materials::Metal;
+    /// # use raytracer::textures::SolidColor;
+    /// # use raytracer::vec3::Vec3;
+    /// # use std::sync::Arc;
+    /// let roughness = Arc::new(SolidColor::new(Vec3(0.1, 0.2, 0.3)));
+    /// let metal = Metal::new(Vec3(0.8, 0.8, 0.8), 0.).with_fuzzy_texture(roughness);
+    /// assert!(metal.fuzzy_texture().is_some());
+    /// ```
+    pub fn with_fuzzy_texture(mut self, fuzzy: Arc<dyn Texture>) -> Metal {
+        self.fuzzy_texture = Some(fuzzy);
+        self
+    }
+
+    /// Create a new metal like `new`, checking that all components of the
+    /// attenuation and the fuzzyness are between 0 and 1.
+    ///
+    /// ```
+    /// # use raytracer::materials::Metal;
+    /// # use raytracer::vec3::Vec3;
+    /// assert!(Metal::try_new(Vec3(0.8, 0.7, 0.9), 0.1).is_ok());
+    /// assert!(Metal::try_new(Vec3(0.8, 0.7, 0.9), -0.1).is_err());
+    /// assert!(Metal::try_new(Vec3(0.8, 1.7, 0.9), 0.1).is_err());
+    /// ```
+    pub fn try_new(attenuation: Vec3, fuzzy: f32) -> Result<Metal, MaterialError> {
+        if !(0. ..=1.).contains(&fuzzy) {
+            return Err(MaterialError::InvalidFuzzyness { fuzzy });
         }
+        Ok(Metal::new(check_attenuation(attenuation)?, fuzzy))
     }
 
     /// Extract the attenuation information from the Metal material.
     ///
+    /// A metal created by `with_texture` has no single attenuation, and
+    /// returns black. Its colors are given by `attenuation_texture`.
+    ///
     /// ```
     /// # use raytracer::materials::Metal;
     /// # use raytracer::vec3::Vec3;
@@ -170,7 +930,22 @@ impl Metal {
         &self.attenuation
     }
 
-    /// Extract the fuzzy parameter from the Metal material.
+    /// Access the texture giving the attenuation of the Metal material.
+    ///
+    /// ```
+    /// # use raytracer::materials::Metal;
+    /// # use raytracer::vec3::Vec3;
+    /// let attenuation = Vec3(0.8, 0.7, 0.9);
+    /// let metal = Metal::new(attenuation, 0.1);
+    /// let p = Vec3(0., 0., 0.);
+    /// assert_eq!(metal.attenuation_texture().value(0., 0., &p), attenuation);
+    /// ```
+    pub fn attenuation_texture(&self) -> &dyn Texture {
+        self.attenuation_texture.as_ref()
+    }
+
+    /// Extract the constant fuzzy parameter from the Metal material, which
+    /// is not used if a fuzzyness texture is set.
     ///
     /// ```
     /// # use raytracer::materials::Metal;
@@ -184,88 +959,2958 @@ impl Metal {
     pub fn fuzzy(&self) -> f32 {
         self.fuzzy
     }
-}
 
-impl Material for Metal {
-    fn scatter(&self, ray: &Ray, hit: &HitRecord) -> Option<(Ray, Vec3)> {
-        let reflected = reflect(&unit_vector(ray.direction()), &hit.normal);
-        let scattered = Ray::new(
-            hit.point_at_parameter,
-            reflected + self.fuzzy * random_in_unit_sphere(),
-        );
-        if dot(&scattered.direction(), &hit.normal) > 0. {
-            Some((scattered, self.attenuation))
-        } else {
-            None
-        }
+    /// Access the texture giving the fuzzyness, if any.
+    pub fn fuzzy_texture(&self) -> Option<&dyn Texture> {
+        self.fuzzy_texture.as_deref()
     }
-}
-
-/// A Dielectric (transparent) material.
-///
-/// A dielectric material is characterized by its refractive index.
-#[derive(Debug, Default)]
-pub struct Dielectric {
-    ref_idx: f32,
-}
+
+    /// Return a warning if the attenuation is outside the 0–1 color range.
+    ///
+    /// ```
+    /// # use raytracer::materials::Metal;
+    /// # use raytracer::vec3::Vec3;
+    /// assert!(Metal::new(Vec3(0.8, 0.2, 0.2), 0.).warning().is_none());
+    /// assert!(Metal::new(Vec3(200., 50., 50.), 0.).warning().is_some());
+    /// ```
+    pub fn warning(&self) -> Option<String> {
+        self.attenuation_texture.warning()
+    }
+
+    // The fuzzyness at the hit point.
+    fn fuzzy_at(&self, hit: &HitRecord) -> f32 {
+        match &self.fuzzy_texture {
+            None => self.fuzzy,
+            Some(texture) => {
+                let value = texture.value_at_hit(hit);
+                let mean = (value.x() + value.y() + value.z()) / 3.;
+                if mean.is_nan() {
+                    0.
+                } else {
+                    mean.clamp(0., 1.)
+                }
+            }
+        }
+    }
+}
+
+impl Default for Metal {
+    fn default() -> Metal {
+        Metal::new(Vec3::default(), 0.)
+    }
+}
+
+// Textures need not be `Debug`, which is why only the attenuation and the
+// fuzzyness are printed.
+impl fmt::Debug for Metal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Metal")
+            .field("attenuation", &self.attenuation)
+            .field("fuzzy", &self.fuzzy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MaterialExt for Metal {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<(Ray, Vec3)> {
+        let reflected = reflect(&unit_vector(ray.direction()), &hit.normal);
+        let scattered = Ray::new_with_time(
+            hit.point_at_parameter,
+            reflected + self.fuzzy_at(hit) * random_in_unit_sphere(rng),
+            ray.time(),
+        );
+        if dot(scattered.direction(), &hit.normal) > 0. {
+            let attenuation = self.attenuation_texture.value_at_hit(hit);
+            Some((scattered, attenuation))
+        } else {
+            None
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    fn describe(&self) -> Option<MaterialDesc> {
+        let fuzz_texture = match &self.fuzzy_texture {
+            Some(texture) => Some(texture.describe()?),
+            None => None,
+        };
+        Some(MaterialDesc::Metal {
+            albedo: self.attenuation_texture.describe()?,
+            fuzz: self.fuzzy,
+            fuzz_texture,
+        })
+    }
+}
+
+/// A conductor, i.e. a metal whose reflectance follows the Fresnel equations.
+///
+/// A conductor is characterized by the complex refractive index `eta + i k`
+/// of each color component. Unlike the flat attenuation of `Metal`, its
+/// reflectance depends on the angle of incidence and goes to 1 at grazing
+/// angles. The `fuzzy` parameter randomizes the reflection as for `Metal`.
+#[derive(Debug, Clone, Copy)]
+pub struct Conductor {
+    eta: Vec3,
+    k: Vec3,
+    fuzzy: f32,
+}
+
+impl Conductor {
+    /// Create a conductor by specifying the real parts `eta` and the
+    /// imaginary parts `k` of the refractive indices of red, green and blue.
+    ///
+    /// `fuzzy` is forced to be between 0 and 1.
+    ///
+    /// ```
+    /// # use raytracer::materials::Conductor;
+    /// # use raytracer::vec3::Vec3;
+    /// let chromium = Conductor::new(Vec3(3.1, 3.2, 2.3), Vec3(3.3, 3.3, 3.1), 0.05);
+    /// assert_eq!(chromium.fuzzy(), 0.05);
+    /// ```
+    pub fn new(eta: Vec3, k: Vec3, fuzzy: f32) -> Conductor {
+        Conductor {
+            eta,
+            k,
+            fuzzy: fuzzy.clamp(0., 1.),
+        }
+    }
+
+    /// Polished gold.
+    pub fn gold() -> Conductor {
+        Conductor::new(Vec3(0.143, 0.374, 1.442), Vec3(3.983, 2.385, 1.603), 0.)
+    }
+
+    /// Polished silver.
+    pub fn silver() -> Conductor {
+        Conductor::new(Vec3(0.155, 0.117, 0.138), Vec3(4.828, 3.122, 2.147), 0.)
+    }
+
+    /// Polished copper.
+    pub fn copper() -> Conductor {
+        Conductor::new(Vec3(0.200, 0.924, 1.102), Vec3(3.912, 2.452, 2.142), 0.)
+    }
+
+    /// Polished aluminum.
+    pub fn aluminum() -> Conductor {
+        Conductor::new(Vec3(1.657, 0.880, 0.521), Vec3(9.224, 6.270, 4.837), 0.)
+    }
+
+    /// Set the fuzzyness of the reflection, which is forced to be between 0 and 1.
+    ///
+    /// ```
+    /// # use raytracer::materials::Conductor;
+    /// let brushed = Conductor::aluminum().with_fuzzy(0.3);
+    /// assert_eq!(brushed.fuzzy(), 0.3);
+    /// ```
+    pub fn with_fuzzy(mut self, fuzzy: f32) -> Conductor {
+        self.fuzzy = fuzzy.clamp(0., 1.);
+        self
+    }
+
+    /// Access the real parts of the refractive indices.
+    pub fn eta(&self) -> &Vec3 {
+        &self.eta
+    }
+
+    /// Access the imaginary parts of the refractive indices.
+    pub fn k(&self) -> &Vec3 {
+        &self.k
+    }
+
+    /// Extract the fuzzy parameter of the conductor.
+    pub fn fuzzy(&self) -> f32 {
+        self.fuzzy
+    }
+
+    /// Return the reflectance of the color components for light incident at
+    /// an angle whose cosine to the normal is `cos_theta`.
+    ///
+    /// ```
+    /// # use raytracer::materials::Conductor;
+    /// let gold = Conductor::gold();
+    /// let normal = gold.reflectance(1.);
+    /// // Gold reflects red much better than blue.
+    /// assert!(normal.r() > 0.9 && normal.b() < 0.5);
+    /// assert!(gold.reflectance(0.).b() > 0.999);
+    /// ```
+    pub fn reflectance(&self, cos_theta: f32) -> Vec3 {
+        let cos_theta = cos_theta.abs().min(1.);
+        Vec3(
+            fresnel_conductor(cos_theta, self.eta.x(), self.k.x()),
+            fresnel_conductor(cos_theta, self.eta.y(), self.k.y()),
+            fresnel_conductor(cos_theta, self.eta.z(), self.k.z()),
+        )
+    }
+}
+
+// The Fresnel reflectance of unpolarized light at the surface of a conductor
+// with the refractive index `eta + i k`, for an angle of incidence whose
+// cosine is `cos_theta`.
+fn fresnel_conductor(cos_theta: f32, eta: f32, k: f32) -> f32 {
+    let cos2 = cos_theta * cos_theta;
+    let sin2 = 1. - cos2;
+    let (eta2, k2) = (eta * eta, k * k);
+    let t0 = eta2 - k2 - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4. * eta2 * k2).sqrt();
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.).sqrt();
+    // The perpendicular polarization.
+    let t1 = a2_plus_b2 + cos2;
+    let t2 = 2. * cos_theta * a;
+    let rs = (t1 - t2) / (t1 + t2);
+    // The parallel polarization.
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+    0.5 * (rs + rp)
+}
+
+impl MaterialExt for Conductor {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<(Ray, Vec3)> {
+        let direction = unit_vector(ray.direction());
+        let reflected = reflect(&direction, &hit.normal);
+        let scattered = Ray::new_with_time(
+            hit.point_at_parameter,
+            reflected + self.fuzzy * random_in_unit_sphere(rng),
+            ray.time(),
+        );
+        if dot(scattered.direction(), &hit.normal) > 0. {
+            let cos_theta = -dot(&direction, &hit.normal);
+            Some((scattered, self.reflectance(cos_theta)))
+        } else {
+            None
+        }
+    }
+}
+
+/// A glossy material, mixing a diffuse with a Blinn–Phong-style specular lobe.
+///
+/// Every scattering picks the specular lobe with a probability given by the
+/// brightness of the specular color relative to the diffuse color, and the
+/// diffuse lobe otherwise. The specular lobe is spread around the mirror
+/// direction following a power of the cosine to it, such that larger
+/// `shininess` exponents give sharper highlights. Rays scattered below the
+/// surface are absorbed, as for `Metal`.
+#[derive(Debug, Clone, Copy)]
+pub struct Glossy {
+    diffuse: Vec3,
+    specular: Vec3,
+    shininess: f32,
+}
+
+impl Glossy {
+    /// Create a glossy material by specifying its diffuse and specular
+    /// colors and the `shininess` exponent, which is forced to be at least 0.
+    ///
+    /// ```
+    /// # use raytracer::materials::Glossy;
+    /// # use raytracer::vec3::Vec3;
+    /// let plastic = Glossy::new(Vec3(0.7, 0.1, 0.1), Vec3(0.3, 0.3, 0.3), 200.);
+    /// assert_eq!(plastic.shininess(), 200.);
+    /// ```
+    pub fn new(diffuse: Vec3, specular: Vec3, shininess: f32) -> Glossy {
+        Glossy {
+            diffuse,
+            specular,
+            shininess: shininess.max(0.),
+        }
+    }
+
+    /// Access the diffuse color.
+    pub fn diffuse(&self) -> &Vec3 {
+        &self.diffuse
+    }
+
+    /// Access the specular color.
+    pub fn specular(&self) -> &Vec3 {
+        &self.specular
+    }
+
+    /// Access the shininess exponent.
+    pub fn shininess(&self) -> f32 {
+        self.shininess
+    }
+
+    /// Return a warning if a color is outside the 0–1 range.
+    pub fn warning(&self) -> Option<String> {
+        validate_attenuation(&self.diffuse).or_else(|| validate_attenuation(&self.specular))
+    }
+
+    // The probability of scattering into the specular lobe.
+    fn specular_probability(&self) -> f32 {
+        let brightest = |c: &Vec3| c.x().max(c.y()).max(c.z());
+        let (specular, diffuse) = (brightest(&self.specular), brightest(&self.diffuse));
+        if specular + diffuse > 0. {
+            specular / (specular + diffuse)
+        } else {
+            0.
+        }
+    }
+}
+
+impl MaterialExt for Glossy {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<(Ray, Vec3)> {
+        let p = self.specular_probability();
+        if rng.gen::<f32>() >= p {
+            let scattered = diffuse_scatter(ray, hit, rng);
+            return Some((scattered, self.diffuse / (1. - p)));
+        }
+        // Invert the distribution of cos^n of the angle to the mirror direction.
+        let mirror = unit_vector(&reflect(ray.direction(), &hit.normal));
+        let cos_alpha = rng.gen::<f32>().powf(1. / (self.shininess + 1.));
+        let direction = around_axis(&mirror, cos_alpha, rng);
+        if dot(&direction, &hit.normal) > 0. {
+            let scattered = Ray::new_with_time(hit.point_at_parameter, direction, ray.time());
+            Some((scattered, self.specular / p))
+        } else {
+            None
+        }
+    }
+}
+
+/// A rough specular material, following the GGX microfacet model.
+///
+/// The surface is made of mirroring microfacets whose normals follow the GGX
+/// distribution, of width `alpha = roughness²`. Reflections are sampled from
+/// the microfacet normals visible from the incoming direction, and weighted
+/// by Schlick's approximation of the Fresnel reflectance, starting from the
+/// color `f0` at normal incidence, and by the height-correlated Smith
+/// shadowing of the reflected direction.
+///
+/// Light reflected more than once between the microfacets is not accounted
+/// for, which darkens very rough surfaces slightly.
+#[derive(Debug, Clone, Copy)]
+pub struct Microfacet {
+    f0: Vec3,
+    roughness: f32,
+}
+
+impl Microfacet {
+    /// Create a microfacet material by specifying its reflectance `f0` at
+    /// normal incidence and its `roughness`, which is forced to be between
+    /// 0 and 1.
+    ///
+    /// A roughness of 0 gives a perfect mirror.
+    ///
+    /// ```
+    /// # use raytracer::materials::Microfacet;
+    /// # use raytracer::vec3::Vec3;
+    /// let brushed_gold = Microfacet::new(Vec3(1., 0.78, 0.34), 0.4);
+    /// assert!((brushed_gold.alpha() - 0.16).abs() < 1e-6);
+    /// ```
+    pub fn new(f0: Vec3, roughness: f32) -> Microfacet {
+        Microfacet {
+            f0,
+            roughness: roughness.clamp(0., 1.),
+        }
+    }
+
+    /// Access the reflectance at normal incidence.
+    pub fn f0(&self) -> &Vec3 {
+        &self.f0
+    }
+
+    /// Access the roughness of the material.
+    pub fn roughness(&self) -> f32 {
+        self.roughness
+    }
+
+    /// The width of the GGX distribution of the microfacet normals.
+    pub fn alpha(&self) -> f32 {
+        self.roughness * self.roughness
+    }
+
+    // Schlick's approximation of the Fresnel reflectance for the cosine
+    // `cosine` between the incoming direction and the microfacet normal.
+    fn fresnel(&self, cosine: f32) -> Vec3 {
+        let weight = (1. - cosine.clamp(0., 1.)).powi(5);
+        self.f0 + weight * (Vec3(1., 1., 1.) - self.f0)
+    }
+
+    // The Smith auxiliary function for a direction whose cosine to the
+    // normal is `cosine`.
+    fn lambda(&self, cosine: f32) -> f32 {
+        // Grazing directions are kept off infinite tangents.
+        let cos2 = (cosine * cosine).max(1e-12);
+        let tan2 = (1. - cos2).max(0.) / cos2;
+        let alpha = self.alpha();
+        0.5 * ((1. + alpha * alpha * tan2).sqrt() - 1.)
+    }
+
+    // Sample a microfacet normal visible from the unit direction `view`, in
+    // the frame where the normal is the z-axis (Heitz 2018).
+    fn sample_visible_normal(&self, view: &Vec3, rng: &mut dyn RngCore) -> Vec3 {
+        let alpha = self.alpha();
+        // Stretch the view direction to a hemisphere configuration.
+        let vh = unit_vector(&Vec3(alpha * view.x(), alpha * view.y(), view.z()));
+        let length2 = vh.x() * vh.x() + vh.y() * vh.y();
+        let t1 = if length2 > 0. {
+            Vec3(-vh.y(), vh.x(), 0.) / length2.sqrt()
+        } else {
+            Vec3(1., 0., 0.)
+        };
+        let t2 = cross(&vh, &t1);
+        // Sample the projected area of the visible hemisphere.
+        let r = rng.gen::<f32>().sqrt();
+        let phi = 2. * PI * rng.gen::<f32>();
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1. + vh.z());
+        let p2 = (1. - s) * (1. - p1 * p1).max(0.).sqrt() + s * r * phi.sin();
+        let nh = p1 * t1 + p2 * t2 + (1. - p1 * p1 - p2 * p2).max(0.).sqrt() * vh;
+        // Unstretch the normal.
+        unit_vector(&Vec3(alpha * nh.x(), alpha * nh.y(), nh.z().max(0.)))
+    }
+}
+
+impl MaterialExt for Microfacet {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<(Ray, Vec3)> {
+        let view = -unit_vector(ray.direction());
+        // The normal on the side of the incoming ray.
+        let normal = unit_vector(&hit.normal);
+        let normal = if dot(&view, &normal) < 0. {
+            -normal
+        } else {
+            normal
+        };
+        let cos_view = dot(&view, &normal);
+        if self.roughness == 0. {
+            let reflected = reflect(&-view, &normal);
+            let scattered = Ray::new_with_time(hit.point_at_parameter, reflected, ray.time());
+            return Some((scattered, self.fresnel(cos_view)));
+        }
+
+        let (u, v) = tangents(&normal);
+        let local = Vec3(dot(&view, &u), dot(&view, &v), cos_view);
+        let m = self.sample_visible_normal(&local, rng);
+        let microfacet = m.x() * u + m.y() * v + m.z() * normal;
+        let reflected = reflect(&-view, &microfacet);
+        let cos_light = dot(&reflected, &normal);
+        if cos_light <= 0. {
+            return None;
+        }
+        // The ratio of the height-correlated masking-shadowing to the masking
+        // of the view, by which sampling the visible normals is weighted.
+        let (lambda_view, lambda_light) = (self.lambda(cos_view), self.lambda(cos_light));
+        let shadowing = (1. + lambda_view) / (1. + lambda_view + lambda_light);
+        let attenuation = shadowing * self.fresnel(dot(&view, &microfacet));
+        let scattered = Ray::new_with_time(hit.point_at_parameter, reflected, ray.time());
+        Some((scattered, attenuation))
+    }
+}
+
+/// A clear coat, i.e. a thin glossy dielectric layer over a base material.
+///
+/// Car paint, varnished wood and glazed ceramics reflect part of the light
+/// at their coat, following its Fresnel reflectance, and let the rest pass to
+/// the material below. Every scattering either reflects off the coat, with
+/// the probability of its Fresnel weight, or leaves the scattering to the
+/// base material and tints the result by the transmission of the coat. As the
+/// coat is picked exactly as often as it reflects, the light is split between
+/// the two layers and neither is reweighted.
+///
+/// The coat reflects like a `Metal`, whose fuzzyness is the roughness of the
+/// coat, and its weight scales its Fresnel reflectance, where a weight of 0
+/// leaves the base material bare.
+pub struct ClearCoat {
+    base: Arc<dyn MaterialExt>,
+    ior: f32,
+    roughness: f32,
+    weight: f32,
+    tint: Vec3,
+}
+
+impl ClearCoat {
+    /// Create a smooth clear coat of refractive index 1.5 over `base`.
+    ///
+    /// ```
+    /// # use raytracer::materials::{ClearCoat, Lambertian};
+    /// # use raytracer::vec3::Vec3;
+    /// # use std::sync::Arc;
+    /// let paint = ClearCoat::new(Arc::new(Lambertian::new(Vec3(0.7, 0.1, 0.1))));
+    /// assert_eq!(paint.ior(), 1.5);
+    /// assert_eq!(paint.roughness(), 0.);
+    /// // Light arriving head-on is reflected by the coat 4% of the time.
+    /// assert!((paint.reflectance(1.) - 0.04).abs() < 1e-6);
+    /// ```
+    pub fn new(base: Arc<dyn MaterialExt>) -> ClearCoat {
+        ClearCoat {
+            base,
+            ior: 1.5,
+            roughness: 0.,
+            weight: 1.,
+            tint: Vec3(1., 1., 1.),
+        }
+    }
+
+    /// Set the refractive index of the coat.
+    pub fn with_ior(mut self, ior: f32) -> Self {
+        self.ior = ior;
+        self
+    }
+
+    /// Set the roughness of the coat, which is forced to be between 0 and 1.
+    pub fn with_roughness(mut self, roughness: f32) -> Self {
+        self.roughness = roughness.clamp(0., 1.);
+        self
+    }
+
+    /// Set the weight of the coat, which is forced to be between 0 and 1.
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight.clamp(0., 1.);
+        self
+    }
+
+    /// Set the color by which the coat attenuates the light passing through
+    /// it to the base material.
+    pub fn with_tint(mut self, tint: Vec3) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    /// Access the base material.
+    pub fn base(&self) -> &dyn MaterialExt {
+        self.base.as_ref()
+    }
+
+    /// Access the refractive index of the coat.
+    pub fn ior(&self) -> f32 {
+        self.ior
+    }
+
+    /// Access the roughness of the coat.
+    pub fn roughness(&self) -> f32 {
+        self.roughness
+    }
+
+    /// Access the weight of the coat.
+    pub fn weight(&self) -> f32 {
+        self.weight
+    }
+
+    /// Access the tint of the light passing through the coat.
+    pub fn tint(&self) -> &Vec3 {
+        &self.tint
+    }
+
+    /// Return the probability that light arriving at the angle with the cosine
+    /// `cos_theta` to the normal is reflected by the coat.
+    pub fn reflectance(&self, cos_theta: f32) -> f32 {
+        self.weight * schlick(cos_theta.clamp(0., 1.), self.ior)
+    }
+
+    /// Return a warning if the tint is outside the 0–1 color range.
+    ///
+    /// ```
+    /// # use raytracer::materials::{ClearCoat, Lambertian};
+    /// # use raytracer::vec3::Vec3;
+    /// # use std::sync::Arc;
+    /// let base = Arc::new(Lambertian::new(Vec3(0.7, 0.1, 0.1)));
+    /// assert!(ClearCoat::new(base.clone()).warning().is_none());
+    /// assert!(ClearCoat::new(base).with_tint(Vec3(255., 240., 200.)).warning().is_some());
+    /// ```
+    pub fn warning(&self) -> Option<String> {
+        validate_attenuation(&self.tint)
+    }
+}
+
+impl fmt::Debug for ClearCoat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClearCoat")
+            .field("ior", &self.ior)
+            .field("roughness", &self.roughness)
+            .field("weight", &self.weight)
+            .field("tint", &self.tint)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MaterialExt for ClearCoat {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<(Ray, Vec3)> {
+        self.scatter_in(ray, hit, 1., rng)
+    }
+
+    fn refractive_index(&self) -> Option<f32> {
+        self.base.refractive_index()
+    }
+
+    fn absorption(&self) -> Vec3 {
+        self.base.absorption()
+    }
+
+    fn scatter_in(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        outside: f32,
+        rng: &mut dyn RngCore,
+    ) -> Option<(Ray, Vec3)> {
+        let direction = unit_vector(ray.direction());
+        // The normal on the side of the incoming ray.
+        let normal = unit_vector(&hit.normal);
+        let normal = if dot(&direction, &normal) > 0. {
+            -normal
+        } else {
+            normal
+        };
+        let probability = self.reflectance(-dot(&direction, &normal));
+        // Without a coat no random number is drawn, such that the base
+        // material scatters exactly as if it were bare.
+        if probability > 0. && rng.gen::<f32>() < probability {
+            let reflected = reflect(&direction, &normal);
+            let scattered = Ray::new_with_time(
+                hit.point_at_parameter,
+                reflected + self.roughness * random_in_unit_sphere(rng),
+                ray.time(),
+            );
+            return if dot(scattered.direction(), &normal) > 0. {
+                Some((scattered, Vec3(1., 1., 1.)))
+            } else {
+                None
+            };
+        }
+        self.base
+            .scatter_in(ray, hit, outside, rng)
+            .map(|(scattered, attenuation)| (scattered, self.tint * attenuation))
+    }
+
+    fn emitted(&self, hit: &HitRecord) -> Vec3 {
+        self.base.emitted(hit) * self.tint
+    }
+}
+
+/// A mix of two materials, e.g. a mostly diffuse surface with a few percent
+/// of mirror reflection.
+///
+/// The blend factor is the probability of scattering like the second
+/// material rather than the first, and is given by a texture for blends that
+/// vary over the surface, where the mean of the color components is used.
+/// Every scattering picks one of the two materials and returns its result
+/// unmodified, which mixes the materials by the blend on average. The light
+/// emitted by the materials is blended directly.
+pub struct MixMaterial {
+    first: Arc<dyn MaterialExt>,
+    second: Arc<dyn MaterialExt>,
+    factor: Arc<dyn Texture>,
+}
+
+impl MixMaterial {
+    /// Create a mix of the materials `first` and `second`, where `factor` is
+    /// the share of the second material, which is forced to be between 0 and
+    /// 1.
+    ///
+    /// ```
+    /// # use raytracer::materials::{Lambertian, Metal, MixMaterial};
+    /// # use raytracer::vec3::Vec3;
+    /// # use std::sync::Arc;
+    /// let red = Arc::new(Lambertian::new(Vec3(0.8, 0.1, 0.1)));
+    /// let mirror = Arc::new(Metal::new(Vec3(0.9, 0.9, 0.9), 0.));
+    /// let glazed = MixMaterial::new(red, mirror, 0.2);
+    /// ```
+    pub fn new(first: Arc<dyn MaterialExt>, second: Arc<dyn MaterialExt>, factor: f32) -> Self {
+        let factor = factor.clamp(0., 1.);
+        MixMaterial::with_texture(
+            first,
+            second,
+            Arc::new(SolidColor::new(Vec3(factor, factor, factor))),
+        )
+    }
+
+    /// Create a mix of the materials `first` and `second`, where the share of
+    /// the second material is given by the texture `factor`.
+    ///
+    /// The share is forced to be between 0 and 1 where the texture is
+    /// evaluated.
+    pub fn with_texture(
+        first: Arc<dyn MaterialExt>,
+        second: Arc<dyn MaterialExt>,
+        factor: Arc<dyn Texture>,
+    ) -> Self {
+        MixMaterial {
+            first,
+            second,
+            factor,
+        }
+    }
+
+    /// Access the first material.
+    pub fn first(&self) -> &dyn MaterialExt {
+        self.first.as_ref()
+    }
+
+    /// Access the second material.
+    pub fn second(&self) -> &dyn MaterialExt {
+        self.second.as_ref()
+    }
+
+    /// Access the texture of the blend factor.
+    pub fn factor(&self) -> &dyn Texture {
+        self.factor.as_ref()
+    }
+
+    // The share of the second material at the hit point.
+    fn blend(&self, hit: &HitRecord) -> f32 {
+        let factor = self.factor.value_at_hit(hit);
+        ((factor.x() + factor.y() + factor.z()) / 3.).clamp(0., 1.)
+    }
+}
+
+impl fmt::Debug for MixMaterial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MixMaterial").finish_non_exhaustive()
+    }
+}
+
+impl MaterialExt for MixMaterial {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<(Ray, Vec3)> {
+        let blend = self.blend(hit);
+        // Pure blends draw no random number, such that they scatter exactly
+        // like the material they select.
+        let second = if blend <= 0. {
+            false
+        } else if blend >= 1. {
+            true
+        } else {
+            rng.gen::<f32>() < blend
+        };
+        if second {
+            self.second.scatter(ray, hit, rng)
+        } else {
+            self.first.scatter(ray, hit, rng)
+        }
+    }
+
+    fn emitted(&self, hit: &HitRecord) -> Vec3 {
+        let blend = self.blend(hit);
+        (1. - blend) * self.first.emitted(hit) + blend * self.second.emitted(hit)
+    }
+}
+
+/// A material whose surface normal is perturbed by a normal map.
+///
+/// The colors of the map, loaded without sRGB conversion, e.g. through
+/// `ImageTexture::open_linear`, encode directions in the tangent space of
+/// the surface, where the red, green and blue components between 0 and 1
+/// are the coordinates from -1 to 1 along the tangent, the bitangent and the
+/// normal of the hit. The flat color `(0.5, 0.5, 1)` leaves the normal
+/// unchanged. Objects without tangents get an arbitrary tangent space around
+/// the normal.
+///
+/// The base material scatters as if the surface had the perturbed normal.
+/// Perturbed normals turned past the incoming ray are brought back to its
+/// side of the surface, such that bumps never let light through the surface.
+pub struct NormalMapped {
+    base: Arc<dyn MaterialExt>,
+    map: Arc<dyn Texture>,
+}
+
+impl NormalMapped {
+    /// Perturb the normal of the `base` material by the normal `map`.
+    ///
+    /// ```
+    /// # use raytracer::materials::{Lambertian, NormalMapped};
+    /// # use raytracer::textures::ImageTexture;
+    /// # use raytracer::vec3::Vec3;
+    /// # use std::sync::Arc;
+    /// let bumps = ImageTexture::open_linear("bricks_normal.png").unwrap_or_else(|_| {
+    ///     // Stand in for the map if it is missing.
+    ///     ImageTexture::magenta()
+    /// });
+    /// let bricks = NormalMapped::new(
+    ///     Arc::new(Lambertian::new(Vec3(0.6, 0.3, 0.2))),
+    ///     Arc::new(bumps),
+    /// );
+    /// ```
+    pub fn new(base: Arc<dyn MaterialExt>, map: Arc<dyn Texture>) -> NormalMapped {
+        NormalMapped { base, map }
+    }
+
+    /// Access the base material.
+    pub fn base(&self) -> &dyn MaterialExt {
+        self.base.as_ref()
+    }
+
+    /// Access the normal map.
+    pub fn map(&self) -> &dyn Texture {
+        self.map.as_ref()
+    }
+
+    // The normal of the hit of `ray` perturbed by the map.
+    fn perturbed_normal(&self, ray: &Ray, hit: &HitRecord) -> Vec3 {
+        let color = self.map.value_at_hit(hit);
+        let local = 2. * color - Vec3(1., 1., 1.);
+        if local.squared_length() < 1e-12 {
+            return hit.normal;
+        }
+        let local = unit_vector(&local);
+        let (tangent, bitangent) = if hit.tangent.squared_length() > 0. {
+            (hit.tangent, hit.bitangent)
+        } else {
+            tangents(&hit.normal)
+        };
+        // The frame is orthonormal, such that the normal stays a unit vector.
+        let normal = local.x() * tangent + local.y() * bitangent + local.z() * hit.normal;
+        let direction = unit_vector(ray.direction());
+        let side = dot(&hit.normal, &direction);
+        let along = dot(&normal, &direction);
+        if along * side > 0. || side == 0. {
+            return normal;
+        }
+        // Turn the normal perpendicular to the ray, tilted slightly towards
+        // the side of the unperturbed normal.
+        unit_vector(&(normal - along * direction + 1e-2 * side.signum() * direction))
+    }
+}
+
+impl fmt::Debug for NormalMapped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NormalMapped").finish_non_exhaustive()
+    }
+}
+
+impl MaterialExt for NormalMapped {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<(Ray, Vec3)> {
+        self.scatter_in(ray, hit, 1., rng)
+    }
+
+    fn refractive_index(&self) -> Option<f32> {
+        self.base.refractive_index()
+    }
+
+    fn absorption(&self) -> Vec3 {
+        self.base.absorption()
+    }
+
+    fn scatter_in(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        outside: f32,
+        rng: &mut dyn RngCore,
+    ) -> Option<(Ray, Vec3)> {
+        let bumped = HitRecord {
+            normal: self.perturbed_normal(ray, hit),
+            ..*hit
+        };
+        self.base.scatter_in(ray, &bumped, outside, rng)
+    }
+
+    fn emitted(&self, hit: &HitRecord) -> Vec3 {
+        self.base.emitted(hit)
+    }
+}
+
+/// How an alpha mask decides where a surface is transparent.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AlphaMode {
+    /// The surface is cut out where the alpha is below the threshold, which
+    /// gives the crisp edges of fences and leaves.
+    #[default]
+    Cutout,
+    /// Light passes through the surface with the probability of one minus
+    /// the alpha, which gives partially transparent surfaces on average.
+    Stochastic,
+}
+
+/// A material with an alpha mask, e.g. for fences and leaves modelled as
+/// textured quads.
+///
+/// The alpha is given by a texture, where the mean of the color components
+/// is used. Where the surface is transparent, rays pass through it: they
+/// continue in the same direction from the hit point, without attenuation.
+/// Elsewhere the base material scatters the light.
+///
+/// Passing through a surface counts as a scattering towards the maximum
+/// depth of the paths, such that many stacked layers of transparent texels,
+/// e.g. in dense foliage, can end paths early. Occlusion queries with
+/// `Hitable::intersect_any` do not look at materials and are blocked by
+/// transparent texels as well.
+pub struct AlphaMasked {
+    base: Arc<dyn MaterialExt>,
+    mask: Arc<dyn Texture>,
+    mode: AlphaMode,
+    threshold: f32,
+}
+
+impl AlphaMasked {
+    /// Cut out the `base` material where the alpha `mask` is below 0.5.
+    ///
+    /// ```
+    /// # use raytracer::materials::{AlphaMasked, AlphaMode, Lambertian};
+    /// # use raytracer::textures::ImageTexture;
+    /// # use raytracer::vec3::Vec3;
+    /// # use std::sync::Arc;
+    /// let leaf = Arc::new(Lambertian::new(Vec3(0.2, 0.5, 0.1)));
+    /// let mask = Arc::new(ImageTexture::open_linear("leaf_alpha.png").unwrap_or_else(|_| {
+    ///     // Stand in for the mask if it is missing.
+    ///     ImageTexture::magenta()
+    /// }));
+    /// let leaf = AlphaMasked::new(leaf, mask).with_threshold(0.3);
+    /// assert_eq!(leaf.mode(), AlphaMode::Cutout);
+    /// ```
+    pub fn new(base: Arc<dyn MaterialExt>, mask: Arc<dyn Texture>) -> AlphaMasked {
+        AlphaMasked {
+            base,
+            mask,
+            mode: AlphaMode::default(),
+            threshold: 0.5,
+        }
+    }
+
+    /// Set how the mask decides where the surface is transparent.
+    pub fn with_mode(mut self, mode: AlphaMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set the alpha below which a cut out surface is transparent.
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Access the base material.
+    pub fn base(&self) -> &dyn MaterialExt {
+        self.base.as_ref()
+    }
+
+    /// Access the alpha mask.
+    pub fn mask(&self) -> &dyn Texture {
+        self.mask.as_ref()
+    }
+
+    /// Access how the mask decides where the surface is transparent.
+    pub fn mode(&self) -> AlphaMode {
+        self.mode
+    }
+
+    /// Access the alpha below which a cut out surface is transparent.
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    // The opacity at the hit point, i.e. the probability that the base
+    // material scatters.
+    fn opacity(&self, hit: &HitRecord) -> f32 {
+        let alpha = self.mask.value_at_hit(hit);
+        let alpha = ((alpha.x() + alpha.y() + alpha.z()) / 3.).clamp(0., 1.);
+        match self.mode {
+            AlphaMode::Cutout if alpha < self.threshold => 0.,
+            AlphaMode::Cutout => 1.,
+            AlphaMode::Stochastic => alpha,
+        }
+    }
+}
+
+impl fmt::Debug for AlphaMasked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AlphaMasked")
+            .field("mode", &self.mode)
+            .field("threshold", &self.threshold)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MaterialExt for AlphaMasked {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<(Ray, Vec3)> {
+        let opacity = self.opacity(hit);
+        // Opaque and transparent points draw no random number.
+        let opaque = if opacity <= 0. {
+            false
+        } else if opacity >= 1. {
+            true
+        } else {
+            rng.gen::<f32>() < opacity
+        };
+        if opaque {
+            return self.base.scatter(ray, hit, rng);
+        }
+        let continued = Ray::new_with_time(hit.point_at_parameter, *ray.direction(), ray.time());
+        Some((continued, Vec3(1., 1., 1.)))
+    }
+
+    fn emitted(&self, hit: &HitRecord) -> Vec3 {
+        self.opacity(hit) * self.base.emitted(hit)
+    }
+}
+
+/// A shadow catcher, for compositing rendered objects onto photographs.
+///
+/// A ground plane made of a shadow catcher is invisible to the camera except
+/// for the shadows cast onto it: the renderer shows the background behind
+/// it, darkened where the light arriving at it is blocked by other objects.
+/// Every sample traces one diffuse ray from the hit point, which either
+/// leaves the scene or hits another shadow catcher, letting the background
+/// through, or is blocked. Transparent objects therefore cast shadows as
+/// dark as opaque ones. As the images have no alpha channel, the shadows are
+/// composited onto the background of the renderer.
+///
+/// Seen by rays other than camera rays, e.g. in reflections, or lighting
+/// the objects from below, the catcher is a diffuse surface of the given
+/// albedo, such that the objects look like they stand on the ground.
+#[derive(Debug, Clone, Copy)]
+pub struct ShadowCatcher {
+    albedo: Vec3,
+}
+
+impl ShadowCatcher {
+    /// Create a shadow catcher, which is a diffuse surface of the color
+    /// `albedo` to rays other than camera rays.
+    ///
+    /// ```
+    /// # use raytracer::materials::{MaterialExt, ShadowCatcher};
+    /// # use raytracer::vec3::Vec3;
+    /// let ground = ShadowCatcher::new(Vec3(0.4, 0.4, 0.35));
+    /// assert!(ground.is_shadow_catcher());
+    /// ```
+    pub fn new(albedo: Vec3) -> ShadowCatcher {
+        ShadowCatcher { albedo }
+    }
+
+    /// Access the albedo seen by rays other than camera rays.
+    pub fn albedo(&self) -> &Vec3 {
+        &self.albedo
+    }
+
+    /// Return a warning if the albedo is outside the 0–1 color range.
+    pub fn warning(&self) -> Option<String> {
+        validate_attenuation(&self.albedo)
+    }
+}
+
+impl MaterialExt for ShadowCatcher {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<(Ray, Vec3)> {
+        Some((diffuse_scatter(ray, hit, rng), self.albedo))
+    }
+
+    fn is_shadow_catcher(&self) -> bool {
+        true
+    }
+}
+
+/// How a `Dielectric` computes the probability of reflection.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Fresnel {
+    /// Schlick's approximation of the Fresnel equations, which is cheaper.
+    #[default]
+    Schlick,
+    /// The Fresnel equations for unpolarized light.
+    Exact,
+}
+
+/// A Dielectric (transparent) material.
+///
+/// A dielectric material is characterized by its refractive index. Tinted
+/// glass additionally absorbs light inside, following the Beer–Lambert law:
+/// the light traveling a distance `d` through the material is attenuated by
+/// `exp(-absorption * d)`. The renderer applies the absorption along every
+/// segment of a path inside the material, including those ending on objects
+/// nested inside it, see `MaterialExt::absorption`, such that `scatter` on
+/// its own does not absorb.
+///
+/// Rough dielectrics, e.g. frosted glass, perturb the reflected and
+/// refracted directions like a fuzzy `Metal`.
+///
+/// Dispersive dielectrics, e.g. prisms and gemstones, have a refractive index
+/// per color component. Every scattering then picks one of the components at
+/// random and only lets it pass, with three times its attenuation, which
+/// keeps the color right on average, at the cost of noise.
+///
+/// Dielectrics may be nested, e.g. an air bubble in glass or a glass inside
+/// water: the renderer tracks the media a path has entered and refracts at
+/// the ratio of the indices on both sides of a surface, see
+/// `MaterialExt::scatter_in`. Surfaces between media of the same index are
+/// invisible. The media are tracked with the index of the green component
+/// of dispersive dielectrics.
+///
+/// The probability of reflection follows Schlick's approximation by default,
+/// and the exact Fresnel equations with `with_fresnel`.
+#[derive(Debug, Default)]
+pub struct Dielectric {
+    ref_idx: f32,
+    absorption: Vec3,
+    roughness: f32,
+    // The refractive indices of red, green and blue if they differ.
+    dispersion: Option<Vec3>,
+    fresnel: Fresnel,
+}
 
 impl Dielectric {
     /// Create a new dielectric material by specifying its refractive index.
     ///
     /// ```
-    /// # use raytracer::materials::Dielectric;
-    /// let ref_idx = 1.5;
-    /// let dielectric = Dielectric::new(ref_idx);
+    /// # use raytracer::materials::Dielectric;
+    /// let ref_idx = 1.5;
+    /// let dielectric = Dielectric::new(ref_idx);
+    /// ```
+    pub fn new(ref_idx: f32) -> Dielectric {
+        Dielectric {
+            ref_idx,
+            absorption: Vec3(0., 0., 0.),
+            roughness: 0.,
+            dispersion: None,
+            fresnel: Fresnel::default(),
+        }
+    }
+
+    /// Create a new dielectric material like `new`, checking that the
+    /// refractive index is positive.
+    ///
+    /// ```
+    /// # use raytracer::materials::Dielectric;
+    /// assert!(Dielectric::try_new(1.5).is_ok());
+    /// assert!(Dielectric::try_new(0.).is_err());
+    /// ```
+    pub fn try_new(ref_idx: f32) -> Result<Dielectric, MaterialError> {
+        if ref_idx.is_nan() || ref_idx <= 0. {
+            return Err(MaterialError::InvalidRefractiveIndex { ref_idx });
+        }
+        Ok(Dielectric::new(ref_idx))
+    }
+
+    /// Set the absorption coefficients per unit of distance of the three
+    /// color components, which default to zero.
+    ///
+    /// ```
+    /// # use raytracer::materials::Dielectric;
+    /// # use raytracer::vec3::Vec3;
+    /// // A green glass, absorbing red and blue light.
+    /// let bottle = Dielectric::new(1.5).with_absorption(Vec3(0.8, 0.1, 0.6));
+    /// assert_eq!(bottle.absorption(), &Vec3(0.8, 0.1, 0.6));
+    /// ```
+    pub fn with_absorption(mut self, absorption: Vec3) -> Dielectric {
+        self.absorption = absorption;
+        self
+    }
+
+    /// Extract the refractive index of a dielectric material.
+    ///
+    /// ```
+    /// # use raytracer::materials::Dielectric;
+    /// let ref_idx = 1.5;
+    /// let dielectric = Dielectric::new(ref_idx);
+    /// assert_eq!(dielectric.ref_idx(), 1.5);
+    /// ```
+    pub fn ref_idx(&self) -> f32 {
+        self.ref_idx
+    }
+
+    /// Access the absorption coefficients of the dielectric material.
+    pub fn absorption(&self) -> &Vec3 {
+        &self.absorption
+    }
+
+    /// Set the roughness of the surface, which is forced to be between 0 and 1.
+    ///
+    /// A roughness of 0, the default, gives perfectly smooth glass.
+    ///
+    /// ```
+    /// # use raytracer::materials::Dielectric;
+    /// let frosted = Dielectric::new(1.5).with_roughness(0.3);
+    /// assert_eq!(frosted.roughness(), 0.3);
+    /// assert_eq!(Dielectric::new(1.5).with_roughness(2.).roughness(), 1.);
+    /// ```
+    pub fn with_roughness(mut self, roughness: f32) -> Dielectric {
+        self.roughness = roughness.clamp(0., 1.);
+        self
+    }
+
+    /// Access the roughness of the dielectric material.
+    pub fn roughness(&self) -> f32 {
+        self.roughness
+    }
+
+    /// Set the refractive indices of the red, green and blue components,
+    /// replacing the single refractive index by that of green.
+    ///
+    /// Equal indices give the same material as a single index.
+    ///
+    /// ```
+    /// # use raytracer::materials::Dielectric;
+    /// # use raytracer::vec3::Vec3;
+    /// let prism = Dielectric::new(1.5).with_dispersion(Vec3(1.51, 1.52, 1.54));
+    /// assert_eq!(prism.ref_idx(), 1.52);
+    /// assert_eq!(prism.ref_indices(), Vec3(1.51, 1.52, 1.54));
+    /// ```
+    pub fn with_dispersion(mut self, indices: Vec3) -> Dielectric {
+        self.ref_idx = indices.g();
+        self.dispersion = if indices.r() == indices.g() && indices.g() == indices.b() {
+            None
+        } else {
+            Some(indices)
+        };
+        self
+    }
+
+    /// Set the refractive indices of the color components from Cauchy's
+    /// equation `n = a + b / λ²`, with the wavelength `λ` in micrometers.
+    ///
+    /// The components are taken at the wavelengths 0.65 (red), 0.55 (green)
+    /// and 0.45 (blue).
+    ///
+    /// ```
+    /// # use raytracer::materials::Dielectric;
+    /// // Borosilicate crown glass.
+    /// let bk7 = Dielectric::new(1.5).with_cauchy(1.5046, 0.0042);
+    /// let indices = bk7.ref_indices();
+    /// assert!(indices.r() < indices.g() && indices.g() < indices.b());
+    /// ```
+    pub fn with_cauchy(self, a: f32, b: f32) -> Dielectric {
+        let index = |wavelength: f32| a + b / (wavelength * wavelength);
+        self.with_dispersion(Vec3(index(0.65), index(0.55), index(0.45)))
+    }
+
+    /// Access the refractive indices of the red, green and blue components.
+    pub fn ref_indices(&self) -> Vec3 {
+        self.dispersion
+            .unwrap_or(Vec3(self.ref_idx, self.ref_idx, self.ref_idx))
+    }
+
+    /// Set how the probability of reflection is computed.
+    ///
+    /// ```
+    /// # use raytracer::materials::{Dielectric, Fresnel};
+    /// let glass = Dielectric::new(1.5).with_fresnel(Fresnel::Exact);
+    /// assert_eq!(glass.fresnel(), Fresnel::Exact);
+    /// assert_eq!(Dielectric::new(1.5).fresnel(), Fresnel::Schlick);
+    /// ```
+    pub fn with_fresnel(mut self, fresnel: Fresnel) -> Dielectric {
+        self.fresnel = fresnel;
+        self
+    }
+
+    /// Access how the probability of reflection is computed.
+    pub fn fresnel(&self) -> Fresnel {
+        self.fresnel
+    }
+}
+
+impl MaterialExt for Dielectric {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<(Ray, Vec3)> {
+        self.scatter_in(ray, hit, 1., rng)
+    }
+
+    fn refractive_index(&self) -> Option<f32> {
+        Some(self.ref_idx)
+    }
+
+    fn absorption(&self) -> Vec3 {
+        self.absorption
+    }
+
+    // Only the refractive index is described, such that dielectrics with
+    // other parameters cannot be.
+    #[cfg(feature = "serde")]
+    fn describe(&self) -> Option<MaterialDesc> {
+        let plain = self.absorption == Vec3(0., 0., 0.)
+            && self.roughness == 0.
+            && self.dispersion.is_none()
+            && self.fresnel == Fresnel::Schlick;
+        if plain {
+            Some(MaterialDesc::Dielectric {
+                ref_idx: self.ref_idx,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn scatter_in(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        outside: f32,
+        rng: &mut dyn RngCore,
+    ) -> Option<(Ray, Vec3)> {
+        // The refractive index, and the weights of the color components
+        // which pass.
+        let (ref_idx, channel) = match self.dispersion {
+            None => (self.ref_idx, Vec3(1., 1., 1.)),
+            Some(indices) => match rng.gen_range(0..3) {
+                0 => (indices.r(), Vec3(3., 0., 0.)),
+                1 => (indices.g(), Vec3(0., 3., 0.)),
+                _ => (indices.b(), Vec3(0., 0., 3.)),
+            },
+        };
+        // The relative refractive index of the material to the medium
+        // outside of it.
+        let eta = ref_idx / outside;
+        let outward_normal = if dot(ray.direction(), &hit.normal) > 0. {
+            -hit.normal
+        } else {
+            hit.normal
+        };
+        let cos_incident = -dot(ray.direction(), &outward_normal) / ray.direction().length();
+        let ni_over_nt = if hit.front_face { 1.0 / eta } else { eta };
+
+        // The absorption inside is applied by the renderer, see
+        // `MaterialExt::absorption`.
+        let attenuation = channel;
+        // Without a change of the refractive index, there is no interface.
+        if eta == 1. {
+            return Some((
+                Ray::new_with_time(hit.point_at_parameter, *ray.direction(), ray.time()),
+                attenuation,
+            ));
+        }
+        let reflected = reflect(ray.direction(), &hit.normal);
+        let direction = match refract(ray.direction(), &outward_normal, ni_over_nt) {
+            None => reflected,
+            Some(refracted) => {
+                if rng.gen::<f32>() < self.reflectance(cos_incident, ni_over_nt) {
+                    reflected
+                } else {
+                    refracted
+                }
+            }
+        };
+        let direction = self.roughen(direction, &outward_normal, rng);
+        Some((
+            Ray::new_with_time(hit.point_at_parameter, direction, ray.time()),
+            attenuation,
+        ))
+    }
+}
+
+impl Dielectric {
+    // The probability of reflection of light arriving at the angle with the
+    // cosine `cos_incident` on the surface, from the medium whose refractive
+    // index relative to the one on the other side is `ni_over_nt`, which is 1
+    // beyond the critical angle of total internal reflection.
+    fn reflectance(&self, cos_incident: f32, ni_over_nt: f32) -> f32 {
+        let cos_incident = cos_incident.clamp(0., 1.);
+        let sin2_transmitted = ni_over_nt * ni_over_nt * (1. - cos_incident * cos_incident);
+        if sin2_transmitted >= 1. {
+            return 1.;
+        }
+        let cos_transmitted = (1. - sin2_transmitted).sqrt();
+        match self.fresnel {
+            // The approximation holds for the angle in the medium of the
+            // lower index, which is the transmitted one when leaving the
+            // denser medium.
+            Fresnel::Schlick if ni_over_nt > 1. => schlick(cos_transmitted, ni_over_nt),
+            Fresnel::Schlick => schlick(cos_incident, ni_over_nt),
+            Fresnel::Exact => {
+                let s = (ni_over_nt * cos_incident - cos_transmitted)
+                    / (ni_over_nt * cos_incident + cos_transmitted);
+                let p = (cos_incident - ni_over_nt * cos_transmitted)
+                    / (cos_incident + ni_over_nt * cos_transmitted);
+                0.5 * (s * s + p * p)
+            }
+        }
+    }
+
+    // Perturb the reflected or refracted `direction` by the roughness, as
+    // `Metal` does. A perturbed direction crossing to the other side of the
+    // surface, whose normal towards the incoming ray is `outward_normal`, is
+    // dropped for the unperturbed one, such that no ray tunnels through.
+    fn roughen(&self, direction: Vec3, outward_normal: &Vec3, rng: &mut dyn RngCore) -> Vec3 {
+        if self.roughness == 0. {
+            return direction;
+        }
+        let perturbed = unit_vector(&direction) + self.roughness * random_in_unit_sphere(rng);
+        let side = dot(&direction, outward_normal);
+        if dot(&perturbed, outward_normal) * side > 0. {
+            perturbed
+        } else {
+            direction
+        }
+    }
+}
+
+// The wavelengths in nanometers at which the red, green and blue components
+// are evaluated, the same as for `Dielectric::with_cauchy`.
+const WAVELENGTHS: [f32; 3] = [650., 550., 450.];
+
+// The reflectance of unpolarized light of the `wavelength` arriving at the
+// angle with the cosine `cos_theta` from a medium of refractive index `n1`
+// on a film of index `n2` and of the `thickness`, over a medium of index
+// `n3`, following the Airy summation of the waves reflected inside the film.
+fn thin_film_reflectance(
+    n1: f32,
+    n2: f32,
+    n3: f32,
+    cos_theta: f32,
+    thickness: f32,
+    wavelength: f32,
+) -> f32 {
+    let sin2_1 = 1. - cos_theta * cos_theta;
+    let sin2_2 = (n1 / n2).powi(2) * sin2_1;
+    let sin2_3 = (n1 / n3).powi(2) * sin2_1;
+    if sin2_2 >= 1. || sin2_3 >= 1. {
+        // Total internal reflection in or under the film.
+        return 1.;
+    }
+    let (cos_1, cos_2, cos_3) = (cos_theta, (1. - sin2_2).sqrt(), (1. - sin2_3).sqrt());
+    // The phase difference between consecutive waves leaving the film.
+    let phase = (4. * PI * n2 * thickness * cos_2 / wavelength).cos();
+    // The reflectance of one polarization from the amplitudes at the top and
+    // the bottom of the film.
+    let airy = |r12: f32, r23: f32| {
+        let cross = 2. * r12 * r23 * phase;
+        (r12 * r12 + r23 * r23 + cross) / (1. + r12 * r12 * r23 * r23 + cross)
+    };
+    let s = airy(
+        (n1 * cos_1 - n2 * cos_2) / (n1 * cos_1 + n2 * cos_2),
+        (n2 * cos_2 - n3 * cos_3) / (n2 * cos_2 + n3 * cos_3),
+    );
+    let p = airy(
+        (n2 * cos_1 - n1 * cos_2) / (n2 * cos_1 + n1 * cos_2),
+        (n3 * cos_2 - n2 * cos_3) / (n3 * cos_2 + n2 * cos_3),
+    );
+    0.5 * (s + p)
+}
+
+/// A thin film coating a material, like soap on a bubble or oil on water.
+///
+/// The light reflected at the top and at the bottom of a film of a thickness
+/// close to the wavelength of light interferes, and the reflectance of the
+/// film depends on the wavelength, which gives iridescent colors. The film
+/// modulates the scattering of the base material, e.g. a `Dielectric` or a
+/// `Metal`: reflected light is weighted by the ratio of the reflectance of
+/// the coated to the uncoated surface, and transmitted light by the ratio of
+/// the transmittances. The uncoated surface is a smooth interface from air
+/// to the substrate index, which should match the base material.
+///
+/// The thickness of the film can vary over the surface, given by a texture
+/// whose components are averaged and mapped from 0 and 1 to a minimal and a
+/// maximal thickness, such that bubbles can swirl.
+pub struct ThinFilm {
+    base: Arc<dyn MaterialExt>,
+    ior: f32,
+    substrate_ior: f32,
+    thickness: Arc<dyn Texture>,
+    range: (f32, f32),
+}
+
+impl ThinFilm {
+    /// Coat the `base` material with a film of the `thickness` in nanometers
+    /// and the refractive index `ior`, over a substrate of index 1.5.
+    ///
+    /// ```
+    /// # use raytracer::materials::{Dielectric, ThinFilm};
+    /// # use std::sync::Arc;
+    /// let bubble = ThinFilm::new(Arc::new(Dielectric::new(1.33)), 400., 1.33)
+    ///     .with_substrate_ior(1.33);
+    /// assert_eq!(bubble.thickness_range(), (400., 400.));
+    /// ```
+    pub fn new(base: Arc<dyn MaterialExt>, thickness: f32, ior: f32) -> ThinFilm {
+        ThinFilm {
+            base,
+            ior,
+            substrate_ior: 1.5,
+            thickness: Arc::new(SolidColor::new(Vec3(0., 0., 0.))),
+            range: (thickness, thickness),
+        }
+    }
+
+    /// Set the refractive index of the substrate under the film.
+    pub fn with_substrate_ior(mut self, substrate_ior: f32) -> Self {
+        self.substrate_ior = substrate_ior;
+        self
+    }
+
+    /// Vary the thickness of the film from `min` to `max` nanometers by the
+    /// `texture`, whose components are averaged.
+    pub fn with_thickness_texture(mut self, texture: Arc<dyn Texture>, min: f32, max: f32) -> Self {
+        self.thickness = texture;
+        self.range = (min, max);
+        self
+    }
+
+    /// Access the base material.
+    pub fn base(&self) -> &dyn MaterialExt {
+        self.base.as_ref()
+    }
+
+    /// Access the refractive index of the film.
+    pub fn ior(&self) -> f32 {
+        self.ior
+    }
+
+    /// Access the refractive index of the substrate under the film.
+    pub fn substrate_ior(&self) -> f32 {
+        self.substrate_ior
+    }
+
+    /// Access the minimal and the maximal thickness of the film in nanometers.
+    pub fn thickness_range(&self) -> (f32, f32) {
+        self.range
+    }
+
+    /// Return the reflectance of the red, green and blue components of light
+    /// arriving from outside at the angle with the cosine `cos_theta` on the
+    /// film of the `thickness` in nanometers.
+    ///
+    /// ```
+    /// # use raytracer::materials::{Metal, ThinFilm};
+    /// # use raytracer::vec3::Vec3;
+    /// # use std::sync::Arc;
+    /// let film = ThinFilm::new(Arc::new(Metal::new(Vec3(0.9, 0.9, 0.9), 0.)), 0., 1.38);
+    /// // Without thickness, the film leaves the reflectance of the substrate.
+    /// let bare = film.reflectance(1., 0.);
+    /// assert!((bare.g() - 0.04).abs() < 1e-6);
     /// ```
-    pub fn new(ref_idx: f32) -> Dielectric {
-        Dielectric { ref_idx }
+    pub fn reflectance(&self, cos_theta: f32, thickness: f32) -> Vec3 {
+        self.film_reflectance(1., self.substrate_ior, cos_theta, thickness)
     }
 
-    /// Extract the refractive index of a dielectric material.
+    // The reflectance of the film between the media of refractive indices
+    // `above` on the side of the incoming light and `below`.
+    fn film_reflectance(&self, above: f32, below: f32, cos_theta: f32, thickness: f32) -> Vec3 {
+        let cos_theta = cos_theta.clamp(0., 1.);
+        let [r, g, b] = WAVELENGTHS.map(|wavelength| {
+            thin_film_reflectance(above, self.ior, below, cos_theta, thickness, wavelength)
+        });
+        Vec3(r, g, b)
+    }
+}
+
+impl fmt::Debug for ThinFilm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThinFilm")
+            .field("ior", &self.ior)
+            .field("substrate_ior", &self.substrate_ior)
+            .field("range", &self.range)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MaterialExt for ThinFilm {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<(Ray, Vec3)> {
+        let (scattered, attenuation) = self.base.scatter(ray, hit, rng)?;
+        let direction = unit_vector(ray.direction());
+        let cos_in = dot(&direction, &hit.normal);
+        // Light arriving from inside the substrate sees the media reversed.
+        let (above, below) = if cos_in > 0. {
+            (self.substrate_ior, 1.)
+        } else {
+            (1., self.substrate_ior)
+        };
+        let cos_theta = cos_in.abs();
+        let (min, max) = self.range;
+        let value = self.thickness.value_at_hit(hit);
+        let thickness = min + (max - min) * (value.x() + value.y() + value.z()) / 3.;
+        let coated = self.film_reflectance(above, below, cos_theta, thickness);
+        let bare = self.film_reflectance(above, below, cos_theta, 0.);
+        let reflected = dot(scattered.direction(), &hit.normal) * cos_in < 0.;
+        // The ratio of the coated to the uncoated reflectance or transmittance,
+        // which is left out where the uncoated surface lets nothing through.
+        let ratio = |coated: f32, bare: f32| {
+            let (coated, bare) = if reflected {
+                (coated, bare)
+            } else {
+                (1. - coated, 1. - bare)
+            };
+            if bare > 0. {
+                coated / bare
+            } else {
+                1.
+            }
+        };
+        let weight = Vec3(
+            ratio(coated.r(), bare.r()),
+            ratio(coated.g(), bare.g()),
+            ratio(coated.b(), bare.b()),
+        );
+        Some((scattered, attenuation * weight))
+    }
+
+    fn emitted(&self, hit: &HitRecord) -> Vec3 {
+        self.base.emitted(hit)
+    }
+}
+
+/// An isotropic material, scattering light uniformly in all directions.
+///
+/// It is the phase function of participating media such as fog and smoke,
+/// see `ConstantMedium`. The albedo is given by a texture, which is sampled
+/// at the point of the scattering.
+pub struct Isotropic {
+    albedo: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    /// Create an isotropic material by specifying its albedo.
     ///
     /// ```
-    /// # use raytracer::materials::Dielectric;
-    /// let ref_idx = 1.5;
-    /// let dielectric = Dielectric::new(ref_idx);
-    /// assert_eq!(dielectric.ref_idx(), 1.5);
+    /// # use raytracer::materials::Isotropic;
+    /// # use raytracer::vec3::Vec3;
+    /// let fog = Isotropic::new(Vec3(0.9, 0.9, 0.9));
+    /// let p = Vec3(0., 0., 0.);
+    /// assert_eq!(fog.texture().value(0., 0., &p), Vec3(0.9, 0.9, 0.9));
     /// ```
-    pub fn ref_idx(&self) -> f32 {
-        self.ref_idx
+    pub fn new(albedo: Vec3) -> Isotropic {
+        Isotropic::with_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    /// Create an isotropic material whose albedo is given by a texture.
+    pub fn with_texture(albedo: Arc<dyn Texture>) -> Isotropic {
+        Isotropic { albedo }
+    }
+
+    /// Access the texture giving the albedo of the isotropic material.
+    pub fn texture(&self) -> &dyn Texture {
+        self.albedo.as_ref()
     }
 }
 
-impl Material for Dielectric {
-    fn scatter(&self, ray: &Ray, hit: &HitRecord) -> Option<(Ray, Vec3)> {
-        let reflected = reflect(&ray.direction(), &hit.normal);
-        let normal_dir = dot(&ray.direction(), &hit.normal);
-        let outward_normal = if normal_dir > 0. {
-            -hit.normal
+impl Default for Isotropic {
+    fn default() -> Isotropic {
+        Isotropic::new(Vec3::default())
+    }
+}
+
+// Textures need not be `Debug`, which is why only the type is printed.
+impl fmt::Debug for Isotropic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Isotropic").finish_non_exhaustive()
+    }
+}
+
+impl MaterialExt for Isotropic {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<(Ray, Vec3)> {
+        let scattered = Ray::new_with_time(
+            hit.point_at_parameter,
+            unit_vector(&random_in_unit_sphere(rng)),
+            ray.time(),
+        );
+        let attenuation = self.albedo.value_at_hit(hit);
+        Some((scattered, attenuation))
+    }
+}
+
+/// A material of participating media scattering light preferably forwards or
+/// backwards, following the Henyey–Greenstein phase function.
+///
+/// The asymmetry `g` is the mean cosine of the angle between the incoming
+/// and the scattered direction: positive values scatter forwards, e.g. in
+/// clouds and haze, negative values backwards, and 0 is isotropic.
+pub struct HenyeyGreenstein {
+    albedo: Arc<dyn Texture>,
+    g: f32,
+}
+
+impl HenyeyGreenstein {
+    /// Create a Henyey–Greenstein material by specifying its albedo and the
+    /// asymmetry `g`.
+    ///
+    /// ```
+    /// # use raytracer::materials::HenyeyGreenstein;
+    /// # use raytracer::vec3::Vec3;
+    /// let haze = HenyeyGreenstein::new(Vec3(0.9, 0.9, 0.9), 0.7);
+    /// assert_eq!(haze.g(), 0.7);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `g` is not strictly between -1 and 1.
+    pub fn new(albedo: Vec3, g: f32) -> HenyeyGreenstein {
+        HenyeyGreenstein::with_texture(Arc::new(SolidColor::new(albedo)), g)
+    }
+
+    /// Create a Henyey–Greenstein material whose albedo is given by a texture.
+    ///
+    /// # Panics
+    ///
+    /// If `g` is not strictly between -1 and 1.
+    pub fn with_texture(albedo: Arc<dyn Texture>, g: f32) -> HenyeyGreenstein {
+        assert!(
+            g > -1. && g < 1.,
+            "the asymmetry {} is not between -1 and 1",
+            g
+        );
+        HenyeyGreenstein { albedo, g }
+    }
+
+    /// Access the asymmetry of the phase function.
+    pub fn g(&self) -> f32 {
+        self.g
+    }
+
+    /// Access the texture giving the albedo of the material.
+    pub fn texture(&self) -> &dyn Texture {
+        self.albedo.as_ref()
+    }
+}
+
+// Textures need not be `Debug`, which is why only the asymmetry is printed.
+impl fmt::Debug for HenyeyGreenstein {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HenyeyGreenstein")
+            .field("g", &self.g)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MaterialExt for HenyeyGreenstein {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<(Ray, Vec3)> {
+        let g = self.g;
+        // Sample the cosine of the angle to the incoming direction by
+        // inverting the cumulative distribution of the phase function.
+        let xi = rng.gen::<f32>();
+        let cos_theta = if g.abs() < 1e-3 {
+            1. - 2. * xi
         } else {
-            hit.normal
+            let s = (1. - g * g) / (1. - g + 2. * g * xi);
+            ((1. + g * g - s * s) / (2. * g)).clamp(-1., 1.)
         };
-        let ni_over_nt = if normal_dir > 0. {
-            self.ref_idx
-        } else {
-            1.0 / self.ref_idx
+        let direction = around_axis(&unit_vector(ray.direction()), cos_theta, rng);
+        let scattered = Ray::new_with_time(hit.point_at_parameter, direction, ray.time());
+        let attenuation = self.albedo.value_at_hit(hit);
+        Some((scattered, attenuation))
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::objects::cuboid::Cuboid;
+    use crate::objects::rect::Rect;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::Hitable;
+    use crate::objects::HitableList;
+    use crate::render::background;
+    use crate::render::color;
+    use crate::render::render;
+    use crate::render::RenderSettings;
+    use rand::rngs::StdRng;
+
+    // Scatter `count` rays travelling along the z-axis off `material` and
+    // return the unit directions of the scattered rays.
+    fn scattered_directions(material: &dyn MaterialExt, count: usize) -> Vec<Vec3> {
+        let mut rng = StdRng::seed_from_u64(78);
+        let ray = Ray::new(Vec3(0., 0., -1.), Vec3(0., 0., 1.));
+        let hit = HitRecord {
+            parameter: 1.,
+            point_at_parameter: Vec3(0., 0., 0.),
+            normal: Vec3(0., 0., -1.),
+            u: 0.,
+            v: 0.,
+            tangent: Vec3(0., 0., 0.),
+            bitangent: Vec3(0., 0., 0.),
+            front_face: true,
+            material,
         };
-        let cosine = if normal_dir > 0. {
-            self.ref_idx() * dot(&ray.direction(), &hit.normal) / ray.direction().length()
-        } else {
-            -dot(&ray.direction(), &hit.normal) / ray.direction().length()
+        (0..count)
+            .map(|_| {
+                let (scattered, attenuation) = material.scatter(&ray, &hit, &mut rng).unwrap();
+                assert_eq!(attenuation, Vec3(0.5, 0.5, 0.5));
+                unit_vector(scattered.direction())
+            })
+            .collect()
+    }
+
+    // The chi-square statistic of directions against the uniform distribution
+    // on the sphere, in 32 buckets of equal area: 4 bands of z times 8
+    // sectors of the azimuth.
+    fn chi_square(directions: &[Vec3]) -> f32 {
+        let mut buckets = [0; 32];
+        for d in directions {
+            let band = (((d.z() + 1.) * 2.) as usize).min(3);
+            let sector = (((d.y().atan2(d.x()) + PI) / (2. * PI) * 8.) as usize).min(7);
+            buckets[band * 8 + sector] += 1;
+        }
+        let expected = directions.len() as f32 / 32.;
+        buckets
+            .iter()
+            .map(|&count| (count as f32 - expected).powi(2) / expected)
+            .sum()
+    }
+
+    #[test]
+    // Test that isotropic materials scatter uniformly over the sphere.
+    fn test_isotropic_uniform() {
+        // The 99.9th percentile of the chi-square distribution with 31
+        // degrees of freedom.
+        let critical = 61.1;
+        let isotropic = Isotropic::new(Vec3(0.5, 0.5, 0.5));
+        let chi = chi_square(&scattered_directions(&isotropic, 32000));
+        assert!(chi < critical, "chi-square {}", chi);
+        let symmetric = HenyeyGreenstein::new(Vec3(0.5, 0.5, 0.5), 0.);
+        let chi = chi_square(&scattered_directions(&symmetric, 32000));
+        assert!(chi < critical, "chi-square {}", chi);
+    }
+
+    #[test]
+    // Test that the Henyey-Greenstein asymmetry is the mean cosine of scattering.
+    fn test_henyey_greenstein_asymmetry() {
+        for &g in &[0.8, -0.5] {
+            let material = HenyeyGreenstein::new(Vec3(0.5, 0.5, 0.5), g);
+            let directions = scattered_directions(&material, 20000);
+            let mean_cos = directions.iter().map(|d| d.z()).sum::<f32>() / 20000.;
+            assert!(
+                (mean_cos - g).abs() < 0.02,
+                "mean cosine {} for g {}",
+                mean_cos,
+                g
+            );
+            assert!(directions.iter().all(|d| (d.length() - 1.).abs() < 1e-4));
+        }
+        let forward = HenyeyGreenstein::new(Vec3(0.5, 0.5, 0.5), 0.8);
+        let directions = scattered_directions(&forward, 20000);
+        let ahead = directions.iter().filter(|d| d.z() > 0.).count();
+        assert!(ahead > 18000, "{} of 20000 scattered forwards", ahead);
+        assert!(chi_square(&directions) > 1000.);
+    }
+
+    #[test]
+    #[should_panic(expected = "asymmetry")]
+    // Test that an asymmetry of 1, scattering only forwards, is rejected.
+    fn test_henyey_greenstein_invalid() {
+        HenyeyGreenstein::new(Vec3(0.5, 0.5, 0.5), 1.);
+    }
+
+    #[test]
+    // Test that the scattering density of a Lambertian integrates to 1 over
+    // the directions, that it scatters diffusely, and that other materials
+    // scatter specularly by default.
+    fn test_lambertian_scattering_pdf() {
+        let lambertian = Lambertian::new(Vec3(0.5, 0.5, 0.5));
+        let hit = HitRecord {
+            parameter: 1.,
+            point_at_parameter: Vec3(0., 0., 0.),
+            normal: unit_vector(&Vec3(0.2, 1., -0.4)),
+            u: 0.,
+            v: 0.,
+            tangent: Vec3(0., 0., 0.),
+            bitangent: Vec3(0., 0., 0.),
+            front_face: true,
+            material: &lambertian,
+        };
+        let ray = Ray::new(Vec3(0., 1., 0.), Vec3(0., -1., 0.));
+        let mut rng = StdRng::seed_from_u64(99);
+        // Uniformly distributed directions over the sphere.
+        let samples = 200_000;
+        let sum: f32 = (0..samples)
+            .map(|_| {
+                let direction = unit_vector(&random_in_unit_sphere(&mut rng));
+                let scattered = Ray::new(Vec3(0., 0., 0.), direction);
+                hit.material.scattering_pdf(&ray, &hit, &scattered)
+            })
+            .sum();
+        let integral = 4. * PI * sum / samples as f32;
+        assert!((integral - 1.).abs() < 0.01, "{}", integral);
+
+        match hit.material.scatter_with_pdf(&ray, &hit, &mut rng) {
+            Some(ScatterRecord::Diffuse { attenuation, pdf }) => {
+                assert_eq!(attenuation, Vec3(0.5, 0.5, 0.5));
+                let direction = pdf.generate(&mut rng);
+                let scattered = Ray::new(Vec3(0., 0., 0.), direction);
+                let density = hit.material.scattering_pdf(&ray, &hit, &scattered);
+                assert!((pdf.value(&direction) - density).abs() < 1e-6);
+            }
+            other => panic!("{:?}", other),
+        }
+        let metal = Metal::new(Vec3(0.5, 0.5, 0.5), 0.);
+        let hit = HitRecord {
+            material: &metal,
+            ..hit
         };
+        match hit.material.scatter_with_pdf(&ray, &hit, &mut rng) {
+            Some(ScatterRecord::Specular { ray, attenuation }) => {
+                assert_eq!(attenuation, Vec3(0.5, 0.5, 0.5));
+                assert!(dot(ray.direction(), &hit.normal) > 0.);
+            }
+            other => panic!("{:?}", other),
+        }
+    }
 
-        let mut rng = rand::thread_rng();
+    // The mean color seen through a glass slab of the given thickness along
+    // the x-axis.
+    fn through_slab(glass: Dielectric, thickness: f32) -> Vec3 {
+        let slab = Cuboid::new(Vec3(0., -5., -5.), Vec3(thickness, 5., 5.), Arc::new(glass));
+        let mut rng = StdRng::seed_from_u64(79);
+        let ray = Ray::new(Vec3(-1., 0., 0.), Vec3(1., 0., 0.));
+        let samples = 2000;
+        let sum = (0..samples).fold(Vec3(0., 0., 0.), |sum, _| {
+            sum + color(&ray, &slab, 0, &mut rng)
+        });
+        sum / samples as f32
+    }
 
-        let attenuation = Vec3(1., 1., 1.);
-        match refract(&ray.direction(), &outward_normal, ni_over_nt) {
-            None => Some((Ray::new(hit.point_at_parameter, reflected), attenuation)),
-            Some(refracted) => {
-                if rng.gen::<f32>() < schlick(cosine, self.ref_idx) {
-                    Some((Ray::new(hit.point_at_parameter, reflected), attenuation))
+    #[test]
+    // Test that a thick tinted glass slab transmits less light than a thin one.
+    fn test_dielectric_absorption() {
+        let tinted = || Dielectric::new(1.5).with_absorption(Vec3(0.6, 0.05, 0.6));
+        let clear = through_slab(Dielectric::new(1.5), 0.5);
+        let thin = through_slab(tinted(), 0.5);
+        let thick = through_slab(tinted(), 4.);
+        assert!(thin.x() < clear.x() && thick.x() < 0.5 * thin.x());
+        assert!(thick.z() < 0.5 * thin.z());
+        // Green is hardly absorbed.
+        assert!(thick.y() > 0.7 * clear.y());
+        assert!(thick.y() > 4. * thick.x());
+    }
+
+    #[test]
+    // Test that glass without absorption or dispersion renders exactly as before.
+    fn test_dielectric_zero_absorption() {
+        let scene =
+            |glass: Dielectric| Cuboid::new(Vec3(-1., -1., -1.), Vec3(1., 1., 1.), Arc::new(glass));
+        let camera = Camera::new(
+            Vec3(3., 2., 4.),
+            Vec3(0., 0., 0.),
+            Vec3(0., 1., 0.),
+            40.,
+            1.5,
+            0.,
+            5.,
+        );
+        let settings = RenderSettings {
+            width: 24,
+            height: 16,
+            samples: 4,
+            ..RenderSettings::default()
+        };
+        let plain = scene(Dielectric::new(1.5));
+        let zero = scene(Dielectric::new(1.5).with_absorption(Vec3(0., 0., 0.)));
+        let expected = render(&plain, &camera, &settings);
+        assert_eq!(render(&zero, &camera, &settings), expected);
+        let equal = scene(Dielectric::new(1.3).with_dispersion(Vec3(1.5, 1.5, 1.5)));
+        assert_eq!(render(&equal, &camera, &settings), expected);
+    }
+
+    #[test]
+    // Test that glass refracts at the ratio of its refractive index to that of
+    // the medium outside, and that vacuum outside gives the plain scattering.
+    fn test_dielectric_scatter_in() {
+        let glass = Dielectric::new(1.5);
+        let hit = HitRecord {
+            parameter: 1.,
+            point_at_parameter: Vec3(0., 0., 0.),
+            normal: Vec3(0., 1., 0.),
+            u: 0.,
+            v: 0.,
+            tangent: Vec3(0., 0., 0.),
+            bitangent: Vec3(0., 0., 0.),
+            front_face: true,
+            material: &glass,
+        };
+        let ray = Ray::new(Vec3(-1., 1., 0.), Vec3(1., -1., 0.));
+        let (mut first, mut second) = (StdRng::seed_from_u64(98), StdRng::seed_from_u64(98));
+        for _ in 0..100 {
+            let plain = hit.material.scatter(&ray, &hit, &mut first).unwrap();
+            let vacuum = hit
+                .material
+                .scatter_in(&ray, &hit, 1., &mut second)
+                .unwrap();
+            assert_eq!(plain.0.direction(), vacuum.0.direction());
+            assert_eq!(plain.1, vacuum.1);
+        }
+        // Glass in glass lets the ray pass unchanged.
+        let (scattered, attenuation) = hit
+            .material
+            .scatter_in(&ray, &hit, 1.5, &mut first)
+            .unwrap();
+        assert_eq!(scattered.direction(), ray.direction());
+        assert_eq!(attenuation, Vec3(1., 1., 1.));
+        // Glass in water follows Snell's law with the relative index.
+        let sin_incident = 0.5f32.sqrt();
+        let refracted = (0..100)
+            .map(|_| {
+                *hit.material
+                    .scatter_in(&ray, &hit, 1.33, &mut first)
+                    .unwrap()
+                    .0
+                    .direction()
+            })
+            .find(|direction| direction.y() < 0.)
+            .unwrap();
+        let sin_refracted = refracted.x() / refracted.length();
+        assert!((sin_refracted - 1.33 / 1.5 * sin_incident).abs() < 1e-5);
+    }
+
+    #[test]
+    // Test that the fraction of rays reflected by glass follows the Fresnel
+    // equations entering and leaving it, also around the critical angle, and
+    // that Schlick's approximation stays close to them.
+    fn test_dielectric_fresnel() {
+        let ref_idx = 1.5f32;
+        // The Fresnel reflectance at the angle `incident` in degrees from the
+        // side of the index `n1` onto the index `n2`.
+        let analytic = |incident: f32, n1: f32, n2: f32| {
+            let sin_transmitted = n1 / n2 * incident.to_radians().sin();
+            if sin_transmitted >= 1. {
+                return 1.;
+            }
+            let (cos_i, cos_t) = (incident.to_radians().cos(), sin_transmitted.asin().cos());
+            let s = (n1 * cos_i - n2 * cos_t) / (n1 * cos_i + n2 * cos_t);
+            let p = (n2 * cos_i - n1 * cos_t) / (n2 * cos_i + n1 * cos_t);
+            0.5 * (s * s + p * p)
+        };
+        // The fraction of rays reflected by `glass` at the angle `incident`,
+        // from outside or from inside of the glass below the plane y = 0.
+        let reflected = |glass: &Dielectric, incident: f32, inside: bool| {
+            let side = if inside { -1. } else { 1. };
+            let hit = HitRecord {
+                parameter: 1.,
+                point_at_parameter: Vec3(0., 0., 0.),
+                normal: Vec3(0., 1., 0.),
+                u: 0.,
+                v: 0.,
+                tangent: Vec3(0., 0., 0.),
+                bitangent: Vec3(0., 0., 0.),
+                front_face: !inside,
+                material: glass,
+            };
+            let (sin, cos) = incident.to_radians().sin_cos();
+            let ray = Ray::new(Vec3(-sin, side * cos, 0.), Vec3(sin, -side * cos, 0.));
+            let mut rng = StdRng::seed_from_u64(107);
+            let samples = 20_000;
+            let count = (0..samples)
+                .filter(|_| {
+                    let (scattered, _) = hit.material.scatter(&ray, &hit, &mut rng).unwrap();
+                    scattered.direction().y() * side > 0.
+                })
+                .count();
+            count as f32 / samples as f32
+        };
+        let critical = (1. / ref_idx).asin().to_degrees();
+        let exact = Dielectric::new(ref_idx).with_fresnel(Fresnel::Exact);
+        let schlick = Dielectric::new(ref_idx);
+        for &(incident, inside) in &[
+            (0., false),
+            (45., false),
+            (80., false),
+            (20., true),
+            (35., true),
+            (critical - 0.5, true),
+            (critical + 0.5, true),
+            (60., true),
+        ] {
+            let expected = if inside {
+                analytic(incident, ref_idx, 1.)
+            } else {
+                analytic(incident, 1., ref_idx)
+            };
+            let fraction = reflected(&exact, incident, inside);
+            assert!(
+                (fraction - expected).abs() < 0.015,
+                "{} instead of {} at {} degrees",
+                fraction,
+                expected,
+                incident
+            );
+            let approximated = reflected(&schlick, incident, inside);
+            assert!(
+                (approximated - expected).abs() < 0.05,
+                "{} instead of {} at {} degrees",
+                approximated,
+                expected,
+                incident
+            );
+        }
+        // Just below the critical angle, the reflectance rises steeply, but
+        // not all light is reflected.
+        let near = analytic(critical - 0.5, ref_idx, 1.);
+        assert!(near > 3. * analytic(35., ref_idx, 1.) && near < 1.);
+    }
+
+    #[test]
+    // Test that rough glass scatters on the correct side of its surface, and
+    // exactly like smooth glass without roughness.
+    fn test_dielectric_roughness() {
+        let smooth = Dielectric::new(1.5);
+        let unroughened = Dielectric::new(1.5).with_roughness(0.);
+        let frosted = Dielectric::new(1.5).with_roughness(0.8);
+        let normal = unit_vector(&Vec3(0., 1., 0.2));
+        // The direction scattered by `material` at a hit of `ray` at the origin.
+        let scatter = |material: &dyn MaterialExt, ray: &Ray, rng: &mut StdRng| {
+            let hit = HitRecord {
+                parameter: 1.,
+                point_at_parameter: Vec3(0., 0., 0.),
+                normal,
+                u: 0.,
+                v: 0.,
+                tangent: Vec3(0., 0., 0.),
+                bitangent: Vec3(0., 0., 0.),
+                front_face: dot(ray.direction(), &normal) < 0.,
+                material,
+            };
+            *material.scatter(ray, &hit, rng).unwrap().0.direction()
+        };
+        let (mut first, mut second) = (StdRng::seed_from_u64(80), StdRng::seed_from_u64(80));
+        let mut rng = StdRng::seed_from_u64(81);
+        let mut perturbed = 0;
+        for i in 0..2000 {
+            // Rays hitting the surface from outside and from inside.
+            let angle = i as f32 * 0.01;
+            let direction = Vec3(angle.cos(), (3. * angle).sin(), angle.sin());
+            let ray = Ray::new(-direction, direction);
+            assert_eq!(
+                scatter(&unroughened, &ray, &mut first),
+                scatter(&smooth, &ray, &mut second)
+            );
+
+            // With the same random choice between reflection and refraction,
+            // the rough ray stays on the side of the smooth one.
+            let reference = scatter(&smooth, &ray, &mut rng.clone());
+            let rough = scatter(&frosted, &ray, &mut rng);
+            assert_eq!(
+                dot(&rough, &normal).signum(),
+                dot(&reference, &normal).signum()
+            );
+            let change = unit_vector(&rough) - unit_vector(&reference);
+            perturbed += (change.length() > 1e-3) as usize;
+        }
+        assert!(perturbed > 1500, "{} of 2000 rays perturbed", perturbed);
+    }
+
+    #[test]
+    // Test that red and blue light leave a dispersive glass ball at different
+    // angles, and that picking the components keeps the color on average.
+    fn test_dielectric_dispersion() {
+        let glass = Dielectric::new(1.5).with_dispersion(Vec3(1.45, 1.5, 1.6));
+        let ball = Sphere::new(Vec3(0., 0., 0.), 1., Arc::new(glass));
+        let mut rng = StdRng::seed_from_u64(81);
+        let (mut red, mut blue) = (Vec::new(), Vec::new());
+        let mut total = Vec3(0., 0., 0.);
+        for _ in 0..3000 {
+            let mut ray = Ray::new(Vec3(-3., 0.5, 0.), Vec3(1., 0., 0.));
+            let mut throughput = Vec3(1., 1., 1.);
+            let mut scatterings = 0;
+            while let Some(hit) = ball.intersect(&ray, 0.001, f32::MAX) {
+                let (scattered, attenuation) = hit.material.scatter(&ray, &hit, &mut rng).unwrap();
+                if scatterings == 0 {
+                    total += attenuation;
+                }
+                throughput *= attenuation;
+                ray = scattered;
+                scatterings += 1;
+            }
+            // Rays refracted into and out of the ball by the same component.
+            if scatterings == 2 {
+                let direction = unit_vector(ray.direction());
+                if throughput == Vec3(9., 0., 0.) {
+                    red.push(direction);
+                } else if throughput == Vec3(0., 0., 9.) {
+                    blue.push(direction);
+                }
+            }
+        }
+        assert!(red.len() > 100 && blue.len() > 100);
+        assert!(red.iter().all(|d| *d == red[0]));
+        assert!(blue.iter().all(|d| *d == blue[0]));
+        // Blue is refracted more strongly, i.e. deflected further down.
+        let angle = dot(&red[0], &blue[0]).clamp(-1., 1.).acos().to_degrees();
+        assert!(angle > 2., "red and blue leave {} degrees apart", angle);
+        assert!(blue[0].y() < red[0].y());
+        let mean = total / 3000.;
+        for c in &[mean.r(), mean.g(), mean.b()] {
+            assert!((c - 1.).abs() < 0.1, "mean attenuation {:?}", mean);
+        }
+    }
+
+    #[test]
+    // Test the reflectance of conductors at normal incidence against
+    // ((eta - 1)² + k²) / ((eta + 1)² + k²).
+    fn test_conductor_normal_incidence() {
+        let presets = [
+            Conductor::gold(),
+            Conductor::silver(),
+            Conductor::copper(),
+            Conductor::aluminum(),
+        ];
+        let mut rng = StdRng::seed_from_u64(82);
+        for conductor in &presets {
+            let hit = HitRecord {
+                parameter: 1.,
+                point_at_parameter: Vec3(0., 0., 0.),
+                normal: Vec3(0., 1., 0.),
+                u: 0.,
+                v: 0.,
+                tangent: Vec3(0., 0., 0.),
+                bitangent: Vec3(0., 0., 0.),
+                front_face: true,
+                material: conductor,
+            };
+            let ray = Ray::new(Vec3(0., 1., 0.), Vec3(0., -1., 0.));
+            let (scattered, attenuation) = hit.material.scatter(&ray, &hit, &mut rng).unwrap();
+            assert_eq!(scattered.direction(), &Vec3(0., 1., 0.));
+            let (eta, k) = (conductor.eta(), conductor.k());
+            let expected =
+                |eta: f32, k: f32| ((eta - 1.).powi(2) + k * k) / ((eta + 1.).powi(2) + k * k);
+            let expected = Vec3(
+                expected(eta.x(), k.x()),
+                expected(eta.y(), k.y()),
+                expected(eta.z(), k.z()),
+            );
+            assert!(
+                (attenuation - expected).length() < 1e-5,
+                "{:?}",
+                attenuation
+            );
+        }
+        // Gold and copper are reddish, silver and aluminum almost grey.
+        let gold = Conductor::gold().reflectance(1.);
+        assert!(gold.r() > gold.g() && gold.g() > gold.b());
+        let silver = Conductor::silver().reflectance(1.);
+        assert!(silver.r() > 0.9 && silver.b() > 0.9);
+    }
+
+    #[test]
+    // Test that the reflectance of conductors goes to 1 at grazing angles.
+    fn test_conductor_grazing() {
+        for conductor in &[
+            Conductor::gold(),
+            Conductor::silver(),
+            Conductor::copper(),
+            Conductor::aluminum(),
+        ] {
+            let normal = conductor.reflectance(1.);
+            let grazing = conductor.reflectance(1e-3);
+            for &(normal, grazing) in &[
+                (normal.r(), grazing.r()),
+                (normal.g(), grazing.g()),
+                (normal.b(), grazing.b()),
+            ] {
+                assert!(grazing > 0.99 && grazing <= 1., "grazing {}", grazing);
+                assert!(grazing > normal);
+            }
+            assert_eq!(conductor.reflectance(0.), Vec3(1., 1., 1.));
+        }
+    }
+
+    // The mean attenuation of `material` for rays arriving at `angle` degrees
+    // from the normal of a hit at the origin.
+    fn mean_attenuation(material: &dyn MaterialExt, angle: f32, rng: &mut StdRng) -> Vec3 {
+        let hit = HitRecord {
+            parameter: 1.,
+            point_at_parameter: Vec3(0., 0., 0.),
+            normal: Vec3(0., 1., 0.),
+            u: 0.,
+            v: 0.,
+            tangent: Vec3(0., 0., 0.),
+            bitangent: Vec3(0., 0., 0.),
+            front_face: true,
+            material,
+        };
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let ray = Ray::new(Vec3(sin, cos, 0.), Vec3(-sin, -cos, 0.));
+        let samples = 4000;
+        let sum = (0..samples).fold(Vec3(0., 0., 0.), |sum, _| {
+            sum + material.scatter(&ray, &hit, rng).unwrap().1
+        });
+        sum / samples as f32
+    }
+
+    #[test]
+    // Test that an Oren-Nayar material without roughness is a Lambertian, and
+    // that roughness darkens the surface most when seen head-on.
+    fn test_oren_nayar() {
+        let albedo = Vec3(0.8, 0.6, 0.4);
+        let lambertian = Lambertian::new(albedo);
+        let smooth = OrenNayar::new(albedo, 0.);
+        let hit = |material| HitRecord {
+            parameter: 1.,
+            point_at_parameter: Vec3(0., 0., 0.),
+            normal: Vec3(0., 1., 0.),
+            u: 0.,
+            v: 0.,
+            tangent: Vec3(0., 0., 0.),
+            bitangent: Vec3(0., 0., 0.),
+            front_face: true,
+            material,
+        };
+        let (mut first, mut second) = (StdRng::seed_from_u64(83), StdRng::seed_from_u64(83));
+        for i in 0..100 {
+            let angle = i as f32 * 0.015;
+            let ray = Ray::new(Vec3(0., 0., 0.), Vec3(angle.sin(), -angle.cos(), 0.));
+            let hit_lambertian = hit(&lambertian);
+            let hit_smooth = hit(&smooth);
+            let (expected, expected_attenuation) = hit_lambertian
+                .material
+                .scatter(&ray, &hit_lambertian, &mut first)
+                .unwrap();
+            let (scattered, attenuation) = hit_smooth
+                .material
+                .scatter(&ray, &hit_smooth, &mut second)
+                .unwrap();
+            assert_eq!(scattered.direction(), expected.direction());
+            assert_eq!(attenuation, expected_attenuation);
+        }
+
+        let mut rng = StdRng::seed_from_u64(84);
+        let rough = OrenNayar::new(albedo, 1.);
+        let head_on = mean_attenuation(&rough, 0., &mut rng);
+        let grazing = mean_attenuation(&rough, 85., &mut rng);
+        assert!(grazing.r() < 0.95 * albedo.r(), "{:?}", grazing);
+        assert!(head_on.r() < grazing.r(), "{:?} {:?}", head_on, grazing);
+        // A moderate roughness darkens less.
+        let moderate = mean_attenuation(&OrenNayar::new(albedo, 0.3), 85., &mut rng);
+        assert!(moderate.r() > grazing.r() && moderate.r() < albedo.r());
+    }
+
+    #[test]
+    // Test that a black sheen scatters exactly like a Lambertian.
+    fn test_sheen_black() {
+        let albedo = Vec3(0.8, 0.6, 0.4);
+        let lambertian = Lambertian::new(albedo);
+        let matte = Sheen::new(albedo, Vec3(0., 0., 0.), 0.5);
+        let (mut first, mut second) = (StdRng::seed_from_u64(102), StdRng::seed_from_u64(102));
+        for &angle in &[0f32, 30., 60., 85.] {
+            let expected = mean_attenuation(&lambertian, angle, &mut first);
+            assert_eq!(mean_attenuation(&matte, angle, &mut second), expected);
+        }
+    }
+
+    #[test]
+    // Test that the reflectance of a sheen increases towards grazing angles,
+    // and that the sheen does not reflect more light than arrives.
+    fn test_sheen_grazing() {
+        for &roughness in &[0.2f32, 0.5, 1.] {
+            let velvet = Sheen::new(Vec3(0.2, 0.2, 0.2), Vec3(1., 1., 1.), roughness);
+            for i in 0..10 {
+                let cos_view = i as f32 / 10.;
+                let (grazing, steep) = (
+                    velvet.reflectance(cos_view),
+                    velvet.reflectance(cos_view + 0.1),
+                );
+                assert!(grazing >= steep && grazing <= 1., "{} {}", grazing, steep);
+            }
+            assert!(velvet.reflectance(0.) > velvet.reflectance(1.));
+        }
+
+        let velvet = Sheen::new(Vec3(0.2, 0.2, 0.2), Vec3(1., 1., 1.), 0.5);
+        let mut rng = StdRng::seed_from_u64(103);
+        let means: Vec<f32> = [0f32, 40., 65., 80., 88.]
+            .iter()
+            .map(|&angle| mean_attenuation(&velvet, angle, &mut rng).r())
+            .collect();
+        assert!(
+            means.windows(2).all(|pair| pair[0] < pair[1]),
+            "{:?}",
+            means
+        );
+        // The base reflects what the sheen does not.
+        for (&angle, mean) in [0f32, 88.].iter().zip(&[means[0], means[4]]) {
+            let expected = 0.2 + 0.8 * velvet.reflectance(angle.to_radians().cos());
+            assert!((mean - expected).abs() < 0.03, "{} {}", mean, expected);
+        }
+    }
+
+    #[test]
+    // Test that a very shiny glossy material reflects like a polished metal.
+    fn test_glossy_mirror() {
+        let mirror = Metal::new(Vec3(0.9, 0.9, 0.9), 0.);
+        let glossy = Glossy::new(Vec3(0., 0., 0.), Vec3(0.9, 0.9, 0.9), 10_000.);
+        let hit = |material| HitRecord {
+            parameter: 1.,
+            point_at_parameter: Vec3(0., 0., 0.),
+            normal: Vec3(0., 1., 0.),
+            u: 0.,
+            v: 0.,
+            tangent: Vec3(0., 0., 0.),
+            bitangent: Vec3(0., 0., 0.),
+            front_face: true,
+            material,
+        };
+        let (hit_mirror, hit_glossy) = (hit(&mirror), hit(&glossy));
+        let mut rng = StdRng::seed_from_u64(84);
+        for i in 0..500 {
+            let angle = (i as f32 * 0.17).rem_euclid(1.4);
+            let ray = Ray::new(Vec3(0., 0., 0.), Vec3(angle.sin(), -angle.cos(), 0.3));
+            let (expected, expected_attenuation) = hit_mirror
+                .material
+                .scatter(&ray, &hit_mirror, &mut rng)
+                .unwrap();
+            if let Some((scattered, attenuation)) =
+                hit_glossy.material.scatter(&ray, &hit_glossy, &mut rng)
+            {
+                let expected = unit_vector(expected.direction());
+                assert!(dot(&expected, scattered.direction()) > 0.999);
+                assert_eq!(attenuation, expected_attenuation);
+            }
+        }
+    }
+
+    #[test]
+    // Test that a glossy material scatters above the surface and keeps the
+    // colors of its lobes on average.
+    fn test_glossy_lobes() {
+        let (diffuse, specular) = (Vec3(0.6, 0.2, 0.2), Vec3(0.2, 0.2, 0.2));
+        let glossy = Glossy::new(diffuse, specular, 20.);
+        let mut rng = StdRng::seed_from_u64(85);
+        let hit = HitRecord {
+            parameter: 1.,
+            point_at_parameter: Vec3(0., 0., 0.),
+            normal: Vec3(0., 1., 0.),
+            u: 0.,
+            v: 0.,
+            tangent: Vec3(0., 0., 0.),
+            bitangent: Vec3(0., 0., 0.),
+            front_face: true,
+            material: &glossy,
+        };
+        let ray = Ray::new(Vec3(-1., 1., 0.), Vec3(1., -1., 0.));
+        let (mut sum, mut specular_rays) = (Vec3(0., 0., 0.), 0);
+        let samples = 20000;
+        for _ in 0..samples {
+            if let Some((scattered, attenuation)) = hit.material.scatter(&ray, &hit, &mut rng) {
+                assert!(scattered.direction().y() > 0.);
+                sum += attenuation;
+                specular_rays += (attenuation.g() == attenuation.r()) as usize;
+            }
+        }
+        // The specular lobe is picked with a probability of 0.2 / (0.2 + 0.6).
+        let fraction = specular_rays as f32 / samples as f32;
+        assert!((fraction - 0.25).abs() < 0.02, "{}", fraction);
+        // Few rays of the lobe fall below the surface at 45 degrees.
+        let mean = sum / samples as f32;
+        assert!((mean - (diffuse + specular)).length() < 0.05, "{:?}", mean);
+    }
+
+    // A material emitting white light and absorbing all light.
+    struct WhiteSky;
+
+    impl MaterialExt for WhiteSky {
+        fn scatter(&self, _: &Ray, _: &HitRecord, _: &mut dyn RngCore) -> Option<(Ray, Vec3)> {
+            None
+        }
+
+        fn emitted(&self, _hit: &HitRecord) -> Vec3 {
+            Vec3(1., 1., 1.)
+        }
+    }
+
+    // The mean color of a white microfacet sphere of the given roughness in
+    // a white furnace, and the brightest color of a ray, for rays at points
+    // spread uniformly over the disk of the sphere.
+    fn furnace(roughness: f32, rng: &mut StdRng) -> (f32, f32) {
+        let sphere = Sphere::new(
+            Vec3(0., 0., 0.),
+            1.,
+            Arc::new(Microfacet::new(Vec3(1., 1., 1.), roughness)),
+        );
+        let sky = Sphere::new(Vec3(0., 0., 0.), -10., Arc::new(WhiteSky));
+        let world = HitableList::new(vec![Box::new(sphere), Box::new(sky)]);
+        let samples = 20000;
+        let (mut sum, mut brightest) = (0., 0_f32);
+        for _ in 0..samples {
+            let (r, phi) = (rng.gen::<f32>().sqrt(), 2. * PI * rng.gen::<f32>());
+            let ray = Ray::new(Vec3(r * phi.cos(), r * phi.sin(), 5.), Vec3(0., 0., -1.));
+            let col = color(&ray, &world, 0, rng);
+            assert!(col.x() == col.y() && col.y() == col.z());
+            sum += col.x();
+            brightest = brightest.max(col.x());
+        }
+        (sum / samples as f32, brightest)
+    }
+
+    #[test]
+    // Test that a white microfacet sphere in a white furnace stays white,
+    // i.e. that the material neither gains nor loses much energy.
+    fn test_microfacet_furnace() {
+        let mut rng = StdRng::seed_from_u64(85);
+        for &roughness in &[0., 0.1, 0.2, 0.3] {
+            let (mean, brightest) = furnace(roughness, &mut rng);
+            assert!(mean > 0.97, "mean {} for roughness {}", mean, roughness);
+            assert!(brightest <= 1., "{} for roughness {}", brightest, roughness);
+        }
+        // Very rough surfaces lose the light reflected more than once between
+        // the microfacets, but never gain energy.
+        let (mean, brightest) = furnace(1., &mut rng);
+        assert!(mean > 0.3 && brightest <= 1., "{} {}", mean, brightest);
+    }
+
+    // The scene of a ball made of `material` on a grey ground.
+    fn ball(material: Arc<dyn MaterialExt>) -> HitableList {
+        let ground: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        HitableList::new(vec![
+            Box::new(Sphere::new(Vec3(0., -100.5, -1.), 100., ground)) as Box<dyn Hitable>,
+            Box::new(Sphere::new(Vec3(0., 0., -1.), 0.5, material)),
+        ])
+    }
+
+    #[test]
+    // Test that a clear coat without weight leaves a seeded render of its base
+    // material unchanged.
+    fn test_clear_coat_zero_weight() {
+        let camera = Camera::new(
+            Vec3(0., 0.5, 1.),
+            Vec3(0., 0., -1.),
+            Vec3(0., 1., 0.),
+            50.,
+            1.5,
+            0.,
+            2.,
+        );
+        let settings = RenderSettings {
+            width: 24,
+            height: 16,
+            samples: 4,
+            ..RenderSettings::default()
+        };
+        let base: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.7, 0.1, 0.1)));
+        let bare = ball(base.clone());
+        let uncoated = ball(Arc::new(ClearCoat::new(base.clone()).with_weight(0.)));
+        let expected = render(&bare, &camera, &settings);
+        assert_eq!(render(&uncoated, &camera, &settings), expected);
+        let coated = ball(Arc::new(ClearCoat::new(base)));
+        assert_ne!(render(&coated, &camera, &settings), expected);
+    }
+
+    #[test]
+    // Test that a clear coat reflects with its Fresnel weight and passes the
+    // rest of the light on to the base material.
+    fn test_clear_coat_split() {
+        let mut rng = StdRng::seed_from_u64(87);
+        let black = ClearCoat::new(Arc::new(Lambertian::new(Vec3(0., 0., 0.))));
+        let grey = ClearCoat::new(Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))));
+        let half = ClearCoat::new(Arc::new(Lambertian::new(Vec3(0., 0., 0.)))).with_weight(0.5);
+        for &angle in &[0f32, 60., 80.] {
+            let reflectance = black.reflectance(angle.to_radians().cos());
+            let mean = mean_attenuation(&black, angle, &mut rng).x();
+            assert!(
+                (mean - reflectance).abs() < 0.03,
+                "{} {}",
+                mean,
+                reflectance
+            );
+            let mean = mean_attenuation(&half, angle, &mut rng).x();
+            assert!(
+                (mean - 0.5 * reflectance).abs() < 0.03,
+                "{} {}",
+                mean,
+                reflectance
+            );
+            let expected = reflectance + 0.5 * (1. - reflectance);
+            let mean = mean_attenuation(&grey, angle, &mut rng).x();
+            assert!((mean - expected).abs() < 0.03, "{} {}", mean, expected);
+        }
+        // The coat reflects more light at grazing angles.
+        assert!(black.reflectance(0.2) > 4. * black.reflectance(1.));
+    }
+
+    #[test]
+    // Test that pure blends render exactly like the selected material.
+    fn test_mix_material_pure() {
+        let camera = Camera::new(
+            Vec3(0., 0.5, 1.),
+            Vec3(0., 0., -1.),
+            Vec3(0., 1., 0.),
+            50.,
+            1.5,
+            0.,
+            2.,
+        );
+        let settings = RenderSettings {
+            width: 24,
+            height: 16,
+            samples: 4,
+            ..RenderSettings::default()
+        };
+        let red: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.8, 0.1, 0.1)));
+        let mirror: Arc<dyn MaterialExt> = Arc::new(Metal::new(Vec3(0.9, 0.9, 0.9), 0.2));
+        for &(factor, expected) in &[(0., &red), (-1., &red), (1., &mirror), (3., &mirror)] {
+            let mix = MixMaterial::new(red.clone(), mirror.clone(), factor);
+            assert_eq!(
+                render(&ball(Arc::new(mix)), &camera, &settings),
+                render(&ball(expected.clone()), &camera, &settings),
+                "factor {}",
+                factor
+            );
+        }
+    }
+
+    #[test]
+    // Test that an even blend converges to the average of the two materials.
+    fn test_mix_material_average() {
+        let mut rng = StdRng::seed_from_u64(88);
+        let red: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.8, 0.1, 0.1)));
+        let mirror: Arc<dyn MaterialExt> = Arc::new(Metal::new(Vec3(0.9, 0.9, 0.9), 0.2));
+        let mix = ball(Arc::new(MixMaterial::new(red.clone(), mirror.clone(), 0.5)));
+        let (red, mirror) = (ball(red), ball(mirror));
+        let samples = 4000;
+        for &target in &[
+            Vec3(0., 0., -0.5),
+            Vec3(0.3, 0.2, -0.7),
+            Vec3(-0.4, -0.2, -0.8),
+        ] {
+            let ray = Ray::new(Vec3(0., 0.5, 1.), target - Vec3(0., 0.5, 1.));
+            let mut mean = |world: &HitableList| {
+                (0..samples).fold(Vec3(0., 0., 0.), |sum, _| {
+                    sum + color(&ray, world, 0, &mut rng)
+                }) / samples as f32
+            };
+            let expected = 0.5 * (mean(&red) + mean(&mirror));
+            let difference = mean(&mix) - expected;
+            assert!(
+                difference.length() < 0.03,
+                "{:?} for {:?}",
+                difference,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    // Test that a flat normal map renders exactly like the unmapped material,
+    // with and without tangents on the objects.
+    fn test_normal_map_flat() {
+        let camera = Camera::new(
+            Vec3(0.3, 0.6, 1.),
+            Vec3(0., 0., -1.),
+            Vec3(0., 1., 0.),
+            50.,
+            1.5,
+            0.,
+            2.,
+        );
+        let settings = RenderSettings {
+            width: 24,
+            height: 16,
+            samples: 4,
+            ..RenderSettings::default()
+        };
+        let flat: Arc<dyn Texture> = Arc::new(SolidColor::new(Vec3(0.5, 0.5, 1.)));
+        let bases: [Arc<dyn MaterialExt>; 2] = [
+            Arc::new(Lambertian::new(Vec3(0.7, 0.1, 0.1))),
+            Arc::new(Metal::new(Vec3(0.9, 0.9, 0.9), 0.3)),
+        ];
+        for base in bases.iter() {
+            let mapped: Arc<dyn MaterialExt> =
+                Arc::new(NormalMapped::new(base.clone(), flat.clone()));
+            assert_eq!(
+                render(&ball(mapped.clone()), &camera, &settings),
+                render(&ball(base.clone()), &camera, &settings)
+            );
+            let cube =
+                |material| Cuboid::new(Vec3(-0.4, -0.4, -1.4), Vec3(0.4, 0.4, -0.6), material);
+            assert_eq!(
+                render(&cube(mapped), &camera, &settings),
+                render(&cube(base.clone()), &camera, &settings)
+            );
+        }
+    }
+
+    // A material reporting the normal it scatters with as the attenuation.
+    struct NormalProbe;
+
+    impl Material for NormalProbe {
+        fn scatter(&self, ray: &Ray, hit: &HitRecord) -> Option<(Ray, Vec3)> {
+            Some((Ray::new(*ray.origin(), *ray.direction()), hit.normal))
+        }
+    }
+
+    #[test]
+    // Test that a normal map tilts the normal in the tangent space of a sphere,
+    // and never past the incoming ray.
+    fn test_normal_map_tilt() {
+        let mut rng = StdRng::seed_from_u64(89);
+        // The normal at the hit of `ray` with the unit sphere through the map
+        // of the single `color`.
+        let mut normal = |color: Vec3, ray: &Ray| {
+            let map = Arc::new(SolidColor::new(color));
+            let sphere = Sphere::new(
+                Vec3(0., 0., 0.),
+                1.,
+                Arc::new(NormalMapped::new(Arc::new(NormalProbe), map)),
+            );
+            let hit = sphere.intersect(ray, 0.001, f32::MAX).unwrap();
+            assert!((hit.point_at_parameter - Vec3(1., 0., 0.)).length() < 1e-5);
+            hit.material.scatter(ray, &hit, &mut rng).unwrap().1
+        };
+        // At (1, 0, 0) the tangent is -z and the bitangent is y.
+        let head_on = Ray::new(Vec3(3., 0., 0.), Vec3(-1., 0., 0.));
+        let (sin, cos) = 30f32.to_radians().sin_cos();
+        let tilted = normal(Vec3(0.5 + 0.5 * sin, 0.5, 0.5 + 0.5 * cos), &head_on);
+        assert!(
+            (tilted - Vec3(cos, 0., -sin)).length() < 1e-5,
+            "{:?}",
+            tilted
+        );
+        let tilted = normal(Vec3(0.5, 0.5 + 0.5 * sin, 0.5 + 0.5 * cos), &head_on);
+        assert!(
+            (tilted - Vec3(cos, sin, 0.)).length() < 1e-5,
+            "{:?}",
+            tilted
+        );
+
+        // Normals turned along or past the ray are brought back to its side.
+        let oblique = Ray::new(Vec3(4., 0., 3.), Vec3(-1., 0., -1.));
+        for (color, ray) in [
+            (Vec3(0., 0.5, 0.5), &head_on),
+            (Vec3(1., 0.5, 0.5), &oblique),
+        ] {
+            let clamped = normal(color, ray);
+            let along = dot(&clamped, &unit_vector(ray.direction()));
+            assert!(along < 0. && along > -0.05, "{:?}", clamped);
+            assert!((clamped.length() - 1.).abs() < 1e-5);
+        }
+    }
+
+    // A checkerboard of 4 by 4 white and black texels, e.g. opaque and
+    // transparent ones.
+    struct Checker;
+
+    impl Texture for Checker {
+        fn value(&self, u: f32, v: f32, _p: &Vec3) -> Vec3 {
+            if ((4. * u).floor() + (4. * v).floor()) as i32 % 2 == 0 {
+                Vec3(1., 1., 1.)
+            } else {
+                Vec3(0., 0., 0.)
+            }
+        }
+    }
+
+    #[test]
+    // Test that rays through the transparent texels of a cut out quad reach
+    // the background, while the opaque texels are black.
+    fn test_alpha_masked_cutout() {
+        let black = Arc::new(Lambertian::new(Vec3(0., 0., 0.)));
+        let fence = AlphaMasked::new(black, Arc::new(Checker));
+        let quad = Rect::new(
+            Vec3(-1., -1., -1.),
+            Vec3(2., 0., 0.),
+            Vec3(0., 2., 0.),
+            Arc::new(fence),
+        );
+        let mut rng = StdRng::seed_from_u64(91);
+        for i in 0..4 {
+            for j in 0..4 {
+                let target = Vec3(-0.75 + 0.5 * i as f32, -0.75 + 0.5 * j as f32, -1.);
+                let ray = Ray::new(Vec3(0.1, 0.2, 1.), target - Vec3(0.1, 0.2, 1.));
+                let col = color(&ray, &quad, 0, &mut rng);
+                if (i + j) % 2 == 0 {
+                    assert_eq!(col, Vec3(0., 0., 0.), "texel {} {}", i, j);
                 } else {
-                    Some((Ray::new(hit.point_at_parameter, refracted), attenuation))
+                    assert_eq!(col, background(&ray), "texel {} {}", i, j);
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Test that a stochastic mask lets light through in proportion to the
+    // transparency.
+    fn test_alpha_masked_stochastic() {
+        let black = Arc::new(Lambertian::new(Vec3(0., 0., 0.)));
+        let mask = Arc::new(SolidColor::new(Vec3(0.25, 0.25, 0.25)));
+        let veil = AlphaMasked::new(black, mask).with_mode(AlphaMode::Stochastic);
+        let quad = Rect::new(
+            Vec3(-1., -1., -1.),
+            Vec3(2., 0., 0.),
+            Vec3(0., 2., 0.),
+            Arc::new(veil),
+        );
+        let mut rng = StdRng::seed_from_u64(92);
+        let ray = Ray::new(Vec3(0., 0., 1.), Vec3(0.2, 0.1, -1.));
+        let samples = 4000;
+        let mean = (0..samples).fold(Vec3(0., 0., 0.), |sum, _| {
+            sum + color(&ray, &quad, 0, &mut rng)
+        }) / samples as f32;
+        let expected = 0.75 * background(&ray);
+        assert!(
+            (mean - expected).length() < 0.03,
+            "{:?} {:?}",
+            mean,
+            expected
+        );
+    }
+
+    #[test]
+    // Test that a film of a quarter of the green wavelength is at the extremum
+    // of the green reflectance, a minimum below the substrate index and a
+    // maximum above it.
+    fn test_thin_film_quarter_wave() {
+        let metal: Arc<dyn MaterialExt> = Arc::new(Metal::new(Vec3(0.9, 0.9, 0.9), 0.));
+        let quarter_wave = |ior: f32| 550. / (4. * ior);
+        let coating = ThinFilm::new(metal.clone(), 0., 1.38);
+        let reflectance = coating.reflectance(1., quarter_wave(1.38));
+        assert!(reflectance.g() < reflectance.r() && reflectance.g() < reflectance.b());
+        // The reflectance of a quarter-wave layer at normal incidence.
+        let expected = ((1.5 - 1.38f32 * 1.38) / (1.5 + 1.38 * 1.38)).powi(2);
+        assert!(
+            (reflectance.g() - expected).abs() < 1e-5,
+            "{:?}",
+            reflectance
+        );
+        assert!(reflectance.g() < coating.reflectance(1., 0.).g());
+
+        let coating = ThinFilm::new(metal, 0., 2.);
+        let reflectance = coating.reflectance(1., quarter_wave(2.));
+        assert!(reflectance.g() > reflectance.r() && reflectance.g() > reflectance.b());
+        let expected = ((1.5f32 - 4.) / (1.5 + 4.)).powi(2);
+        assert!(
+            (reflectance.g() - expected).abs() < 1e-5,
+            "{:?}",
+            reflectance
+        );
+    }
+
+    #[test]
+    // Test that a film scales the reflections and the transmissions of the
+    // base material to its reflectance, and leaves it unchanged without
+    // thickness.
+    fn test_thin_film_scatter() {
+        let mut rng = StdRng::seed_from_u64(95);
+        let metal = Arc::new(Metal::new(Vec3(0.9, 0.8, 0.7), 0.));
+        let bare = ThinFilm::new(metal, 0., 1.33);
+        let attenuation = mean_attenuation(&bare, 30., &mut rng);
+        assert!(
+            (attenuation - Vec3(0.9, 0.8, 0.7)).length() < 1e-4,
+            "{:?}",
+            attenuation
+        );
+
+        let soap = ThinFilm::new(Arc::new(Dielectric::new(1.5)), 300., 1.33);
+        let hit = HitRecord {
+            parameter: 1.,
+            point_at_parameter: Vec3(0., 0., 0.),
+            normal: Vec3(0., 1., 0.),
+            u: 0.,
+            v: 0.,
+            tangent: Vec3(0., 0., 0.),
+            bitangent: Vec3(0., 0., 0.),
+            front_face: true,
+            material: &soap,
+        };
+        let ray = Ray::new(Vec3(0., 1., 0.), Vec3(0., -1., 0.));
+        let samples = 20000;
+        let (mut reflected, mut total) = (Vec3(0., 0., 0.), Vec3(0., 0., 0.));
+        for _ in 0..samples {
+            let (scattered, attenuation) = hit.material.scatter(&ray, &hit, &mut rng).unwrap();
+            if scattered.direction().y() > 0. {
+                reflected += attenuation;
+            }
+            total += attenuation;
+        }
+        let expected = soap.reflectance(1., 300.);
+        let reflected = reflected / samples as f32;
+        assert!(
+            (reflected - expected).length() < 0.1 * expected.length(),
+            "{:?}",
+            reflected
+        );
+        assert!((total / samples as f32 - Vec3(1., 1., 1.)).length() < 0.02);
+    }
+
+    #[test]
+    // Test that the validating constructors reject every invalid parameter.
+    fn test_material_try_new() {
+        let color = Vec3(0.8, 0.5, 0.2);
+        assert!(Lambertian::try_new(color).is_ok());
+        assert!(Metal::try_new(color, 0.).is_ok());
+        assert!(Metal::try_new(Vec3(0., 1., 0.), 1.).is_ok());
+        assert!(Dielectric::try_new(1.5).is_ok());
+
+        for &attenuation in &[
+            Vec3(1.2, 0.5, 0.2),
+            Vec3(0.8, -0.1, 0.2),
+            Vec3(0.8, 0.5, f32::NAN),
+            Vec3(200., 128., 50.),
+        ] {
+            let error = MaterialError::InvalidAttenuation { attenuation };
+            let error = format!("{:?}", error);
+            let lambertian = Lambertian::try_new(attenuation).map(|_| ());
+            assert_eq!(format!("{:?}", lambertian.unwrap_err()), error);
+            let metal = Metal::try_new(attenuation, 0.5).map(|_| ());
+            assert_eq!(format!("{:?}", metal.unwrap_err()), error);
+        }
+        for &fuzzy in &[-0.1, 1.1, f32::NAN, f32::INFINITY] {
+            match Metal::try_new(color, fuzzy) {
+                Err(MaterialError::InvalidFuzzyness { fuzzy: rejected }) => {
+                    assert!(rejected == fuzzy || fuzzy.is_nan() && rejected.is_nan())
                 }
+                other => panic!("{:?} for the fuzzyness {}", other, fuzzy),
+            }
+        }
+        for &ref_idx in &[0., -1.5, f32::NAN] {
+            assert!(matches!(
+                Dielectric::try_new(ref_idx),
+                Err(MaterialError::InvalidRefractiveIndex { .. })
+            ));
+        }
+        let message = Dielectric::try_new(-1.).unwrap_err().to_string();
+        assert_eq!(message, "invalid refractive index -1: must be positive");
+    }
+
+    #[test]
+    // Test that the infallible metal constructor clamps the fuzzyness.
+    fn test_metal_new_clamps() {
+        let color = Vec3(0.8, 0.5, 0.2);
+        assert_eq!(Metal::new(color, -0.3).fuzzy(), 0.);
+        assert_eq!(Metal::new(color, 0.3).fuzzy(), 0.3);
+        assert_eq!(Metal::new(color, 3.).fuzzy(), 1.);
+        assert_eq!(Metal::new(color, f32::NAN).fuzzy(), 0.);
+        assert_eq!(Metal::new(color, f32::NEG_INFINITY).fuzzy(), 0.);
+        // A negative fuzzyness reflects like a perfect mirror.
+        let metal = Metal::new(color, -0.3);
+        let hit = HitRecord {
+            parameter: 1.,
+            point_at_parameter: Vec3(0., 0., 0.),
+            normal: Vec3(0., 1., 0.),
+            u: 0.,
+            v: 0.,
+            tangent: Vec3(0., 0., 0.),
+            bitangent: Vec3(0., 0., 0.),
+            front_face: true,
+            material: &metal,
+        };
+        let ray = Ray::new(Vec3(-1., 1., 0.), Vec3(1., -1., 0.));
+        let mut rng = StdRng::seed_from_u64(97);
+        for _ in 0..100 {
+            let (scattered, _) = hit.material.scatter(&ray, &hit, &mut rng).unwrap();
+            assert_eq!(
+                *scattered.direction(),
+                reflect(&unit_vector(ray.direction()), &hit.normal)
+            );
+        }
+    }
+
+    #[test]
+    // Test that a metal sphere with a checkered attenuation reflects with the
+    // color of the check that is hit, and that a checkered fuzzyness only
+    // perturbs the reflections off the rough checks.
+    fn test_metal_textures() {
+        let painted = Metal::with_texture(Arc::new(Checker), 0.);
+        let sphere = Sphere::new(Vec3(0., 0., 0.), 1., Arc::new(painted));
+        let origin = Vec3(0., 0., 5.);
+        let mut rng = StdRng::seed_from_u64(101);
+        let mut attenuations = Vec::new();
+        for i in 0..20 {
+            let target = Vec3(-0.95 + 0.1 * i as f32, 0.3, 0.);
+            let ray = Ray::new(origin, target - origin);
+            let hit = sphere.intersect(&ray, 0.001, f32::MAX).unwrap();
+            let (_, attenuation) = hit.material.scatter(&ray, &hit, &mut rng).unwrap();
+            assert_eq!(
+                attenuation,
+                Checker.value(hit.u, hit.v, &hit.point_at_parameter)
+            );
+            attenuations.push(attenuation);
+        }
+        assert!(attenuations.contains(&Vec3(1., 1., 1.)));
+        assert!(attenuations.contains(&Vec3(0., 0., 0.)));
+
+        let patchy = Metal::new(Vec3(0.9, 0.9, 0.9), 0.).with_fuzzy_texture(Arc::new(Checker));
+        let hit = |u| HitRecord {
+            parameter: 1.,
+            point_at_parameter: Vec3(0., 0., 0.),
+            normal: Vec3(0., 1., 0.),
+            u,
+            v: 0.1,
+            tangent: Vec3(0., 0., 0.),
+            bitangent: Vec3(0., 0., 0.),
+            front_face: true,
+            material: &patchy,
+        };
+        let ray = Ray::new(Vec3(-1., 1., 0.), Vec3(1., -1., 0.));
+        let mirrored = reflect(&unit_vector(ray.direction()), &Vec3(0., 1., 0.));
+        // The check at u = 0.1 is white, i.e. fully rough, the one at 0.3 black.
+        let (rough, polished) = (hit(0.1), hit(0.3));
+        for _ in 0..100 {
+            if let Some((scattered, _)) = rough.material.scatter(&ray, &rough, &mut rng) {
+                assert_ne!(*scattered.direction(), mirrored);
             }
+            let (scattered, _) = polished
+                .material
+                .scatter(&ray, &polished, &mut rng)
+                .unwrap();
+            assert_eq!(*scattered.direction(), mirrored);
         }
     }
 }
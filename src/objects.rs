@@ -1,7 +1,192 @@
+use crate::aabb::surrounding_box;
+use crate::aabb::Aabb;
 use crate::hit_record::HitRecord;
+use crate::lights::Light;
+use crate::lights::Lights;
 use crate::ray::Ray;
+use crate::ray::PACKET_SIZE;
+use crate::stats;
+use crate::vec3::Vec3;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
 
+pub mod arena;
+pub mod bvh;
+pub mod capsule;
+pub mod constant_medium;
+pub mod csg;
+pub mod cuboid;
+pub mod density_grid;
+pub mod disk;
+pub mod ellipsoid;
+pub mod flip_normals;
+#[cfg(feature = "gltf")]
+pub mod gltf;
+pub mod group;
+pub mod infinite_cylinder;
+pub mod instance;
+pub mod mesh;
+pub mod moving_sphere;
+pub mod obj;
+pub mod object;
+pub mod parametric;
+pub mod polygon;
+pub mod quadric;
+pub mod rect;
+pub mod rotate;
+pub mod scale;
+pub mod scene;
+pub mod sdf;
 pub mod sphere;
+pub mod sphere_list;
+pub mod sphere_shell;
+pub mod torus;
+pub mod transformed;
+pub mod translate;
+pub mod triangle;
+pub mod uniform_grid;
+
+/// Error returned when an object is created from invalid parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectError {
+    /// The inner radius of an annulus is negative or not smaller than its outer radius.
+    InvalidAnnulus {
+        inner_radius: f32,
+        outer_radius: f32,
+    },
+    /// The inner radius of a spherical shell is not positive or not smaller than its outer radius.
+    InvalidShell {
+        inner_radius: f32,
+        outer_radius: f32,
+    },
+    /// The transformation matrix of an object cannot be inverted.
+    SingularTransform,
+    /// A scale factor is zero, negative or not a number.
+    InvalidScale { factors: Vec3 },
+    /// A face of a mesh refers to a vertex that does not exist.
+    InvalidVertexIndex { face: usize, index: usize },
+    /// The number of vertex normals of a mesh differs from its number of vertices.
+    VertexNormalCount { vertices: usize, normals: usize },
+    /// An edge of a mesh is shared by more than two faces.
+    NonManifoldEdge {
+        vertices: (usize, usize),
+        faces: usize,
+    },
+    /// The faces of a mesh cannot be oriented consistently, like a Möbius strip.
+    NonOrientableMesh,
+    /// A polygon has fewer than three vertices.
+    PolygonVertexCount { vertices: usize },
+    /// The vertices of a polygon do not lie in a common plane.
+    NonPlanarPolygon,
+    /// A polygon is not convex, self-intersecting or without area.
+    NonConvexPolygon,
+    /// A parameter range is empty, reversed or not finite.
+    InvalidParameterRange { start: f32, end: f32 },
+    /// A tessellation has zero subdivisions along a parameter.
+    InvalidResolution { u: usize, v: usize },
+    /// The minimum corner of a box is not below its maximum corner along every axis.
+    InvalidBounds { min: Vec3, max: Vec3 },
+    /// A grid has zero cells along an axis.
+    InvalidGridResolution { x: usize, y: usize, z: usize },
+    /// The number of values given for a grid differs from its number of cells.
+    GridValueCount { cells: usize, values: usize },
+    /// A density is negative or not finite.
+    InvalidDensity { index: usize, density: f32 },
+}
+
+impl fmt::Display for ObjectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjectError::InvalidAnnulus {
+                inner_radius,
+                outer_radius,
+            } => write!(
+                f,
+                "invalid annulus: inner radius {} must be non-negative and smaller than outer radius {}",
+                inner_radius, outer_radius
+            ),
+            ObjectError::InvalidShell {
+                inner_radius,
+                outer_radius,
+            } => write!(
+                f,
+                "invalid sphere shell: inner radius {} must be positive and smaller than outer radius {}",
+                inner_radius, outer_radius
+            ),
+            ObjectError::SingularTransform => write!(f, "transformation matrix is singular"),
+            ObjectError::InvalidScale { factors } => write!(
+                f,
+                "invalid scale factors ({}, {}, {}): all factors must be positive",
+                factors.x(),
+                factors.y(),
+                factors.z()
+            ),
+            ObjectError::InvalidVertexIndex { face, index } => write!(
+                f,
+                "invalid mesh: face {} refers to the missing vertex {}",
+                face, index
+            ),
+            ObjectError::VertexNormalCount { vertices, normals } => write!(
+                f,
+                "invalid mesh: {} vertex normals given for {} vertices",
+                normals, vertices
+            ),
+            ObjectError::NonManifoldEdge { vertices, faces } => write!(
+                f,
+                "invalid mesh: edge between vertices {} and {} is shared by {} faces",
+                vertices.0, vertices.1, faces
+            ),
+            ObjectError::NonOrientableMesh => {
+                write!(f, "invalid mesh: faces cannot be oriented consistently")
+            }
+            ObjectError::PolygonVertexCount { vertices } => write!(
+                f,
+                "invalid polygon: {} vertices given, at least 3 are required",
+                vertices
+            ),
+            ObjectError::NonPlanarPolygon => write!(f, "invalid polygon: vertices are not coplanar"),
+            ObjectError::NonConvexPolygon => write!(f, "invalid polygon: polygon is not convex"),
+            ObjectError::InvalidParameterRange { start, end } => write!(
+                f,
+                "invalid parameter range [{}, {}]: start must be smaller than end",
+                start, end
+            ),
+            ObjectError::InvalidResolution { u, v } => write!(
+                f,
+                "invalid resolution {}x{}: at least one subdivision is required per parameter",
+                u, v
+            ),
+            ObjectError::InvalidBounds { min, max } => write!(
+                f,
+                "invalid bounds: minimum ({}, {}, {}) must be below maximum ({}, {}, {})",
+                min.x(),
+                min.y(),
+                min.z(),
+                max.x(),
+                max.y(),
+                max.z()
+            ),
+            ObjectError::InvalidGridResolution { x, y, z } => write!(
+                f,
+                "invalid grid resolution {}x{}x{}: at least one cell is required per axis",
+                x, y, z
+            ),
+            ObjectError::GridValueCount { cells, values } => write!(
+                f,
+                "invalid grid: {} values given for {} cells",
+                values, cells
+            ),
+            ObjectError::InvalidDensity { index, density } => write!(
+                f,
+                "invalid density {} at index {}: densities must be finite and non-negative",
+                density, index
+            ),
+        }
+    }
+}
+
+impl Error for ObjectError {}
 
 /// Trait for objects that can be hit by a ray of light.
 pub trait Hitable: Send + Sync {
@@ -14,12 +199,173 @@ pub trait Hitable: Send + Sync {
     /// within `t_min` and `t_max`.
     ///
     /// If the ray does not intersect the object, `None` is returned.
-    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>>;
+
+    /// Check whether the ray intersects the object at all for a parameter
+    /// `t` within `t_min` and `t_max`, e.g. to find out whether a shadow ray
+    /// is blocked on its way to a light.
+    ///
+    /// Unlike `intersect`, the search can stop at the first hit rather than
+    /// the closest one, and no `HitRecord` has to be built. The default
+    /// implementation calls `intersect`, objects searching through many
+    /// parts should override it. Every hit counts as blocking the ray, the
+    /// material is not taken into account, such that semi-transparent
+    /// occluders block as much as opaque ones.
+    fn intersect_any(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.intersect(ray, t_min, t_max).is_some()
+    }
+
+    /// Return the closest hit of each of the `rays` within `t_min` and
+    /// `t_max`, like `intersect` does for each ray on its own.
+    ///
+    /// Coherent rays, e.g. the primary rays of neighbouring pixels, take the
+    /// same paths through an acceleration structure, which can then test them
+    /// together. The default implementation intersects the rays one after
+    /// another, structures testing them together override it. The hits have
+    /// to be exactly those of `intersect`.
+    fn intersect_packet(
+        &self,
+        rays: &[Ray; PACKET_SIZE],
+        t_min: f32,
+        t_max: f32,
+    ) -> [Option<HitRecord<'_>>; PACKET_SIZE] {
+        std::array::from_fn(|i| self.intersect(&rays[i], t_min, t_max))
+    }
+
+    /// Return all intersections of the line through the ray with the object,
+    /// sorted by increasing parameter `t`, including those behind the origin.
+    ///
+    /// This is required for constructive solid geometry, which needs to know
+    /// where a ray enters and leaves each operand. The default implementation
+    /// walks along the line calling `intersect` repeatedly, closed primitives
+    /// should override it to report all roots directly.
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        let mut hits = Vec::new();
+        let mut t_min = f32::MIN;
+        while let Some(hit) = self.intersect(ray, t_min, f32::MAX) {
+            // Step past the hit, relative to its magnitude for distant hits.
+            t_min = hit.parameter + 1e-4 * hit.parameter.abs().max(1.);
+            hits.push(hit);
+        }
+        hits
+    }
+
+    /// Return a box enclosing the object, or `None` if it is unbounded.
+    ///
+    /// Objects which move during the exposure are enclosed at all times.
+    /// Callers, e.g. acceleration structures, have to cope with unbounded
+    /// objects such as planes by testing them against every ray.
+    fn bounding_box(&self) -> Option<Aabb>;
+
+    /// Add a `Light` to `lights` for every emitting part of the object, see
+    /// `lights::Lights::collect`.
+    ///
+    /// Spheres and rects of emitting materials add themselves, and the
+    /// collections of objects the lights of their objects. The default adds
+    /// none, such that the light of other objects is only found by the rays
+    /// hitting them.
+    fn collect_lights(&self, _lights: &mut Vec<Light>) {}
+
+    /// Access the lights of the scene, if they have been collected, e.g. by
+    /// a `scene::Scene`. The renderer samples directions towards them at
+    /// diffuse surfaces. Defaults to `None`.
+    fn lights(&self) -> Option<&Lights> {
+        None
+    }
+}
+
+/// Shared geometry is hitable as well, such that the same object can be
+/// placed many times without copying it, e.g. with `instance::Instance`.
+///
+/// ```
+/// # use raytracer::objects::sphere::Sphere;
+/// # use raytracer::objects::{Hitable, HitableList};
+/// # use raytracer::vec3::Vec3;
+/// # use raytracer::materials::Lambertian;
+/// # use std::sync::Arc;
+/// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+/// let sphere: Arc<dyn Hitable> = Arc::new(Sphere::new(Vec3(0., 0., -1.), 0.5, material));
+/// let world = HitableList::new(vec![Box::new(sphere.clone()), Box::new(sphere)]);
+/// ```
+impl<T: Hitable + ?Sized> Hitable for Arc<T> {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        (**self).intersect(ray, t_min, t_max)
+    }
+
+    fn intersect_any(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        (**self).intersect_any(ray, t_min, t_max)
+    }
+
+    fn intersect_packet(
+        &self,
+        rays: &[Ray; PACKET_SIZE],
+        t_min: f32,
+        t_max: f32,
+    ) -> [Option<HitRecord<'_>>; PACKET_SIZE] {
+        (**self).intersect_packet(rays, t_min, t_max)
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        (**self).intersect_all(ray)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        (**self).bounding_box()
+    }
+
+    fn collect_lights(&self, lights: &mut Vec<Light>) {
+        (**self).collect_lights(lights)
+    }
+
+    fn lights(&self) -> Option<&Lights> {
+        (**self).lights()
+    }
 }
 
-#[derive(Default)]
-pub struct HitableList {
-    hitable_objects: Vec<Box<dyn Hitable>>,
+/// Boxed objects are hitable, such that a `HitableList` or a `BvhNode` of
+/// trait objects can hold shapes of any type, including custom ones.
+impl<T: Hitable + ?Sized> Hitable for Box<T> {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        (**self).intersect(ray, t_min, t_max)
+    }
+
+    fn intersect_any(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        (**self).intersect_any(ray, t_min, t_max)
+    }
+
+    fn intersect_packet(
+        &self,
+        rays: &[Ray; PACKET_SIZE],
+        t_min: f32,
+        t_max: f32,
+    ) -> [Option<HitRecord<'_>>; PACKET_SIZE] {
+        (**self).intersect_packet(rays, t_min, t_max)
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        (**self).intersect_all(ray)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        (**self).bounding_box()
+    }
+
+    fn collect_lights(&self, lights: &mut Vec<Light>) {
+        (**self).collect_lights(lights)
+    }
+
+    fn lights(&self) -> Option<&Lights> {
+        (**self).lights()
+    }
+}
+
+/// A list of objects, hitable by the closest hit among them.
+///
+/// The objects are trait objects by default. A list can hold any other
+/// hitable type `H` as well, e.g. the `object::Object` enum, in which case
+/// the calls to the objects are dispatched statically and can be inlined.
+pub struct HitableList<H: Hitable = Box<dyn Hitable>> {
+    hitable_objects: Vec<H>,
 }
 
 impl HitableList {
@@ -29,12 +375,44 @@ impl HitableList {
     }
 }
 
-impl Hitable for HitableList {
-    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+impl<H: Hitable> HitableList<H> {
+    /// Create a collection of hitable objects of the same type.
+    ///
+    /// ```
+    /// # use raytracer::objects::sphere::Sphere;
+    /// # use raytracer::objects::{Hitable, HitableList};
+    /// # use raytracer::ray::Ray;
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::materials::Lambertian;
+    /// # use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let world = HitableList::from_objects(vec![
+    ///     Sphere::new(Vec3(0., 0., -1.), 0.5, material.clone()),
+    ///     Sphere::new(Vec3(0., 0., -3.), 0.5, material),
+    /// ]);
+    /// let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., -1.));
+    /// assert_eq!(world.intersect(&ray, 0.001, f32::MAX).unwrap().parameter, 0.5);
+    /// ```
+    pub fn from_objects(hitable_objects: Vec<H>) -> HitableList<H> {
+        HitableList { hitable_objects }
+    }
+}
+
+// An empty list of trait objects, such that `HitableList::default()` needs
+// no type annotation.
+impl Default for HitableList {
+    fn default() -> HitableList {
+        HitableList::new(Vec::new())
+    }
+}
+
+impl<H: Hitable> Hitable for HitableList<H> {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let mut hit_record = None;
         let mut closest_so_far = t_max;
         for object in &self.hitable_objects {
-            if let Some(hit) = object.intersect(&ray, t_min, closest_so_far) {
+            stats::count_intersection_test();
+            if let Some(hit) = object.intersect(ray, t_min, closest_so_far) {
                 closest_so_far = hit.parameter;
                 hit_record = Some(hit);
             }
@@ -42,4 +420,27 @@ impl Hitable for HitableList {
 
         hit_record
     }
+
+    fn intersect_any(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.hitable_objects.iter().any(|object| {
+            stats::count_intersection_test();
+            object.intersect_any(ray, t_min, t_max)
+        })
+    }
+
+    /// The box enclosing all objects, which is `None` if the list is empty
+    /// or contains an unbounded object.
+    fn bounding_box(&self) -> Option<Aabb> {
+        let mut objects = self.hitable_objects.iter();
+        let first = objects.next()?.bounding_box()?;
+        objects.try_fold(first, |aabb, object| {
+            Some(surrounding_box(&aabb, &object.bounding_box()?))
+        })
+    }
+
+    fn collect_lights(&self, lights: &mut Vec<Light>) {
+        for object in &self.hitable_objects {
+            object.collect_lights(lights);
+        }
+    }
 }
@@ -0,0 +1,286 @@
+//! Storage of a scene in contiguous vectors, addressed by indices.
+//!
+//! A scene built as `Vec<Box<dyn Hitable>>` with `Arc` materials allocates
+//! every object and every material on its own. A `SceneArena` instead owns
+//! the objects in a single vector and refers to materials by a `MaterialId`,
+//! an index into the vector of its materials. Spheres, which make up most
+//! scenes, are stored with the id of their material. Any other object keeps
+//! its own materials as an `object::Object`.
+//!
+//! The arena is hit through `ObjectRef` handles, e.g. in the leaves of the
+//! `BvhNode` returned by `SceneArena::bvh`, which resolve the material of a
+//! sphere against the arena when it is hit.
+
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::lights::Light;
+use crate::materials::MaterialExt;
+use crate::objects::bvh::BvhBuildStrategy;
+use crate::objects::bvh::BvhNode;
+use crate::objects::object::Object;
+use crate::objects::sphere::Sphere;
+use crate::objects::Hitable;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// The index of a material in a `SceneArena`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MaterialId(u32);
+
+/// The index of an object in a `SceneArena`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObjectId(u32);
+
+// An object stored in the arena.
+enum Entry {
+    Sphere(Sphere<MaterialId>),
+    Object(Object),
+}
+
+/// The materials and objects of a scene.
+///
+/// ```
+/// use raytracer::materials::{Dielectric, Lambertian};
+/// use raytracer::objects::arena::SceneArena;
+/// use raytracer::objects::bvh::BvhBuildStrategy;
+/// use raytracer::objects::Hitable;
+/// use raytracer::ray::Ray;
+/// use raytracer::vec3::Vec3;
+/// let mut arena = SceneArena::new();
+/// let glass = arena.add_material(Dielectric::new(1.5));
+/// let ground = arena.add_material(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+/// arena.add_sphere(Vec3(0., -1000., 0.), 1000., ground);
+/// for x in -5..5 {
+///     arena.add_sphere(Vec3(2. * x as f32, 0.5, -2.), 0.5, glass);
+/// }
+/// let world = arena.bvh(BvhBuildStrategy::Median);
+/// let ray = Ray::new(Vec3(0., 0.5, 0.), Vec3(0., 0., -1.));
+/// assert_eq!(world.intersect(&ray, 0.001, f32::MAX).unwrap().parameter, 1.5);
+/// ```
+#[derive(Default)]
+pub struct SceneArena {
+    // Materials are of different types, which is why they are boxed, but
+    // they are addressed by index and never shared through reference counts.
+    materials: Vec<Box<dyn MaterialExt>>,
+    objects: Vec<Entry>,
+}
+
+impl SceneArena {
+    /// Create an empty arena.
+    pub fn new() -> SceneArena {
+        SceneArena::default()
+    }
+
+    /// Add a material and return its id.
+    pub fn add_material(&mut self, material: impl MaterialExt + 'static) -> MaterialId {
+        self.materials.push(Box::new(material));
+        MaterialId(self.materials.len() as u32 - 1)
+    }
+
+    /// Add a sphere made of the material `material` and return its id.
+    ///
+    /// # Panics
+    ///
+    /// If the material was not added to this arena.
+    pub fn add_sphere(&mut self, center: Vec3, radius: f32, material: MaterialId) -> ObjectId {
+        assert!(
+            (material.0 as usize) < self.materials.len(),
+            "unknown material {:?}",
+            material
+        );
+        self.push(Entry::Sphere(Sphere::with_material(
+            center, radius, material,
+        )))
+    }
+
+    /// Add an object with materials of its own and return its id.
+    pub fn add_object(&mut self, object: impl Into<Object>) -> ObjectId {
+        self.push(Entry::Object(object.into()))
+    }
+
+    fn push(&mut self, entry: Entry) -> ObjectId {
+        self.objects.push(entry);
+        ObjectId(self.objects.len() as u32 - 1)
+    }
+
+    /// Access the material with the given id.
+    pub fn material(&self, id: MaterialId) -> &dyn MaterialExt {
+        self.materials[id.0 as usize].as_ref()
+    }
+
+    /// Return a hitable handle of the object with the given id.
+    pub fn object(&self, id: ObjectId) -> ObjectRef<'_> {
+        assert!(
+            (id.0 as usize) < self.objects.len(),
+            "unknown object {:?}",
+            id
+        );
+        ObjectRef { arena: self, id }
+    }
+
+    /// Return the handles of all objects, in the order they were added.
+    pub fn objects(&self) -> Vec<ObjectRef<'_>> {
+        (0..self.objects.len() as u32)
+            .map(|id| ObjectRef {
+                arena: self,
+                id: ObjectId(id),
+            })
+            .collect()
+    }
+
+    /// Build a bounding volume hierarchy over all objects.
+    pub fn bvh(&self, strategy: BvhBuildStrategy) -> BvhNode<ObjectRef<'_>> {
+        BvhNode::from_objects(self.objects(), strategy)
+    }
+}
+
+/// A handle of an object in a `SceneArena`, which is hit like the object.
+#[derive(Clone, Copy)]
+pub struct ObjectRef<'a> {
+    arena: &'a SceneArena,
+    id: ObjectId,
+}
+
+impl<'a> ObjectRef<'a> {
+    /// Access the id of the object.
+    pub fn id(&self) -> ObjectId {
+        self.id
+    }
+
+    fn entry(&self) -> &'a Entry {
+        &self.arena.objects[self.id.0 as usize]
+    }
+}
+
+impl Hitable for ObjectRef<'_> {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        match self.entry() {
+            Entry::Sphere(sphere) => {
+                let material = self.arena.material(*sphere.material_ref());
+                sphere.intersect_with(ray, t_min, t_max, material)
+            }
+            Entry::Object(object) => object.intersect(ray, t_min, t_max),
+        }
+    }
+
+    fn intersect_any(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        match self.entry() {
+            Entry::Sphere(sphere) => sphere.intersect_any_within(ray, t_min, t_max),
+            Entry::Object(object) => object.intersect_any(ray, t_min, t_max),
+        }
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        match self.entry() {
+            Entry::Sphere(sphere) => {
+                sphere.intersect_all_with(ray, self.arena.material(*sphere.material_ref()))
+            }
+            Entry::Object(object) => object.intersect_all(ray),
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        match self.entry() {
+            Entry::Sphere(sphere) => Some(sphere.aabb()),
+            Entry::Object(object) => object.bounding_box(),
+        }
+    }
+
+    fn collect_lights(&self, lights: &mut Vec<Light>) {
+        match self.entry() {
+            Entry::Sphere(sphere) => {
+                let material = self.arena.material(*sphere.material_ref());
+                lights.extend(Light::sphere(*sphere.center(), *sphere.radius(), material));
+            }
+            Entry::Object(object) => object.collect_lights(lights),
+        }
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::materials::Dielectric;
+    use crate::materials::Lambertian;
+    use crate::materials::Metal;
+    use crate::objects::cuboid::Cuboid;
+    use crate::render::render;
+    use crate::render::RenderSettings;
+    use rand::prelude::*;
+    use rand::rngs::StdRng;
+    use std::sync::Arc;
+
+    #[test]
+    // Test that a scene stored in an arena renders exactly like the same scene of boxed objects.
+    fn test_arena_matches_boxed() {
+        let mut rng = StdRng::seed_from_u64(63);
+        let mut arena = SceneArena::new();
+        let mut boxed: Vec<Box<dyn Hitable>> = Vec::new();
+        let ground = arena.add_material(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        arena.add_sphere(Vec3(0., -1000., 0.), 1000., ground);
+        boxed.push(Box::new(Sphere::new(
+            Vec3(0., -1000., 0.),
+            1000.,
+            Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))),
+        )));
+        for a in -4..4_i32 {
+            for b in -4..4 {
+                let center = Vec3(a as f32 + 0.6 * rng.gen::<f32>(), 0.2, b as f32);
+                let albedo = Vec3(rng.gen(), rng.gen(), rng.gen());
+                let material: Arc<dyn MaterialExt> = match (a + b).rem_euclid(3) {
+                    0 => {
+                        let id = arena.add_material(Lambertian::new(albedo));
+                        arena.add_sphere(center, 0.2, id);
+                        Arc::new(Lambertian::new(albedo))
+                    }
+                    1 => {
+                        let id = arena.add_material(Metal::new(albedo, 0.3));
+                        arena.add_sphere(center, 0.2, id);
+                        Arc::new(Metal::new(albedo, 0.3))
+                    }
+                    _ => {
+                        let id = arena.add_material(Dielectric::new(1.5));
+                        arena.add_sphere(center, 0.2, id);
+                        Arc::new(Dielectric::new(1.5))
+                    }
+                };
+                boxed.push(Box::new(Sphere::new(center, 0.2, material)));
+            }
+        }
+        // An object with a material of its own.
+        let cuboid = || {
+            let material = Arc::new(Metal::new(Vec3(0.7, 0.6, 0.5), 0.));
+            Cuboid::new(Vec3(-1., 0., -0.5), Vec3(0.5, 1., 0.5), material)
+        };
+        arena.add_object(cuboid());
+        boxed.push(Box::new(cuboid()));
+
+        let world = arena.bvh(BvhBuildStrategy::Median);
+        let expected = BvhNode::with_strategy(boxed, BvhBuildStrategy::Median);
+        assert_eq!(world.bounding_box(), expected.bounding_box());
+        let camera = Camera::new(
+            Vec3(6., 2., 3.),
+            Vec3(0., 0., 0.),
+            Vec3(0., 1., 0.),
+            40.,
+            1.5,
+            0.1,
+            6.,
+        );
+        let settings = RenderSettings {
+            width: 45,
+            height: 30,
+            samples: 4,
+            ..RenderSettings::default()
+        };
+        assert_eq!(
+            render(&world, &camera, &settings),
+            render(&expected, &camera, &settings)
+        );
+    }
+}
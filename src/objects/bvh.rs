@@ -0,0 +1,1652 @@
+//! Bounding volume hierarchies.
+//!
+//! A bounding volume hierarchy is a binary tree of objects, in which each
+//! node stores the bounding boxes of its two subtrees. A ray only descends
+//! into the subtrees whose box it hits, which reduces the number of objects
+//! tested per ray from linear to roughly logarithmic in the size of the scene.
+
+use crate::aabb::surrounding_box;
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::lights::Light;
+use crate::objects::Hitable;
+use crate::objects::HitableList;
+use crate::ray::Ray;
+use crate::ray::PACKET_SIZE;
+use crate::stats;
+use crate::vec3::Vec3;
+use rayon::prelude::*;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+
+// An object with its index among the objects the tree is built from.
+type Indexed<H> = (u32, H);
+
+// An indexed object with its bounding box.
+type Bounded<H> = (Aabb, Indexed<H>);
+
+/// How the objects of a node are split between its two children when
+/// building a `BvhNode`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BvhBuildStrategy {
+    /// Split in two halves at the median of the centers of the bounding
+    /// boxes, along the axis in which the centers are spread most. This is
+    /// quick to build, down to leaves of single objects.
+    #[default]
+    Median,
+    /// Choose the split with the lowest expected cost of tracing a ray by
+    /// the surface area heuristic. The objects are sorted into `buckets`
+    /// of equal width along each axis, and every split between two buckets
+    /// is evaluated. Several objects are kept in a single leaf when this is
+    /// expected to be cheaper than splitting them. At least two buckets are
+    /// used.
+    Sah { buckets: usize },
+    /// Sort the objects along a space-filling curve by the Morton codes of
+    /// the centers of their bounding boxes, and split where the codes first
+    /// differ. The sort and the subtrees are computed in parallel, which
+    /// builds large scenes much faster at the price of a somewhat worse tree.
+    Lbvh,
+}
+
+/// A node of a bounding volume hierarchy, which is hitable like the list of
+/// objects it was built from.
+///
+/// Unbounded objects, e.g. planes, cannot be sorted into the tree. They are
+/// kept in a list next to it, which every ray is tested against.
+///
+/// When objects move, e.g. between the frames of an animation, the tree can
+/// be refitted to their new bounding boxes instead of being built anew.
+///
+/// Like `HitableList`, the tree holds trait objects by default, and can be
+/// built from objects of any other hitable type `H` with `from_objects`.
+pub struct BvhNode<H: Hitable = Box<dyn Hitable>> {
+    left: Child<H>,
+    left_box: Option<Aabb>,
+    right: Option<Child<H>>,
+    right_box: Option<Aabb>,
+    // The total surface area of the boxes in this subtree, now and when built.
+    area: f32,
+    built_area: f32,
+}
+
+// A child of a `BvhNode`: either another node, or objects tested directly.
+// The objects are stored with their indices among the objects the tree was
+// built from, which is all a saved tree needs to know about them.
+enum Child<H: Hitable> {
+    Node(Box<BvhNode<H>>),
+    Leaf(u32, H),
+    List(Box<[u32]>, HitableList<H>),
+}
+
+impl<H: Hitable> Child<H> {
+    // A child testing the given objects, a leaf if it is a single one.
+    fn objects(mut objects: Vec<Indexed<H>>) -> Child<H> {
+        if objects.len() == 1 {
+            let (index, object) = objects.pop().unwrap();
+            return Child::Leaf(index, object);
+        }
+        let (indices, objects): (Vec<u32>, Vec<H>) = objects.into_iter().unzip();
+        Child::List(indices.into(), HitableList::from_objects(objects))
+    }
+
+    // Compute the bounding box of the child, after refitting the boxes
+    // below it to the current state of the objects if `deep`.
+    fn fit(&mut self, deep: bool) -> Option<Aabb> {
+        match self {
+            Child::Node(node) if deep => {
+                node.refit();
+                node.bounding_box()
+            }
+            child => child.bounding_box(),
+        }
+    }
+
+    // The total surface area of the boxes below the child.
+    fn area(&self) -> f32 {
+        match self {
+            Child::Node(node) => node.area,
+            _ => 0.,
+        }
+    }
+}
+
+impl<H: Hitable> Child<H> {
+    // The closest hits of the active lanes of a packet, see
+    // `BvhNode::intersect_lanes`. Objects are tested ray by ray.
+    fn intersect_lanes(
+        &self,
+        rays: &[Ray; PACKET_SIZE],
+        t_min: f32,
+        t_max: &[f32; PACKET_SIZE],
+        active: [bool; PACKET_SIZE],
+    ) -> [Option<HitRecord<'_>>; PACKET_SIZE] {
+        match self {
+            Child::Node(node) => node.intersect_lanes(rays, t_min, t_max, active),
+            child => std::array::from_fn(|i| {
+                if active[i] {
+                    child.intersect(&rays[i], t_min, t_max[i])
+                } else {
+                    None
+                }
+            }),
+        }
+    }
+}
+
+impl<H: Hitable> Hitable for Child<H> {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        match self {
+            Child::Node(node) => node.intersect(ray, t_min, t_max),
+            Child::Leaf(_, object) => {
+                stats::count_intersection_test();
+                object.intersect(ray, t_min, t_max)
+            }
+            Child::List(_, list) => list.intersect(ray, t_min, t_max),
+        }
+    }
+
+    fn intersect_any(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        match self {
+            Child::Node(node) => node.intersect_any(ray, t_min, t_max),
+            Child::Leaf(_, object) => {
+                stats::count_intersection_test();
+                object.intersect_any(ray, t_min, t_max)
+            }
+            Child::List(_, list) => list.intersect_any(ray, t_min, t_max),
+        }
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        match self {
+            Child::Node(node) => node.intersect_all(ray),
+            Child::Leaf(_, object) => {
+                stats::count_intersection_test();
+                object.intersect_all(ray)
+            }
+            Child::List(_, list) => list.intersect_all(ray),
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        match self {
+            Child::Node(node) => node.bounding_box(),
+            Child::Leaf(_, object) => object.bounding_box(),
+            Child::List(_, list) => list.bounding_box(),
+        }
+    }
+
+    fn collect_lights(&self, lights: &mut Vec<Light>) {
+        match self {
+            Child::Node(node) => node.collect_lights(lights),
+            Child::Leaf(_, object) => object.collect_lights(lights),
+            Child::List(_, list) => list.collect_lights(lights),
+        }
+    }
+}
+
+// The surface area of an optional box, which is zero for no box.
+fn area(aabb: &Option<Aabb>) -> f32 {
+    aabb.map_or(0., |aabb| aabb.surface_area())
+}
+
+impl BvhNode {
+    /// Build a bounding volume hierarchy from the hitable `objects`.
+    ///
+    /// The objects are split recursively in two halves at the median of the
+    /// centers of their bounding boxes, along the axis in which the centers
+    /// are spread most.
+    ///
+    /// ```
+    /// use raytracer::objects::bvh::BvhNode;
+    /// use raytracer::objects::sphere::Sphere;
+    /// use raytracer::objects::Hitable;
+    /// use raytracer::ray::Ray;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let spheres: Vec<Box<dyn Hitable>> = (0..100)
+    ///     .map(|i| {
+    ///         let center = Vec3((i % 10) as f32, (i / 10) as f32, -5.);
+    ///         Box::new(Sphere::new(center, 0.4, material.clone())) as Box<dyn Hitable>
+    ///     })
+    ///     .collect();
+    /// let world = BvhNode::new(spheres);
+    /// let ray = Ray::new(Vec3(3., 4., 0.), Vec3(0., 0., -1.));
+    /// let hit = world.intersect(&ray, 0.001, f32::MAX).unwrap();
+    /// assert!((hit.point_at_parameter - Vec3(3., 4., -4.6)).length() < 1e-5);
+    /// ```
+    pub fn new(objects: Vec<Box<dyn Hitable>>) -> BvhNode {
+        BvhNode::with_strategy(objects, BvhBuildStrategy::Median)
+    }
+
+    /// Build a bounding volume hierarchy from the hitable `objects`, choosing
+    /// the splits according to `strategy`.
+    ///
+    /// ```
+    /// use raytracer::objects::bvh::{BvhBuildStrategy, BvhNode};
+    /// use raytracer::objects::sphere::Sphere;
+    /// use raytracer::objects::Hitable;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let spheres: Vec<Box<dyn Hitable>> = (0..100)
+    ///     .map(|i| {
+    ///         let center = Vec3(i as f32 * i as f32 / 100., 0., -5.);
+    ///         Box::new(Sphere::new(center, 0.4, material.clone())) as Box<dyn Hitable>
+    ///     })
+    ///     .collect();
+    /// let world = BvhNode::with_strategy(spheres, BvhBuildStrategy::Sah { buckets: 12 });
+    /// ```
+    pub fn with_strategy(objects: Vec<Box<dyn Hitable>>, strategy: BvhBuildStrategy) -> BvhNode {
+        BvhNode::from_objects(objects, strategy)
+    }
+}
+
+impl<H: Hitable> BvhNode<H> {
+    /// Build a bounding volume hierarchy from hitable `objects` of the same
+    /// type, choosing the splits according to `strategy`.
+    ///
+    /// The tree is built exactly like with `with_strategy`, but the objects
+    /// are stored and called without dynamic dispatch.
+    ///
+    /// ```
+    /// use raytracer::objects::bvh::{BvhBuildStrategy, BvhNode};
+    /// use raytracer::objects::sphere::Sphere;
+    /// use raytracer::objects::Hitable;
+    /// use raytracer::ray::Ray;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let spheres: Vec<Sphere> = (0..100)
+    ///     .map(|i| Sphere::new(Vec3((i % 10) as f32, (i / 10) as f32, -5.), 0.4, material.clone()))
+    ///     .collect();
+    /// let world = BvhNode::from_objects(spheres, BvhBuildStrategy::Median);
+    /// let ray = Ray::new(Vec3(3., 4., 0.), Vec3(0., 0., -1.));
+    /// let hit = world.intersect(&ray, 0.001, f32::MAX).unwrap();
+    /// assert!((hit.point_at_parameter - Vec3(3., 4., -4.6)).length() < 1e-5);
+    /// ```
+    pub fn from_objects(objects: Vec<H>, strategy: BvhBuildStrategy) -> BvhNode<H> {
+        let (bounded, unbounded): (Vec<_>, Vec<_>) = objects
+            .into_iter()
+            .enumerate()
+            .map(|(index, object)| (object.bounding_box(), (index as u32, object)))
+            .partition(|(aabb, _)| aabb.is_some());
+        let bounded = bounded
+            .into_iter()
+            .map(|(aabb, object)| (aabb.unwrap(), object))
+            .collect();
+        let tree = match strategy {
+            BvhBuildStrategy::Lbvh => BvhNode::build_linear(bounded),
+            _ => BvhNode::build(bounded, strategy),
+        };
+        if unbounded.is_empty() {
+            return tree;
+        }
+        let unbounded = unbounded.into_iter().map(|(_, object)| object).collect();
+        BvhNode::new_node(Child::Node(Box::new(tree)), Some(Child::objects(unbounded)))
+    }
+
+    // Build the tree from bounded objects.
+    fn build(mut objects: Vec<Bounded<H>>, strategy: BvhBuildStrategy) -> BvhNode<H> {
+        match objects.len() {
+            0 | 1 => BvhNode::leaf(objects),
+            2 if strategy == BvhBuildStrategy::Median => {
+                let (_, (index, right)) = objects.pop().unwrap();
+                let (_, (left_index, left)) = objects.pop().unwrap();
+                BvhNode::new_node(
+                    Child::Leaf(left_index, left),
+                    Some(Child::Leaf(index, right)),
+                )
+            }
+            n => {
+                let split = match strategy {
+                    BvhBuildStrategy::Median | BvhBuildStrategy::Lbvh => {
+                        Some(median_split(&objects))
+                    }
+                    BvhBuildStrategy::Sah { buckets } => sah_split(&objects, buckets.max(2)),
+                };
+                let (axis, count) = match split {
+                    Some(split) => split,
+                    None => return BvhNode::leaf(objects),
+                };
+                let key = |aabb: &Aabb| component(&center(aabb), axis);
+                objects.sort_by(|(a, _), (b, _)| key(a).total_cmp(&key(b)));
+                let upper = objects.split_off(count);
+                debug_assert!(!objects.is_empty() && count < n);
+                let left = BvhNode::build(objects, strategy);
+                let right = BvhNode::build(upper, strategy);
+                BvhNode::pair(left, right)
+            }
+        }
+    }
+
+    // Build the tree from bounded objects sorted by their Morton codes.
+    fn build_linear(objects: Vec<Bounded<H>>) -> BvhNode<H> {
+        let centers: Vec<Vec3> = objects.iter().map(|(aabb, _)| center(aabb)).collect();
+        let bounds = match Aabb::from_points(&centers) {
+            Some(bounds) => bounds,
+            None => return BvhNode::build(objects, BvhBuildStrategy::Median),
+        };
+        let mut coded: Vec<(u32, Bounded<H>)> = objects
+            .into_par_iter()
+            .map(|object| (morton_code(&center(&object.0), &bounds), object))
+            .collect();
+        // The sort is stable, such that equal codes keep the order of the objects.
+        coded.par_sort_by_key(|(code, _)| *code);
+        BvhNode::emit(coded)
+    }
+
+    // Split objects sorted by their Morton codes at the highest bit in which
+    // the codes differ, such that both halves are compact in space.
+    fn emit(mut objects: Vec<(u32, Bounded<H>)>) -> BvhNode<H> {
+        let n = objects.len();
+        if n <= 2 {
+            let objects = objects.into_iter().map(|(_, object)| object).collect();
+            return BvhNode::build(objects, BvhBuildStrategy::Median);
+        }
+        let (first, last) = (objects[0].0, objects[n - 1].0);
+        let count = if first == last {
+            // Coincident centers cannot be told apart, split them in halves.
+            n / 2
+        } else {
+            let common = (first ^ last).leading_zeros();
+            objects.partition_point(|(code, _)| (code ^ first).leading_zeros() > common)
+        };
+        let upper = objects.split_off(count);
+        let (left, right) = if n >= PARALLEL_BUILD_SIZE {
+            rayon::join(|| BvhNode::emit(objects), || BvhNode::emit(upper))
+        } else {
+            (BvhNode::emit(objects), BvhNode::emit(upper))
+        };
+        // The boxes of the children are fitted on the way back up.
+        BvhNode::pair(left, right)
+    }
+
+    // Put the objects into a single node, which tests all of them.
+    fn leaf(objects: Vec<Bounded<H>>) -> BvhNode<H> {
+        let objects = objects.into_iter().map(|(_, object)| object).collect();
+        BvhNode::new_node(Child::objects(objects), None)
+    }
+
+    // Join two subtrees under a new node.
+    fn pair(left: BvhNode<H>, right: BvhNode<H>) -> BvhNode<H> {
+        BvhNode::new_node(
+            Child::Node(Box::new(left)),
+            Some(Child::Node(Box::new(right))),
+        )
+    }
+
+    // Create a node and fit the boxes of its children.
+    fn new_node(left: Child<H>, right: Option<Child<H>>) -> BvhNode<H> {
+        let mut node = BvhNode {
+            left,
+            left_box: None,
+            right,
+            right_box: None,
+            area: 0.,
+            built_area: 0.,
+        };
+        node.refit_children(false);
+        node.built_area = node.area;
+        node
+    }
+
+    // Recompute the boxes of the children, after refitting them if `deep`.
+    fn refit_children(&mut self, deep: bool) {
+        self.left_box = self.left.fit(deep);
+        self.right_box = self.right.as_mut().and_then(|right| right.fit(deep));
+        self.area = area(&self.left_box)
+            + self.left.area()
+            + area(&self.right_box)
+            + self.right.as_ref().map_or(0., |right| right.area());
+    }
+
+    /// Recompute the bounding boxes of all nodes bottom-up from the current
+    /// bounding boxes of the objects, keeping the structure of the tree.
+    ///
+    /// Objects can move while owned by the tree if they are shared, e.g.
+    /// through an `Arc` to an object with interior mutability, and report
+    /// their new position through `Hitable::bounding_box`. Refitting is
+    /// much cheaper than building a new tree, but the tree degrades as the
+    /// objects move away from where they were grouped, which is measured by
+    /// `surface_area_growth`.
+    ///
+    /// ```
+    /// use raytracer::objects::bvh::BvhNode;
+    /// use raytracer::objects::Hitable;
+    /// use raytracer::objects::sphere::Sphere;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let sphere = Box::new(Sphere::new(Vec3(0., 0., -1.), 0.5, material));
+    /// let mut world = BvhNode::new(vec![sphere]);
+    /// world.refit();
+    /// assert_eq!(world.surface_area_growth(), 1.);
+    /// ```
+    pub fn refit(&mut self) {
+        self.refit_children(true);
+    }
+
+    /// Return the ratio of the total surface area of the boxes in the tree
+    /// to that when the tree was built.
+    ///
+    /// The cost of tracing a ray grows with the area of the boxes it has to
+    /// be tested against, such that a tree should be built anew once
+    /// refitting has grown it by more than some factor, e.g. 1.5.
+    pub fn surface_area_growth(&self) -> f32 {
+        if self.built_area > 0. {
+            self.area / self.built_area
+        } else {
+            1.
+        }
+    }
+}
+
+/// Error returned when a saved bounding volume hierarchy cannot be loaded.
+#[derive(Debug)]
+pub enum BvhFileError {
+    /// The file could not be read or written.
+    Io(io::Error),
+    /// The file does not hold a bounding volume hierarchy.
+    InvalidFormat,
+    /// The file was written in another version of the format.
+    UnsupportedVersion { version: u32 },
+    /// The tree was built from other objects than it is loaded with, e.g.
+    /// from another number of objects or objects at other places.
+    GeometryMismatch { expected: u64, found: u64 },
+    /// The tree refers to an object which does not exist or to an object
+    /// twice, or misses one.
+    InvalidTree,
+}
+
+impl fmt::Display for BvhFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BvhFileError::Io(error) => write!(f, "failed to access file: {}", error),
+            BvhFileError::InvalidFormat => write!(f, "not a bounding volume hierarchy file"),
+            BvhFileError::UnsupportedVersion { version } => write!(
+                f,
+                "unsupported version {} of the bounding volume hierarchy format, expected {}",
+                version, FORMAT_VERSION
+            ),
+            BvhFileError::GeometryMismatch { expected, found } => write!(
+                f,
+                "bounding volume hierarchy was built for other objects (fingerprint {:016x}, objects have {:016x})",
+                expected, found
+            ),
+            BvhFileError::InvalidTree => write!(
+                f,
+                "bounding volume hierarchy refers to missing or repeated objects"
+            ),
+        }
+    }
+}
+
+impl Error for BvhFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BvhFileError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for BvhFileError {
+    fn from(error: io::Error) -> BvhFileError {
+        match error.kind() {
+            // A truncated file.
+            io::ErrorKind::UnexpectedEof => BvhFileError::InvalidFormat,
+            _ => BvhFileError::Io(error),
+        }
+    }
+}
+
+// The first bytes of a saved tree, followed by the version of the format.
+const MAGIC: &[u8; 8] = b"RTBVH\0\0\0";
+const FORMAT_VERSION: u32 = 1;
+
+// The tags of the children in a saved tree.
+const NODE: u8 = 0;
+const LEAF: u8 = 1;
+const LIST: u8 = 2;
+
+impl<H: Hitable> BvhNode<H> {
+    /// Save the tree to the file at `path`, to load it again with `load`
+    /// instead of building it anew.
+    ///
+    /// Only the structure of the tree and its bounding boxes are saved, in
+    /// a compact binary format. The objects are referred to by their index
+    /// among the objects the tree was built from, and the file records a
+    /// fingerprint of their number and bounding boxes.
+    ///
+    /// ```
+    /// use raytracer::materials::Lambertian;
+    /// use raytracer::objects::bvh::{BvhBuildStrategy, BvhNode};
+    /// use raytracer::objects::sphere::Sphere;
+    /// use std::sync::Arc;
+    /// use raytracer::vec3::Vec3;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let spheres = || -> Vec<Sphere> {
+    ///     (0..100)
+    ///         .map(|i| Sphere::new(Vec3(i as f32, 0., -5.), 0.4, material.clone()))
+    ///         .collect()
+    /// };
+    /// let path = std::env::temp_dir().join("raytracer_doctest_bvh_save.bvh");
+    /// let world = BvhNode::from_objects(spheres(), BvhBuildStrategy::Sah { buckets: 12 });
+    /// world.save(&path).unwrap();
+    /// let loaded = BvhNode::load(&path, spheres()).unwrap();
+    /// // The tree does not fit other objects.
+    /// assert!(BvhNode::load(&path, spheres().split_off(1)).is_err());
+    /// ```
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), BvhFileError> {
+        let mut objects = Vec::new();
+        self.collect_objects(&mut objects);
+        objects.sort_by_key(|&(index, _)| index);
+        let fingerprint = fingerprint(objects.iter().map(|&(_, aabb)| aabb));
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        file.write_all(&fingerprint.to_le_bytes())?;
+        self.write(&mut file)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Load a tree saved by `save` for the hitable `objects`, which have to
+    /// be those the tree was built from, in the same order.
+    ///
+    /// Loading is much faster than building the tree, which is then exactly
+    /// the one that was saved. It fails if the number or the bounding boxes
+    /// of the objects differ from when the tree was saved, since the tree
+    /// would not fit them.
+    pub fn load<P: AsRef<Path>>(path: P, objects: Vec<H>) -> Result<BvhNode<H>, BvhFileError> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut magic = [0; 8];
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(BvhFileError::InvalidFormat);
+        }
+        let version = read_u32(&mut file)?;
+        if version != FORMAT_VERSION {
+            return Err(BvhFileError::UnsupportedVersion { version });
+        }
+        let expected = read_u64(&mut file)?;
+        let found = fingerprint(objects.iter().map(|object| object.bounding_box()));
+        if found != expected {
+            return Err(BvhFileError::GeometryMismatch { expected, found });
+        }
+        let mut objects: Vec<Option<H>> = objects.into_iter().map(Some).collect();
+        let tree = BvhNode::read(&mut file, &mut objects)?;
+        if objects.iter().any(Option::is_some) {
+            return Err(BvhFileError::InvalidTree);
+        }
+        if file.read(&mut [0])? > 0 {
+            return Err(BvhFileError::InvalidFormat);
+        }
+        Ok(tree)
+    }
+
+    // Collect the indices and bounding boxes of all objects in the tree.
+    fn collect_objects(&self, objects: &mut Vec<(u32, Option<Aabb>)>) {
+        for child in std::iter::once(&self.left).chain(&self.right) {
+            match child {
+                Child::Node(node) => node.collect_objects(objects),
+                Child::Leaf(index, object) => objects.push((*index, object.bounding_box())),
+                Child::List(indices, list) => objects.extend(
+                    indices
+                        .iter()
+                        .zip(&list.hitable_objects)
+                        .map(|(&index, object)| (index, object.bounding_box())),
+                ),
+            }
+        }
+    }
+
+    // Write the node and its subtrees in depth-first order.
+    fn write(&self, file: &mut impl Write) -> io::Result<()> {
+        write_child(file, &self.left)?;
+        write_aabb(file, &self.left_box)?;
+        match &self.right {
+            Some(right) => {
+                file.write_all(&[1])?;
+                write_child(file, right)?;
+                write_aabb(file, &self.right_box)?;
+            }
+            None => file.write_all(&[0])?,
+        }
+        file.write_all(&self.area.to_le_bytes())?;
+        file.write_all(&self.built_area.to_le_bytes())
+    }
+
+    // Read a node written by `write`, taking its objects from `objects`.
+    fn read(file: &mut impl Read, objects: &mut [Option<H>]) -> Result<BvhNode<H>, BvhFileError> {
+        let left = BvhNode::read_child(file, objects)?;
+        let left_box = read_aabb(file)?;
+        let (right, right_box) = match read_u8(file)? {
+            0 => (None, None),
+            1 => (Some(BvhNode::read_child(file, objects)?), read_aabb(file)?),
+            _ => return Err(BvhFileError::InvalidFormat),
+        };
+        Ok(BvhNode {
+            left,
+            left_box,
+            right,
+            right_box,
+            area: read_f32(file)?,
+            built_area: read_f32(file)?,
+        })
+    }
+
+    // Read a child written by `write_child`.
+    fn read_child(
+        file: &mut impl Read,
+        objects: &mut [Option<H>],
+    ) -> Result<Child<H>, BvhFileError> {
+        let mut take = |index: u32| {
+            objects
+                .get_mut(index as usize)
+                .and_then(Option::take)
+                .map(|object| (index, object))
+                .ok_or(BvhFileError::InvalidTree)
+        };
+        match read_u8(file)? {
+            NODE => Ok(Child::Node(Box::new(BvhNode::read(file, objects)?))),
+            LEAF => {
+                let (index, object) = take(read_u32(file)?)?;
+                Ok(Child::Leaf(index, object))
+            }
+            LIST => {
+                let count = read_u32(file)?;
+                let objects = (0..count)
+                    .map(|_| take(read_u32(file)?))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let (indices, objects): (Vec<u32>, Vec<H>) = objects.into_iter().unzip();
+                Ok(Child::List(
+                    indices.into(),
+                    HitableList::from_objects(objects),
+                ))
+            }
+            _ => Err(BvhFileError::InvalidFormat),
+        }
+    }
+}
+
+// Write a child as its tag followed by the node or the indices of its objects.
+fn write_child<H: Hitable>(file: &mut impl Write, child: &Child<H>) -> io::Result<()> {
+    match child {
+        Child::Node(node) => {
+            file.write_all(&[NODE])?;
+            node.write(file)
+        }
+        Child::Leaf(index, _) => {
+            file.write_all(&[LEAF])?;
+            file.write_all(&index.to_le_bytes())
+        }
+        Child::List(indices, _) => {
+            file.write_all(&[LIST])?;
+            file.write_all(&(indices.len() as u32).to_le_bytes())?;
+            for index in indices.iter() {
+                file.write_all(&index.to_le_bytes())?;
+            }
+            Ok(())
+        }
+    }
+}
+
+// Write an optional box as a flag followed by its corners.
+fn write_aabb(file: &mut impl Write, aabb: &Option<Aabb>) -> io::Result<()> {
+    match aabb {
+        Some(aabb) => {
+            file.write_all(&[1])?;
+            for corner in [aabb.min(), aabb.max()] {
+                for x in [corner.x(), corner.y(), corner.z()] {
+                    file.write_all(&x.to_le_bytes())?;
+                }
+            }
+            Ok(())
+        }
+        None => file.write_all(&[0]),
+    }
+}
+
+// Read an optional box written by `write_aabb`.
+fn read_aabb(file: &mut impl Read) -> Result<Option<Aabb>, BvhFileError> {
+    match read_u8(file)? {
+        0 => Ok(None),
+        1 => {
+            let mut read_vec3 = || -> io::Result<Vec3> {
+                Ok(Vec3(read_f32(file)?, read_f32(file)?, read_f32(file)?))
+            };
+            let min = read_vec3()?;
+            let max = read_vec3()?;
+            Ok(Some(Aabb::new(min, max)))
+        }
+        _ => Err(BvhFileError::InvalidFormat),
+    }
+}
+
+fn read_u8(file: &mut impl Read) -> io::Result<u8> {
+    let mut bytes = [0; 1];
+    file.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u32(file: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    file.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(file: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    file.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f32(file: &mut impl Read) -> io::Result<f32> {
+    let mut bytes = [0; 4];
+    file.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+// Hash the number of objects and their bounding boxes with FNV-1a, to tell
+// whether a saved tree was built from the same objects.
+fn fingerprint(boxes: impl ExactSizeIterator<Item = Option<Aabb>>) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut add = |bytes: &[u8]| {
+        for &byte in bytes {
+            hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    add(&(boxes.len() as u64).to_le_bytes());
+    for aabb in boxes {
+        match aabb {
+            Some(aabb) => {
+                add(&[1]);
+                for corner in [aabb.min(), aabb.max()] {
+                    for x in [corner.x(), corner.y(), corner.z()] {
+                        add(&x.to_le_bytes());
+                    }
+                }
+            }
+            None => add(&[0]),
+        }
+    }
+    hash
+}
+
+// The cost of testing a ray against a bounding box, relative to an object.
+const TRAVERSAL_COST: f32 = 0.125;
+
+// Leaves of more objects are split even if the heuristic advises against it.
+const MAX_LEAF_SIZE: usize = 8;
+
+// Subtrees of fewer objects are not worth building on another thread.
+const PARALLEL_BUILD_SIZE: usize = 1024;
+
+// Interleave the bits of the position of `p` within `bounds`, quantized to
+// 10 bits per axis, such that nearby points tend to have close codes.
+fn morton_code(p: &Vec3, bounds: &Aabb) -> u32 {
+    let extent = *bounds.max() - *bounds.min();
+    let quantize = |x: f32, min: f32, extent: f32| {
+        if extent > 0. {
+            ((x - min) / extent * 1023.).clamp(0., 1023.) as u32
+        } else {
+            0
+        }
+    };
+    let min = bounds.min();
+    spread_bits(quantize(p.x(), min.x(), extent.x())) << 2
+        | spread_bits(quantize(p.y(), min.y(), extent.y())) << 1
+        | spread_bits(quantize(p.z(), min.z(), extent.z()))
+}
+
+// Insert two zero bits between each of the lower 10 bits of `x`.
+fn spread_bits(x: u32) -> u32 {
+    let x = (x | x << 16) & 0x0300_00ff;
+    let x = (x | x << 8) & 0x0300_f00f;
+    let x = (x | x << 4) & 0x030c_30c3;
+    (x | x << 2) & 0x0924_9249
+}
+
+// The coordinate of `v` along the axis with the given index.
+fn component(v: &Vec3, axis: usize) -> f32 {
+    match axis {
+        0 => v.x(),
+        1 => v.y(),
+        _ => v.z(),
+    }
+}
+
+// Return the axis in which the centers are spread most, and half the
+// number of objects to go into the first child.
+fn median_split<H>(objects: &[Bounded<H>]) -> (usize, usize) {
+    let centers: Vec<_> = objects.iter().map(|(aabb, _)| center(aabb)).collect();
+    let spread = Aabb::from_points(&centers).unwrap();
+    let extent = *spread.max() - *spread.min();
+    let axis = if extent.x() >= extent.y() && extent.x() >= extent.z() {
+        0
+    } else if extent.y() >= extent.z() {
+        1
+    } else {
+        2
+    };
+    (axis, objects.len() / 2)
+}
+
+// Return the axis and the number of objects to go into the first child for
+// the split with the lowest cost according to the surface area heuristic,
+// or `None` if the objects should rather be kept in a leaf.
+fn sah_split<H>(objects: &[Bounded<H>], buckets: usize) -> Option<(usize, usize)> {
+    let n = objects.len();
+    let area = objects
+        .iter()
+        .map(|(aabb, _)| *aabb)
+        .reduce(|a, b| surrounding_box(&a, &b))?
+        .surface_area();
+    let centers: Vec<_> = objects.iter().map(|(aabb, _)| center(aabb)).collect();
+    let spread = Aabb::from_points(&centers)?;
+
+    // The lowest cost with the axis and the size of the first child.
+    let mut best: Option<(f32, usize, usize)> = None;
+    for axis in 0..3 {
+        let (low, high) = (component(spread.min(), axis), component(spread.max(), axis));
+        if high <= low {
+            continue;
+        }
+        let mut counts = vec![0; buckets];
+        let mut boxes: Vec<Option<Aabb>> = vec![None; buckets];
+        for ((aabb, _), c) in objects.iter().zip(centers.iter()) {
+            let position = (component(c, axis) - low) / (high - low);
+            let bucket = ((position * buckets as f32) as usize).min(buckets - 1);
+            counts[bucket] += 1;
+            boxes[bucket] = Some(match boxes[bucket] {
+                Some(b) => surrounding_box(&b, aabb),
+                None => *aabb,
+            });
+        }
+
+        // Sweep from the right to know the area and count of every suffix.
+        let mut right = vec![(0., 0); buckets];
+        let mut accumulated: Option<Aabb> = None;
+        let mut count = 0;
+        for bucket in (1..buckets).rev() {
+            accumulated = join(accumulated, boxes[bucket]);
+            count += counts[bucket];
+            right[bucket] = (accumulated.map_or(0., |b| b.surface_area()), count);
+        }
+        let mut accumulated: Option<Aabb> = None;
+        let mut count = 0;
+        for bucket in 0..buckets - 1 {
+            accumulated = join(accumulated, boxes[bucket]);
+            count += counts[bucket];
+            let (right_area, right_count) = right[bucket + 1];
+            if count == 0 || right_count == 0 {
+                continue;
+            }
+            let left_area = accumulated.map_or(0., |b| b.surface_area());
+            let cost = if area > 0. {
+                TRAVERSAL_COST + (left_area * count as f32 + right_area * right_count as f32) / area
+            } else {
+                TRAVERSAL_COST + n as f32
+            };
+            if best.is_none_or(|(lowest, _, _)| cost < lowest) {
+                best = Some((cost, axis, count));
+            }
+        }
+    }
+
+    match best {
+        // Testing all objects of a leaf costs one per object.
+        Some((cost, axis, count)) if cost < n as f32 || n > MAX_LEAF_SIZE => Some((axis, count)),
+        // All centers coincide, splitting cannot separate the objects.
+        None if n > MAX_LEAF_SIZE => Some(median_split(objects)),
+        _ => None,
+    }
+}
+
+// Join two optional boxes.
+fn join(a: Option<Aabb>, b: Option<Aabb>) -> Option<Aabb> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(surrounding_box(&a, &b)),
+        (a, b) => a.or(b),
+    }
+}
+
+// The center of a bounding box.
+fn center(aabb: &Aabb) -> Vec3 {
+    (*aabb.min() + *aabb.max()) / 2.
+}
+
+#[cfg(test)]
+thread_local! {
+    // The number of bounding boxes tested on this thread.
+    static BOX_TESTS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+// Check which of the active lanes of a packet may hit a child with the
+// bounding box `aabb`, each within its own limit in `t_max`.
+fn may_hit_lanes(
+    aabb: &Option<Aabb>,
+    rays: &[Ray; PACKET_SIZE],
+    t_min: f32,
+    t_max: &[f32; PACKET_SIZE],
+    active: [bool; PACKET_SIZE],
+) -> [bool; PACKET_SIZE] {
+    let hits = match aabb {
+        Some(aabb) => aabb.hit_packet(rays, t_min, t_max),
+        None => [true; PACKET_SIZE],
+    };
+    std::array::from_fn(|i| active[i] && hits[i])
+}
+
+impl<H: Hitable> BvhNode<H> {
+    // The closest hit of every active lane of a packet, each within its own
+    // limit in `t_max`. A lane follows the same path through the tree as a
+    // single ray in `intersect`, such that it finds the same hit, but the
+    // boxes of a node are tested for all lanes at once.
+    fn intersect_lanes(
+        &self,
+        rays: &[Ray; PACKET_SIZE],
+        t_min: f32,
+        t_max: &[f32; PACKET_SIZE],
+        active: [bool; PACKET_SIZE],
+    ) -> [Option<HitRecord<'_>>; PACKET_SIZE] {
+        stats::count_bvh_node();
+        let left_active = may_hit_lanes(&self.left_box, rays, t_min, t_max, active);
+        let mut left = if left_active.contains(&true) {
+            self.left.intersect_lanes(rays, t_min, t_max, left_active)
+        } else {
+            std::array::from_fn(|_| None)
+        };
+        let mut right = match &self.right {
+            Some(right) => {
+                let closest_so_far: [f32; PACKET_SIZE] =
+                    std::array::from_fn(|i| left[i].as_ref().map_or(t_max[i], |hit| hit.parameter));
+                let right_active =
+                    may_hit_lanes(&self.right_box, rays, t_min, &closest_so_far, active);
+                if right_active.contains(&true) {
+                    right.intersect_lanes(rays, t_min, &closest_so_far, right_active)
+                } else {
+                    std::array::from_fn(|_| None)
+                }
+            }
+            None => std::array::from_fn(|_| None),
+        };
+        std::array::from_fn(|i| right[i].take().or_else(|| left[i].take()))
+    }
+}
+
+// Check whether a ray may hit a child with the bounding box `aabb`.
+fn may_hit(aabb: &Option<Aabb>, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+    #[cfg(test)]
+    BOX_TESTS.with(|tests| tests.set(tests.get() + 1));
+    aabb.as_ref().is_none_or(|aabb| aabb.hit(ray, t_min, t_max))
+}
+
+impl<H: Hitable> Hitable for BvhNode<H> {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        stats::count_bvh_node();
+        let left = if may_hit(&self.left_box, ray, t_min, t_max) {
+            self.left.intersect(ray, t_min, t_max)
+        } else {
+            None
+        };
+        let closest_so_far = left.as_ref().map_or(t_max, |hit| hit.parameter);
+        let right = match &self.right {
+            Some(right) if may_hit(&self.right_box, ray, t_min, closest_so_far) => {
+                right.intersect(ray, t_min, closest_so_far)
+            }
+            _ => None,
+        };
+        right.or(left)
+    }
+
+    fn intersect_any(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        stats::count_bvh_node();
+        (may_hit(&self.left_box, ray, t_min, t_max) && self.left.intersect_any(ray, t_min, t_max))
+            || self.right.as_ref().is_some_and(|right| {
+                may_hit(&self.right_box, ray, t_min, t_max)
+                    && right.intersect_any(ray, t_min, t_max)
+            })
+    }
+
+    fn intersect_packet(
+        &self,
+        rays: &[Ray; PACKET_SIZE],
+        t_min: f32,
+        t_max: f32,
+    ) -> [Option<HitRecord<'_>>; PACKET_SIZE] {
+        self.intersect_lanes(rays, t_min, &[t_max; PACKET_SIZE], [true; PACKET_SIZE])
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        stats::count_bvh_node();
+        let mut hits = Vec::new();
+        if may_hit(&self.left_box, ray, f32::MIN, f32::MAX) {
+            hits.extend(self.left.intersect_all(ray));
+        }
+        if let Some(right) = &self.right {
+            if may_hit(&self.right_box, ray, f32::MIN, f32::MAX) {
+                hits.extend(right.intersect_all(ray));
+            }
+        }
+        hits.sort_by(|a, b| a.parameter.total_cmp(&b.parameter));
+        hits
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        match &self.right {
+            Some(_) => Some(surrounding_box(&self.left_box?, &self.right_box?)),
+            None => self.left_box,
+        }
+    }
+
+    fn collect_lights(&self, lights: &mut Vec<Light>) {
+        self.left.collect_lights(lights);
+        if let Some(right) = &self.right {
+            right.collect_lights(lights);
+        }
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+    use crate::materials::MaterialExt;
+    use crate::objects::cuboid::Cuboid;
+    use crate::objects::infinite_cylinder::InfiniteCylinder;
+    use crate::objects::sphere::Sphere;
+    use rand::prelude::*;
+    use rand::rngs::StdRng;
+    use std::fs;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    // A sphere which can be moved while shared.
+    struct Movable {
+        center: Mutex<Vec3>,
+        material: Arc<dyn MaterialExt>,
+    }
+
+    impl Movable {
+        fn sphere(&self) -> Sphere {
+            Sphere::new(*self.center.lock().unwrap(), 0.5, self.material.clone())
+        }
+    }
+
+    impl Hitable for Movable {
+        fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+            // The hit borrows the material of the temporary sphere, which is
+            // the material of the `Movable` as well.
+            let sphere = self.sphere();
+            let hit = sphere.intersect(ray, t_min, t_max)?;
+            Some(HitRecord {
+                parameter: hit.parameter,
+                point_at_parameter: hit.point_at_parameter,
+                normal: hit.normal,
+                u: hit.u,
+                v: hit.v,
+                tangent: Vec3(0., 0., 0.),
+                bitangent: Vec3(0., 0., 0.),
+                front_face: hit.front_face,
+                material: self.material.as_ref(),
+            })
+        }
+
+        fn bounding_box(&self) -> Option<Aabb> {
+            self.sphere().bounding_box()
+        }
+    }
+
+    // Count the intersection tests of the wrapped object.
+    struct Counted {
+        object: Box<dyn Hitable>,
+        count: Arc<AtomicUsize>,
+    }
+
+    impl Hitable for Counted {
+        fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+            self.count.fetch_add(1, Ordering::Relaxed);
+            self.object.intersect(ray, t_min, t_max)
+        }
+
+        fn bounding_box(&self) -> Option<Aabb> {
+            self.object.bounding_box()
+        }
+    }
+
+    // A random scene of spheres and boxes, with an unbounded cylinder.
+    fn scene(rng: &mut StdRng, n: usize) -> Vec<Arc<dyn Hitable>> {
+        let mut objects: Vec<Arc<dyn Hitable>> = Vec::new();
+        for i in 0..n {
+            let material: Arc<dyn MaterialExt> =
+                Arc::new(Lambertian::new(Vec3(rng.gen(), rng.gen(), rng.gen())));
+            let center = Vec3(
+                rng.gen_range(-10.0..10.0),
+                rng.gen_range(-10.0..10.0),
+                rng.gen_range(-30.0..-5.0),
+            );
+            let size = rng.gen_range(0.1..1.5);
+            if i % 3 == 0 {
+                let half = Vec3(size, size / 2., size);
+                objects.push(Arc::new(Cuboid::new(
+                    center - half,
+                    center + half,
+                    material,
+                )));
+            } else {
+                objects.push(Arc::new(Sphere::new(center, size, material)));
+            }
+        }
+        let material = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        objects.push(Arc::new(InfiniteCylinder::new(
+            Vec3(0., 0., -20.),
+            Vec3(1., 0.2, 0.),
+            0.3,
+            material,
+        )));
+        objects
+    }
+
+    #[test]
+    // Test that all strategies find the same closest hits as a flat list.
+    fn test_bvh_matches_list() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for &n in &[0, 1, 2, 3, 10, 200] {
+            // Both hold the same objects, such that their materials can be compared.
+            let objects = scene(&mut rng, n);
+            let boxed = || {
+                objects
+                    .iter()
+                    .map(|object| Box::new(object.clone()) as Box<dyn Hitable>)
+                    .collect()
+            };
+            let list = HitableList::new(boxed());
+            let bvh = BvhNode::new(boxed());
+            let sah = BvhNode::with_strategy(boxed(), BvhBuildStrategy::Sah { buckets: 12 });
+            let lbvh = BvhNode::with_strategy(boxed(), BvhBuildStrategy::Lbvh);
+            for _ in 0..2000 {
+                let origin = Vec3(
+                    rng.gen_range(-5.0..5.0),
+                    rng.gen_range(-5.0..5.0),
+                    rng.gen_range(-5.0..5.0),
+                );
+                let direction = Vec3(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-2.0..0.0),
+                );
+                let ray = Ray::new(origin, direction);
+                let expected = list.intersect(&ray, 0.001, f32::MAX);
+                let hit = bvh.intersect(&ray, 0.001, f32::MAX);
+                let key = |hit: &Option<HitRecord>| {
+                    hit.as_ref()
+                        .map(|h| (h.parameter, h.point_at_parameter, h.normal))
+                };
+                assert_eq!(key(&sah.intersect(&ray, 0.001, f32::MAX)), key(&hit));
+                assert_eq!(key(&lbvh.intersect(&ray, 0.001, f32::MAX)), key(&hit));
+                match (hit, expected) {
+                    (None, None) => {}
+                    (Some(hit), Some(expected)) => {
+                        assert_eq!(hit.parameter, expected.parameter);
+                        assert_eq!(hit.point_at_parameter, expected.point_at_parameter);
+                        assert_eq!(hit.normal, expected.normal);
+                        assert!(std::ptr::addr_eq(hit.material, expected.material));
+                    }
+                    (hit, expected) => panic!(
+                        "hit {:?} differs from expected {:?}",
+                        hit.map(|h| h.parameter),
+                        expected.map(|h| h.parameter)
+                    ),
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Test that packets of coherent and of incoherent rays hit like single rays.
+    fn test_intersect_packet() {
+        let mut rng = StdRng::seed_from_u64(56);
+        let objects = scene(&mut rng, 200);
+        let boxed = || {
+            objects
+                .iter()
+                .map(|object| Box::new(object.clone()) as Box<dyn Hitable>)
+                .collect()
+        };
+        let bvh = BvhNode::new(boxed());
+        let sah = BvhNode::with_strategy(boxed(), BvhBuildStrategy::Sah { buckets: 12 });
+        let key = |hit: &Option<HitRecord>| {
+            hit.as_ref()
+                .map(|h| (h.parameter, h.point_at_parameter, h.normal))
+        };
+        for i in 0..2000 {
+            let direction = Vec3(rng.gen_range(-0.5..0.5), rng.gen_range(-0.5..0.5), -1.);
+            let spread = if i % 2 == 0 { 0.01 } else { 1. };
+            let rays: [Ray; PACKET_SIZE] = std::array::from_fn(|_| {
+                let jitter = Vec3(
+                    rng.gen_range(-spread..spread),
+                    rng.gen_range(-spread..spread),
+                    0.,
+                );
+                Ray::new(Vec3(0., 0., 0.), direction + jitter)
+            });
+            for world in [&bvh, &sah] {
+                let hits = world.intersect_packet(&rays, 0.001, f32::MAX);
+                for (ray, hit) in rays.iter().zip(hits.iter()) {
+                    assert_eq!(key(hit), key(&world.intersect(ray, 0.001, f32::MAX)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Test that occlusion queries agree with closest hits on many spheres.
+    fn test_intersect_any() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let objects = scene(&mut rng, 300);
+        let boxed = || {
+            objects
+                .iter()
+                .map(|object| Box::new(object.clone()) as Box<dyn Hitable>)
+                .collect()
+        };
+        let list = HitableList::new(boxed());
+        let bvh = BvhNode::new(boxed());
+        let (mut blocked, mut free) = (0, 0);
+        for _ in 0..5000 {
+            let origin = Vec3(
+                rng.gen_range(-10.0..10.0),
+                rng.gen_range(-10.0..10.0),
+                rng.gen_range(-30.0..0.0),
+            );
+            let direction = Vec3(
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+            );
+            let ray = Ray::new(origin, direction);
+            // Segments of random lengths, like shadow rays to lights.
+            let t_max = rng.gen_range(0.1..20.0);
+            let expected = list.intersect(&ray, 0.001, t_max).is_some();
+            assert_eq!(list.intersect_any(&ray, 0.001, t_max), expected);
+            assert_eq!(bvh.intersect_any(&ray, 0.001, t_max), expected);
+            if expected {
+                blocked += 1;
+            } else {
+                free += 1;
+            }
+        }
+        assert!(blocked > 500 && free > 500);
+    }
+
+    #[test]
+    // Test the bounding box of a hierarchy with and without unbounded objects.
+    fn test_bvh_bounding_box() {
+        let material: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        let spheres = || -> Vec<Box<dyn Hitable>> {
+            (0..5)
+                .map(|i| {
+                    let center = Vec3(i as f32, 0., 0.);
+                    Box::new(Sphere::new(center, 0.5, material.clone())) as Box<dyn Hitable>
+                })
+                .collect()
+        };
+        let bvh = BvhNode::new(spheres());
+        assert_eq!(
+            bvh.bounding_box(),
+            Some(Aabb::new(Vec3(-0.5, -0.5, -0.5), Vec3(4.5, 0.5, 0.5)))
+        );
+        let mut objects = spheres();
+        objects.push(Box::new(InfiniteCylinder::new(
+            Vec3(0., 0., 0.),
+            Vec3(0., 1., 0.),
+            1.,
+            material.clone(),
+        )));
+        assert!(BvhNode::new(objects).bounding_box().is_none());
+        assert!(BvhNode::new(Vec::new()).bounding_box().is_none());
+    }
+
+    #[test]
+    // Test that the surface area heuristic pays off on an uneven scene: a
+    // dense cluster of small spheres, crossed by a few long beams, which
+    // splitting at the median mixes into the same nodes.
+    fn test_sah_traversal_win() {
+        let material: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        let count = Arc::new(AtomicUsize::new(0));
+        let objects = || -> Vec<Box<dyn Hitable>> {
+            let mut rng = StdRng::seed_from_u64(1);
+            let mut objects: Vec<Box<dyn Hitable>> = Vec::new();
+            for _ in 0..500 {
+                let center = Vec3(
+                    rng.gen_range(-3.0..3.0),
+                    rng.gen_range(-3.0..3.0),
+                    rng.gen_range(-13.0..-7.0),
+                );
+                objects.push(Box::new(Sphere::new(center, 0.05, material.clone())));
+            }
+            for _ in 0..20 {
+                let y = rng.gen_range(-3.0..3.0);
+                let z = rng.gen_range(-13.0..-7.0);
+                objects.push(Box::new(Cuboid::new(
+                    Vec3(-50., y, z),
+                    Vec3(50., y + 0.05, z + 0.05),
+                    material.clone(),
+                )));
+            }
+            objects
+                .into_iter()
+                .map(|object| {
+                    Box::new(Counted {
+                        object,
+                        count: count.clone(),
+                    }) as Box<dyn Hitable>
+                })
+                .collect()
+        };
+        let median = BvhNode::new(objects());
+        let sah = BvhNode::with_strategy(objects(), BvhBuildStrategy::Sah { buckets: 12 });
+
+        // The work of tracing rays, weighted like in the heuristic.
+        let cost = |bvh: &BvhNode| {
+            let mut rng = StdRng::seed_from_u64(7);
+            count.store(0, Ordering::Relaxed);
+            BOX_TESTS.with(|tests| tests.set(0));
+            for _ in 0..5000 {
+                let direction = Vec3(rng.gen_range(-0.4..0.4), rng.gen_range(-0.4..0.4), -1.);
+                bvh.intersect(&Ray::new(Vec3(0., 0., 0.), direction), 0.001, f32::MAX);
+            }
+            let boxes = BOX_TESTS.with(|tests| tests.get());
+            count.load(Ordering::Relaxed) as f32 + TRAVERSAL_COST * boxes as f32
+        };
+        let (median_cost, sah_cost) = (cost(&median), cost(&sah));
+        assert!(
+            sah_cost < 0.9 * median_cost,
+            "SAH: {}, median: {}",
+            sah_cost,
+            median_cost
+        );
+    }
+
+    #[test]
+    // Test the linear hierarchy on a cloud of 10k spheres, a tenth of which
+    // share their centers with another.
+    fn test_lbvh_cloud_matches_list() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let material: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        let mut objects: Vec<Arc<dyn Hitable>> = Vec::new();
+        let mut center = Vec3(0., 0., 0.);
+        for i in 0..10_000 {
+            if i % 10 != 0 {
+                center = Vec3(
+                    rng.gen_range(-20.0..20.0),
+                    rng.gen_range(-20.0..20.0),
+                    rng.gen_range(-60.0..-20.0),
+                );
+            }
+            let radius = rng.gen_range(0.05..0.3);
+            objects.push(Arc::new(Sphere::new(center, radius, material.clone())));
+        }
+        let boxed = || {
+            objects
+                .iter()
+                .map(|object| Box::new(object.clone()) as Box<dyn Hitable>)
+                .collect()
+        };
+        let list = HitableList::new(boxed());
+        let lbvh = BvhNode::with_strategy(boxed(), BvhBuildStrategy::Lbvh);
+        assert_eq!(lbvh.bounding_box(), list.bounding_box());
+
+        let mut hits = 0;
+        for _ in 0..500 {
+            let direction = Vec3(rng.gen_range(-0.5..0.5), rng.gen_range(-0.5..0.5), -1.);
+            let ray = Ray::new(Vec3(0., 0., 0.), direction);
+            let expected = list.intersect(&ray, 0.001, f32::MAX);
+            let hit = lbvh.intersect(&ray, 0.001, f32::MAX);
+            assert_eq!(hit.is_some(), expected.is_some());
+            if let (Some(hit), Some(expected)) = (hit, expected) {
+                assert_eq!(hit.parameter, expected.parameter);
+                assert_eq!(hit.normal, expected.normal);
+                hits += 1;
+            }
+        }
+        assert!(hits > 100);
+
+        // Thousands of objects with the very same box are split all the same.
+        let same: Vec<Box<dyn Hitable>> = (0..3000)
+            .map(|_| {
+                Box::new(Sphere::new(Vec3(0., 0., -5.), 1., material.clone())) as Box<dyn Hitable>
+            })
+            .collect();
+        let lbvh = BvhNode::with_strategy(same, BvhBuildStrategy::Lbvh);
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., -1.));
+        assert_eq!(lbvh.intersect(&ray, 0.001, f32::MAX).unwrap().parameter, 4.);
+    }
+
+    #[test]
+    // Test that refitting follows moved spheres and keeps the hits correct.
+    fn test_bvh_refit() {
+        let material: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        let spheres: Vec<Arc<Movable>> = (0..50)
+            .map(|i| {
+                Arc::new(Movable {
+                    center: Mutex::new(Vec3((i % 10) as f32 * 2., (i / 10) as f32 * 2., -10.)),
+                    material: material.clone(),
+                })
+            })
+            .collect();
+        let boxed = || {
+            spheres
+                .iter()
+                .map(|sphere| Box::new(sphere.clone()) as Box<dyn Hitable>)
+                .collect()
+        };
+        let list = HitableList::new(boxed());
+        let mut bvh = BvhNode::new(boxed());
+        assert_eq!(bvh.surface_area_growth(), 1.);
+
+        let moves = [
+            (3, Vec3(0., 0., 4.)),
+            (17, Vec3(-5., 1., 0.)),
+            (42, Vec3(2., 9., -3.)),
+        ];
+        for (i, offset) in &moves {
+            let mut center = spheres[*i].center.lock().unwrap();
+            *center += *offset;
+        }
+        // Before refitting, the old boxes hide a moved sphere.
+        let ray = |i: usize| {
+            let center = *spheres[i].center.lock().unwrap();
+            Ray::new(Vec3(0., 0., 0.), center)
+        };
+        assert!(list.intersect(&ray(42), 0.001, f32::MAX).is_some());
+        assert!(bvh.intersect(&ray(42), 0.001, f32::MAX).is_none());
+
+        bvh.refit();
+        let aabb = bvh.bounding_box().unwrap();
+        for sphere in &spheres {
+            let sphere_box = sphere.bounding_box().unwrap();
+            assert_eq!(surrounding_box(&aabb, &sphere_box), aabb);
+        }
+        assert_eq!(aabb, list.bounding_box().unwrap());
+        for i in 0..spheres.len() {
+            let expected = list.intersect(&ray(i), 0.001, f32::MAX).unwrap();
+            let hit = bvh.intersect(&ray(i), 0.001, f32::MAX).unwrap();
+            assert_eq!(hit.parameter, expected.parameter);
+        }
+        assert!(bvh.surface_area_growth() > 1.);
+
+        // Moving the spheres back restores the tree as built.
+        for (i, offset) in &moves {
+            let mut center = spheres[*i].center.lock().unwrap();
+            *center -= *offset;
+        }
+        bvh.refit();
+        assert!((bvh.surface_area_growth() - 1.).abs() < 1e-5);
+    }
+
+    #[test]
+    // Test that a saved and loaded tree finds the same hits as the tree that was saved.
+    fn test_bvh_save_load() {
+        let mut rng = StdRng::seed_from_u64(58);
+        let objects = scene(&mut rng, 300);
+        let boxed = || -> Vec<Box<dyn Hitable>> {
+            objects
+                .iter()
+                .map(|object| Box::new(object.clone()) as Box<dyn Hitable>)
+                .collect()
+        };
+        let path = std::env::temp_dir().join("raytracer_test_bvh_save_load.bvh");
+        for strategy in [
+            BvhBuildStrategy::Median,
+            BvhBuildStrategy::Sah { buckets: 12 },
+            BvhBuildStrategy::Lbvh,
+        ] {
+            let bvh = BvhNode::with_strategy(boxed(), strategy);
+            bvh.save(&path).unwrap();
+            let loaded = BvhNode::load(&path, boxed()).unwrap();
+            assert_eq!(loaded.bounding_box(), bvh.bounding_box());
+            assert_eq!(loaded.surface_area_growth(), bvh.surface_area_growth());
+            for _ in 0..1000 {
+                let origin = Vec3(
+                    rng.gen_range(-5.0..5.0),
+                    rng.gen_range(-5.0..5.0),
+                    rng.gen_range(-5.0..5.0),
+                );
+                let direction = Vec3(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-2.0..0.0),
+                );
+                let ray = Ray::new(origin, direction);
+                let expected = bvh.intersect(&ray, 0.001, f32::MAX);
+                let hit = loaded.intersect(&ray, 0.001, f32::MAX);
+                assert_eq!(
+                    hit.as_ref()
+                        .map(|h| (h.parameter, h.point_at_parameter, h.normal)),
+                    expected
+                        .as_ref()
+                        .map(|h| (h.parameter, h.point_at_parameter, h.normal))
+                );
+                if let (Some(hit), Some(expected)) = (hit, expected) {
+                    assert!(std::ptr::addr_eq(hit.material, expected.material));
+                }
+            }
+            // The saved tree is the same as the one it was loaded from.
+            let resaved = std::env::temp_dir().join("raytracer_test_bvh_resave.bvh");
+            loaded.save(&resaved).unwrap();
+            assert_eq!(fs::read(&resaved).unwrap(), fs::read(&path).unwrap());
+        }
+    }
+
+    #[test]
+    // Test that loading fails for other objects and for broken files.
+    fn test_bvh_load_errors() {
+        let mut rng = StdRng::seed_from_u64(59);
+        let objects = scene(&mut rng, 50);
+        let boxed = |objects: &[Arc<dyn Hitable>]| -> Vec<Box<dyn Hitable>> {
+            objects
+                .iter()
+                .map(|object| Box::new(object.clone()) as Box<dyn Hitable>)
+                .collect()
+        };
+        let path = std::env::temp_dir().join("raytracer_test_bvh_load_errors.bvh");
+        BvhNode::new(boxed(&objects)).save(&path).unwrap();
+        assert!(BvhNode::load(&path, boxed(&objects)).is_ok());
+
+        // Fewer objects, and objects at other places.
+        let fewer = BvhNode::load(&path, boxed(&objects[1..]));
+        assert!(matches!(fewer, Err(BvhFileError::GeometryMismatch { .. })));
+        let mut moved = objects.clone();
+        moved.swap(0, 1);
+        let moved = BvhNode::load(&path, boxed(&moved));
+        assert!(matches!(moved, Err(BvhFileError::GeometryMismatch { .. })));
+
+        let saved = fs::read(&path).unwrap();
+        let broken = std::env::temp_dir().join("raytracer_test_bvh_broken.bvh");
+        let load = |bytes: &[u8]| {
+            fs::write(&broken, bytes).unwrap();
+            BvhNode::load(&broken, boxed(&objects))
+        };
+        assert!(matches!(
+            load(b"not a tree"),
+            Err(BvhFileError::InvalidFormat)
+        ));
+        assert!(matches!(
+            load(&saved[..saved.len() - 3]),
+            Err(BvhFileError::InvalidFormat)
+        ));
+        assert!(matches!(
+            load(&[&saved[..], &[0]].concat()),
+            Err(BvhFileError::InvalidFormat)
+        ));
+        let mut version = saved.clone();
+        version[8] = 2;
+        assert!(matches!(
+            load(&version),
+            Err(BvhFileError::UnsupportedVersion { version: 2 })
+        ));
+        let missing = BvhNode::load(
+            std::env::temp_dir().join("raytracer_missing.bvh"),
+            boxed(&objects),
+        );
+        assert!(matches!(missing, Err(BvhFileError::Io(_))));
+    }
+
+    #[cfg(feature = "stats")]
+    #[test]
+    // Test that the BVH counts its visited nodes and tests fewer objects than a list.
+    fn test_bvh_stats() {
+        let mut rng = StdRng::seed_from_u64(47);
+        let objects = scene(&mut rng, 200);
+        let boxed = || {
+            objects
+                .iter()
+                .map(|object| Box::new(object.clone()) as Box<dyn Hitable>)
+                .collect()
+        };
+        let list = HitableList::new(boxed());
+        let bvh = BvhNode::with_strategy(boxed(), BvhBuildStrategy::Sah { buckets: 12 });
+        let rays: Vec<Ray> = (0..100)
+            .map(|_| {
+                let direction = Vec3(rng.gen_range(-0.5..0.5), rng.gen_range(-0.5..0.5), -1.);
+                Ray::new(Vec3(0., 0., 0.), direction)
+            })
+            .collect();
+
+        stats::take();
+        for ray in &rays {
+            list.intersect(ray, 0.001, f32::MAX);
+        }
+        let list_stats = stats::take();
+        assert_eq!(list_stats.intersection_tests, 100 * 201);
+        assert_eq!(list_stats.bvh_nodes_visited, 0);
+
+        for ray in &rays {
+            bvh.intersect(ray, 0.001, f32::MAX);
+        }
+        let bvh_stats = stats::take();
+        assert!(bvh_stats.bvh_nodes_visited >= 100);
+        assert!(bvh_stats.intersection_tests < list_stats.intersection_tests / 4);
+    }
+}
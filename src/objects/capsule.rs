@@ -0,0 +1,283 @@
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::materials::MaterialExt;
+use crate::objects::Hitable;
+use crate::ray::Ray;
+use crate::vec3::dot;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+/// A capsule, i.e. the volume swept by a sphere moving along a segment.
+///
+/// It is characterized by:
+/// - The two endpoints of the segment.
+/// - The radius of the swept sphere.
+/// - A pointer to the material that it is made of.
+///
+/// Its surface consists of a cylindrical body and two hemispherical ends.
+/// If both endpoints coincide, the capsule is a sphere.
+pub struct Capsule {
+    start: Vec3,
+    end: Vec3,
+    radius: f32,
+    material: Arc<dyn MaterialExt>,
+}
+
+/// Return the roots of `a * t^2 + 2 * half_b * t + c` in ascending order.
+fn solve_quadratic(a: f32, half_b: f32, c: f32) -> Option<(f32, f32)> {
+    let discriminant = half_b * half_b - a * c;
+    if a == 0. || discriminant < 0. {
+        return None;
+    }
+    let sqrt_discriminant = discriminant.sqrt();
+    Some((
+        (-half_b - sqrt_discriminant) / a,
+        (-half_b + sqrt_discriminant) / a,
+    ))
+}
+
+impl Capsule {
+    /// Create a `Capsule` by specifying the endpoints of its segment, its `radius` and `Material`.
+    ///
+    /// ```
+    /// use raytracer::objects::capsule::Capsule;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let capsule = Capsule::new(Vec3(0., 0., 0.), Vec3(0., 2., 0.), 0.5, material);
+    /// ```
+    pub fn new(start: Vec3, end: Vec3, radius: f32, material: Arc<dyn MaterialExt>) -> Capsule {
+        Capsule {
+            start,
+            end,
+            radius,
+            material,
+        }
+    }
+
+    /// Access the first endpoint of the segment of a `Capsule`.
+    ///
+    /// ```
+    /// # use raytracer::objects::capsule::Capsule;
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::materials::Lambertian;
+    /// # use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let capsule = Capsule::new(Vec3(0., 0., 0.), Vec3(0., 2., 0.), 0.5, material);
+    /// assert_eq!(capsule.start(), &Vec3(0., 0., 0.));
+    /// ```
+    pub fn start(&self) -> &Vec3 {
+        &self.start
+    }
+
+    /// Access the second endpoint of the segment of a `Capsule`.
+    ///
+    /// ```
+    /// # use raytracer::objects::capsule::Capsule;
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::materials::Lambertian;
+    /// # use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let capsule = Capsule::new(Vec3(0., 0., 0.), Vec3(0., 2., 0.), 0.5, material);
+    /// assert_eq!(capsule.end(), &Vec3(0., 2., 0.));
+    /// ```
+    pub fn end(&self) -> &Vec3 {
+        &self.end
+    }
+
+    /// Access the `radius` of a `Capsule`.
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// Access the `material` a `Capsule` is made of.
+    pub fn material(&self) -> Arc<dyn MaterialExt> {
+        Arc::clone(&self.material)
+    }
+
+    // Nearest valid hit on the hemisphere around `center`. Only the part of the
+    // sphere on the outer side of the segment (as indicated by `side`) belongs
+    // to the capsule surface.
+    fn intersect_cap(
+        &self,
+        ray: &Ray,
+        center: Vec3,
+        side: impl Fn(&Vec3) -> bool,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<(f32, Vec3)> {
+        let oc = *ray.origin() - center;
+        let a = dot(ray.direction(), ray.direction());
+        let half_b = dot(&oc, ray.direction());
+        let c = dot(&oc, &oc) - self.radius.powi(2);
+        let (t0, t1) = solve_quadratic(a, half_b, c)?;
+        [t0, t1]
+            .iter()
+            .filter(|&&t| t > t_min && t < t_max)
+            .map(|&t| (t, ray.point_at_parameter(t)))
+            .find(|(_, point)| side(point))
+            .map(|(t, point)| (t, (point - center) / self.radius))
+    }
+}
+
+impl Hitable for Capsule {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let segment = self.end - self.start;
+        let length = segment.length();
+
+        let mut closest: Option<(f32, Vec3)> = None;
+        let mut consider = |candidate: Option<(f32, Vec3)>| {
+            if let Some((t, normal)) = candidate {
+                if closest.is_none_or(|(closest_t, _)| t < closest_t) {
+                    closest = Some((t, normal));
+                }
+            }
+        };
+
+        if length == 0. {
+            // A degenerate capsule is just a sphere.
+            consider(self.intersect_cap(ray, self.start, |_| true, t_min, t_max));
+        } else {
+            let axis = segment / length;
+
+            // The cylindrical body: remove the components along the axis and
+            // solve for the infinite cylinder, then clip to the segment.
+            let oc = *ray.origin() - self.start;
+            let oc_axial = dot(&oc, &axis);
+            let d_axial = dot(ray.direction(), &axis);
+            let oc_perp = oc - oc_axial * axis;
+            let d_perp = *ray.direction() - d_axial * axis;
+            let a = dot(&d_perp, &d_perp);
+            let half_b = dot(&oc_perp, &d_perp);
+            let c = dot(&oc_perp, &oc_perp) - self.radius.powi(2);
+            // Rays parallel to the axis have `a == 0` and can only hit the caps.
+            if let Some((t0, t1)) = solve_quadratic(a, half_b, c) {
+                let body_hit = [t0, t1]
+                    .iter()
+                    .filter(|&&t| t > t_min && t < t_max)
+                    .map(|&t| (t, oc_axial + t * d_axial))
+                    .find(|&(_, height)| height >= 0. && height <= length)
+                    .map(|(t, _)| (t, (oc_perp + t * d_perp) / self.radius));
+                consider(body_hit);
+            }
+
+            let start = self.start;
+            let end = self.end;
+            consider(self.intersect_cap(
+                ray,
+                start,
+                |p| dot(&(*p - start), &axis) <= 0.,
+                t_min,
+                t_max,
+            ));
+            consider(self.intersect_cap(ray, end, |p| dot(&(*p - end), &axis) >= 0., t_min, t_max));
+        }
+
+        closest.map(|(t, normal)| HitRecord {
+            parameter: t,
+            point_at_parameter: ray.point_at_parameter(t),
+            normal,
+            u: 0.,
+            v: 0.,
+            tangent: Vec3(0., 0., 0.),
+            bitangent: Vec3(0., 0., 0.),
+            front_face: dot(ray.direction(), &normal) < 0.,
+            material: self.material.as_ref(),
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let extent = Vec3(self.radius, self.radius, self.radius);
+        let axis = Aabb::new(self.start, self.end);
+        Some(Aabb::new(*axis.min() - extent, *axis.max() + extent))
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+
+    fn capsule(start: Vec3, end: Vec3) -> Capsule {
+        Capsule::new(
+            start,
+            end,
+            0.5,
+            Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))),
+        )
+    }
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    // Test a ray hitting the cylindrical body from the side.
+    fn test_capsule_body_hit() {
+        let capsule = capsule(Vec3(0., 0., 0.), Vec3(0., 2., 0.));
+        let ray = Ray::new(Vec3(-5., 1., 0.), Vec3(1., 0., 0.));
+        let hit = capsule.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.parameter - 4.5).abs() < 1e-5);
+        assert_close(hit.normal, Vec3(-1., 0., 0.));
+    }
+
+    #[test]
+    // Test rays hitting each of the hemispherical ends along the axis.
+    fn test_capsule_cap_hits() {
+        let capsule = capsule(Vec3(0., 0., 0.), Vec3(0., 2., 0.));
+        let ray = Ray::new(Vec3(0., 5., 0.), Vec3(0., -1., 0.));
+        let hit = capsule.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.parameter - 2.5).abs() < 1e-5);
+        assert_close(hit.normal, Vec3(0., 1., 0.));
+
+        let ray = Ray::new(Vec3(0., -5., 0.), Vec3(0., 1., 0.));
+        let hit = capsule.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.parameter - 4.5).abs() < 1e-5);
+        assert_close(hit.normal, Vec3(0., -1., 0.));
+
+        // An oblique hit on the rounded part of the lower end.
+        let ray = Ray::new(Vec3(-5., -0.3, 0.), Vec3(1., 0., 0.));
+        let hit = capsule.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_close(hit.point_at_parameter, Vec3(-0.4, -0.3, 0.));
+        assert_close(hit.normal, Vec3(-0.8, -0.6, 0.));
+    }
+
+    #[test]
+    // Test rays passing just outside the radius and beyond the ends.
+    fn test_capsule_misses() {
+        let capsule = capsule(Vec3(0., 0., 0.), Vec3(0., 2., 0.));
+        let ray = Ray::new(Vec3(-5., 1., 0.6), Vec3(1., 0., 0.));
+        assert!(capsule.intersect(&ray, 0.001, f32::MAX).is_none());
+        let ray = Ray::new(Vec3(-5., 2.6, 0.), Vec3(1., 0., 0.));
+        assert!(capsule.intersect(&ray, 0.001, f32::MAX).is_none());
+        let ray = Ray::new(Vec3(-5., -0.6, 0.), Vec3(1., 0., 0.));
+        assert!(capsule.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    // Test a ray starting inside the capsule hitting the body from within.
+    fn test_capsule_inside() {
+        let capsule = capsule(Vec3(0., 0., 0.), Vec3(0., 2., 0.));
+        let ray = Ray::new(Vec3(0., 1., 0.), Vec3(0., 0., 1.));
+        let hit = capsule.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.parameter - 0.5).abs() < 1e-5);
+        assert_close(hit.normal, Vec3(0., 0., 1.));
+    }
+
+    #[test]
+    // Test that a zero-length capsule behaves like a sphere.
+    fn test_zero_length_capsule() {
+        let capsule = capsule(Vec3(0., 0., 0.), Vec3(0., 0., 0.));
+        let ray = Ray::new(Vec3(-5., 0., 0.), Vec3(1., 0., 0.));
+        let hit = capsule.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.parameter - 4.5).abs() < 1e-5);
+        assert_close(hit.normal, Vec3(-1., 0., 0.));
+        let ray = Ray::new(Vec3(-5., 0.6, 0.), Vec3(1., 0., 0.));
+        assert!(capsule.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+}
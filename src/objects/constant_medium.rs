@@ -0,0 +1,183 @@
+use rand::prelude::*;
+
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::materials::MaterialExt;
+use crate::objects::Hitable;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+/// A participating medium of constant density, such as fog or smoke.
+///
+/// It fills the volume enclosed by a boundary object, which is expected to
+/// be convex. A ray passing through the medium is scattered at a random
+/// distance, which is exponentially distributed with the density as rate,
+/// or leaves the medium unaffected if that distance exceeds the path length
+/// inside the boundary. The distance is drawn from the generator of the ray,
+/// see `Ray::medium_rng`, such that a ray is always scattered at the same
+/// point.
+///
+/// The phase function material decides how light is scattered, usually it
+/// is `Isotropic`. The normal of a hit is meaningless and arbitrary.
+pub struct ConstantMedium {
+    boundary: Box<dyn Hitable>,
+    density: f32,
+    phase_function: Arc<dyn MaterialExt>,
+}
+
+impl ConstantMedium {
+    /// Fill the volume of `boundary` with a medium of `density` scattering
+    /// light according to `phase_function`.
+    ///
+    /// ```
+    /// use raytracer::objects::constant_medium::ConstantMedium;
+    /// use raytracer::objects::sphere::Sphere;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Isotropic;
+    /// use std::sync::Arc;
+    /// let fog = Arc::new(Isotropic::new(Vec3(0.9, 0.9, 0.9)));
+    /// let boundary = Sphere::new(Vec3(0., 1., 0.), 2., fog.clone());
+    /// let medium = ConstantMedium::new(Box::new(boundary), 0.5, fog);
+    /// assert_eq!(medium.density(), 0.5);
+    /// ```
+    pub fn new(
+        boundary: Box<dyn Hitable>,
+        density: f32,
+        phase_function: Arc<dyn MaterialExt>,
+    ) -> ConstantMedium {
+        ConstantMedium {
+            boundary,
+            density,
+            phase_function,
+        }
+    }
+
+    /// Access the density of the medium.
+    pub fn density(&self) -> f32 {
+        self.density
+    }
+
+    /// Access the phase function material of the medium.
+    pub fn phase_function(&self) -> Arc<dyn MaterialExt> {
+        Arc::clone(&self.phase_function)
+    }
+}
+
+impl Hitable for ConstantMedium {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        // Find where the line through the ray enters and leaves the boundary,
+        // also behind the origin such that rays starting inside are handled.
+        // A ray which only grazes the boundary has no exit and never scatters.
+        let entry = self.boundary.intersect(ray, f32::MIN, f32::MAX)?;
+        let exit = self
+            .boundary
+            .intersect(ray, entry.parameter + 1e-4, f32::MAX)?;
+
+        let t_entry = entry.parameter.max(t_min).max(0.);
+        let t_exit = exit.parameter.min(t_max);
+        if t_entry >= t_exit {
+            return None;
+        }
+
+        let ray_length = ray.direction().length();
+        let distance_inside = (t_exit - t_entry) * ray_length;
+        let mut rng = ray.medium_rng(entry.parameter);
+        // Sample `1 - u` in (0, 1] to avoid the logarithm of zero.
+        let hit_distance = -(1. - rng.gen::<f32>()).ln() / self.density;
+        if hit_distance > distance_inside {
+            return None;
+        }
+
+        let t = t_entry + hit_distance / ray_length;
+        Some(HitRecord {
+            parameter: t,
+            point_at_parameter: ray.point_at_parameter(t),
+            normal: Vec3(1., 0., 0.),
+            u: 0.,
+            v: 0.,
+            tangent: Vec3(0., 0., 0.),
+            bitangent: Vec3(0., 0., 0.),
+            front_face: true,
+            material: self.phase_function.as_ref(),
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.boundary.bounding_box()
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Isotropic;
+    use crate::objects::sphere::Sphere;
+    use rand::rngs::StdRng;
+
+    fn medium(density: f32) -> ConstantMedium {
+        let fog = Arc::new(Isotropic::new(Vec3(0.9, 0.9, 0.9)));
+        let boundary = Sphere::new(Vec3(0., 0., 0.), 1., fog.clone());
+        ConstantMedium::new(Box::new(boundary), density, fog)
+    }
+
+    #[test]
+    // Test that the fraction of scattered rays follows the Beer-Lambert law,
+    // and that a ray is always scattered at the same point.
+    fn test_constant_medium_transmission() {
+        let medium = medium(0.5);
+        let mut rng = StdRng::seed_from_u64(3);
+        let n = 20_000;
+        let mut scattered = 0;
+        for _ in 0..n {
+            let ray = Ray::new(Vec3(0., 0., -5.), Vec3(0., 0., 2.)).with_medium_seed(rng.gen());
+            let parameter = |hit: HitRecord| hit.parameter;
+            assert_eq!(
+                medium.intersect(&ray, 0.001, f32::MAX).map(parameter),
+                medium.intersect(&ray, 0.001, f32::MAX).map(parameter)
+            );
+            if let Some(hit) = medium.intersect(&ray, 0.001, f32::MAX) {
+                assert!(hit.parameter >= 2. && hit.parameter <= 3.);
+                assert!(
+                    (hit.point_at_parameter - ray.point_at_parameter(hit.parameter)).length()
+                        < 1e-6
+                );
+                scattered += 1;
+            }
+        }
+        // The path length through the sphere is 2.
+        let expected = 1. - (-0.5_f32 * 2.).exp();
+        assert!((scattered as f32 / n as f32 - expected).abs() < 0.02);
+    }
+
+    #[test]
+    // Test rays starting inside the boundary and rays limited by `t_max`.
+    fn test_constant_medium_ray_inside() {
+        let medium = medium(1e6);
+        let ray = Ray::new(Vec3(0., 0., 0.5), Vec3(0., 0., 1.));
+        let hit = medium.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!(hit.parameter >= 0.001 && hit.parameter < 0.01);
+        // The dense medium scatters right where the allowed range starts.
+        let ray = Ray::new(Vec3(0., 0., -5.), Vec3(0., 0., 1.));
+        let hit = medium.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.parameter - 4.).abs() < 1e-3);
+        assert!(medium.intersect(&ray, 0.001, 3.9).is_none());
+        // The medium behind the ray is never hit.
+        let ray = Ray::new(Vec3(0., 0., 5.), Vec3(0., 0., 1.));
+        assert!(medium.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    // Test that rays grazing or missing the boundary pass unaffected.
+    fn test_constant_medium_grazing() {
+        let medium = medium(1e6);
+        let ray = Ray::new(Vec3(0., 1., -5.), Vec3(0., 0., 1.));
+        assert!(medium.intersect(&ray, 0.001, f32::MAX).is_none());
+        let ray = Ray::new(Vec3(0., 1.5, -5.), Vec3(0., 0., 1.));
+        assert!(medium.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+}
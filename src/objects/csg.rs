@@ -0,0 +1,320 @@
+//! Constructive solid geometry.
+//!
+//! Two closed objects are combined by a boolean operation on the space they
+//! enclose. Along a ray, each operand is described by the intervals between
+//! the points where the ray enters and leaves it; the surface of the result
+//! is where the boolean combination of being inside the operands changes.
+//!
+//! The operands are expected to be closed with outward pointing normals, such
+//! that a hit is an entry if the ray travels against the normal.
+
+use crate::aabb::surrounding_box;
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::objects::Hitable;
+use crate::ray::Ray;
+use crate::vec3::dot;
+
+// Combine all hits of `left` and `right` along the line through the ray
+// into the hits on the surface of the object where `inside(in_left, in_right)`.
+//
+// If `flip_right` is set, the normals of the surface taken from `right` are
+// inverted, as they point into the result.
+fn combine<'a>(
+    ray: &Ray,
+    left: &'a dyn Hitable,
+    right: &'a dyn Hitable,
+    inside: fn(bool, bool) -> bool,
+    flip_right: bool,
+) -> Vec<HitRecord<'a>> {
+    let mut events: Vec<(HitRecord, bool)> = left
+        .intersect_all(ray)
+        .into_iter()
+        .map(|hit| (hit, false))
+        .chain(right.intersect_all(ray).into_iter().map(|hit| (hit, true)))
+        .collect();
+    events.sort_by(|a, b| a.0.parameter.partial_cmp(&b.0.parameter).unwrap());
+
+    let mut in_left = false;
+    let mut in_right = false;
+    let mut result = Vec::new();
+    for (mut hit, is_right) in events {
+        let was_inside = inside(in_left, in_right);
+        let entering = dot(ray.direction(), &hit.normal) < 0.;
+        if is_right {
+            in_right = entering;
+        } else {
+            in_left = entering;
+        }
+        if inside(in_left, in_right) != was_inside {
+            if is_right && flip_right {
+                hit.normal = -hit.normal;
+                hit.front_face = !hit.front_face;
+            }
+            result.push(hit);
+        }
+    }
+    result
+}
+
+// Return the closest of the sorted `hits` within `t_min` and `t_max`.
+fn closest(hits: Vec<HitRecord<'_>>, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+    hits.into_iter()
+        .find(|hit| hit.parameter > t_min && hit.parameter < t_max)
+}
+
+/// The union of two objects, i.e. the space inside either of them.
+pub struct CsgUnion {
+    left: Box<dyn Hitable>,
+    right: Box<dyn Hitable>,
+}
+
+impl CsgUnion {
+    /// Create the union of the objects `left` and `right`.
+    ///
+    /// ```
+    /// use raytracer::objects::csg::CsgUnion;
+    /// use raytracer::objects::sphere::Sphere;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.8, 0.8)));
+    /// let union = CsgUnion::new(
+    ///     Box::new(Sphere::new(Vec3(-0.5, 0., 0.), 1., material.clone())),
+    ///     Box::new(Sphere::new(Vec3(0.5, 0., 0.), 1., material)),
+    /// );
+    /// ```
+    pub fn new(left: Box<dyn Hitable>, right: Box<dyn Hitable>) -> CsgUnion {
+        CsgUnion { left, right }
+    }
+}
+
+impl Hitable for CsgUnion {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        closest(self.intersect_all(ray), t_min, t_max)
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        combine(ray, &*self.left, &*self.right, |l, r| l || r, false)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(surrounding_box(
+            &self.left.bounding_box()?,
+            &self.right.bounding_box()?,
+        ))
+    }
+}
+
+/// The intersection of two objects, i.e. the space inside both of them.
+pub struct CsgIntersection {
+    left: Box<dyn Hitable>,
+    right: Box<dyn Hitable>,
+}
+
+impl CsgIntersection {
+    /// Create the intersection of the objects `left` and `right`.
+    ///
+    /// ```
+    /// use raytracer::objects::csg::CsgIntersection;
+    /// use raytracer::objects::sphere::Sphere;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.8, 0.8)));
+    /// let lens = CsgIntersection::new(
+    ///     Box::new(Sphere::new(Vec3(-0.5, 0., 0.), 1., material.clone())),
+    ///     Box::new(Sphere::new(Vec3(0.5, 0., 0.), 1., material)),
+    /// );
+    /// ```
+    pub fn new(left: Box<dyn Hitable>, right: Box<dyn Hitable>) -> CsgIntersection {
+        CsgIntersection { left, right }
+    }
+}
+
+impl Hitable for CsgIntersection {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        closest(self.intersect_all(ray), t_min, t_max)
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        combine(ray, &*self.left, &*self.right, |l, r| l && r, false)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // The intersection lies within both operands.
+        self.left
+            .bounding_box()
+            .or_else(|| self.right.bounding_box())
+    }
+}
+
+/// The difference of two objects, i.e. the space inside the first but
+/// outside the second one.
+pub struct CsgDifference {
+    left: Box<dyn Hitable>,
+    right: Box<dyn Hitable>,
+}
+
+impl CsgDifference {
+    /// Create the difference of the objects `left` and `right`, i.e. carve
+    /// `right` out of `left`.
+    ///
+    /// ```
+    /// use raytracer::objects::csg::CsgDifference;
+    /// use raytracer::objects::cuboid::Cuboid;
+    /// use raytracer::objects::sphere::Sphere;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.8, 0.8)));
+    /// let carved = CsgDifference::new(
+    ///     Box::new(Cuboid::new(Vec3(-1., -1., -1.), Vec3(1., 1., 1.), material.clone())),
+    ///     Box::new(Sphere::new(Vec3(0., 1., 0.), 0.5, material)),
+    /// );
+    /// ```
+    pub fn new(left: Box<dyn Hitable>, right: Box<dyn Hitable>) -> CsgDifference {
+        CsgDifference { left, right }
+    }
+}
+
+impl Hitable for CsgDifference {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        closest(self.intersect_all(ray), t_min, t_max)
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        combine(ray, &*self.left, &*self.right, |l, r| l && !r, true)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.left.bounding_box()
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+    use crate::materials::MaterialExt;
+    use crate::objects::cuboid::Cuboid;
+    use crate::objects::sphere::Sphere;
+    use crate::vec3::Vec3;
+    use std::sync::Arc;
+
+    fn material() -> Arc<dyn MaterialExt> {
+        Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)))
+    }
+
+    // A unit sphere at the origin with the octants at positive x and y removed.
+    fn notched_sphere() -> CsgDifference {
+        CsgDifference::new(
+            Box::new(Sphere::new(Vec3(0., 0., 0.), 1., material())),
+            Box::new(Cuboid::new(Vec3(0., 0., -2.), Vec3(2., 2., 2.), material())),
+        )
+    }
+
+    #[test]
+    // Test a ray entering the notch through the carved face.
+    fn test_difference_carved_face() {
+        let notched = notched_sphere();
+        let ray = Ray::new(Vec3(5., 0.5, 0.), Vec3(-1., 0., 0.));
+        let hit = notched.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.parameter, 5.);
+        assert_eq!(hit.point_at_parameter, Vec3(0., 0.5, 0.));
+        // The normal of the box face is flipped to point out of the result.
+        assert_eq!(hit.normal, Vec3(1., 0., 0.));
+        // Beside the notch the sphere surface is hit from above, inside it the carved face.
+        let ray = Ray::new(Vec3(-0.5, 5., 0.), Vec3(0., -1., 0.));
+        let hit = notched.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.point_at_parameter.y() - 0.75_f32.sqrt()).abs() < 1e-5);
+        let ray = Ray::new(Vec3(0.5, 5., 0.), Vec3(0., -1., 0.));
+        let hit = notched.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.point_at_parameter, Vec3(0.5, 0., 0.));
+        assert_eq!(hit.normal, Vec3(0., 1., 0.));
+    }
+
+    #[test]
+    // Test rays missing the notch and rays passing through the removed part.
+    fn test_difference_outside_notch() {
+        let notched = notched_sphere();
+        // The sphere surface is untouched below the notch.
+        let ray = Ray::new(Vec3(5., -0.5, 0.), Vec3(-1., 0., 0.));
+        let hit = notched.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.point_at_parameter.x() - 0.75_f32.sqrt()).abs() < 1e-5);
+        assert!((hit.normal - hit.point_at_parameter).length() < 1e-5);
+        // A ray through the removed octants only hits nothing.
+        let ray = Ray::new(Vec3(5., 0.5, 0.), Vec3(0., 0., 1.));
+        assert!(notched.intersect(&ray, 0.001, f32::MAX).is_none());
+        let ray = Ray::new(Vec3(0.5, 0.5, -5.), Vec3(0., 0., 1.));
+        assert!(notched.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    // Test the union and intersection of two overlapping spheres.
+    fn test_union_and_intersection() {
+        let left = || Box::new(Sphere::new(Vec3(-0.5, 0., 0.), 1., material()));
+        let right = || Box::new(Sphere::new(Vec3(0.5, 0., 0.), 1., material()));
+        let ray = Ray::new(Vec3(-5., 0., 0.), Vec3(1., 0., 0.));
+
+        let union = CsgUnion::new(left(), right());
+        let hits = union.intersect_all(&ray);
+        let parameters: Vec<f32> = hits.iter().map(|hit| hit.parameter).collect();
+        assert_eq!(parameters, vec![3.5, 6.5]);
+
+        let lens = CsgIntersection::new(left(), right());
+        let hits = lens.intersect_all(&ray);
+        let parameters: Vec<f32> = hits.iter().map(|hit| hit.parameter).collect();
+        assert_eq!(parameters, vec![4.5, 5.5]);
+        assert_eq!(hits[0].normal, Vec3(-1., 0., 0.));
+        // Starting inside the lens, its exit is the closest hit.
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(1., 0., 0.));
+        let hit = lens.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.parameter, 0.5);
+    }
+
+    #[test]
+    // Test that CSG objects compose and that the default `intersect_all` agrees.
+    fn test_nested_csg() {
+        let notched = notched_sphere();
+        let nested = CsgUnion::new(
+            Box::new(notched_sphere()),
+            Box::new(Sphere::new(Vec3(5., 0., 0.), 1., material())),
+        );
+        let ray = Ray::new(Vec3(10., 0.5, 0.), Vec3(-1., 0., 0.));
+        let parameters: Vec<f32> = nested
+            .intersect_all(&ray)
+            .iter()
+            .map(|hit| hit.parameter)
+            .collect();
+        assert_eq!(parameters.len(), 4);
+        assert_eq!(parameters[2], 10.);
+
+        // Walk along the ray with `intersect` only.
+        struct Walk<'a>(&'a dyn Hitable);
+        impl<'a> Hitable for Walk<'a> {
+            fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+                self.0.intersect(ray, t_min, t_max)
+            }
+
+            fn bounding_box(&self) -> Option<Aabb> {
+                self.0.bounding_box()
+            }
+        }
+        let walked: Vec<f32> = Walk(&notched)
+            .intersect_all(&ray)
+            .iter()
+            .map(|hit| hit.parameter)
+            .collect();
+        let direct: Vec<f32> = notched
+            .intersect_all(&ray)
+            .iter()
+            .map(|hit| hit.parameter)
+            .collect();
+        assert_eq!(walked, direct);
+    }
+}
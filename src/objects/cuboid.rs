@@ -0,0 +1,199 @@
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::materials::MaterialExt;
+use crate::objects::Hitable;
+use crate::ray::Ray;
+use crate::vec3::dot;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+/// An axis-aligned box in three-dimensional space.
+///
+/// It is characterized by:
+/// - The corner with the smallest coordinates.
+/// - The corner with the largest coordinates.
+/// - A pointer to the material that it is made of.
+pub struct Cuboid {
+    min: Vec3,
+    max: Vec3,
+    material: Arc<dyn MaterialExt>,
+}
+
+impl Cuboid {
+    /// Create a `Cuboid` spanned by the two opposite corners `a` and `b`.
+    ///
+    /// The corners may be given in any order.
+    ///
+    /// ```
+    /// use raytracer::objects::cuboid::Cuboid;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.8, 0.8)));
+    /// let cuboid = Cuboid::new(Vec3(1., 0., 1.), Vec3(0., 1., 0.), material);
+    /// assert_eq!(cuboid.min(), &Vec3(0., 0., 0.));
+    /// assert_eq!(cuboid.max(), &Vec3(1., 1., 1.));
+    /// ```
+    pub fn new(a: Vec3, b: Vec3, material: Arc<dyn MaterialExt>) -> Cuboid {
+        Cuboid {
+            min: Vec3(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z())),
+            max: Vec3(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z())),
+            material,
+        }
+    }
+
+    /// Access the corner of a `Cuboid` with the smallest coordinates.
+    pub fn min(&self) -> &Vec3 {
+        &self.min
+    }
+
+    /// Access the corner of a `Cuboid` with the largest coordinates.
+    pub fn max(&self) -> &Vec3 {
+        &self.max
+    }
+
+    /// Access the `material` a `Cuboid` is made of.
+    pub fn material(&self) -> Arc<dyn MaterialExt> {
+        Arc::clone(&self.material)
+    }
+
+    // Return the parameters and normals where the line through the ray
+    // enters and leaves the box, using the slab method.
+    fn slabs(&self, ray: &Ray) -> Option<[(f32, Vec3); 2]> {
+        let origin = [ray.origin().x(), ray.origin().y(), ray.origin().z()];
+        let direction = [
+            ray.direction().x(),
+            ray.direction().y(),
+            ray.direction().z(),
+        ];
+        let min = [self.min.x(), self.min.y(), self.min.z()];
+        let max = [self.max.x(), self.max.y(), self.max.z()];
+
+        let mut enter = (f32::NEG_INFINITY, Vec3(0., 0., 0.));
+        let mut exit = (f32::INFINITY, Vec3(0., 0., 0.));
+        for axis in 0..3 {
+            let mut unit = [0.; 3];
+            unit[axis] = 1.;
+            let unit = Vec3(unit[0], unit[1], unit[2]);
+            if direction[axis] == 0. {
+                // Parallel to the slab, the ray is either always or never inside.
+                if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = (min[axis] - origin[axis]) / direction[axis];
+            let t1 = (max[axis] - origin[axis]) / direction[axis];
+            // The ray enters through the face it is heading towards.
+            let (near, far, near_normal) = if t0 < t1 {
+                (t0, t1, -unit)
+            } else {
+                (t1, t0, unit)
+            };
+            if near > enter.0 {
+                enter = (near, near_normal);
+            }
+            if far < exit.0 {
+                exit = (far, -near_normal);
+            }
+        }
+        if enter.0 >= exit.0 {
+            return None;
+        }
+        Some([enter, exit])
+    }
+}
+
+impl Hitable for Cuboid {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let [enter, exit] = self.slabs(ray)?;
+        let (t, normal) = [enter, exit]
+            .iter()
+            .cloned()
+            .find(|&(t, _)| t > t_min && t < t_max)?;
+        Some(HitRecord {
+            parameter: t,
+            point_at_parameter: ray.point_at_parameter(t),
+            normal,
+            u: 0.,
+            v: 0.,
+            tangent: Vec3(0., 0., 0.),
+            bitangent: Vec3(0., 0., 0.),
+            front_face: dot(ray.direction(), &normal) < 0.,
+            material: self.material.as_ref(),
+        })
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        match self.slabs(ray) {
+            Some(bounds) => bounds
+                .iter()
+                .map(|&(t, normal)| HitRecord {
+                    parameter: t,
+                    point_at_parameter: ray.point_at_parameter(t),
+                    normal,
+                    u: 0.,
+                    v: 0.,
+                    tangent: Vec3(0., 0., 0.),
+                    bitangent: Vec3(0., 0., 0.),
+                    front_face: dot(ray.direction(), &normal) < 0.,
+                    material: self.material.as_ref(),
+                })
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(self.min, self.max))
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+
+    fn cuboid() -> Cuboid {
+        Cuboid::new(
+            Vec3(-1., -1., -1.),
+            Vec3(1., 2., 1.),
+            Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))),
+        )
+    }
+
+    #[test]
+    // Test hits on the faces of a box from outside and inside.
+    fn test_cuboid_hits() {
+        let cuboid = cuboid();
+        let ray = Ray::new(Vec3(0., 5., 0.), Vec3(0., -1., 0.));
+        let hit = cuboid.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.parameter, 3.);
+        assert_eq!(hit.normal, Vec3(0., 1., 0.));
+        // From the inside the exit face is hit, with the outward normal.
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(-1., 0., 0.));
+        let hit = cuboid.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.parameter, 1.);
+        assert_eq!(hit.normal, Vec3(-1., 0., 0.));
+        // Passing beside the box.
+        let ray = Ray::new(Vec3(0., 5., 1.5), Vec3(0., -1., 0.));
+        assert!(cuboid.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    // Test that all intersections are reported, including those behind the origin.
+    fn test_cuboid_intersect_all() {
+        let cuboid = cuboid();
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., 1.));
+        let hits = cuboid.intersect_all(&ray);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].parameter, -1.);
+        assert_eq!(hits[0].normal, Vec3(0., 0., -1.));
+        assert_eq!(hits[1].parameter, 1.);
+        assert_eq!(hits[1].normal, Vec3(0., 0., 1.));
+    }
+}
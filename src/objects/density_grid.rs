@@ -0,0 +1,427 @@
+use rand::prelude::*;
+
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::materials::MaterialExt;
+use crate::objects::Hitable;
+use crate::objects::ObjectError;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+/// A participating medium whose density varies in space, such as smoke.
+///
+/// The densities are given on a regular grid of cells filling an
+/// axis-aligned box. Each value is located at the center of its cell and
+/// the density in between is interpolated trilinearly, where the values
+/// outside of the grid are taken to be zero. The medium thus thins out
+/// within half a cell of the boundary of the box, which is only crossed by
+/// rays but never scattered from outside.
+///
+/// Rays are scattered by delta tracking: tentative collisions are sampled
+/// as in a medium of the maximum density, and each of them is accepted
+/// with the ratio of the actual density to the maximum. This yields the
+/// exact distribution of scattering distances without integrating the
+/// density along the ray.
+///
+/// Like for a `ConstantMedium`, the phase function material decides how
+/// light is scattered, and the normal of a hit is meaningless and arbitrary.
+pub struct DensityGrid {
+    min: Vec3,
+    max: Vec3,
+    resolution: (usize, usize, usize),
+    densities: Vec<f32>,
+    max_density: f32,
+    phase_function: Arc<dyn MaterialExt>,
+}
+
+impl DensityGrid {
+    /// Create a `DensityGrid` filling the box between `min` and `max`.
+    ///
+    /// The `densities` of the `resolution.0 * resolution.1 * resolution.2`
+    /// cells are ordered by x first, then y, then z. An error is returned if
+    /// the box is empty, if the resolution is zero along an axis, if the
+    /// number of densities does not match, or if a density is negative or
+    /// not finite.
+    ///
+    /// ```
+    /// use raytracer::objects::density_grid::DensityGrid;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Isotropic;
+    /// use std::sync::Arc;
+    /// let smoke = Arc::new(Isotropic::new(Vec3(0.7, 0.7, 0.7)));
+    /// // Two cells, the upper one twice as dense as the lower one.
+    /// let grid = DensityGrid::new(
+    ///     Vec3(0., 0., 0.),
+    ///     Vec3(1., 2., 1.),
+    ///     (1, 2, 1),
+    ///     vec![1., 2.],
+    ///     smoke.clone(),
+    /// )
+    /// .unwrap();
+    /// assert_eq!(grid.density_at(&Vec3(0.5, 0.5, 0.5)), 1.);
+    /// assert_eq!(grid.density_at(&Vec3(0.5, 1., 0.5)), 1.5);
+    /// let invalid = DensityGrid::new(Vec3(0., 0., 0.), Vec3(1., 1., 1.), (2, 2, 2), vec![1.], smoke);
+    /// assert!(invalid.is_err());
+    /// ```
+    pub fn new(
+        min: Vec3,
+        max: Vec3,
+        resolution: (usize, usize, usize),
+        densities: Vec<f32>,
+        phase_function: Arc<dyn MaterialExt>,
+    ) -> Result<DensityGrid, ObjectError> {
+        // Written such that NaN coordinates are rejected as well.
+        if !(min.x() < max.x() && min.y() < max.y() && min.z() < max.z()) {
+            return Err(ObjectError::InvalidBounds { min, max });
+        }
+        let (nx, ny, nz) = resolution;
+        if nx == 0 || ny == 0 || nz == 0 {
+            return Err(ObjectError::InvalidGridResolution {
+                x: nx,
+                y: ny,
+                z: nz,
+            });
+        }
+        if densities.len() != nx * ny * nz {
+            return Err(ObjectError::GridValueCount {
+                cells: nx * ny * nz,
+                values: densities.len(),
+            });
+        }
+        if let Some((index, &density)) = densities
+            .iter()
+            .enumerate()
+            .find(|(_, density)| !(density.is_finite() && **density >= 0.))
+        {
+            return Err(ObjectError::InvalidDensity { index, density });
+        }
+        // Interpolated densities never exceed the largest value on the grid.
+        let max_density = densities.iter().cloned().fold(0., f32::max);
+        Ok(DensityGrid {
+            min,
+            max,
+            resolution,
+            densities,
+            max_density,
+            phase_function,
+        })
+    }
+
+    /// Create a `DensityGrid` by sampling the function `density` at the
+    /// centers of the cells, e.g. to bake procedural noise.
+    ///
+    /// The same errors as for `DensityGrid::new` are returned.
+    ///
+    /// ```
+    /// use raytracer::objects::density_grid::DensityGrid;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Isotropic;
+    /// use std::sync::Arc;
+    /// let smoke = Arc::new(Isotropic::new(Vec3(0.7, 0.7, 0.7)));
+    /// // A plume thinning out with height.
+    /// let plume = DensityGrid::from_fn(
+    ///     Vec3(-1., 0., -1.),
+    ///     Vec3(1., 4., 1.),
+    ///     (16, 32, 16),
+    ///     |p| (2. - p.y() / 2.).max(0.),
+    ///     smoke,
+    /// )
+    /// .unwrap();
+    /// assert!(plume.density_at(&Vec3(0., 1., 0.)) > plume.density_at(&Vec3(0., 3., 0.)));
+    /// ```
+    pub fn from_fn(
+        min: Vec3,
+        max: Vec3,
+        resolution: (usize, usize, usize),
+        density: impl Fn(Vec3) -> f32,
+        phase_function: Arc<dyn MaterialExt>,
+    ) -> Result<DensityGrid, ObjectError> {
+        let (nx, ny, nz) = resolution;
+        let size = max - min;
+        let mut densities = Vec::with_capacity(nx * ny * nz);
+        for k in 0..nz {
+            for j in 0..ny {
+                for i in 0..nx {
+                    densities.push(density(Vec3(
+                        min.x() + size.x() * (i as f32 + 0.5) / nx as f32,
+                        min.y() + size.y() * (j as f32 + 0.5) / ny as f32,
+                        min.z() + size.z() * (k as f32 + 0.5) / nz as f32,
+                    )));
+                }
+            }
+        }
+        DensityGrid::new(min, max, resolution, densities, phase_function)
+    }
+
+    /// Access the minimum corner of the box filled by a `DensityGrid`.
+    pub fn min(&self) -> &Vec3 {
+        &self.min
+    }
+
+    /// Access the maximum corner of the box filled by a `DensityGrid`.
+    pub fn max(&self) -> &Vec3 {
+        &self.max
+    }
+
+    /// Access the number of cells of a `DensityGrid` along each axis.
+    pub fn resolution(&self) -> (usize, usize, usize) {
+        self.resolution
+    }
+
+    /// Access the phase function material of the medium.
+    pub fn phase_function(&self) -> Arc<dyn MaterialExt> {
+        Arc::clone(&self.phase_function)
+    }
+
+    /// Return the interpolated density at the point `p`, zero far outside the box.
+    pub fn density_at(&self, p: &Vec3) -> f32 {
+        let (nx, ny, nz) = self.resolution;
+        // Continuous grid coordinates, in which the cell centers are integers.
+        let grid = |p: f32, min: f32, max: f32, n: usize| (p - min) / (max - min) * n as f32 - 0.5;
+        let gx = grid(p.x(), self.min.x(), self.max.x(), nx);
+        let gy = grid(p.y(), self.min.y(), self.max.y(), ny);
+        let gz = grid(p.z(), self.min.z(), self.max.z(), nz);
+        let (x0, y0, z0) = (gx.floor(), gy.floor(), gz.floor());
+        let (fx, fy, fz) = (gx - x0, gy - y0, gz - z0);
+
+        let mut density = 0.;
+        for (dz, wz) in [(0, 1. - fz), (1, fz)] {
+            for (dy, wy) in [(0, 1. - fy), (1, fy)] {
+                for (dx, wx) in [(0, 1. - fx), (1, fx)] {
+                    let weight = wx * wy * wz;
+                    if weight > 0. {
+                        density +=
+                            weight * self.value(x0 as i64 + dx, y0 as i64 + dy, z0 as i64 + dz);
+                    }
+                }
+            }
+        }
+        density
+    }
+
+    /// Sample where `ray` is scattered between `t_min` and `t_max` using the
+    /// random numbers of `rng`.
+    ///
+    /// This is what `intersect` does with the generator of the ray, see
+    /// `Ray::medium_rng`.
+    pub fn sample_scatter<R: Rng + ?Sized>(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        rng: &mut R,
+    ) -> Option<HitRecord<'_>> {
+        if self.max_density == 0. {
+            return None;
+        }
+        let (t_entry, t_exit) = self.clip(ray)?;
+        let t_entry = t_entry.max(t_min).max(0.);
+        let t_exit = t_exit.min(t_max);
+        if t_entry >= t_exit {
+            return None;
+        }
+
+        let rate = self.max_density * ray.direction().length();
+        let mut t = t_entry;
+        loop {
+            // Sample `1 - u` in (0, 1] to avoid the logarithm of zero.
+            t -= (1. - rng.gen::<f32>()).ln() / rate;
+            if t >= t_exit {
+                return None;
+            }
+            let point = ray.point_at_parameter(t);
+            if rng.gen::<f32>() * self.max_density < self.density_at(&point) {
+                return Some(HitRecord {
+                    parameter: t,
+                    point_at_parameter: point,
+                    normal: Vec3(1., 0., 0.),
+                    u: 0.,
+                    v: 0.,
+                    tangent: Vec3(0., 0., 0.),
+                    bitangent: Vec3(0., 0., 0.),
+                    front_face: true,
+                    material: self.phase_function.as_ref(),
+                });
+            }
+        }
+    }
+
+    // The density of the cell at the given indices, zero outside of the grid.
+    fn value(&self, i: i64, j: i64, k: i64) -> f32 {
+        let (nx, ny, nz) = self.resolution;
+        if i < 0 || j < 0 || k < 0 || i >= nx as i64 || j >= ny as i64 || k >= nz as i64 {
+            return 0.;
+        }
+        self.densities[(k as usize * ny + j as usize) * nx + i as usize]
+    }
+
+    // Return the parameters where the line through the ray enters and leaves
+    // the box, by intersecting the intervals between the pairs of planes.
+    fn clip(&self, ray: &Ray) -> Option<(f32, f32)> {
+        let origin = ray.origin();
+        let direction = ray.direction();
+        let mut t_entry = f32::MIN;
+        let mut t_exit = f32::MAX;
+        for (o, d, min, max) in [
+            (origin.x(), direction.x(), self.min.x(), self.max.x()),
+            (origin.y(), direction.y(), self.min.y(), self.max.y()),
+            (origin.z(), direction.z(), self.min.z(), self.max.z()),
+        ] {
+            if d == 0. {
+                // Parallel to the planes, either always or never between them.
+                if o < min || o > max {
+                    return None;
+                }
+                continue;
+            }
+            let (t0, t1) = ((min - o) / d, (max - o) / d);
+            t_entry = t_entry.max(t0.min(t1));
+            t_exit = t_exit.min(t0.max(t1));
+        }
+        if t_entry < t_exit {
+            Some((t_entry, t_exit))
+        } else {
+            None
+        }
+    }
+}
+
+impl Hitable for DensityGrid {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let (t_entry, _) = self.clip(ray)?;
+        self.sample_scatter(ray, t_min, t_max, &mut ray.medium_rng(t_entry))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(self.min, self.max))
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Isotropic;
+    use rand::rngs::StdRng;
+
+    fn smoke() -> Arc<dyn MaterialExt> {
+        Arc::new(Isotropic::new(Vec3(0.9, 0.9, 0.9)))
+    }
+
+    fn uniform(density: f32) -> DensityGrid {
+        DensityGrid::from_fn(
+            Vec3(-100., -100., -100.),
+            Vec3(100., 100., 100.),
+            (4, 4, 4),
+            |_| density,
+            smoke(),
+        )
+        .unwrap()
+    }
+
+    // The mean distance travelled from the center before being scattered.
+    fn mean_free_path(grid: &DensityGrid, rng: &mut StdRng) -> f32 {
+        let n = 20_000;
+        let mut total = 0.;
+        for _ in 0..n {
+            let ray = Ray::new(Vec3(0., 0., 0.), Vec3(1., 0., 0.));
+            let hit = grid.sample_scatter(&ray, 0., f32::MAX, rng).unwrap();
+            total += hit.parameter;
+        }
+        total / n as f32
+    }
+
+    #[test]
+    // Test that doubling the density halves the mean free path.
+    fn test_density_grid_mean_free_path() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let thin = mean_free_path(&uniform(1.), &mut rng);
+        let dense = mean_free_path(&uniform(2.), &mut rng);
+        assert!((thin - 1.).abs() < 0.03);
+        assert!((thin / dense - 2.).abs() < 0.1);
+    }
+
+    #[test]
+    // Test that delta tracking in a varying medium matches the optical depth.
+    fn test_density_grid_transmission() {
+        // A single cell, whose density falls off from its center.
+        let grid = DensityGrid::new(
+            Vec3(0., 0., 0.),
+            Vec3(1., 1., 1.),
+            (1, 1, 1),
+            vec![1.],
+            smoke(),
+        )
+        .unwrap();
+        // Between the centers of two cells the density is interpolated, and
+        // it fades out towards the boundary of the box.
+        assert_eq!(grid.density_at(&Vec3(0.5, 0.5, 0.5)), 1.);
+        assert_eq!(grid.density_at(&Vec3(0., 0.5, 0.5)), 0.5);
+        assert_eq!(grid.density_at(&Vec3(0.25, 0.25, 0.5)), 0.5625);
+        assert_eq!(grid.density_at(&Vec3(-1., 0.5, 0.5)), 0.);
+
+        let mut rng = StdRng::seed_from_u64(5);
+        let ray = Ray::new(Vec3(-1., 0.5, 0.5), Vec3(1., 0., 0.));
+        let n = 20_000;
+        let scattered = (0..n)
+            .filter(|_| {
+                grid.sample_scatter(&ray, 0.001, f32::MAX, &mut rng)
+                    .is_some()
+            })
+            .count();
+        // Along the ray the density falls from 1 at the center to 0.5 at the
+        // faces of the box, an optical depth of 0.75.
+        let expected = 1. - (-0.75_f32).exp();
+        assert!((scattered as f32 / n as f32 - expected).abs() < 0.02);
+    }
+
+    #[test]
+    // Test that rays missing the box or an empty medium are never scattered.
+    fn test_density_grid_misses() {
+        let grid = uniform(100.);
+        let ray = Ray::new(Vec3(0., 101., 0.), Vec3(1., 0., 0.));
+        assert!(grid.intersect(&ray, 0.001, f32::MAX).is_none());
+        let ray = Ray::new(Vec3(200., 0., 0.), Vec3(1., 0., 0.));
+        assert!(grid.intersect(&ray, 0.001, f32::MAX).is_none());
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(1., 0., 0.));
+        assert!(grid.intersect(&ray, 0.001, f32::MAX).is_some());
+        assert!(uniform(0.).intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    // Test that invalid grids are rejected.
+    fn test_density_grid_invalid() {
+        let create = |max, resolution, densities| {
+            DensityGrid::new(Vec3(0., 0., 0.), max, resolution, densities, smoke()).err()
+        };
+        assert_eq!(
+            create(Vec3(1., 0., 1.), (1, 1, 1), vec![1.]),
+            Some(ObjectError::InvalidBounds {
+                min: Vec3(0., 0., 0.),
+                max: Vec3(1., 0., 1.)
+            })
+        );
+        assert_eq!(
+            create(Vec3(1., 1., 1.), (1, 0, 1), vec![]),
+            Some(ObjectError::InvalidGridResolution { x: 1, y: 0, z: 1 })
+        );
+        assert_eq!(
+            create(Vec3(1., 1., 1.), (2, 1, 1), vec![1.]),
+            Some(ObjectError::GridValueCount {
+                cells: 2,
+                values: 1
+            })
+        );
+        assert_eq!(
+            create(Vec3(1., 1., 1.), (2, 1, 1), vec![1., -1.]),
+            Some(ObjectError::InvalidDensity {
+                index: 1,
+                density: -1.
+            })
+        );
+    }
+}
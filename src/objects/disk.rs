@@ -0,0 +1,251 @@
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::materials::MaterialExt;
+use crate::objects::Hitable;
+use crate::objects::ObjectError;
+use crate::ray::Ray;
+use crate::vec3::dot;
+use crate::vec3::unit_vector;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+/// A flat disk in three-dimensional space.
+///
+/// It is characterized by:
+/// - The coordinates of its center.
+/// - The normal of the plane it lies in.
+/// - Its (outer) radius.
+/// - An optional inner radius, which turns the disk into an annulus.
+/// - A pointer to the material that it is made of.
+pub struct Disk {
+    center: Vec3,
+    normal: Vec3,
+    radius: f32,
+    inner_radius: f32,
+    material: Arc<dyn MaterialExt>,
+}
+
+impl Disk {
+    /// Create a `Disk` by specifying its `center`, `normal`, `radius` and `Material`.
+    ///
+    /// The `normal` does not need to be normalized.
+    ///
+    /// ```
+    /// use raytracer::objects::disk::Disk;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.8, 0.8)));
+    /// let disk = Disk::new(Vec3(0., 0., 0.), Vec3(0., 1., 0.), 2., material);
+    /// ```
+    pub fn new(center: Vec3, normal: Vec3, radius: f32, material: Arc<dyn MaterialExt>) -> Disk {
+        Disk {
+            center,
+            normal: unit_vector(&normal),
+            radius,
+            inner_radius: 0.,
+            material,
+        }
+    }
+
+    /// Create an annulus, i.e. a `Disk` with a hole of radius `inner_radius`.
+    ///
+    /// An error is returned unless `0 <= inner_radius < outer_radius`.
+    ///
+    /// ```
+    /// # use raytracer::objects::disk::Disk;
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::materials::Lambertian;
+    /// # use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.8, 0.8)));
+    /// let ring = Disk::annulus(Vec3(0., 0., 0.), Vec3(0., 1., 0.), 1., 2., material.clone());
+    /// assert!(ring.is_ok());
+    /// let invalid = Disk::annulus(Vec3(0., 0., 0.), Vec3(0., 1., 0.), 2., 2., material);
+    /// assert!(invalid.is_err());
+    /// ```
+    pub fn annulus(
+        center: Vec3,
+        normal: Vec3,
+        inner_radius: f32,
+        outer_radius: f32,
+        material: Arc<dyn MaterialExt>,
+    ) -> Result<Disk, ObjectError> {
+        // Written such that NaN radii are rejected as well.
+        if !(inner_radius >= 0. && inner_radius < outer_radius) {
+            return Err(ObjectError::InvalidAnnulus {
+                inner_radius,
+                outer_radius,
+            });
+        }
+        Ok(Disk {
+            inner_radius,
+            ..Disk::new(center, normal, outer_radius, material)
+        })
+    }
+
+    /// Access the center of a `Disk`.
+    ///
+    /// ```
+    /// # use raytracer::objects::disk::Disk;
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::materials::Lambertian;
+    /// # use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.8, 0.8)));
+    /// let disk = Disk::new(Vec3(0., 1., 0.), Vec3(0., 1., 0.), 2., material);
+    /// assert_eq!(disk.center(), &Vec3(0., 1., 0.));
+    /// ```
+    pub fn center(&self) -> &Vec3 {
+        &self.center
+    }
+
+    /// Access the normalized normal of a `Disk`.
+    ///
+    /// ```
+    /// # use raytracer::objects::disk::Disk;
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::materials::Lambertian;
+    /// # use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.8, 0.8)));
+    /// let disk = Disk::new(Vec3(0., 1., 0.), Vec3(0., 5., 0.), 2., material);
+    /// assert_eq!(disk.normal(), &Vec3(0., 1., 0.));
+    /// ```
+    pub fn normal(&self) -> &Vec3 {
+        &self.normal
+    }
+
+    /// Access the (outer) radius of a `Disk`.
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// Access the inner radius of a `Disk`, which is zero unless it is an annulus.
+    pub fn inner_radius(&self) -> f32 {
+        self.inner_radius
+    }
+
+    /// Access the `material` a `Disk` is made of.
+    pub fn material(&self) -> Arc<dyn MaterialExt> {
+        Arc::clone(&self.material)
+    }
+}
+
+impl Hitable for Disk {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let denominator = dot(ray.direction(), &self.normal);
+        // Rays parallel to the plane never hit the disk.
+        if denominator == 0. {
+            return None;
+        }
+        let t = dot(&(self.center - *ray.origin()), &self.normal) / denominator;
+        if !(t > t_min && t < t_max) {
+            return None;
+        }
+        let point = ray.point_at_parameter(t);
+        let distance_squared = (point - self.center).squared_length();
+        if distance_squared > self.radius.powi(2) || distance_squared < self.inner_radius.powi(2) {
+            return None;
+        }
+        Some(HitRecord {
+            parameter: t,
+            point_at_parameter: point,
+            normal: self.normal,
+            u: 0.,
+            v: 0.,
+            tangent: Vec3(0., 0., 0.),
+            bitangent: Vec3(0., 0., 0.),
+            front_face: dot(ray.direction(), &self.normal) < 0.,
+            material: self.material.as_ref(),
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // A circle extends along each axis by its radius times the sine of the
+        // angle between that axis and the normal.
+        let extent = |n: f32| self.radius * (1. - n * n).max(0.).sqrt();
+        let extent = Vec3(
+            extent(self.normal.x()),
+            extent(self.normal.y()),
+            extent(self.normal.z()),
+        );
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+
+    fn material() -> Arc<dyn MaterialExt> {
+        Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)))
+    }
+
+    #[test]
+    // Test a ray hitting a disk head-on.
+    fn test_disk_hit() {
+        let disk = Disk::new(Vec3(0., 0., 0.), Vec3(0., 1., 0.), 1., material());
+        let ray = Ray::new(Vec3(0.5, 2., 0.), Vec3(0., -1., 0.));
+        let hit = disk.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.parameter, 2.);
+        assert_eq!(hit.point_at_parameter, Vec3(0.5, 0., 0.));
+        assert_eq!(hit.normal, Vec3(0., 1., 0.));
+        // Outside the radius.
+        let ray = Ray::new(Vec3(1.5, 2., 0.), Vec3(0., -1., 0.));
+        assert!(disk.intersect(&ray, 0.001, f32::MAX).is_none());
+        // Outside the parameter range.
+        let ray = Ray::new(Vec3(0.5, 2., 0.), Vec3(0., -1., 0.));
+        assert!(disk.intersect(&ray, 0.001, 1.).is_none());
+    }
+
+    #[test]
+    // Test hits on the ring and misses in the hole of an annulus.
+    fn test_annulus() {
+        let ring = Disk::annulus(Vec3(0., 0., 0.), Vec3(0., 0., 1.), 1., 2., material()).unwrap();
+        let ray = Ray::new(Vec3(0., 1.5, -3.), Vec3(0., 0., 1.));
+        let hit = ring.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.point_at_parameter, Vec3(0., 1.5, 0.));
+        let ray = Ray::new(Vec3(0., 0.5, -3.), Vec3(0., 0., 1.));
+        assert!(ring.intersect(&ray, 0.001, f32::MAX).is_none());
+        let ray = Ray::new(Vec3(0., 2.5, -3.), Vec3(0., 0., 1.));
+        assert!(ring.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    // Test that rays parallel to the disk plane miss, even within the plane.
+    fn test_disk_parallel_ray() {
+        let disk = Disk::new(Vec3(0., 0., 0.), Vec3(0., 1., 0.), 1., material());
+        let ray = Ray::new(Vec3(-5., 0.5, 0.), Vec3(1., 0., 0.));
+        assert!(disk.intersect(&ray, 0.001, f32::MAX).is_none());
+        let ray = Ray::new(Vec3(-5., 0., 0.), Vec3(1., 0., 0.));
+        assert!(disk.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    // Test that invalid annulus radii are rejected.
+    fn test_invalid_annulus() {
+        let center = Vec3(0., 0., 0.);
+        let normal = Vec3(0., 1., 0.);
+        assert!(Disk::annulus(center, normal, 2., 1., material()).is_err());
+        assert!(Disk::annulus(center, normal, 1., 1., material()).is_err());
+        assert!(Disk::annulus(center, normal, -1., 1., material()).is_err());
+        assert!(Disk::annulus(center, normal, f32::NAN, 1., material()).is_err());
+    }
+
+    #[test]
+    // Test that the bounding box of a tilted disk is tight.
+    fn test_disk_bounding_box() {
+        let disk = Disk::new(Vec3(1., 0., 0.), Vec3(0., 1., 1.), 2., material());
+        let aabb = disk.bounding_box().unwrap();
+        let extent = 2. * 0.5_f32.sqrt();
+        assert!((*aabb.min() - Vec3(-1., -extent, -extent)).length() < 1e-5);
+        assert!((*aabb.max() - Vec3(3., extent, extent)).length() < 1e-5);
+        // A disk in a coordinate plane has a box of zero extent.
+        let flat = Disk::new(Vec3(0., 0., 0.), Vec3(0., 1., 0.), 1., material());
+        let aabb = flat.bounding_box().unwrap();
+        assert_eq!(aabb.min().y(), aabb.max().y());
+    }
+}
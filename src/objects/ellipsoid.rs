@@ -0,0 +1,194 @@
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::materials::MaterialExt;
+use crate::objects::sphere::sphere_uv;
+use crate::objects::Hitable;
+use crate::ray::Ray;
+use crate::vec3::dot;
+use crate::vec3::unit_vector;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+/// An axis-aligned ellipsoid in three-dimensional space.
+///
+/// It is characterized by:
+/// - The coordinates of its center.
+/// - The lengths of its three semi-axes along x, y and z.
+/// - A pointer to the material that it is made of.
+pub struct Ellipsoid {
+    center: Vec3,
+    semi_axes: Vec3,
+    material: Arc<dyn MaterialExt>,
+}
+
+// Divide two vectors component-wise.
+fn divide(v: &Vec3, w: &Vec3) -> Vec3 {
+    Vec3(v.x() / w.x(), v.y() / w.y(), v.z() / w.z())
+}
+
+impl Ellipsoid {
+    /// Create an `Ellipsoid` by specifying its `center`, `semi_axes` and `Material`.
+    ///
+    /// ```
+    /// use raytracer::objects::ellipsoid::Ellipsoid;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let ellipsoid = Ellipsoid::new(Vec3(0., 1., 0.), Vec3(2., 1., 1.), material);
+    /// ```
+    pub fn new(center: Vec3, semi_axes: Vec3, material: Arc<dyn MaterialExt>) -> Ellipsoid {
+        Ellipsoid {
+            center,
+            semi_axes,
+            material,
+        }
+    }
+
+    /// Access the center of an `Ellipsoid`.
+    ///
+    /// ```
+    /// # use raytracer::objects::ellipsoid::Ellipsoid;
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::materials::Lambertian;
+    /// # use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let ellipsoid = Ellipsoid::new(Vec3(0., 1., 0.), Vec3(2., 1., 1.), material);
+    /// assert_eq!(ellipsoid.center(), &Vec3(0., 1., 0.));
+    /// ```
+    pub fn center(&self) -> &Vec3 {
+        &self.center
+    }
+
+    /// Access the semi-axes of an `Ellipsoid`.
+    ///
+    /// ```
+    /// # use raytracer::objects::ellipsoid::Ellipsoid;
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::materials::Lambertian;
+    /// # use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let ellipsoid = Ellipsoid::new(Vec3(0., 1., 0.), Vec3(2., 1., 1.), material);
+    /// assert_eq!(ellipsoid.semi_axes(), &Vec3(2., 1., 1.));
+    /// ```
+    pub fn semi_axes(&self) -> &Vec3 {
+        &self.semi_axes
+    }
+
+    /// Access the `material` an `Ellipsoid` is made of.
+    pub fn material(&self) -> Arc<dyn MaterialExt> {
+        Arc::clone(&self.material)
+    }
+}
+
+impl Hitable for Ellipsoid {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        // Scale the ray into the space where the ellipsoid is the unit sphere.
+        // This scaling keeps the ray parameter `t` unchanged.
+        let origin = divide(&(*ray.origin() - self.center), &self.semi_axes);
+        let direction = divide(ray.direction(), &self.semi_axes);
+        let a = dot(&direction, &direction);
+        let half_b = dot(&origin, &direction);
+        let c = dot(&origin, &origin) - 1.;
+        let discriminant = half_b * half_b - a * c;
+        if discriminant <= 0. {
+            return None;
+        }
+        let sqrt_discriminant = discriminant.sqrt();
+        let t = [
+            (-half_b - sqrt_discriminant) / a,
+            (-half_b + sqrt_discriminant) / a,
+        ]
+        .iter()
+        .cloned()
+        .find(|&t| t > t_min && t < t_max)?;
+
+        // The normal of the unit sphere is transformed back with the inverse
+        // transpose of the scaling, i.e. divided by the semi-axes once more.
+        let local_normal = origin + t * direction;
+        let (u, v) = sphere_uv(&local_normal);
+        let normal = unit_vector(&divide(&local_normal, &self.semi_axes));
+        Some(HitRecord {
+            parameter: t,
+            point_at_parameter: ray.point_at_parameter(t),
+            normal,
+            u,
+            v,
+            tangent: Vec3(0., 0., 0.),
+            bitangent: Vec3(0., 0., 0.),
+            front_face: dot(ray.direction(), &normal) < 0.,
+            material: self.material.as_ref(),
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(
+            self.center - self.semi_axes,
+            self.center + self.semi_axes,
+        ))
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+
+    fn ellipsoid() -> Ellipsoid {
+        Ellipsoid::new(
+            Vec3(1., 0., 0.),
+            Vec3(2., 1., 1.),
+            Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))),
+        )
+    }
+
+    // The implicit function of the ellipsoid, zero on the surface.
+    fn implicit(p: &Vec3) -> f32 {
+        let local = divide(&(*p - Vec3(1., 0., 0.)), &Vec3(2., 1., 1.));
+        local.squared_length() - 1.
+    }
+
+    #[test]
+    // Test hits along the long and short axes.
+    fn test_ellipsoid_hits() {
+        let ellipsoid = ellipsoid();
+        let ray = Ray::new(Vec3(-5., 0., 0.), Vec3(1., 0., 0.));
+        let hit = ellipsoid.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.parameter - 4.).abs() < 1e-5);
+        assert_eq!(hit.normal, Vec3(-1., 0., 0.));
+        let ray = Ray::new(Vec3(1., 5., 0.), Vec3(0., -1., 0.));
+        let hit = ellipsoid.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.parameter - 4.).abs() < 1e-5);
+        assert_eq!(hit.normal, Vec3(0., 1., 0.));
+        // Misses just beyond the short axis, which a sphere of radius 2 would hit.
+        let ray = Ray::new(Vec3(1., 5., 1.1), Vec3(0., -1., 0.));
+        assert!(ellipsoid.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    // Test that the normal is the normalized gradient of the implicit function.
+    fn test_ellipsoid_normal_is_gradient() {
+        let ellipsoid = ellipsoid();
+        let ray = Ray::new(Vec3(-5., 0.6, 0.3), Vec3(1., 0., 0.));
+        let hit = ellipsoid.intersect(&ray, 0.001, f32::MAX).unwrap();
+        let p = hit.point_at_parameter;
+        assert!(implicit(&p).abs() < 1e-5);
+
+        let h = 1e-3;
+        let gradient = Vec3(
+            implicit(&(p + Vec3(h, 0., 0.))) - implicit(&(p - Vec3(h, 0., 0.))),
+            implicit(&(p + Vec3(0., h, 0.))) - implicit(&(p - Vec3(0., h, 0.))),
+            implicit(&(p + Vec3(0., 0., h))) - implicit(&(p - Vec3(0., 0., h))),
+        );
+        let gradient = unit_vector(&gradient);
+        assert!((hit.normal - gradient).length() < 1e-3);
+        assert!((hit.normal.length() - 1.).abs() < 1e-5);
+        // Naively scaling the unit sphere normal forward gives a different direction.
+        let naive = unit_vector(&(p - Vec3(1., 0., 0.)));
+        assert!((naive - gradient).length() > 0.1);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::objects::Hitable;
+use crate::ray::Ray;
+
+/// A hitable object whose surface normals point to the other side.
+///
+/// This turns outward-facing surfaces into inward-facing ones, e.g. for the
+/// walls of a room built from rectangles which are lit from inside.
+pub struct FlipNormals {
+    object: Box<dyn Hitable>,
+}
+
+impl FlipNormals {
+    /// Flip the normals of the hitable `object`.
+    ///
+    /// ```
+    /// use raytracer::objects::flip_normals::FlipNormals;
+    /// use raytracer::objects::rect::Rect;
+    /// use raytracer::objects::Hitable;
+    /// use raytracer::ray::Ray;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.8, 0.8)));
+    /// let floor = Rect::new(Vec3(-1., 0., 1.), Vec3(2., 0., 0.), Vec3(0., 0., -2.), material);
+    /// let ceiling = FlipNormals::new(Box::new(floor));
+    /// let ray = Ray::new(Vec3(0., -1., 0.), Vec3(0., 1., 0.));
+    /// let hit = ceiling.intersect(&ray, 0.001, f32::MAX).unwrap();
+    /// assert_eq!(hit.normal, Vec3(0., -1., 0.));
+    /// ```
+    pub fn new(object: Box<dyn Hitable>) -> FlipNormals {
+        FlipNormals { object }
+    }
+
+    // Turn a hit on the wrapped object around.
+    fn flip(mut hit: HitRecord) -> HitRecord {
+        hit.normal = -hit.normal;
+        hit.front_face = !hit.front_face;
+        hit
+    }
+}
+
+impl Hitable for FlipNormals {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.object
+            .intersect(ray, t_min, t_max)
+            .map(FlipNormals::flip)
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        self.object
+            .intersect_all(ray)
+            .into_iter()
+            .map(FlipNormals::flip)
+            .collect()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.object.bounding_box()
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+    use crate::objects::rect::Rect;
+    use crate::objects::rotate::RotateY;
+    use crate::objects::translate::Translate;
+    use crate::vec3::dot;
+    use crate::vec3::Vec3;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+    use std::sync::Arc;
+
+    // A rectangle in the plane z = -2 facing towards +z.
+    fn rect() -> Rect {
+        Rect::new(
+            Vec3(-1., -1., -2.),
+            Vec3(2., 0., 0.),
+            Vec3(0., 2., 0.),
+            Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))),
+        )
+    }
+
+    #[test]
+    // Test that a Lambertian rectangle hit from behind scatters back once flipped.
+    fn test_flip_normals_lambertian_from_behind() {
+        let mut rng = SmallRng::seed_from_u64(20);
+        let ray = Ray::new(Vec3(0., 0., -5.), Vec3(0., 0., 1.));
+        let flipped = FlipNormals::new(Box::new(rect()));
+        let hit = flipped.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.parameter, 3.);
+        assert_eq!(hit.normal, Vec3(0., 0., -1.));
+        for _ in 0..1000 {
+            let (scattered, _) = hit.material.scatter(&ray, &hit, &mut rng).unwrap();
+            // The light is scattered back towards the side it came from.
+            assert!(dot(scattered.direction(), &hit.normal) > 0.);
+            assert!(scattered.direction().z() < 0.);
+        }
+    }
+
+    #[test]
+    // Test that flipping composes with the translate and rotate wrappers.
+    fn test_flip_normals_composes() {
+        let wall = Translate::new(
+            Box::new(RotateY::new(
+                Box::new(FlipNormals::new(Box::new(rect()))),
+                90.,
+            )),
+            Vec3(0., 0., 1.),
+        );
+        // The rotated rectangle lies in the plane x = -2, facing towards +x
+        // before flipping.
+        let ray = Ray::new(Vec3(0., 0., 1.), Vec3(-1., 0., 0.));
+        let hit = wall.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.parameter - 2.).abs() < 1e-5);
+        assert!((hit.normal - Vec3(-1., 0., 0.)).length() < 1e-5);
+        let flipped_twice = FlipNormals::new(Box::new(wall));
+        let hit = flipped_twice.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.normal - Vec3(1., 0., 0.)).length() < 1e-5);
+    }
+}
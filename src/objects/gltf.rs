@@ -0,0 +1,364 @@
+//! Import of triangle meshes from glTF 2.0 files.
+//!
+//! This module is only available with the `gltf` feature. The meshes of all
+//! nodes in the default scene are converted to `TriangleMesh` objects placed
+//! by the transforms of the node hierarchy. Materials are approximated from
+//! their PBR base color: mostly metallic materials become `Metal`, with the
+//! roughness as fuzzyness, all others become `Lambertian`.
+
+use crate::materials::Lambertian;
+use crate::materials::MaterialExt;
+use crate::materials::Metal;
+use crate::objects::mesh::TriangleMesh;
+use crate::objects::Hitable;
+use crate::objects::ObjectError;
+use crate::transform::Mat4;
+use crate::vec3::cross;
+use crate::vec3::dot;
+use crate::vec3::Vec3;
+use ::gltf::mesh::Mode;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+/// The objects imported from a glTF file.
+pub struct GltfScene {
+    /// One mesh per triangle primitive and node it is placed by, ready to be
+    /// added to a `HitableList`.
+    pub objects: Vec<Box<dyn Hitable>>,
+    /// The number of primitives skipped because they are made of points or lines.
+    pub skipped_primitives: usize,
+}
+
+/// Error returned when a glTF file cannot be imported.
+#[derive(Debug)]
+pub enum GltfError {
+    /// The file could not be read or is not valid glTF.
+    Import(::gltf::Error),
+    /// A primitive of a mesh has no vertex positions.
+    MissingPositions { mesh: usize },
+    /// A primitive of a mesh does not form a valid `TriangleMesh`.
+    Mesh(ObjectError),
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GltfError::Import(error) => write!(f, "failed to import glTF: {}", error),
+            GltfError::MissingPositions { mesh } => {
+                write!(f, "a primitive of glTF mesh {} has no positions", mesh)
+            }
+            GltfError::Mesh(error) => write!(f, "invalid glTF mesh: {}", error),
+        }
+    }
+}
+
+impl Error for GltfError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GltfError::Import(error) => Some(error),
+            GltfError::MissingPositions { .. } => None,
+            GltfError::Mesh(error) => Some(error),
+        }
+    }
+}
+
+/// Import the meshes of a `.gltf` or `.glb` file.
+///
+/// Primitives without a material are made of the `fallback` material.
+pub fn load_gltf<P: AsRef<Path>>(
+    path: P,
+    fallback: Arc<dyn MaterialExt>,
+) -> Result<GltfScene, GltfError> {
+    let (document, buffers, _) = ::gltf::import(path).map_err(GltfError::Import)?;
+    convert(&document, &buffers, fallback)
+}
+
+/// Import the meshes of glTF data held in memory, e.g. the contents of a `.glb` file.
+///
+/// Primitives without a material are made of the `fallback` material.
+pub fn load_gltf_slice(
+    data: &[u8],
+    fallback: Arc<dyn MaterialExt>,
+) -> Result<GltfScene, GltfError> {
+    let (document, buffers, _) = ::gltf::import_slice(data).map_err(GltfError::Import)?;
+    convert(&document, &buffers, fallback)
+}
+
+// Approximate a glTF PBR material by one of the built-in materials.
+fn convert_material(material: ::gltf::Material) -> Arc<dyn MaterialExt> {
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, _] = pbr.base_color_factor();
+    if pbr.metallic_factor() >= 0.5 {
+        Arc::new(Metal::new(Vec3(r, g, b), pbr.roughness_factor()))
+    } else {
+        Arc::new(Lambertian::new(Vec3(r, g, b)))
+    }
+}
+
+// Convert a column-major glTF matrix.
+fn matrix(columns: [[f32; 4]; 4]) -> Mat4 {
+    let mut rows = [[0.; 4]; 4];
+    for (column, values) in columns.iter().enumerate() {
+        for (row, value) in values.iter().enumerate() {
+            rows[row][column] = *value;
+        }
+    }
+    Mat4::new(rows)
+}
+
+// Split the vertex indices of a primitive into triangles.
+fn faces(mode: Mode, indices: &[usize]) -> Option<Vec<[usize; 3]>> {
+    match mode {
+        Mode::Triangles => Some(
+            indices
+                .chunks_exact(3)
+                .map(|face| [face[0], face[1], face[2]])
+                .collect(),
+        ),
+        // Every other triangle of a strip is reversed to keep the winding.
+        Mode::TriangleStrip => Some(
+            indices
+                .windows(3)
+                .enumerate()
+                .map(|(i, face)| {
+                    if i % 2 == 0 {
+                        [face[0], face[1], face[2]]
+                    } else {
+                        [face[1], face[0], face[2]]
+                    }
+                })
+                .collect(),
+        ),
+        Mode::TriangleFan => Some(
+            indices
+                .windows(2)
+                .skip(1)
+                .map(|edge| [indices[0], edge[0], edge[1]])
+                .collect(),
+        ),
+        Mode::Points | Mode::Lines | Mode::LineLoop | Mode::LineStrip => None,
+    }
+}
+
+struct Converter<'a> {
+    buffers: &'a [::gltf::buffer::Data],
+    materials: Vec<Arc<dyn MaterialExt>>,
+    fallback: Arc<dyn MaterialExt>,
+    scene: GltfScene,
+}
+
+fn convert(
+    document: &::gltf::Document,
+    buffers: &[::gltf::buffer::Data],
+    fallback: Arc<dyn MaterialExt>,
+) -> Result<GltfScene, GltfError> {
+    let mut converter = Converter {
+        buffers,
+        materials: document.materials().map(convert_material).collect(),
+        fallback,
+        scene: GltfScene {
+            objects: Vec::new(),
+            skipped_primitives: 0,
+        },
+    };
+    if let Some(scene) = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        for node in scene.nodes() {
+            converter.add_node(&node, &Mat4::identity())?;
+        }
+    }
+    Ok(converter.scene)
+}
+
+impl<'a> Converter<'a> {
+    fn add_node(&mut self, node: &::gltf::Node, parent: &Mat4) -> Result<(), GltfError> {
+        let transform = *parent * matrix(node.transform().matrix());
+        if let Some(mesh) = node.mesh() {
+            for primitive in mesh.primitives() {
+                self.add_primitive(&mesh, &primitive, &transform)?;
+            }
+        }
+        for child in node.children() {
+            self.add_node(&child, &transform)?;
+        }
+        Ok(())
+    }
+
+    fn add_primitive(
+        &mut self,
+        mesh: &::gltf::Mesh,
+        primitive: &::gltf::Primitive,
+        transform: &Mat4,
+    ) -> Result<(), GltfError> {
+        let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
+        let positions: Vec<Vec3> = reader
+            .read_positions()
+            .ok_or(GltfError::MissingPositions { mesh: mesh.index() })?
+            .map(|[x, y, z]| transform.transform_point(&Vec3(x, y, z)))
+            .collect();
+        let indices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|index| index as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+        let mut faces = match faces(primitive.mode(), &indices) {
+            Some(faces) => faces,
+            None => {
+                self.scene.skipped_primitives += 1;
+                return Ok(());
+            }
+        };
+        // A mirroring transform turns the front side of the faces around.
+        let x = transform.transform_vector(&Vec3(1., 0., 0.));
+        let y = transform.transform_vector(&Vec3(0., 1., 0.));
+        let z = transform.transform_vector(&Vec3(0., 0., 1.));
+        if dot(&cross(&x, &y), &z) < 0. {
+            for face in faces.iter_mut() {
+                face.swap(1, 2);
+            }
+        }
+
+        let material = match primitive.material().index() {
+            Some(index) => self.materials[index].clone(),
+            None => self.fallback.clone(),
+        };
+        let mut triangles =
+            TriangleMesh::new(positions, faces, material).map_err(GltfError::Mesh)?;
+        if let (Some(normals), Some(inverse)) = (reader.read_normals(), transform.inverse()) {
+            let normals = normals
+                .map(|[x, y, z]| inverse.transform_normal(&Vec3(x, y, z)))
+                .collect();
+            triangles = triangles
+                .with_vertex_normals(normals)
+                .map_err(GltfError::Mesh)?;
+        }
+        self.scene.objects.push(Box::new(triangles));
+        Ok(())
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Ray;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    // Pack a glTF JSON document and its binary buffer into a `.glb` file.
+    fn glb(json: &str, bin: &[u8]) -> Vec<u8> {
+        let mut json = json.as_bytes().to_vec();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let mut bin = bin.to_vec();
+        bin.resize(bin.len().next_multiple_of(4), 0);
+        let length = 12 + 8 + json.len() + 8 + bin.len();
+        let mut data = Vec::new();
+        data.extend_from_slice(b"glTF");
+        data.extend_from_slice(&2_u32.to_le_bytes());
+        data.extend_from_slice(&(length as u32).to_le_bytes());
+        data.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        data.extend_from_slice(b"JSON");
+        data.extend_from_slice(&json);
+        data.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        data.extend_from_slice(b"BIN\0");
+        data.extend_from_slice(&bin);
+        data
+    }
+
+    // A triangle used by two nodes, the second one a scaled child of the first.
+    fn two_nodes() -> Vec<u8> {
+        let mut bin = Vec::new();
+        let positions = [0., 0., 0., 1., 0., 0., 0., 1., 0.];
+        let normals = [0., 0., 1., 0., 0., 1., 0., 0., 1.];
+        for value in positions.iter().chain(normals.iter()) {
+            bin.extend_from_slice(&(*value as f32).to_le_bytes());
+        }
+        for index in [0_u16, 1, 2] {
+            bin.extend_from_slice(&index.to_le_bytes());
+        }
+        let json = r#"{
+            "asset": {"version": "2.0"},
+            "buffers": [{"byteLength": 78}],
+            "bufferViews": [
+                {"buffer": 0, "byteOffset": 0, "byteLength": 36},
+                {"buffer": 0, "byteOffset": 36, "byteLength": 36},
+                {"buffer": 0, "byteOffset": 72, "byteLength": 6}
+            ],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                 "min": [0, 0, 0], "max": [1, 1, 0]},
+                {"bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3"},
+                {"bufferView": 2, "componentType": 5123, "count": 3, "type": "SCALAR"}
+            ],
+            "materials": [
+                {"pbrMetallicRoughness": {"baseColorFactor": [0.8, 0.1, 0.1, 1.0],
+                                          "metallicFactor": 0.0}},
+                {"pbrMetallicRoughness": {"baseColorFactor": [0.9, 0.9, 0.9, 1.0],
+                                          "metallicFactor": 1.0, "roughnessFactor": 0.1}}
+            ],
+            "meshes": [
+                {"primitives": [
+                    {"attributes": {"POSITION": 0, "NORMAL": 1}, "indices": 2, "material": 0},
+                    {"attributes": {"POSITION": 0}, "mode": 0}
+                ]},
+                {"primitives": [
+                    {"attributes": {"POSITION": 0}, "indices": 2, "material": 1},
+                    {"attributes": {"POSITION": 0}, "mode": 6}
+                ]}
+            ],
+            "nodes": [
+                {"mesh": 0, "translation": [0, 0, -5], "children": [1]},
+                {"mesh": 1, "translation": [2, 0, 0], "scale": [2, 2, 2]}
+            ],
+            "scenes": [{"nodes": [0]}],
+            "scene": 0
+        }"#;
+        glb(json, &bin)
+    }
+
+    #[test]
+    // Test that the meshes of both nodes are placed by the node hierarchy.
+    fn test_load_gltf_two_nodes() {
+        let mut rng = SmallRng::seed_from_u64(30);
+        let fallback: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.1, 0.2, 0.3)));
+        let scene = load_gltf_slice(&two_nodes(), fallback).unwrap();
+        assert_eq!(scene.objects.len(), 3);
+        assert_eq!(scene.skipped_primitives, 1);
+
+        // The parent triangle is moved, with its normals and red material.
+        let ray = Ray::new(Vec3(0.2, 0.2, 0.), Vec3(0., 0., -1.));
+        let hit = scene.objects[0].intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.point_at_parameter - Vec3(0.2, 0.2, -5.)).length() < 1e-5);
+        assert_eq!(hit.normal, Vec3(0., 0., 1.));
+        let (_, attenuation) = hit.material.scatter(&ray, &hit, &mut rng).unwrap();
+        assert_eq!(attenuation, Vec3(0.8, 0.1, 0.1));
+        assert!(scene.objects[1].intersect(&ray, 0.001, f32::MAX).is_none());
+
+        // The child triangle is scaled and moved relative to its parent.
+        for (object, expected) in [(1, Vec3(0.9, 0.9, 0.9)), (2, Vec3(0.1, 0.2, 0.3))] {
+            let ray = Ray::new(Vec3(3.5, 0.4, 0.), Vec3(0., 0., -1.));
+            let hit = scene.objects[object]
+                .intersect(&ray, 0.001, f32::MAX)
+                .unwrap();
+            assert!((hit.point_at_parameter - Vec3(3.5, 0.4, -5.)).length() < 1e-5);
+            let (_, attenuation) = hit.material.scatter(&ray, &hit, &mut rng).unwrap();
+            assert_eq!(attenuation, expected);
+        }
+    }
+
+    #[test]
+    // Test that invalid data is reported as an import error.
+    fn test_load_gltf_invalid() {
+        let fallback: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        match load_gltf_slice(b"not a glTF file", fallback) {
+            Err(GltfError::Import(_)) => (),
+            _ => panic!("expected an import error"),
+        }
+    }
+}
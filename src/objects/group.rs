@@ -0,0 +1,257 @@
+use crate::aabb::surrounding_box;
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::objects::Hitable;
+use crate::objects::ObjectError;
+use crate::ray::Ray;
+use crate::transform::Mat4;
+use crate::vec3::unit_vector;
+
+/// A node of a scene graph, grouping children under a common transformation.
+///
+/// The children are given in the local frame of the group, which is placed
+/// in its parent frame by the affine transformation of the group. A ray is
+/// mapped into the local frame once for all children, and hits are mapped
+/// back. Groups can be nested, in which case the transformations compose
+/// from the innermost to the outermost group.
+///
+/// Like for a `TransformedHitable`, the direction of the mapped ray is not
+/// renormalized, such that the ray parameter `t` of a hit is the same in
+/// all frames.
+pub struct Group {
+    children: Vec<Box<dyn Hitable>>,
+    matrix: Mat4,
+    inverse: Mat4,
+    // The inverse transpose transforms the normals of the children.
+    normal_matrix: Mat4,
+}
+
+impl Group {
+    /// Create an empty `Group` placed by the affine transformation `matrix`.
+    ///
+    /// An error is returned if the matrix cannot be inverted.
+    ///
+    /// ```
+    /// use raytracer::objects::cuboid::Cuboid;
+    /// use raytracer::objects::group::Group;
+    /// use raytracer::transform::Mat4;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let wood = Arc::new(Lambertian::new(Vec3(0.6, 0.4, 0.2)));
+    /// let mut room = Group::new(Mat4::identity()).unwrap();
+    /// for (i, x) in [-3., 0., 3.].iter().enumerate() {
+    ///     let rotation = Mat4::rotation_y(30. * i as f32);
+    ///     let mut table = Group::new(Mat4::translation(Vec3(*x, 0., -5.)) * rotation).unwrap();
+    ///     let top = Cuboid::new(Vec3(-1., 0.9, -0.5), Vec3(1., 1., 0.5), wood.clone());
+    ///     table.add_child(Box::new(top));
+    ///     for (lx, lz) in [(-0.9, -0.4), (0.8, -0.4), (-0.9, 0.3), (0.8, 0.3)] {
+    ///         let leg = Cuboid::new(Vec3(lx, 0., lz), Vec3(lx + 0.1, 0.9, lz + 0.1), wood.clone());
+    ///         table.add_child(Box::new(leg));
+    ///     }
+    ///     room.add_child(Box::new(table));
+    /// }
+    /// assert_eq!(room.children().len(), 3);
+    /// ```
+    pub fn new(matrix: Mat4) -> Result<Group, ObjectError> {
+        let inverse = matrix.inverse().ok_or(ObjectError::SingularTransform)?;
+        Ok(Group {
+            children: Vec::new(),
+            matrix,
+            inverse,
+            normal_matrix: inverse.transpose(),
+        })
+    }
+
+    /// Add a `child`, given in the local frame of the group.
+    pub fn add_child(&mut self, child: Box<dyn Hitable>) {
+        self.children.push(child);
+    }
+
+    /// Access the children of a `Group`.
+    pub fn children(&self) -> &[Box<dyn Hitable>] {
+        &self.children
+    }
+
+    /// Access the transformation matrix placing the group in its parent frame.
+    pub fn matrix(&self) -> &Mat4 {
+        &self.matrix
+    }
+
+    fn local_ray(&self, ray: &Ray) -> Ray {
+        Ray::new_with_time(
+            self.inverse.transform_point(ray.origin()),
+            self.inverse.transform_vector(ray.direction()),
+            ray.time(),
+        )
+        .with_medium_seed(ray.medium_seed())
+    }
+
+    fn world_hit<'a>(&self, mut hit: HitRecord<'a>) -> HitRecord<'a> {
+        hit.point_at_parameter = self.matrix.transform_point(&hit.point_at_parameter);
+        hit.normal = unit_vector(&self.normal_matrix.transform_vector(&hit.normal));
+        hit.map_tangents(|v| self.matrix.transform_vector(v));
+        hit
+    }
+}
+
+impl Hitable for Group {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let local_ray = self.local_ray(ray);
+        let mut hit_record = None;
+        let mut closest_so_far = t_max;
+        for child in &self.children {
+            if let Some(hit) = child.intersect(&local_ray, t_min, closest_so_far) {
+                closest_so_far = hit.parameter;
+                hit_record = Some(hit);
+            }
+        }
+        hit_record.map(|hit| self.world_hit(hit))
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        let local_ray = self.local_ray(ray);
+        let mut hits: Vec<HitRecord> = self
+            .children
+            .iter()
+            .flat_map(|child| child.intersect_all(&local_ray))
+            .map(|hit| self.world_hit(hit))
+            .collect();
+        hits.sort_by(|a, b| a.parameter.total_cmp(&b.parameter));
+        hits
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let mut children = self.children.iter();
+        let first = children.next()?.bounding_box()?;
+        let local = children.try_fold(first, |aabb, child| {
+            Some(surrounding_box(&aabb, &child.bounding_box()?))
+        })?;
+        Some(local.transformed(&self.matrix))
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+    use crate::materials::MaterialExt;
+    use crate::objects::cuboid::Cuboid;
+    use crate::objects::transformed::TransformedHitable;
+    use crate::objects::HitableList;
+    use crate::vec3::Vec3;
+    use std::sync::Arc;
+
+    fn material() -> Arc<dyn MaterialExt> {
+        Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)))
+    }
+
+    // The top and the four legs of a table, in the frame of the table.
+    fn table_parts() -> Vec<Cuboid> {
+        let mut parts = vec![Cuboid::new(
+            Vec3(-1., 0.9, -0.5),
+            Vec3(1., 1., 0.5),
+            material(),
+        )];
+        for &(x, z) in &[(-0.9, -0.4), (0.8, -0.4), (-0.9, 0.3), (0.8, 0.3)] {
+            parts.push(Cuboid::new(
+                Vec3(x, 0., z),
+                Vec3(x + 0.1, 0.9, z + 0.1),
+                material(),
+            ));
+        }
+        parts
+    }
+
+    fn placements() -> Vec<Mat4> {
+        vec![
+            Mat4::translation(Vec3(-3., 0., 0.)) * Mat4::rotation_y(20.),
+            Mat4::translation(Vec3(0., 0., 1.)),
+            Mat4::translation(Vec3(3., 0.5, 0.)) * Mat4::scaling(Vec3(1., 0.5, 1.5)),
+        ]
+    }
+
+    #[test]
+    // Test a room of tables made of parts against the parts placed individually.
+    fn test_nested_groups_match_flat_geometry() {
+        let room_matrix = Mat4::translation(Vec3(0., -1., -6.)) * Mat4::rotation_x(15.);
+        let mut room = Group::new(room_matrix).unwrap();
+        let mut flat: Vec<Box<dyn Hitable>> = Vec::new();
+        for placement in placements() {
+            let mut table = Group::new(placement).unwrap();
+            for part in table_parts() {
+                table.add_child(Box::new(part));
+            }
+            room.add_child(Box::new(table));
+            for part in table_parts() {
+                let matrix = room_matrix * placement;
+                flat.push(Box::new(
+                    TransformedHitable::new(Box::new(part), matrix).unwrap(),
+                ));
+            }
+        }
+        let flat = HitableList::new(flat);
+
+        let mut hits = 0;
+        for y in 0..20 {
+            for x in 0..40 {
+                let target = Vec3(x as f32 * 0.2 - 4., y as f32 * 0.15 - 2., -6.);
+                let ray = Ray::new(Vec3(0., 1., 0.), target - Vec3(0., 1., 0.));
+                let expected = flat.intersect(&ray, 0.001, f32::MAX);
+                let hit = room.intersect(&ray, 0.001, f32::MAX);
+                assert_eq!(hit.is_some(), expected.is_some());
+                if let (Some(hit), Some(expected)) = (hit, expected) {
+                    assert!((hit.parameter - expected.parameter).abs() < 1e-4);
+                    assert!((hit.point_at_parameter - expected.point_at_parameter).length() < 1e-4);
+                    assert!((hit.normal - expected.normal).length() < 1e-4);
+                    hits += 1;
+                }
+            }
+        }
+        // Make sure the rays do not only miss.
+        assert!(hits > 50);
+    }
+
+    #[test]
+    // Test that all crossings of the subtree are reported in order.
+    fn test_group_intersect_all() {
+        let mut group = Group::new(Mat4::translation(Vec3(0., 0., -5.))).unwrap();
+        group.add_child(Box::new(Cuboid::new(
+            Vec3(-1., -1., 1.),
+            Vec3(1., 1., 2.),
+            material(),
+        )));
+        group.add_child(Box::new(Cuboid::new(
+            Vec3(-1., -1., -2.),
+            Vec3(1., 1., -1.),
+            material(),
+        )));
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., -1.));
+        let parameters: Vec<f32> = group
+            .intersect_all(&ray)
+            .iter()
+            .map(|hit| hit.parameter)
+            .collect();
+        assert_eq!(parameters, vec![3., 4., 6., 7.]);
+        assert!(Group::new(Mat4::scaling(Vec3(1., 0., 1.))).is_err());
+    }
+
+    #[test]
+    // Test that the bounding box of nested groups encloses the placed children.
+    fn test_group_bounding_box() {
+        let mut table = Group::new(Mat4::translation(Vec3(0., 0., -5.))).unwrap();
+        for part in table_parts() {
+            table.add_child(Box::new(part));
+        }
+        let mut room = Group::new(Mat4::scaling(Vec3(2., 2., 2.))).unwrap();
+        assert!(room.bounding_box().is_none());
+        room.add_child(Box::new(table));
+        let aabb = room.bounding_box().unwrap();
+        assert_eq!(aabb.min(), &Vec3(-2., 0., -11.));
+        assert_eq!(aabb.max(), &Vec3(2., 2., -9.));
+    }
+}
@@ -0,0 +1,207 @@
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::materials::MaterialExt;
+use crate::objects::Hitable;
+use crate::ray::Ray;
+use crate::vec3::dot;
+use crate::vec3::unit_vector;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+/// A cylinder of infinite length, without caps.
+///
+/// It is characterized by:
+/// - A point on its axis.
+/// - The direction of its axis.
+/// - Its radius. Like for a `Sphere`, a negative radius makes the normals
+///   point inwards, which turns the cylinder into the inside of a tube.
+/// - A pointer to the material that it is made of.
+pub struct InfiniteCylinder {
+    point: Vec3,
+    axis: Vec3,
+    radius: f32,
+    material: Arc<dyn MaterialExt>,
+}
+
+impl InfiniteCylinder {
+    /// Create an `InfiniteCylinder` around the line through `point` along `axis`.
+    ///
+    /// The `axis` does not need to be normalized.
+    ///
+    /// ```
+    /// use raytracer::objects::infinite_cylinder::InfiniteCylinder;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let pipe = InfiniteCylinder::new(Vec3(0., 0., -3.), Vec3(0., 2., 0.), 0.5, material);
+    /// assert_eq!(pipe.axis(), &Vec3(0., 1., 0.));
+    /// ```
+    pub fn new(
+        point: Vec3,
+        axis: Vec3,
+        radius: f32,
+        material: Arc<dyn MaterialExt>,
+    ) -> InfiniteCylinder {
+        InfiniteCylinder {
+            point,
+            axis: unit_vector(&axis),
+            radius,
+            material,
+        }
+    }
+
+    /// Access the point on the axis of an `InfiniteCylinder`.
+    pub fn point(&self) -> &Vec3 {
+        &self.point
+    }
+
+    /// Access the normalized direction of the axis of an `InfiniteCylinder`.
+    pub fn axis(&self) -> &Vec3 {
+        &self.axis
+    }
+
+    /// Access the radius of an `InfiniteCylinder`.
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// Access the `material` an `InfiniteCylinder` is made of.
+    pub fn material(&self) -> Arc<dyn MaterialExt> {
+        Arc::clone(&self.material)
+    }
+
+    // Remove the component of `v` along the axis.
+    fn perpendicular(&self, v: &Vec3) -> Vec3 {
+        *v - dot(v, &self.axis) * self.axis
+    }
+
+    // Return the parameters where the line through the ray crosses the surface.
+    fn roots(&self, ray: &Ray) -> Option<[f32; 2]> {
+        // Only the motion perpendicular to the axis moves the ray relative
+        // to the surface, which reduces the problem to a circle in 2D.
+        let oc = self.perpendicular(&(*ray.origin() - self.point));
+        let direction = self.perpendicular(ray.direction());
+        let a = dot(&direction, &direction);
+        // A ray parallel to the axis never crosses the surface.
+        if a < 1e-12 {
+            return None;
+        }
+        let half_b = dot(&oc, &direction);
+        let c = dot(&oc, &oc) - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+        if discriminant <= 0. {
+            return None;
+        }
+        let sqrt_discriminant = discriminant.sqrt();
+        Some([
+            (-half_b - sqrt_discriminant) / a,
+            (-half_b + sqrt_discriminant) / a,
+        ])
+    }
+
+    fn hit_record(&self, ray: &Ray, t: f32) -> HitRecord<'_> {
+        let point = ray.point_at_parameter(t);
+        let normal = self.perpendicular(&(point - self.point)) / self.radius;
+        HitRecord {
+            parameter: t,
+            point_at_parameter: point,
+            normal,
+            u: 0.,
+            v: 0.,
+            tangent: Vec3(0., 0., 0.),
+            bitangent: Vec3(0., 0., 0.),
+            front_face: dot(ray.direction(), &normal) < 0.,
+            material: self.material.as_ref(),
+        }
+    }
+}
+
+impl Hitable for InfiniteCylinder {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let t = self
+            .roots(ray)?
+            .iter()
+            .cloned()
+            .find(|&t| t > t_min && t < t_max)?;
+        Some(self.hit_record(ray, t))
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        match self.roots(ray) {
+            Some(roots) => roots.iter().map(|&t| self.hit_record(ray, t)).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+
+    fn cylinder(radius: f32) -> InfiniteCylinder {
+        InfiniteCylinder::new(
+            Vec3(0., 0., -3.),
+            Vec3(0., 1., 0.),
+            radius,
+            Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))),
+        )
+    }
+
+    #[test]
+    // Test that hits have normals perpendicular to the axis, at any height.
+    fn test_infinite_cylinder_hits() {
+        let cylinder = cylinder(1.);
+        for height in [-100., 0., 2.5, 1000.] {
+            let ray = Ray::new(Vec3(0., height, 0.), Vec3(0., 0., -1.));
+            let hit = cylinder.intersect(&ray, 0.001, f32::MAX).unwrap();
+            assert!((hit.parameter - 2.).abs() < 1e-5);
+            assert!((hit.normal - Vec3(0., 0., 1.)).length() < 1e-5);
+        }
+        // A slanted ray is hit where its distance to the axis equals the radius.
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0.3, 0.8, -1.));
+        let hit = cylinder.intersect(&ray, 0.001, f32::MAX).unwrap();
+        let radial = cylinder.perpendicular(&(hit.point_at_parameter - Vec3(0., 0., -3.)));
+        assert!((radial.length() - 1.).abs() < 1e-5);
+        assert!(dot(&hit.normal, &Vec3(0., 1., 0.)).abs() < 1e-6);
+        assert!((hit.normal.length() - 1.).abs() < 1e-5);
+        // Passing beside the cylinder.
+        let ray = Ray::new(Vec3(1.5, 0., 0.), Vec3(0., 0., -1.));
+        assert!(cylinder.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    // Test that rays parallel to the axis, also on the axis, are handled.
+    fn test_infinite_cylinder_parallel_rays() {
+        let cylinder = cylinder(1.);
+        for origin in [Vec3(0., 0., -3.), Vec3(0.5, 0., -3.), Vec3(3., 0., -3.)] {
+            let ray = Ray::new(origin, Vec3(0., 1., 0.));
+            assert!(cylinder.intersect(&ray, 0.001, f32::MAX).is_none());
+            assert!(cylinder.intersect_all(&ray).is_empty());
+        }
+    }
+
+    #[test]
+    // Test that a negative radius turns the cylinder into the inside of a tube.
+    fn test_infinite_cylinder_tube() {
+        let tube = cylinder(-1.);
+        // From inside, the wall ahead is hit with a normal pointing back inside.
+        let ray = Ray::new(Vec3(0., 0., -3.), Vec3(1., 0., 0.));
+        let hit = tube.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.parameter - 1.).abs() < 1e-5);
+        assert!((hit.normal - Vec3(-1., 0., 0.)).length() < 1e-5);
+        let hits = tube.intersect_all(&ray);
+        assert_eq!(hits.len(), 2);
+        assert!((hits[0].parameter + 1.).abs() < 1e-5);
+        assert!((hits[0].normal - Vec3(1., 0., 0.)).length() < 1e-5);
+    }
+}
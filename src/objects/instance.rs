@@ -0,0 +1,210 @@
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::objects::rotate::RotateY;
+use crate::objects::translate::Translate;
+use crate::objects::Hitable;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+/// A placement of shared geometry in the scene.
+///
+/// The geometry is rotated around the y-axis and then moved by an offset.
+/// Only a pointer to the geometry is stored, such that the same mesh can be
+/// stamped many times while its data is kept in memory once. For other
+/// placements, the shared geometry can be wrapped in any of the transform
+/// wrappers directly, since `Arc<dyn Hitable>` is hitable itself.
+pub struct Instance {
+    rotation_y: f32,
+    placed: Translate,
+}
+
+impl Instance {
+    /// Place `geometry` rotated by `rotation_y` degrees around the y-axis and
+    /// moved by `offset`.
+    ///
+    /// ```
+    /// use raytracer::objects::instance::Instance;
+    /// use raytracer::objects::sphere::Sphere;
+    /// use raytracer::objects::{Hitable, HitableList};
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let ball: Arc<dyn Hitable> = Arc::new(Sphere::new(Vec3(0., 0., 0.), 0.5, material));
+    /// let row: Vec<Box<dyn Hitable>> = (0..10)
+    ///     .map(|i| {
+    ///         let instance = Instance::new(ball.clone(), 0., Vec3(i as f32, 0.5, -3.));
+    ///         Box::new(instance) as Box<dyn Hitable>
+    ///     })
+    ///     .collect();
+    /// let world = HitableList::new(row);
+    /// assert_eq!(Arc::strong_count(&ball), 11);
+    /// ```
+    pub fn new(geometry: Arc<dyn Hitable>, rotation_y: f32, offset: Vec3) -> Instance {
+        let rotated = RotateY::new(Box::new(geometry), rotation_y);
+        Instance {
+            rotation_y,
+            placed: Translate::new(Box::new(rotated), offset),
+        }
+    }
+
+    /// Access the angle in degrees by which the geometry is rotated around the y-axis.
+    pub fn rotation_y(&self) -> f32 {
+        self.rotation_y
+    }
+
+    /// Access the offset by which the geometry is moved.
+    pub fn offset(&self) -> &Vec3 {
+        self.placed.offset()
+    }
+}
+
+impl Hitable for Instance {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.placed.intersect(ray, t_min, t_max)
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        self.placed.intersect_all(ray)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.placed.bounding_box()
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+    use crate::materials::MaterialExt;
+    use crate::objects::bvh::BvhBuildStrategy;
+    use crate::objects::bvh::BvhNode;
+    use crate::objects::mesh::TriangleMesh;
+    use crate::objects::HitableList;
+
+    // A unit cube between the origin and (1, 1, 1), made of twelve triangles.
+    fn cube() -> TriangleMesh {
+        let material: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        let vertices = vec![
+            Vec3(0., 0., 0.),
+            Vec3(1., 0., 0.),
+            Vec3(1., 1., 0.),
+            Vec3(0., 1., 0.),
+            Vec3(0., 0., 1.),
+            Vec3(1., 0., 1.),
+            Vec3(1., 1., 1.),
+            Vec3(0., 1., 1.),
+        ];
+        let faces = vec![
+            [0, 2, 1],
+            [0, 3, 2],
+            [4, 5, 6],
+            [4, 6, 7],
+            [0, 1, 5],
+            [0, 5, 4],
+            [3, 6, 2],
+            [3, 7, 6],
+            [0, 4, 7],
+            [0, 7, 3],
+            [1, 2, 6],
+            [1, 6, 5],
+        ];
+        TriangleMesh::new(vertices, faces, material).unwrap()
+    }
+
+    fn assert_same_hit(a: &HitRecord, b: &HitRecord) {
+        assert!((a.parameter - b.parameter).abs() < 1e-5);
+        assert!((a.point_at_parameter - b.point_at_parameter).length() < 1e-5);
+        assert!((a.normal - b.normal).length() < 1e-5);
+    }
+
+    #[test]
+    // Test that instances of one mesh are hit at each offset like owned copies.
+    fn test_instances_match_copies() {
+        let mesh: Arc<dyn Hitable> = Arc::new(cube());
+        let offsets = [
+            Vec3(-3., 0., -5.),
+            Vec3(0., 0., -5.),
+            Vec3(3., 0., -5.),
+            Vec3(0., 4., -8.),
+        ];
+        let instances = HitableList::new(
+            offsets
+                .iter()
+                .map(|&offset| {
+                    Box::new(Instance::new(mesh.clone(), 30., offset)) as Box<dyn Hitable>
+                })
+                .collect(),
+        );
+        // The geometry is shared, not copied.
+        assert_eq!(Arc::strong_count(&mesh), offsets.len() + 1);
+
+        for offset in offsets.iter() {
+            let copy = Translate::new(Box::new(RotateY::new(Box::new(cube()), 30.)), *offset);
+            let ray = Ray::new(Vec3(0., 0.5, 0.), *offset + Vec3(0.2, 0.5, 0.3));
+            let expected = copy.intersect(&ray, 0.001, f32::MAX).unwrap();
+            let hit = instances.intersect(&ray, 0.001, f32::MAX).unwrap();
+            assert_same_hit(&hit, &expected);
+        }
+        // Between the instances nothing is hit.
+        let ray = Ray::new(Vec3(0., 0.5, 0.), Vec3(-1.5, 0., -5.));
+        assert!(instances.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    // Test a hierarchy over instances of a mesh hierarchy against a flat list
+    // of the placed meshes.
+    fn test_two_level_hierarchy() {
+        let mesh = Arc::new(cube());
+        let strategy = BvhBuildStrategy::Sah { buckets: 8 };
+        let mesh_bvh: Arc<dyn Hitable> = Arc::new(TriangleMesh::build_bvh(&mesh, strategy));
+        let placements = [
+            (0., Vec3(-3., 0., -5.)),
+            (45., Vec3(0., 1., -6.)),
+            (120., Vec3(2.5, -1., -4.)),
+        ];
+        let instances: Vec<Box<dyn Hitable>> = placements
+            .iter()
+            .map(|&(angle, offset)| {
+                Box::new(Instance::new(mesh_bvh.clone(), angle, offset)) as Box<dyn Hitable>
+            })
+            .collect();
+        let scene = BvhNode::new(instances);
+        // The triangles are stored once, by the hierarchy of the mesh, which
+        // is stored once for all instances.
+        assert_eq!(Arc::strong_count(&mesh), mesh.faces().len() + 1);
+        assert_eq!(Arc::strong_count(&mesh_bvh), placements.len() + 1);
+
+        let flat = HitableList::new(
+            placements
+                .iter()
+                .map(|&(angle, offset)| {
+                    let rotated = RotateY::new(Box::new(cube()), angle);
+                    Box::new(Translate::new(Box::new(rotated), offset)) as Box<dyn Hitable>
+                })
+                .collect(),
+        );
+        let mut hits = 0;
+        for y in 0..30 {
+            for x in 0..60 {
+                // Off the edges of the cubes, where faces tie in any order.
+                let target = Vec3(x as f32 * 0.15 - 4.513, y as f32 * 0.15 - 2.017, -5.);
+                let ray = Ray::new(Vec3(0., 0.5, 0.), target - Vec3(0., 0.5, 0.));
+                let expected = flat.intersect(&ray, 0.001, f32::MAX);
+                let hit = scene.intersect(&ray, 0.001, f32::MAX);
+                assert_eq!(hit.is_some(), expected.is_some());
+                if let (Some(hit), Some(expected)) = (hit, expected) {
+                    assert_same_hit(&hit, &expected);
+                    hits += 1;
+                }
+            }
+        }
+        assert!(hits > 100);
+    }
+}
@@ -0,0 +1,720 @@
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::materials::MaterialExt;
+use crate::objects::bvh::BvhBuildStrategy;
+use crate::objects::bvh::BvhFileError;
+use crate::objects::bvh::BvhNode;
+use crate::objects::triangle::facing_normal;
+use crate::objects::triangle::interpolate_normal;
+use crate::objects::triangle::intersect_triangle;
+use crate::objects::triangle::tangent_frame;
+use crate::objects::triangle::Sidedness;
+use crate::objects::Hitable;
+use crate::objects::ObjectError;
+use crate::ray::Ray;
+use crate::vec3::cross;
+use crate::vec3::dot;
+use crate::vec3::unit_vector;
+use crate::vec3::Vec3;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A mesh of triangles sharing their vertices.
+///
+/// It is characterized by:
+/// - A list of vertex positions.
+/// - A list of faces, each given by the indices of its three vertices in
+///   counter-clockwise order when seen from the front.
+/// - Optional normals at the vertices, which are interpolated across the
+///   faces for smooth shading.
+/// - The sides of the faces which can be hit, both by default.
+/// - A pointer to the material that it is made of.
+pub struct TriangleMesh {
+    vertices: Vec<Vec3>,
+    faces: Vec<[usize; 3]>,
+    normals: Option<Vec<Vec3>>,
+    sidedness: Sidedness,
+    material: Arc<dyn MaterialExt>,
+}
+
+impl TriangleMesh {
+    /// Create a flat-shaded `TriangleMesh` from its `vertices`, `faces` and `Material`.
+    ///
+    /// An error is returned if a face refers to a vertex that does not exist.
+    ///
+    /// ```
+    /// use raytracer::objects::mesh::TriangleMesh;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let vertices = vec![
+    ///     Vec3(0., 0., 0.),
+    ///     Vec3(1., 0., 0.),
+    ///     Vec3(1., 1., 0.),
+    ///     Vec3(0., 1., 0.),
+    /// ];
+    /// let square = TriangleMesh::new(vertices.clone(), vec![[0, 1, 2], [0, 2, 3]], material.clone());
+    /// assert!(square.is_ok());
+    /// let invalid = TriangleMesh::new(vertices, vec![[0, 1, 4]], material);
+    /// assert!(invalid.is_err());
+    /// ```
+    pub fn new(
+        vertices: Vec<Vec3>,
+        faces: Vec<[usize; 3]>,
+        material: Arc<dyn MaterialExt>,
+    ) -> Result<TriangleMesh, ObjectError> {
+        for (face, indices) in faces.iter().enumerate() {
+            if let Some(&index) = indices.iter().find(|&&index| index >= vertices.len()) {
+                return Err(ObjectError::InvalidVertexIndex { face, index });
+            }
+        }
+        Ok(TriangleMesh {
+            vertices,
+            faces,
+            normals: None,
+            sidedness: Sidedness::default(),
+            material,
+        })
+    }
+
+    /// Set the normals at the vertices, making the mesh smooth-shaded.
+    ///
+    /// There must be exactly one normal per vertex. The normals do not need
+    /// to be normalized.
+    pub fn with_vertex_normals(mut self, normals: Vec<Vec3>) -> Result<TriangleMesh, ObjectError> {
+        if normals.len() != self.vertices.len() {
+            return Err(ObjectError::VertexNormalCount {
+                vertices: self.vertices.len(),
+                normals: normals.len(),
+            });
+        }
+        self.normals = Some(normals.iter().map(unit_vector).collect());
+        Ok(self)
+    }
+
+    /// Set the sides of the faces from which the `TriangleMesh` can be hit.
+    ///
+    /// Closed meshes seen from outside only need their front sides.
+    pub fn with_sidedness(mut self, sidedness: Sidedness) -> TriangleMesh {
+        self.sidedness = sidedness;
+        self
+    }
+
+    /// Compute smooth vertex normals from the faces of the mesh.
+    ///
+    /// The normal at a vertex is the average of the normals of the adjacent
+    /// faces, weighted by their area. Any existing vertex normals are replaced.
+    ///
+    /// ```
+    /// # use raytracer::objects::mesh::TriangleMesh;
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::materials::Lambertian;
+    /// # use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// // Two faces of a roof meeting at a ridge along the x-axis.
+    /// let vertices = vec![
+    ///     Vec3(0., 0., 0.),
+    ///     Vec3(1., 0., 0.),
+    ///     Vec3(0., -1., 1.),
+    ///     Vec3(0., -1., -1.),
+    /// ];
+    /// let mut roof = TriangleMesh::new(vertices, vec![[0, 2, 1], [0, 1, 3]], material).unwrap();
+    /// roof.compute_vertex_normals();
+    /// assert_eq!(roof.vertex_normals().unwrap()[1], Vec3(0., 1., 0.));
+    /// ```
+    pub fn compute_vertex_normals(&mut self) {
+        let mut normals = vec![Vec3(0., 0., 0.); self.vertices.len()];
+        for [i0, i1, i2] in &self.faces {
+            let (p0, p1, p2) = (self.vertices[*i0], self.vertices[*i1], self.vertices[*i2]);
+            // The length of the cross product is twice the area of the face.
+            let weighted_normal = cross(&(p1 - p0), &(p2 - p0));
+            for &index in &[*i0, *i1, *i2] {
+                normals[index] += weighted_normal;
+            }
+        }
+        self.normals = Some(
+            normals
+                .iter()
+                .map(|normal| {
+                    // Vertices without any face keep a zero normal, which the
+                    // interpolation replaces by the face normal.
+                    if normal.squared_length() > 0. {
+                        unit_vector(normal)
+                    } else {
+                        *normal
+                    }
+                })
+                .collect(),
+        );
+    }
+
+    /// Reverse the winding of all faces, turning the mesh inside out.
+    ///
+    /// Any vertex normals are reversed as well.
+    ///
+    /// ```
+    /// # use raytracer::objects::mesh::TriangleMesh;
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::materials::Lambertian;
+    /// # use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let vertices = vec![Vec3(0., 0., 0.), Vec3(1., 0., 0.), Vec3(0., 1., 0.)];
+    /// let mut triangle = TriangleMesh::new(vertices, vec![[0, 1, 2]], material).unwrap();
+    /// triangle.flip_winding();
+    /// assert_eq!(triangle.faces(), &[[0, 2, 1]]);
+    /// ```
+    pub fn flip_winding(&mut self) {
+        for face in &mut self.faces {
+            face.swap(1, 2);
+        }
+        if let Some(normals) = &mut self.normals {
+            for normal in normals.iter_mut() {
+                *normal = -*normal;
+            }
+        }
+    }
+
+    /// Orient the faces consistently, such that faces sharing an edge
+    /// traverse it in opposite directions, and return the number of faces
+    /// whose winding was reversed.
+    ///
+    /// The orientation spreads over edge-adjacent faces from the first face
+    /// of each connected part of the mesh, which keeps its winding. Whether
+    /// that leaves closed parts facing outwards depends on this face. Vertex
+    /// normals are not changed and may need to be computed again.
+    ///
+    /// An error is returned, leaving the mesh unchanged, if an edge is shared
+    /// by more than two faces or if the mesh cannot be oriented.
+    ///
+    /// ```
+    /// # use raytracer::objects::mesh::TriangleMesh;
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::materials::Lambertian;
+    /// # use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let vertices = vec![
+    ///     Vec3(0., 0., 0.),
+    ///     Vec3(1., 0., 0.),
+    ///     Vec3(1., 1., 0.),
+    ///     Vec3(0., 1., 0.),
+    /// ];
+    /// // The second face of the square is wound the wrong way.
+    /// let mut square = TriangleMesh::new(vertices, vec![[0, 1, 2], [0, 3, 2]], material).unwrap();
+    /// assert_eq!(square.make_winding_consistent(), Ok(1));
+    /// assert_eq!(square.faces(), &[[0, 1, 2], [0, 2, 3]]);
+    /// ```
+    pub fn make_winding_consistent(&mut self) -> Result<usize, ObjectError> {
+        // Map each undirected edge to the faces containing it.
+        let mut adjacency: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (index, face) in self.faces.iter().enumerate() {
+            for k in 0..3 {
+                let (a, b) = (face[k], face[(k + 1) % 3]);
+                adjacency
+                    .entry((a.min(b), a.max(b)))
+                    .or_default()
+                    .push(index);
+            }
+        }
+        if let Some((&vertices, faces)) = adjacency
+            .iter()
+            .filter(|(_, faces)| faces.len() > 2)
+            .min_by_key(|(&edge, _)| edge)
+        {
+            return Err(ObjectError::NonManifoldEdge {
+                vertices,
+                faces: faces.len(),
+            });
+        }
+
+        let traverses = |face: &[usize; 3], a: usize, b: usize| {
+            (0..3).any(|k| face[k] == a && face[(k + 1) % 3] == b)
+        };
+        let mut flipped: Vec<Option<bool>> = vec![None; self.faces.len()];
+        let mut queue = VecDeque::new();
+        for start in 0..self.faces.len() {
+            if flipped[start].is_some() {
+                continue;
+            }
+            flipped[start] = Some(false);
+            queue.push_back(start);
+            while let Some(index) = queue.pop_front() {
+                let face = self.faces[index];
+                let face_flipped = flipped[index].unwrap();
+                for k in 0..3 {
+                    let (a, b) = (face[k], face[(k + 1) % 3]);
+                    for &neighbor in &adjacency[&(a.min(b), a.max(b))] {
+                        if neighbor == index {
+                            continue;
+                        }
+                        // A neighbor traversing the edge in the same direction
+                        // must be wound the other way than this face.
+                        let required = face_flipped != traverses(&self.faces[neighbor], a, b);
+                        match flipped[neighbor] {
+                            None => {
+                                flipped[neighbor] = Some(required);
+                                queue.push_back(neighbor);
+                            }
+                            Some(neighbor_flipped) if neighbor_flipped != required => {
+                                return Err(ObjectError::NonOrientableMesh);
+                            }
+                            Some(_) => {}
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut count = 0;
+        for (face, flip) in self.faces.iter_mut().zip(flipped) {
+            if flip == Some(true) {
+                face.swap(1, 2);
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Access the vertices of a `TriangleMesh`.
+    pub fn vertices(&self) -> &[Vec3] {
+        &self.vertices
+    }
+
+    /// Access the faces of a `TriangleMesh` as indices into its vertices.
+    pub fn faces(&self) -> &[[usize; 3]] {
+        &self.faces
+    }
+
+    /// Access the vertex normals of a smooth-shaded `TriangleMesh`.
+    pub fn vertex_normals(&self) -> Option<&[Vec3]> {
+        self.normals.as_deref()
+    }
+
+    /// Access the sides of the faces from which a `TriangleMesh` can be hit.
+    pub fn sidedness(&self) -> Sidedness {
+        self.sidedness
+    }
+
+    /// Access the `material` a `TriangleMesh` is made of.
+    pub fn material(&self) -> Arc<dyn MaterialExt> {
+        Arc::clone(&self.material)
+    }
+
+    /// Build a bounding volume hierarchy over the faces of a shared mesh.
+    ///
+    /// The faces of the hierarchy refer to the shared mesh, whose data is
+    /// therefore kept in memory once. Placing the hierarchy itself, shared
+    /// through an `Arc`, with several `Instance`s, and building a hierarchy
+    /// over the instances gives a two-level structure: rays are transformed
+    /// into the frame of an instance once, and then descend into the
+    /// hierarchy of the mesh.
+    ///
+    /// ```
+    /// use raytracer::objects::bvh::{BvhBuildStrategy, BvhNode};
+    /// use raytracer::objects::instance::Instance;
+    /// use raytracer::objects::mesh::TriangleMesh;
+    /// use raytracer::objects::Hitable;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let vertices = vec![Vec3(0., 0., 0.), Vec3(1., 0., 0.), Vec3(0., 1., 0.)];
+    /// let mesh = Arc::new(TriangleMesh::new(vertices, vec![[0, 1, 2]], material).unwrap());
+    /// let strategy = BvhBuildStrategy::Sah { buckets: 12 };
+    /// let bvh: Arc<dyn Hitable> = Arc::new(TriangleMesh::build_bvh(&mesh, strategy));
+    /// let instances: Vec<Box<dyn Hitable>> = (0..3)
+    ///     .map(|i| {
+    ///         let instance = Instance::new(bvh.clone(), 0., Vec3(i as f32 * 2., 0., -3.));
+    ///         Box::new(instance) as Box<dyn Hitable>
+    ///     })
+    ///     .collect();
+    /// let world = BvhNode::new(instances);
+    /// ```
+    pub fn build_bvh(mesh: &Arc<TriangleMesh>, strategy: BvhBuildStrategy) -> BvhNode {
+        BvhNode::with_strategy(TriangleMesh::shared_faces(mesh), strategy)
+    }
+
+    /// Load a bounding volume hierarchy over the faces of a shared mesh,
+    /// which was built by `build_bvh` and saved with `BvhNode::save`.
+    ///
+    /// Loading fails if the vertices or the number of faces of the mesh
+    /// changed since, see `BvhNode::load`.
+    pub fn load_bvh<P: AsRef<Path>>(
+        mesh: &Arc<TriangleMesh>,
+        path: P,
+    ) -> Result<BvhNode, BvhFileError> {
+        BvhNode::load(path, TriangleMesh::shared_faces(mesh))
+    }
+
+    // The faces of a shared mesh as separate objects.
+    fn shared_faces(mesh: &Arc<TriangleMesh>) -> Vec<Box<dyn Hitable>> {
+        (0..mesh.faces.len())
+            .map(|face| {
+                Box::new(MeshFace {
+                    mesh: mesh.clone(),
+                    face,
+                }) as Box<dyn Hitable>
+            })
+            .collect()
+    }
+
+    // Intersect a ray with the face of index `face`, returning the ray
+    // parameter and the barycentric coordinates of the hit.
+    fn intersect_face(
+        &self,
+        face: usize,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<(f32, f32, f32)> {
+        let face = &self.faces[face];
+        let [p0, p1, p2] = [
+            &self.vertices[face[0]],
+            &self.vertices[face[1]],
+            &self.vertices[face[2]],
+        ];
+        if self.sidedness != Sidedness::DoubleSided
+            && !self
+                .sidedness
+                .accepts(ray, &cross(&(*p1 - *p0), &(*p2 - *p0)))
+        {
+            return None;
+        }
+        intersect_triangle(ray, p0, p1, p2, t_min, t_max)
+    }
+
+    // Create the record of a hit of the face of index `face`.
+    fn hit_record(&self, ray: &Ray, face: usize, (t, u, v): (f32, f32, f32)) -> HitRecord<'_> {
+        let face = &self.faces[face];
+        let [p0, p1, p2] = [
+            self.vertices[face[0]],
+            self.vertices[face[1]],
+            self.vertices[face[2]],
+        ];
+        let geometric = unit_vector(&cross(&(p1 - p0), &(p2 - p0)));
+        let normal = match &self.normals {
+            Some(normals) => interpolate_normal(
+                &[normals[face[0]], normals[face[1]], normals[face[2]]],
+                u,
+                v,
+                &geometric,
+            ),
+            None => geometric,
+        };
+        let normal = facing_normal(ray, &geometric, normal);
+        let (tangent, bitangent) = tangent_frame(&p0, &p1, &p2, &normal);
+        HitRecord {
+            parameter: t,
+            point_at_parameter: ray.point_at_parameter(t),
+            normal,
+            u,
+            v,
+            tangent,
+            bitangent,
+            front_face: dot(ray.direction(), &geometric) < 0.,
+            material: self.material.as_ref(),
+        }
+    }
+}
+
+impl Hitable for TriangleMesh {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let mut closest = None;
+        let mut closest_so_far = t_max;
+        for face in 0..self.faces.len() {
+            if let Some(hit) = self.intersect_face(face, ray, t_min, closest_so_far) {
+                closest_so_far = hit.0;
+                closest = Some((face, hit));
+            }
+        }
+        let (face, hit) = closest?;
+        Some(self.hit_record(ray, face, hit))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Aabb::from_points(&self.vertices)
+    }
+}
+
+// A single face of a shared mesh, to sort the faces into a hierarchy.
+struct MeshFace {
+    mesh: Arc<TriangleMesh>,
+    face: usize,
+}
+
+impl Hitable for MeshFace {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let hit = self.mesh.intersect_face(self.face, ray, t_min, t_max)?;
+        Some(self.mesh.hit_record(ray, self.face, hit))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let face = &self.mesh.faces[self.face];
+        Aabb::from_points(face.iter().map(|&index| &self.mesh.vertices[index]))
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::reflect;
+    use crate::materials::Lambertian;
+    use crate::objects::sphere::Sphere;
+    use crate::vec3::dot;
+    use std::f32::consts::PI;
+
+    fn material() -> Arc<dyn MaterialExt> {
+        Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)))
+    }
+
+    // A unit sphere at the origin made of `rings` by `segments` quads.
+    fn low_poly_sphere(rings: usize, segments: usize) -> TriangleMesh {
+        let mut vertices = Vec::new();
+        for i in 0..=rings {
+            let theta = PI * i as f32 / rings as f32;
+            for j in 0..segments {
+                let phi = 2. * PI * j as f32 / segments as f32;
+                vertices.push(Vec3(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    -theta.sin() * phi.sin(),
+                ));
+            }
+        }
+        let index = |i: usize, j: usize| i * segments + j % segments;
+        let mut faces = Vec::new();
+        for i in 0..rings {
+            for j in 0..segments {
+                faces.push([index(i, j), index(i + 1, j), index(i + 1, j + 1)]);
+                faces.push([index(i, j), index(i + 1, j + 1), index(i, j + 1)]);
+            }
+        }
+        TriangleMesh::new(vertices, faces, material()).unwrap()
+    }
+
+    // Render the specular highlight of a light from `light` on `object` seen
+    // along -z, and return the pixel with the brightest highlight.
+    fn highlight_position(object: &dyn Hitable, light: &Vec3) -> (usize, usize) {
+        let n = 80;
+        let mut brightest = (0., (0, 0));
+        for y in 0..n {
+            for x in 0..n {
+                let origin = Vec3(
+                    2.4 * (x as f32 + 0.5) / n as f32 - 1.2,
+                    2.4 * (y as f32 + 0.5) / n as f32 - 1.2,
+                    5.,
+                );
+                let ray = Ray::new(origin, Vec3(0., 0., -1.));
+                if let Some(hit) = object.intersect(&ray, 0.001, f32::MAX) {
+                    let reflected = reflect(ray.direction(), &hit.normal);
+                    let specular = dot(&reflected, light).max(0.).powi(200);
+                    if specular > brightest.0 {
+                        brightest = (specular, (x, y));
+                    }
+                }
+            }
+        }
+        brightest.1
+    }
+
+    #[test]
+    // Test that the highlight on a smooth low-poly sphere matches the analytic sphere.
+    fn test_smooth_shading_highlight() {
+        let light = unit_vector(&Vec3(0.3, 0.5, 1.));
+        let sphere = Sphere::new(Vec3(0., 0., 0.), 1., material());
+        let expected = highlight_position(&sphere, &light);
+
+        let mut mesh = low_poly_sphere(12, 16);
+        let flat = highlight_position(&mesh, &light);
+        mesh.compute_vertex_normals();
+        let smooth = highlight_position(&mesh, &light);
+
+        let distance = |a: (usize, usize), b: (usize, usize)| {
+            ((a.0 as f32 - b.0 as f32).powi(2) + (a.1 as f32 - b.1 as f32).powi(2)).sqrt()
+        };
+        assert!(distance(smooth, expected) <= 2.);
+        assert!(distance(smooth, expected) < distance(flat, expected));
+    }
+
+    #[test]
+    // Test that a front-only quad is invisible from behind.
+    fn test_mesh_front_only_quad() {
+        let vertices = vec![
+            Vec3(-1., -1., -2.),
+            Vec3(1., -1., -2.),
+            Vec3(1., 1., -2.),
+            Vec3(-1., 1., -2.),
+        ];
+        let quad = TriangleMesh::new(vertices, vec![[0, 1, 2], [0, 2, 3]], material()).unwrap();
+        let from_behind = Ray::new(Vec3(0.3, 0.2, -4.), Vec3(0., 0., 1.));
+        let hit = quad.intersect(&from_behind, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.normal, Vec3(0., 0., -1.));
+
+        let quad = quad.with_sidedness(Sidedness::FrontOnly);
+        assert!(quad.intersect(&from_behind, 0.001, f32::MAX).is_none());
+        let from_front = Ray::new(Vec3(0.3, 0.2, 0.), Vec3(0., 0., -1.));
+        let hit = quad.intersect(&from_front, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.normal, Vec3(0., 0., 1.));
+    }
+
+    #[test]
+    // Test that the area-weighted vertex normals of a sphere point outwards.
+    fn test_compute_vertex_normals() {
+        let mut mesh = low_poly_sphere(6, 8);
+        assert!(mesh.vertex_normals().is_none());
+        mesh.compute_vertex_normals();
+        for (vertex, normal) in mesh.vertices().iter().zip(mesh.vertex_normals().unwrap()) {
+            assert!((normal.length() - 1.).abs() < 1e-5);
+            assert!(dot(vertex, normal) > 0.95);
+        }
+    }
+
+    #[test]
+    // Test that vertex normals must match the number of vertices.
+    fn test_vertex_normal_count() {
+        let mesh = low_poly_sphere(2, 3);
+        assert_eq!(
+            mesh.with_vertex_normals(vec![Vec3(0., 1., 0.)]).err(),
+            Some(ObjectError::VertexNormalCount {
+                vertices: 9,
+                normals: 1
+            })
+        );
+    }
+
+    // The faces of a unit cube between the origin and (1, 1, 1), wound
+    // counter-clockwise when seen from outside.
+    fn cube(faces: Vec<[usize; 3]>) -> TriangleMesh {
+        let vertices = vec![
+            Vec3(0., 0., 0.),
+            Vec3(1., 0., 0.),
+            Vec3(1., 1., 0.),
+            Vec3(0., 1., 0.),
+            Vec3(0., 0., 1.),
+            Vec3(1., 0., 1.),
+            Vec3(1., 1., 1.),
+            Vec3(0., 1., 1.),
+        ];
+        TriangleMesh::new(vertices, faces, material()).unwrap()
+    }
+
+    const CUBE_FACES: [[usize; 3]; 12] = [
+        [0, 2, 1],
+        [0, 3, 2],
+        [4, 5, 6],
+        [4, 6, 7],
+        [0, 1, 5],
+        [0, 5, 4],
+        [3, 6, 2],
+        [3, 7, 6],
+        [0, 4, 7],
+        [0, 7, 3],
+        [1, 2, 6],
+        [1, 6, 5],
+    ];
+
+    // Whether the normal of each face points away from the center of the cube.
+    fn faces_point_outwards(mesh: &TriangleMesh) -> Vec<bool> {
+        mesh.faces()
+            .iter()
+            .map(|face| {
+                let [p0, p1, p2] = [
+                    mesh.vertices()[face[0]],
+                    mesh.vertices()[face[1]],
+                    mesh.vertices()[face[2]],
+                ];
+                let normal = cross(&(p1 - p0), &(p2 - p0));
+                let centroid = (p0 + p1 + p2) / 3.;
+                dot(&normal, &(centroid - Vec3(0.5, 0.5, 0.5))) > 0.
+            })
+            .collect()
+    }
+
+    #[test]
+    // Test that two reversed faces of a cube are repaired.
+    fn test_make_winding_consistent() {
+        let mut faces = CUBE_FACES.to_vec();
+        faces[3].swap(0, 1);
+        faces[10].swap(1, 2);
+        let mut mesh = cube(faces);
+        let outwards = faces_point_outwards(&mesh);
+        assert_eq!(outwards.iter().filter(|&&outwards| !outwards).count(), 2);
+        assert_eq!(mesh.make_winding_consistent(), Ok(2));
+        assert!(faces_point_outwards(&mesh).iter().all(|&outwards| outwards));
+        // A consistent mesh is left alone.
+        assert_eq!(mesh.make_winding_consistent(), Ok(0));
+
+        mesh.compute_vertex_normals();
+        mesh.flip_winding();
+        assert!(faces_point_outwards(&mesh)
+            .iter()
+            .all(|&outwards| !outwards));
+        for (vertex, normal) in mesh.vertices().iter().zip(mesh.vertex_normals().unwrap()) {
+            assert!(dot(&(*vertex - Vec3(0.5, 0.5, 0.5)), normal) < 0.);
+        }
+    }
+
+    #[test]
+    // Test that non-manifold and non-orientable meshes are reported.
+    fn test_winding_errors() {
+        // A third face on an edge of the cube.
+        let mut faces = CUBE_FACES.to_vec();
+        faces.push([0, 1, 6]);
+        let mut mesh = cube(faces.clone());
+        assert_eq!(
+            mesh.make_winding_consistent(),
+            Err(ObjectError::NonManifoldEdge {
+                vertices: (0, 1),
+                faces: 3
+            })
+        );
+        assert_eq!(mesh.faces(), &faces[..]);
+
+        // A strip of three quads joined after a half twist.
+        let vertices = (0..6).map(|i| Vec3(i as f32, (i % 2) as f32, 0.)).collect();
+        let faces = vec![
+            [0, 1, 3],
+            [0, 3, 2],
+            [2, 3, 5],
+            [2, 5, 4],
+            [4, 5, 0],
+            [4, 0, 1],
+        ];
+        let mut strip = TriangleMesh::new(vertices, faces, material()).unwrap();
+        assert_eq!(
+            strip.make_winding_consistent(),
+            Err(ObjectError::NonOrientableMesh)
+        );
+    }
+
+    #[test]
+    // Test that the saved hierarchy of a mesh is loaded for the same mesh only.
+    fn test_load_bvh() {
+        let mesh = Arc::new(low_poly_sphere(16, 32));
+        let bvh = TriangleMesh::build_bvh(&mesh, BvhBuildStrategy::Sah { buckets: 12 });
+        let path = std::env::temp_dir().join("raytracer_test_mesh_load_bvh.bvh");
+        bvh.save(&path).unwrap();
+        let loaded = TriangleMesh::load_bvh(&mesh, &path).unwrap();
+        for i in 0..20 {
+            let ray = Ray::new(Vec3(i as f32 / 20. - 0.5, 0.3, 2.), Vec3(0., 0., -1.));
+            assert_eq!(
+                loaded
+                    .intersect(&ray, 0.001, f32::MAX)
+                    .map(|hit| hit.parameter),
+                bvh.intersect(&ray, 0.001, f32::MAX)
+                    .map(|hit| hit.parameter)
+            );
+        }
+        let finer = Arc::new(low_poly_sphere(16, 33));
+        assert!(matches!(
+            TriangleMesh::load_bvh(&finer, &path),
+            Err(BvhFileError::GeometryMismatch { .. })
+        ));
+    }
+}
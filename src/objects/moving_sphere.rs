@@ -0,0 +1,189 @@
+use crate::aabb::surrounding_box;
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::materials::MaterialExt;
+use crate::objects::sphere::sphere_tangents;
+use crate::objects::sphere::sphere_uv;
+use crate::objects::Hitable;
+use crate::ray::Ray;
+use crate::vec3::dot;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+/// A sphere moving along a straight line, used to render motion blur.
+///
+/// It is characterized by:
+/// - The coordinates of its center `center0` at time `time0`.
+/// - The coordinates of its center `center1` at time `time1`.
+/// - Its radius.
+/// - A pointer to the material that it is made of.
+///
+/// In between the two times the center is linearly interpolated. Before
+/// `time0` and after `time1` the sphere rests at `center0` and `center1`.
+pub struct MovingSphere {
+    center0: Vec3,
+    center1: Vec3,
+    time0: f32,
+    time1: f32,
+    radius: f32,
+    material: Arc<dyn MaterialExt>,
+}
+
+impl MovingSphere {
+    /// Create a `MovingSphere` moving from `center0` at `time0` to `center1` at `time1`.
+    ///
+    /// ```
+    /// use raytracer::objects::moving_sphere::MovingSphere;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let sphere = MovingSphere::new(Vec3(0., 0., 0.), Vec3(0., 1., 0.), 0., 1., 0.5, material);
+    /// ```
+    pub fn new(
+        center0: Vec3,
+        center1: Vec3,
+        time0: f32,
+        time1: f32,
+        radius: f32,
+        material: Arc<dyn MaterialExt>,
+    ) -> MovingSphere {
+        MovingSphere {
+            center0,
+            center1,
+            time0,
+            time1,
+            radius,
+            material,
+        }
+    }
+
+    /// Return the center of a `MovingSphere` at the given `time`.
+    ///
+    /// Times outside of `[time0, time1]` are clamped to that interval.
+    ///
+    /// ```
+    /// # use raytracer::objects::moving_sphere::MovingSphere;
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::materials::Lambertian;
+    /// # use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let sphere = MovingSphere::new(Vec3(0., 0., 0.), Vec3(0., 1., 0.), 0., 1., 0.5, material);
+    /// assert_eq!(sphere.center(0.5), Vec3(0., 0.5, 0.));
+    /// assert_eq!(sphere.center(2.), Vec3(0., 1., 0.));
+    /// assert_eq!(sphere.center(-1.), Vec3(0., 0., 0.));
+    /// ```
+    pub fn center(&self, time: f32) -> Vec3 {
+        if self.time1 == self.time0 {
+            return self.center0;
+        }
+        let fraction = ((time - self.time0) / (self.time1 - self.time0)).clamp(0., 1.);
+        self.center0 + fraction * (self.center1 - self.center0)
+    }
+
+    /// Access the `radius` of a `MovingSphere`.
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// Access the `material` a `MovingSphere` is made of.
+    pub fn material(&self) -> Arc<dyn MaterialExt> {
+        Arc::clone(&self.material)
+    }
+}
+
+impl Hitable for MovingSphere {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let center = self.center(ray.time());
+        let oc = *ray.origin() - center;
+        // Construct the coefficients in a quadratic equation a*x^2 + b*x + c.
+        let a = dot(ray.direction(), ray.direction());
+        let b = dot(&oc, ray.direction());
+        let c = dot(&oc, &oc) - self.radius.powi(2);
+        let discriminant = b * b - a * c;
+        if discriminant > 0. {
+            for t_candidate in [
+                (-b - discriminant.sqrt()) / a,
+                (-b + discriminant.sqrt()) / a,
+            ] {
+                if t_candidate > t_min && t_candidate < t_max {
+                    let point = ray.point_at_parameter(t_candidate);
+                    let outward = (point - center) / self.radius.abs();
+                    let (u, v) = sphere_uv(&outward);
+                    let (tangent, bitangent) = sphere_tangents(&outward);
+                    let normal = (point - center) / self.radius;
+                    return Some(HitRecord {
+                        parameter: t_candidate,
+                        point_at_parameter: point,
+                        normal,
+                        u,
+                        v,
+                        tangent,
+                        bitangent,
+                        front_face: dot(ray.direction(), &normal) < 0.,
+                        material: self.material.as_ref(),
+                    });
+                }
+            }
+        }
+
+        None
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // The sphere rests at the end points of its path outside of the time
+        // interval, so the boxes around these contain it at all times.
+        let extent = Vec3(self.radius.abs(), self.radius.abs(), self.radius.abs());
+        Some(surrounding_box(
+            &Aabb::new(self.center0 - extent, self.center0 + extent),
+            &Aabb::new(self.center1 - extent, self.center1 + extent),
+        ))
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+
+    fn sphere() -> MovingSphere {
+        MovingSphere::new(
+            Vec3(0., 0., -3.),
+            Vec3(5., 0., -3.),
+            0.,
+            1.,
+            1.,
+            Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))),
+        )
+    }
+
+    #[test]
+    // Test that the same ray hits the sphere at `time0` and misses it at `time1`.
+    fn test_moving_sphere_hit_and_miss() {
+        let sphere = sphere();
+        let ray = Ray::new_with_time(Vec3(0., 0., 0.), Vec3(0., 0., -1.), 0.);
+        let hit = sphere.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.parameter, 2.);
+        assert_eq!(hit.normal, Vec3(0., 0., 1.));
+        let ray = Ray::new_with_time(Vec3(0., 0., 0.), Vec3(0., 0., -1.), 1.);
+        assert!(sphere.intersect(&ray, 0.001, f32::MAX).is_none());
+        // At the end of the motion the sphere is hit at its new position.
+        let ray = Ray::new_with_time(Vec3(5., 0., 0.), Vec3(0., 0., -1.), 1.);
+        assert!(sphere.intersect(&ray, 0.001, f32::MAX).is_some());
+    }
+
+    #[test]
+    // Test that times outside the motion interval do not extrapolate.
+    fn test_moving_sphere_clamps_time() {
+        let sphere = sphere();
+        let ray = Ray::new_with_time(Vec3(5., 0., 0.), Vec3(0., 0., -1.), 100.);
+        let hit = sphere.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.parameter, 2.);
+        let ray = Ray::new_with_time(Vec3(0., 0., 0.), Vec3(0., 0., -1.), -100.);
+        assert!(sphere.intersect(&ray, 0.001, f32::MAX).is_some());
+    }
+}
@@ -0,0 +1,543 @@
+//! Import of triangle meshes from Wavefront OBJ files and their MTL materials.
+//!
+//! Only the geometry and the material assignment of an OBJ file are read:
+//! vertex positions (`v`), vertex normals (`vn`), polygonal faces (`f`), the
+//! material libraries (`mtllib`) and material switches (`usemtl`). Faces with
+//! more than three vertices are split into a fan of triangles, which assumes
+//! that they are convex. All faces using the same material are collected into
+//! one `TriangleMesh`.
+//!
+//! The MTL materials are mapped onto the built-in materials, see `MtlMaterial`.
+
+use crate::hit_record::HitRecord;
+use crate::materials::Dielectric;
+use crate::materials::Lambertian;
+use crate::materials::MaterialExt;
+use crate::materials::Metal;
+use crate::objects::mesh::TriangleMesh;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use rand::RngCore;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Error returned when an OBJ or MTL file cannot be read.
+#[derive(Debug)]
+pub enum ObjError {
+    /// A file could not be read.
+    Io(io::Error),
+    /// A statement has missing or malformed arguments.
+    InvalidStatement { line: usize, statement: String },
+    /// A face refers to a vertex or normal that does not exist.
+    InvalidIndex { line: usize, index: i64 },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Io(error) => write!(f, "failed to read file: {}", error),
+            ObjError::InvalidStatement { line, statement } => {
+                write!(f, "line {}: invalid statement '{}'", line, statement)
+            }
+            ObjError::InvalidIndex { line, index } => {
+                write!(f, "line {}: index {} does not exist", line, index)
+            }
+        }
+    }
+}
+
+impl Error for ObjError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ObjError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// A material read from an MTL file, approximated by a built-in material.
+///
+/// The MTL parameters are mapped as follows:
+/// - Transparent materials, i.e. with a dissolve `d` below 1 or a
+///   transparency `Tr` above 0, become a `Dielectric` with the optical
+///   density `Ni` as refractive index, 1.5 by default.
+/// - Materials with a specular color `Ks` other than black become a `Metal`
+///   reflecting `Ks`. Its fuzzyness is derived from the specular exponent
+///   `Ns` as `sqrt(2 / (Ns + 2))`, such that shinier materials are less fuzzy.
+/// - All other materials become a `Lambertian` with the diffuse color `Kd`.
+#[derive(Debug)]
+pub enum MtlMaterial {
+    Lambertian(Lambertian),
+    Metal(Metal),
+    Dielectric(Dielectric),
+}
+
+impl MaterialExt for MtlMaterial {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut dyn RngCore) -> Option<(Ray, Vec3)> {
+        match self {
+            MtlMaterial::Lambertian(material) => MaterialExt::scatter(material, ray, hit, rng),
+            MtlMaterial::Metal(material) => MaterialExt::scatter(material, ray, hit, rng),
+            MtlMaterial::Dielectric(material) => MaterialExt::scatter(material, ray, hit, rng),
+        }
+    }
+}
+
+// The material of faces without a known material.
+fn default_material() -> Lambertian {
+    Lambertian::new(Vec3(0.5, 0.5, 0.5))
+}
+
+// The parameters of a material in an MTL file.
+#[derive(Default)]
+struct MtlParameters {
+    diffuse: Option<Vec3>,
+    specular: Option<Vec3>,
+    specular_exponent: Option<f32>,
+    optical_density: Option<f32>,
+    dissolve: Option<f32>,
+}
+
+impl MtlParameters {
+    fn material(&self) -> MtlMaterial {
+        if self.dissolve.is_some_and(|dissolve| dissolve < 1.) {
+            MtlMaterial::Dielectric(Dielectric::new(self.optical_density.unwrap_or(1.5)))
+        } else if let Some(specular) = self.specular.filter(|s| s.squared_length() > 0.) {
+            let exponent = self.specular_exponent.unwrap_or(0.).max(0.);
+            MtlMaterial::Metal(Metal::new(specular, (2. / (exponent + 2.)).sqrt()))
+        } else {
+            MtlMaterial::Lambertian(match self.diffuse {
+                Some(diffuse) => Lambertian::new(diffuse),
+                None => default_material(),
+            })
+        }
+    }
+}
+
+// Parse the arguments of a statement as exactly `count` numbers.
+fn numbers(
+    arguments: &[&str],
+    count: usize,
+    line: usize,
+    statement: &str,
+) -> Result<Vec<f32>, ObjError> {
+    let invalid = || ObjError::InvalidStatement {
+        line,
+        statement: statement.to_string(),
+    };
+    if arguments.len() != count {
+        return Err(invalid());
+    }
+    arguments
+        .iter()
+        .map(|argument| argument.parse().map_err(|_| invalid()))
+        .collect()
+}
+
+// Parse a color, given either as red, green and blue or as a single gray value.
+fn color(arguments: &[&str], line: usize, statement: &str) -> Result<Vec3, ObjError> {
+    if arguments.len() == 1 {
+        let gray = numbers(arguments, 1, line, statement)?[0];
+        return Ok(Vec3(gray, gray, gray));
+    }
+    let rgb = numbers(arguments, 3, line, statement)?;
+    Ok(Vec3(rgb[0], rgb[1], rgb[2]))
+}
+
+/// Parse the materials of an MTL file, indexed by their name.
+///
+/// ```
+/// use raytracer::objects::obj::{parse_mtl, MtlMaterial};
+/// let materials = parse_mtl("newmtl glass\nNi 1.45\nd 0.2\n").unwrap();
+/// match &materials["glass"] {
+///     MtlMaterial::Dielectric(glass) => assert_eq!(glass.ref_idx(), 1.45),
+///     _ => panic!("glass should be a dielectric"),
+/// }
+/// ```
+pub fn parse_mtl(source: &str) -> Result<HashMap<String, MtlMaterial>, ObjError> {
+    let mut parsed: Vec<(String, MtlParameters)> = Vec::new();
+    for (index, statement) in source.lines().enumerate() {
+        let line = index + 1;
+        let statement = statement.trim();
+        let mut words = statement.split_whitespace();
+        let keyword = match words.next() {
+            Some(keyword) if !keyword.starts_with('#') => keyword,
+            _ => continue,
+        };
+        let arguments: Vec<&str> = words.collect();
+        if keyword == "newmtl" {
+            if arguments.is_empty() {
+                return Err(ObjError::InvalidStatement {
+                    line,
+                    statement: statement.to_string(),
+                });
+            }
+            parsed.push((arguments.join(" "), MtlParameters::default()));
+            continue;
+        }
+        // Statements before the first material are ignored.
+        let parameters = match parsed.last_mut() {
+            Some((_, parameters)) => parameters,
+            None => continue,
+        };
+        let number = || numbers(&arguments, 1, line, statement).map(|number| number[0]);
+        match keyword {
+            "Kd" => parameters.diffuse = Some(color(&arguments, line, statement)?),
+            "Ks" => parameters.specular = Some(color(&arguments, line, statement)?),
+            "Ns" => parameters.specular_exponent = Some(number()?),
+            "Ni" => parameters.optical_density = Some(number()?),
+            "d" => parameters.dissolve = Some(number()?),
+            "Tr" => parameters.dissolve = Some(1. - number()?),
+            // Other parameters, such as texture maps, are not supported.
+            _ => (),
+        }
+    }
+    Ok(parsed
+        .into_iter()
+        .map(|(name, parameters)| (name, parameters.material()))
+        .collect())
+}
+
+// Resolve a one-based or negative, i.e. relative, OBJ index into `count` elements.
+fn resolve_index(
+    index: &str,
+    count: usize,
+    line: usize,
+    statement: &str,
+) -> Result<usize, ObjError> {
+    let index: i64 = index.parse().map_err(|_| ObjError::InvalidStatement {
+        line,
+        statement: statement.to_string(),
+    })?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(ObjError::InvalidIndex { line, index });
+    }
+    Ok(resolved as usize)
+}
+
+// A corner of a face, given by the indices of its position and optional normal.
+type Corner = (usize, Option<usize>);
+
+/// Parse the geometry of an OBJ file into one `TriangleMesh` per material.
+///
+/// The faces are assigned the `materials` named by the preceding `usemtl`
+/// statement. Faces before any `usemtl` statement or with a material missing
+/// from `materials` are made of a gray `Lambertian`. The meshes are returned
+/// in the order the materials are first used. A mesh is smooth-shaded if
+/// all of its face corners have a normal.
+///
+/// ```
+/// use raytracer::objects::obj::parse_obj;
+/// use std::collections::HashMap;
+/// let source = "
+/// v 0 0 0
+/// v 1 0 0
+/// v 1 1 0
+/// v 0 1 0
+/// f 1 2 3 4
+/// ";
+/// let meshes = parse_obj(source, &HashMap::new()).unwrap();
+/// assert_eq!(meshes.len(), 1);
+/// assert_eq!(meshes[0].faces(), &[[0, 1, 2], [0, 2, 3]]);
+/// ```
+pub fn parse_obj(
+    source: &str,
+    materials: &HashMap<String, Arc<dyn MaterialExt>>,
+) -> Result<Vec<TriangleMesh>, ObjError> {
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    // The faces of each material, in the order the materials are first used.
+    let mut segments: Vec<(String, Vec<[Corner; 3]>)> = Vec::new();
+    let mut current: Option<usize> = None;
+
+    for (index, statement) in source.lines().enumerate() {
+        let line = index + 1;
+        let statement = statement.trim();
+        let mut words = statement.split_whitespace();
+        let keyword = match words.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let arguments: Vec<&str> = words.collect();
+        match keyword {
+            "v" => {
+                // An optional fourth weight coordinate is ignored.
+                let count = if arguments.len() == 4 { 4 } else { 3 };
+                let xyz = numbers(&arguments, count, line, statement)?;
+                positions.push(Vec3(xyz[0], xyz[1], xyz[2]));
+            }
+            "vn" => {
+                let xyz = numbers(&arguments, 3, line, statement)?;
+                normals.push(Vec3(xyz[0], xyz[1], xyz[2]));
+            }
+            "usemtl" => {
+                let name = arguments.join(" ");
+                current = Some(match segments.iter().position(|(used, _)| *used == name) {
+                    Some(segment) => segment,
+                    None => {
+                        segments.push((name, Vec::new()));
+                        segments.len() - 1
+                    }
+                });
+            }
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err(ObjError::InvalidStatement {
+                        line,
+                        statement: statement.to_string(),
+                    });
+                }
+                let mut corners = Vec::new();
+                for argument in &arguments {
+                    // A corner is `v`, `v/vt`, `v//vn` or `v/vt/vn`.
+                    let mut references = argument.split('/');
+                    let position = resolve_index(
+                        references.next().unwrap_or(""),
+                        positions.len(),
+                        line,
+                        statement,
+                    )?;
+                    let normal = match references.nth(1) {
+                        Some(normal) if !normal.is_empty() => {
+                            Some(resolve_index(normal, normals.len(), line, statement)?)
+                        }
+                        _ => None,
+                    };
+                    corners.push((position, normal));
+                }
+                let segment = match current {
+                    Some(segment) => segment,
+                    None => {
+                        segments.push((String::new(), Vec::new()));
+                        let segment = segments.len() - 1;
+                        current = Some(segment);
+                        segment
+                    }
+                };
+                for pair in corners[1..].windows(2) {
+                    segments[segment].1.push([corners[0], pair[0], pair[1]]);
+                }
+            }
+            // Texture coordinates, groups, smoothing groups, material
+            // libraries and comments do not change the geometry.
+            _ => (),
+        }
+    }
+
+    let default: Arc<dyn MaterialExt> = Arc::new(default_material());
+    Ok(segments
+        .into_iter()
+        .filter(|(_, faces)| !faces.is_empty())
+        .map(|(name, faces)| {
+            let material = materials
+                .get(&name)
+                .cloned()
+                .unwrap_or_else(|| default.clone());
+            build_mesh(&positions, &normals, &faces, material)
+        })
+        .collect())
+}
+
+// Build a mesh from the faces of one material, with its own vertices.
+fn build_mesh(
+    positions: &[Vec3],
+    normals: &[Vec3],
+    faces: &[[Corner; 3]],
+    material: Arc<dyn MaterialExt>,
+) -> TriangleMesh {
+    let mut vertices: HashMap<Corner, usize> = HashMap::new();
+    let mut mesh_positions = Vec::new();
+    let mut mesh_normals = Vec::new();
+    let mut mesh_faces = Vec::new();
+    for face in faces {
+        let mut indices = [0; 3];
+        for (index, corner) in indices.iter_mut().zip(face.iter()) {
+            *index = *vertices.entry(*corner).or_insert_with(|| {
+                mesh_positions.push(positions[corner.0]);
+                mesh_normals.push(corner.1.map(|normal| normals[normal]));
+                mesh_positions.len() - 1
+            });
+        }
+        mesh_faces.push(indices);
+    }
+    // The indices are valid and there is one normal per vertex by construction.
+    let mesh = TriangleMesh::new(mesh_positions, mesh_faces, material).unwrap();
+    match mesh_normals.into_iter().collect::<Option<Vec<Vec3>>>() {
+        Some(normals) => mesh.with_vertex_normals(normals).unwrap(),
+        None => mesh,
+    }
+}
+
+/// Load the meshes of an OBJ file together with the materials of its MTL files.
+///
+/// The material libraries named by `mtllib` are looked up relative to the
+/// OBJ file. See `parse_obj` and `parse_mtl` for how they are interpreted.
+pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<Vec<TriangleMesh>, ObjError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(ObjError::Io)?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut materials: HashMap<String, Arc<dyn MaterialExt>> = HashMap::new();
+    for statement in source.lines() {
+        let mut words = statement.split_whitespace();
+        if words.next() == Some("mtllib") {
+            for library in words {
+                let mtl = fs::read_to_string(directory.join(library)).map_err(ObjError::Io)?;
+                for (name, material) in parse_mtl(&mtl)? {
+                    materials.insert(name, Arc::new(material));
+                }
+            }
+        }
+    }
+    parse_obj(&source, &materials)
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::Hitable;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    const MTL: &str = "
+# Three materials, one of each class.
+newmtl red_paint
+Kd 0.8 0.1 0.1
+Ks 0 0 0
+
+newmtl polished steel
+Kd 0.1 0.1 0.1
+Ks 0.7 0.7 0.8
+Ns 198
+
+newmtl glass
+Kd 1 1 1
+Ni 1.45
+Tr 0.9
+";
+
+    const OBJ: &str = "
+mtllib scene.mtl
+v 0 0 -1
+v 1 0 -1
+v 1 1 -1
+v 0 1 -1
+v 2 0 -1
+v 3 0 -1
+v 2 1 -1
+vn 0 0 1
+vn 0 1 1
+
+usemtl red_paint
+f 1//1 2//1 3//2 4//2
+usemtl unknown
+f 5 6 7
+usemtl red_paint
+f -4 -1 -3
+";
+
+    #[test]
+    // Test that the three classes of MTL materials map onto the built-in materials.
+    fn test_parse_mtl() {
+        let materials = parse_mtl(MTL).unwrap();
+        assert_eq!(materials.len(), 3);
+        match &materials["red_paint"] {
+            MtlMaterial::Lambertian(paint) => assert_eq!(paint.attenuation(), &Vec3(0.8, 0.1, 0.1)),
+            other => panic!("expected a Lambertian, got {:?}", other),
+        }
+        match &materials["polished steel"] {
+            MtlMaterial::Metal(steel) => {
+                assert_eq!(steel.attenuation(), &Vec3(0.7, 0.7, 0.8));
+                assert!((steel.fuzzy() - 0.1).abs() < 1e-6);
+            }
+            other => panic!("expected a Metal, got {:?}", other),
+        }
+        match &materials["glass"] {
+            MtlMaterial::Dielectric(glass) => assert_eq!(glass.ref_idx(), 1.45),
+            other => panic!("expected a Dielectric, got {:?}", other),
+        }
+    }
+
+    #[test]
+    // Test that malformed statements are reported with their line.
+    fn test_parse_mtl_invalid() {
+        match parse_mtl("newmtl paint\nKd 0.8 red 0.1\n") {
+            Err(ObjError::InvalidStatement { line, statement }) => {
+                assert_eq!(line, 2);
+                assert_eq!(statement, "Kd 0.8 red 0.1");
+            }
+            _ => panic!("expected an invalid statement"),
+        }
+    }
+
+    #[test]
+    // Test that the faces are split into one mesh per material.
+    fn test_parse_obj_materials() {
+        let mut rng = SmallRng::seed_from_u64(40);
+        let materials: HashMap<String, Arc<dyn MaterialExt>> = parse_mtl(MTL)
+            .unwrap()
+            .into_iter()
+            .map(|(name, material)| (name, Arc::new(material) as Arc<dyn MaterialExt>))
+            .collect();
+        let meshes = parse_obj(OBJ, &materials).unwrap();
+        assert_eq!(meshes.len(), 2);
+        // The quad and the triangle referenced with negative indices share
+        // the red material, and their vertices with different normals are kept apart.
+        assert_eq!(meshes[0].faces().len(), 3);
+        assert_eq!(meshes[0].vertices().len(), 7);
+        assert!(meshes[0].vertex_normals().is_none());
+
+        let ray = Ray::new(Vec3(0.6, 0.4, 0.), Vec3(0., 0., -1.));
+        let hit = meshes[0].intersect(&ray, 0.001, f32::MAX).unwrap();
+        let (_, attenuation) = hit.material.scatter(&ray, &hit, &mut rng).unwrap();
+        assert_eq!(attenuation, Vec3(0.8, 0.1, 0.1));
+        // The unknown material falls back to gray.
+        let ray = Ray::new(Vec3(2.2, 0.2, 0.), Vec3(0., 0., -1.));
+        let hit = meshes[1].intersect(&ray, 0.001, f32::MAX).unwrap();
+        let (_, attenuation) = hit.material.scatter(&ray, &hit, &mut rng).unwrap();
+        assert_eq!(attenuation, Vec3(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    // Test that faces with normals at every corner are smooth-shaded.
+    fn test_parse_obj_normals() {
+        let source = "v 0 0 -1\nv 1 0 -1\nv 0 1 -1\nvn 0 0 1\nvn 1 0 1\nf 1//1 2//2 3//1\n";
+        let meshes = parse_obj(source, &HashMap::new()).unwrap();
+        let normals = meshes[0].vertex_normals().unwrap();
+        assert_eq!(normals[0], Vec3(0., 0., 1.));
+        assert!((normals[1] - Vec3(1., 0., 1.) / 2_f32.sqrt()).length() < 1e-6);
+        match parse_obj("v 0 0 0\nv 1 0 0\nf 1 2 3\n", &HashMap::new()) {
+            Err(ObjError::InvalidIndex { line, index }) => assert_eq!((line, index), (3, 3)),
+            _ => panic!("expected an invalid index"),
+        }
+    }
+
+    #[test]
+    // Test that the material libraries are read relative to the OBJ file.
+    fn test_load_obj() {
+        let mut rng = SmallRng::seed_from_u64(41);
+        let directory = std::env::temp_dir().join("raytracer_test_load_obj");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("scene.mtl"), MTL).unwrap();
+        fs::write(directory.join("scene.obj"), OBJ).unwrap();
+        let meshes = load_obj(directory.join("scene.obj")).unwrap();
+        assert_eq!(meshes.len(), 2);
+        let ray = Ray::new(Vec3(0.6, 0.4, 0.), Vec3(0., 0., -1.));
+        let hit = meshes[0].intersect(&ray, 0.001, f32::MAX).unwrap();
+        let (_, attenuation) = hit.material.scatter(&ray, &hit, &mut rng).unwrap();
+        assert_eq!(attenuation, Vec3(0.8, 0.1, 0.1));
+        assert!(load_obj(directory.join("missing.obj")).is_err());
+    }
+}
@@ -0,0 +1,293 @@
+//! An enum of the common shapes, for scenes built without trait objects.
+//!
+//! Calling an object through `Box<dyn Hitable>` is an indirect call, which
+//! the compiler cannot inline into the loops of `HitableList` or the
+//! traversal of `BvhNode`. An `Object` instead calls the intersection routine
+//! of its shape through a `match`, such that a `HitableList<Object>` or a
+//! `BvhNode<Object>` is dispatched statically throughout.
+
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::lights::Light;
+use crate::objects::bvh::BvhNode;
+use crate::objects::cuboid::Cuboid;
+use crate::objects::disk::Disk;
+use crate::objects::moving_sphere::MovingSphere;
+use crate::objects::rect::Rect;
+use crate::objects::sphere::Sphere;
+use crate::objects::triangle::Triangle;
+use crate::objects::Hitable;
+use crate::objects::HitableList;
+use crate::ray::Ray;
+use crate::ray::PACKET_SIZE;
+
+/// A hitable object of one of the common shapes.
+///
+/// Shapes without a variant of their own, e.g. custom ones, are kept as trait
+/// objects in `Object::Dyn`, such that any scene can be expressed with the
+/// enum. Every shape converts into an `Object` with `From`.
+///
+/// ```
+/// use raytracer::objects::bvh::{BvhBuildStrategy, BvhNode};
+/// use raytracer::objects::object::Object;
+/// use raytracer::objects::rect::Rect;
+/// use raytracer::objects::sphere::Sphere;
+/// use raytracer::objects::Hitable;
+/// use raytracer::ray::Ray;
+/// use raytracer::vec3::Vec3;
+/// use raytracer::materials::Lambertian;
+/// use std::sync::Arc;
+/// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+/// let objects: Vec<Object> = vec![
+///     Sphere::new(Vec3(0., 0., -2.), 0.5, material.clone()).into(),
+///     Rect::new(Vec3(-5., -5., -4.), Vec3(10., 0., 0.), Vec3(0., 10., 0.), material).into(),
+/// ];
+/// let world = BvhNode::from_objects(objects, BvhBuildStrategy::Median);
+/// let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., -1.));
+/// assert_eq!(world.intersect(&ray, 0.001, f32::MAX).unwrap().parameter, 1.5);
+/// ```
+pub enum Object {
+    Sphere(Sphere),
+    MovingSphere(MovingSphere),
+    Rect(Rect),
+    Cuboid(Cuboid),
+    Disk(Disk),
+    Triangle(Triangle),
+    List(HitableList<Object>),
+    Bvh(Box<BvhNode<Object>>),
+    Dyn(Box<dyn Hitable>),
+}
+
+impl Hitable for Object {
+    #[inline]
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        match self {
+            Object::Sphere(sphere) => sphere.intersect(ray, t_min, t_max),
+            Object::MovingSphere(sphere) => sphere.intersect(ray, t_min, t_max),
+            Object::Rect(rect) => rect.intersect(ray, t_min, t_max),
+            Object::Cuboid(cuboid) => cuboid.intersect(ray, t_min, t_max),
+            Object::Disk(disk) => disk.intersect(ray, t_min, t_max),
+            Object::Triangle(triangle) => triangle.intersect(ray, t_min, t_max),
+            Object::List(list) => list.intersect(ray, t_min, t_max),
+            Object::Bvh(bvh) => bvh.intersect(ray, t_min, t_max),
+            Object::Dyn(object) => object.intersect(ray, t_min, t_max),
+        }
+    }
+
+    #[inline]
+    fn intersect_any(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        match self {
+            Object::Sphere(sphere) => sphere.intersect_any(ray, t_min, t_max),
+            Object::MovingSphere(sphere) => sphere.intersect_any(ray, t_min, t_max),
+            Object::Rect(rect) => rect.intersect_any(ray, t_min, t_max),
+            Object::Cuboid(cuboid) => cuboid.intersect_any(ray, t_min, t_max),
+            Object::Disk(disk) => disk.intersect_any(ray, t_min, t_max),
+            Object::Triangle(triangle) => triangle.intersect_any(ray, t_min, t_max),
+            Object::List(list) => list.intersect_any(ray, t_min, t_max),
+            Object::Bvh(bvh) => bvh.intersect_any(ray, t_min, t_max),
+            Object::Dyn(object) => object.intersect_any(ray, t_min, t_max),
+        }
+    }
+
+    fn intersect_packet(
+        &self,
+        rays: &[Ray; PACKET_SIZE],
+        t_min: f32,
+        t_max: f32,
+    ) -> [Option<HitRecord<'_>>; PACKET_SIZE] {
+        match self {
+            Object::List(list) => list.intersect_packet(rays, t_min, t_max),
+            Object::Bvh(bvh) => bvh.intersect_packet(rays, t_min, t_max),
+            Object::Dyn(object) => object.intersect_packet(rays, t_min, t_max),
+            // Single shapes gain nothing from testing the rays together.
+            object => std::array::from_fn(|i| object.intersect(&rays[i], t_min, t_max)),
+        }
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        match self {
+            Object::Sphere(sphere) => sphere.intersect_all(ray),
+            Object::MovingSphere(sphere) => sphere.intersect_all(ray),
+            Object::Rect(rect) => rect.intersect_all(ray),
+            Object::Cuboid(cuboid) => cuboid.intersect_all(ray),
+            Object::Disk(disk) => disk.intersect_all(ray),
+            Object::Triangle(triangle) => triangle.intersect_all(ray),
+            Object::List(list) => list.intersect_all(ray),
+            Object::Bvh(bvh) => bvh.intersect_all(ray),
+            Object::Dyn(object) => object.intersect_all(ray),
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        match self {
+            Object::Sphere(sphere) => sphere.bounding_box(),
+            Object::MovingSphere(sphere) => sphere.bounding_box(),
+            Object::Rect(rect) => rect.bounding_box(),
+            Object::Cuboid(cuboid) => cuboid.bounding_box(),
+            Object::Disk(disk) => disk.bounding_box(),
+            Object::Triangle(triangle) => triangle.bounding_box(),
+            Object::List(list) => list.bounding_box(),
+            Object::Bvh(bvh) => bvh.bounding_box(),
+            Object::Dyn(object) => object.bounding_box(),
+        }
+    }
+
+    fn collect_lights(&self, lights: &mut Vec<Light>) {
+        match self {
+            Object::Sphere(sphere) => sphere.collect_lights(lights),
+            Object::Rect(rect) => rect.collect_lights(lights),
+            Object::List(list) => list.collect_lights(lights),
+            Object::Bvh(bvh) => bvh.collect_lights(lights),
+            Object::Dyn(object) => object.collect_lights(lights),
+            _ => {}
+        }
+    }
+}
+
+impl From<Sphere> for Object {
+    fn from(sphere: Sphere) -> Object {
+        Object::Sphere(sphere)
+    }
+}
+
+impl From<MovingSphere> for Object {
+    fn from(sphere: MovingSphere) -> Object {
+        Object::MovingSphere(sphere)
+    }
+}
+
+impl From<Rect> for Object {
+    fn from(rect: Rect) -> Object {
+        Object::Rect(rect)
+    }
+}
+
+impl From<Cuboid> for Object {
+    fn from(cuboid: Cuboid) -> Object {
+        Object::Cuboid(cuboid)
+    }
+}
+
+impl From<Disk> for Object {
+    fn from(disk: Disk) -> Object {
+        Object::Disk(disk)
+    }
+}
+
+impl From<Triangle> for Object {
+    fn from(triangle: Triangle) -> Object {
+        Object::Triangle(triangle)
+    }
+}
+
+impl From<HitableList<Object>> for Object {
+    fn from(list: HitableList<Object>) -> Object {
+        Object::List(list)
+    }
+}
+
+impl From<BvhNode<Object>> for Object {
+    fn from(bvh: BvhNode<Object>) -> Object {
+        Object::Bvh(Box::new(bvh))
+    }
+}
+
+impl From<Box<dyn Hitable>> for Object {
+    fn from(object: Box<dyn Hitable>) -> Object {
+        Object::Dyn(object)
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+    use crate::materials::MaterialExt;
+    use crate::objects::bvh::BvhBuildStrategy;
+    use crate::objects::constant_medium::ConstantMedium;
+    use crate::vec3::Vec3;
+    use rand::prelude::*;
+    use rand::rngs::StdRng;
+    use std::sync::Arc;
+
+    #[test]
+    // Test that a scene of objects is hit exactly like the same scene of trait objects.
+    fn test_object_matches_trait_objects() {
+        let mut rng = StdRng::seed_from_u64(46);
+        let material: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        let mut objects: Vec<Object> = Vec::new();
+        let mut boxed: Vec<Box<dyn Hitable>> = Vec::new();
+        for i in 0..300 {
+            let p = Vec3(
+                rng.gen_range(-10.0..10.0),
+                rng.gen_range(-10.0..10.0),
+                rng.gen_range(-30.0..-5.0),
+            );
+            let size = rng.gen_range(0.2..1.0);
+            let (edge_u, edge_v) = (Vec3(size, 0., 0.), Vec3(0., size, size));
+            match i % 3 {
+                0 => {
+                    objects.push(Sphere::new(p, size, material.clone()).into());
+                    boxed.push(Box::new(Sphere::new(p, size, material.clone())));
+                }
+                1 => {
+                    objects.push(Rect::new(p, edge_u, edge_v, material.clone()).into());
+                    boxed.push(Box::new(Rect::new(p, edge_u, edge_v, material.clone())));
+                }
+                _ => {
+                    let q = p + edge_u + edge_v;
+                    objects.push(Triangle::new(p, p + edge_u, q, material.clone()).into());
+                    boxed.push(Box::new(Triangle::new(p, p + edge_u, q, material.clone())));
+                }
+            }
+        }
+        // Nested structures and a shape without a variant of its own.
+        let fog = || -> Box<dyn Hitable> {
+            let boundary = Sphere::new(Vec3(0., 0., -20.), 2., material.clone());
+            Box::new(ConstantMedium::new(
+                Box::new(boundary),
+                0.,
+                material.clone(),
+            ))
+        };
+        let inner = vec![Object::from(Cuboid::new(
+            Vec3(-1., -1., -41.),
+            Vec3(1., 1., -40.),
+            material.clone(),
+        ))];
+        objects.push(HitableList::from_objects(inner).into());
+        objects.push(fog().into());
+        boxed.push(Box::new(Cuboid::new(
+            Vec3(-1., -1., -41.),
+            Vec3(1., 1., -40.),
+            material.clone(),
+        )));
+        boxed.push(fog());
+
+        let world = BvhNode::from_objects(objects, BvhBuildStrategy::Sah { buckets: 12 });
+        let expected = BvhNode::with_strategy(boxed, BvhBuildStrategy::Sah { buckets: 12 });
+        assert_eq!(world.bounding_box(), expected.bounding_box());
+        let world = Object::from(world);
+        let mut hits = 0;
+        for _ in 0..2000 {
+            let direction = Vec3(rng.gen_range(-0.5..0.5), rng.gen_range(-0.5..0.5), -1.);
+            let ray = Ray::new(Vec3(0., 0., 0.), direction);
+            let hit = world.intersect(&ray, 0.001, f32::MAX);
+            let expected_hit = expected.intersect(&ray, 0.001, f32::MAX);
+            assert_eq!(
+                hit.as_ref().map(|hit| (hit.parameter, hit.normal)),
+                expected_hit.as_ref().map(|hit| (hit.parameter, hit.normal))
+            );
+            assert_eq!(
+                world.intersect_any(&ray, 0.001, f32::MAX),
+                expected_hit.is_some()
+            );
+            hits += hit.is_some() as usize;
+        }
+        assert!(hits > 200);
+    }
+}
@@ -0,0 +1,253 @@
+//! Surfaces given by a parametrization `(u, v) -> Vec3`.
+//!
+//! The parametrization is sampled on a regular grid when the surface is
+//! created, and the samples are connected into a smooth-shaded mesh.
+
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::materials::MaterialExt;
+use crate::objects::mesh::TriangleMesh;
+use crate::objects::Hitable;
+use crate::objects::ObjectError;
+use crate::ray::Ray;
+use crate::vec3::cross;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+/// A surface described by a function of two parameters, tessellated into triangles.
+///
+/// The front side of the surface is the one the normal `∂f/∂u × ∂f/∂v`
+/// points to.
+pub struct ParametricSurface {
+    mesh: TriangleMesh,
+}
+
+impl ParametricSurface {
+    /// Tessellate the surface `f` over `u_range` and `v_range` into a mesh of
+    /// `resolution.0` by `resolution.1` quads, each split into two triangles.
+    ///
+    /// The normals at the vertices are taken from the partial derivatives of
+    /// `f`, estimated by finite differences. Where these do not span a plane,
+    /// e.g. at the poles of a sphere, the normals of the adjacent faces are
+    /// averaged instead. An error is returned if a range is not finite or
+    /// its start is not smaller than its end, or if a resolution is zero.
+    ///
+    /// ```
+    /// use raytracer::objects::parametric::ParametricSurface;
+    /// use raytracer::objects::torus::Torus;
+    /// use raytracer::objects::Hitable;
+    /// use raytracer::ray::Ray;
+    /// use raytracer::vec3::{dot, Vec3};
+    /// use raytracer::materials::Lambertian;
+    /// use std::f32::consts::PI;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let (major, minor) = (1., 0.3);
+    /// let surface = ParametricSurface::new(
+    ///     |u: f32, v: f32| {
+    ///         let ring = major + minor * v.cos();
+    ///         Vec3(ring * u.cos(), minor * v.sin(), -ring * u.sin())
+    ///     },
+    ///     (0., 2. * PI),
+    ///     (0., 2. * PI),
+    ///     (64, 32),
+    ///     material.clone(),
+    /// )
+    /// .unwrap();
+    /// let torus = Torus::new(Vec3(0., 0., 0.), Vec3(0., 1., 0.), major, minor, material);
+    /// let rays = [
+    ///     Ray::new(Vec3(0., 0., 5.), Vec3(0., 0., -1.)),
+    ///     Ray::new(Vec3(0.5, 0.1, 5.), Vec3(0., 0., -1.)),
+    ///     Ray::new(Vec3(0., 5., -1.), Vec3(0., -1., 0.)),
+    ///     Ray::new(Vec3(3., 2., 1.), Vec3(-1., -0.8, -0.6)),
+    /// ];
+    /// for ray in rays.iter() {
+    ///     let expected = torus.intersect(ray, 0.001, f32::MAX).unwrap();
+    ///     let hit = surface.intersect(ray, 0.001, f32::MAX).unwrap();
+    ///     assert!((hit.point_at_parameter - expected.point_at_parameter).length() < 1e-2);
+    ///     assert!(dot(&hit.normal, &expected.normal) > 0.99);
+    /// }
+    /// ```
+    pub fn new(
+        f: impl Fn(f32, f32) -> Vec3,
+        u_range: (f32, f32),
+        v_range: (f32, f32),
+        resolution: (usize, usize),
+        material: Arc<dyn MaterialExt>,
+    ) -> Result<ParametricSurface, ObjectError> {
+        for &(start, end) in &[u_range, v_range] {
+            if !(start.is_finite() && end.is_finite() && start < end) {
+                return Err(ObjectError::InvalidParameterRange { start, end });
+            }
+        }
+        let (nu, nv) = resolution;
+        if nu == 0 || nv == 0 {
+            return Err(ObjectError::InvalidResolution { u: nu, v: nv });
+        }
+
+        let u_at = |i: usize| u_range.0 + (u_range.1 - u_range.0) * i as f32 / nu as f32;
+        let v_at = |j: usize| v_range.0 + (v_range.1 - v_range.0) * j as f32 / nv as f32;
+        // The samples are stored row by row along v.
+        let index = |i: usize, j: usize| i * (nv + 1) + j;
+
+        let mut vertices = Vec::with_capacity((nu + 1) * (nv + 1));
+        let mut normals = Vec::with_capacity((nu + 1) * (nv + 1));
+        let hu = 1e-3 * (u_range.1 - u_range.0);
+        let hv = 1e-3 * (v_range.1 - v_range.0);
+        for i in 0..=nu {
+            for j in 0..=nv {
+                let (u, v) = (u_at(i), v_at(j));
+                vertices.push(f(u, v));
+                // Central differences, which become one-sided at the borders
+                // so that `f` is only evaluated within its ranges.
+                let (u0, u1) = ((u - hu).max(u_range.0), (u + hu).min(u_range.1));
+                let (v0, v1) = ((v - hv).max(v_range.0), (v + hv).min(v_range.1));
+                let du = (f(u1, v) - f(u0, v)) / (u1 - u0);
+                let dv = (f(u, v1) - f(u, v0)) / (v1 - v0);
+                normals.push(cross(&du, &dv));
+            }
+        }
+
+        // Both triangles of a quad are wound like the parameter axes, such
+        // that their normals point to the same side as `du × dv`.
+        let mut faces = Vec::with_capacity(2 * nu * nv);
+        for i in 0..nu {
+            for j in 0..nv {
+                faces.push([index(i, j), index(i + 1, j), index(i + 1, j + 1)]);
+                faces.push([index(i, j), index(i + 1, j + 1), index(i, j + 1)]);
+            }
+        }
+
+        let mut mesh = TriangleMesh::new(vertices, faces, material)?;
+        mesh.compute_vertex_normals();
+        let averaged = mesh.vertex_normals().unwrap();
+        // Derivatives spanning a tiny area compared to the rest of the surface
+        // indicate a degenerate point of the parametrization.
+        let threshold = 1e-6 * normals.iter().map(Vec3::length).fold(0., f32::max);
+        let normals = normals
+            .iter()
+            .zip(averaged.iter())
+            .map(|(analytic, average)| {
+                if analytic.length() > threshold {
+                    *analytic
+                } else {
+                    *average
+                }
+            })
+            .collect();
+        Ok(ParametricSurface {
+            mesh: mesh.with_vertex_normals(normals)?,
+        })
+    }
+
+    /// Access the `TriangleMesh` the surface was tessellated into.
+    pub fn mesh(&self) -> &TriangleMesh {
+        &self.mesh
+    }
+
+    /// Access the `material` a `ParametricSurface` is made of.
+    pub fn material(&self) -> Arc<dyn MaterialExt> {
+        self.mesh.material()
+    }
+}
+
+impl Hitable for ParametricSurface {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.mesh.intersect(ray, t_min, t_max)
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        self.mesh.intersect_all(ray)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.mesh.bounding_box()
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+    use crate::vec3::dot;
+    use std::f32::consts::PI;
+
+    fn material() -> Arc<dyn MaterialExt> {
+        Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)))
+    }
+
+    // A unit sphere parametrized by longitude u and latitude v.
+    fn sphere(u: f32, v: f32) -> Vec3 {
+        Vec3(v.cos() * u.cos(), v.sin(), -v.cos() * u.sin())
+    }
+
+    #[test]
+    // Test that the tessellation has the expected number of vertices and faces.
+    fn test_parametric_tessellation() {
+        let plane = ParametricSurface::new(
+            |u, v| Vec3(u, v, 0.),
+            (0., 1.),
+            (0., 2.),
+            (4, 3),
+            material(),
+        )
+        .unwrap();
+        assert_eq!(plane.mesh().vertices().len(), 5 * 4);
+        assert_eq!(plane.mesh().faces().len(), 2 * 4 * 3);
+        assert_eq!(plane.mesh().vertices()[19], Vec3(1., 2., 0.));
+        // The front side is the one of du x dv.
+        let ray = Ray::new(Vec3(0.3, 0.7, 1.), Vec3(0., 0., -1.));
+        let hit = plane.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.normal - Vec3(0., 0., 1.)).length() < 1e-5);
+    }
+
+    #[test]
+    // Test that the normals at the degenerate poles of a sphere are usable.
+    fn test_parametric_degenerate_poles() {
+        let surface = ParametricSurface::new(
+            sphere,
+            (0., 2. * PI),
+            (-PI / 2., PI / 2.),
+            (32, 16),
+            material(),
+        )
+        .unwrap();
+        let normals = surface.mesh().vertex_normals().unwrap();
+        assert!(normals.iter().all(|n| (n.length() - 1.).abs() < 1e-4));
+        let north = surface.mesh().vertices().len() - 1;
+        assert!(dot(&normals[north], &Vec3(0., 1., 0.)) > 0.99);
+        let ray = Ray::new(Vec3(0., 5., 0.), Vec3(0., -1., 0.));
+        let hit = surface.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.point_at_parameter.y() - 1.).abs() < 1e-2);
+        assert!(dot(&hit.normal, &Vec3(0., 1., 0.)) > 0.99);
+    }
+
+    #[test]
+    // Test that invalid ranges and resolutions are rejected.
+    fn test_parametric_invalid_parameters() {
+        let create = |u_range, v_range, resolution| {
+            ParametricSurface::new(sphere, u_range, v_range, resolution, material()).err()
+        };
+        assert_eq!(
+            create((1., 1.), (0., 1.), (4, 4)),
+            Some(ObjectError::InvalidParameterRange { start: 1., end: 1. })
+        );
+        assert_eq!(
+            create((0., 1.), (2., -1.), (4, 4)),
+            Some(ObjectError::InvalidParameterRange {
+                start: 2.,
+                end: -1.
+            })
+        );
+        assert!(create((0., f32::INFINITY), (0., 1.), (4, 4)).is_some());
+        assert!(create((0., f32::NAN), (0., 1.), (4, 4)).is_some());
+        assert_eq!(
+            create((0., 1.), (0., 1.), (4, 0)),
+            Some(ObjectError::InvalidResolution { u: 4, v: 0 })
+        );
+    }
+}
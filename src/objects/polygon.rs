@@ -0,0 +1,297 @@
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::materials::MaterialExt;
+use crate::objects::Hitable;
+use crate::objects::ObjectError;
+use crate::ray::Ray;
+use crate::vec3::cross;
+use crate::vec3::dot;
+use crate::vec3::unit_vector;
+use crate::vec3::Vec3;
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+/// A flat convex polygon in three-dimensional space.
+///
+/// It is characterized by:
+/// - Its vertices, which lie in a common plane. The front side is the one
+///   from which they appear in counter-clockwise order.
+/// - A pointer to the material that it is made of.
+///
+/// Points on the edges and vertices count as inside, such that adjacent
+/// polygons sharing an edge leave no gap between them.
+pub struct Polygon {
+    vertices: Vec<Vec3>,
+    normal: Vec3,
+    material: Arc<dyn MaterialExt>,
+}
+
+impl Polygon {
+    /// Create a `Polygon` from its ordered `vertices` and its `Material`.
+    ///
+    /// An error is returned if there are fewer than three vertices, if they
+    /// are not coplanar, or if the polygon they outline is not convex.
+    /// Consecutive collinear vertices are allowed.
+    ///
+    /// ```
+    /// use raytracer::objects::polygon::Polygon;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.8, 0.8)));
+    /// let pentagon = Polygon::new(
+    ///     vec![
+    ///         Vec3(0., 0., 0.),
+    ///         Vec3(2., 0., 0.),
+    ///         Vec3(2., 0., -1.),
+    ///         Vec3(1., 0., -2.),
+    ///         Vec3(0., 0., -1.),
+    ///     ],
+    ///     material.clone(),
+    /// )
+    /// .unwrap();
+    /// assert_eq!(pentagon.normal(), &Vec3(0., 1., 0.));
+    /// // An arrow head is not convex.
+    /// let arrow = Polygon::new(
+    ///     vec![
+    ///         Vec3(0., 0., 0.),
+    ///         Vec3(1., 1., 0.),
+    ///         Vec3(2., 0., 0.),
+    ///         Vec3(1., 3., 0.),
+    ///     ],
+    ///     material,
+    /// );
+    /// assert!(arrow.is_err());
+    /// ```
+    pub fn new(
+        vertices: Vec<Vec3>,
+        material: Arc<dyn MaterialExt>,
+    ) -> Result<Polygon, ObjectError> {
+        let n = vertices.len();
+        if n < 3 {
+            return Err(ObjectError::PolygonVertexCount { vertices: n });
+        }
+
+        // Summing the normals of the fan of triangles around the first vertex
+        // gives a normal of the polygon, with a length of twice its area.
+        // Working relative to the first vertex keeps this accurate for
+        // polygons far from the origin.
+        let mut area_normal = Vec3(0., 0., 0.);
+        for i in 1..n - 1 {
+            area_normal += cross(
+                &(vertices[i] - vertices[0]),
+                &(vertices[i + 1] - vertices[0]),
+            );
+        }
+        if area_normal.squared_length() == 0. {
+            return Err(ObjectError::NonConvexPolygon);
+        }
+        let normal = unit_vector(&area_normal);
+
+        let size = vertices
+            .iter()
+            .map(|vertex| (*vertex - vertices[0]).length())
+            .fold(0., f32::max);
+        let tolerance = 1e-5 * size;
+        if vertices
+            .iter()
+            .any(|vertex| dot(&normal, &(*vertex - vertices[0])).abs() > tolerance)
+        {
+            return Err(ObjectError::NonPlanarPolygon);
+        }
+
+        // A convex polygon never turns right, and turns once around in total.
+        // The latter excludes self-intersecting shapes such as a pentagram.
+        let mut total_turn = 0.;
+        for i in 0..n {
+            let incoming = vertices[(i + 1) % n] - vertices[i];
+            let outgoing = vertices[(i + 2) % n] - vertices[(i + 1) % n];
+            let turn = dot(&cross(&incoming, &outgoing), &normal);
+            if turn < -tolerance * size {
+                return Err(ObjectError::NonConvexPolygon);
+            }
+            total_turn += turn.atan2(dot(&incoming, &outgoing));
+        }
+        if (total_turn - 2. * PI).abs() > 1e-3 {
+            return Err(ObjectError::NonConvexPolygon);
+        }
+
+        Ok(Polygon {
+            vertices,
+            normal,
+            material,
+        })
+    }
+
+    /// Access the vertices of a `Polygon`.
+    pub fn vertices(&self) -> &[Vec3] {
+        &self.vertices
+    }
+
+    /// Access the normal of a `Polygon`, pointing towards its front side.
+    pub fn normal(&self) -> &Vec3 {
+        &self.normal
+    }
+
+    /// Access the `material` a `Polygon` is made of.
+    pub fn material(&self) -> Arc<dyn MaterialExt> {
+        Arc::clone(&self.material)
+    }
+
+    // Check whether a point in the plane of the polygon lies inside it or
+    // on its boundary, i.e. on the left of or on every edge.
+    fn contains(&self, point: &Vec3) -> bool {
+        let n = self.vertices.len();
+        self.vertices.iter().enumerate().all(|(i, start)| {
+            let edge = self.vertices[(i + 1) % n] - *start;
+            dot(&cross(&edge, &(*point - *start)), &self.normal) >= 0.
+        })
+    }
+}
+
+impl Hitable for Polygon {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let denominator = dot(&self.normal, ray.direction());
+        // The ray is parallel to the plane of the polygon.
+        if denominator.abs() < 1e-12 {
+            return None;
+        }
+        let t = dot(&self.normal, &(self.vertices[0] - *ray.origin())) / denominator;
+        if t <= t_min || t >= t_max {
+            return None;
+        }
+        let point = ray.point_at_parameter(t);
+        if !self.contains(&point) {
+            return None;
+        }
+        Some(HitRecord {
+            parameter: t,
+            point_at_parameter: point,
+            normal: self.normal,
+            u: 0.,
+            v: 0.,
+            tangent: Vec3(0., 0., 0.),
+            bitangent: Vec3(0., 0., 0.),
+            front_face: dot(ray.direction(), &self.normal) < 0.,
+            material: self.material.as_ref(),
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Aabb::from_points(&self.vertices)
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+
+    fn material() -> Arc<dyn MaterialExt> {
+        Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)))
+    }
+
+    // A regular hexagon with unit circumradius in the plane z = -2.
+    fn hexagon() -> Polygon {
+        let vertices = (0..6)
+            .map(|i| {
+                let angle = PI / 3. * i as f32;
+                Vec3(angle.cos(), angle.sin(), -2.)
+            })
+            .collect();
+        Polygon::new(vertices, material()).unwrap()
+    }
+
+    #[test]
+    // Test rays inside, outside and on a vertex of a hexagon.
+    fn test_hexagon_hits() {
+        let hexagon = hexagon();
+        assert_eq!(hexagon.normal(), &Vec3(0., 0., 1.));
+        let ray = Ray::new(Vec3(0.3, -0.4, 0.), Vec3(0., 0., -1.));
+        let hit = hexagon.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.parameter, 2.);
+        assert_eq!(hit.point_at_parameter, Vec3(0.3, -0.4, -2.));
+        assert_eq!(hit.normal, Vec3(0., 0., 1.));
+        // Inside the circumcircle, but outside the hexagon.
+        let ray = Ray::new(Vec3(0., 0.95, 0.), Vec3(0., 0., -1.));
+        assert!(hexagon.intersect(&ray, 0.001, f32::MAX).is_none());
+        let ray = Ray::new(Vec3(1.01, 0., 0.), Vec3(0., 0., -1.));
+        assert!(hexagon.intersect(&ray, 0.001, f32::MAX).is_none());
+        // Exactly on a vertex.
+        let ray = Ray::new(Vec3(1., 0., 0.), Vec3(0., 0., -1.));
+        let hit = hexagon.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.point_at_parameter, Vec3(1., 0., -2.));
+    }
+
+    #[test]
+    // Test that a ray through the shared edge of two polygons hits one of them.
+    fn test_shared_edge_does_not_leak() {
+        let left = Polygon::new(
+            vec![
+                Vec3(-1., -1., -1.),
+                Vec3(0., -1., -1.),
+                Vec3(0., 1., -1.),
+                Vec3(-1., 1., -1.),
+            ],
+            material(),
+        )
+        .unwrap();
+        let right = Polygon::new(
+            vec![
+                Vec3(0., -1., -1.),
+                Vec3(1., -1., -1.),
+                Vec3(1., 1., -1.),
+                Vec3(0., 1., -1.),
+            ],
+            material(),
+        )
+        .unwrap();
+        for y in [-0.9, -0.3, 0., 0.4, 0.8] {
+            let ray = Ray::new(Vec3(0., y, 0.), Vec3(0., 0., -1.));
+            assert!(left.intersect(&ray, 0.001, f32::MAX).is_some());
+            assert!(right.intersect(&ray, 0.001, f32::MAX).is_some());
+        }
+    }
+
+    #[test]
+    // Test that invalid polygons are rejected.
+    fn test_invalid_polygons() {
+        let square = |z: f32| {
+            vec![
+                Vec3(0., 0., 0.),
+                Vec3(1., 0., 0.),
+                Vec3(1., 1., z),
+                Vec3(0., 1., 0.),
+            ]
+        };
+        assert!(Polygon::new(square(0.), material()).is_ok());
+        assert_eq!(
+            Polygon::new(square(0.5), material()).err(),
+            Some(ObjectError::NonPlanarPolygon)
+        );
+        assert_eq!(
+            Polygon::new(square(0.)[..2].to_vec(), material()).err(),
+            Some(ObjectError::PolygonVertexCount { vertices: 2 })
+        );
+        let collinear = vec![Vec3(0., 0., 0.), Vec3(1., 0., 0.), Vec3(2., 0., 0.)];
+        assert_eq!(
+            Polygon::new(collinear, material()).err(),
+            Some(ObjectError::NonConvexPolygon)
+        );
+        // A pentagram turns the right way at every vertex, but twice around.
+        let pentagram = (0..5)
+            .map(|i| {
+                let angle = 4. * PI / 5. * i as f32;
+                Vec3(angle.cos(), angle.sin(), 0.)
+            })
+            .collect();
+        assert_eq!(
+            Polygon::new(pentagram, material()).err(),
+            Some(ObjectError::NonConvexPolygon)
+        );
+    }
+}
@@ -0,0 +1,339 @@
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::materials::MaterialExt;
+use crate::objects::Hitable;
+use crate::ray::Ray;
+use crate::vec3::dot;
+use crate::vec3::unit_vector;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+/// A general quadric surface in three-dimensional space.
+///
+/// It is the set of points satisfying
+///
+/// A x^2 + B y^2 + C z^2 + D xy + E xz + F yz + G x + H y + I z + J = 0
+///
+/// and is given by the ten coefficients `[A, B, C, D, E, F, G, H, I, J]`.
+/// This covers ellipsoids, paraboloids, hyperboloids, cones and cylinders.
+/// The normal points towards the side where the left hand side is positive,
+/// which is the outside for the shapes created by the named constructors.
+pub struct Quadric {
+    coefficients: [f32; 10],
+    material: Arc<dyn MaterialExt>,
+}
+
+impl Quadric {
+    /// Create a `Quadric` by specifying its ten `coefficients` and `Material`.
+    ///
+    /// ```
+    /// use raytracer::objects::quadric::Quadric;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// // The unit sphere x^2 + y^2 + z^2 - 1 = 0.
+    /// let sphere = Quadric::new([1., 1., 1., 0., 0., 0., 0., 0., 0., -1.], material);
+    /// ```
+    pub fn new(coefficients: [f32; 10], material: Arc<dyn MaterialExt>) -> Quadric {
+        Quadric {
+            coefficients,
+            material,
+        }
+    }
+
+    // Create a quadric without mixed terms, given in coordinates relative to `center`.
+    fn centered(
+        center: Vec3,
+        quadratic: Vec3,
+        linear: Vec3,
+        constant: f32,
+        material: Arc<dyn MaterialExt>,
+    ) -> Quadric {
+        let (a, b, c) = (quadratic.x(), quadratic.y(), quadratic.z());
+        let (g, h, i) = (linear.x(), linear.y(), linear.z());
+        let (x, y, z) = (center.x(), center.y(), center.z());
+        Quadric::new(
+            [
+                a,
+                b,
+                c,
+                0.,
+                0.,
+                0.,
+                g - 2. * a * x,
+                h - 2. * b * y,
+                i - 2. * c * z,
+                constant + a * x * x + b * y * y + c * z * z - g * x - h * y - i * z,
+            ],
+            material,
+        )
+    }
+
+    /// Create an axis-aligned ellipsoid from its `center` and `semi_axes`.
+    ///
+    /// ```
+    /// # use raytracer::objects::quadric::Quadric;
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::materials::Lambertian;
+    /// # use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let ellipsoid = Quadric::ellipsoid(Vec3(0., 0., 0.), Vec3(2., 1., 1.), material);
+    /// assert_eq!(ellipsoid.coefficients()[0], 0.25);
+    /// ```
+    pub fn ellipsoid(center: Vec3, semi_axes: Vec3, material: Arc<dyn MaterialExt>) -> Quadric {
+        let quadratic = Vec3(
+            semi_axes.x().powi(-2),
+            semi_axes.y().powi(-2),
+            semi_axes.z().powi(-2),
+        );
+        Quadric::centered(center, quadratic, Vec3(0., 0., 0.), -1., material)
+    }
+
+    /// Create an elliptic paraboloid opening upwards along the y-axis.
+    ///
+    /// Relative to the `vertex`, the surface is given by `y = (x / a)^2 + (z / b)^2`.
+    ///
+    /// ```
+    /// # use raytracer::objects::quadric::Quadric;
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::materials::Lambertian;
+    /// # use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let bowl = Quadric::paraboloid(Vec3(0., 0., 0.), 1., 1., material);
+    /// ```
+    pub fn paraboloid(vertex: Vec3, a: f32, b: f32, material: Arc<dyn MaterialExt>) -> Quadric {
+        let quadratic = Vec3(a.powi(-2), 0., b.powi(-2));
+        Quadric::centered(vertex, quadratic, Vec3(0., -1., 0.), 0., material)
+    }
+
+    /// Create a hyperboloid of one sheet around the y-axis.
+    ///
+    /// Relative to the `center`, the surface is given by
+    /// `(x / a)^2 - (y / b)^2 + (z / c)^2 = 1`, i.e. its waist is an ellipse
+    /// with semi-axes `a` and `c`.
+    ///
+    /// ```
+    /// # use raytracer::objects::quadric::Quadric;
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::materials::Lambertian;
+    /// # use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let tower = Quadric::hyperboloid_one_sheet(Vec3(0., 0., 0.), 1., 2., 1., material);
+    /// ```
+    pub fn hyperboloid_one_sheet(
+        center: Vec3,
+        a: f32,
+        b: f32,
+        c: f32,
+        material: Arc<dyn MaterialExt>,
+    ) -> Quadric {
+        let quadratic = Vec3(a.powi(-2), -b.powi(-2), c.powi(-2));
+        Quadric::centered(center, quadratic, Vec3(0., 0., 0.), -1., material)
+    }
+
+    /// Create a hyperboloid of two sheets around the y-axis.
+    ///
+    /// Relative to the `center`, the surface is given by
+    /// `(y / b)^2 - (x / a)^2 - (z / c)^2 = 1`, i.e. its vertices lie at `y = ±b`.
+    ///
+    /// ```
+    /// # use raytracer::objects::quadric::Quadric;
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::materials::Lambertian;
+    /// # use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let sheets = Quadric::hyperboloid_two_sheets(Vec3(0., 0., 0.), 1., 1., 1., material);
+    /// ```
+    pub fn hyperboloid_two_sheets(
+        center: Vec3,
+        a: f32,
+        b: f32,
+        c: f32,
+        material: Arc<dyn MaterialExt>,
+    ) -> Quadric {
+        // The sign is chosen such that the normals point away from the sheets' interior.
+        let quadratic = Vec3(a.powi(-2), -b.powi(-2), c.powi(-2));
+        Quadric::centered(center, quadratic, Vec3(0., 0., 0.), 1., material)
+    }
+
+    /// Access the ten coefficients `[A, B, C, D, E, F, G, H, I, J]` of a `Quadric`.
+    pub fn coefficients(&self) -> &[f32; 10] {
+        &self.coefficients
+    }
+
+    /// Access the `material` a `Quadric` is made of.
+    pub fn material(&self) -> Arc<dyn MaterialExt> {
+        Arc::clone(&self.material)
+    }
+
+    // The gradient of the defining polynomial at `p`.
+    fn gradient(&self, p: &Vec3) -> Vec3 {
+        let [a, b, c, d, e, f, g, h, i, _] = self.coefficients;
+        let (x, y, z) = (p.x(), p.y(), p.z());
+        Vec3(
+            2. * a * x + d * y + e * z + g,
+            2. * b * y + d * x + f * z + h,
+            2. * c * z + e * x + f * y + i,
+        )
+    }
+}
+
+impl Hitable for Quadric {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let [a, b, c, d, e, f, g, h, i, j] = self.coefficients;
+        let o = ray.origin();
+        let r = ray.direction();
+        let (ox, oy, oz) = (o.x(), o.y(), o.z());
+        let (dx, dy, dz) = (r.x(), r.y(), r.z());
+
+        // Coefficients of the polynomial in t: qa * t^2 + qb * t + qc.
+        let qa = a * dx * dx + b * dy * dy + c * dz * dz + d * dx * dy + e * dx * dz + f * dy * dz;
+        let qb = 2. * (a * ox * dx + b * oy * dy + c * oz * dz)
+            + d * (ox * dy + oy * dx)
+            + e * (ox * dz + oz * dx)
+            + f * (oy * dz + oz * dy)
+            + g * dx
+            + h * dy
+            + i * dz;
+        let qc = a * ox * ox
+            + b * oy * oy
+            + c * oz * oz
+            + d * ox * oy
+            + e * ox * oz
+            + f * oy * oz
+            + g * ox
+            + h * oy
+            + i * oz
+            + j;
+
+        let mut roots = if qa == 0. {
+            // The equation degenerates to a linear one, e.g. for rays parallel
+            // to the axis of a paraboloid.
+            if qb == 0. {
+                return None;
+            }
+            vec![-qc / qb]
+        } else {
+            let discriminant = qb * qb - 4. * qa * qc;
+            if discriminant < 0. {
+                return None;
+            }
+            // Numerically stable form which avoids cancellation for small `qa`.
+            let q = -0.5 * (qb + qb.signum() * discriminant.sqrt());
+            if q == 0. {
+                vec![0.]
+            } else {
+                vec![q / qa, qc / q]
+            }
+        };
+        roots.sort_by(|x, y| x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal));
+
+        let t = roots.into_iter().find(|&t| t > t_min && t < t_max)?;
+        let point = ray.point_at_parameter(t);
+        let normal = unit_vector(&self.gradient(&point));
+        Some(HitRecord {
+            parameter: t,
+            point_at_parameter: point,
+            normal,
+            u: 0.,
+            v: 0.,
+            tangent: Vec3(0., 0., 0.),
+            bitangent: Vec3(0., 0., 0.),
+            front_face: dot(ray.direction(), &normal) < 0.,
+            material: self.material.as_ref(),
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // Quadrics such as planes, cones and hyperboloids are unbounded.
+        None
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+    use crate::objects::sphere::Sphere;
+
+    fn material() -> Arc<dyn MaterialExt> {
+        Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)))
+    }
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    // Test that a spherical quadric matches the analytic sphere.
+    fn test_quadric_sphere() {
+        let center = Vec3(1., 2., -1.);
+        let quadric = Quadric::ellipsoid(center, Vec3(1.5, 1.5, 1.5), material());
+        let sphere = Sphere::new(center, 1.5, material());
+        for ray in &[
+            Ray::new(Vec3(-5., 2., -1.), Vec3(1., 0., 0.)),
+            Ray::new(Vec3(-5., 2.5, -0.5), Vec3(1., 0.1, 0.)),
+            Ray::new(Vec3(1., 2., -1.), Vec3(0., 0., 1.)),
+        ] {
+            let expected = sphere.intersect(ray, 0.001, f32::MAX).unwrap();
+            let hit = quadric.intersect(ray, 0.001, f32::MAX).unwrap();
+            assert!((hit.parameter - expected.parameter).abs() < 1e-4);
+            assert_close(hit.normal, expected.normal);
+        }
+    }
+
+    #[test]
+    // Test the paraboloid, including the degenerate linear case along its axis.
+    fn test_quadric_paraboloid() {
+        let paraboloid = Quadric::paraboloid(Vec3(0., 1., 0.), 1., 1., material());
+        // Along the axis the equation in t is linear.
+        let ray = Ray::new(Vec3(0., 5., 0.), Vec3(0., -1., 0.));
+        let hit = paraboloid.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.parameter - 4.).abs() < 1e-5);
+        assert_close(hit.normal, Vec3(0., -1., 0.));
+        // Parallel to the axis but offset, the bowl is hit at y = 1 + x^2.
+        let ray = Ray::new(Vec3(1., 5., 0.), Vec3(0., -1., 0.));
+        let hit = paraboloid.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_close(hit.point_at_parameter, Vec3(1., 2., 0.));
+        assert_close(hit.normal, unit_vector(&Vec3(2., -1., 0.)));
+        // A horizontal ray below the vertex misses.
+        let ray = Ray::new(Vec3(-5., 0.5, 0.), Vec3(1., 0., 0.));
+        assert!(paraboloid.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    // Test the hyperboloid of one sheet at its waist and away from it.
+    fn test_quadric_hyperboloid_one_sheet() {
+        let hyperboloid = Quadric::hyperboloid_one_sheet(Vec3(0., 0., 0.), 1., 1., 1., material());
+        let ray = Ray::new(Vec3(-5., 0., 0.), Vec3(1., 0., 0.));
+        let hit = hyperboloid.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_close(hit.point_at_parameter, Vec3(-1., 0., 0.));
+        assert_close(hit.normal, Vec3(-1., 0., 0.));
+        // At height 1 the radius is sqrt(2).
+        let ray = Ray::new(Vec3(-5., 1., 0.), Vec3(1., 0., 0.));
+        let hit = hyperboloid.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_close(hit.point_at_parameter, Vec3(-(2f32.sqrt()), 1., 0.));
+        // A ray through the center along the axis never hits.
+        let ray = Ray::new(Vec3(0., -5., 0.), Vec3(0., 1., 0.));
+        assert!(hyperboloid.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    // Test the hyperboloid of two sheets along its axis.
+    fn test_quadric_hyperboloid_two_sheets() {
+        let hyperboloid = Quadric::hyperboloid_two_sheets(Vec3(0., 0., 0.), 1., 2., 1., material());
+        let ray = Ray::new(Vec3(0., 5., 0.), Vec3(0., -1., 0.));
+        let hit = hyperboloid.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_close(hit.point_at_parameter, Vec3(0., 2., 0.));
+        // The ray starts inside the upper sheet and the normal points out of it.
+        assert_close(hit.normal, Vec3(0., -1., 0.));
+        // The gap between the sheets is empty.
+        let ray = Ray::new(Vec3(-5., 0., 0.), Vec3(1., 0., 0.));
+        assert!(hyperboloid.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+}
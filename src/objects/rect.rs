@@ -0,0 +1,200 @@
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::lights::Light;
+use crate::materials::MaterialExt;
+use crate::objects::Hitable;
+use crate::ray::Ray;
+use crate::vec3::cross;
+use crate::vec3::dot;
+use crate::vec3::unit_vector;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+/// A flat rectangle, or more generally a parallelogram, in three-dimensional space.
+///
+/// It is characterized by:
+/// - The coordinates of one of its corners.
+/// - The two edges starting at that corner. The front side is the one from
+///   which the second edge is counter-clockwise from the first.
+/// - A pointer to the material that it is made of.
+///
+/// Its texture coordinates `(u, v)` run from 0 to 1 along the two edges.
+pub struct Rect {
+    corner: Vec3,
+    edge_u: Vec3,
+    edge_v: Vec3,
+    material: Arc<dyn MaterialExt>,
+}
+
+impl Rect {
+    /// Create a `Rect` from a `corner`, the two edges `edge_u` and `edge_v`
+    /// starting at it, and its `Material`.
+    ///
+    /// ```
+    /// use raytracer::objects::rect::Rect;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.8, 0.8)));
+    /// let wall = Rect::new(Vec3(0., 0., 0.), Vec3(2., 0., 0.), Vec3(0., 1., 0.), material);
+    /// assert_eq!(wall.normal(), Vec3(0., 0., 1.));
+    /// ```
+    pub fn new(corner: Vec3, edge_u: Vec3, edge_v: Vec3, material: Arc<dyn MaterialExt>) -> Rect {
+        Rect {
+            corner,
+            edge_u,
+            edge_v,
+            material,
+        }
+    }
+
+    /// Access the corner of a `Rect` the edges start at.
+    pub fn corner(&self) -> &Vec3 {
+        &self.corner
+    }
+
+    /// Access the edge of a `Rect` along which `u` increases.
+    pub fn edge_u(&self) -> &Vec3 {
+        &self.edge_u
+    }
+
+    /// Access the edge of a `Rect` along which `v` increases.
+    pub fn edge_v(&self) -> &Vec3 {
+        &self.edge_v
+    }
+
+    /// Return the normal of a `Rect`, pointing towards its front side.
+    pub fn normal(&self) -> Vec3 {
+        unit_vector(&cross(&self.edge_u, &self.edge_v))
+    }
+
+    /// Access the `material` a `Rect` is made of.
+    pub fn material(&self) -> Arc<dyn MaterialExt> {
+        Arc::clone(&self.material)
+    }
+}
+
+impl Hitable for Rect {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let n = cross(&self.edge_u, &self.edge_v);
+        let denominator = dot(&n, ray.direction());
+        // The ray is parallel to the plane of the rectangle.
+        if denominator.abs() < 1e-12 {
+            return None;
+        }
+        let t = dot(&n, &(self.corner - *ray.origin())) / denominator;
+        if t <= t_min || t >= t_max {
+            return None;
+        }
+
+        // Express the hit in the basis of the edges, which need not be orthogonal.
+        let point = ray.point_at_parameter(t);
+        let p = point - self.corner;
+        let w = n / dot(&n, &n);
+        let u = dot(&w, &cross(&p, &self.edge_v));
+        let v = dot(&w, &cross(&self.edge_u, &p));
+        if !(0. ..=1.).contains(&u) || !(0. ..=1.).contains(&v) {
+            return None;
+        }
+        Some(HitRecord {
+            parameter: t,
+            point_at_parameter: point,
+            normal: unit_vector(&n),
+            u,
+            v,
+            tangent: Vec3(0., 0., 0.),
+            bitangent: Vec3(0., 0., 0.),
+            front_face: dot(ray.direction(), &n) < 0.,
+            material: self.material.as_ref(),
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Aabb::from_points(&[
+            self.corner,
+            self.corner + self.edge_u,
+            self.corner + self.edge_v,
+            self.corner + self.edge_u + self.edge_v,
+        ])
+    }
+
+    fn collect_lights(&self, lights: &mut Vec<Light>) {
+        lights.extend(Light::rect(
+            self.corner,
+            self.edge_u,
+            self.edge_v,
+            self.material.as_ref(),
+        ));
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+
+    fn rect() -> Rect {
+        Rect::new(
+            Vec3(-1., -1., -2.),
+            Vec3(4., 0., 0.),
+            Vec3(0., 2., 0.),
+            Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))),
+        )
+    }
+
+    #[test]
+    // Test hits inside and misses outside a rectangle.
+    fn test_rect_hit() {
+        let rect = rect();
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., -1.));
+        let hit = rect.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.parameter, 2.);
+        assert_eq!(hit.point_at_parameter, Vec3(0., 0., -2.));
+        assert_eq!(hit.normal, Vec3(0., 0., 1.));
+        assert!(rect.intersect(&ray, 0.001, 1.5).is_none());
+        let ray = Ray::new(Vec3(3.5, 0., 0.), Vec3(0., 0., -1.));
+        assert!(rect.intersect(&ray, 0.001, f32::MAX).is_none());
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(1., 0., 0.));
+        assert!(rect.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    // Test the texture coordinates at the corners and the center of a rectangle.
+    fn test_rect_uv() {
+        let rect = rect();
+        for (x, y, u, v) in [
+            (-1., -1., 0., 0.),
+            (3., -1., 1., 0.),
+            (-1., 1., 0., 1.),
+            (3., 1., 1., 1.),
+            (1., 0., 0.5, 0.5),
+        ] {
+            let ray = Ray::new(Vec3(x, y, 0.), Vec3(0., 0., -1.));
+            let hit = rect.intersect(&ray, 0.001, f32::MAX).unwrap();
+            assert!((hit.u - u).abs() < 1e-6);
+            assert!((hit.v - v).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    // Test the texture coordinates on a slanted parallelogram.
+    fn test_parallelogram_uv() {
+        let rect = Rect::new(
+            Vec3(0., 0., -1.),
+            Vec3(2., 0., 0.),
+            Vec3(1., 1., 0.),
+            Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))),
+        );
+        let ray = Ray::new(Vec3(2.5, 0.5, 0.), Vec3(0., 0., -1.));
+        let hit = rect.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.u - 1.).abs() < 1e-6);
+        assert!((hit.v - 0.5).abs() < 1e-6);
+        // Inside the bounding box, but outside the parallelogram.
+        let ray = Ray::new(Vec3(0.25, 0.75, 0.), Vec3(0., 0., -1.));
+        assert!(rect.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+}
@@ -0,0 +1,303 @@
+//! Rotations of hitable objects around the coordinate axes.
+//!
+//! Instead of rotating the object, incoming rays are rotated into the frame
+//! of the object by the opposite angle, and the resulting hit points and
+//! normals are rotated back into the world.
+
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::objects::Hitable;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+// The coordinate axis of a rotation.
+#[derive(Debug, Clone, Copy)]
+enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+// A rotation around a coordinate axis, with sine and cosine of the angle
+// precomputed.
+#[derive(Debug, Clone, Copy)]
+struct AxisRotation {
+    axis: Axis,
+    sin: f32,
+    cos: f32,
+}
+
+impl AxisRotation {
+    fn new(axis: Axis, degrees: f32) -> AxisRotation {
+        let radians = degrees.to_radians();
+        AxisRotation {
+            axis,
+            sin: radians.sin(),
+            cos: radians.cos(),
+        }
+    }
+
+    // Rotate `v` by the angle whose sine is `sin`, following the right-hand rule.
+    fn rotate(&self, v: &Vec3, sin: f32) -> Vec3 {
+        let cos = self.cos;
+        match self.axis {
+            Axis::X => Vec3(v.x(), cos * v.y() - sin * v.z(), sin * v.y() + cos * v.z()),
+            Axis::Y => Vec3(cos * v.x() + sin * v.z(), v.y(), -sin * v.x() + cos * v.z()),
+            Axis::Z => Vec3(cos * v.x() - sin * v.y(), sin * v.x() + cos * v.y(), v.z()),
+        }
+    }
+
+    fn apply(&self, v: &Vec3) -> Vec3 {
+        self.rotate(v, self.sin)
+    }
+
+    fn apply_inverse(&self, v: &Vec3) -> Vec3 {
+        self.rotate(v, -self.sin)
+    }
+
+    fn local_ray(&self, ray: &Ray) -> Ray {
+        Ray::new_with_time(
+            self.apply_inverse(ray.origin()),
+            self.apply_inverse(ray.direction()),
+            ray.time(),
+        )
+        .with_medium_seed(ray.medium_seed())
+    }
+
+    fn world_hit<'a>(&self, mut hit: HitRecord<'a>) -> HitRecord<'a> {
+        hit.point_at_parameter = self.apply(&hit.point_at_parameter);
+        // Rotations are orthogonal, so normals transform like points.
+        hit.normal = self.apply(&hit.normal);
+        hit.map_tangents(|v| self.apply(v));
+        hit
+    }
+
+    fn intersect<'a>(
+        &self,
+        object: &'a dyn Hitable,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<HitRecord<'a>> {
+        object
+            .intersect(&self.local_ray(ray), t_min, t_max)
+            .map(|hit| self.world_hit(hit))
+    }
+
+    fn bounding_box(&self, object: &dyn Hitable) -> Option<Aabb> {
+        let corners = object.bounding_box()?.corners();
+        Aabb::from_points(&corners.iter().map(|c| self.apply(c)).collect::<Vec<_>>())
+    }
+
+    fn intersect_all<'a>(&self, object: &'a dyn Hitable, ray: &Ray) -> Vec<HitRecord<'a>> {
+        object
+            .intersect_all(&self.local_ray(ray))
+            .into_iter()
+            .map(|hit| self.world_hit(hit))
+            .collect()
+    }
+}
+
+/// A hitable object rotated around the x-axis.
+pub struct RotateX {
+    object: Box<dyn Hitable>,
+    rotation: AxisRotation,
+}
+
+impl RotateX {
+    /// Rotate the hitable `object` by `degrees` around the x-axis.
+    ///
+    /// ```
+    /// use raytracer::objects::rotate::RotateX;
+    /// use raytracer::objects::sphere::Sphere;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let sphere = Sphere::new(Vec3(0., 2., 0.), 1., material);
+    /// let rotated = RotateX::new(Box::new(sphere), 90.);
+    /// ```
+    pub fn new(object: Box<dyn Hitable>, degrees: f32) -> RotateX {
+        RotateX {
+            object,
+            rotation: AxisRotation::new(Axis::X, degrees),
+        }
+    }
+}
+
+impl Hitable for RotateX {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.rotation.intersect(&*self.object, ray, t_min, t_max)
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        self.rotation.intersect_all(&*self.object, ray)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.rotation.bounding_box(self.object.as_ref())
+    }
+}
+
+/// A hitable object rotated around the y-axis.
+pub struct RotateY {
+    object: Box<dyn Hitable>,
+    rotation: AxisRotation,
+}
+
+impl RotateY {
+    /// Rotate the hitable `object` by `degrees` around the y-axis.
+    ///
+    /// ```
+    /// use raytracer::objects::rotate::RotateY;
+    /// use raytracer::objects::cuboid::Cuboid;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let cuboid = Cuboid::new(Vec3(-1., -1., -1.), Vec3(1., 1., 1.), material);
+    /// let rotated = RotateY::new(Box::new(cuboid), 45.);
+    /// ```
+    pub fn new(object: Box<dyn Hitable>, degrees: f32) -> RotateY {
+        RotateY {
+            object,
+            rotation: AxisRotation::new(Axis::Y, degrees),
+        }
+    }
+}
+
+impl Hitable for RotateY {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.rotation.intersect(&*self.object, ray, t_min, t_max)
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        self.rotation.intersect_all(&*self.object, ray)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.rotation.bounding_box(self.object.as_ref())
+    }
+}
+
+/// A hitable object rotated around the z-axis.
+pub struct RotateZ {
+    object: Box<dyn Hitable>,
+    rotation: AxisRotation,
+}
+
+impl RotateZ {
+    /// Rotate the hitable `object` by `degrees` around the z-axis.
+    ///
+    /// ```
+    /// use raytracer::objects::rotate::RotateZ;
+    /// use raytracer::objects::sphere::Sphere;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let sphere = Sphere::new(Vec3(2., 0., 0.), 1., material);
+    /// let rotated = RotateZ::new(Box::new(sphere), 90.);
+    /// ```
+    pub fn new(object: Box<dyn Hitable>, degrees: f32) -> RotateZ {
+        RotateZ {
+            object,
+            rotation: AxisRotation::new(Axis::Z, degrees),
+        }
+    }
+}
+
+impl Hitable for RotateZ {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.rotation.intersect(&*self.object, ray, t_min, t_max)
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        self.rotation.intersect_all(&*self.object, ray)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.rotation.bounding_box(self.object.as_ref())
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+    use crate::materials::MaterialExt;
+    use crate::objects::cuboid::Cuboid;
+    use crate::objects::infinite_cylinder::InfiniteCylinder;
+    use crate::objects::sphere::Sphere;
+    use std::sync::Arc;
+
+    fn material() -> Arc<dyn MaterialExt> {
+        Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)))
+    }
+
+    fn assert_close(a: &Vec3, b: &Vec3) {
+        assert!((*a - *b).length() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    // Test a long thin box rotated around y, checking hit points and normals.
+    fn test_rotate_y_thin_box() {
+        let cuboid = Cuboid::new(Vec3(-2., -0.1, -0.1), Vec3(2., 0.1, 0.1), material());
+        let rotated = RotateY::new(Box::new(cuboid), 45.);
+        let h = 0.5_f32.sqrt();
+
+        // Hit the long side of the box.
+        let ray = Ray::new(Vec3(0., 0., 5.), Vec3(0., 0., -1.));
+        let hit = rotated.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_close(&hit.point_at_parameter, &Vec3(0., 0., 0.1 / h));
+        assert_close(&hit.normal, &Vec3(h, 0., h));
+
+        // Hit the end cap of the box, which now sits at (2h, 0, -2h).
+        let ray = Ray::new(Vec3(5., 0., -2. * h), Vec3(-1., 0., 0.));
+        let hit = rotated.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_close(&hit.point_at_parameter, &Vec3(2. * h, 0., -2. * h));
+        assert_close(&hit.normal, &Vec3(h, 0., -h));
+
+        // The unrotated box would be hit here, the rotated one is not.
+        let ray = Ray::new(Vec3(-1.5, 0., 5.), Vec3(0., 0., -1.));
+        assert!(rotated.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    // Test the direction of rotations around x and z.
+    fn test_rotate_x_and_z() {
+        let sphere = Sphere::new(Vec3(0., 2., 0.), 1., material());
+        let rotated = RotateX::new(Box::new(sphere), 90.);
+        // The sphere moves from the y- onto the z-axis.
+        let ray = Ray::new(Vec3(0., 0., 5.), Vec3(0., 0., -1.));
+        let hit = rotated.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_close(&hit.point_at_parameter, &Vec3(0., 0., 3.));
+        assert_close(&hit.normal, &Vec3(0., 0., 1.));
+
+        let sphere = Sphere::new(Vec3(2., 0., 0.), 1., material());
+        let rotated = RotateZ::new(Box::new(sphere), 90.);
+        // The sphere moves from the x- onto the y-axis.
+        let ray = Ray::new(Vec3(0., 5., 0.), Vec3(0., -1., 0.));
+        let hit = rotated.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_close(&hit.point_at_parameter, &Vec3(0., 3., 0.));
+        assert_close(&hit.normal, &Vec3(0., 1., 0.));
+        assert_eq!(rotated.intersect_all(&ray).len(), 2);
+    }
+
+    #[test]
+    // Test that the bounding box of a rotated object encloses its rotated box.
+    fn test_rotate_bounding_box() {
+        let sphere = Sphere::new(Vec3(2., 0., 0.), 1., material());
+        let rotated = RotateY::new(Box::new(sphere), 90.);
+        let aabb = rotated.bounding_box().unwrap();
+        assert_close(aabb.min(), &Vec3(-1., -1., -3.));
+        assert_close(aabb.max(), &Vec3(1., 1., -1.));
+        // An unbounded object stays unbounded.
+        let pipe = InfiniteCylinder::new(Vec3(0., 0., 0.), Vec3(0., 1., 0.), 1., material());
+        assert!(RotateX::new(Box::new(pipe), 30.).bounding_box().is_none());
+    }
+}
@@ -0,0 +1,131 @@
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::objects::Hitable;
+use crate::objects::ObjectError;
+use crate::ray::Ray;
+use crate::vec3::unit_vector;
+use crate::vec3::Vec3;
+
+/// A hitable object scaled by a separate factor along each axis.
+///
+/// Incoming rays are divided component-wise by the factors, which keeps the
+/// ray parameter `t` of a hit unchanged. Normals are divided by the factors
+/// as well and renormalized, so that they stay perpendicular to the surface.
+pub struct Scale {
+    object: Box<dyn Hitable>,
+    factors: Vec3,
+}
+
+impl Scale {
+    /// Scale the hitable `object` by `factors` along the x-, y- and z-axis.
+    ///
+    /// An error is returned unless all factors are positive.
+    ///
+    /// ```
+    /// use raytracer::objects::scale::Scale;
+    /// use raytracer::objects::sphere::Sphere;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let sphere = || Box::new(Sphere::new(Vec3(0., 0., 0.), 1., material.clone()));
+    /// assert!(Scale::new(sphere(), Vec3(3., 1., 1.)).is_ok());
+    /// assert!(Scale::new(sphere(), Vec3(3., 0., 1.)).is_err());
+    /// assert!(Scale::new(sphere(), Vec3(3., -1., 1.)).is_err());
+    /// ```
+    pub fn new(object: Box<dyn Hitable>, factors: Vec3) -> Result<Scale, ObjectError> {
+        // Written such that NaN factors are rejected as well.
+        if !(factors.x() > 0. && factors.y() > 0. && factors.z() > 0.) {
+            return Err(ObjectError::InvalidScale { factors });
+        }
+        Ok(Scale { object, factors })
+    }
+
+    /// Access the scale factors along the x-, y- and z-axis.
+    pub fn factors(&self) -> &Vec3 {
+        &self.factors
+    }
+
+    fn local_ray(&self, ray: &Ray) -> Ray {
+        Ray::new_with_time(
+            *ray.origin() / self.factors,
+            *ray.direction() / self.factors,
+            ray.time(),
+        )
+        .with_medium_seed(ray.medium_seed())
+    }
+
+    fn world_hit<'a>(&self, mut hit: HitRecord<'a>) -> HitRecord<'a> {
+        hit.point_at_parameter *= self.factors;
+        hit.normal = unit_vector(&(hit.normal / self.factors));
+        hit.map_tangents(|v| *v * self.factors);
+        hit
+    }
+}
+
+impl Hitable for Scale {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.object
+            .intersect(&self.local_ray(ray), t_min, t_max)
+            .map(|hit| self.world_hit(hit))
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        self.object
+            .intersect_all(&self.local_ray(ray))
+            .into_iter()
+            .map(|hit| self.world_hit(hit))
+            .collect()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let aabb = self.object.bounding_box()?;
+        Some(Aabb::new(
+            *aabb.min() * self.factors,
+            *aabb.max() * self.factors,
+        ))
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+    use crate::objects::ellipsoid::Ellipsoid;
+    use crate::objects::sphere::Sphere;
+    use std::sync::Arc;
+
+    #[test]
+    // Test that a unit sphere scaled into an ellipsoid is hit where the sphere is not.
+    fn test_scale_sphere_to_ellipsoid() {
+        let material = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        let sphere = Sphere::new(Vec3(0., 0., 0.), 1., material.clone());
+        let ray = Ray::new(Vec3(2., 5., 0.), Vec3(0., -1., 0.));
+        assert!(sphere.intersect(&ray, 0.001, f32::MAX).is_none());
+
+        let scaled = Scale::new(Box::new(sphere), Vec3(3., 1., 1.)).unwrap();
+        let hit = scaled.intersect(&ray, 0.001, f32::MAX).unwrap();
+        let ellipsoid = Ellipsoid::new(Vec3(0., 0., 0.), Vec3(3., 1., 1.), material);
+        let expected = ellipsoid.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.parameter - expected.parameter).abs() < 1e-5);
+        assert!((hit.point_at_parameter - expected.point_at_parameter).length() < 1e-5);
+        assert!((hit.normal - expected.normal).length() < 1e-5);
+    }
+
+    #[test]
+    // Test that zero, negative and NaN factors are rejected.
+    fn test_invalid_scale() {
+        let material = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        for factors in [Vec3(0., 1., 1.), Vec3(1., -2., 1.), Vec3(1., 1., f32::NAN)] {
+            let sphere = Box::new(Sphere::new(Vec3(0., 0., 0.), 1., material.clone()));
+            assert!(matches!(
+                Scale::new(sphere, factors),
+                Err(ObjectError::InvalidScale { .. })
+            ));
+        }
+    }
+}
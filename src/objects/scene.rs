@@ -0,0 +1,102 @@
+//! A scene ready to be rendered, with its lights collected.
+//!
+//! Building a `Scene` collects the emitting objects of the world, see
+//! `Hitable::collect_lights`, and builds a light tree over them. The
+//! renderer then samples directions towards the lights at every diffuse
+//! surface, see `render::color`, which resolves small and distant lights
+//! with far fewer samples than waiting for rays to hit them.
+
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::lights::Light;
+use crate::lights::Lights;
+use crate::objects::Hitable;
+use crate::ray::Ray;
+use crate::ray::PACKET_SIZE;
+
+/// A world together with the lights among its objects.
+///
+/// The scene is hit like the world. Objects changed afterwards, e.g. moved
+/// by refitting a `BvhNode`, keep their lights where they were collected.
+///
+/// ```
+/// # use raytracer::materials::{Lambertian, MaterialExt};
+/// # use raytracer::hit_record::HitRecord;
+/// # use raytracer::objects::scene::Scene;
+/// # use raytracer::objects::sphere::Sphere;
+/// # use raytracer::objects::{Hitable, HitableList};
+/// # use raytracer::ray::Ray;
+/// # use raytracer::vec3::Vec3;
+/// # use rand::RngCore;
+/// # use std::sync::Arc;
+/// struct Glow;
+///
+/// impl MaterialExt for Glow {
+///     fn scatter(&self, _ray: &Ray, _hit: &HitRecord, _rng: &mut dyn RngCore) -> Option<(Ray, Vec3)> {
+///         None
+///     }
+///
+///     fn emitted(&self, _hit: &HitRecord) -> Vec3 {
+///         Vec3(4., 4., 4.)
+///     }
+/// }
+///
+/// let world = HitableList::new(vec![
+///     Box::new(Sphere::new(Vec3(0., -100., 0.), 100., Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))))),
+///     Box::new(Sphere::new(Vec3(0., 3., 0.), 0.5, Arc::new(Glow))),
+/// ]);
+/// let scene = Scene::new(world);
+/// assert_eq!(scene.lights().unwrap().lights().len(), 1);
+/// ```
+pub struct Scene<H: Hitable = Box<dyn Hitable>> {
+    world: H,
+    lights: Lights,
+}
+
+impl<H: Hitable> Scene<H> {
+    /// Collect the lights of `world` and build the light tree over them.
+    pub fn new(world: H) -> Scene<H> {
+        let lights = Lights::collect(&world);
+        Scene { world, lights }
+    }
+
+    /// Access the world of the scene.
+    pub fn world(&self) -> &H {
+        &self.world
+    }
+}
+
+impl<H: Hitable> Hitable for Scene<H> {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.world.intersect(ray, t_min, t_max)
+    }
+
+    fn intersect_any(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.world.intersect_any(ray, t_min, t_max)
+    }
+
+    fn intersect_packet(
+        &self,
+        rays: &[Ray; PACKET_SIZE],
+        t_min: f32,
+        t_max: f32,
+    ) -> [Option<HitRecord<'_>>; PACKET_SIZE] {
+        self.world.intersect_packet(rays, t_min, t_max)
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        self.world.intersect_all(ray)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.world.bounding_box()
+    }
+
+    fn collect_lights(&self, lights: &mut Vec<Light>) {
+        lights.extend_from_slice(self.lights.lights());
+    }
+
+    fn lights(&self) -> Option<&Lights> {
+        Some(&self.lights)
+    }
+}
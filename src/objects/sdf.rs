@@ -0,0 +1,289 @@
+//! Objects described by a signed distance function.
+//!
+//! A signed distance function returns for each point in space the distance
+//! to the closest point on the surface, negative inside the object. Such
+//! objects are intersected by sphere tracing: the ray is advanced by the
+//! distance at its current point, which cannot overshoot the surface.
+
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::materials::MaterialExt;
+use crate::objects::Hitable;
+use crate::ray::Ray;
+use crate::vec3::dot;
+use crate::vec3::unit_vector;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+/// Signed distance of `p` to a sphere of `radius` around `center`.
+///
+/// ```
+/// # use raytracer::objects::sdf::sphere_distance;
+/// # use raytracer::vec3::Vec3;
+/// assert_eq!(sphere_distance(Vec3(3., 0., 0.), Vec3(0., 0., 0.), 1.), 2.);
+/// assert_eq!(sphere_distance(Vec3(0., 0., 0.), Vec3(0., 0., 0.), 1.), -1.);
+/// ```
+pub fn sphere_distance(p: Vec3, center: Vec3, radius: f32) -> f32 {
+    (p - center).length() - radius
+}
+
+/// Signed distance of `p` to an axis-aligned box around `center` with
+/// `half_extents`, whose edges are rounded with `radius`.
+///
+/// The rounding is added on the outside, such that the box extends by
+/// `half_extents + radius` from its center along each axis.
+///
+/// ```
+/// # use raytracer::objects::sdf::rounded_box_distance;
+/// # use raytracer::vec3::Vec3;
+/// let half_extents = Vec3(1., 1., 1.);
+/// let d = rounded_box_distance(Vec3(3., 0., 0.), Vec3(0., 0., 0.), half_extents, 0.5);
+/// assert_eq!(d, 1.5);
+/// ```
+pub fn rounded_box_distance(p: Vec3, center: Vec3, half_extents: Vec3, radius: f32) -> f32 {
+    let local = p - center;
+    let q = Vec3(
+        local.x().abs() - half_extents.x(),
+        local.y().abs() - half_extents.y(),
+        local.z().abs() - half_extents.z(),
+    );
+    let outside = Vec3(q.x().max(0.), q.y().max(0.), q.z().max(0.)).length();
+    let inside = q.x().max(q.y()).max(q.z()).min(0.);
+    outside + inside - radius
+}
+
+/// Smooth minimum of the distances `a` and `b`, blending over a range `k`.
+///
+/// Used to take the union of two distance functions with a smooth fillet
+/// where their surfaces meet. For distances further apart than `k` it is
+/// the plain minimum.
+///
+/// ```
+/// # use raytracer::objects::sdf::smooth_min;
+/// assert_eq!(smooth_min(1., 3., 0.5), 1.);
+/// assert!(smooth_min(1., 1., 0.5) < 1.);
+/// ```
+pub fn smooth_min(a: f32, b: f32, k: f32) -> f32 {
+    let h = (k - (a - b).abs()).max(0.) / k;
+    a.min(b) - 0.25 * h * h * k
+}
+
+/// An object described by a signed distance function.
+pub struct SdfObject {
+    distance: Box<dyn Fn(Vec3) -> f32 + Send + Sync>,
+    material: Arc<dyn MaterialExt>,
+    max_steps: usize,
+    epsilon: f32,
+}
+
+impl SdfObject {
+    /// Create an `SdfObject` from the signed `distance` function and its `Material`.
+    ///
+    /// Sphere tracing takes at most 256 steps and considers a point within
+    /// a distance of `1e-4` to be on the surface, see `with_max_steps` and
+    /// `with_epsilon` to change these.
+    ///
+    /// ```
+    /// use raytracer::objects::sdf::{smooth_min, sphere_distance, SdfObject};
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// // Two spheres smoothly merged into a single blob.
+    /// let blob = SdfObject::new(
+    ///     |p| {
+    ///         smooth_min(
+    ///             sphere_distance(p, Vec3(-0.5, 1., 0.), 0.7),
+    ///             sphere_distance(p, Vec3(0.5, 1., 0.), 0.7),
+    ///             0.3,
+    ///         )
+    ///     },
+    ///     material,
+    /// );
+    /// ```
+    pub fn new<F>(distance: F, material: Arc<dyn MaterialExt>) -> SdfObject
+    where
+        F: Fn(Vec3) -> f32 + Send + Sync + 'static,
+    {
+        SdfObject {
+            distance: Box::new(distance),
+            material,
+            max_steps: 256,
+            epsilon: 1e-4,
+        }
+    }
+
+    /// Set the maximal number of sphere tracing steps before giving up.
+    pub fn with_max_steps(mut self, max_steps: usize) -> SdfObject {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Set the distance to the surface below which a point counts as a hit.
+    pub fn with_epsilon(mut self, epsilon: f32) -> SdfObject {
+        self.epsilon = epsilon;
+        self
+    }
+
+    /// Evaluate the signed distance function at `p`.
+    pub fn distance(&self, p: Vec3) -> f32 {
+        (self.distance)(p)
+    }
+
+    /// Access the `material` an `SdfObject` is made of.
+    pub fn material(&self) -> Arc<dyn MaterialExt> {
+        Arc::clone(&self.material)
+    }
+
+    // Estimate the normal at `p` by central differences of the distance.
+    fn normal(&self, p: Vec3) -> Vec3 {
+        let h = self.epsilon.max(1e-4);
+        let dx = Vec3(h, 0., 0.);
+        let dy = Vec3(0., h, 0.);
+        let dz = Vec3(0., 0., h);
+        unit_vector(&Vec3(
+            self.distance(p + dx) - self.distance(p - dx),
+            self.distance(p + dy) - self.distance(p - dy),
+            self.distance(p + dz) - self.distance(p - dz),
+        ))
+    }
+}
+
+impl Hitable for SdfObject {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        // The distance is measured in world units, while the ray parameter
+        // is in units of the (not necessarily normalized) direction.
+        let ray_length = ray.direction().length();
+        let mut t = t_min;
+        for _ in 0..self.max_steps {
+            let point = ray.point_at_parameter(t);
+            // The absolute distance also steps towards the surface from inside.
+            let distance = self.distance(point).abs();
+            if distance < self.epsilon {
+                let normal = self.normal(point);
+                return Some(HitRecord {
+                    parameter: t,
+                    point_at_parameter: point,
+                    normal,
+                    u: 0.,
+                    v: 0.,
+                    tangent: Vec3(0., 0., 0.),
+                    bitangent: Vec3(0., 0., 0.),
+                    front_face: dot(ray.direction(), &normal) < 0.,
+                    material: self.material.as_ref(),
+                });
+            }
+            t += distance / ray_length;
+            if t >= t_max {
+                return None;
+            }
+        }
+        None
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // The extent of the surface is not known from the distance function.
+        None
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+    use crate::objects::sphere::Sphere;
+
+    fn material() -> Arc<dyn MaterialExt> {
+        Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)))
+    }
+
+    fn sdf_sphere() -> SdfObject {
+        SdfObject::new(|p| sphere_distance(p, Vec3(0., 0., -3.), 1.), material())
+    }
+
+    #[test]
+    // Test that hits on the SDF sphere agree with the analytic sphere.
+    fn test_sdf_sphere_matches_sphere() {
+        let sdf = sdf_sphere();
+        let sphere = Sphere::new(Vec3(0., 0., -3.), 1., material());
+        for direction in [
+            Vec3(0., 0., -1.),
+            Vec3(0.2, 0.1, -1.),
+            Vec3(-0.25, 0.15, -2.),
+            Vec3(0., 0.3, -1.),
+        ] {
+            let ray = Ray::new(Vec3(0., 0., 0.), direction);
+            let expected = sphere.intersect(&ray, 0.001, f32::MAX).unwrap();
+            let hit = sdf.intersect(&ray, 0.001, f32::MAX).unwrap();
+            assert!((hit.parameter - expected.parameter).abs() < 1e-3);
+            assert!((hit.point_at_parameter - expected.point_at_parameter).length() < 1e-3);
+            assert!((hit.normal - expected.normal).length() < 1e-2);
+        }
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0., 1., -1.));
+        assert!(sdf.intersect(&ray, 0.001, 100.).is_none());
+    }
+
+    #[test]
+    // Test that hits are never reported behind `t_min` or beyond `t_max`.
+    fn test_sdf_parameter_range() {
+        let sdf = sdf_sphere();
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., -1.));
+        // Starting inside the sphere, the far side is hit.
+        let hit = sdf.intersect(&ray, 3., f32::MAX).unwrap();
+        assert!((hit.parameter - 4.).abs() < 1e-3);
+        assert!(sdf.intersect(&ray, 0.001, 1.9).is_none());
+        assert!(sdf.intersect(&ray, 4.1, f32::MAX).is_none());
+    }
+
+    #[test]
+    // Test that the marcher bails out when the step count is exhausted.
+    fn test_sdf_max_steps() {
+        let sdf = sdf_sphere().with_max_steps(1);
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0.2, 0., -1.));
+        assert!(sdf.intersect(&ray, 0.001, f32::MAX).is_none());
+        let sdf = sdf_sphere().with_max_steps(1000).with_epsilon(1e-3);
+        assert!(sdf.intersect(&ray, 0.001, f32::MAX).is_some());
+    }
+
+    #[test]
+    // Test the rounded box and the smooth union distance functions.
+    fn test_rounded_box_and_smooth_union() {
+        let rounded = SdfObject::new(
+            |p| rounded_box_distance(p, Vec3(0., 0., 0.), Vec3(1., 0.5, 0.5), 0.2),
+            material(),
+        );
+        let ray = Ray::new(Vec3(5., 0., 0.), Vec3(-1., 0., 0.));
+        let hit = rounded.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.point_at_parameter.x() - 1.2).abs() < 1e-3);
+        assert!((hit.normal - Vec3(1., 0., 0.)).length() < 1e-3);
+
+        // The smooth union fills the gap between two nearly touching spheres.
+        let union = SdfObject::new(
+            |p| {
+                smooth_min(
+                    sphere_distance(p, Vec3(-1.05, 0., 0.), 1.),
+                    sphere_distance(p, Vec3(1.05, 0., 0.), 1.),
+                    0.5,
+                )
+            },
+            material(),
+        );
+        let ray = Ray::new(Vec3(0., 5., 0.), Vec3(0., -1., 0.));
+        assert!(union.intersect(&ray, 0.001, f32::MAX).is_some());
+        let hard_union = SdfObject::new(
+            |p| {
+                sphere_distance(p, Vec3(-1.05, 0., 0.), 1.).min(sphere_distance(
+                    p,
+                    Vec3(1.05, 0., 0.),
+                    1.,
+                ))
+            },
+            material(),
+        );
+        assert!(hard_union.intersect(&ray, 0.001, 10.).is_none());
+    }
+}
@@ -1,11 +1,59 @@
+use crate::aabb::Aabb;
 use crate::hit_record::HitRecord;
-use crate::materials::Material;
+use crate::lights::Light;
+use crate::materials::MaterialExt;
 use crate::objects::Hitable;
 use crate::ray::Ray;
+use crate::vec3::cross;
 use crate::vec3::dot;
+use crate::vec3::unit_vector;
 use crate::vec3::Vec3;
+use std::f32::consts::PI;
 use std::sync::Arc;
 
+/// Return the surface coordinates of the point `p` on the unit sphere.
+///
+/// `u` is the angle around the y-axis, starting from the -x-axis towards
+/// -z, and `v` is the angle from the south pole at -y to the north pole,
+/// both scaled to lie between 0 and 1.
+///
+/// ```
+/// # use raytracer::objects::sphere::sphere_uv;
+/// # use raytracer::vec3::Vec3;
+/// assert_eq!(sphere_uv(&Vec3(1., 0., 0.)), (0.5, 0.5));
+/// assert_eq!(sphere_uv(&Vec3(0., 1., 0.)).1, 1.);
+/// ```
+pub fn sphere_uv(p: &Vec3) -> (f32, f32) {
+    // Clamp to avoid NaN for points slightly off the sphere at the poles.
+    let theta = (-p.y()).clamp(-1., 1.).acos();
+    let phi = (-p.z()).atan2(p.x()) + PI;
+    ((phi / (2. * PI)).clamp(0., 1.), theta / PI)
+}
+
+/// Return the tangent and the bitangent of the unit sphere at the point `p`,
+/// the directions in which the surface coordinates `u` and `v` of
+/// `sphere_uv` increase.
+///
+/// Both vanish at the poles, where `u` is undefined.
+///
+/// ```
+/// # use raytracer::objects::sphere::sphere_tangents;
+/// # use raytracer::vec3::Vec3;
+/// let (tangent, bitangent) = sphere_tangents(&Vec3(1., 0., 0.));
+/// assert_eq!(tangent, Vec3(0., 0., -1.));
+/// assert_eq!(bitangent, Vec3(0., 1., 0.));
+/// assert_eq!(sphere_tangents(&Vec3(0., 1., 0.)).0, Vec3(0., 0., 0.));
+/// ```
+pub fn sphere_tangents(p: &Vec3) -> (Vec3, Vec3) {
+    let tangent = Vec3(p.z(), 0., -p.x());
+    let length = tangent.length();
+    if length < 1e-6 {
+        return (Vec3(0., 0., 0.), Vec3(0., 0., 0.));
+    }
+    let tangent = tangent / length;
+    (tangent, unit_vector(&cross(p, &tangent)))
+}
+
 /// A Sphere in three-dimensional space.
 ///
 /// It is characterized by three properties:  
@@ -13,14 +61,25 @@ use std::sync::Arc;
 /// - It's radius
 /// - A pointer to the material that it is made of.  
 ///
+/// A negative radius turns the normals inwards while the surface stays
+/// the same. Hollow spheres, e.g. glass bubbles, are better modelled by a
+/// `SphereShell`, which takes care of this for its inner boundary.
+///
 /// The pointer is an `Arc` because we want to use
 /// the Sphere object belonging to a scene in rayon
 /// threads through `Arc`s without having to clone them.
-pub struct Sphere {
+///
+/// A sphere can refer to its material by other means `M` as well, e.g. by
+/// the `arena::MaterialId` of a material stored in a `SceneArena`, in which
+/// case the owner of the materials makes it hitable.
+pub struct Sphere<M = Arc<dyn MaterialExt>> {
     center: Vec3,
     radius: f32,
+    // Derived from the radius once, since they are needed for every ray.
+    radius_squared: f32,
+    inv_radius: f32,
     // We want to use spheres with rayon.
-    material: Arc<dyn Material>,
+    material: M,
 }
 
 impl Sphere {
@@ -35,14 +94,45 @@ impl Sphere {
     /// let arc_material = Arc::new(Dielectric::new(1.5));
     /// let sphere = sphere::Sphere::new(center, 3., arc_material);
     /// ```
-    pub fn new(center: Vec3, radius: f32, material: Arc<dyn Material>) -> Sphere {
+    pub fn new(center: Vec3, radius: f32, material: Arc<dyn MaterialExt>) -> Sphere {
+        Sphere::with_material(center, radius, material)
+    }
+
+    /// Access the `material` a `Sphere` is made of.
+    pub fn material(&self) -> Arc<dyn MaterialExt> {
+        Arc::clone(&self.material)
+    }
+}
+
+impl<M> Sphere<M> {
+    /// Create a `Sphere` by specifying its `center`, `radius` and a
+    /// reference to its material of any kind.
+    ///
+    /// ```
+    /// # use raytracer::objects::arena::SceneArena;
+    /// # use raytracer::objects::sphere::Sphere;
+    /// # use raytracer::materials::Lambertian;
+    /// # use raytracer::vec3::Vec3;
+    /// let mut arena = SceneArena::new();
+    /// let material = arena.add_material(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let sphere = Sphere::with_material(Vec3(0., 0., -1.), 0.5, material);
+    /// assert_eq!(sphere.material_ref(), &material);
+    /// ```
+    pub fn with_material(center: Vec3, radius: f32, material: M) -> Sphere<M> {
         Sphere {
             center,
             radius,
+            radius_squared: radius * radius,
+            inv_radius: 1. / radius,
             material,
         }
     }
 
+    /// Access the reference to the material of a `Sphere`.
+    pub fn material_ref(&self) -> &M {
+        &self.material
+    }
+
     /// Access the center of a `Sphere`.
     ///
     /// ```
@@ -75,41 +165,240 @@ impl Sphere {
         &self.radius
     }
 
-    /// Access the `material` a `Sphere` is made of.
-    pub fn material(&self) -> Arc<dyn Material> {
-        Arc::clone(&self.material)
+    // The nearest hit within the range, borrowing `material`.
+    pub(crate) fn intersect_with<'a>(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        material: &'a dyn MaterialExt,
+    ) -> Option<HitRecord<'a>> {
+        let t = self
+            .roots(ray)?
+            .iter()
+            .cloned()
+            .find(|&t| t > t_min && t < t_max)?;
+        Some(self.hit_record(ray, t, material))
     }
-}
 
-impl Hitable for Sphere {
-    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    // Whether the sphere is hit within the range.
+    pub(crate) fn intersect_any_within(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.roots(ray)
+            .is_some_and(|roots| roots.iter().any(|&t| t > t_min && t < t_max))
+    }
+
+    // All hits, borrowing `material`.
+    pub(crate) fn intersect_all_with<'a>(
+        &self,
+        ray: &Ray,
+        material: &'a dyn MaterialExt,
+    ) -> Vec<HitRecord<'a>> {
+        match self.roots(ray) {
+            Some(roots) => roots
+                .iter()
+                .map(|&t| self.hit_record(ray, t, material))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // The box enclosing the sphere.
+    pub(crate) fn aabb(&self) -> Aabb {
+        let extent = Vec3(self.radius.abs(), self.radius.abs(), self.radius.abs());
+        Aabb::new(self.center - extent, self.center + extent)
+    }
+
+    // The hit record at the parameter `t`, borrowing `material`, which is
+    // the material of the sphere unless it is stored elsewhere.
+    pub(crate) fn hit_record<'a>(
+        &self,
+        ray: &Ray,
+        t: f32,
+        material: &'a dyn MaterialExt,
+    ) -> HitRecord<'a> {
+        let point = ray.point_at_parameter(t);
+        // A negative radius flips the normal, but not the surface coordinates.
+        let normal = (point - self.center) * self.inv_radius;
+        let outward = normal * self.inv_radius.signum();
+        let (u, v) = sphere_uv(&outward);
+        let (tangent, bitangent) = sphere_tangents(&outward);
+        HitRecord {
+            parameter: t,
+            point_at_parameter: point,
+            normal,
+            u,
+            v,
+            tangent,
+            bitangent,
+            front_face: dot(ray.direction(), &normal) < 0.,
+            material,
+        }
+    }
+
+    // Return the parameters where the line through the ray crosses the
+    // surface, nearest first.
+    fn roots(&self, ray: &Ray) -> Option<[f32; 2]> {
         let oc = *ray.origin() - self.center;
-        // Construct the coefficients in a quadratic equation a*x^2 + b*x + c.
+        // The quadratic a*t^2 + 2*half_b*t + c, with the factor 2 cancelled.
         let a = dot(ray.direction(), ray.direction());
-        let b = dot(&oc, ray.direction());
-        let c = dot(&oc, &oc) - self.radius.powi(2);
-        let discriminant = b * b - a * c;
-        if discriminant > 0. {
-            let t_candidate = (-b - discriminant.sqrt()) / a;
-            if t_candidate > t_min && t_candidate < t_max {
-                return Some(HitRecord {
-                    parameter: t_candidate,
-                    point_at_parameter: ray.point_at_parameter(t_candidate),
-                    normal: (ray.point_at_parameter(t_candidate) - self.center) / self.radius,
-                    material: self.material.clone(),
-                });
-            }
-            let t_candidate = (-b + discriminant.sqrt()) / a;
-            if t_candidate > t_min && t_candidate < t_max {
-                return Some(HitRecord {
-                    parameter: t_candidate,
-                    point_at_parameter: ray.point_at_parameter(t_candidate),
-                    normal: (ray.point_at_parameter(t_candidate) - self.center) / self.radius,
-                    material: self.material.clone(),
-                });
-            }
+        let half_b = dot(&oc, ray.direction());
+        let c = dot(&oc, &oc) - self.radius_squared;
+        let discriminant = half_b * half_b - a * c;
+        if discriminant <= 0. {
+            return None;
         }
+        let sqrt_discriminant = discriminant.sqrt();
+        Some([
+            (-half_b - sqrt_discriminant) / a,
+            (-half_b + sqrt_discriminant) / a,
+        ])
+    }
+}
+
+impl Hitable for Sphere {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.intersect_with(ray, t_min, t_max, self.material.as_ref())
+    }
+
+    fn intersect_any(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.intersect_any_within(ray, t_min, t_max)
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        self.intersect_all_with(ray, self.material.as_ref())
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.aabb())
+    }
+
+    fn collect_lights(&self, lights: &mut Vec<Light>) {
+        lights.extend(Light::sphere(
+            self.center,
+            self.radius,
+            self.material.as_ref(),
+        ));
+    }
+}
 
-        None
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+
+    #[test]
+    // Test the surface coordinates at the poles and on the equator.
+    fn test_sphere_uv() {
+        let material = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        let sphere = Sphere::new(Vec3(0., 0., -5.), 2., material);
+        // South and north pole.
+        let ray = Ray::new(Vec3(0., -5., -5.), Vec3(0., 1., 0.));
+        let hit = sphere.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.v, 0.);
+        assert!((0. ..=1.).contains(&hit.u));
+        let ray = Ray::new(Vec3(0., 5., -5.), Vec3(0., -1., 0.));
+        let hit = sphere.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.v, 1.);
+        assert!((0. ..=1.).contains(&hit.u));
+        // Points on the equator.
+        let ray = Ray::new(Vec3(5., 0., -5.), Vec3(-1., 0., 0.));
+        let hit = sphere.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.u - 0.5).abs() < 1e-6 && (hit.v - 0.5).abs() < 1e-6);
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., -1.));
+        let hit = sphere.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.u - 0.25).abs() < 1e-6 && (hit.v - 0.5).abs() < 1e-6);
+        let ray = Ray::new(Vec3(0., 0., -10.), Vec3(0., 0., 1.));
+        let hit = sphere.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.u - 0.75).abs() < 1e-6 && (hit.v - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    // Test that the surface coordinates stay in range across the seam.
+    fn test_sphere_uv_seam() {
+        for z in [-1e-7, -0., 0., 1e-7] {
+            let (u, v) = sphere_uv(&Vec3(-1., 0., z));
+            assert!((0. ..=1.).contains(&u));
+            assert!(!(1e-6..=1. - 1e-6).contains(&u));
+            assert_eq!(v, 0.5);
+        }
+    }
+
+    fn sphere(radius: f32) -> Sphere {
+        let material = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        Sphere::new(Vec3(0., 0., -5.), radius, material)
+    }
+
+    #[test]
+    // Test the nearest hit and the normals of a ray through the center.
+    fn test_sphere_hits() {
+        let sphere = sphere(2.);
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., -1.));
+        let hit = sphere.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.parameter, 3.);
+        assert_eq!(hit.point_at_parameter, Vec3(0., 0., -3.));
+        assert_eq!(hit.normal, Vec3(0., 0., 1.));
+        // The far side is hit when the near one is excluded by the range.
+        let hit = sphere.intersect(&ray, 4., f32::MAX).unwrap();
+        assert_eq!(hit.parameter, 7.);
+        assert_eq!(hit.normal, Vec3(0., 0., -1.));
+        assert!(sphere.intersect(&ray, 0.001, 2.5).is_none());
+        // A direction of any length gives the same point.
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., -4.));
+        let hit = sphere.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.parameter, 0.75);
+        assert_eq!(hit.point_at_parameter, Vec3(0., 0., -3.));
+    }
+
+    #[test]
+    // Test rays passing just inside, exactly along and just outside the surface.
+    fn test_sphere_grazing_rays() {
+        let sphere = sphere(2.);
+        let ray = Ray::new(Vec3(1.999, 0., 0.), Vec3(0., 0., -1.));
+        let hit = sphere.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.normal.length() - 1.).abs() < 1e-4);
+        assert!(hit.normal.x() > 0.99);
+        assert_eq!(sphere.intersect_all(&ray).len(), 2);
+        // A tangent ray touches the surface in a single point, which is not
+        // counted as a hit, like a ray that misses.
+        for x in [2., 2.001] {
+            let ray = Ray::new(Vec3(x, 0., 0.), Vec3(0., 0., -1.));
+            assert!(sphere.intersect(&ray, 0.001, f32::MAX).is_none());
+            assert!(sphere.intersect_all(&ray).is_empty());
+        }
+    }
+
+    #[test]
+    // Test rays starting inside the sphere, which only hit the far side.
+    fn test_sphere_ray_from_inside() {
+        let sphere = sphere(2.);
+        let ray = Ray::new(Vec3(0., 0., -5.), Vec3(0., 1., 0.));
+        let hit = sphere.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.parameter, 2.);
+        assert_eq!(hit.normal, Vec3(0., 1., 0.));
+        let hits = sphere.intersect_all(&ray);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].parameter, -2.);
+        assert_eq!(hits[1].parameter, 2.);
+    }
+
+    #[test]
+    // Test that a negative radius flips the normals but not the hits or coordinates.
+    fn test_sphere_negative_radius() {
+        let outer = sphere(2.);
+        let inner = sphere(-2.);
+        for ray in [
+            Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., -1.)),
+            Ray::new(Vec3(0.5, 0.3, -5.), Vec3(0.2, 1., 0.1)),
+        ] {
+            let a = outer.intersect(&ray, 0.001, f32::MAX).unwrap();
+            let b = inner.intersect(&ray, 0.001, f32::MAX).unwrap();
+            assert_eq!(a.parameter, b.parameter);
+            assert_eq!(a.normal, -b.normal);
+            assert_eq!((a.u, a.v), (b.u, b.v));
+        }
     }
 }
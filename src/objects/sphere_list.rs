@@ -0,0 +1,337 @@
+use crate::aabb::surrounding_box;
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::materials::MaterialExt;
+use crate::objects::sphere::Sphere;
+use crate::objects::Hitable;
+use crate::ray::Ray;
+use crate::ray::PACKET_SIZE;
+use crate::vec3::Vec3;
+use std::collections::HashMap;
+use std::iter::FromIterator;
+use std::sync::Arc;
+
+/// Many spheres stored as a structure of arrays.
+///
+/// The coordinates of the centers and the radii are kept in separate
+/// contiguous arrays, which a ray is tested against in a tight loop that
+/// the compiler can vectorize. Unlike a `HitableList` of boxed spheres, no
+/// pointer has to be followed per sphere, and the `HitRecord` is only built
+/// for the closest sphere hit. The materials are stored once and referred
+/// to by index, such that spheres sharing a material share the pointer.
+///
+/// The hits are exactly those of the corresponding `Sphere`s. A
+/// `SphereList` is hitable itself, and can be an element of a
+/// `HitableList` or any other structure.
+#[derive(Default)]
+pub struct SphereList {
+    x: Vec<f32>,
+    y: Vec<f32>,
+    z: Vec<f32>,
+    radius: Vec<f32>,
+    radius_squared: Vec<f32>,
+    material_index: Vec<usize>,
+    materials: Vec<Arc<dyn MaterialExt>>,
+}
+
+impl SphereList {
+    /// Create a `SphereList` from the center, radius and material of each sphere.
+    ///
+    /// ```
+    /// use raytracer::objects::sphere_list::SphereList;
+    /// use raytracer::objects::Hitable;
+    /// use raytracer::ray::Ray;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::{Lambertian, MaterialExt};
+    /// use std::sync::Arc;
+    /// let material: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let spheres = SphereList::new((0..100).map(|i| {
+    ///     let center = Vec3((i % 10) as f32, 0.2, -((i / 10) as f32) - 2.);
+    ///     (center, 0.2, material.clone())
+    /// }));
+    /// assert_eq!(spheres.len(), 100);
+    /// let ray = Ray::new(Vec3(3., 0.2, 0.), Vec3(0., 0., -1.));
+    /// let hit = spheres.intersect(&ray, 0.001, f32::MAX).unwrap();
+    /// assert!((hit.parameter - 1.8).abs() < 1e-5);
+    /// ```
+    pub fn new(spheres: impl IntoIterator<Item = (Vec3, f32, Arc<dyn MaterialExt>)>) -> SphereList {
+        let mut list = SphereList::default();
+        // Index the materials by their address, to store each one once.
+        let mut material_indices = HashMap::new();
+        for (center, radius, material) in spheres {
+            let address = Arc::as_ptr(&material) as *const () as usize;
+            let index = *material_indices.entry(address).or_insert_with(|| {
+                list.materials.push(material);
+                list.materials.len() - 1
+            });
+            list.x.push(center.x());
+            list.y.push(center.y());
+            list.z.push(center.z());
+            list.radius.push(radius);
+            list.radius_squared.push(radius * radius);
+            list.material_index.push(index);
+        }
+        list
+    }
+
+    /// Return the number of spheres in a `SphereList`.
+    pub fn len(&self) -> usize {
+        self.radius.len()
+    }
+
+    /// Check whether a `SphereList` holds no spheres.
+    pub fn is_empty(&self) -> bool {
+        self.radius.is_empty()
+    }
+
+    /// Return the sphere with the given `index`, in the order the spheres were given.
+    pub fn sphere(&self, index: usize) -> Sphere {
+        Sphere::new(
+            Vec3(self.x[index], self.y[index], self.z[index]),
+            self.radius[index],
+            self.materials[self.material_index[index]].clone(),
+        )
+    }
+
+    // Return the index of the sphere hit first between `t_min` and `t_max`,
+    // with the parameter of the hit, or of any sphere hit if `any`.
+    fn closest(&self, ray: &Ray, t_min: f32, t_max: f32, any: bool) -> Option<(usize, f32)> {
+        let (ox, oy, oz) = (ray.origin().x(), ray.origin().y(), ray.origin().z());
+        let direction = ray.direction();
+        let (dx, dy, dz) = (direction.x(), direction.y(), direction.z());
+        let a = dx * dx + dy * dy + dz * dz;
+        let mut closest = None;
+        let mut closest_so_far = t_max;
+        // The same arithmetic as `Sphere`, such that the same roots are found.
+        for i in 0..self.len() {
+            let (ocx, ocy, ocz) = (ox - self.x[i], oy - self.y[i], oz - self.z[i]);
+            let half_b = ocx * dx + ocy * dy + ocz * dz;
+            let c = (ocx * ocx + ocy * ocy + ocz * ocz) - self.radius_squared[i];
+            let discriminant = half_b * half_b - a * c;
+            if discriminant <= 0. {
+                continue;
+            }
+            let sqrt_discriminant = discriminant.sqrt();
+            for t in [
+                (-half_b - sqrt_discriminant) / a,
+                (-half_b + sqrt_discriminant) / a,
+            ] {
+                if t > t_min && t < closest_so_far {
+                    if any {
+                        return Some((i, t));
+                    }
+                    closest_so_far = t;
+                    closest = Some((i, t));
+                    break;
+                }
+            }
+        }
+        closest
+    }
+
+    // Return the index of the sphere hit first by each of the `rays`, with
+    // the parameter of the hit. Every sphere is tested against all rays
+    // before the next one is loaded.
+    fn closest_packet(
+        &self,
+        rays: &[Ray; PACKET_SIZE],
+        t_min: f32,
+        t_max: f32,
+    ) -> [Option<(usize, f32)>; PACKET_SIZE] {
+        let ox = rays.each_ref().map(|ray| ray.origin().x());
+        let oy = rays.each_ref().map(|ray| ray.origin().y());
+        let oz = rays.each_ref().map(|ray| ray.origin().z());
+        let dx = rays.each_ref().map(|ray| ray.direction().x());
+        let dy = rays.each_ref().map(|ray| ray.direction().y());
+        let dz = rays.each_ref().map(|ray| ray.direction().z());
+        let a: [f32; PACKET_SIZE] =
+            std::array::from_fn(|j| dx[j] * dx[j] + dy[j] * dy[j] + dz[j] * dz[j]);
+        let mut closest = [None; PACKET_SIZE];
+        let mut closest_so_far = [t_max; PACKET_SIZE];
+        // The same arithmetic as `closest` for each ray.
+        for i in 0..self.len() {
+            for j in 0..PACKET_SIZE {
+                let (ocx, ocy, ocz) = (ox[j] - self.x[i], oy[j] - self.y[i], oz[j] - self.z[i]);
+                let half_b = ocx * dx[j] + ocy * dy[j] + ocz * dz[j];
+                let c = (ocx * ocx + ocy * ocy + ocz * ocz) - self.radius_squared[i];
+                let discriminant = half_b * half_b - a[j] * c;
+                if discriminant <= 0. {
+                    continue;
+                }
+                let sqrt_discriminant = discriminant.sqrt();
+                for t in [
+                    (-half_b - sqrt_discriminant) / a[j],
+                    (-half_b + sqrt_discriminant) / a[j],
+                ] {
+                    if t > t_min && t < closest_so_far[j] {
+                        closest_so_far[j] = t;
+                        closest[j] = Some((i, t));
+                        break;
+                    }
+                }
+            }
+        }
+        closest
+    }
+}
+
+impl FromIterator<(Vec3, f32, Arc<dyn MaterialExt>)> for SphereList {
+    fn from_iter<I: IntoIterator<Item = (Vec3, f32, Arc<dyn MaterialExt>)>>(
+        spheres: I,
+    ) -> SphereList {
+        SphereList::new(spheres)
+    }
+}
+
+impl Hitable for SphereList {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let (index, t) = self.closest(ray, t_min, t_max, false)?;
+        let material = self.materials[self.material_index[index]].as_ref();
+        Some(self.sphere(index).hit_record(ray, t, material))
+    }
+
+    fn intersect_any(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.closest(ray, t_min, t_max, true).is_some()
+    }
+
+    fn intersect_packet(
+        &self,
+        rays: &[Ray; PACKET_SIZE],
+        t_min: f32,
+        t_max: f32,
+    ) -> [Option<HitRecord<'_>>; PACKET_SIZE] {
+        let closest = self.closest_packet(rays, t_min, t_max);
+        std::array::from_fn(|j| {
+            let (index, t) = closest[j]?;
+            let material = self.materials[self.material_index[index]].as_ref();
+            Some(self.sphere(index).hit_record(&rays[j], t, material))
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        (0..self.len())
+            .map(|i| {
+                let r = self.radius[i].abs();
+                let center = Vec3(self.x[i], self.y[i], self.z[i]);
+                Aabb::new(center - Vec3(r, r, r), center + Vec3(r, r, r))
+            })
+            .reduce(|a, b| surrounding_box(&a, &b))
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Dielectric;
+    use crate::materials::Lambertian;
+    use crate::objects::HitableList;
+    use rand::prelude::*;
+    use rand::rngs::StdRng;
+
+    #[test]
+    // Test that the hits are exactly those of a list of boxed spheres.
+    fn test_sphere_list_matches_boxed_spheres() {
+        let mut rng = StdRng::seed_from_u64(9);
+        let glass: Arc<dyn MaterialExt> = Arc::new(Dielectric::new(1.5));
+        let spheres: Vec<(Vec3, f32, Arc<dyn MaterialExt>)> = (0..500)
+            .map(|i| {
+                let center = Vec3(
+                    rng.gen_range(-10.0..10.0),
+                    rng.gen_range(-10.0..10.0),
+                    rng.gen_range(-30.0..-5.0),
+                );
+                // Some shared materials, some hollow spheres of negative radius.
+                let material: Arc<dyn MaterialExt> = if i % 4 == 0 {
+                    glass.clone()
+                } else {
+                    Arc::new(Lambertian::new(Vec3(rng.gen(), rng.gen(), rng.gen())))
+                };
+                let radius = rng.gen_range(0.1..1.0) * if i % 7 == 0 { -1. } else { 1. };
+                (center, radius, material)
+            })
+            .collect();
+        let boxed = HitableList::new(
+            spheres
+                .iter()
+                .map(|(center, radius, material)| {
+                    Box::new(Sphere::new(*center, *radius, material.clone())) as Box<dyn Hitable>
+                })
+                .collect(),
+        );
+        let list: SphereList = spheres.into_iter().collect();
+        assert_eq!(list.len(), 500);
+        assert_eq!(list.materials.len(), 376);
+        assert_eq!(list.bounding_box(), boxed.bounding_box());
+
+        let mut hits = 0;
+        let mut packet = Vec::new();
+        for _ in 0..2000 {
+            let origin = Vec3(
+                rng.gen_range(-5.0..5.0),
+                rng.gen_range(-5.0..5.0),
+                rng.gen_range(-5.0..5.0),
+            );
+            let direction = Vec3(
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-2.0..0.0),
+            );
+            let ray = Ray::new(origin, direction);
+            packet.push(Ray::new(origin, direction));
+            if packet.len() == PACKET_SIZE {
+                let mut drain = packet.drain(..);
+                let rays: [Ray; PACKET_SIZE] = std::array::from_fn(|_| drain.next().unwrap());
+                let packet_hits = list.intersect_packet(&rays, 0.001, 20.);
+                for (ray, hit) in rays.iter().zip(packet_hits.iter()) {
+                    let expected = boxed.intersect(ray, 0.001, 20.);
+                    assert_eq!(
+                        hit.as_ref().map(|h| (h.parameter, h.normal, h.u, h.v)),
+                        expected.map(|h| (h.parameter, h.normal, h.u, h.v))
+                    );
+                }
+            }
+            let t_max = rng.gen_range(1.0..50.0);
+            let expected = boxed.intersect(&ray, 0.001, t_max);
+            let hit = list.intersect(&ray, 0.001, t_max);
+            assert_eq!(hit.is_some(), expected.is_some());
+            assert_eq!(list.intersect_any(&ray, 0.001, t_max), expected.is_some());
+            if let (Some(hit), Some(expected)) = (hit, expected) {
+                assert_eq!(hit.parameter, expected.parameter);
+                assert_eq!(hit.point_at_parameter, expected.point_at_parameter);
+                assert_eq!(hit.normal, expected.normal);
+                assert_eq!((hit.u, hit.v), (expected.u, expected.v));
+                assert!(std::ptr::addr_eq(hit.material, expected.material));
+                hits += 1;
+            }
+        }
+        assert!(hits > 200);
+    }
+
+    #[test]
+    // Test a sphere list as an element of a hitable list.
+    fn test_sphere_list_in_list() {
+        let material: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        let spheres = SphereList::new(vec![
+            (Vec3(0., 0., -5.), 1., material.clone()),
+            (Vec3(0., 0., -10.), 1., material.clone()),
+        ]);
+        let world = HitableList::new(vec![
+            Box::new(spheres),
+            Box::new(Sphere::new(Vec3(0., 0., -3.), 0.5, material)),
+        ]);
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., -1.));
+        assert_eq!(
+            world.intersect(&ray, 0.001, f32::MAX).unwrap().parameter,
+            2.5
+        );
+        assert_eq!(
+            world.intersect(&ray, 0.001, 2.).map(|hit| hit.parameter),
+            None
+        );
+        assert!(SphereList::default().bounding_box().is_none());
+    }
+}
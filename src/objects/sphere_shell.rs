@@ -0,0 +1,186 @@
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::materials::MaterialExt;
+use crate::objects::sphere::Sphere;
+use crate::objects::Hitable;
+use crate::objects::ObjectError;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+/// A hollow sphere, i.e. the matter between two concentric spheres.
+///
+/// It is characterized by:
+/// - The coordinates of its center.
+/// - The outer radius.
+/// - The inner radius of the cavity.
+/// - A pointer to the material that it is made of.
+///
+/// The normals on both boundaries point out of the material: away from the
+/// center on the outer sphere and towards it on the inner one. Materials
+/// like `Dielectric` therefore see a ray entering the cavity as leaving the
+/// material, which makes a glass shell refract like a bubble.
+pub struct SphereShell {
+    outer: Sphere,
+    inner: Sphere,
+}
+
+impl SphereShell {
+    /// Create a `SphereShell` by specifying its `center`, radii and `Material`.
+    ///
+    /// An error is returned unless `0 < inner_radius < outer_radius`.
+    ///
+    /// ```
+    /// use raytracer::objects::sphere_shell::SphereShell;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Dielectric;
+    /// use std::sync::Arc;
+    /// let glass = Arc::new(Dielectric::new(1.5));
+    /// let bubble = SphereShell::new(Vec3(-4., 1., 0.), 0.5, 0.45, glass.clone());
+    /// assert!(bubble.is_ok());
+    /// let invalid = SphereShell::new(Vec3(-4., 1., 0.), 0.5, 0.5, glass);
+    /// assert!(invalid.is_err());
+    /// ```
+    pub fn new(
+        center: Vec3,
+        outer_radius: f32,
+        inner_radius: f32,
+        material: Arc<dyn MaterialExt>,
+    ) -> Result<SphereShell, ObjectError> {
+        // Written such that NaN radii are rejected as well.
+        if !(inner_radius > 0. && inner_radius < outer_radius) {
+            return Err(ObjectError::InvalidShell {
+                inner_radius,
+                outer_radius,
+            });
+        }
+        Ok(SphereShell {
+            outer: Sphere::new(center, outer_radius, material.clone()),
+            // The negative radius turns the normals towards the center.
+            inner: Sphere::new(center, -inner_radius, material),
+        })
+    }
+
+    /// Access the center of a `SphereShell`.
+    pub fn center(&self) -> &Vec3 {
+        self.outer.center()
+    }
+
+    /// Access the outer radius of a `SphereShell`.
+    pub fn outer_radius(&self) -> f32 {
+        *self.outer.radius()
+    }
+
+    /// Access the radius of the cavity of a `SphereShell`.
+    pub fn inner_radius(&self) -> f32 {
+        -*self.inner.radius()
+    }
+
+    /// Access the `material` a `SphereShell` is made of.
+    pub fn material(&self) -> Arc<dyn MaterialExt> {
+        self.outer.material()
+    }
+}
+
+impl Hitable for SphereShell {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let outer = self.outer.intersect(ray, t_min, t_max);
+        let closest_so_far = outer.as_ref().map_or(t_max, |hit| hit.parameter);
+        self.inner.intersect(ray, t_min, closest_so_far).or(outer)
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        let outer = self.outer.intersect_all(ray);
+        let inner = self.inner.intersect_all(ray);
+        // A line through the cavity crosses both spheres; the crossings of
+        // the inner one lie between those of the outer one.
+        match (outer.len(), inner.len()) {
+            (2, 2) => {
+                let mut hits = outer;
+                let exit = hits.pop().unwrap();
+                hits.extend(inner);
+                hits.push(exit);
+                hits
+            }
+            _ => outer,
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.outer.bounding_box()
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Dielectric;
+    use crate::vec3::dot;
+
+    fn shell() -> SphereShell {
+        let glass = Arc::new(Dielectric::new(1.5));
+        SphereShell::new(Vec3(0., 0., -5.), 2., 1., glass).unwrap()
+    }
+
+    #[test]
+    // Test the four boundary crossings of a ray through the center.
+    fn test_shell_crossings() {
+        let shell = shell();
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., -1.));
+        let mut t_min = 0.001;
+        let mut crossings = Vec::new();
+        while let Some(hit) = shell.intersect(&ray, t_min, f32::MAX) {
+            t_min = hit.parameter + 0.001;
+            crossings.push(hit);
+        }
+        let parameters: Vec<f32> = crossings.iter().map(|hit| hit.parameter).collect();
+        assert_eq!(parameters, vec![3., 4., 6., 7.]);
+        // Entering and leaving the glass alternate, as seen by a `Dielectric`.
+        let entering: Vec<bool> = crossings
+            .iter()
+            .map(|hit| dot(ray.direction(), &hit.normal) < 0.)
+            .collect();
+        assert_eq!(entering, vec![true, false, true, false]);
+        assert_eq!(crossings[1].normal, Vec3(0., 0., -1.));
+        assert_eq!(crossings[2].normal, Vec3(0., 0., 1.));
+
+        let all: Vec<f32> = shell
+            .intersect_all(&ray)
+            .iter()
+            .map(|hit| hit.parameter)
+            .collect();
+        assert_eq!(all, parameters);
+    }
+
+    #[test]
+    // Test rays that only cross the outer sphere or start inside the glass.
+    fn test_shell_partial_crossings() {
+        let shell = shell();
+        // Passing beside the cavity.
+        let ray = Ray::new(Vec3(1.5, 0., 0.), Vec3(0., 0., -1.));
+        assert_eq!(shell.intersect_all(&ray).len(), 2);
+        let hit = shell.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!(dot(ray.direction(), &hit.normal) < 0.);
+        // From within the glass, the cavity is reached first.
+        let ray = Ray::new(Vec3(0., 0., -3.5), Vec3(0., 0., -1.));
+        let hit = shell.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.parameter, 0.5);
+        assert!(dot(ray.direction(), &hit.normal) > 0.);
+    }
+
+    #[test]
+    // Test that invalid radii are rejected.
+    fn test_shell_invalid_radii() {
+        let glass = Arc::new(Dielectric::new(1.5));
+        for &(outer, inner) in &[(1., 2.), (1., 1.), (1., 0.), (1., -0.5), (1., f32::NAN)] {
+            let shell = SphereShell::new(Vec3(0., 0., 0.), outer, inner, glass.clone());
+            assert!(shell.is_err());
+        }
+        let shell = SphereShell::new(Vec3(0., 0., 0.), 1., 0.5, glass).unwrap();
+        assert_eq!((shell.outer_radius(), shell.inner_radius()), (1., 0.5));
+    }
+}
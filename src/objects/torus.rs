@@ -0,0 +1,391 @@
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::materials::MaterialExt;
+use crate::objects::Hitable;
+use crate::ray::Ray;
+use crate::vec3::cross;
+use crate::vec3::dot;
+use crate::vec3::unit_vector;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+/// A torus in three-dimensional space.
+///
+/// It is characterized by:
+/// - The coordinates of its center.
+/// - The axis of rotational symmetry.
+/// - The `major_radius`, i.e. the distance from the center to the center of the tube.
+/// - The `minor_radius`, i.e. the radius of the tube.
+/// - A pointer to the material that it is made of.
+///
+/// The intersection with a ray requires solving a quartic equation. This is done
+/// in double precision by isolating the roots between the critical points of the
+/// polynomial and refining them by bisection, which does not lose roots for rays
+/// that are almost tangent to the tube.
+pub struct Torus {
+    center: Vec3,
+    axis: Vec3,
+    major_radius: f32,
+    minor_radius: f32,
+    // Orthonormal basis of the local frame in which the axis is the z-axis.
+    u: Vec3,
+    v: Vec3,
+    material: Arc<dyn MaterialExt>,
+}
+
+impl Torus {
+    /// Create a `Torus` by specifying its `center`, `axis`, radii and `Material`.
+    ///
+    /// The `axis` does not need to be normalized.
+    ///
+    /// ```
+    /// use raytracer::objects::torus::Torus;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let torus = Torus::new(Vec3(0., 1., 0.), Vec3(0., 1., 0.), 1., 0.25, material);
+    /// ```
+    pub fn new(
+        center: Vec3,
+        axis: Vec3,
+        major_radius: f32,
+        minor_radius: f32,
+        material: Arc<dyn MaterialExt>,
+    ) -> Torus {
+        let axis = unit_vector(&axis);
+        // Pick the coordinate axis least aligned with `axis` to build the frame.
+        let helper = if axis.x().abs() < 0.9 {
+            Vec3(1., 0., 0.)
+        } else {
+            Vec3(0., 1., 0.)
+        };
+        let u = unit_vector(&cross(&helper, &axis));
+        let v = cross(&axis, &u);
+        Torus {
+            center,
+            axis,
+            major_radius,
+            minor_radius,
+            u,
+            v,
+            material,
+        }
+    }
+
+    /// Access the center of a `Torus`.
+    ///
+    /// ```
+    /// # use raytracer::objects::torus::Torus;
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::materials::Lambertian;
+    /// # use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let torus = Torus::new(Vec3(0., 1., 0.), Vec3(0., 1., 0.), 1., 0.25, material);
+    /// assert_eq!(torus.center(), &Vec3(0., 1., 0.));
+    /// ```
+    pub fn center(&self) -> &Vec3 {
+        &self.center
+    }
+
+    /// Access the normalized axis of a `Torus`.
+    ///
+    /// ```
+    /// # use raytracer::objects::torus::Torus;
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::materials::Lambertian;
+    /// # use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let torus = Torus::new(Vec3(0., 1., 0.), Vec3(0., 3., 0.), 1., 0.25, material);
+    /// assert_eq!(torus.axis(), &Vec3(0., 1., 0.));
+    /// ```
+    pub fn axis(&self) -> &Vec3 {
+        &self.axis
+    }
+
+    /// Access the major radius of a `Torus`.
+    pub fn major_radius(&self) -> f32 {
+        self.major_radius
+    }
+
+    /// Access the minor radius of a `Torus`.
+    pub fn minor_radius(&self) -> f32 {
+        self.minor_radius
+    }
+
+    /// Access the `material` a `Torus` is made of.
+    pub fn material(&self) -> Arc<dyn MaterialExt> {
+        Arc::clone(&self.material)
+    }
+
+    // Express a vector in the local frame of the torus.
+    fn to_local(&self, vec: &Vec3) -> [f64; 3] {
+        [
+            dot(vec, &self.u) as f64,
+            dot(vec, &self.v) as f64,
+            dot(vec, &self.axis) as f64,
+        ]
+    }
+}
+
+/// Evaluate the polynomial with coefficients `coeffs` (lowest order first) at `t`.
+fn evaluate(coeffs: &[f64], t: f64) -> f64 {
+    coeffs.iter().rev().fold(0., |acc, c| acc * t + c)
+}
+
+/// Find the real roots of a polynomial within `[lo, hi]` in ascending order.
+///
+/// The coefficients are given with the lowest order first. The roots of the
+/// derivative split the interval into pieces on which the polynomial is monotonic,
+/// so each piece contains at most one root which is then found by bisection.
+/// Extrema that touch zero (double roots) are reported as roots as well.
+fn polynomial_roots(coeffs: &[f64], lo: f64, hi: f64) -> Vec<f64> {
+    let degree = coeffs.len() - 1;
+    if degree == 0 {
+        return vec![];
+    }
+    if degree == 1 {
+        if coeffs[1] == 0. {
+            return vec![];
+        }
+        let root = -coeffs[0] / coeffs[1];
+        return if root >= lo && root <= hi {
+            vec![root]
+        } else {
+            vec![]
+        };
+    }
+
+    let derivative = coeffs
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, c)| i as f64 * c)
+        .collect::<Vec<_>>();
+
+    let mut points = vec![lo];
+    points.extend(polynomial_roots(&derivative, lo, hi));
+    points.push(hi);
+
+    // Scale used to decide whether an extremum touches zero.
+    let scale = coeffs.iter().fold(0f64, |acc, c| acc.max(c.abs()));
+    let touch_tolerance = 1e-10 * scale.max(1.);
+
+    let mut roots: Vec<f64> = vec![];
+    for window in points.windows(2) {
+        let (mut a, mut b) = (window[0], window[1]);
+        let (fa, fb) = (evaluate(coeffs, a), evaluate(coeffs, b));
+        if fa == 0. {
+            roots.push(a);
+            continue;
+        }
+        if fa.signum() == fb.signum() {
+            continue;
+        }
+        for _ in 0..100 {
+            let mid = 0.5 * (a + b);
+            if mid <= a || mid >= b {
+                break;
+            }
+            if evaluate(coeffs, mid).signum() == fa.signum() {
+                a = mid;
+            } else {
+                b = mid;
+            }
+        }
+        roots.push(0.5 * (a + b));
+    }
+
+    // Interior extrema that graze zero are (numerically) double roots.
+    for &critical in &points[1..points.len() - 1] {
+        if evaluate(coeffs, critical).abs() <= touch_tolerance {
+            roots.push(critical);
+        }
+    }
+
+    roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    roots.dedup_by(|a, b| (*a - *b).abs() <= 1e-9 * (1. + b.abs()));
+    roots
+}
+
+impl Hitable for Torus {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let origin = self.to_local(&(*ray.origin() - self.center));
+        let direction = self.to_local(ray.direction());
+        let length = direction.iter().map(|d| d * d).sum::<f64>().sqrt();
+        if length == 0. {
+            return None;
+        }
+        let d = [
+            direction[0] / length,
+            direction[1] / length,
+            direction[2] / length,
+        ];
+        let o = origin;
+
+        // Solve for the distance `s` along the normalized direction.
+        let big_r2 = (self.major_radius as f64).powi(2);
+        let small_r2 = (self.minor_radius as f64).powi(2);
+        let f = o[0] * d[0] + o[1] * d[1] + o[2] * d[2];
+        let e = o[0] * o[0] + o[1] * o[1] + o[2] * o[2] - big_r2 - small_r2;
+        let coeffs = [
+            e * e - 4. * big_r2 * (small_r2 - o[2] * o[2]),
+            4. * f * e + 8. * big_r2 * o[2] * d[2],
+            2. * e + 4. * f * f + 4. * big_r2 * d[2] * d[2],
+            4. * f,
+            1.,
+        ];
+
+        // All roots of a monic polynomial lie within the Cauchy bound.
+        let bound = 1. + coeffs[..4].iter().fold(0f64, |acc, c| acc.max(c.abs()));
+        let lo = (t_min as f64 * length).max(-bound);
+        let hi = (t_max as f64 * length).min(bound);
+        if lo >= hi {
+            return None;
+        }
+
+        let t = polynomial_roots(&coeffs, lo, hi)
+            .into_iter()
+            .map(|s| (s / length) as f32)
+            .find(|&t| t > t_min && t < t_max)?;
+
+        let point = ray.point_at_parameter(t);
+        // The normal is the gradient of the implicit function
+        // (|p|^2 - R^2 - r^2)^2 + 4 R^2 p_z^2 - 4 R^2 r^2.
+        let local = *ray.origin() - self.center + t * *ray.direction();
+        let z = dot(&local, &self.axis);
+        let k = local.squared_length() - self.major_radius.powi(2) - self.minor_radius.powi(2);
+        let gradient = 4. * k * local + 8. * self.major_radius.powi(2) * z * self.axis;
+        let normal = unit_vector(&gradient);
+        Some(HitRecord {
+            parameter: t,
+            point_at_parameter: point,
+            normal,
+            u: 0.,
+            v: 0.,
+            tangent: Vec3(0., 0., 0.),
+            bitangent: Vec3(0., 0., 0.),
+            front_face: dot(ray.direction(), &normal) < 0.,
+            material: self.material.as_ref(),
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // The circle through the center of the tube, widened by the tube.
+        let extent = |a: f32| self.major_radius * (1. - a * a).max(0.).sqrt() + self.minor_radius;
+        let extent = Vec3(
+            extent(self.axis.x()),
+            extent(self.axis.y()),
+            extent(self.axis.z()),
+        );
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+
+    fn torus() -> Torus {
+        Torus::new(
+            Vec3(0., 0., 0.),
+            Vec3(0., 1., 0.),
+            1.,
+            0.25,
+            Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))),
+        )
+    }
+
+    #[test]
+    // Test the polynomial root finder on a quartic with known roots.
+    fn test_polynomial_roots() {
+        // (t - 1)(t - 2)(t - 3)(t - 4)
+        let coeffs = [24., -50., 35., -10., 1.];
+        let roots = polynomial_roots(&coeffs, -10., 10.);
+        assert_eq!(roots.len(), 4);
+        for (root, expected) in roots.iter().zip(&[1., 2., 3., 4.]) {
+            assert!((root - expected).abs() < 1e-9);
+        }
+        // (t - 1)^2 (t + 1)^2 only touches zero.
+        let coeffs = [1., 0., -2., 0., 1.];
+        let roots = polynomial_roots(&coeffs, -10., 10.);
+        assert_eq!(roots.len(), 2);
+    }
+
+    #[test]
+    // Test a ray along the x-axis through the tube hitting the outside first.
+    fn test_torus_hit() {
+        let torus = torus();
+        let ray = Ray::new(Vec3(-5., 0., 0.), Vec3(1., 0., 0.));
+        let hit = torus.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.parameter - 3.75).abs() < 1e-4);
+        assert!((hit.normal - Vec3(-1., 0., 0.)).length() < 1e-4);
+        // Starting inside the hole, the inner side of the tube is hit.
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(1., 0., 0.));
+        let hit = torus.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.parameter - 0.75).abs() < 1e-4);
+        assert!((hit.normal - Vec3(-1., 0., 0.)).length() < 1e-4);
+    }
+
+    #[test]
+    // Test that a ray through the hole along the axis misses.
+    fn test_torus_miss_through_hole() {
+        let torus = torus();
+        let ray = Ray::new(Vec3(0., 5., 0.), Vec3(0., -1., 0.));
+        assert!(torus.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    // Test that the hit respects the parameter range.
+    fn test_torus_parameter_range() {
+        let torus = torus();
+        let ray = Ray::new(Vec3(-5., 0., 0.), Vec3(1., 0., 0.));
+        let hit = torus.intersect(&ray, 4., f32::MAX).unwrap();
+        assert!((hit.parameter - 4.25).abs() < 1e-4);
+        assert!(torus.intersect(&ray, 0.001, 3.).is_none());
+    }
+
+    #[test]
+    // Regression test: rays grazing the tube must neither miss nor produce spurious hits.
+    fn test_torus_grazing_grid() {
+        let torus = torus();
+        let mut hits = 0;
+        let mut misses = 0;
+        // Rays parallel to the x-axis, grazing the top and bottom of the tube.
+        for i in 1..=20 {
+            let offset = i as f32 * 1e-3;
+            for &height in &[0.25 - offset, -0.25 + offset] {
+                let ray = Ray::new(Vec3(-5., height, 0.), Vec3(1., 0., 0.));
+                if torus.intersect(&ray, 0.001, f32::MAX).is_some() {
+                    hits += 1;
+                }
+            }
+            for &height in &[0.25 + offset, -0.25 - offset] {
+                let ray = Ray::new(Vec3(-5., height, 0.), Vec3(1., 0., 0.));
+                if torus.intersect(&ray, 0.001, f32::MAX).is_none() {
+                    misses += 1;
+                }
+            }
+            // Rays in the equatorial plane grazing the outer rim.
+            for &depth in &[1.25 - offset, -1.25 + offset] {
+                let ray = Ray::new(Vec3(-5., 0., depth), Vec3(1., 0., 0.));
+                if torus.intersect(&ray, 0.001, f32::MAX).is_some() {
+                    hits += 1;
+                }
+            }
+            for &depth in &[1.25 + offset, -1.25 - offset] {
+                let ray = Ray::new(Vec3(-5., 0., depth), Vec3(1., 0., 0.));
+                if torus.intersect(&ray, 0.001, f32::MAX).is_none() {
+                    misses += 1;
+                }
+            }
+        }
+        assert_eq!(hits, 80);
+        assert_eq!(misses, 80);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::objects::Hitable;
+use crate::objects::ObjectError;
+use crate::ray::Ray;
+use crate::transform::Mat4;
+use crate::vec3::unit_vector;
+
+/// A hitable object under an arbitrary affine transformation.
+///
+/// Incoming rays are mapped into the frame of the object by the inverse
+/// transformation. The direction of the mapped ray is not renormalized, such
+/// that the ray parameter `t` of a hit is the same in both frames.
+pub struct TransformedHitable {
+    object: Box<dyn Hitable>,
+    matrix: Mat4,
+    inverse: Mat4,
+    // The inverse transpose transforms the normals of the object.
+    normal_matrix: Mat4,
+}
+
+impl TransformedHitable {
+    /// Transform the hitable `object` by the affine transformation `matrix`.
+    ///
+    /// An error is returned if the matrix cannot be inverted.
+    ///
+    /// ```
+    /// use raytracer::objects::transformed::TransformedHitable;
+    /// use raytracer::objects::sphere::Sphere;
+    /// use raytracer::transform::Mat4;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let sphere = Sphere::new(Vec3(0., 0., 0.), 1., material);
+    /// let matrix = Mat4::translation(Vec3(0., 1., -3.)) * Mat4::scaling(Vec3(2., 1., 1.));
+    /// let stretched = TransformedHitable::new(Box::new(sphere), matrix);
+    /// assert!(stretched.is_ok());
+    /// ```
+    pub fn new(object: Box<dyn Hitable>, matrix: Mat4) -> Result<TransformedHitable, ObjectError> {
+        let inverse = matrix.inverse().ok_or(ObjectError::SingularTransform)?;
+        Ok(TransformedHitable {
+            object,
+            matrix,
+            inverse,
+            normal_matrix: inverse.transpose(),
+        })
+    }
+
+    /// Access the transformation matrix.
+    pub fn matrix(&self) -> &Mat4 {
+        &self.matrix
+    }
+
+    /// Access the inverse of the transformation matrix.
+    pub fn inverse(&self) -> &Mat4 {
+        &self.inverse
+    }
+
+    fn local_ray(&self, ray: &Ray) -> Ray {
+        Ray::new_with_time(
+            self.inverse.transform_point(ray.origin()),
+            self.inverse.transform_vector(ray.direction()),
+            ray.time(),
+        )
+        .with_medium_seed(ray.medium_seed())
+    }
+
+    fn world_hit<'a>(&self, mut hit: HitRecord<'a>) -> HitRecord<'a> {
+        hit.point_at_parameter = self.matrix.transform_point(&hit.point_at_parameter);
+        hit.normal = unit_vector(&self.normal_matrix.transform_vector(&hit.normal));
+        hit.map_tangents(|v| self.matrix.transform_vector(v));
+        hit
+    }
+}
+
+impl Hitable for TransformedHitable {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.object
+            .intersect(&self.local_ray(ray), t_min, t_max)
+            .map(|hit| self.world_hit(hit))
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        self.object
+            .intersect_all(&self.local_ray(ray))
+            .into_iter()
+            .map(|hit| self.world_hit(hit))
+            .collect()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.object.bounding_box()?.transformed(&self.matrix))
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+    use crate::materials::MaterialExt;
+    use crate::objects::ellipsoid::Ellipsoid;
+    use crate::objects::sphere::Sphere;
+    use crate::vec3::Vec3;
+    use std::sync::Arc;
+
+    fn material() -> Arc<dyn MaterialExt> {
+        Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)))
+    }
+
+    fn unit_sphere() -> Box<dyn Hitable> {
+        Box::new(Sphere::new(Vec3(0., 0., 0.), 1., material()))
+    }
+
+    #[test]
+    // Test a non-uniformly scaled sphere against the ellipsoid primitive.
+    fn test_non_uniform_scale_normals() {
+        let semi_axes = Vec3(3., 1., 1.);
+        let matrix = Mat4::translation(Vec3(0., 0., -5.)) * Mat4::scaling(semi_axes);
+        let stretched = TransformedHitable::new(unit_sphere(), matrix).unwrap();
+        let ellipsoid = Ellipsoid::new(Vec3(0., 0., -5.), semi_axes, material());
+
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0.3, 0.1, -1.));
+        let hit = stretched.intersect(&ray, 0.001, f32::MAX).unwrap();
+        let expected = ellipsoid.intersect(&ray, 0.001, f32::MAX).unwrap();
+        // The parameter refers to the world ray, even though the local ray is scaled.
+        assert!((hit.parameter - expected.parameter).abs() < 1e-4);
+        assert!((hit.point_at_parameter - ray.point_at_parameter(hit.parameter)).length() < 1e-4);
+        assert!((hit.point_at_parameter - expected.point_at_parameter).length() < 1e-4);
+        assert!((hit.normal - expected.normal).length() < 1e-4);
+
+        // Transforming the normal like a direction would tilt it visibly.
+        let local = Mat4::scaling(semi_axes)
+            .inverse()
+            .unwrap()
+            .transform_point(&(hit.point_at_parameter - Vec3(0., 0., -5.)));
+        let naive = unit_vector(&matrix.transform_vector(&local));
+        assert!((naive - expected.normal).length() > 0.3);
+    }
+
+    #[test]
+    // Test a sheared and rotated sphere by checking hits lie on the mapped surface.
+    fn test_shear_and_rotation() {
+        let matrix = Mat4::rotation_y(30.)
+            * Mat4::new([
+                [1., 0.8, 0., 0.],
+                [0., 1., 0., 0.],
+                [0., 0., 1., -4.],
+                [0., 0., 0., 1.],
+            ]);
+        let sheared = TransformedHitable::new(unit_sphere(), matrix).unwrap();
+        for direction in [
+            Vec3(-2., 0., -3.5),
+            Vec3(-2.3, 0.3, -3.5),
+            Vec3(-1.5, -0.2, -3.4),
+        ] {
+            let ray = Ray::new(Vec3(0., 0., 0.), direction);
+            let hit = sheared.intersect(&ray, 0.001, f32::MAX).unwrap();
+            let local = sheared.inverse().transform_point(&hit.point_at_parameter);
+            assert!((local.length() - 1.).abs() < 1e-4);
+            assert!((hit.normal.length() - 1.).abs() < 1e-5);
+            // The normal faces the incoming ray at the first hit.
+            assert!(crate::vec3::dot(&hit.normal, &direction) < 0.);
+        }
+        assert_eq!(
+            sheared
+                .intersect_all(&Ray::new(Vec3(0., 0., 0.), Vec3(-2., 0., -3.5)))
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    // Test that singular matrices are rejected.
+    fn test_singular_transform() {
+        let matrix = Mat4::scaling(Vec3(1., 0., 1.));
+        assert_eq!(
+            TransformedHitable::new(unit_sphere(), matrix).err(),
+            Some(ObjectError::SingularTransform)
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::objects::Hitable;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// A hitable object moved by a constant offset.
+///
+/// Instead of moving the object, incoming rays are moved by the opposite
+/// offset and the resulting hit points are moved back.
+pub struct Translate {
+    object: Box<dyn Hitable>,
+    offset: Vec3,
+}
+
+impl Translate {
+    /// Move the hitable `object` by `offset`.
+    ///
+    /// ```
+    /// use raytracer::objects::translate::Translate;
+    /// use raytracer::objects::sphere::Sphere;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let sphere = Sphere::new(Vec3(0., 0., 0.), 1., material);
+    /// let moved = Translate::new(Box::new(sphere), Vec3(0., 1., -2.));
+    /// assert_eq!(moved.offset(), &Vec3(0., 1., -2.));
+    /// ```
+    pub fn new(object: Box<dyn Hitable>, offset: Vec3) -> Translate {
+        Translate { object, offset }
+    }
+
+    /// Access the offset by which the object is moved.
+    pub fn offset(&self) -> &Vec3 {
+        &self.offset
+    }
+
+    // Move the ray into the frame of the wrapped object.
+    fn local_ray(&self, ray: &Ray) -> Ray {
+        Ray::new_with_time(*ray.origin() - self.offset, *ray.direction(), ray.time())
+            .with_medium_seed(ray.medium_seed())
+    }
+
+    // Move a hit on the wrapped object back into the world.
+    fn to_world<'a>(&self, mut hit: HitRecord<'a>) -> HitRecord<'a> {
+        hit.point_at_parameter += self.offset;
+        hit
+    }
+}
+
+impl Hitable for Translate {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.object
+            .intersect(&self.local_ray(ray), t_min, t_max)
+            .map(|hit| self.to_world(hit))
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<HitRecord<'_>> {
+        self.object
+            .intersect_all(&self.local_ray(ray))
+            .into_iter()
+            .map(|hit| self.to_world(hit))
+            .collect()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let aabb = self.object.bounding_box()?;
+        Some(Aabb::new(
+            *aabb.min() + self.offset,
+            *aabb.max() + self.offset,
+        ))
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+    use crate::materials::MaterialExt;
+    use crate::objects::sphere::Sphere;
+    use std::sync::Arc;
+
+    fn material() -> Arc<dyn MaterialExt> {
+        Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)))
+    }
+
+    fn assert_same_hit(a: &HitRecord, b: &HitRecord) {
+        assert!((a.parameter - b.parameter).abs() < 1e-5);
+        assert!((a.point_at_parameter - b.point_at_parameter).length() < 1e-5);
+        assert!((a.normal - b.normal).length() < 1e-5);
+    }
+
+    #[test]
+    // Test that a translated sphere behaves like a sphere built at the offset.
+    fn test_translate_matches_direct_sphere() {
+        let offset = Vec3(1., 2., -3.);
+        let moved = Translate::new(
+            Box::new(Sphere::new(Vec3(0., 0., 0.), 1., material())),
+            offset,
+        );
+        let direct = Sphere::new(offset, 1., material());
+        for direction in [Vec3(1., 2., -3.), Vec3(1.2, 2., -3.), Vec3(0.9, 2.3, -3.1)] {
+            let ray = Ray::new(Vec3(0., 0., 0.), direction);
+            let expected = direct.intersect(&ray, 0.001, f32::MAX).unwrap();
+            let hit = moved.intersect(&ray, 0.001, f32::MAX).unwrap();
+            assert_same_hit(&hit, &expected);
+        }
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., -1.));
+        assert!(moved.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    // Test that nested translations add up to a single offset.
+    fn test_translate_composes() {
+        let sphere = Box::new(Sphere::new(Vec3(0., 0., 0.), 1., material()));
+        let nested = Translate::new(
+            Box::new(Translate::new(sphere, Vec3(1., 0., -2.))),
+            Vec3(0., 2., -1.),
+        );
+        let single = Translate::new(
+            Box::new(Sphere::new(Vec3(0., 0., 0.), 1., material())),
+            Vec3(1., 2., -3.),
+        );
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(1., 2., -3.));
+        let expected = single.intersect(&ray, 0.001, f32::MAX).unwrap();
+        let hit = nested.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_same_hit(&hit, &expected);
+        assert_eq!(nested.intersect_all(&ray).len(), 2);
+    }
+}
@@ -0,0 +1,497 @@
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::materials::MaterialExt;
+use crate::objects::Hitable;
+use crate::ray::Ray;
+use crate::vec3::cross;
+use crate::vec3::dot;
+use crate::vec3::unit_vector;
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+// Return the component of `v` along the coordinate `axis`.
+fn component(v: &Vec3, axis: usize) -> f32 {
+    match axis {
+        0 => v.x(),
+        1 => v.y(),
+        _ => v.z(),
+    }
+}
+
+// Check whether a triangle owns its edge from `p` to `q`, given in the
+// sheared coordinates of the ray, if the ray passes exactly through it.
+//
+// Of two triangles sharing an edge, exactly one owns it: with consistent
+// winding they traverse the edge in opposite directions, otherwise their
+// orientations `positive` differ.
+fn owns_edge(p: (f32, f32), q: (f32, f32), positive: bool) -> bool {
+    let (dx, dy) = (q.0 - p.0, q.1 - p.1);
+    (dy > 0. || (dy == 0. && dx > 0.)) == positive
+}
+
+/// Intersect a ray with the triangle spanned by `p0`, `p1` and `p2`.
+///
+/// Returns the ray parameter and the barycentric coordinates `(u, v)` of
+/// the hit, such that the hit point is `(1 - u - v) * p0 + u * p1 + v * p2`.
+///
+/// This is the watertight algorithm by Woop, Benthin and Wald: the vertices
+/// are sheared into a frame where the ray runs along the z-axis, in which
+/// the edge functions of adjacent triangles are exact opposites. A ray
+/// crossing an edge shared by two triangles therefore hits exactly one of
+/// them, and rays never slip through the cracks of a closed mesh.
+pub(crate) fn intersect_triangle(
+    ray: &Ray,
+    p0: &Vec3,
+    p1: &Vec3,
+    p2: &Vec3,
+    t_min: f32,
+    t_max: f32,
+) -> Option<(f32, f32, f32)> {
+    let direction = ray.direction();
+    // Run the ray along the dimension with the largest extent, swapping the
+    // other two if needed to preserve the winding of the triangle.
+    let kz = (0..3)
+        .max_by(|&a, &b| {
+            component(direction, a)
+                .abs()
+                .total_cmp(&component(direction, b).abs())
+        })
+        .unwrap_or(2);
+    let (mut kx, mut ky) = ((kz + 1) % 3, (kz + 2) % 3);
+    if component(direction, kz) < 0. {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+    let dz = component(direction, kz);
+    let (sx, sy, sz) = (
+        component(direction, kx) / dz,
+        component(direction, ky) / dz,
+        1. / dz,
+    );
+
+    // The vertices relative to the ray origin, sheared and scaled such that
+    // the ray direction becomes (0, 0, 1).
+    let shear = |p: &Vec3| {
+        let a = *p - *ray.origin();
+        let az = component(&a, kz);
+        (
+            (component(&a, kx) - sx * az, component(&a, ky) - sy * az),
+            sz * az,
+        )
+    };
+    let (a, az) = shear(p0);
+    let (b, bz) = shear(p1);
+    let (c, cz) = shear(p2);
+
+    // The edge functions, i.e. twice the signed areas of the triangles
+    // between the ray and each edge. They are recomputed in double
+    // precision if the ray appears to pass exactly through an edge.
+    let edge = |p: (f32, f32), q: (f32, f32)| p.0 * q.1 - p.1 * q.0;
+    let (mut u, mut v, mut w) = (edge(b, c), edge(c, a), edge(a, b));
+    if u == 0. || v == 0. || w == 0. {
+        let edge = |p: (f32, f32), q: (f32, f32)| {
+            (p.0 as f64 * q.1 as f64 - p.1 as f64 * q.0 as f64) as f32
+        };
+        u = edge(b, c);
+        v = edge(c, a);
+        w = edge(a, b);
+    }
+
+    let determinant = u + v + w;
+    if determinant == 0. {
+        return None;
+    }
+    let positive = determinant > 0.;
+    let inside = |e: f32, p: (f32, f32), q: (f32, f32)| {
+        if e == 0. {
+            owns_edge(p, q, positive)
+        } else {
+            (e > 0.) == positive
+        }
+    };
+    if !(inside(u, b, c) && inside(v, c, a) && inside(w, a, b)) {
+        return None;
+    }
+
+    let t = (u * az + v * bz + w * cz) / determinant;
+    if t > t_min && t < t_max {
+        Some((t, v / determinant, w / determinant))
+    } else {
+        None
+    }
+}
+
+/// Interpolate the vertex `normals` at the barycentric coordinates `(u, v)`.
+///
+/// Falls back to the `geometric` normal if the interpolated normal
+/// degenerates, e.g. for opposing vertex normals.
+pub(crate) fn interpolate_normal(normals: &[Vec3; 3], u: f32, v: f32, geometric: &Vec3) -> Vec3 {
+    let normal = (1. - u - v) * normals[0] + u * normals[1] + v * normals[2];
+    if normal.squared_length() < 1e-12 {
+        *geometric
+    } else {
+        unit_vector(&normal)
+    }
+}
+
+// Return the tangent and the bitangent of the triangle with the vertices
+// `p0`, `p1` and `p2` at a hit with the shading `normal`. They follow the
+// edges along which the barycentric coordinates `u` and `v` increase, made
+// orthonormal to the normal, and vanish for degenerate triangles.
+pub(crate) fn tangent_frame(p0: &Vec3, p1: &Vec3, p2: &Vec3, normal: &Vec3) -> (Vec3, Vec3) {
+    let (edge_u, edge_v) = (*p1 - *p0, *p2 - *p0);
+    let tangent = edge_u - dot(&edge_u, normal) * *normal;
+    if tangent.squared_length() < 1e-12 {
+        return (Vec3(0., 0., 0.), Vec3(0., 0., 0.));
+    }
+    let tangent = unit_vector(&tangent);
+    let bitangent = cross(normal, &tangent);
+    if dot(&bitangent, &edge_v) < 0. {
+        (tangent, -bitangent)
+    } else {
+        (tangent, bitangent)
+    }
+}
+
+/// The sides of a triangle which can be hit by rays.
+///
+/// The front side is the one from which the vertices appear in
+/// counter-clockwise order. Hits on the back side report the flipped normal,
+/// such that materials always see a normal pointing towards the ray.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Sidedness {
+    /// Both sides are hit, e.g. for open surfaces.
+    #[default]
+    DoubleSided,
+    /// Only the front side is hit, e.g. for the outside of closed meshes.
+    FrontOnly,
+    /// Only the back side is hit, e.g. for the inside of closed meshes.
+    BackOnly,
+}
+
+impl Sidedness {
+    // Check whether a ray may hit the triangle with the `geometric` normal.
+    pub(crate) fn accepts(self, ray: &Ray, geometric: &Vec3) -> bool {
+        let front = dot(ray.direction(), geometric) < 0.;
+        match self {
+            Sidedness::DoubleSided => true,
+            Sidedness::FrontOnly => front,
+            Sidedness::BackOnly => !front,
+        }
+    }
+}
+
+// Turn the shading `normal` towards the ray if it hits the back side.
+pub(crate) fn facing_normal(ray: &Ray, geometric: &Vec3, normal: Vec3) -> Vec3 {
+    if dot(ray.direction(), geometric) > 0. {
+        -normal
+    } else {
+        normal
+    }
+}
+
+/// A triangle in three-dimensional space.
+///
+/// It is characterized by:
+/// - Its three vertices. The front side is the one from which they appear
+///   in counter-clockwise order.
+/// - Optional normals at the vertices, which are interpolated across the
+///   triangle for smooth shading.
+/// - The sides from which it can be hit, both by default.
+/// - A pointer to the material that it is made of.
+pub struct Triangle {
+    vertices: [Vec3; 3],
+    normals: Option<[Vec3; 3]>,
+    sidedness: Sidedness,
+    material: Arc<dyn MaterialExt>,
+}
+
+impl Triangle {
+    /// Create a flat-shaded `Triangle` from its three vertices and its `Material`.
+    ///
+    /// ```
+    /// use raytracer::objects::triangle::Triangle;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let triangle = Triangle::new(Vec3(0., 0., 0.), Vec3(1., 0., 0.), Vec3(0., 1., 0.), material);
+    /// assert_eq!(triangle.normal(), Vec3(0., 0., 1.));
+    /// ```
+    pub fn new(p0: Vec3, p1: Vec3, p2: Vec3, material: Arc<dyn MaterialExt>) -> Triangle {
+        Triangle {
+            vertices: [p0, p1, p2],
+            normals: None,
+            sidedness: Sidedness::default(),
+            material,
+        }
+    }
+
+    /// Set the normals at the three vertices, making the triangle smooth-shaded.
+    ///
+    /// The normals do not need to be normalized.
+    ///
+    /// ```
+    /// # use raytracer::objects::triangle::Triangle;
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::materials::Lambertian;
+    /// # use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let triangle = Triangle::new(Vec3(0., 0., 0.), Vec3(1., 0., 0.), Vec3(0., 1., 0.), material)
+    ///     .with_normals([Vec3(0., 0., 1.), Vec3(1., 0., 1.), Vec3(0., 1., 1.)]);
+    /// ```
+    pub fn with_normals(mut self, normals: [Vec3; 3]) -> Triangle {
+        self.normals = Some([
+            unit_vector(&normals[0]),
+            unit_vector(&normals[1]),
+            unit_vector(&normals[2]),
+        ]);
+        self
+    }
+
+    /// Set the sides from which the `Triangle` can be hit.
+    ///
+    /// ```
+    /// # use raytracer::objects::triangle::{Sidedness, Triangle};
+    /// # use raytracer::objects::Hitable;
+    /// # use raytracer::ray::Ray;
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::materials::Lambertian;
+    /// # use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let triangle = Triangle::new(Vec3(0., 0., 0.), Vec3(1., 0., 0.), Vec3(0., 1., 0.), material)
+    ///     .with_sidedness(Sidedness::FrontOnly);
+    /// let from_behind = Ray::new(Vec3(0.2, 0.2, -1.), Vec3(0., 0., 1.));
+    /// assert!(triangle.intersect(&from_behind, 0.001, f32::MAX).is_none());
+    /// ```
+    pub fn with_sidedness(mut self, sidedness: Sidedness) -> Triangle {
+        self.sidedness = sidedness;
+        self
+    }
+
+    /// Access the sides from which a `Triangle` can be hit.
+    pub fn sidedness(&self) -> Sidedness {
+        self.sidedness
+    }
+
+    /// Access the vertices of a `Triangle`.
+    pub fn vertices(&self) -> &[Vec3; 3] {
+        &self.vertices
+    }
+
+    /// Access the vertex normals of a smooth-shaded `Triangle`.
+    pub fn vertex_normals(&self) -> Option<&[Vec3; 3]> {
+        self.normals.as_ref()
+    }
+
+    /// Return the geometric normal of a `Triangle`, i.e. the normal of its plane.
+    pub fn normal(&self) -> Vec3 {
+        let [p0, p1, p2] = &self.vertices;
+        unit_vector(&cross(&(*p1 - *p0), &(*p2 - *p0)))
+    }
+
+    /// Access the `material` a `Triangle` is made of.
+    pub fn material(&self) -> Arc<dyn MaterialExt> {
+        Arc::clone(&self.material)
+    }
+}
+
+impl Hitable for Triangle {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let geometric = self.normal();
+        if !self.sidedness.accepts(ray, &geometric) {
+            return None;
+        }
+        let [p0, p1, p2] = &self.vertices;
+        let (t, u, v) = intersect_triangle(ray, p0, p1, p2, t_min, t_max)?;
+        let normal = match &self.normals {
+            Some(normals) => interpolate_normal(normals, u, v, &geometric),
+            None => geometric,
+        };
+        let normal = facing_normal(ray, &geometric, normal);
+        let (tangent, bitangent) = tangent_frame(p0, p1, p2, &normal);
+        Some(HitRecord {
+            parameter: t,
+            point_at_parameter: ray.point_at_parameter(t),
+            normal,
+            u,
+            v,
+            tangent,
+            bitangent,
+            front_face: dot(ray.direction(), &geometric) < 0.,
+            material: self.material.as_ref(),
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Aabb::from_points(&self.vertices)
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+
+    fn triangle() -> Triangle {
+        Triangle::new(
+            Vec3(0., 0., -1.),
+            Vec3(1., 0., -1.),
+            Vec3(0., 1., -1.),
+            Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))),
+        )
+    }
+
+    #[test]
+    // Test hits inside and misses outside a flat-shaded triangle.
+    fn test_triangle_hit() {
+        let triangle = triangle();
+        let ray = Ray::new(Vec3(0.25, 0.25, 0.), Vec3(0., 0., -1.));
+        let hit = triangle.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.parameter, 1.);
+        assert_eq!(hit.point_at_parameter, Vec3(0.25, 0.25, -1.));
+        assert_eq!(hit.normal, Vec3(0., 0., 1.));
+        // The texture coordinates are the barycentric coordinates.
+        assert_eq!((hit.u, hit.v), (0.25, 0.25));
+        let ray = Ray::new(Vec3(0.75, 0.75, 0.), Vec3(0., 0., -1.));
+        assert!(triangle.intersect(&ray, 0.001, f32::MAX).is_none());
+        let ray = Ray::new(Vec3(0.25, 0.25, 0.), Vec3(1., 0., 0.));
+        assert!(triangle.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+
+    #[test]
+    // Test which sides of a triangle are hit, and that the normal faces the ray.
+    fn test_triangle_sidedness() {
+        let from_front = Ray::new(Vec3(0.25, 0.25, 0.), Vec3(0., 0., -1.));
+        let from_behind = Ray::new(Vec3(0.25, 0.25, -2.), Vec3(0., 0., 1.));
+        let triangle = triangle();
+        assert_eq!(triangle.sidedness(), Sidedness::DoubleSided);
+        let hit = triangle.intersect(&from_front, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.normal, Vec3(0., 0., 1.));
+        let hit = triangle.intersect(&from_behind, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.normal, Vec3(0., 0., -1.));
+
+        let front_only = triangle.with_sidedness(Sidedness::FrontOnly);
+        assert!(front_only.intersect(&from_front, 0.001, f32::MAX).is_some());
+        assert!(front_only
+            .intersect(&from_behind, 0.001, f32::MAX)
+            .is_none());
+
+        let back_only = front_only.with_sidedness(Sidedness::BackOnly);
+        assert!(back_only.intersect(&from_front, 0.001, f32::MAX).is_none());
+        let hit = back_only.intersect(&from_behind, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.normal, Vec3(0., 0., -1.));
+    }
+
+    #[test]
+    // Test that vertex normals are interpolated with the barycentric coordinates.
+    fn test_triangle_smooth_normals() {
+        let triangle =
+            triangle().with_normals([Vec3(0., 0., 1.), Vec3(1., 0., 0.), Vec3(0., 1., 0.)]);
+        // At a vertex, the vertex normal is used.
+        let ray = Ray::new(Vec3(0.999, 0.0005, 0.), Vec3(0., 0., -1.));
+        let hit = triangle.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.normal - Vec3(1., 0., 0.)).length() < 1e-2);
+        // At the centroid, the normals are averaged and renormalized.
+        let ray = Ray::new(Vec3(1. / 3., 1. / 3., 0.), Vec3(0., 0., -1.));
+        let hit = triangle.intersect(&ray, 0.001, f32::MAX).unwrap();
+        let expected = unit_vector(&Vec3(1., 1., 1.));
+        assert!((hit.normal - expected).length() < 1e-5);
+    }
+
+    #[test]
+    // Test that rays through the edges and vertices of a tessellated quad hit
+    // exactly one of its triangles.
+    fn test_watertight_tessellated_quad() {
+        // A quad in the plane z = -2 split into n x n cells of two triangles,
+        // with the diagonals alternating between the cells.
+        let n = 8;
+        let corner = |i: usize, j: usize| {
+            Vec3(
+                -1. + 2. * i as f32 / n as f32,
+                -1. + 2. * j as f32 / n as f32,
+                -2.,
+            )
+        };
+        let mut triangles = Vec::new();
+        for i in 0..n {
+            for j in 0..n {
+                let [a, b, c, d] = [
+                    corner(i, j),
+                    corner(i + 1, j),
+                    corner(i + 1, j + 1),
+                    corner(i, j + 1),
+                ];
+                if (i + j) % 2 == 0 {
+                    triangles.push([a, b, c]);
+                    triangles.push([a, c, d]);
+                } else {
+                    triangles.push([a, b, d]);
+                    triangles.push([b, c, d]);
+                }
+            }
+        }
+
+        // Fire rays from a few origins through a grid four times finer than
+        // the cells, which includes every edge and vertex.
+        let mut misses = 0;
+        for origin in [
+            Vec3(0., 0., 0.),
+            Vec3(0.31, -0.17, 1.3),
+            Vec3(-2.5, 1.7, 0.9),
+        ] {
+            for i in 1..4 * n {
+                for j in 1..4 * n {
+                    let target = Vec3(
+                        -1. + 0.5 * i as f32 / n as f32,
+                        -1. + 0.5 * j as f32 / n as f32,
+                        -2.,
+                    );
+                    let ray = Ray::new(origin, target - origin);
+                    let hits = triangles
+                        .iter()
+                        .filter(|[p0, p1, p2]| {
+                            intersect_triangle(&ray, p0, p1, p2, 0.001, f32::MAX).is_some()
+                        })
+                        .count();
+                    if hits == 0 {
+                        misses += 1;
+                    }
+                    assert!(hits <= 1);
+                }
+            }
+        }
+        assert_eq!(misses, 0);
+    }
+
+    #[test]
+    // Test the fallback to the geometric normal for degenerate interpolation.
+    fn test_interpolate_normal_degenerate() {
+        let normals = [Vec3(1., 0., 0.), Vec3(-1., 0., 0.), Vec3(0., 0., 1.)];
+        let geometric = Vec3(0., 0., 1.);
+        assert_eq!(interpolate_normal(&normals, 0.5, 0., &geometric), geometric);
+    }
+
+    #[test]
+    // Test that the tangents follow the barycentric coordinates from both sides.
+    fn test_triangle_tangents() {
+        let triangle = triangle();
+        for &(z, normal) in &[(0., Vec3(0., 0., 1.)), (-2., Vec3(0., 0., -1.))] {
+            let ray = Ray::new(Vec3(0.25, 0.25, z), Vec3(0., 0., -1. - z));
+            let hit = triangle.intersect(&ray, 0.001, f32::MAX).unwrap();
+            assert_eq!(hit.normal, normal);
+            assert_eq!(hit.tangent, Vec3(1., 0., 0.));
+            assert_eq!(hit.bitangent, Vec3(0., 1., 0.));
+        }
+        // Shading normals tilt the tangents along with them.
+        let normals = [unit_vector(&Vec3(1., 0., 1.)); 3];
+        let smooth = triangle.with_normals(normals);
+        let ray = Ray::new(Vec3(0.25, 0.25, 0.), Vec3(0., 0., -1.));
+        let hit = smooth.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!((hit.tangent - unit_vector(&Vec3(1., 0., -1.))).length() < 1e-6);
+        assert!((hit.bitangent - Vec3(0., 1., 0.)).length() < 1e-6);
+    }
+}
@@ -0,0 +1,463 @@
+use crate::aabb::surrounding_box;
+use crate::aabb::Aabb;
+use crate::hit_record::HitRecord;
+use crate::objects::Hitable;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+// The number of cells per object which the resolution aims for.
+const CELLS_PER_OBJECT: f32 = 2.;
+
+// The largest number of cells along an axis.
+const MAX_RESOLUTION: usize = 128;
+
+/// An acceleration structure dividing the box around the objects into cells
+/// of equal size, each listing the objects which overlap it.
+///
+/// A ray walks through the cells it crosses in order, stepping from cell to
+/// cell by a 3D digital differential analyzer, and is only tested against
+/// the objects listed in these cells. Once a hit lies within the cell being
+/// visited, no later cell can hold a closer one. Objects overlapping several
+/// cells are tested only once per ray.
+///
+/// A grid is much cheaper to build than a `BvhNode` and traverses dense,
+/// evenly distributed scenes quickly, but it adapts badly to objects of very
+/// different sizes: a single huge object, e.g. a ground sphere, stretches
+/// the cells until each holds many of the small objects. Like for a
+/// `BvhNode`, unbounded objects are kept in a list next to the grid.
+pub struct UniformGrid {
+    objects: Vec<Box<dyn Hitable>>,
+    unbounded: Vec<Box<dyn Hitable>>,
+    bounds: Option<Aabb>,
+    resolution: [usize; 3],
+    cell_size: [f32; 3],
+    // The indices of the objects overlapping each cell, ordered by x first,
+    // then y, then z.
+    cells: Vec<Vec<usize>>,
+}
+
+impl UniformGrid {
+    /// Sort the hitable `objects` into a `UniformGrid`.
+    ///
+    /// The number of cells is chosen proportional to the number of objects,
+    /// with cells as close to cubes as the box around the objects allows.
+    ///
+    /// ```
+    /// use raytracer::objects::sphere::Sphere;
+    /// use raytracer::objects::uniform_grid::UniformGrid;
+    /// use raytracer::objects::Hitable;
+    /// use raytracer::ray::Ray;
+    /// use raytracer::vec3::Vec3;
+    /// use raytracer::materials::Lambertian;
+    /// use std::sync::Arc;
+    /// let material = Arc::new(Lambertian::new(Vec3(0.8, 0.3, 0.3)));
+    /// let spheres: Vec<Box<dyn Hitable>> = (0..100)
+    ///     .map(|i| {
+    ///         let center = Vec3((i % 10) as f32, 0.2, -((i / 10) as f32) - 2.);
+    ///         Box::new(Sphere::new(center, 0.2, material.clone())) as Box<dyn Hitable>
+    ///     })
+    ///     .collect();
+    /// let world = UniformGrid::new(spheres);
+    /// let ray = Ray::new(Vec3(3., 0.2, 0.), Vec3(0., 0., -1.));
+    /// let hit = world.intersect(&ray, 0.001, f32::MAX).unwrap();
+    /// assert!((hit.parameter - 1.8).abs() < 1e-5);
+    /// ```
+    pub fn new(objects: Vec<Box<dyn Hitable>>) -> UniformGrid {
+        let (bounded, unbounded): (Vec<_>, Vec<_>) = objects
+            .into_iter()
+            .map(|object| (object.bounding_box(), object))
+            .partition(|(aabb, _)| aabb.is_some());
+        let (boxes, objects): (Vec<Aabb>, Vec<_>) = bounded
+            .into_iter()
+            .map(|(aabb, object)| (aabb.unwrap(), object))
+            .unzip();
+        let unbounded = unbounded.into_iter().map(|(_, object)| object).collect();
+        let bounds = boxes.iter().copied().reduce(|a, b| surrounding_box(&a, &b));
+
+        let extent = bounds.map_or([0.; 3], |bounds| {
+            components(&(*bounds.max() - *bounds.min()))
+        });
+        let resolution = choose_resolution(extent, objects.len());
+        let mut grid = UniformGrid {
+            objects,
+            unbounded,
+            bounds,
+            resolution,
+            cell_size: [0, 1, 2].map(|axis| extent[axis] / resolution[axis] as f32),
+            cells: vec![Vec::new(); resolution.iter().product()],
+        };
+        for (index, aabb) in boxes.iter().enumerate() {
+            // Widen the range of cells slightly, such that rounding in the
+            // traversal cannot miss an object touching a cell boundary.
+            let low = grid.cell_coordinates(aabb.min(), -1e-3);
+            let high = grid.cell_coordinates(aabb.max(), 1e-3);
+            for z in low[2]..=high[2] {
+                for y in low[1]..=high[1] {
+                    for x in low[0]..=high[0] {
+                        let cell = grid.cell_index([x, y, z]);
+                        grid.cells[cell].push(index);
+                    }
+                }
+            }
+        }
+        grid
+    }
+
+    /// Access the number of cells along the x-, y- and z-axis.
+    pub fn resolution(&self) -> (usize, usize, usize) {
+        (self.resolution[0], self.resolution[1], self.resolution[2])
+    }
+
+    // Return the cell containing the point `p`, moved by `offset` cells
+    // along each axis, clamped to the grid.
+    fn cell_coordinates(&self, p: &Vec3, offset: f32) -> [usize; 3] {
+        let min = components(self.bounds.as_ref().unwrap().min());
+        let p = components(p);
+        [0, 1, 2].map(|axis| {
+            if self.cell_size[axis] > 0. {
+                let x = (p[axis] - min[axis]) / self.cell_size[axis] + offset;
+                (x.max(0.) as usize).min(self.resolution[axis] - 1)
+            } else {
+                0
+            }
+        })
+    }
+
+    fn cell_index(&self, [x, y, z]: [usize; 3]) -> usize {
+        x + self.resolution[0] * (y + self.resolution[1] * z)
+    }
+
+    // Walk through the cells crossed by the ray between `t_min` and `t_max`
+    // in order, passing the objects of each cell and the parameter where the
+    // ray leaves it to `visit`, until it returns `true`.
+    fn traverse(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        mut visit: impl FnMut(&[usize], f32) -> bool,
+    ) {
+        let bounds = match &self.bounds {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let (t_entry, t_exit) = match bounds.clip(ray, t_min, t_max) {
+            Some(range) => range,
+            None => return,
+        };
+        let mut cell = self.cell_coordinates(&ray.point_at_parameter(t_entry), 0.);
+        let min = components(bounds.min());
+        let origin = components(ray.origin());
+        let direction = components(ray.direction());
+        let inv_direction = components(ray.inv_direction());
+
+        // The parameter where the ray crosses the next cell boundary along
+        // each axis, and the parameter distance between two boundaries.
+        let mut t_next = [f32::INFINITY; 3];
+        let mut t_delta = [f32::INFINITY; 3];
+        for axis in 0..3 {
+            let size = self.cell_size[axis];
+            if direction[axis] > 0. {
+                let boundary = min[axis] + (cell[axis] + 1) as f32 * size;
+                t_next[axis] = (boundary - origin[axis]) * inv_direction[axis];
+                t_delta[axis] = size * inv_direction[axis];
+            } else if direction[axis] < 0. {
+                let boundary = min[axis] + cell[axis] as f32 * size;
+                t_next[axis] = (boundary - origin[axis]) * inv_direction[axis];
+                t_delta[axis] = -size * inv_direction[axis];
+            }
+        }
+
+        loop {
+            let axis = (0..3)
+                .min_by(|&a, &b| t_next[a].total_cmp(&t_next[b]))
+                .unwrap();
+            let t_cell_exit = t_next[axis].min(t_exit);
+            if visit(&self.cells[self.cell_index(cell)], t_cell_exit) || t_next[axis] > t_exit {
+                return;
+            }
+            if direction[axis] > 0. {
+                if cell[axis] + 1 == self.resolution[axis] {
+                    return;
+                }
+                cell[axis] += 1;
+            } else {
+                if cell[axis] == 0 {
+                    return;
+                }
+                cell[axis] -= 1;
+            }
+            t_next[axis] += t_delta[axis];
+        }
+    }
+}
+
+impl Hitable for UniformGrid {
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let mut hit_record = None;
+        let mut closest_so_far = t_max;
+        // The objects tested so far, which may be listed in later cells again.
+        let mut tested = Vec::new();
+        self.traverse(ray, t_min, t_max, |objects, t_cell_exit| {
+            for &index in objects {
+                if tested.contains(&index) {
+                    continue;
+                }
+                tested.push(index);
+                if let Some(hit) = self.objects[index].intersect(ray, t_min, closest_so_far) {
+                    closest_so_far = hit.parameter;
+                    hit_record = Some(hit);
+                }
+            }
+            // Hits in later cells lie further along the ray.
+            closest_so_far < t_cell_exit
+        });
+        for object in &self.unbounded {
+            if let Some(hit) = object.intersect(ray, t_min, closest_so_far) {
+                closest_so_far = hit.parameter;
+                hit_record = Some(hit);
+            }
+        }
+        hit_record
+    }
+
+    fn intersect_any(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        let mut blocked = false;
+        let mut tested = Vec::new();
+        self.traverse(ray, t_min, t_max, |objects, _| {
+            blocked = objects.iter().any(|&index| {
+                if tested.contains(&index) {
+                    return false;
+                }
+                tested.push(index);
+                self.objects[index].intersect_any(ray, t_min, t_max)
+            });
+            blocked
+        });
+        blocked
+            || self
+                .unbounded
+                .iter()
+                .any(|object| object.intersect_any(ray, t_min, t_max))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        if self.unbounded.is_empty() {
+            self.bounds
+        } else {
+            None
+        }
+    }
+}
+
+// The coordinates of `v` as an array, to loop over the axes.
+fn components(v: &Vec3) -> [f32; 3] {
+    [v.x(), v.y(), v.z()]
+}
+
+// Choose the number of cells along each axis for `n` objects within a box
+// of the given `extent`, such that the cells are roughly cubes.
+fn choose_resolution(extent: [f32; 3], n: usize) -> [usize; 3] {
+    let largest = extent.iter().cloned().fold(0., f32::max);
+    if n == 0 || largest <= 0. || !largest.is_finite() {
+        return [1; 3];
+    }
+    // Flat boxes count as thin slabs, such that they are still divided.
+    let extent = extent.map(|x| x.max(largest * 1e-3));
+    let volume: f32 = extent.iter().product();
+    let cells_per_length = (CELLS_PER_OBJECT * n as f32 / volume).cbrt();
+    extent.map(|x| ((x * cells_per_length).round() as usize).clamp(1, MAX_RESOLUTION))
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+    use crate::materials::MaterialExt;
+    use crate::objects::cuboid::Cuboid;
+    use crate::objects::infinite_cylinder::InfiniteCylinder;
+    use crate::objects::rect::Rect;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::HitableList;
+    use rand::prelude::*;
+    use rand::rngs::StdRng;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    // Count the intersection tests of the wrapped object.
+    struct Counted {
+        object: Box<dyn Hitable>,
+        count: Arc<AtomicUsize>,
+    }
+
+    impl Hitable for Counted {
+        fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+            self.count.fetch_add(1, Ordering::Relaxed);
+            self.object.intersect(ray, t_min, t_max)
+        }
+
+        fn bounding_box(&self) -> Option<Aabb> {
+            self.object.bounding_box()
+        }
+    }
+
+    // A field of small spheres and boxes like in the cover scene, with a
+    // few large objects overlapping many cells and an unbounded cylinder.
+    fn scene(rng: &mut StdRng) -> Vec<Arc<dyn Hitable>> {
+        let mut objects: Vec<Arc<dyn Hitable>> = Vec::new();
+        let mut material = || -> Arc<dyn MaterialExt> {
+            Arc::new(Lambertian::new(Vec3(rng.gen(), rng.gen(), rng.gen())))
+        };
+        for a in -11..11 {
+            for b in -11..11 {
+                let center = Vec3(a as f32 + 0.6 * 0.5, 0.2, b as f32 + 0.6 * 0.5);
+                if (a + b) % 5 == 0 {
+                    let half = Vec3(0.2, 0.2, 0.2);
+                    objects.push(Arc::new(Cuboid::new(
+                        center - half,
+                        center + half,
+                        material(),
+                    )));
+                } else {
+                    objects.push(Arc::new(Sphere::new(center, 0.2, material())));
+                }
+            }
+        }
+        for &(center, radius) in &[
+            (Vec3(0., 1., 0.), 1.),
+            (Vec3(-4., 1., 0.), 1.),
+            (Vec3(4., 1., 0.), 1.),
+            (Vec3(0., -100., 0.), 99.9),
+        ] {
+            objects.push(Arc::new(Sphere::new(center, radius, material())));
+        }
+        objects.push(Arc::new(InfiniteCylinder::new(
+            Vec3(0., 3., 0.),
+            Vec3(1., 0., 0.3),
+            0.2,
+            material(),
+        )));
+        objects
+    }
+
+    #[test]
+    // Test that the grid finds the same closest hits as a flat list.
+    fn test_grid_matches_list() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let objects = scene(&mut rng);
+        // Both hold the same objects, such that their materials can be compared.
+        let boxed = || {
+            objects
+                .iter()
+                .map(|object| Box::new(object.clone()) as Box<dyn Hitable>)
+                .collect()
+        };
+        let list = HitableList::new(boxed());
+        let grid = UniformGrid::new(boxed());
+        let (nx, ny, nz) = grid.resolution();
+        assert!(nx > 1 && ny > 1 && nz > 1);
+
+        let mut hits = 0;
+        for _ in 0..5000 {
+            let origin = Vec3(
+                rng.gen_range(-15.0..15.0),
+                rng.gen_range(0.0..5.0),
+                rng.gen_range(-15.0..15.0),
+            );
+            let direction = Vec3(
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+            );
+            let ray = Ray::new(origin, direction);
+            let t_max = rng.gen_range(1.0..50.0);
+            let expected = list.intersect(&ray, 0.001, t_max);
+            let hit = grid.intersect(&ray, 0.001, t_max);
+            match (hit, expected) {
+                (None, None) => {}
+                (Some(hit), Some(expected)) => {
+                    assert_eq!(hit.parameter, expected.parameter);
+                    assert_eq!(hit.normal, expected.normal);
+                    assert!(std::ptr::addr_eq(hit.material, expected.material));
+                    hits += 1;
+                }
+                (hit, expected) => panic!(
+                    "hit {:?} differs from expected {:?}",
+                    hit.map(|h| h.parameter),
+                    expected.map(|h| h.parameter)
+                ),
+            }
+            let blocked = list.intersect_any(&ray, 0.001, t_max);
+            assert_eq!(grid.intersect_any(&ray, 0.001, t_max), blocked);
+        }
+        // Make sure the rays do not only miss.
+        assert!(hits > 1000);
+    }
+
+    #[test]
+    // Test that an object overlapping many cells is tested once per ray.
+    fn test_grid_tests_objects_once() {
+        let material: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        let count = Arc::new(AtomicUsize::new(0));
+        let mut objects: Vec<Box<dyn Hitable>> = (0..1000)
+            .map(|i| {
+                let center = Vec3((i % 10) as f32, ((i / 10) % 10) as f32, (i / 100) as f32);
+                Box::new(Sphere::new(center, 0.1, material.clone())) as Box<dyn Hitable>
+            })
+            .collect();
+        // A thin slab through all cells, but missed by the ray.
+        objects.push(Box::new(Counted {
+            object: Box::new(Cuboid::new(
+                Vec3(0., 4.45, 0.),
+                Vec3(9., 4.55, 9.),
+                material.clone(),
+            )),
+            count: count.clone(),
+        }));
+        let grid = UniformGrid::new(objects);
+        let (nx, ny, nz) = grid.resolution();
+        assert!(nx > 5 && ny > 5 && nz > 5);
+        let ray = Ray::new(Vec3(-1., 4.3, -1.), Vec3(1., 0.01, 1.));
+        assert!(grid.intersect(&ray, 0.001, f32::MAX).is_none());
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    // Test grids of no objects, a single point and a flat layer.
+    fn test_grid_degenerate_bounds() {
+        let material: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        let ray = Ray::new(Vec3(0.5, 0.5, 1.), Vec3(0., 0., -1.));
+        let empty = UniformGrid::new(Vec::new());
+        assert!(empty.intersect(&ray, 0.001, f32::MAX).is_none());
+        assert!(empty.bounding_box().is_none());
+
+        let point = UniformGrid::new(vec![Box::new(Sphere::new(
+            Vec3(0.5, 0.5, 0.),
+            0.,
+            material.clone(),
+        ))]);
+        assert_eq!(point.resolution(), (1, 1, 1));
+        assert!(point.intersect(&ray, 0.001, f32::MAX).is_none());
+
+        let tiles: Vec<Box<dyn Hitable>> = (0..100)
+            .map(|i| {
+                let corner = Vec3((i % 10) as f32, (i / 10) as f32, 0.);
+                let (u, v) = (Vec3(0.9, 0., 0.), Vec3(0., 0.9, 0.));
+                let tile = Rect::new(corner, u, v, material.clone());
+                Box::new(tile) as Box<dyn Hitable>
+            })
+            .collect();
+        let layer = UniformGrid::new(tiles);
+        let (nx, ny, nz) = layer.resolution();
+        assert!(nx > 1 && ny > 1 && nz == 1);
+        let hit = layer.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert_eq!(hit.parameter, 1.);
+        let ray = Ray::new(Vec3(0.95, 0.5, 1.), Vec3(0., 0., -1.));
+        assert!(layer.intersect(&ray, 0.001, f32::MAX).is_none());
+    }
+}
@@ -0,0 +1,112 @@
+//! Probability densities over directions, for importance sampling.
+//!
+//! A material scattering diffusely reports the density it draws directions
+//! from, see `materials::ScatterRecord`. An integrator may then draw the
+//! scattered direction from another density, e.g. towards a light, and
+//! weight the path by the ratio of the material's own scattering density to
+//! the density the direction was drawn from.
+//!
+//! Densities are per unit of solid angle, such that they integrate to 1 over
+//! the sphere of directions.
+
+use rand::Rng;
+use rand::RngCore;
+use std::f32::consts::PI;
+
+use crate::materials::around_axis;
+use crate::vec3::dot;
+use crate::vec3::unit_vector;
+use crate::vec3::Vec3;
+
+/// A probability density over directions.
+pub trait Pdf: Send + Sync {
+    /// Return the density of the `direction`, which need not be normalized.
+    fn value(&self, direction: &Vec3) -> f32;
+
+    /// Draw a direction from the density, using `rng`.
+    fn generate(&self, rng: &mut dyn RngCore) -> Vec3;
+}
+
+/// The density proportional to the cosine of the angle to a normal, over
+/// the hemisphere around it, which is the one of ideal diffuse reflection.
+///
+/// ```
+/// # use raytracer::pdf::{CosinePdf, Pdf};
+/// # use raytracer::vec3::Vec3;
+/// let pdf = CosinePdf::new(&Vec3(0., 0., 2.));
+/// assert_eq!(pdf.value(&Vec3(0., 0., 1.)), 1. / std::f32::consts::PI);
+/// assert_eq!(pdf.value(&Vec3(0., 0., -1.)), 0.);
+/// let direction = pdf.generate(&mut rand::thread_rng());
+/// assert!(direction.z() >= 0.);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CosinePdf {
+    normal: Vec3,
+}
+
+impl CosinePdf {
+    /// Create the cosine density around the `normal`, which need not be
+    /// normalized.
+    pub fn new(normal: &Vec3) -> CosinePdf {
+        CosinePdf {
+            normal: unit_vector(normal),
+        }
+    }
+
+    /// Access the unit normal of the density.
+    pub fn normal(&self) -> &Vec3 {
+        &self.normal
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, direction: &Vec3) -> f32 {
+        let cosine = dot(&unit_vector(direction), &self.normal);
+        if cosine > 0. {
+            cosine / PI
+        } else {
+            0.
+        }
+    }
+
+    fn generate(&self, rng: &mut dyn RngCore) -> Vec3 {
+        // The squared cosine of a cosine-distributed direction is uniform.
+        let cos_theta = rng.gen::<f32>().sqrt();
+        around_axis(&self.normal, cos_theta, rng)
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    // Test that the generated directions follow the density: weighting them by
+    // the inverse of their density estimates the area of the hemisphere.
+    fn test_cosine_pdf_generate() {
+        let normal = Vec3(0.3, -1., 0.5);
+        let pdf = CosinePdf::new(&normal);
+        let mut rng = StdRng::seed_from_u64(99);
+        let samples = 100_000;
+        let mut area = 0.;
+        let mut mean_cosine = 0.;
+        for _ in 0..samples {
+            let direction = pdf.generate(&mut rng);
+            assert!((direction.length() - 1.).abs() < 1e-5);
+            let cosine = dot(&direction, pdf.normal());
+            assert!(cosine >= -1e-6);
+            area += 1. / pdf.value(&direction).max(1e-3);
+            mean_cosine += cosine;
+        }
+        // The estimate of the area is cut off close to the horizon.
+        assert!((area / samples as f32 - 2. * PI).abs() < 0.05 * 2. * PI);
+        // The mean cosine of the cosine density is 2/3.
+        assert!((mean_cosine / samples as f32 - 2. / 3.).abs() < 0.01);
+    }
+}
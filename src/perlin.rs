@@ -0,0 +1,222 @@
+//! Perlin noise, the building block of procedural textures.
+//!
+//! The noise is defined on an integer lattice: every lattice point gets a
+//! random unit gradient, picked through three permutation tables, and the
+//! noise at a point is the Hermite-smoothed trilinear interpolation of the
+//! dot products between the gradients of the surrounding lattice points and
+//! the offsets to them. This makes the noise continuous, zero on the lattice
+//! points and free of the blockiness of interpolated random values.
+//!
+//! The tables are filled from a random number generator, which is why two
+//! generators created from equally seeded random number generators give the
+//! exact same noise.
+
+use rand::seq::SliceRandom;
+use rand::RngCore;
+
+use crate::materials::random_in_unit_sphere;
+use crate::vec3::dot;
+use crate::vec3::unit_vector;
+use crate::vec3::Vec3;
+
+// The number of gradients, after which the noise repeats along each axis.
+pub(crate) const POINT_COUNT: usize = 256;
+
+/// A generator of Perlin noise.
+///
+/// ```
+/// # use raytracer::perlin::Perlin;
+/// # use rand::rngs::StdRng;
+/// # use rand::SeedableRng;
+/// # use raytracer::vec3::Vec3;
+/// let perlin = Perlin::new(&mut StdRng::seed_from_u64(0));
+/// // The noise vanishes on the lattice points.
+/// assert_eq!(perlin.noise(&Vec3(1., 2., 3.)), 0.);
+/// assert!(perlin.noise(&Vec3(1.5, 2.5, 3.5)).abs() <= 1.);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    /// Create a noise generator whose tables are drawn from `rng`.
+    pub fn new(rng: &mut dyn RngCore) -> Perlin {
+        let gradients = (0..POINT_COUNT)
+            .map(|_| loop {
+                // Reject points too close to the origin to be normalized.
+                let p = random_in_unit_sphere(rng);
+                if p.squared_length() > 1e-6 {
+                    break unit_vector(&p);
+                }
+            })
+            .collect();
+        Perlin {
+            gradients,
+            perm_x: permutation(rng),
+            perm_y: permutation(rng),
+            perm_z: permutation(rng),
+        }
+    }
+
+    /// Return the noise at the point `p`, between -1 and 1.
+    pub fn noise(&self, p: &Vec3) -> f32 {
+        let (fx, fy, fz) = (p.x().floor(), p.y().floor(), p.z().floor());
+        let (u, v, w) = (p.x() - fx, p.y() - fy, p.z() - fz);
+        let (i, j, k) = (fx as i64, fy as i64, fz as i64);
+        // The smoothed weights, whose derivative vanishes on the lattice.
+        let (uu, vv, ww) = (hermite(u), hermite(v), hermite(w));
+
+        let mut accum = 0.;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let gradient = &self.gradients[self.perm_x[wrap(i + di)]
+                        ^ self.perm_y[wrap(j + dj)]
+                        ^ self.perm_z[wrap(k + dk)]];
+                    let (a, b, c) = (di as f32, dj as f32, dk as f32);
+                    let offset = Vec3(u - a, v - b, w - c);
+                    accum += (a * uu + (1. - a) * (1. - uu))
+                        * (b * vv + (1. - b) * (1. - vv))
+                        * (c * ww + (1. - c) * (1. - ww))
+                        * dot(gradient, &offset);
+                }
+            }
+        }
+        accum.clamp(-1., 1.)
+    }
+
+    /// Return the turbulence at the point `p`, between 0 and 1.
+    ///
+    /// The turbulence sums the magnitude of the noise over `octaves`
+    /// octaves, each of double the frequency and half the weight of the
+    /// previous one, normalized by the total weight.
+    ///
+    /// ```
+    /// # use raytracer::perlin::Perlin;
+    /// # use rand::rngs::StdRng;
+    /// # use rand::SeedableRng;
+    /// # use raytracer::vec3::Vec3;
+    /// let perlin = Perlin::new(&mut StdRng::seed_from_u64(0));
+    /// let turbulence = perlin.turbulence(&Vec3(0.3, 1.7, -2.2), 7);
+    /// assert!((0. ..=1.).contains(&turbulence));
+    /// ```
+    pub fn turbulence(&self, p: &Vec3, octaves: u32) -> f32 {
+        let mut accum = 0.;
+        let mut total = 0.;
+        let mut p = *p;
+        let mut weight = 1.;
+        for _ in 0..octaves {
+            accum += weight * self.noise(&p).abs();
+            total += weight;
+            weight *= 0.5;
+            p *= 2.;
+        }
+        if total > 0. {
+            accum / total
+        } else {
+            0.
+        }
+    }
+}
+
+// A random permutation of the gradient indices.
+pub(crate) fn permutation(rng: &mut dyn RngCore) -> Vec<usize> {
+    let mut perm: Vec<usize> = (0..POINT_COUNT).collect();
+    perm.shuffle(rng);
+    perm
+}
+
+// The index of a lattice coordinate into the tables.
+pub(crate) fn wrap(coordinate: i64) -> usize {
+    (coordinate & (POINT_COUNT as i64 - 1)) as usize
+}
+
+// Hermite smoothing of an interpolation weight between 0 and 1.
+fn hermite(t: f32) -> f32 {
+    t * t * (3. - 2. * t)
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    // The points of a grid spanning several lattice cells, including
+    // negative coordinates.
+    fn grid() -> impl Iterator<Item = Vec3> {
+        (0..20).flat_map(|i| {
+            (0..20).flat_map(move |j| {
+                (0..20).map(move |k| {
+                    Vec3(
+                        -3.1 + 0.37 * i as f32,
+                        -2.9 + 0.41 * j as f32,
+                        -3.3 + 0.33 * k as f32,
+                    )
+                })
+            })
+        })
+    }
+
+    #[test]
+    // Test that the noise and the turbulence stay within their ranges.
+    fn test_perlin_range() {
+        let perlin = Perlin::new(&mut StdRng::seed_from_u64(0));
+        for p in grid() {
+            let noise = perlin.noise(&p);
+            assert!((-1. ..=1.).contains(&noise), "noise {} at {:?}", noise, p);
+            let turbulence = perlin.turbulence(&p, 7);
+            assert!(
+                (0. ..=1.).contains(&turbulence),
+                "turbulence {} at {:?}",
+                turbulence,
+                p
+            );
+        }
+        assert_eq!(perlin.turbulence(&Vec3(0.5, 0.5, 0.5), 0), 0.);
+    }
+
+    #[test]
+    // Test that the noise has no discontinuity between lattice cells.
+    fn test_perlin_continuity() {
+        let perlin = Perlin::new(&mut StdRng::seed_from_u64(1));
+        let step = 1e-3;
+        // Cross the lattice planes at -1, 0, 1 and at the wrap of the tables.
+        for &edge in &[-1., 0., 1., POINT_COUNT as f32] {
+            for p in grid().step_by(97) {
+                for &axis in &[Vec3(1., 0., 0.), Vec3(0., 1., 0.), Vec3(0., 0., 1.)] {
+                    // Move the point onto the lattice plane along the axis.
+                    let on_plane = p + (edge - dot(&p, &axis)) * axis;
+                    let before = perlin.noise(&(on_plane - 0.5 * step * axis));
+                    let after = perlin.noise(&(on_plane + 0.5 * step * axis));
+                    assert!(
+                        (before - after).abs() < 1e-2,
+                        "jump from {} to {} at {:?}",
+                        before,
+                        after,
+                        on_plane
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Test that generators with the same seed agree exactly, and others do not.
+    fn test_perlin_seed() {
+        let first = Perlin::new(&mut StdRng::seed_from_u64(42));
+        let second = Perlin::new(&mut StdRng::seed_from_u64(42));
+        let other = Perlin::new(&mut StdRng::seed_from_u64(43));
+        assert!(grid().all(|p| first.noise(&p) == second.noise(&p)));
+        assert!(grid().all(|p| first.turbulence(&p, 5) == second.turbulence(&p, 5)));
+        assert!(grid().any(|p| first.noise(&p) != other.noise(&p)));
+    }
+}
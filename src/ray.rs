@@ -6,21 +6,48 @@
 //!
 //! where `r`, `origin` and `direction` are elements of type
 //! `Vec3` and `t` is a parameter.
+//!
+//! Additionally, a ray carries the point in time at which it was emitted,
+//! such that moving objects can be rendered with motion blur, and the seed
+//! of the random numbers drawn by the media it passes through.
+
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
 
 use crate::vec3::Vec3;
 
+/// The number of rays in a packet, which are intersected with a scene at
+/// once by `Hitable::intersect_packet`, e.g. the primary rays of 2x2 pixels.
+pub const PACKET_SIZE: usize = 4;
+
 /// Ray in 3-dimensional space.
 ///
 /// A ray is given by an origin and a direction.
-#[derive(Debug, Default)]
+///
+/// The reciprocal of the direction and the signs of its components are
+/// computed once when the ray is created, since bounding box tests need
+/// them for every box a ray is tested against.
+#[derive(Debug, Clone)]
 pub struct Ray {
     origin: Vec3,
     direction: Vec3,
+    time: f32,
+    medium_seed: u64,
+    inv_direction: Vec3,
+    sign: [bool; 3],
+}
+
+impl Default for Ray {
+    fn default() -> Ray {
+        Ray::new(Vec3::default(), Vec3::default())
+    }
 }
 
 impl Ray {
     /// Create a ray by specifying `origin` and `direction`.
     ///
+    /// The ray is emitted at time zero.
+    ///
     /// ```
     /// # use raytracer::vec3::Vec3;
     /// # use raytracer::ray::Ray;
@@ -29,7 +56,34 @@ impl Ray {
     /// let ray = Ray::new(origin, direction);
     /// ```
     pub fn new(origin: Vec3, direction: Vec3) -> Ray {
-        Ray { origin, direction }
+        Ray::new_with_time(origin, direction, 0.)
+    }
+
+    /// Create a ray by specifying `origin`, `direction` and the `time` at which
+    /// it is emitted.
+    ///
+    /// ```
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::ray::Ray;
+    /// let ray = Ray::new_with_time(Vec3(0., 0., 0.), Vec3(1., 3., 0.), 0.5);
+    /// assert_eq!(ray.time(), 0.5);
+    /// assert_eq!(Ray::new(Vec3(0., 0., 0.), Vec3(1., 3., 0.)).time(), 0.);
+    /// ```
+    pub fn new_with_time(origin: Vec3, direction: Vec3, time: f32) -> Ray {
+        // Zero components give infinities of the sign of the zero.
+        let inv_direction = Vec3(1. / direction.x(), 1. / direction.y(), 1. / direction.z());
+        Ray {
+            origin,
+            direction,
+            time,
+            medium_seed: 0,
+            inv_direction,
+            sign: [
+                inv_direction.x().is_sign_negative(),
+                inv_direction.y().is_sign_negative(),
+                inv_direction.z().is_sign_negative(),
+            ],
+        }
     }
 
     /// Access the origin of the ray.
@@ -40,7 +94,7 @@ impl Ray {
     /// let origin = Vec3(0., 0., 0.);
     /// let direction = Vec3(1., 3., 0.);
     /// let ray = Ray::new(origin, direction);
-    /// assert_eq!(ray.origin().0, 0.);
+    /// assert_eq!(ray.origin().x(), 0.);
     /// ```
     pub fn origin(&self) -> &Vec3 {
         &self.origin
@@ -60,6 +114,87 @@ impl Ray {
         &self.direction
     }
 
+    /// Access the componentwise reciprocal of the direction of the ray.
+    ///
+    /// Components of the direction which are zero have an infinite
+    /// reciprocal, whose sign is that of the zero.
+    ///
+    /// ```
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::ray::Ray;
+    /// let ray = Ray::new(Vec3(0., 0., 0.), Vec3(2., -0., 0.));
+    /// assert_eq!(ray.inv_direction(), &Vec3(0.5, f32::NEG_INFINITY, f32::INFINITY));
+    /// ```
+    pub fn inv_direction(&self) -> &Vec3 {
+        &self.inv_direction
+    }
+
+    /// Access whether the components of the direction of the ray are
+    /// negative, counting a negative zero as negative.
+    ///
+    /// ```
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::ray::Ray;
+    /// let ray = Ray::new(Vec3(0., 0., 0.), Vec3(2., -0., -1.));
+    /// assert_eq!(ray.sign(), &[false, true, true]);
+    /// ```
+    pub fn sign(&self) -> &[bool; 3] {
+        &self.sign
+    }
+
+    /// Access the time at which the ray was emitted.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Give the ray the seed of the random numbers drawn by the media it
+    /// passes through, see `medium_rng`.
+    ///
+    /// Rays are created with the seed 0. The renderer draws a new seed for
+    /// every ray it traces from the generator of the pixel.
+    ///
+    /// ```
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::ray::Ray;
+    /// let ray = Ray::new(Vec3(0., 0., 0.), Vec3(1., 3., 0.)).with_medium_seed(7);
+    /// assert_eq!(ray.medium_seed(), 7);
+    /// ```
+    pub fn with_medium_seed(mut self, seed: u64) -> Ray {
+        self.medium_seed = seed;
+        self
+    }
+
+    /// Access the seed of the random numbers drawn by the media the ray
+    /// passes through.
+    pub fn medium_seed(&self) -> u64 {
+        self.medium_seed
+    }
+
+    /// Return the generator from which a medium entered by the ray at the
+    /// parameter `t_entry` draws where the ray is scattered.
+    ///
+    /// Media sample the distance a ray travels in them while the ray is
+    /// intersected, before the closest hit is known. The generator only
+    /// depends on the seed of the ray and the parameter, such that
+    /// intersecting is deterministic and renders are reproducible, while
+    /// media entered at different parameters draw different numbers.
+    ///
+    /// ```
+    /// # use raytracer::vec3::Vec3;
+    /// # use raytracer::ray::Ray;
+    /// # use rand::Rng;
+    /// let ray = Ray::new(Vec3(0., 0., 0.), Vec3(1., 3., 0.)).with_medium_seed(7);
+    /// let u: f32 = ray.medium_rng(2.).gen();
+    /// assert_eq!(ray.medium_rng(2.).gen::<f32>(), u);
+    /// assert_ne!(ray.medium_rng(3.).gen::<f32>(), u);
+    /// ```
+    pub fn medium_rng(&self, t_entry: f32) -> SmallRng {
+        // Spread the bits of the parameter over the seed, such that nearby
+        // parameters give unrelated generators.
+        let salt = u64::from(t_entry.to_bits()).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        SmallRng::seed_from_u64(self.medium_seed ^ salt)
+    }
+
     /// Evaluate the ray coordinates at a parameter point t.
     ///
     /// ```
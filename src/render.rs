@@ -0,0 +1,2414 @@
+//! Shading of rays traced through a scene.
+//!
+//! The color seen along a ray is the light emitted at the closest hit point
+//! plus the light arriving along the scattered ray, attenuated by the
+//! material. Rays that leave the scene see a simple gradient sky. Camera
+//! rays hitting a shadow catcher see the sky darkened by the shadows on it.
+//! Paths keep track of the transparent media they have entered, such that
+//! nested dielectrics refract at the ratio of their refractive indices, and
+//! light is absorbed along every segment inside a tinted medium.
+//!
+//! In a scene which knows its lights, see `objects::scene::Scene`, the light
+//! reaching diffuse surfaces is also sampled directly, and combined with the
+//! light found by the scattered rays by multiple importance sampling.
+//!
+//! Images are rendered in square tiles, which are traced in parallel.
+//! `render_with_stats` additionally reports the work done, see `stats`, and
+//! `render_with_progress` reports the progress while rendering.
+//! `autofocus` finds the focus distance of a camera from the scene.
+//! With the `stats` feature, the cost of traversing the scene can be
+//! rendered as a heatmap instead of the shaded image.
+
+use crate::camera::Camera;
+use crate::camera::CameraModel;
+use crate::film::FilmBuffer;
+use crate::hit_record::HitRecord;
+use crate::lights::Lights;
+use crate::materials::ScatterRecord;
+use crate::objects::Hitable;
+use crate::pdf::Pdf;
+use crate::ray::Ray;
+use crate::ray::PACKET_SIZE;
+use crate::stats;
+use crate::stats::RenderStats;
+use crate::vec3::dot;
+use crate::vec3::unit_vector;
+use crate::vec3::Vec3;
+use rand::prelude::*;
+use rand::rngs::SmallRng;
+use rayon::prelude::*;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Maximal number of times a ray is scattered before it is absorbed.
+pub const MAX_DEPTH: i32 = 50;
+
+/// Return the color of the background for a ray that does not hit anything.
+///
+/// ```
+/// # use raytracer::render::background;
+/// # use raytracer::ray::Ray;
+/// # use raytracer::vec3::Vec3;
+/// let up = Ray::new(Vec3(0., 0., 0.), Vec3(0., 1., 0.));
+/// assert_eq!(background(&up), Vec3(0.5, 0.7, 1.0));
+/// ```
+pub fn background(r: &Ray) -> Vec3 {
+    let unit_direction = unit_vector(r.direction());
+    let t = 0.5 * (unit_direction.y() + 1.);
+    (1. - t) * Vec3(1., 1., 1.) + t * Vec3(0.5, 0.7, 1.0)
+}
+
+/// Return the color seen along the ray `r` in the scene `world`.
+///
+/// `depth` is the number of times the ray has already been scattered, and
+/// the random decisions of the materials are drawn from `rng`. So is the
+/// seed of the ray, from which media draw where they scatter it, see
+/// `Ray::medium_rng`. The rays scattered along the path take the following
+/// seeds.
+///
+/// The path is followed in a loop rather than by recursion. The light
+/// emitted along the path is weighted by the throughput, the product of the
+/// attenuations of all previous bounces, and the path ends when it leaves
+/// the scene, is absorbed, or has been scattered `MAX_DEPTH` times.
+///
+/// If `world` knows its lights, see `Hitable::lights`, a light is sampled at
+/// every opaque surface scattering diffusely, and a shadow ray traced
+/// towards it. The light seen along the shadow ray and along the scattered
+/// ray are weighted by the power heuristic, using the probability with
+/// which the light tree selects the light.
+pub fn color(r: &Ray, world: &dyn Hitable, depth: i32, rng: &mut dyn RngCore) -> Vec3 {
+    color_with_roulette(r, world, depth, None, rng)
+}
+
+/// Terminate paths at random once their throughput is low.
+///
+/// From `min_depth` bounces on, a path continues with the probability of
+/// the largest component of its throughput, and the throughput of the paths
+/// which continue is divided by this probability. The image converges to
+/// the same result, but less time is spent on paths which contribute little.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RussianRoulette {
+    /// The number of bounces before paths can be terminated.
+    pub min_depth: i32,
+}
+
+impl Default for RussianRoulette {
+    fn default() -> RussianRoulette {
+        RussianRoulette { min_depth: 3 }
+    }
+}
+
+/// Return the color seen along the ray `r` in the scene `world` like
+/// `color`, terminating the path with Russian `roulette` if given.
+///
+/// ```
+/// # use raytracer::objects::HitableList;
+/// # use raytracer::ray::Ray;
+/// # use raytracer::render::{background, color_with_roulette, RussianRoulette};
+/// # use raytracer::vec3::Vec3;
+/// let world = HitableList::default();
+/// let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0., 1., 0.));
+/// let roulette = Some(RussianRoulette::default());
+/// let col = color_with_roulette(&ray, &world, 0, roulette, &mut rand::thread_rng());
+/// assert_eq!(col, background(&ray));
+/// ```
+pub fn color_with_roulette(
+    r: &Ray,
+    world: &dyn Hitable,
+    depth: i32,
+    roulette: Option<RussianRoulette>,
+    rng: &mut dyn RngCore,
+) -> Vec3 {
+    let r = r.clone().with_medium_seed(rng.gen());
+    let hit = world.intersect(&r, 0.001, f32::MAX);
+    shade(&r, hit, world, depth, roulette, 0., rng)
+}
+
+// Return the color seen along the ray `r`, whose closest hit in the scene
+// `world` is `hit`, like `color_with_roulette`. Paths are terminated once
+// their throughput falls below `min_throughput`, see `RenderSettings`.
+fn shade<'a>(
+    r: &Ray,
+    hit: Option<HitRecord<'a>>,
+    world: &'a dyn Hitable,
+    depth: i32,
+    roulette: Option<RussianRoulette>,
+    min_throughput: f32,
+    rng: &mut dyn RngCore,
+) -> Vec3 {
+    let mut col = Vec3(0., 0., 0.);
+    let mut throughput = Vec3(1., 1., 1.);
+    let mut depth = depth;
+    let mut scattered_ray;
+    let mut ray = r;
+    let mut hit = hit;
+    let mut media = Media::new();
+    let lights = world.lights().filter(|lights| !lights.is_empty());
+    // The point the ray was scattered from diffusely and the density of its
+    // direction, if it was, to weight the light it hits.
+    let mut diffuse = None;
+    loop {
+        let record = match hit {
+            Some(record) => record,
+            None => return col + throughput * background(ray),
+        };
+        if depth == 0 && record.material.is_shadow_catcher() {
+            return catch_shadow(ray, &record, world, rng);
+        }
+        // The segment up to the hit runs through the innermost medium.
+        throughput *= media.transmittance(record.parameter * ray.direction().length());
+        let emitted = record.material.emitted(&record);
+        col += throughput
+            * match (lights, diffuse) {
+                (Some(lights), Some((point, pdf))) if emitted != Vec3(0., 0., 0.) => {
+                    emitted * power_heuristic(pdf, lights.pdf(&point, &record))
+                }
+                _ => emitted,
+            };
+        diffuse = None;
+        let outside = media.outside_index(&record);
+        let scattered = match lights {
+            // Transparent materials scatter depending on the media outside.
+            Some(lights) if depth < MAX_DEPTH && record.material.refractive_index().is_none() => {
+                match record.material.scatter_with_pdf(ray, &record, rng) {
+                    Some(ScatterRecord::Diffuse { attenuation, pdf }) => {
+                        let direct =
+                            direct_light(ray, &record, lights, pdf.as_ref(), &media, world, rng);
+                        col += throughput * attenuation * direct;
+                        let direction = pdf.generate(rng);
+                        diffuse = Some((record.point_at_parameter, pdf.value(&direction)));
+                        let point = record.point_at_parameter;
+                        Some((
+                            Ray::new_with_time(point, direction, ray.time()),
+                            attenuation,
+                        ))
+                    }
+                    Some(ScatterRecord::Specular {
+                        ray: scattered,
+                        attenuation,
+                    }) => Some((scattered, attenuation)),
+                    None => None,
+                }
+            }
+            _ => record.material.scatter_in(ray, &record, outside, rng),
+        };
+        match scattered {
+            Some((scattered, attenuation)) if depth < MAX_DEPTH => {
+                stats::count_scattered_ray();
+                media.update(&record, ray, &scattered);
+                throughput *= attenuation;
+                scattered_ray = scattered.with_medium_seed(next_medium_seed(ray));
+                ray = &scattered_ray;
+                depth += 1;
+            }
+            _ => return col,
+        }
+        let largest = throughput.x().max(throughput.y()).max(throughput.z());
+        if largest < min_throughput {
+            // With roulette, the paths which continue are weighted up to the
+            // threshold, which keeps the expected color.
+            if roulette.is_none() {
+                return col;
+            }
+            let p = largest / min_throughput;
+            if rng.gen::<f32>() >= p {
+                return col;
+            }
+            throughput /= p;
+        } else if roulette.is_some_and(|roulette| depth >= roulette.min_depth) {
+            let p = largest.min(1.);
+            if rng.gen::<f32>() >= p {
+                return col;
+            }
+            throughput /= p;
+        }
+        hit = world.intersect(ray, 0.001, f32::MAX);
+    }
+}
+
+// Return the light arriving at the hit `record` of the `ray` straight from
+// one of the `lights`, through the innermost of the `media`, times the
+// density with which the material scatters it over the density with which
+// it was drawn. The light is weighted against finding it by drawing
+// directions from the scattering density `pdf`.
+fn direct_light(
+    ray: &Ray,
+    record: &HitRecord,
+    lights: &Lights,
+    pdf: &dyn Pdf,
+    media: &Media,
+    world: &dyn Hitable,
+    rng: &mut dyn RngCore,
+) -> Vec3 {
+    let black = Vec3(0., 0., 0.);
+    let sample = match lights.sample(&record.point_at_parameter, rng) {
+        Some(sample) => sample,
+        None => return black,
+    };
+    let shadow = Ray::new_with_time(record.point_at_parameter, sample.direction, ray.time())
+        .with_medium_seed(next_medium_seed(ray));
+    let scattering = record.material.scattering_pdf(ray, record, &shadow);
+    if scattering <= 0. {
+        return black;
+    }
+    // The light counts if nothing is in between.
+    let hit = match world.intersect(&shadow, 0.001, f32::MAX) {
+        Some(hit) if lights.find(&hit) == Some(sample.index) => hit,
+        _ => return black,
+    };
+    let weight = power_heuristic(sample.pdf, pdf.value(&sample.direction));
+    media.transmittance(hit.parameter)
+        * hit.material.emitted(&hit)
+        * (scattering * weight / sample.pdf)
+}
+
+// The weight of a sample drawn with the density `pdf` against another
+// strategy drawing it with the density `other`, by the power heuristic.
+fn power_heuristic(pdf: f32, other: f32) -> f32 {
+    let (pdf, other) = (pdf * pdf, other * other);
+    if pdf + other > 0. {
+        pdf / (pdf + other)
+    } else {
+        0.
+    }
+}
+
+// The seed of the media along the ray scattered from `ray`. Generators are
+// seeded through a hash, such that consecutive seeds give unrelated numbers.
+fn next_medium_seed(ray: &Ray) -> u64 {
+    ray.medium_seed().wrapping_add(1)
+}
+
+// The number of nested media tracked along a path. Media entered beyond are
+// ignored.
+const MAX_MEDIA: usize = 8;
+
+// The transparent media entered by a path, innermost last, kept without
+// allocating. Each medium is the address of its material, which tells the
+// media apart, its refractive index and its absorption coefficients.
+struct Media {
+    entries: [(*const (), f32, Vec3); MAX_MEDIA],
+    len: usize,
+}
+
+impl Media {
+    fn new() -> Media {
+        Media {
+            entries: [(std::ptr::null(), 1., Vec3(0., 0., 0.)); MAX_MEDIA],
+            len: 0,
+        }
+    }
+
+    // Return the fraction of the light passing a `distance` through the
+    // innermost medium, following the Beer–Lambert law.
+    fn transmittance(&self, distance: f32) -> Vec3 {
+        match self.entries[..self.len].last() {
+            Some(&(_, _, absorption)) if absorption != Vec3(0., 0., 0.) => Vec3(
+                (-absorption.x() * distance).exp(),
+                (-absorption.y() * distance).exp(),
+                (-absorption.z() * distance).exp(),
+            ),
+            _ => Vec3(1., 1., 1.),
+        }
+    }
+
+    // Return the refractive index outside of the surface hit in `record`:
+    // that of the innermost medium other than the material of the surface,
+    // or of vacuum if there is none.
+    fn outside_index(&self, record: &HitRecord) -> f32 {
+        let address = material_address(record);
+        self.entries[..self.len]
+            .iter()
+            .rev()
+            .find(|(medium, _, _)| *medium != address)
+            .map_or(1., |&(_, index, _)| index)
+    }
+
+    // Update the media after the `ray` has been scattered into `scattered`
+    // at the hit `record`: a ray passing through the front face of a
+    // transparent material enters it, and one passing through a back face
+    // leaves it.
+    fn update(&mut self, record: &HitRecord, ray: &Ray, scattered: &Ray) {
+        let index = match record.material.refractive_index() {
+            Some(index) => index,
+            None => return,
+        };
+        let normal = &record.normal;
+        if dot(ray.direction(), normal) * dot(scattered.direction(), normal) <= 0. {
+            return;
+        }
+        let address = material_address(record);
+        if record.front_face {
+            if self.len < MAX_MEDIA {
+                self.entries[self.len] = (address, index, record.material.absorption());
+                self.len += 1;
+            }
+        } else if let Some(position) = self.entries[..self.len]
+            .iter()
+            .rposition(|(medium, _, _)| *medium == address)
+        {
+            self.entries.copy_within(position + 1..self.len, position);
+            self.len -= 1;
+        }
+    }
+}
+
+// The address of the material of the hit `record`.
+fn material_address(record: &HitRecord) -> *const () {
+    record.material as *const _ as *const ()
+}
+
+// Return the color seen along the camera ray `r` at its hit `record` with a
+// shadow catcher: the background behind it if a diffuse ray scattered from
+// the hit point is not blocked, and black otherwise. Other shadow catchers
+// do not block the light, such that they do not shadow each other.
+fn catch_shadow(r: &Ray, record: &HitRecord, world: &dyn Hitable, rng: &mut dyn RngCore) -> Vec3 {
+    let probe = match record.material.scatter(r, record, rng) {
+        Some((probe, _)) => probe.with_medium_seed(next_medium_seed(r)),
+        None => return background(r),
+    };
+    stats::count_scattered_ray();
+    match world.intersect(&probe, 0.001, f32::MAX) {
+        Some(hit) if !hit.material.is_shadow_catcher() => Vec3(0., 0., 0.),
+        _ => background(r),
+    }
+}
+
+/// The settings of a render.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderSettings {
+    /// The width of the image in pixels.
+    pub width: usize,
+    /// The height of the image in pixels.
+    pub height: usize,
+    /// The number of samples traced per pixel.
+    pub samples: usize,
+    /// The edge length in pixels of the square tiles the image is split into.
+    /// Each tile is rendered by a single worker.
+    pub tile_size: usize,
+    /// The order in which the tiles are started.
+    pub tile_order: TileOrder,
+    /// The number of threads rendering, or `None` to use all cores. A pool
+    /// of the given size is created for every render.
+    pub threads: Option<usize>,
+    /// The seed of the random number generators, of which every pixel has
+    /// its own, such that an image does not depend on how it is split.
+    pub seed: u64,
+    /// How the work is split between threads.
+    pub parallelism: Parallelism,
+    /// What is rendered.
+    pub mode: RenderMode,
+    /// Terminate paths at random once their throughput is low, if given.
+    pub roulette: Option<RussianRoulette>,
+    /// Terminate paths once the largest component of their throughput falls
+    /// below this threshold, e.g. `1e-4`, such that they contribute no more
+    /// light. This darkens the image slightly. With `roulette`, such paths
+    /// instead continue with the probability of the ratio of their largest
+    /// component to the threshold, whatever their depth, which keeps the
+    /// image unbiased. Zero, the default, never terminates paths.
+    pub min_throughput: f32,
+    /// Intersect the primary rays of 2x2 pixels with the scene together, see
+    /// `Hitable::intersect_packet`, and the scattered rays one by one. The
+    /// image is the same as without packets. Only used to render in tiles.
+    pub packets: bool,
+}
+
+/// What is rendered for every pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RenderMode {
+    /// The color seen along the rays, see `color`.
+    #[default]
+    Shaded,
+    /// The cost of intersecting the primary rays with the scene, i.e. the
+    /// number of visited BVH nodes and tested objects, in false colors from
+    /// blue for cheap to red for expensive pixels. Costs from the 99th
+    /// percentile up are red, such that a few outliers do not wash out the
+    /// others. The `parallelism` is ignored.
+    #[cfg(feature = "stats")]
+    TraversalHeatmap,
+}
+
+/// The order in which the tiles of an image are started.
+///
+/// The image does not depend on the order, but a preview showing the tiles
+/// as they are done fills the interesting regions first.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TileOrder {
+    /// Row by row from the top, every row from the left.
+    #[default]
+    Scanline,
+    /// Around the center of the image, in rings of growing distance.
+    Spiral,
+    /// Along a Hilbert curve, which keeps consecutive tiles next to each
+    /// other, such that they share more of the scene in the caches.
+    Hilbert,
+}
+
+/// How the work of a render is split between threads.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Parallelism {
+    /// Render the tiles of the image in parallel, which suits images with
+    /// many more pixels than threads.
+    #[default]
+    Tiles,
+    /// Split the samples of every pixel into `chunks`, render the whole
+    /// image once per chunk in parallel, and average the chunks. This keeps
+    /// all threads busy on small images with many samples, e.g. previews.
+    ///
+    /// The image differs from the one rendered in tiles, as the chunks
+    /// draw different random numbers, but converges to the same result.
+    Samples { chunks: usize },
+}
+
+impl Default for RenderSettings {
+    fn default() -> RenderSettings {
+        RenderSettings {
+            width: 1200,
+            height: 800,
+            samples: 150,
+            tile_size: 32,
+            tile_order: TileOrder::Scanline,
+            threads: None,
+            seed: 0,
+            parallelism: Parallelism::Tiles,
+            mode: RenderMode::Shaded,
+            roulette: None,
+            min_throughput: 0.,
+            packets: false,
+        }
+    }
+}
+
+/// Return the focus distance for `camera` that puts the object of `world`
+/// seen at the point `(x_frac, y_frac)` of the image in focus, to be set with
+/// `Camera::with_focus_dist`.
+///
+/// The ray from the center of the lens through that point is intersected
+/// with the scene, and the depth of the closest hit along the view direction
+/// is returned, which is its distance at the center of the image. If the ray
+/// misses the scene, the distance to the point the camera looks at is
+/// returned.
+///
+/// ```
+/// # use raytracer::camera::Camera;
+/// # use raytracer::materials::Lambertian;
+/// # use raytracer::objects::sphere::Sphere;
+/// # use raytracer::render::autofocus;
+/// # use raytracer::vec3::Vec3;
+/// # use std::sync::Arc;
+/// let sphere = Sphere::new(Vec3(0., 0., -6.), 1., Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))));
+/// let camera = Camera::new(Vec3(0., 0., 0.), Vec3(0., 0., -1.), Vec3(0., 1., 0.), 40., 1.5, 0.2, 1.);
+/// let distance = autofocus(&sphere, &camera, 0.5, 0.5);
+/// assert!((distance - 5.).abs() < 1e-4);
+/// let camera = camera.with_focus_dist(distance);
+/// // Past the sphere, the camera focuses on the point it looks at.
+/// assert_eq!(autofocus(&sphere, &camera, 0.5, 1.), 1.);
+/// ```
+pub fn autofocus(world: &dyn Hitable, camera: &Camera, x_frac: f32, y_frac: f32) -> f32 {
+    // The chief ray reaches the plane in focus at the parameter 1, so the
+    // parameter of the hit is its depth in units of the focus distance.
+    match world.intersect(&camera.chief_ray(x_frac, y_frac), 0.001, f32::MAX) {
+        Some(hit) => hit.parameter * camera.focus_dist(),
+        None => camera.look_at_dist(),
+    }
+}
+
+/// Render the scene `world` seen by `camera`.
+///
+/// The image is returned as 8-bit RGB values, row by row from the top, with
+/// a gamma of 2. It is identical for the same `seed` and `parallelism`
+/// whatever the tile size, the tile order and the number of threads.
+///
+/// # Panics
+///
+/// If the threads of the render cannot be created.
+///
+/// ```
+/// # use raytracer::camera::Camera;
+/// # use raytracer::objects::HitableList;
+/// # use raytracer::render::{render, RenderSettings};
+/// # use raytracer::vec3::Vec3;
+/// let world = HitableList::default();
+/// let camera = Camera::new(
+///     Vec3(0., 0., 0.),
+///     Vec3(0., 0., -1.),
+///     Vec3(0., 1., 0.),
+///     90.,
+///     2.,
+///     0.,
+///     1.,
+/// );
+/// let settings = RenderSettings {
+///     width: 20,
+///     height: 10,
+///     samples: 4,
+///     ..RenderSettings::default()
+/// };
+/// let image = render(&world, &camera, &settings);
+/// assert_eq!(image.len(), 20 * 10 * 3);
+/// ```
+pub fn render(world: &dyn Hitable, camera: &dyn CameraModel, settings: &RenderSettings) -> Vec<u8> {
+    render_with_stats(world, camera, settings).0
+}
+
+/// Render the scene `world` seen by `camera` like `render`, and return the
+/// statistics of the render along with the image.
+///
+/// ```
+/// # use raytracer::camera::Camera;
+/// # use raytracer::objects::HitableList;
+/// # use raytracer::render::{render_with_stats, RenderSettings};
+/// # use raytracer::vec3::Vec3;
+/// # let world = HitableList::default();
+/// # let camera = Camera::new(
+/// #     Vec3(0., 0., 0.),
+/// #     Vec3(0., 0., -1.),
+/// #     Vec3(0., 1., 0.),
+/// #     90.,
+/// #     2.,
+/// #     0.,
+/// #     1.,
+/// # );
+/// let settings = RenderSettings {
+///     width: 20,
+///     height: 10,
+///     samples: 4,
+///     ..RenderSettings::default()
+/// };
+/// let (image, stats) = render_with_stats(&world, &camera, &settings);
+/// assert_eq!(stats.primary_rays, 20 * 10 * 4);
+/// ```
+pub fn render_with_stats(
+    world: &dyn Hitable,
+    camera: &dyn CameraModel,
+    settings: &RenderSettings,
+) -> (Vec<u8>, RenderStats) {
+    let mut image = vec![0; settings.width * settings.height * Rgb8::CHANNELS];
+    let stats = render_counted::<Rgb8>(world, camera, settings, &mut image, &AtomicUsize::new(0));
+    (image, stats)
+}
+
+/// Render the scene `world` seen by `camera` like `render_with_stats`, into
+/// the linear colors of the pixels of `film`, and return the statistics.
+///
+/// The dimensions of the image are those of the film rather than those of
+/// the `settings`. Tone-mapping the film with `FilmBuffer::to_rgb8` gives
+/// the image of `render`. A `RenderMode::TraversalHeatmap` is written as the
+/// colors that tone-map to its false colors.
+///
+/// # Panics
+///
+/// If the threads of the render cannot be created.
+///
+/// ```
+/// # use raytracer::camera::Camera;
+/// # use raytracer::film::FilmBuffer;
+/// # use raytracer::objects::HitableList;
+/// # use raytracer::render::{render, render_into, RenderSettings};
+/// # use raytracer::vec3::Vec3;
+/// # let world = HitableList::default();
+/// # let camera = Camera::new(
+/// #     Vec3(0., 0., 0.),
+/// #     Vec3(0., 0., -1.),
+/// #     Vec3(0., 1., 0.),
+/// #     90.,
+/// #     2.,
+/// #     0.,
+/// #     1.,
+/// # );
+/// let settings = RenderSettings {
+///     width: 20,
+///     height: 10,
+///     samples: 4,
+///     ..RenderSettings::default()
+/// };
+/// let mut film = FilmBuffer::new(20, 10);
+/// let stats = render_into(&mut film, &world, &camera, &settings);
+/// assert_eq!(stats.primary_rays, 20 * 10 * 4);
+/// assert_eq!(film.to_rgb8(), render(&world, &camera, &settings));
+/// ```
+pub fn render_into(
+    film: &mut FilmBuffer,
+    world: &dyn Hitable,
+    camera: &dyn CameraModel,
+    settings: &RenderSettings,
+) -> RenderStats {
+    let settings = RenderSettings {
+        width: film.width(),
+        height: film.height(),
+        ..settings.clone()
+    };
+    render_counted::<Linear>(
+        world,
+        camera,
+        &settings,
+        film.pixels_mut(),
+        &AtomicUsize::new(0),
+    )
+}
+
+/// The progress of a render, in units of work such as tiles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// The number of units done.
+    pub completed: usize,
+    /// The number of units of the whole render.
+    pub total: usize,
+    /// The time since the render started.
+    pub elapsed: Duration,
+    /// The estimated time until the render is done, assuming that the
+    /// remaining units take as long as the completed ones on average, or
+    /// `None` before the first unit is done.
+    pub eta: Option<Duration>,
+}
+
+impl Progress {
+    fn new(completed: usize, total: usize, elapsed: Duration) -> Progress {
+        let eta =
+            (completed > 0).then(|| elapsed.mul_f64((total - completed) as f64 / completed as f64));
+        Progress {
+            completed,
+            total,
+            elapsed,
+            eta,
+        }
+    }
+
+    /// The part of the render which is done, from 0 to 1.
+    ///
+    /// ```
+    /// # use raytracer::render::Progress;
+    /// # use std::time::Duration;
+    /// let progress = Progress {
+    ///     completed: 6,
+    ///     total: 24,
+    ///     elapsed: Duration::from_secs(3),
+    ///     eta: Some(Duration::from_secs(9)),
+    /// };
+    /// assert_eq!(progress.fraction(), 0.25);
+    /// ```
+    pub fn fraction(&self) -> f64 {
+        if self.total > 0 {
+            self.completed as f64 / self.total as f64
+        } else {
+            1.
+        }
+    }
+}
+
+/// Render the scene `world` seen by `camera` like `render_with_stats`, and
+/// report the progress to `callback` every `interval`.
+///
+/// The workers count the tiles they have done, or the rows when rendering
+/// in chunks of samples or a heatmap, and a thread of its own calls
+/// `callback` with the count. The last call reports the finished render.
+///
+/// ```
+/// # use raytracer::camera::Camera;
+/// # use raytracer::objects::HitableList;
+/// # use raytracer::render::{render_with_progress, RenderSettings};
+/// # use raytracer::vec3::Vec3;
+/// # use std::time::Duration;
+/// # let world = HitableList::default();
+/// # let camera = Camera::new(
+/// #     Vec3(0., 0., 0.),
+/// #     Vec3(0., 0., -1.),
+/// #     Vec3(0., 1., 0.),
+/// #     90.,
+/// #     2.,
+/// #     0.,
+/// #     1.,
+/// # );
+/// let settings = RenderSettings {
+///     width: 20,
+///     height: 10,
+///     samples: 4,
+///     tile_size: 5,
+///     ..RenderSettings::default()
+/// };
+/// let mut last = None;
+/// let interval = Duration::from_millis(100);
+/// render_with_progress(&world, &camera, &settings, interval, |progress| {
+///     last = Some(progress);
+/// });
+/// assert_eq!(last.unwrap().completed, 8);
+/// assert_eq!(last.unwrap().fraction(), 1.);
+/// ```
+pub fn render_with_progress(
+    world: &dyn Hitable,
+    camera: &dyn CameraModel,
+    settings: &RenderSettings,
+    interval: Duration,
+    mut callback: impl FnMut(Progress) + Send,
+) -> (Vec<u8>, RenderStats) {
+    let total = work_units(settings);
+    let completed = AtomicUsize::new(0);
+    let start = Instant::now();
+    let (done, finished) = mpsc::channel::<()>();
+    thread::scope(|scope| {
+        let completed = &completed;
+        scope.spawn(move || {
+            let progress =
+                || Progress::new(completed.load(Ordering::Relaxed), total, start.elapsed());
+            // The sender is dropped once the render is done.
+            while let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(interval) {
+                callback(progress());
+            }
+            callback(progress());
+        });
+        let mut image = vec![0; settings.width * settings.height * Rgb8::CHANNELS];
+        let stats = render_counted::<Rgb8>(world, camera, settings, &mut image, completed);
+        drop(done);
+        (image, stats)
+    })
+}
+
+// The number of units of work of a render, see `render_with_progress`.
+fn work_units(settings: &RenderSettings) -> usize {
+    let tile_size = settings.tile_size.max(1);
+    match (settings.mode, settings.parallelism) {
+        (RenderMode::Shaded, Parallelism::Tiles) => {
+            settings.width.div_ceil(tile_size) * settings.height.div_ceil(tile_size)
+        }
+        (RenderMode::Shaded, Parallelism::Samples { chunks }) => {
+            chunks.clamp(1, settings.samples.max(1)) * settings.height
+        }
+        #[cfg(feature = "stats")]
+        (RenderMode::TraversalHeatmap, _) => settings.height,
+    }
+}
+
+// The values a pixel of an image being rendered is stored as, `CHANNELS`
+// values of the type `Value`.
+trait PixelFormat {
+    type Value: Send;
+    const CHANNELS: usize;
+
+    // Store the linear `color` into the values of a pixel.
+    fn store(color: Vec3, pixel: &mut [Self::Value]);
+}
+
+// 8-bit RGB values with a gamma of 2, see `to_rgb`.
+struct Rgb8;
+
+impl PixelFormat for Rgb8 {
+    type Value = u8;
+    const CHANNELS: usize = 3;
+
+    fn store(color: Vec3, pixel: &mut [u8]) {
+        pixel.copy_from_slice(&to_rgb(color));
+    }
+}
+
+// The linear colors of a `FilmBuffer`.
+struct Linear;
+
+impl PixelFormat for Linear {
+    type Value = Vec3;
+    const CHANNELS: usize = 1;
+
+    fn store(color: Vec3, pixel: &mut [Vec3]) {
+        pixel[0] = color;
+    }
+}
+
+// Render the scene into the pixels of `image`, row by row from the top,
+// counting the units of work done in `completed`.
+fn render_counted<F: PixelFormat>(
+    world: &dyn Hitable,
+    camera: &dyn CameraModel,
+    settings: &RenderSettings,
+    image: &mut [F::Value],
+    completed: &AtomicUsize,
+) -> RenderStats {
+    let start = Instant::now();
+    let mut run = || match (settings.mode, settings.parallelism) {
+        (RenderMode::Shaded, Parallelism::Tiles) => {
+            render_tiles::<F>(world, camera, settings, image, completed)
+        }
+        (RenderMode::Shaded, Parallelism::Samples { chunks }) => {
+            render_chunks::<F>(world, camera, settings, chunks, image, completed)
+        }
+        #[cfg(feature = "stats")]
+        (RenderMode::TraversalHeatmap, _) => {
+            render_heatmap::<F>(world, camera, settings, image, completed)
+        }
+    };
+    let stats = match settings.threads {
+        Some(threads) => rayon::ThreadPoolBuilder::new()
+            .num_threads(threads.max(1))
+            .build()
+            .expect("failed to create the threads of the render")
+            .install(run),
+        None => run(),
+    };
+    RenderStats {
+        time: start.elapsed(),
+        ..stats
+    }
+}
+
+// Render the tiles of the image in parallel, starting them in the order of
+// `settings.tile_order`.
+fn render_tiles<F: PixelFormat>(
+    world: &dyn Hitable,
+    camera: &dyn CameraModel,
+    settings: &RenderSettings,
+    image: &mut [F::Value],
+    completed: &AtomicUsize,
+) -> RenderStats {
+    let (width, height) = (settings.width, settings.height);
+    let tile_size = settings.tile_size.max(1);
+    let columns = width.div_ceil(tile_size);
+    // The image is split into the rows of every tile, which the workers
+    // write in place.
+    let mut tiles: Vec<Option<Vec<&mut [F::Value]>>> = (0..columns * height.div_ceil(tile_size))
+        .map(|_| Some(Vec::with_capacity(tile_size)))
+        .collect();
+    for (y, row) in image.chunks_mut((width * F::CHANNELS).max(1)).enumerate() {
+        for (column, pixels) in row.chunks_mut(tile_size * F::CHANNELS).enumerate() {
+            if let Some(rows) = &mut tiles[y / tile_size * columns + column] {
+                rows.push(pixels);
+            }
+        }
+    }
+    let ordered: Vec<_> = tile_order(settings.tile_order, width, height, tile_size)
+        .into_iter()
+        .map(|(x0, y0)| {
+            let rows = tiles[y0 / tile_size * columns + x0 / tile_size].take();
+            ((x0, y0), rows.unwrap())
+        })
+        .collect();
+    // The workers take the tiles from the ordered iterator one at a time.
+    ordered
+        .into_iter()
+        .par_bridge()
+        .map(|(corner, rows)| {
+            let stats = render_tile::<F>(world, camera, settings, corner, rows);
+            completed.fetch_add(1, Ordering::Relaxed);
+            stats
+        })
+        .reduce(RenderStats::default, |a, b| a + b)
+}
+
+// The top left corners of the tiles of the given size covering an image,
+// in the given order.
+fn tile_order(
+    order: TileOrder,
+    width: usize,
+    height: usize,
+    tile_size: usize,
+) -> Vec<(usize, usize)> {
+    let (columns, rows) = (width.div_ceil(tile_size), height.div_ceil(tile_size));
+    let mut tiles: Vec<(usize, usize)> = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .collect();
+    match order {
+        TileOrder::Scanline => {}
+        TileOrder::Spiral => {
+            // Sort by the ring around the central tile, then by the angle
+            // within the ring.
+            let center = ((columns as f32 - 1.) / 2., (rows as f32 - 1.) / 2.);
+            let key = |&(column, row): &(usize, usize)| {
+                let (dx, dy) = (column as f32 - center.0, row as f32 - center.1);
+                (dx.abs().max(dy.abs()), dy.atan2(dx))
+            };
+            tiles.sort_by(|a, b| {
+                let (a, b) = (key(a), key(b));
+                a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1))
+            });
+        }
+        TileOrder::Hilbert => {
+            let side = columns.max(rows).next_power_of_two();
+            tiles.sort_by_key(|&(column, row)| hilbert_index(side, column, row));
+        }
+    }
+    tiles
+        .into_iter()
+        .map(|(column, row)| (column * tile_size, row * tile_size))
+        .collect()
+}
+
+// The position of the cell `(x, y)` along the Hilbert curve through a square
+// grid of `side` cells, where `side` is a power of two.
+fn hilbert_index(side: usize, x: usize, y: usize) -> usize {
+    let (mut x, mut y) = (x, y);
+    let mut index = 0;
+    let mut s = side / 2;
+    while s > 0 {
+        let rx = (x & s > 0) as usize;
+        let ry = (y & s > 0) as usize;
+        index += s * s * ((3 * rx) ^ ry);
+        // Rotate the quadrant, such that the curve within it starts next to
+        // the end of the previous quadrant.
+        if ry == 0 {
+            if rx == 1 {
+                x = side - 1 - x;
+                y = side - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    index
+}
+
+// Render the whole image once per chunk of the samples in parallel, and
+// average the chunks.
+fn render_chunks<F: PixelFormat>(
+    world: &dyn Hitable,
+    camera: &dyn CameraModel,
+    settings: &RenderSettings,
+    chunks: usize,
+    image: &mut [F::Value],
+    completed: &AtomicUsize,
+) -> RenderStats {
+    let pixels = settings.width * settings.height;
+    let chunks = chunks.clamp(1, settings.samples.max(1));
+    // The sums of the samples, in double precision such that the chunks
+    // can be added without rounding before dividing once.
+    let (sums, stats) = (0..chunks)
+        .into_par_iter()
+        .map(|chunk| {
+            // The samples are spread as evenly as possible over the chunks.
+            let samples = settings.samples / chunks + (chunk < settings.samples % chunks) as usize;
+            let seed = chunk_seed(settings.seed, chunk);
+            // Discard the counts left on this thread by other work.
+            stats::take();
+            let sums = (0..pixels)
+                .map(|index| {
+                    let (x, y) = (index % settings.width, index / settings.width);
+                    let mut rng = pixel_rng(seed, index);
+                    let sum = sample_sum(world, camera, settings, (x, y), samples, &mut rng);
+                    if x + 1 == settings.width {
+                        completed.fetch_add(1, Ordering::Relaxed);
+                    }
+                    [sum.x() as f64, sum.y() as f64, sum.z() as f64]
+                })
+                .collect::<Vec<_>>();
+            let stats = RenderStats {
+                primary_rays: (pixels * samples) as u64,
+                ..stats::take()
+            };
+            (sums, stats)
+        })
+        .reduce(
+            || (vec![[0.; 3]; pixels], RenderStats::default()),
+            |(mut a, a_stats), (b, b_stats)| {
+                for (a, b) in a.iter_mut().zip(b) {
+                    *a = [a[0] + b[0], a[1] + b[1], a[2] + b[2]];
+                }
+                (a, a_stats + b_stats)
+            },
+        );
+    let samples = settings.samples as f64;
+    for (pixel, sum) in image.chunks_mut(F::CHANNELS).zip(sums) {
+        let color = Vec3(
+            (sum[0] / samples) as f32,
+            (sum[1] / samples) as f32,
+            (sum[2] / samples) as f32,
+        );
+        F::store(color, pixel);
+    }
+    stats
+}
+
+// Render the tile with the top left corner `(x0, y0)` into its rows of
+// pixels, and return the statistics of the tile.
+fn render_tile<F: PixelFormat>(
+    world: &dyn Hitable,
+    camera: &dyn CameraModel,
+    settings: &RenderSettings,
+    (x0, y0): (usize, usize),
+    mut rows: Vec<&mut [F::Value]>,
+) -> RenderStats {
+    // Discard the counts left on this thread by other work.
+    stats::take();
+    let (width, height) = (rows[0].len() / F::CHANNELS, rows.len());
+    let mut set = |(x, y): (usize, usize), color: Vec3| {
+        let start = (x - x0) * F::CHANNELS;
+        F::store(color, &mut rows[y - y0][start..start + F::CHANNELS]);
+    };
+    // With packets, the pixels are rendered in 2x2 blocks, except for the
+    // last row and column of tiles of odd size.
+    let (packet_width, packet_height) = if settings.packets {
+        (width - width % 2, height - height % 2)
+    } else {
+        (0, 0)
+    };
+    for y in (y0..y0 + packet_height).step_by(2) {
+        for x in (x0..x0 + packet_width).step_by(2) {
+            let pixels = [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)];
+            let colors = render_packet(world, camera, settings, pixels);
+            for (&pixel, &color) in pixels.iter().zip(colors.iter()) {
+                set(pixel, color);
+            }
+        }
+    }
+    for y in y0..y0 + height {
+        for x in x0..x0 + width {
+            if x - x0 >= packet_width || y - y0 >= packet_height {
+                set((x, y), render_pixel(world, camera, settings, x, y));
+            }
+        }
+    }
+    RenderStats {
+        primary_rays: (width * height * settings.samples) as u64,
+        ..stats::take()
+    }
+}
+
+// Render the linear color of the pixel in column `x` and row `y` from the top.
+fn render_pixel(
+    world: &dyn Hitable,
+    camera: &dyn CameraModel,
+    settings: &RenderSettings,
+    x: usize,
+    y: usize,
+) -> Vec3 {
+    let mut rng = pixel_rng(settings.seed, y * settings.width + x);
+    let col = sample_sum(world, camera, settings, (x, y), settings.samples, &mut rng);
+    col / settings.samples as f32
+}
+
+// Render the pixels given by their columns and rows from the top, tracing
+// the primary rays of each sample as a packet. Every pixel draws the same
+// random numbers from its own generator as in `render_pixel`, such that the
+// colors are the same.
+fn render_packet(
+    world: &dyn Hitable,
+    camera: &dyn CameraModel,
+    settings: &RenderSettings,
+    pixels: [(usize, usize); PACKET_SIZE],
+) -> [Vec3; PACKET_SIZE] {
+    let mut rngs = pixels.map(|(x, y)| pixel_rng(settings.seed, y * settings.width + x));
+    let mut cols = [Vec3(0., 0., 0.); PACKET_SIZE];
+    for _ in 0..settings.samples {
+        let mut rays: [Option<Ray>; PACKET_SIZE] =
+            std::array::from_fn(|i| primary_ray(camera, settings, pixels[i], &mut rngs[i]));
+        if rays.iter().all(Option::is_some) {
+            let rays: [Ray; PACKET_SIZE] = std::array::from_fn(|i| rays[i].take().unwrap());
+            let hits = world.intersect_packet(&rays, 0.001, f32::MAX);
+            for (i, hit) in IntoIterator::into_iter(hits).enumerate() {
+                cols[i] += shade(
+                    &rays[i],
+                    hit,
+                    world,
+                    0,
+                    settings.roulette,
+                    settings.min_throughput,
+                    &mut rngs[i],
+                );
+            }
+        } else {
+            // Points the camera does not see are black, and the other rays
+            // are traced one by one.
+            for (i, ray) in rays.iter().enumerate() {
+                if let Some(ray) = ray {
+                    let hit = world.intersect(ray, 0.001, f32::MAX);
+                    cols[i] += shade(
+                        ray,
+                        hit,
+                        world,
+                        0,
+                        settings.roulette,
+                        settings.min_throughput,
+                        &mut rngs[i],
+                    );
+                }
+            }
+        }
+    }
+    cols.map(|col| col / settings.samples as f32)
+}
+
+// Sum the colors of `samples` samples of the pixel in column `x` and row `y`
+// from the top.
+fn sample_sum(
+    world: &dyn Hitable,
+    camera: &dyn CameraModel,
+    settings: &RenderSettings,
+    (x, y): (usize, usize),
+    samples: usize,
+    rng: &mut SmallRng,
+) -> Vec3 {
+    let mut col = Vec3(0., 0., 0.);
+    for _ in 0..samples {
+        // Points the camera does not see are black.
+        let r = match primary_ray(camera, settings, (x, y), rng) {
+            Some(r) => r,
+            None => continue,
+        };
+        let hit = world.intersect(&r, 0.001, f32::MAX);
+        col += shade(
+            &r,
+            hit,
+            world,
+            0,
+            settings.roulette,
+            settings.min_throughput,
+            rng,
+        );
+    }
+    col
+}
+
+// Return a ray through a random point of the pixel in column `x` and row `y`
+// from the top, unless the camera does not see the point.
+fn primary_ray(
+    camera: &dyn CameraModel,
+    settings: &RenderSettings,
+    (x, y): (usize, usize),
+    rng: &mut SmallRng,
+) -> Option<Ray> {
+    let (nx, ny) = (settings.width as f32, settings.height as f32);
+    // The vertical image coordinate grows upwards.
+    let y = settings.height - y - 1;
+    let u = (x as f32 + rng.gen::<f32>()) / nx;
+    let v = (y as f32 + rng.gen::<f32>()) / ny;
+    let ray = camera.get_ray(u, v, rng)?;
+    Some(ray.with_medium_seed(rng.gen()))
+}
+
+// Render the cost of intersecting the primary rays with the scene in false
+// colors.
+#[cfg(feature = "stats")]
+fn render_heatmap<F: PixelFormat>(
+    world: &dyn Hitable,
+    camera: &dyn CameraModel,
+    settings: &RenderSettings,
+    image: &mut [F::Value],
+    completed: &AtomicUsize,
+) -> RenderStats {
+    let (costs, stats) = traversal_costs(world, camera, settings, completed);
+    // Normalize by a percentile rather than the maximum, which may be an
+    // outlier.
+    let mut sorted = costs.clone();
+    sorted.sort_by(f32::total_cmp);
+    let percentile = sorted
+        .get((sorted.len().saturating_sub(1) as f32 * 0.99).round() as usize)
+        .map_or(0., |&cost| cost);
+    let scale = if percentile > 0. { percentile } else { 1. };
+    for (pixel, cost) in image.chunks_mut(F::CHANNELS).zip(costs) {
+        F::store(from_rgb(heat_color(cost / scale)), pixel);
+    }
+    stats
+}
+
+// The average number of visited BVH nodes and tested objects of the primary
+// rays of every pixel, row by row from the top, counting the rows done in
+// `completed`.
+#[cfg(feature = "stats")]
+fn traversal_costs(
+    world: &dyn Hitable,
+    camera: &dyn CameraModel,
+    settings: &RenderSettings,
+    completed: &AtomicUsize,
+) -> (Vec<f32>, RenderStats) {
+    let pixels = settings.width * settings.height;
+    (0..pixels)
+        .into_par_iter()
+        .map(|index| {
+            let (x, y) = (index % settings.width, index / settings.width);
+            let mut rng = pixel_rng(settings.seed, index);
+            let mut pixel_stats = RenderStats::default();
+            for _ in 0..settings.samples {
+                let r = primary_ray(camera, settings, (x, y), &mut rng);
+                // Discard the counts left on this thread by other work.
+                stats::take();
+                if let Some(r) = r {
+                    world.intersect(&r, 0.001, f32::MAX);
+                }
+                pixel_stats = pixel_stats + stats::take();
+            }
+            if x + 1 == settings.width {
+                completed.fetch_add(1, Ordering::Relaxed);
+            }
+            let cost = pixel_stats.bvh_nodes_visited + pixel_stats.intersection_tests;
+            let stats = RenderStats {
+                primary_rays: settings.samples as u64,
+                ..pixel_stats
+            };
+            (cost as f32 / settings.samples.max(1) as f32, stats)
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .fold(
+            (Vec::with_capacity(pixels), RenderStats::default()),
+            |(mut costs, total), (cost, stats)| {
+                costs.push(cost);
+                (costs, total + stats)
+            },
+        )
+}
+
+// Map a value from 0 to 1 to a gradient from blue over cyan, green and
+// yellow to red. Larger values are red.
+#[cfg(feature = "stats")]
+fn heat_color(t: f32) -> [u8; 3] {
+    const GRADIENT: [[f32; 3]; 5] = [
+        [0., 0., 255.],
+        [0., 255., 255.],
+        [0., 255., 0.],
+        [255., 255., 0.],
+        [255., 0., 0.],
+    ];
+    let position = t.clamp(0., 1.) * (GRADIENT.len() - 1) as f32;
+    let i = (position as usize).min(GRADIENT.len() - 2);
+    let f = position - i as f32;
+    let (a, b) = (GRADIENT[i], GRADIENT[i + 1]);
+    [
+        (a[0] + f * (b[0] - a[0])).round() as u8,
+        (a[1] + f * (b[1] - a[1])).round() as u8,
+        (a[2] + f * (b[2] - a[2])).round() as u8,
+    ]
+}
+
+// Convert 8-bit values with a gamma of 2 to the linear color in the middle
+// of the colors `to_rgb` converts to them.
+#[cfg(feature = "stats")]
+fn from_rgb(rgb: [u8; 3]) -> Vec3 {
+    let linear = |value: u8| ((f32::from(value) + 0.5) / 254.99).powi(2);
+    Vec3(linear(rgb[0]), linear(rgb[1]), linear(rgb[2]))
+}
+
+// Convert a linear color to 8-bit values with a gamma of 2.
+pub(crate) fn to_rgb(col: Vec3) -> [u8; 3] {
+    [
+        (col.r().sqrt() * 254.99) as u8,
+        (col.g().sqrt() * 254.99) as u8,
+        (col.b().sqrt() * 254.99) as u8,
+    ]
+}
+
+// The seed of the given chunk of samples, which is `seed` for the first.
+fn chunk_seed(seed: u64, chunk: usize) -> u64 {
+    seed ^ (chunk as u64).wrapping_mul(0xd1b5_4a32_d192_ed03)
+}
+
+// The random number generator of the pixel with the given index.
+fn pixel_rng(seed: u64, index: usize) -> SmallRng {
+    SmallRng::seed_from_u64(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ index as u64)
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::OrthographicCamera;
+    use crate::hit_record::HitRecord;
+    use crate::materials::Dielectric;
+    use crate::materials::Isotropic;
+    use crate::materials::Lambertian;
+    use crate::materials::Material;
+    use crate::materials::MaterialExt;
+    use crate::materials::ShadowCatcher;
+    use crate::objects::constant_medium::ConstantMedium;
+    use crate::objects::rect::Rect;
+    use crate::objects::scene::Scene;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::HitableList;
+    use crate::pdf::CosinePdf;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+    use std::sync::Arc;
+
+    // A material only implementing the legacy trait: reflects along the normal.
+    struct LegacyMirror;
+
+    impl Material for LegacyMirror {
+        fn scatter(&self, _ray: &Ray, hit: &HitRecord) -> Option<(Ray, Vec3)> {
+            Some((
+                Ray::new(hit.point_at_parameter, hit.normal),
+                Vec3(0.5, 0.5, 0.5),
+            ))
+        }
+    }
+
+    // A material implementing the full extended trait: emits and absorbs.
+    struct RedLight;
+
+    impl MaterialExt for RedLight {
+        fn scatter(
+            &self,
+            _ray: &Ray,
+            _hit: &HitRecord,
+            _rng: &mut dyn RngCore,
+        ) -> Option<(Ray, Vec3)> {
+            None
+        }
+
+        fn emitted(&self, _hit: &HitRecord) -> Vec3 {
+            Vec3(1., 0., 0.)
+        }
+    }
+
+    // A diffuse material whose scattered rays follow the cosine density it
+    // reports, unlike those of a `Lambertian`.
+    struct Matte;
+
+    impl MaterialExt for Matte {
+        fn scatter(
+            &self,
+            ray: &Ray,
+            hit: &HitRecord,
+            rng: &mut dyn RngCore,
+        ) -> Option<(Ray, Vec3)> {
+            let direction = CosinePdf::new(&hit.normal).generate(rng);
+            let scattered = Ray::new_with_time(hit.point_at_parameter, direction, ray.time());
+            Some((scattered, Vec3(0.5, 0.5, 0.5)))
+        }
+
+        fn scatter_with_pdf(
+            &self,
+            _ray: &Ray,
+            hit: &HitRecord,
+            _rng: &mut dyn RngCore,
+        ) -> Option<ScatterRecord> {
+            Some(ScatterRecord::Diffuse {
+                attenuation: Vec3(0.5, 0.5, 0.5),
+                pdf: Box::new(CosinePdf::new(&hit.normal)),
+            })
+        }
+
+        fn scattering_pdf(&self, _ray: &Ray, hit: &HitRecord, scattered: &Ray) -> f32 {
+            CosinePdf::new(&hit.normal).value(scattered.direction())
+        }
+    }
+
+    // A small bright light, which scattered rays rarely hit.
+    struct Lamp;
+
+    impl MaterialExt for Lamp {
+        fn scatter(
+            &self,
+            _ray: &Ray,
+            _hit: &HitRecord,
+            _rng: &mut dyn RngCore,
+        ) -> Option<(Ray, Vec3)> {
+            None
+        }
+
+        fn emitted(&self, _hit: &HitRecord) -> Vec3 {
+            Vec3(50., 50., 50.)
+        }
+    }
+
+    // The recursive definition of `color`, which the loop has to follow.
+    fn recursive_color(r: &Ray, world: &dyn Hitable, depth: i32, rng: &mut dyn RngCore) -> Vec3 {
+        match world.intersect(r, 0.001, f32::MAX) {
+            Some(hit) => {
+                let emitted = hit.material.emitted(&hit);
+                match hit.material.scatter(r, &hit, rng) {
+                    Some((scattered, attenuation)) if depth < MAX_DEPTH => {
+                        emitted + attenuation * recursive_color(&scattered, world, depth + 1, rng)
+                    }
+                    _ => emitted,
+                }
+            }
+            None => background(r),
+        }
+    }
+
+    fn scene() -> HitableList {
+        HitableList::new(vec![
+            Box::new(Sphere::new(Vec3(0., 0., -3.), 1., Arc::new(LegacyMirror))),
+            Box::new(Sphere::new(Vec3(0., 0., 3.), 1., Arc::new(RedLight))),
+        ])
+    }
+
+    #[test]
+    // Test that the full material is rendered with its emission.
+    fn test_color_extended_material() {
+        let mut rng = SmallRng::seed_from_u64(50);
+        let world = scene();
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., 1.));
+        assert_eq!(color(&ray, &world, 0, &mut rng), Vec3(1., 0., 0.));
+    }
+
+    #[test]
+    // Test that the legacy material scatters into the light in the same scene.
+    fn test_color_legacy_material() {
+        let mut rng = SmallRng::seed_from_u64(51);
+        let world = scene();
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., -1.));
+        assert_eq!(color(&ray, &world, 0, &mut rng), Vec3(0.5, 0., 0.));
+    }
+
+    #[test]
+    // Test that rays missing the scene see the background.
+    fn test_color_background() {
+        let mut rng = SmallRng::seed_from_u64(52);
+        let world = scene();
+        let ray = Ray::new(Vec3(0., 0., 0.), Vec3(0., 1., 0.));
+        assert_eq!(color(&ray, &world, 0, &mut rng), background(&ray));
+    }
+
+    // A small scene with random decisions in the camera and the materials.
+    fn glass_scene() -> (HitableList, Camera) {
+        let world = HitableList::new(vec![
+            Box::new(Sphere::new(
+                Vec3(0., -100.5, -1.),
+                100.,
+                Arc::new(Lambertian::new(Vec3(0.8, 0.8, 0.))),
+            )),
+            Box::new(Sphere::new(
+                Vec3(0., 0., -1.),
+                0.5,
+                Arc::new(Dielectric::new(1.5)),
+            )),
+        ]);
+        let camera = Camera::new(
+            Vec3(0., 0., 1.),
+            Vec3(0., 0., -1.),
+            Vec3(0., 1., 0.),
+            60.,
+            1.5,
+            0.1,
+            2.,
+        );
+        (world, camera)
+    }
+
+    #[test]
+    // Test that the image does not depend on the tile size, and equals rendering pixel by pixel.
+    fn test_render_tiles() {
+        let (world, camera) = glass_scene();
+        let settings = RenderSettings {
+            width: 45,
+            height: 30,
+            samples: 3,
+            tile_size: 32,
+            tile_order: TileOrder::Scanline,
+            threads: None,
+            seed: 7,
+            parallelism: Parallelism::Tiles,
+            mode: RenderMode::Shaded,
+            roulette: None,
+            min_throughput: 0.,
+            packets: false,
+        };
+        let image = render(&world, &camera, &settings);
+        let pixels: Vec<u8> = (0..settings.height)
+            .into_par_iter()
+            .flat_map(|y| (0..settings.width).into_par_iter().map(move |x| (x, y)))
+            .flat_map(|(x, y)| to_rgb(render_pixel(&world, &camera, &settings, x, y)).to_vec())
+            .collect();
+        assert_eq!(image, pixels);
+        for tile_size in [0, 1, 7, 45, 100] {
+            let tiled = RenderSettings {
+                tile_size,
+                ..settings.clone()
+            };
+            assert!(render(&world, &camera, &tiled) == image);
+        }
+        let reseeded = RenderSettings {
+            seed: 8,
+            ..settings
+        };
+        assert!(render(&world, &camera, &reseeded) != image);
+    }
+
+    #[test]
+    // Test that rendering into a film tone-maps to the rendered image, and
+    // that the film gives the dimensions of the image.
+    fn test_render_into() {
+        let (world, camera) = glass_scene();
+        let settings = RenderSettings {
+            width: 30,
+            height: 20,
+            samples: 4,
+            tile_size: 8,
+            seed: 5,
+            ..RenderSettings::default()
+        };
+        for settings in [
+            settings.clone(),
+            RenderSettings {
+                packets: true,
+                ..settings.clone()
+            },
+            RenderSettings {
+                parallelism: Parallelism::Samples { chunks: 3 },
+                ..settings.clone()
+            },
+        ] {
+            let mut film = FilmBuffer::new(30, 20);
+            let stats = render_into(&mut film, &world, &camera, &settings);
+            assert_eq!(stats.primary_rays, 30 * 20 * 4);
+            assert_eq!(film.to_rgb8(), render(&world, &camera, &settings));
+        }
+        let mut film = FilmBuffer::new(15, 10);
+        render_into(&mut film, &world, &camera, &settings);
+        let smaller = RenderSettings {
+            width: 15,
+            height: 10,
+            ..settings
+        };
+        assert_eq!(film.to_rgb8(), render(&world, &camera, &smaller));
+    }
+
+    #[test]
+    // Test that rendering through the camera model of a perspective camera
+    // gives the image traced with its inherent rays, and that an orthographic
+    // camera sees the same color over the whole silhouette of a light.
+    fn test_render_camera_model() {
+        let (world, camera) = glass_scene();
+        let settings = RenderSettings {
+            width: 12,
+            height: 8,
+            samples: 4,
+            ..RenderSettings::default()
+        };
+        let model: &dyn CameraModel = &camera;
+        let image = render(&world, model, &settings);
+        let (nx, ny) = (settings.width as f32, settings.height as f32);
+        let expected: Vec<u8> = (0..settings.width * settings.height)
+            .flat_map(|index| {
+                let (x, y) = (index % settings.width, index / settings.width);
+                let mut rng = pixel_rng(settings.seed, index);
+                let mut col = Vec3(0., 0., 0.);
+                for _ in 0..settings.samples {
+                    let u = (x as f32 + rng.gen::<f32>()) / nx;
+                    let v = ((settings.height - y - 1) as f32 + rng.gen::<f32>()) / ny;
+                    let ray = Camera::get_ray(&camera, u, v, &mut rng);
+                    col += color(&ray, &world, 0, &mut rng);
+                }
+                col /= settings.samples as f32;
+                to_rgb(col)
+            })
+            .collect();
+        assert_eq!(image, expected);
+
+        // A light of radius 1 in front of a camera showing a 4 by 4 square.
+        let light = Sphere::new(Vec3(0., 0., -5.), 1., Arc::new(RedLight));
+        let orthographic = OrthographicCamera::new(
+            Vec3(0., 0., 0.),
+            Vec3(0., 0., -1.),
+            Vec3(0., 1., 0.),
+            4.,
+            4.,
+        );
+        let settings = RenderSettings {
+            width: 8,
+            height: 8,
+            ..settings
+        };
+        let image = render(&light, &orthographic, &settings);
+        let pixel = |x: usize, y: usize| {
+            let index = 3 * (y * settings.width + x);
+            [image[index], image[index + 1], image[index + 2]]
+        };
+        // The pixels within 0.5 of the center are fully covered by the light,
+        // and the corners see the sky straight ahead.
+        for &(x, y) in &[(3, 3), (4, 3), (3, 4), (4, 4)] {
+            assert_eq!(pixel(x, y), to_rgb(Vec3(1., 0., 0.)));
+        }
+        let sky = to_rgb(background(&Ray::new(Vec3(0., 0., 0.), Vec3(0., 0., -1.))));
+        for &(x, y) in &[(0, 0), (7, 0), (0, 7), (7, 7)] {
+            assert_eq!(pixel(x, y), sky);
+        }
+    }
+
+    #[test]
+    // Test that an empty image is rendered without any tile.
+    fn test_render_empty() {
+        let (world, camera) = glass_scene();
+        let settings = RenderSettings {
+            width: 0,
+            height: 10,
+            ..RenderSettings::default()
+        };
+        assert!(render(&world, &camera, &settings).is_empty());
+    }
+
+    #[test]
+    // Test that 4 chunks of 25 samples agree with 1 chunk of 100 samples within the noise.
+    fn test_render_sample_chunks() {
+        let (world, camera) = glass_scene();
+        let settings = |chunks| RenderSettings {
+            width: 30,
+            height: 20,
+            samples: 100,
+            seed: 3,
+            parallelism: Parallelism::Samples { chunks },
+            ..RenderSettings::default()
+        };
+        let mean = |image: &[u8]| image.iter().map(|&c| c as f64).sum::<f64>() / image.len() as f64;
+        let one = render(&world, &camera, &settings(1));
+        let four = render(&world, &camera, &settings(4));
+        assert_eq!(four.len(), 30 * 20 * 3);
+        assert!(one != four);
+        assert!((mean(&one) - mean(&four)).abs() < 1.);
+        let differences: Vec<f64> = one
+            .iter()
+            .zip(&four)
+            .map(|(&a, &b)| (a as f64 - b as f64).abs())
+            .collect();
+        assert!(mean_difference(&differences) < 4.);
+        // More chunks than samples render one sample per chunk.
+        assert_eq!(
+            render(&world, &camera, &settings(1000)),
+            render(&world, &camera, &settings(100))
+        );
+    }
+
+    fn mean_difference(differences: &[f64]) -> f64 {
+        differences.iter().sum::<f64>() / differences.len() as f64
+    }
+
+    #[test]
+    // Test that one primary ray is traced per sample of every pixel.
+    fn test_render_stats_primary_rays() {
+        let (world, camera) = glass_scene();
+        for parallelism in [Parallelism::Tiles, Parallelism::Samples { chunks: 3 }] {
+            let settings = RenderSettings {
+                width: 17,
+                height: 9,
+                samples: 5,
+                tile_size: 4,
+                parallelism,
+                ..RenderSettings::default()
+            };
+            let (image, stats) = render_with_stats(&world, &camera, &settings);
+            assert_eq!(image, render(&world, &camera, &settings));
+            assert_eq!(stats.primary_rays, 17 * 9 * 5);
+            if cfg!(feature = "stats") {
+                assert!(stats.scattered_rays > 0);
+                assert!(stats.intersection_tests >= 2 * stats.primary_rays);
+            } else {
+                assert_eq!(stats.scattered_rays, 0);
+                assert_eq!(stats.intersection_tests, 0);
+            }
+            assert_eq!(stats.bvh_nodes_visited, 0);
+        }
+    }
+
+    #[cfg(feature = "stats")]
+    #[test]
+    // Test that sky pixels are cheap in the heatmap while pixels over a dense field of spheres are expensive.
+    fn test_render_traversal_heatmap() {
+        use crate::objects::bvh::BvhNode;
+
+        let material: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        let mut spheres: Vec<Box<dyn Hitable>> = Vec::new();
+        for i in 0..20 {
+            for j in 0..20 {
+                let center = Vec3(-2. + 0.2 * i as f32, -1.5, -2. - 0.2 * j as f32);
+                spheres.push(Box::new(Sphere::new(center, 0.08, material.clone())));
+            }
+        }
+        let world = BvhNode::new(spheres);
+        let camera = Camera::new(
+            Vec3(0., 0., 0.),
+            Vec3(0., 0., -1.),
+            Vec3(0., 1., 0.),
+            90.,
+            1.,
+            0.,
+            1.,
+        );
+        let settings = RenderSettings {
+            width: 20,
+            height: 20,
+            samples: 2,
+            mode: RenderMode::TraversalHeatmap,
+            ..RenderSettings::default()
+        };
+        let (costs, stats) = traversal_costs(&world, &camera, &settings, &AtomicUsize::new(0));
+        assert_eq!(stats.primary_rays, 20 * 20 * 2);
+        let average = |rows: std::ops::Range<usize>| {
+            let pixels = &costs[rows.start * 20..rows.end * 20];
+            pixels.iter().sum::<f32>() / pixels.len() as f32
+        };
+        // Rays into the sky only visit the root.
+        assert!(average(0..5) <= 1.);
+        assert!(average(13..17) > 10.);
+
+        let (image, _) = render_with_stats(&world, &camera, &settings);
+        assert_eq!(image.len(), 20 * 20 * 3);
+        assert_eq!((image[0], image[2]), (0, 255));
+        assert!(image[(15 * 20 + 10) * 3] > 0);
+
+        // A film holds the colors tone-mapping to the false colors.
+        assert!((0..=255).all(|value| to_rgb(from_rgb([value; 3])) == [value; 3]));
+        let mut film = FilmBuffer::new(20, 20);
+        render_into(&mut film, &world, &camera, &settings);
+        assert_eq!(film.to_rgb8(), image);
+    }
+
+    #[test]
+    // Test that the loop follows the same paths as the recursion, up to rounding.
+    fn test_color_matches_recursion() {
+        let (world, camera) = glass_scene();
+        let settings = RenderSettings {
+            width: 30,
+            height: 20,
+            ..RenderSettings::default()
+        };
+        for index in 0..settings.width * settings.height {
+            let (x, y) = (index % settings.width, index / settings.width);
+            let mut rng = pixel_rng(3, index);
+            let mut recursive_rng = rng.clone();
+            for _ in 0..8 {
+                let r = primary_ray(&camera, &settings, (x, y), &mut rng).unwrap();
+                let col = color(&r, &world, 0, &mut rng);
+                let r = primary_ray(&camera, &settings, (x, y), &mut recursive_rng).unwrap();
+                // The loop draws the seed of the media along the ray first.
+                recursive_rng.gen::<u64>();
+                let expected = recursive_color(&r, &world, 0, &mut recursive_rng);
+                assert!((col - expected).length() <= 1e-5 * expected.length());
+            }
+            // Both drew the same random numbers.
+            assert_eq!(rng.gen::<u64>(), recursive_rng.gen::<u64>());
+        }
+    }
+
+    #[test]
+    // Test that Russian roulette leaves the mean of the image unchanged within the noise.
+    fn test_render_roulette() {
+        let (world, camera) = glass_scene();
+        let settings = RenderSettings {
+            width: 30,
+            height: 20,
+            samples: 64,
+            seed: 5,
+            ..RenderSettings::default()
+        };
+        let roulette = RenderSettings {
+            roulette: Some(RussianRoulette { min_depth: 1 }),
+            ..settings.clone()
+        };
+        let (image, stats) = render_with_stats(&world, &camera, &settings);
+        let (roulette_image, roulette_stats) = render_with_stats(&world, &camera, &roulette);
+        assert!(image != roulette_image);
+        let mean = |image: &[u8]| image.iter().map(|&c| c as f64).sum::<f64>() / image.len() as f64;
+        assert!((mean(&image) - mean(&roulette_image)).abs() < 1.);
+        if cfg!(feature = "stats") {
+            assert!(roulette_stats.scattered_rays < stats.scattered_rays);
+        }
+    }
+
+    #[test]
+    // Test that terminating dark paths changes the image little, but saves most of their rays.
+    fn test_render_min_throughput() {
+        // A red light in a closed grey room, in which paths only end at the light.
+        let world = HitableList::new(vec![
+            Box::new(Sphere::new(
+                Vec3(0., 0., 0.),
+                -5.,
+                Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))),
+            )),
+            Box::new(Sphere::new(Vec3(0., 0., -3.), 0.3, Arc::new(RedLight))),
+        ]);
+        let camera = Camera::new(
+            Vec3(0., 0., 2.),
+            Vec3(0., 0., -1.),
+            Vec3(0., 1., 0.),
+            90.,
+            1.5,
+            0.,
+            1.,
+        );
+        let settings = RenderSettings {
+            width: 24,
+            height: 16,
+            samples: 32,
+            seed: 6,
+            ..RenderSettings::default()
+        };
+        let (image, stats) = render_with_stats(&world, &camera, &settings);
+        let mean = |image: &[u8]| image.iter().map(|&c| c as f64).sum::<f64>() / image.len() as f64;
+        for roulette in [
+            None,
+            Some(RussianRoulette {
+                min_depth: MAX_DEPTH,
+            }),
+        ] {
+            let threshold = RenderSettings {
+                min_throughput: 1e-4,
+                roulette,
+                ..settings.clone()
+            };
+            let (threshold_image, threshold_stats) = render_with_stats(&world, &camera, &threshold);
+            assert!(threshold_image != image);
+            assert!((mean(&image) - mean(&threshold_image)).abs() < 0.5);
+            if cfg!(feature = "stats") {
+                assert!(threshold_stats.scattered_rays < stats.scattered_rays / 2);
+            }
+        }
+    }
+
+    #[test]
+    // Test that the progress grows monotonically to the whole render.
+    fn test_render_progress() {
+        let (world, camera) = glass_scene();
+        let settings = RenderSettings {
+            width: 45,
+            height: 30,
+            samples: 8,
+            tile_size: 8,
+            ..RenderSettings::default()
+        };
+        let chunks = RenderSettings {
+            parallelism: Parallelism::Samples { chunks: 3 },
+            ..settings.clone()
+        };
+        for (settings, total) in [(settings, 6 * 4), (chunks, 3 * 30)] {
+            let mut reports = Vec::new();
+            let interval = Duration::from_millis(1);
+            let (image, _) =
+                render_with_progress(&world, &camera, &settings, interval, |progress| {
+                    reports.push(progress)
+                });
+            assert!(image == render(&world, &camera, &settings));
+            for pair in reports.windows(2) {
+                assert!(pair[0].completed <= pair[1].completed);
+                assert!(pair[0].elapsed <= pair[1].elapsed);
+            }
+            let last = reports.last().unwrap();
+            assert_eq!((last.completed, last.total), (total, total));
+            assert_eq!(last.eta, Some(Duration::ZERO));
+            assert!(reports.iter().all(|progress| progress.total == total));
+        }
+    }
+
+    #[test]
+    // Test that tracing the primary rays in packets gives the same image as one by one.
+    fn test_render_packets() {
+        use crate::objects::bvh::BvhNode;
+        use crate::objects::sphere_list::SphereList;
+
+        let (list, camera) = glass_scene();
+        let mut rng = SmallRng::seed_from_u64(64);
+        let material: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.6, 0.4, 0.2)));
+        let spheres: Vec<(Vec3, f32, Arc<dyn MaterialExt>)> = (0..100)
+            .map(|_| {
+                let center = Vec3(
+                    rng.gen_range(-2.0..2.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-4.0..-1.0),
+                );
+                (center, rng.gen_range(0.05..0.3), material.clone())
+            })
+            .collect();
+        let bvh = BvhNode::new(
+            spheres
+                .iter()
+                .map(|(center, radius, material)| {
+                    Box::new(Sphere::new(*center, *radius, material.clone())) as Box<dyn Hitable>
+                })
+                .collect(),
+        );
+        let sphere_list = SphereList::new(spheres);
+        let worlds: [&dyn Hitable; 3] = [&list, &bvh, &sphere_list];
+        for world in worlds.iter() {
+            for tile_size in [5, 8] {
+                let settings = RenderSettings {
+                    width: 23,
+                    height: 17,
+                    samples: 3,
+                    tile_size,
+                    ..RenderSettings::default()
+                };
+                let packets = RenderSettings {
+                    packets: true,
+                    ..settings.clone()
+                };
+                assert_eq!(
+                    render(*world, &camera, &packets),
+                    render(*world, &camera, &settings)
+                );
+            }
+        }
+    }
+
+    #[test]
+    // Test that the points a camera does not see are black, also when the
+    // pixels are traced in packets.
+    fn test_render_unseen() {
+        use crate::camera::FisheyeCamera;
+
+        let (world, _) = glass_scene();
+        let camera = FisheyeCamera::new(
+            Vec3(0., 0., 1.),
+            Vec3(0., 0., 0.),
+            Vec3(0., 1., 0.),
+            180.,
+            2.,
+        );
+        let settings = RenderSettings {
+            width: 40,
+            height: 20,
+            samples: 4,
+            ..RenderSettings::default()
+        };
+        let image = render(&world, &camera, &settings);
+        let pixel = |x: usize, y: usize| &image[(y * settings.width + x) * 3..][..3];
+        for &(x, y) in &[(0, 0), (39, 0), (0, 19), (39, 19), (5, 10), (34, 10)] {
+            assert_eq!(pixel(x, y), [0, 0, 0]);
+        }
+        assert_ne!(pixel(20, 10), [0, 0, 0]);
+        assert_ne!(pixel(20, 1), [0, 0, 0]);
+        let packets = RenderSettings {
+            packets: true,
+            ..settings.clone()
+        };
+        assert_eq!(render(&world, &camera, &packets), image);
+    }
+
+    // A camera model casting the rays of a camera with its own generator,
+    // leaving the one of the renderer untouched.
+    struct OwnRng(Camera);
+
+    impl CameraModel for OwnRng {
+        fn get_ray(&self, x_frac: f32, y_frac: f32, _rng: &mut dyn RngCore) -> Option<Ray> {
+            let mut rng = SmallRng::seed_from_u64(0);
+            Some(self.0.get_ray(x_frac, y_frac, &mut rng))
+        }
+    }
+
+    #[test]
+    // Test that a camera without a shutter interval leaves the random numbers
+    // of the materials unchanged, and that a moving sphere is blurred along
+    // its path while the shutter is open.
+    fn test_render_shutter() {
+        use crate::objects::moving_sphere::MovingSphere;
+
+        let camera = |shutter: (f32, f32)| {
+            Camera::new(
+                Vec3(0., 0., 0.),
+                Vec3(0., 0., -1.),
+                Vec3(0., 1., 0.),
+                90.,
+                2.,
+                0.,
+                1.,
+            )
+            .with_shutter(shutter.0, shutter.1)
+        };
+        let settings = RenderSettings {
+            width: 80,
+            height: 20,
+            samples: 8,
+            ..RenderSettings::default()
+        };
+        // Diffuse spheres, whose scattering draws random numbers after the
+        // camera ray of every sample.
+        let diffuse = |albedo| Arc::new(Lambertian::new(albedo));
+        let still = HitableList::new(vec![
+            Box::new(Sphere::new(
+                Vec3(0., -100.5, -3.),
+                100.,
+                diffuse(Vec3(0.5, 0.5, 0.5)),
+            )) as Box<dyn Hitable>,
+            Box::new(Sphere::new(
+                Vec3(-1., 0., -3.),
+                1.,
+                diffuse(Vec3(0.8, 0.3, 0.3)),
+            )),
+            Box::new(Sphere::new(
+                Vec3(1.5, 0., -3.),
+                1.,
+                diffuse(Vec3(0.3, 0.3, 0.8)),
+            )),
+        ]);
+        // Without a shutter interval, the pinhole camera draws no random
+        // numbers, such that the materials draw the same ones as if the rays
+        // were cast from another generator.
+        let image = render(&still, &camera((0., 0.)), &settings);
+        assert_eq!(image, render(&still, &OwnRng(camera((0., 0.))), &settings));
+        assert_eq!(image, render(&still, &camera((0.5, 0.5)), &settings));
+        // Drawing the times shifts the random numbers of the materials.
+        assert!(render(&still, &camera((0., 1.)), &settings) != image);
+
+        // A light moving from x = -2 to x = 2 at a distance of 5, where a
+        // pixel spans 0.25 units.
+        let moving = MovingSphere::new(
+            Vec3(-2., 0., -5.),
+            Vec3(2., 0., -5.),
+            0.,
+            1.,
+            0.5,
+            Arc::new(RedLight),
+        );
+        let settings = RenderSettings {
+            samples: 64,
+            ..settings
+        };
+        // The first and the last column of the middle row showing the light.
+        let extent = |shutter| {
+            let image = render(&moving, &camera(shutter), &settings);
+            let row = &image[10 * settings.width * 3..11 * settings.width * 3];
+            let sky = row[1];
+            let columns: Vec<usize> = (0..settings.width)
+                .filter(|x| row[x * 3 + 1] < sky)
+                .collect();
+            (columns[0], columns[columns.len() - 1])
+        };
+        let close = |(a, b): (usize, usize), (c, d): (usize, usize)| {
+            a.abs_diff(c) <= 1 && b.abs_diff(d) <= 1
+        };
+        // The light covers x from -2.5 to 2.5, or to 0.5 in half the time.
+        assert!(close(extent((0., 1.)), (30, 49)), "{:?}", extent((0., 1.)));
+        assert!(
+            close(extent((0., 0.5)), (30, 41)),
+            "{:?}",
+            extent((0., 0.5))
+        );
+        assert!(close(extent((0., 0.)), (30, 33)), "{:?}", extent((0., 0.)));
+    }
+
+    #[test]
+    // Test that the image is the same when rendered by 1 and by 4 threads.
+    fn test_render_threads() {
+        let (world, camera) = glass_scene();
+        let settings = RenderSettings {
+            width: 30,
+            height: 20,
+            samples: 3,
+            tile_size: 4,
+            threads: Some(1),
+            ..RenderSettings::default()
+        };
+        let image = render(&world, &camera, &settings);
+        let threads = RenderSettings {
+            threads: Some(4),
+            ..settings.clone()
+        };
+        assert!(render(&world, &camera, &threads) == image);
+        let chunks = RenderSettings {
+            parallelism: Parallelism::Samples { chunks: 3 },
+            ..settings.clone()
+        };
+        let chunks_threads = RenderSettings {
+            threads: Some(4),
+            ..chunks.clone()
+        };
+        assert!(render(&world, &camera, &chunks) == render(&world, &camera, &chunks_threads));
+    }
+
+    #[test]
+    // Test that the image does not depend on the order of the tiles.
+    fn test_render_tile_orders() {
+        let (world, camera) = glass_scene();
+        let settings = RenderSettings {
+            width: 31,
+            height: 17,
+            samples: 2,
+            tile_size: 4,
+            ..RenderSettings::default()
+        };
+        let image = render(&world, &camera, &settings);
+        for tile_order in [TileOrder::Spiral, TileOrder::Hilbert] {
+            let ordered = RenderSettings {
+                tile_order,
+                threads: Some(2),
+                ..settings.clone()
+            };
+            assert!(render(&world, &camera, &ordered) == image);
+        }
+    }
+
+    #[test]
+    // Test that media draw where they scatter from the seed of the render,
+    // such that two renders of a fog with the same seed are identical, also
+    // on a different number of threads.
+    fn test_render_medium_reproducible() {
+        let (glass, camera) = glass_scene();
+        let fog = Arc::new(Isotropic::new(Vec3(0.9, 0.9, 0.9)));
+        let boundary = Sphere::new(Vec3(0.5, 0., -1.), 0.6, fog.clone());
+        let world = HitableList::new(vec![
+            Box::new(glass),
+            Box::new(ConstantMedium::new(Box::new(boundary), 2., fog)),
+        ]);
+        let settings = RenderSettings {
+            width: 24,
+            height: 16,
+            samples: 4,
+            tile_size: 8,
+            threads: Some(1),
+            seed: 3,
+            ..RenderSettings::default()
+        };
+        let image = render(&world, &camera, &settings);
+        let threads = RenderSettings {
+            threads: Some(4),
+            ..settings.clone()
+        };
+        assert!(render(&world, &camera, &settings) == image);
+        assert!(render(&world, &camera, &threads) == image);
+        let reseeded = RenderSettings {
+            seed: 4,
+            ..settings.clone()
+        };
+        assert!(render(&world, &camera, &reseeded) != image);
+        // The fog is seen.
+        assert!(render(&glass_scene().0, &camera, &settings) != image);
+    }
+
+    #[test]
+    // Test that every order covers all tiles once, starting in the center for spirals.
+    fn test_tile_order() {
+        let scanline = tile_order(TileOrder::Scanline, 50, 30, 10);
+        assert_eq!(scanline.len(), 15);
+        assert_eq!(
+            &scanline[..6],
+            &[(0, 0), (10, 0), (20, 0), (30, 0), (40, 0), (0, 10)]
+        );
+        for order in [TileOrder::Spiral, TileOrder::Hilbert] {
+            let mut tiles = tile_order(order, 50, 30, 10);
+            tiles.sort_by_key(|&(x, y)| (y, x));
+            assert_eq!(tiles, scanline);
+        }
+        let spiral = tile_order(TileOrder::Spiral, 50, 30, 10);
+        assert_eq!(spiral[0], (20, 10));
+        assert!(spiral[1..9]
+            .iter()
+            .all(|&(x, y)| (10..=30).contains(&x) && y <= 20));
+        // Consecutive tiles of a Hilbert curve are neighbors.
+        let hilbert = tile_order(TileOrder::Hilbert, 8, 8, 1);
+        assert_eq!(hilbert.len(), 64);
+        assert_eq!(hilbert[0], (0, 0));
+        for pair in hilbert.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            assert_eq!(a.0.abs_diff(b.0) + a.1.abs_diff(b.1), 1);
+        }
+    }
+
+    #[test]
+    // Test that tinted glass absorbs along a segment ending on an object
+    // nested inside it, here a light in the middle of a glass ball, and not
+    // inside an air bubble nested in it.
+    fn test_nested_absorption() {
+        let absorption = Vec3(0.6, 0.6, 0.6);
+        let ball = |absorption: Vec3, inner: Arc<dyn MaterialExt>| {
+            HitableList::new(vec![
+                Box::new(Sphere::new(
+                    Vec3(0., 0., 0.),
+                    1.,
+                    Arc::new(Dielectric::new(1.5).with_absorption(absorption)),
+                )) as Box<dyn Hitable>,
+                Box::new(Sphere::new(Vec3(0., 0., 0.), 0.5, inner)),
+            ])
+        };
+        let clear = ball(Vec3(0., 0., 0.), Arc::new(RedLight));
+        let tinted = ball(absorption, Arc::new(RedLight));
+        // The ray through the center travels 0.5 through the glass to the light.
+        let transmittance = (-0.5 * absorption.x()).exp();
+        let ray = Ray::new(Vec3(0., 0., 5.), Vec3(0., 0., -1.));
+        let (mut first, mut second) = (SmallRng::seed_from_u64(100), SmallRng::seed_from_u64(100));
+        let samples = 200;
+        let mut absorbed = 0;
+        for _ in 0..samples {
+            let expected = color(&ray, &clear, 0, &mut first);
+            let col = color(&ray, &tinted, 0, &mut second);
+            // Rays reflected off the ball do not enter the glass.
+            if (col - expected).length() > 1e-6 {
+                assert!((col - transmittance * expected).length() < 1e-5);
+                absorbed += 1;
+            }
+        }
+        assert!(absorbed > samples * 9 / 10, "{}", absorbed);
+
+        // Through the center of a ball with an air bubble of radius 0.5, the
+        // light travels 1 through the glass and none through the bubble.
+        let bubble = |absorption| ball(absorption, Arc::new(Dielectric::new(1.)));
+        let (mut first, mut second) = (SmallRng::seed_from_u64(101), SmallRng::seed_from_u64(101));
+        let mean = |world: &HitableList, rng: &mut SmallRng| {
+            (0..samples).fold(Vec3(0., 0., 0.), |sum, _| sum + color(&ray, world, 0, rng))
+                / samples as f32
+        };
+        let tinted = mean(&bubble(absorption), &mut first);
+        let clear = mean(&bubble(Vec3(0., 0., 0.)), &mut second);
+        let ratio = tinted.x() / clear.x();
+        assert!((ratio - (-absorption.x()).exp()).abs() < 0.03, "{}", ratio);
+    }
+
+    #[test]
+    // Test that sampling the lights of a scene converges to the color found
+    // by following the scattered rays alone, with a lower variance.
+    fn test_light_sampling() {
+        let world = || {
+            let gray = Arc::new(Matte);
+            HitableList::new(vec![
+                Box::new(Rect::new(
+                    Vec3(-10., 0., 10.),
+                    Vec3(20., 0., 0.),
+                    Vec3(0., 0., -20.),
+                    gray.clone(),
+                )) as Box<dyn Hitable>,
+                Box::new(Sphere::new(Vec3(0., 1., 0.), 0.1, Arc::new(Lamp))),
+                Box::new(Rect::new(
+                    Vec3(1., 2., 0.),
+                    Vec3(0., 0., -0.2),
+                    Vec3(0.2, 0., 0.),
+                    Arc::new(Lamp),
+                )),
+                Box::new(Sphere::new(Vec3(-1.5, 0.5, -0.5), 0.5, gray)),
+            ])
+        };
+        let scene = Scene::new(world());
+        assert_eq!(scene.lights().unwrap().lights().len(), 2);
+        let ray = Ray::new(Vec3(0., 1., 3.), Vec3(0.5, -1., -3.));
+        let samples = 20_000;
+        let estimate = |world: &dyn Hitable, seed| {
+            let mut rng = SmallRng::seed_from_u64(seed);
+            let colors = (0..samples)
+                .map(|_| f64::from(color(&ray, world, 0, &mut rng).x()))
+                .collect::<Vec<_>>();
+            let mean = colors.iter().sum::<f64>() / samples as f64;
+            let variance =
+                colors.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (samples - 1) as f64;
+            (mean, variance)
+        };
+        let (plain, plain_variance) = estimate(&world(), 7);
+        let (sampled, sampled_variance) = estimate(&scene, 8);
+        let error = ((plain_variance + sampled_variance) / samples as f64).sqrt();
+        assert!(
+            (plain - sampled).abs() < 4. * error,
+            "{} {} {} {} {}",
+            plain,
+            sampled,
+            error,
+            plain_variance,
+            sampled_variance
+        );
+        assert!(
+            sampled_variance < 0.01 * plain_variance,
+            "{} {}",
+            sampled_variance,
+            plain_variance
+        );
+    }
+
+    #[test]
+    // Test that a shadow catcher shows the background, darkened only by the
+    // shadow of a sphere standing on it, while the sphere is shaded.
+    fn test_shadow_catcher() {
+        let ground = Rect::new(
+            Vec3(-100., 0., -100.),
+            Vec3(0., 0., 200.),
+            Vec3(200., 0., 0.),
+            Arc::new(ShadowCatcher::new(Vec3(0.5, 0.5, 0.5))),
+        );
+        let red = Arc::new(Lambertian::new(Vec3(0.8, 0.1, 0.1)));
+        let world = HitableList::new(vec![
+            Box::new(ground) as Box<dyn Hitable>,
+            Box::new(Sphere::new(Vec3(0., 1., 0.), 1., red)),
+        ]);
+        let mut rng = SmallRng::seed_from_u64(93);
+        let eye = Vec3(0., 2., 6.);
+        // The mean color seen from the eye towards `target`, and the background
+        // in that direction.
+        let mut look_at = |target: Vec3| {
+            let ray = Ray::new(eye, target - eye);
+            let samples = 400;
+            let sum = (0..samples).fold(Vec3(0., 0., 0.), |sum, _| {
+                sum + color(&ray, &world, 0, &mut rng)
+            });
+            (sum / samples as f32, background(&ray))
+        };
+        let (far, sky) = look_at(Vec3(30., 0., -30.));
+        assert!((far - sky).length() < 0.02 * sky.length(), "{:?}", far);
+        let (shadow, sky) = look_at(Vec3(0.2, 0., 0.3));
+        // The shadow is the darkened sky, not the red sphere.
+        assert!(shadow.length() < 0.5 * sky.length(), "{:?}", shadow);
+        assert!(shadow.x() < shadow.z());
+        let (sphere, sky) = look_at(Vec3(0., 1., 0.));
+        assert!(sphere.x() > 2. * sphere.z() && sphere.x() < sky.x());
+    }
+
+    // A glass sphere at the origin, with a sphere of the refractive index
+    // `inner` inside if given.
+    fn glass_ball(inner: Option<f32>) -> HitableList {
+        let mut objects: Vec<Box<dyn Hitable>> = vec![Box::new(Sphere::new(
+            Vec3(0., 0., 0.),
+            1.,
+            Arc::new(Dielectric::new(1.5)),
+        ))];
+        if let Some(index) = inner {
+            objects.push(Box::new(Sphere::new(
+                Vec3(0., 0., 0.),
+                0.6,
+                Arc::new(Dielectric::new(index)),
+            )));
+        }
+        HitableList::new(objects)
+    }
+
+    #[test]
+    // Test that an air bubble inside glass refracts as a surface between glass
+    // and air, and that a sphere inside glass of the same refractive index is
+    // invisible.
+    fn test_nested_dielectric() {
+        let plain = glass_ball(None);
+        let bubble = glass_ball(Some(1.));
+        let invisible = glass_ball(Some(1.5));
+        let samples = 400;
+        for &height in &[0.3f32, 0.5] {
+            let ray = Ray::new(Vec3(0., height, 5.), Vec3(0., 0., -1.));
+            let (mut first, mut second) =
+                (SmallRng::seed_from_u64(98), SmallRng::seed_from_u64(98));
+            let mut rng = SmallRng::seed_from_u64(99);
+            let mut difference = Vec3(0., 0., 0.);
+            for _ in 0..samples {
+                let expected = color(&ray, &plain, 0, &mut first);
+                let same = color(&ray, &invisible, 0, &mut second);
+                assert!(
+                    (same - expected).length() < 1e-3,
+                    "{:?} {:?}",
+                    same,
+                    expected
+                );
+                difference += color(&ray, &bubble, 0, &mut rng) - expected;
+            }
+            // A bubble entered from vacuum, as if the glass were not there,
+            // would barely deflect the rays.
+            let difference = difference / samples as f32;
+            assert!(difference.length() > 0.05, "{:?} at {}", difference, height);
+        }
+    }
+
+    #[test]
+    // Test that autofocus on the center of a scene of spheres finds the nearest
+    // sphere along the view direction, ignoring closer ones beside it or
+    // behind the camera, and falls back to the point looked at on a miss.
+    fn test_autofocus() {
+        let (look_from, look_at) = (Vec3(1., 2., 3.), Vec3(-2., 0., -3.));
+        let direction = unit_vector(&(look_at - look_from));
+        let material: Arc<dyn MaterialExt> = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        let sphere = |center: Vec3, radius: f32| {
+            Box::new(Sphere::new(center, radius, material.clone())) as Box<dyn Hitable>
+        };
+        let world = HitableList::new(vec![
+            sphere(look_from + 9. * direction, 1.5),
+            sphere(look_from + 6. * direction, 0.5),
+            sphere(look_from + 3. * direction + Vec3(0., 2., 0.), 1.),
+            sphere(look_from - 2. * direction, 1.),
+        ]);
+        let camera = Camera::new(look_from, look_at, Vec3(0., 1., 0.), 30., 1.5, 0.1, 2.);
+        assert!((autofocus(&world, &camera, 0.5, 0.5) - 5.5).abs() < 1e-4);
+        let focused = camera.with_focus_dist(5.5);
+        assert!((autofocus(&world, &focused, 0.5, 0.5) - 5.5).abs() < 1e-4);
+
+        // Away from the center, the depth of the hit is along the view.
+        let ray = focused.chief_ray(0.52, 0.5);
+        let hit = world.intersect(&ray, 0.001, f32::MAX).unwrap();
+        let depth = dot(&(hit.point_at_parameter - look_from), &direction);
+        assert!((autofocus(&world, &focused, 0.52, 0.5) - depth).abs() < 1e-4);
+
+        assert_eq!(
+            autofocus(&world, &focused, 0., 0.),
+            (look_at - look_from).length()
+        );
+    }
+}
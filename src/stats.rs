@@ -0,0 +1,108 @@
+//! Statistics of the work done while rendering.
+//!
+//! The number of primary rays and the time of a render are always known.
+//! The fine-grained counters of scattered rays, intersection tests and
+//! visited BVH nodes sit in the hot path of the traversal, which is why they
+//! are only compiled in with the `stats` feature. Without it, the functions
+//! counting them are empty and the counters stay at zero.
+//!
+//! The counters are kept per thread and collected by the renderer after
+//! every unit of work, such that workers never share a counter.
+
+use std::ops;
+use std::time::Duration;
+
+/// The statistics of a render.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RenderStats {
+    /// The number of rays traced from the camera.
+    pub primary_rays: u64,
+    /// The number of rays scattered by materials. Needs the `stats` feature.
+    pub scattered_rays: u64,
+    /// The number of objects tested for an intersection by lists and BVH
+    /// leaves. Needs the `stats` feature.
+    pub intersection_tests: u64,
+    /// The number of BVH nodes visited. Needs the `stats` feature.
+    pub bvh_nodes_visited: u64,
+    /// The wall-clock time of the render.
+    pub time: Duration,
+}
+
+impl RenderStats {
+    /// The number of primary and scattered rays traced per second.
+    ///
+    /// ```
+    /// # use raytracer::stats::RenderStats;
+    /// # use std::time::Duration;
+    /// let stats = RenderStats {
+    ///     primary_rays: 300,
+    ///     scattered_rays: 100,
+    ///     time: Duration::from_secs(2),
+    ///     ..RenderStats::default()
+    /// };
+    /// assert_eq!(stats.rays_per_second(), 200.);
+    /// ```
+    pub fn rays_per_second(&self) -> f64 {
+        (self.primary_rays + self.scattered_rays) as f64 / self.time.as_secs_f64()
+    }
+}
+
+/// Add the counts of two renders, or of two parts of a render.
+impl ops::Add for RenderStats {
+    type Output = RenderStats;
+
+    fn add(self, rhs: RenderStats) -> RenderStats {
+        RenderStats {
+            primary_rays: self.primary_rays + rhs.primary_rays,
+            scattered_rays: self.scattered_rays + rhs.scattered_rays,
+            intersection_tests: self.intersection_tests + rhs.intersection_tests,
+            bvh_nodes_visited: self.bvh_nodes_visited + rhs.bvh_nodes_visited,
+            time: self.time + rhs.time,
+        }
+    }
+}
+
+#[cfg(feature = "stats")]
+thread_local! {
+    // The counts of this thread since they were last taken.
+    static COUNTERS: std::cell::Cell<RenderStats> = std::cell::Cell::new(RenderStats::default());
+}
+
+// Increment a counter of this thread.
+#[cfg(feature = "stats")]
+fn count(increment: impl FnOnce(&mut RenderStats)) {
+    COUNTERS.with(|counters| {
+        let mut stats = counters.get();
+        increment(&mut stats);
+        counters.set(stats);
+    });
+}
+
+/// Count a ray scattered on this thread.
+#[inline(always)]
+pub(crate) fn count_scattered_ray() {
+    #[cfg(feature = "stats")]
+    count(|stats| stats.scattered_rays += 1);
+}
+
+/// Count an object tested for an intersection on this thread.
+#[inline(always)]
+pub(crate) fn count_intersection_test() {
+    #[cfg(feature = "stats")]
+    count(|stats| stats.intersection_tests += 1);
+}
+
+/// Count a BVH node visited on this thread.
+#[inline(always)]
+pub(crate) fn count_bvh_node() {
+    #[cfg(feature = "stats")]
+    count(|stats| stats.bvh_nodes_visited += 1);
+}
+
+/// Return the counts of this thread and reset them.
+pub(crate) fn take() -> RenderStats {
+    #[cfg(feature = "stats")]
+    return COUNTERS.with(|counters| counters.take());
+    #[cfg(not(feature = "stats"))]
+    RenderStats::default()
+}
@@ -0,0 +1,1826 @@
+//! Textures, which give the color of a surface at a hit point.
+//!
+//! A texture is looked up with the surface coordinates `u` and `v` and the
+//! position of the hit point, as found in the `HitRecord`. Materials hold
+//! their textures as `Arc<dyn Texture>`, such that a texture can be shared
+//! between materials.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::mem;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+#[cfg(feature = "serde")]
+use crate::description::TextureDesc;
+use crate::hit_record::HitRecord;
+use crate::materials::validate_attenuation;
+use crate::perlin::Perlin;
+use crate::transform::Mat4;
+use crate::vec3::dot;
+use crate::vec3::unit_vector;
+use crate::vec3::Vec3;
+use crate::worley::Worley;
+
+/// The color of a surface, varying over the surface or in space.
+///
+/// ```
+/// use raytracer::textures::Texture;
+/// use raytracer::vec3::Vec3;
+///
+/// /// A texture which is white above the plane y = 0 and black below it.
+/// struct Horizon;
+///
+/// impl Texture for Horizon {
+///     fn value(&self, _u: f32, _v: f32, p: &Vec3) -> Vec3 {
+///         if p.y() > 0. {
+///             Vec3(1., 1., 1.)
+///         } else {
+///             Vec3(0., 0., 0.)
+///         }
+///     }
+/// }
+///
+/// assert_eq!(Horizon.value(0., 0., &Vec3(0., 1., 0.)), Vec3(1., 1., 1.));
+/// ```
+pub trait Texture: Send + Sync {
+    // Subtraiting `Send` & `Sync` for the same reason as `Material`, such that
+    // textures can be shared through `Arc` on rayon threads.
+
+    /// Return the color at the surface coordinates `u` and `v` of the point `p`.
+    fn value(&self, u: f32, v: f32, p: &Vec3) -> Vec3;
+
+    /// Return the color at the hit point `hit`, which is how materials look
+    /// up their textures.
+    ///
+    /// Defaults to the color at the surface coordinates and the position of
+    /// the hit point. Textures which also depend on the normal override it.
+    fn value_at_hit(&self, hit: &HitRecord) -> Vec3 {
+        self.value(hit.u, hit.v, &hit.point_at_parameter)
+    }
+
+    /// Return a warning if the colors of the texture are outside the 0–1 range.
+    ///
+    /// Defaults to no warning, for textures whose colors are not known upfront.
+    fn warning(&self) -> Option<String> {
+        None
+    }
+
+    /// Return the description the texture is built from again, see
+    /// `TextureDesc::build`.
+    ///
+    /// Defaults to `None`, for textures which cannot be described.
+    #[cfg(feature = "serde")]
+    fn describe(&self) -> Option<TextureDesc> {
+        None
+    }
+}
+
+/// A texture of a single color.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct SolidColor {
+    color: Vec3,
+}
+
+impl SolidColor {
+    /// Create a texture which has the color `color` everywhere.
+    ///
+    /// ```
+    /// # use raytracer::textures::{SolidColor, Texture};
+    /// # use raytracer::vec3::Vec3;
+    /// let red = SolidColor::new(Vec3(0.8, 0.1, 0.1));
+    /// assert_eq!(red.value(0.5, 0.5, &Vec3(1., 2., 3.)), Vec3(0.8, 0.1, 0.1));
+    /// ```
+    pub fn new(color: Vec3) -> SolidColor {
+        SolidColor { color }
+    }
+
+    /// Access the color of the texture.
+    pub fn color(&self) -> &Vec3 {
+        &self.color
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f32, _v: f32, _p: &Vec3) -> Vec3 {
+        self.color
+    }
+
+    fn warning(&self) -> Option<String> {
+        validate_attenuation(&self.color)
+    }
+
+    #[cfg(feature = "serde")]
+    fn describe(&self) -> Option<TextureDesc> {
+        Some(TextureDesc::Solid {
+            color: self.color.into(),
+        })
+    }
+}
+
+/// A grey texture of Perlin turbulence.
+///
+/// ```
+/// # use raytracer::perlin::Perlin;
+/// # use raytracer::textures::{NoiseTexture, Texture};
+/// # use raytracer::vec3::Vec3;
+/// # use rand::rngs::StdRng;
+/// # use rand::SeedableRng;
+/// let perlin = Perlin::new(&mut StdRng::seed_from_u64(0));
+/// let marble = NoiseTexture::new(perlin, 4., 7);
+/// let color = marble.value(0., 0., &Vec3(0.3, 0.2, 0.1));
+/// assert!(color.x() >= 0. && color.x() <= 1.);
+/// ```
+#[derive(Debug, Clone)]
+pub struct NoiseTexture {
+    noise: Perlin,
+    scale: f32,
+    octaves: u32,
+}
+
+impl NoiseTexture {
+    /// Create a texture of the turbulence of `noise` over `octaves` octaves.
+    ///
+    /// The point is multiplied by `scale` before the noise is evaluated, i.e.
+    /// larger scales give finer patterns.
+    pub fn new(noise: Perlin, scale: f32, octaves: u32) -> NoiseTexture {
+        NoiseTexture {
+            noise,
+            scale,
+            octaves,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f32, _v: f32, p: &Vec3) -> Vec3 {
+        // The turbulence is between 0 and 1, which keeps the attenuation
+        // a valid color.
+        let t = self.noise.turbulence(&(self.scale * *p), self.octaves);
+        Vec3(t, t, t)
+    }
+}
+
+/// A marble texture of bands perturbed by turbulence.
+///
+/// The bands follow a sine along an axis, whose phase is shifted by the
+/// turbulence of Perlin noise, and are colored between two colors. The
+/// pattern only depends on the position of the hit point, such that it works
+/// on objects without surface coordinates.
+///
+/// ```
+/// # use raytracer::perlin::Perlin;
+/// # use raytracer::textures::{MarbleTexture, Texture};
+/// # use raytracer::vec3::Vec3;
+/// # use rand::rngs::StdRng;
+/// # use rand::SeedableRng;
+/// let perlin = Perlin::new(&mut StdRng::seed_from_u64(0));
+/// let marble = MarbleTexture::new(perlin)
+///     .with_axis(Vec3(1., 0., 0.))
+///     .with_colors(Vec3(0.9, 0.9, 0.85), Vec3(0.2, 0.25, 0.3));
+/// let color = marble.value(0., 0., &Vec3(0.3, 0.2, 0.1));
+/// assert!(color.x() >= 0.2 && color.x() <= 0.9);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MarbleTexture {
+    noise: Perlin,
+    axis: Vec3,
+    scale: f32,
+    turbulence: f32,
+    octaves: u32,
+    colors: (Vec3, Vec3),
+}
+
+impl MarbleTexture {
+    /// Create a marble texture from a noise generator.
+    ///
+    /// The bands run along the z axis with a scale of 4, the phase is
+    /// perturbed by 10 times the turbulence over 7 octaves, and the colors go
+    /// from white to dark grey.
+    pub fn new(noise: Perlin) -> MarbleTexture {
+        MarbleTexture {
+            noise,
+            axis: Vec3(0., 0., 1.),
+            scale: 4.,
+            turbulence: 10.,
+            octaves: 7,
+            colors: (Vec3(0.9, 0.9, 0.9), Vec3(0.1, 0.1, 0.1)),
+        }
+    }
+
+    /// Set the direction along which the bands alternate.
+    pub fn with_axis(mut self, axis: Vec3) -> MarbleTexture {
+        self.axis = unit_vector(&axis);
+        self
+    }
+
+    /// Set the frequency of the bands and of the noise.
+    pub fn with_scale(mut self, scale: f32) -> MarbleTexture {
+        self.scale = scale;
+        self
+    }
+
+    /// Set how strongly the turbulence shifts the bands.
+    pub fn with_turbulence(mut self, turbulence: f32) -> MarbleTexture {
+        self.turbulence = turbulence;
+        self
+    }
+
+    /// Set the number of octaves of the turbulence.
+    pub fn with_octaves(mut self, octaves: u32) -> MarbleTexture {
+        self.octaves = octaves;
+        self
+    }
+
+    /// Set the colors between which the bands alternate.
+    pub fn with_colors(mut self, first: Vec3, second: Vec3) -> MarbleTexture {
+        self.colors = (first, second);
+        self
+    }
+}
+
+impl Texture for MarbleTexture {
+    fn value(&self, _u: f32, _v: f32, p: &Vec3) -> Vec3 {
+        let p = self.scale * *p;
+        let phase = dot(&p, &self.axis) + self.turbulence * self.noise.turbulence(&p, self.octaves);
+        lerp(self.colors, 0.5 * (1. + phase.sin()))
+    }
+
+    fn warning(&self) -> Option<String> {
+        validate_attenuation(&self.colors.0).or_else(|| validate_attenuation(&self.colors.1))
+    }
+}
+
+/// A wood texture of concentric rings around an axis.
+///
+/// The rings are centered on the line through `center` along `axis` and
+/// follow the distance to it, jittered by the turbulence of Perlin noise to
+/// give a grain. Each ring fades from the first to the second color. As for
+/// marble, the pattern only depends on the position of the hit point.
+///
+/// ```
+/// # use raytracer::perlin::Perlin;
+/// # use raytracer::textures::{Texture, WoodTexture};
+/// # use raytracer::vec3::Vec3;
+/// # use rand::rngs::StdRng;
+/// # use rand::SeedableRng;
+/// let perlin = Perlin::new(&mut StdRng::seed_from_u64(0));
+/// let wood = WoodTexture::new(perlin).with_rings(12.).with_grain(0.2);
+/// let color = wood.value(0., 0., &Vec3(0.3, 0.2, 0.1));
+/// assert!(color.x() > 0. && color.x() < 1.);
+/// ```
+#[derive(Debug, Clone)]
+pub struct WoodTexture {
+    noise: Perlin,
+    center: Vec3,
+    axis: Vec3,
+    rings: f32,
+    grain: f32,
+    scale: f32,
+    octaves: u32,
+    colors: (Vec3, Vec3),
+}
+
+impl WoodTexture {
+    /// Create a wood texture from a noise generator.
+    ///
+    /// The rings are centered on the y axis with 8 rings per unit, jittered
+    /// by half a ring times the turbulence over 4 octaves of noise of scale 2,
+    /// and colored from light to dark brown.
+    pub fn new(noise: Perlin) -> WoodTexture {
+        WoodTexture {
+            noise,
+            center: Vec3(0., 0., 0.),
+            axis: Vec3(0., 1., 0.),
+            rings: 8.,
+            grain: 0.5,
+            scale: 2.,
+            octaves: 4,
+            colors: (Vec3(0.75, 0.55, 0.3), Vec3(0.4, 0.23, 0.1)),
+        }
+    }
+
+    /// Set the axis of the rings, the line through `center` along `axis`.
+    pub fn with_axis(mut self, center: Vec3, axis: Vec3) -> WoodTexture {
+        self.center = center;
+        self.axis = unit_vector(&axis);
+        self
+    }
+
+    /// Set the number of rings per unit of distance to the axis.
+    pub fn with_rings(mut self, rings: f32) -> WoodTexture {
+        self.rings = rings;
+        self
+    }
+
+    /// Set by how many rings the turbulence jitters the rings.
+    pub fn with_grain(mut self, grain: f32) -> WoodTexture {
+        self.grain = grain;
+        self
+    }
+
+    /// Set the frequency of the noise of the grain.
+    pub fn with_scale(mut self, scale: f32) -> WoodTexture {
+        self.scale = scale;
+        self
+    }
+
+    /// Set the number of octaves of the turbulence.
+    pub fn with_octaves(mut self, octaves: u32) -> WoodTexture {
+        self.octaves = octaves;
+        self
+    }
+
+    /// Set the colors from which and to which every ring fades.
+    pub fn with_colors(mut self, first: Vec3, second: Vec3) -> WoodTexture {
+        self.colors = (first, second);
+        self
+    }
+}
+
+impl Texture for WoodTexture {
+    fn value(&self, _u: f32, _v: f32, p: &Vec3) -> Vec3 {
+        let offset = *p - self.center;
+        let radius = (offset - dot(&offset, &self.axis) * self.axis).length();
+        let grain = self.grain * self.noise.turbulence(&(self.scale * *p), self.octaves);
+        let rings = self.rings * radius + grain;
+        lerp(self.colors, rings - rings.floor())
+    }
+
+    fn warning(&self) -> Option<String> {
+        validate_attenuation(&self.colors.0).or_else(|| validate_attenuation(&self.colors.1))
+    }
+}
+
+/// Which distances to the feature points of Worley noise a `WorleyTexture`
+/// shows.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum WorleyFeature {
+    /// The distance to the nearest feature point, which gives round cells
+    /// darkest at their centers.
+    #[default]
+    F1,
+    /// The distance to the second nearest feature point.
+    F2,
+    /// The difference between both distances, which vanishes on the borders
+    /// between the cells and gives cracks.
+    F2MinusF1,
+}
+
+/// A cellular texture of the distances to the feature points of Worley noise.
+///
+/// The distance selected by the `WorleyFeature` is divided by its largest
+/// possible value, such that it lies between 0 and 1, and colors the point
+/// between two colors. As for marble, the pattern only depends on the
+/// position of the hit point.
+///
+/// ```
+/// # use raytracer::textures::{Texture, WorleyFeature, WorleyTexture};
+/// # use raytracer::vec3::Vec3;
+/// # use raytracer::worley::Worley;
+/// # use rand::rngs::StdRng;
+/// # use rand::SeedableRng;
+/// let worley = Worley::new(&mut StdRng::seed_from_u64(0));
+/// let mud = WorleyTexture::new(worley)
+///     .with_feature(WorleyFeature::F2MinusF1)
+///     .with_scale(3.)
+///     .with_colors(Vec3(0.1, 0.05, 0.), Vec3(0.6, 0.45, 0.3));
+/// let color = mud.value(0., 0., &Vec3(0.3, 0.2, 0.1));
+/// assert!(color.x() >= 0.1 && color.x() <= 0.6);
+/// ```
+#[derive(Debug, Clone)]
+pub struct WorleyTexture {
+    noise: Worley,
+    feature: WorleyFeature,
+    scale: f32,
+    colors: (Vec3, Vec3),
+}
+
+impl WorleyTexture {
+    /// Create a cellular texture from a noise generator.
+    ///
+    /// The texture shows the distance to the nearest feature point at a
+    /// scale of 1, from black to white.
+    pub fn new(noise: Worley) -> WorleyTexture {
+        WorleyTexture {
+            noise,
+            feature: WorleyFeature::default(),
+            scale: 1.,
+            colors: (Vec3(0., 0., 0.), Vec3(1., 1., 1.)),
+        }
+    }
+
+    /// Set which distances to the feature points are shown.
+    pub fn with_feature(mut self, feature: WorleyFeature) -> WorleyTexture {
+        self.feature = feature;
+        self
+    }
+
+    /// Set the frequency of the cells, i.e. larger scales give smaller cells.
+    pub fn with_scale(mut self, scale: f32) -> WorleyTexture {
+        self.scale = scale;
+        self
+    }
+
+    /// Set the colors at the smallest and the largest distance.
+    pub fn with_colors(mut self, first: Vec3, second: Vec3) -> WorleyTexture {
+        self.colors = (first, second);
+        self
+    }
+}
+
+impl Texture for WorleyTexture {
+    fn value(&self, _u: f32, _v: f32, p: &Vec3) -> Vec3 {
+        let (f1, f2) = self.noise.distances(&(self.scale * *p));
+        // The bounds of the distances over the cells around the point.
+        let t = match self.feature {
+            WorleyFeature::F1 => f1 / 3f32.sqrt(),
+            WorleyFeature::F2 => f2 / 6f32.sqrt(),
+            WorleyFeature::F2MinusF1 => (f2 - f1) / 6f32.sqrt(),
+        };
+        lerp(self.colors, t.clamp(0., 1.))
+    }
+
+    fn warning(&self) -> Option<String> {
+        validate_attenuation(&self.colors.0).or_else(|| validate_attenuation(&self.colors.1))
+    }
+}
+
+/// How the color of an image texture is looked up between the pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FilterMode {
+    /// The color of the pixel containing the point.
+    #[default]
+    Nearest,
+    /// The bilinear interpolation between the centers of the four pixels
+    /// closest to the point, which hides the pixels of close-ups.
+    Bilinear,
+}
+
+/// How an image texture is continued outside the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WrapMode {
+    /// Repeat the pixels at the edge of the image.
+    #[default]
+    Clamp,
+    /// Tile the image.
+    Repeat,
+    /// Tile the image, mirroring every other tile, such that the tiles meet
+    /// without a seam.
+    Mirror,
+}
+
+impl WrapMode {
+    // Map a pixel index, which may lie outside of the image, into the image
+    // of `size` pixels.
+    fn wrap(self, index: i64, size: usize) -> usize {
+        let size = size as i64;
+        let wrapped = match self {
+            WrapMode::Clamp => index.clamp(0, size - 1),
+            WrapMode::Repeat => index.rem_euclid(size),
+            WrapMode::Mirror => {
+                let index = index.rem_euclid(2 * size);
+                if index < size {
+                    index
+                } else {
+                    2 * size - 1 - index
+                }
+            }
+        };
+        wrapped as usize
+    }
+}
+
+/// A texture of an image, mapped with the surface coordinates.
+///
+/// The coordinate `u` runs from the left to the right edge of the image and
+/// `v` from its bottom to its top edge, such that an equirectangular image,
+/// e.g. a map of the Earth, wraps around a sphere with the north up.
+///
+/// By default, the color of the pixel containing the point is taken, and
+/// points outside the image take the color of the nearest edge pixel. Both
+/// can be changed with `with_filter` and `with_wrap`.
+///
+/// The 8-bit sRGB values of the image are decoded into linear colors once,
+/// when the image is loaded.
+///
+/// ```
+/// # use raytracer::textures::{FilterMode, ImageTexture, WrapMode};
+/// let tiles = ImageTexture::magenta()
+///     .with_filter(FilterMode::Bilinear)
+///     .with_wrap(WrapMode::Repeat, WrapMode::Clamp);
+/// ```
+#[derive(Clone)]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+    filter: FilterMode,
+    wrap: (WrapMode, WrapMode),
+}
+
+impl ImageTexture {
+    /// Load the image at `path`, in any format supported by the `image` crate.
+    ///
+    /// ```
+    /// # use raytracer::textures::ImageTexture;
+    /// assert!(ImageTexture::open("does/not/exist.png").is_err());
+    /// ```
+    pub fn open<P: AsRef<Path>>(path: P) -> image::ImageResult<ImageTexture> {
+        let image = image::open(path)?.into_rgb8();
+        Ok(ImageTexture::new(
+            image.width() as usize,
+            image.height() as usize,
+            image
+                .pixels()
+                .map(|pixel| Vec3::from_u8(pixel[0], pixel[1], pixel[2]))
+                .collect(),
+        ))
+    }
+
+    /// Load the image at `path` without converting its colors from sRGB, for
+    /// images holding data rather than colors, e.g. normal maps.
+    ///
+    /// ```
+    /// # use raytracer::textures::ImageTexture;
+    /// assert!(ImageTexture::open_linear("does/not/exist.png").is_err());
+    /// ```
+    pub fn open_linear<P: AsRef<Path>>(path: P) -> image::ImageResult<ImageTexture> {
+        let image = image::open(path)?.into_rgb8();
+        Ok(ImageTexture::new(
+            image.width() as usize,
+            image.height() as usize,
+            image
+                .pixels()
+                .map(|pixel| Vec3(pixel[0] as f32, pixel[1] as f32, pixel[2] as f32) / 255.)
+                .collect(),
+        ))
+    }
+
+    /// Load the image at `path` with the `options`, which select how its
+    /// colors are decoded and how it is looked up.
+    ///
+    /// ```
+    /// # use raytracer::textures::{ImageOptions, ImageTexture};
+    /// assert!(ImageTexture::open_with("does/not/exist.png", &ImageOptions::default()).is_err());
+    /// ```
+    pub fn open_with<P: AsRef<Path>>(
+        path: P,
+        options: &ImageOptions,
+    ) -> image::ImageResult<ImageTexture> {
+        let texture = if options.linear {
+            ImageTexture::open_linear(path)?
+        } else {
+            ImageTexture::open(path)?
+        };
+        Ok(texture
+            .with_filter(options.filter)
+            .with_wrap(options.wrap.0, options.wrap.1))
+    }
+
+    /// Create a magenta texture, to stand in for an image that failed to load.
+    ///
+    /// ```
+    /// # use raytracer::textures::{ImageTexture, Texture};
+    /// # use raytracer::vec3::Vec3;
+    /// let texture = ImageTexture::open("does/not/exist.png").unwrap_or_else(|error| {
+    ///     eprintln!("{}", error);
+    ///     ImageTexture::magenta()
+    /// });
+    /// assert_eq!(texture.value(0.3, 0.7, &Vec3(0., 0., 0.)), Vec3(1., 0., 1.));
+    /// ```
+    pub fn magenta() -> ImageTexture {
+        ImageTexture::new(1, 1, vec![Vec3(1., 0., 1.)])
+    }
+
+    // Create a texture of the linear colors of the rows of pixels from the
+    // top, with the default filter and wrap modes.
+    fn new(width: usize, height: usize, pixels: Vec<Vec3>) -> ImageTexture {
+        ImageTexture {
+            width,
+            height,
+            pixels,
+            filter: FilterMode::default(),
+            wrap: (WrapMode::default(), WrapMode::default()),
+        }
+    }
+
+    /// Set how the color is looked up between the pixels.
+    pub fn with_filter(mut self, filter: FilterMode) -> ImageTexture {
+        self.filter = filter;
+        self
+    }
+
+    /// Set how the image is continued outside of it, along `u` and `v`.
+    pub fn with_wrap(mut self, u: WrapMode, v: WrapMode) -> ImageTexture {
+        self.wrap = (u, v);
+        self
+    }
+
+    /// The width of the image in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the image in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+}
+
+// The pixels are left out, since there are too many to print.
+impl fmt::Debug for ImageTexture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ImageTexture")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("filter", &self.filter)
+            .field("wrap", &self.wrap)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f32, v: f32, _p: &Vec3) -> Vec3 {
+        // The position in pixels, where the rows go from the top to the bottom.
+        let x = u * self.width as f32;
+        let y = (1. - v) * self.height as f32;
+        let pixel = |i: f32, j: f32| {
+            let i = self.wrap.0.wrap(i as i64, self.width);
+            let j = self.wrap.1.wrap(j as i64, self.height);
+            self.pixels[j * self.width + i]
+        };
+        match self.filter {
+            FilterMode::Nearest => pixel(x.floor(), y.floor()),
+            FilterMode::Bilinear => {
+                // Interpolate between the centers of the pixels, which are
+                // at half-integer positions.
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (i, j) = (x.floor(), y.floor());
+                let (s, t) = (x - i, y - j);
+                (1. - t) * ((1. - s) * pixel(i, j) + s * pixel(i + 1., j))
+                    + t * ((1. - s) * pixel(i, j + 1.) + s * pixel(i + 1., j + 1.))
+            }
+        }
+    }
+}
+
+/// How an image texture is opened, see `ImageTexture::open_with`.
+///
+/// The default decodes the image as sRGB colors and looks it up like
+/// `ImageTexture::open` does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ImageOptions {
+    /// Whether the image holds data rather than colors, such that its values
+    /// are not converted from sRGB, see `ImageTexture::open_linear`.
+    pub linear: bool,
+    /// How the color is looked up between the pixels.
+    pub filter: FilterMode,
+    /// How the image is continued outside of it, along `u` and `v`.
+    pub wrap: (WrapMode, WrapMode),
+}
+
+// The texture of an image in a `TextureCache`, which is empty until the image
+// is loaded, and whose lock is held while it is.
+type CacheSlot = Arc<Mutex<Option<Arc<ImageTexture>>>>;
+
+/// A cache of image textures, such that materials using the same image share
+/// a single copy of its pixels.
+///
+/// Images are identified by their canonical path and the `ImageOptions` they
+/// are opened with. The cache may be shared between threads: loads of the
+/// same image wait for the first one to decode it, while different images
+/// are decoded in parallel. The textures stay alive as long as materials hold
+/// them, even after the cache is cleared.
+///
+/// ```
+/// # use raytracer::materials::Lambertian;
+/// # use raytracer::textures::{ImageOptions, TextureCache};
+/// # use std::sync::Arc;
+/// let cache = TextureCache::new();
+/// if let Ok(albedo) = cache.open("earth.jpg", ImageOptions::default()) {
+///     // Both materials use the same pixels.
+///     let matte = Lambertian::with_texture(albedo);
+///     let again = Lambertian::with_texture(cache.open("earth.jpg", ImageOptions::default()).unwrap());
+///     assert_eq!(cache.len(), 1);
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct TextureCache {
+    slots: Mutex<HashMap<(PathBuf, ImageOptions), CacheSlot>>,
+}
+
+impl TextureCache {
+    /// Create an empty cache.
+    pub fn new() -> TextureCache {
+        TextureCache::default()
+    }
+
+    /// Return the texture of the image at `path` opened with the `options`,
+    /// loading it unless it is in the cache already.
+    ///
+    /// Return an error if the image cannot be loaded, in which case the next
+    /// call tries to load it again.
+    pub fn open<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: ImageOptions,
+    ) -> image::ImageResult<Arc<dyn Texture>> {
+        let path = fs::canonicalize(path).map_err(image::ImageError::IoError)?;
+        // Only hold the lock of the cache to find the slot, such that the
+        // image is decoded without blocking the loads of other images.
+        let slot = self
+            .slots
+            .lock()
+            .unwrap()
+            .entry((path.clone(), options))
+            .or_default()
+            .clone();
+        let mut texture = slot.lock().unwrap();
+        if let Some(texture) = texture.as_ref() {
+            return Ok(texture.clone());
+        }
+        let loaded = Arc::new(ImageTexture::open_with(&path, &options)?);
+        *texture = Some(loaded.clone());
+        Ok(loaded)
+    }
+
+    /// Return the number of images in the cache.
+    pub fn len(&self) -> usize {
+        self.textures().len()
+    }
+
+    /// Return whether there are no images in the cache.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the number of bytes taken by the pixels of the images in the
+    /// cache.
+    pub fn memory_usage(&self) -> usize {
+        self.textures()
+            .iter()
+            .map(|texture| texture.pixels.len() * mem::size_of::<Vec3>())
+            .sum()
+    }
+
+    /// Remove all images from the cache, which frees their pixels once no
+    /// material uses them anymore.
+    pub fn clear(&self) {
+        self.slots.lock().unwrap().clear();
+    }
+
+    // The loaded textures, waiting for the images being loaded.
+    fn textures(&self) -> Vec<Arc<ImageTexture>> {
+        let slots: Vec<CacheSlot> = self.slots.lock().unwrap().values().cloned().collect();
+        slots
+            .iter()
+            .filter_map(|slot| slot.lock().unwrap().clone())
+            .collect()
+    }
+}
+
+/// A texture whose surface coordinates are transformed before the lookup.
+///
+/// The transformations are applied to `(u, v)` in the order in which they
+/// are added, and the point is passed on unchanged, such that procedural
+/// textures of the point are not affected. Wrapping a `UvTransform` in
+/// another one applies the inner transformations after the outer ones.
+///
+/// ```
+/// # use raytracer::textures::{ImageTexture, UvTransform, WrapMode};
+/// # use std::sync::Arc;
+/// // Repeat an image 4 times along u and twice along v, shifted by half an image.
+/// let tile = ImageTexture::magenta().with_wrap(WrapMode::Repeat, WrapMode::Repeat);
+/// let floor = UvTransform::new(Arc::new(tile)).tile(4., 2.).offset(0.5, 0.);
+/// ```
+#[derive(Clone)]
+pub struct UvTransform {
+    inner: Arc<dyn Texture>,
+    transform: Mat4,
+}
+
+impl UvTransform {
+    /// Wrap `inner` without transforming its coordinates yet.
+    pub fn new(inner: Arc<dyn Texture>) -> UvTransform {
+        UvTransform {
+            inner,
+            transform: Mat4::identity(),
+        }
+    }
+
+    /// Scale `u` and `v`, such that the inner texture repeats `u_count` and
+    /// `v_count` times over the surface if it wraps with `WrapMode::Repeat`.
+    pub fn tile(self, u_count: f32, v_count: f32) -> UvTransform {
+        self.then(Mat4::scaling(Vec3(u_count, v_count, 1.)))
+    }
+
+    /// Shift `u` and `v` by `du` and `dv`.
+    pub fn offset(self, du: f32, dv: f32) -> UvTransform {
+        self.then(Mat4::translation(Vec3(du, dv, 0.)))
+    }
+
+    /// Rotate `(u, v)` around `(0, 0)` by `degrees`, counter-clockwise.
+    pub fn rotate(self, degrees: f32) -> UvTransform {
+        self.then(Mat4::rotation_z(degrees))
+    }
+
+    // Apply `transform` after the transformations so far.
+    fn then(mut self, transform: Mat4) -> UvTransform {
+        self.transform = transform * self.transform;
+        self
+    }
+}
+
+// The inner texture need not be `Debug`.
+impl fmt::Debug for UvTransform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UvTransform")
+            .field("transform", &self.transform)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Texture for UvTransform {
+    fn value(&self, u: f32, v: f32, p: &Vec3) -> Vec3 {
+        let uv = self.transform.transform_point(&Vec3(u, v, 0.));
+        self.inner.value(uv.x(), uv.y(), p)
+    }
+
+    fn value_at_hit(&self, hit: &HitRecord) -> Vec3 {
+        // Forward the whole hit, such that the inner texture sees the normal.
+        let uv = self.transform.transform_point(&Vec3(hit.u, hit.v, 0.));
+        self.inner.value_at_hit(&HitRecord {
+            u: uv.x(),
+            v: uv.y(),
+            ..*hit
+        })
+    }
+
+    fn warning(&self) -> Option<String> {
+        self.inner.warning()
+    }
+}
+
+/// A texture projecting an inner texture onto a surface along the three axes,
+/// for objects without surface coordinates, e.g. imported meshes.
+///
+/// The inner texture is looked up three times, with the coordinates of the
+/// hit point in the YZ, XZ and XY planes as `(u, v)`, i.e. `(z, y)`, `(x, z)`
+/// and `(x, y)`, and the colors are blended by the absolute components of the
+/// normal, raised to the power `sharpness`. On a face perpendicular to an
+/// axis, this is the projection along that axis only, such that the texture
+/// is not stretched. Larger sharpnesses narrow the blend between the faces
+/// of a curved surface.
+///
+/// The plain `value`, which has no normal, blends the three projections
+/// equally.
+///
+/// ```
+/// # use raytracer::textures::{ImageTexture, Triplanar, WrapMode};
+/// # use std::sync::Arc;
+/// // Tile an image every 2 units on all sides of a mesh.
+/// let tile = ImageTexture::magenta().with_wrap(WrapMode::Repeat, WrapMode::Repeat);
+/// let bricks = Triplanar::new(Arc::new(tile)).with_scale(0.5).with_sharpness(8.);
+/// ```
+#[derive(Clone)]
+pub struct Triplanar {
+    inner: Arc<dyn Texture>,
+    scale: f32,
+    sharpness: f32,
+}
+
+impl Triplanar {
+    /// Project `inner` at a scale of 1 with a sharpness of 4.
+    pub fn new(inner: Arc<dyn Texture>) -> Triplanar {
+        Triplanar {
+            inner,
+            scale: 1.,
+            sharpness: 4.,
+        }
+    }
+
+    /// Set the factor by which the coordinates of the hit point are
+    /// multiplied, i.e. the number of repetitions of the inner texture per
+    /// unit length if it repeats over 0–1.
+    pub fn with_scale(mut self, scale: f32) -> Triplanar {
+        self.scale = scale;
+        self
+    }
+
+    /// Set the power of the normal components blending the projections,
+    /// which is at least 0. A sharpness of 0 blends them equally.
+    pub fn with_sharpness(mut self, sharpness: f32) -> Triplanar {
+        self.sharpness = sharpness.max(0.);
+        self
+    }
+
+    /// Access the scale of the projections.
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Access the sharpness of the blend.
+    pub fn sharpness(&self) -> f32 {
+        self.sharpness
+    }
+
+    // The weights of the projections along x, y and z for the `normal`,
+    // which sum to 1. Normals of zero length weigh them equally.
+    fn weights(&self, normal: &Vec3) -> Vec3 {
+        let length = normal.length();
+        let n = if length > 0. {
+            *normal / length
+        } else {
+            *normal
+        };
+        let weights = Vec3(
+            n.x().abs().powf(self.sharpness),
+            n.y().abs().powf(self.sharpness),
+            n.z().abs().powf(self.sharpness),
+        );
+        // The largest component of a unit normal is at least 1/√3, such that
+        // the sum only vanishes for a zero normal or an underflowing power.
+        let total = weights.x() + weights.y() + weights.z();
+        if total > 0. {
+            weights / total
+        } else {
+            Vec3(1., 1., 1.) / 3.
+        }
+    }
+
+    // Blend the lookups of the inner texture by `lookup` at the coordinates
+    // of the three projections of `p`, weighed for the `normal`.
+    fn blend(&self, p: &Vec3, normal: &Vec3, lookup: impl Fn(f32, f32) -> Vec3) -> Vec3 {
+        let p = self.scale * *p;
+        let weights = self.weights(normal);
+        let projections = [
+            (weights.x(), p.z(), p.y()),
+            (weights.y(), p.x(), p.z()),
+            (weights.z(), p.x(), p.y()),
+        ];
+        // Projections of no weight are skipped, which saves lookups on faces
+        // perpendicular to an axis.
+        projections
+            .iter()
+            .filter(|&&(weight, _, _)| weight > 0.)
+            .fold(Vec3(0., 0., 0.), |color, &(weight, u, v)| {
+                color + weight * lookup(u, v)
+            })
+    }
+}
+
+// The inner texture need not be `Debug`.
+impl fmt::Debug for Triplanar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Triplanar")
+            .field("scale", &self.scale)
+            .field("sharpness", &self.sharpness)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Texture for Triplanar {
+    fn value(&self, _u: f32, _v: f32, p: &Vec3) -> Vec3 {
+        self.blend(p, &Vec3(0., 0., 0.), |u, v| self.inner.value(u, v, p))
+    }
+
+    fn value_at_hit(&self, hit: &HitRecord) -> Vec3 {
+        self.blend(&hit.point_at_parameter, &hit.normal, |u, v| {
+            self.inner.value_at_hit(&HitRecord { u, v, ..*hit })
+        })
+    }
+
+    fn warning(&self) -> Option<String> {
+        self.inner.warning()
+    }
+}
+
+/// What drives the position along a `RampTexture`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RampInput {
+    /// The height of the point along `axis`, which runs from 0 at the height
+    /// `min` to 1 at the height `max`. The axis need not be normalized.
+    Height { axis: Vec3, min: f32, max: f32 },
+    /// How much the normal faces `up`, which runs from 0 where it points
+    /// away from it to 1 where it points along it. Lookups without a normal
+    /// read 0.5, as for a normal perpendicular to `up`.
+    Facing { up: Vec3 },
+    /// The distance of the point from `center`, which runs from 0 at the
+    /// center to 1 at the distance `radius`.
+    Distance { center: Vec3, radius: f32 },
+}
+
+impl RampInput {
+    // The position along the ramp of the point `p` with the unit `normal`,
+    // if there is one.
+    fn position(&self, p: &Vec3, normal: Option<&Vec3>) -> f32 {
+        match self {
+            RampInput::Height { axis, min, max } => {
+                (dot(p, &unit_vector(axis)) - min) / (max - min)
+            }
+            RampInput::Facing { up } => match normal {
+                Some(normal) => 0.5 * (1. + dot(&unit_vector(normal), &unit_vector(up))),
+                None => 0.5,
+            },
+            RampInput::Distance { center, radius } => (*p - *center).length() / radius,
+        }
+    }
+}
+
+/// Error returned when a texture is created from invalid parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextureError {
+    /// A ramp has no stops.
+    NoStops,
+    /// The stop at `index` of a ramp lies before the one preceding it.
+    UnsortedStops { index: usize },
+    /// The position of a stop of a ramp is not between 0 and 1.
+    StopOutOfRange { position: f32 },
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextureError::NoStops => write!(f, "a ramp needs at least one stop"),
+            TextureError::UnsortedStops { index } => write!(
+                f,
+                "stop {} of the ramp lies before the previous one: stops must be sorted",
+                index
+            ),
+            TextureError::StopOutOfRange { position } => write!(
+                f,
+                "invalid stop position {}: must be between 0 and 1",
+                position
+            ),
+        }
+    }
+}
+
+impl Error for TextureError {}
+
+/// A texture interpolating linearly between colors at stops along a ramp.
+///
+/// The position along the ramp is computed from the hit point as selected by
+/// the `RampInput`. Between the first and the last stop, the color is
+/// interpolated between the two stops around the position. Two stops at the
+/// same position make a hard edge. Positions outside of 0–1 are brought back
+/// according to the `WrapMode`, which clamps them by default.
+///
+/// ```
+/// # use raytracer::textures::{RampInput, RampTexture, Texture};
+/// # use raytracer::vec3::Vec3;
+/// // A sky from white at the horizon to blue at a height of 10.
+/// let input = RampInput::Height { axis: Vec3(0., 1., 0.), min: 0., max: 10. };
+/// let stops = vec![(0., Vec3(1., 1., 1.)), (1., Vec3(0.5, 0.5, 1.))];
+/// let sky = RampTexture::new(stops, input).unwrap();
+/// assert_eq!(sky.value(0., 0., &Vec3(3., 5., 0.)), Vec3(0.75, 0.75, 1.));
+/// assert_eq!(sky.value(0., 0., &Vec3(3., 20., 0.)), Vec3(0.5, 0.5, 1.));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RampTexture {
+    stops: Vec<(f32, Vec3)>,
+    input: RampInput,
+    wrap: WrapMode,
+}
+
+impl RampTexture {
+    /// Create a ramp of the `stops`, each a position between 0 and 1 and the
+    /// color at it, driven by `input`.
+    ///
+    /// Return an error if there are no stops, if a position is outside of 0–1
+    /// or if the positions are not sorted.
+    ///
+    /// ```
+    /// # use raytracer::textures::{RampInput, RampTexture, TextureError};
+    /// # use raytracer::vec3::Vec3;
+    /// let input = RampInput::Facing { up: Vec3(0., 1., 0.) };
+    /// let stops = vec![(0.6, Vec3(0., 0., 0.)), (0.4, Vec3(1., 1., 1.))];
+    /// let error = RampTexture::new(stops, input).unwrap_err();
+    /// assert_eq!(error, TextureError::UnsortedStops { index: 1 });
+    /// ```
+    pub fn new(stops: Vec<(f32, Vec3)>, input: RampInput) -> Result<RampTexture, TextureError> {
+        if stops.is_empty() {
+            return Err(TextureError::NoStops);
+        }
+        if let Some(&(position, _)) = stops
+            .iter()
+            .find(|(position, _)| !(0. ..=1.).contains(position))
+        {
+            return Err(TextureError::StopOutOfRange { position });
+        }
+        if let Some(index) = (1..stops.len()).find(|&i| stops[i].0 < stops[i - 1].0) {
+            return Err(TextureError::UnsortedStops { index });
+        }
+        Ok(RampTexture {
+            stops,
+            input,
+            wrap: WrapMode::default(),
+        })
+    }
+
+    /// Set how positions outside of 0–1 are brought back onto the ramp.
+    pub fn with_wrap(mut self, wrap: WrapMode) -> RampTexture {
+        self.wrap = wrap;
+        self
+    }
+
+    /// Access the stops of the ramp.
+    pub fn stops(&self) -> &[(f32, Vec3)] {
+        &self.stops
+    }
+
+    /// Access what drives the position along the ramp.
+    pub fn input(&self) -> &RampInput {
+        &self.input
+    }
+
+    // The color at the `position` along the ramp.
+    fn color(&self, position: f32) -> Vec3 {
+        let t = match self.wrap {
+            WrapMode::Clamp => position.clamp(0., 1.),
+            WrapMode::Repeat => position.rem_euclid(1.),
+            WrapMode::Mirror => 1. - (position.rem_euclid(2.) - 1.).abs(),
+        };
+        // The first stop after `t`, such that equal positions give the later
+        // color at the edge.
+        let next = self.stops.partition_point(|&(position, _)| position <= t);
+        if next == 0 {
+            self.stops[0].1
+        } else if next == self.stops.len() {
+            self.stops[next - 1].1
+        } else {
+            let ((start, first), (end, second)) = (self.stops[next - 1], self.stops[next]);
+            lerp((first, second), (t - start) / (end - start))
+        }
+    }
+}
+
+impl Texture for RampTexture {
+    fn value(&self, _u: f32, _v: f32, p: &Vec3) -> Vec3 {
+        self.color(self.input.position(p, None))
+    }
+
+    fn value_at_hit(&self, hit: &HitRecord) -> Vec3 {
+        let position = self
+            .input
+            .position(&hit.point_at_parameter, Some(&hit.normal));
+        self.color(position)
+    }
+
+    fn warning(&self) -> Option<String> {
+        self.stops
+            .iter()
+            .find_map(|(_, color)| validate_attenuation(color))
+    }
+}
+
+// Interpolate linearly between two colors, `t` being between 0 and 1.
+fn lerp((first, second): (Vec3, Vec3), t: f32) -> Vec3 {
+    (1. - t) * first + t * second
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Lambertian;
+    use crate::objects::cuboid::Cuboid;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::Hitable;
+    use crate::ray::Ray;
+    use rand::rngs::SmallRng;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::sync::Arc;
+
+    // A texture which is red for x < 0 and blue otherwise.
+    struct TwoColors;
+
+    impl Texture for TwoColors {
+        fn value(&self, _u: f32, _v: f32, p: &Vec3) -> Vec3 {
+            if p.x() < 0. {
+                Vec3(0.9, 0.1, 0.1)
+            } else {
+                Vec3(0.1, 0.1, 0.9)
+            }
+        }
+    }
+
+    #[test]
+    // Test that a Lambertian scatters with the color of its texture at the hit point.
+    fn test_lambertian_texture() {
+        let material = Arc::new(Lambertian::with_texture(Arc::new(TwoColors)));
+        let sphere = Sphere::new(Vec3(0., 0., 0.), 1., material);
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut attenuation = |origin: Vec3| {
+            let ray = Ray::new(origin, -origin);
+            let hit = sphere.intersect(&ray, 0.001, f32::MAX).unwrap();
+            hit.material.scatter(&ray, &hit, &mut rng).unwrap().1
+        };
+        assert_eq!(attenuation(Vec3(-3., 0., 0.)), Vec3(0.9, 0.1, 0.1));
+        assert_eq!(attenuation(Vec3(3., 0., 0.)), Vec3(0.1, 0.1, 0.9));
+    }
+
+    #[test]
+    // Test that a solid color warns about colors outside the 0–1 range.
+    fn test_solid_color_warning() {
+        assert!(SolidColor::new(Vec3(0.5, 0.5, 0.5)).warning().is_none());
+        assert!(SolidColor::new(Vec3(0.5, 255., 0.5)).warning().is_some());
+        assert!(TwoColors.warning().is_none());
+    }
+
+    #[test]
+    // Test that a noise texture gives valid colors, identical for the same seed.
+    fn test_noise_texture() {
+        let texture =
+            |seed| NoiseTexture::new(Perlin::new(&mut StdRng::seed_from_u64(seed)), 3., 5);
+        let (first, second) = (texture(5), texture(5));
+        for i in 0..100 {
+            let p = Vec3(0.13 * i as f32, -0.07 * i as f32, 0.5);
+            let color = first.value(0., 0., &p);
+            assert!((0. ..=1.).contains(&color.x()));
+            assert_eq!(color.x(), color.z());
+            assert_eq!(color, second.value(0., 0., &p));
+        }
+    }
+
+    // The points of a grid through a few lattice cells of the noise.
+    fn grid() -> impl Iterator<Item = Vec3> {
+        (0..1000).map(|i| {
+            Vec3(
+                -1.3 + 0.031 * (i % 10) as f32,
+                -0.7 + 0.029 * (i / 10 % 10) as f32,
+                0.4 + 0.037 * (i / 100) as f32,
+            )
+        })
+    }
+
+    // Check that all colors of a texture on the grid lie between the two
+    // colors, and that the pattern varies.
+    fn check_between(texture: &dyn Texture, first: Vec3, second: Vec3) {
+        let mut values = Vec::new();
+        for p in grid() {
+            let color = texture.value(0., 0., &p);
+            for (c, (a, b)) in [
+                (color.x(), (first.x(), second.x())),
+                (color.y(), (first.y(), second.y())),
+                (color.z(), (first.z(), second.z())),
+            ]
+            .iter()
+            {
+                assert!(
+                    *c >= a.min(*b) - 1e-6 && *c <= a.max(*b) + 1e-6,
+                    "color {:?} at {:?} is not between {:?} and {:?}",
+                    color,
+                    p,
+                    first,
+                    second
+                );
+            }
+            values.push(color.x());
+        }
+        let min = values.iter().cloned().fold(f32::MAX, f32::min);
+        let max = values.iter().cloned().fold(f32::MIN, f32::max);
+        assert!(max - min > 0.5 * (first.x() - second.x()).abs());
+    }
+
+    #[test]
+    // Test that marble and wood interpolate between their two colors.
+    fn test_procedural_colors() {
+        let perlin = || Perlin::new(&mut StdRng::seed_from_u64(3));
+        let (light, dark) = (Vec3(0.9, 0.8, 0.7), Vec3(0.2, 0.3, 0.1));
+        let marble = MarbleTexture::new(perlin())
+            .with_axis(Vec3(1., 1., 0.))
+            .with_colors(light, dark);
+        check_between(&marble, light, dark);
+        assert!(marble.warning().is_none());
+        check_between(
+            &MarbleTexture::new(perlin()),
+            Vec3(0.9, 0.9, 0.9),
+            Vec3(0.1, 0.1, 0.1),
+        );
+        let wood = WoodTexture::new(perlin())
+            .with_axis(Vec3(0., 0., 1.), Vec3(0., 1., 1.))
+            .with_colors(light, dark);
+        check_between(&wood, light, dark);
+        assert!(wood.warning().is_none());
+        check_between(
+            &WoodTexture::new(perlin()),
+            Vec3(0.75, 0.55, 0.3),
+            Vec3(0.4, 0.23, 0.1),
+        );
+        let bright = WoodTexture::new(perlin()).with_colors(light, Vec3(0., 128., 0.));
+        assert!(bright.warning().is_some());
+    }
+
+    #[test]
+    // Test that marble and wood are identical for the same seed only.
+    fn test_procedural_seed() {
+        let perlin = |seed| Perlin::new(&mut StdRng::seed_from_u64(seed));
+        let textures = |seed| -> [Box<dyn Texture>; 2] {
+            [
+                Box::new(MarbleTexture::new(perlin(seed))),
+                Box::new(WoodTexture::new(perlin(seed))),
+            ]
+        };
+        let (first, second, other) = (textures(8), textures(8), textures(9));
+        for i in 0..2 {
+            let value = |texture: &[Box<dyn Texture>; 2], p: &Vec3| texture[i].value(0., 0., p);
+            assert!(grid().all(|p| value(&first, &p) == value(&second, &p)));
+            assert!(grid().any(|p| value(&first, &p) != value(&other, &p)));
+        }
+    }
+
+    #[test]
+    // Test that an image wraps around a sphere with the north pole at the top.
+    fn test_image_texture_sphere() {
+        // The top row is red, green, blue and white, the bottom row black,
+        // cyan, magenta and yellow.
+        #[rustfmt::skip]
+        let bytes = [
+            255, 0, 0,  0, 255, 0,    0, 0, 255,  255, 255, 255,
+            0, 0, 0,    0, 255, 255,  255, 0, 255,  255, 255, 0,
+        ];
+        let path = std::env::temp_dir().join("raytracer_test_image_texture.png");
+        image::save_buffer(&path, &bytes, 4, 2, image::ColorType::Rgb8).unwrap();
+        let texture = ImageTexture::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((texture.width(), texture.height()), (4, 2));
+
+        let material = Arc::new(Lambertian::with_texture(Arc::new(texture)));
+        let sphere = Sphere::new(Vec3(0., 0., 0.), 1., material);
+        let mut rng = SmallRng::seed_from_u64(0);
+        // The color seen by a ray from `origin` towards the sphere.
+        let mut color = |origin: Vec3, direction: Vec3| {
+            let ray = Ray::new(origin, direction);
+            let hit = sphere.intersect(&ray, 0.001, f32::MAX).unwrap();
+            hit.material.scatter(&ray, &hit, &mut rng).unwrap().1
+        };
+        // The poles, at u = 0.5, are in the third column.
+        assert_eq!(color(Vec3(0., 3., 0.), Vec3(0., -1., 0.)), Vec3(0., 0., 1.));
+        assert_eq!(color(Vec3(0., -3., 0.), Vec3(0., 1., 0.)), Vec3(1., 0., 1.));
+        // Just north of the equator, around the sphere.
+        let north = |x, z| (Vec3(x, 0.3, z), Vec3(-x, 0., -z));
+        let (origin, direction) = north(-3., 0.);
+        assert_eq!(color(origin, direction), Vec3(1., 0., 0.));
+        let (origin, direction) = north(0., 3.);
+        assert_eq!(color(origin, direction), Vec3(0., 1., 0.));
+        let (origin, direction) = north(3., 0.);
+        assert_eq!(color(origin, direction), Vec3(0., 0., 1.));
+        let (origin, direction) = north(0., -3.);
+        assert_eq!(color(origin, direction), Vec3(1., 1., 1.));
+        // Just south of the equator.
+        assert_eq!(
+            color(Vec3(0., -0.3, 3.), Vec3(0., 0., -1.)),
+            Vec3(0., 1., 1.)
+        );
+        assert_eq!(
+            color(Vec3(0., -0.3, -3.), Vec3(0., 0., 1.)),
+            Vec3(1., 1., 0.)
+        );
+    }
+
+    #[test]
+    // Test that an image texture clamps at its borders and decodes sRGB, unless
+    // it is loaded as data.
+    fn test_image_texture_borders() {
+        let bytes = [128, 0, 0, 0, 0, 128];
+        let path = std::env::temp_dir().join("raytracer_test_image_texture_borders.png");
+        image::save_buffer(&path, &bytes, 2, 1, image::ColorType::Rgb8).unwrap();
+        let texture = ImageTexture::open(&path).unwrap();
+        let linear = ImageTexture::open_linear(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let p = Vec3(0., 0., 0.);
+        assert_eq!(linear.value(0.25, 0.5, &p), Vec3(128. / 255., 0., 0.));
+        let (left, right) = (Vec3::from_u8(128, 0, 0), Vec3::from_u8(0, 0, 128));
+        assert!(left.x() < 0.25);
+        assert_eq!(texture.value(-1., 0.5, &p), left);
+        assert_eq!(texture.value(0.2, 2., &p), left);
+        assert_eq!(texture.value(0.8, -1., &p), right);
+        assert_eq!(texture.value(1., 0.5, &p), right);
+        assert_eq!(texture.value(f32::NAN, 0.5, &p), left);
+    }
+
+    #[test]
+    // Test that a cache loads an image once per canonical path and options,
+    // also when it is opened concurrently, and loads it again once cleared.
+    fn test_texture_cache() {
+        use rayon::prelude::*;
+
+        let bytes = [255, 0, 0, 0, 0, 255, 0, 255, 0, 255, 255, 255];
+        let directory = std::env::temp_dir();
+        let name = "raytracer_test_texture_cache.png";
+        let path = directory.join(name);
+        image::save_buffer(&path, &bytes, 2, 2, image::ColorType::Rgb8).unwrap();
+
+        let cache = TextureCache::new();
+        assert!(cache.is_empty());
+        let options = ImageOptions::default();
+        let first = cache.open(&path, options).unwrap();
+        let again = cache.open(directory.join(".").join(name), options).unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+        let concurrent: Vec<_> = (0..16)
+            .into_par_iter()
+            .map(|_| cache.open(&path, options).unwrap())
+            .collect();
+        assert!(concurrent
+            .iter()
+            .all(|texture| Arc::ptr_eq(&first, texture)));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.memory_usage(), 4 * mem::size_of::<Vec3>());
+
+        let linear = ImageOptions {
+            linear: true,
+            ..ImageOptions::default()
+        };
+        let data = cache.open(&path, linear).unwrap();
+        assert!(!Arc::ptr_eq(&first, &data));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.memory_usage(), 8 * mem::size_of::<Vec3>());
+
+        cache.clear();
+        assert!(cache.is_empty());
+        let reloaded = cache.open(&path, options).unwrap();
+        assert!(!Arc::ptr_eq(&first, &reloaded));
+        let p = Vec3(0., 0., 0.);
+        assert_eq!(first.value(0.25, 0.75, &p), Vec3(1., 0., 0.));
+        assert_eq!(reloaded.value(0.25, 0.75, &p), Vec3(1., 0., 0.));
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(cache.open(&path, options).is_err());
+        assert_eq!(cache.len(), 1);
+    }
+
+    // An image texture of the given pixels, one row from the top after the other.
+    fn image(width: usize, height: usize, pixels: &[Vec3]) -> ImageTexture {
+        ImageTexture::new(width, height, pixels.to_vec())
+    }
+
+    #[test]
+    // Test that bilinear filtering averages the pixels between their centers.
+    fn test_image_texture_bilinear() {
+        let p = Vec3(0., 0., 0.);
+        let (red, blue) = (Vec3(1., 0., 0.), Vec3(0., 0., 1.));
+        let texture = image(2, 1, &[red, blue]).with_filter(FilterMode::Bilinear);
+        assert_eq!(texture.value(0.5, 0.5, &p), Vec3(0.5, 0., 0.5));
+        assert_eq!(texture.value(0.25, 0.5, &p), red);
+        assert_eq!(texture.value(0.75, 0.5, &p), blue);
+        assert_eq!(texture.value(0.375, 0.5, &p), Vec3(0.75, 0., 0.25));
+        // Nearest neighbors switch at the pixel edge instead.
+        let nearest = image(2, 1, &[red, blue]);
+        assert_eq!(nearest.value(0.49, 0.5, &p), red);
+        assert_eq!(nearest.value(0.51, 0.5, &p), blue);
+
+        // The wrap modes of the two axes are independent: the top row is
+        // red and blue, the bottom row green and black.
+        let (green, black) = (Vec3(0., 1., 0.), Vec3(0., 0., 0.));
+        let texture = image(2, 2, &[red, blue, green, black])
+            .with_filter(FilterMode::Bilinear)
+            .with_wrap(WrapMode::Repeat, WrapMode::Clamp);
+        // Across the seam between the right and left edges.
+        assert_eq!(texture.value(0., 0.75, &p), Vec3(0.5, 0., 0.5));
+        assert_eq!(texture.value(1., 0.25, &p), Vec3(0., 0.5, 0.));
+        // Above the top edge, the top row is continued.
+        assert_eq!(texture.value(0.25, 1., &p), red);
+        assert_eq!(texture.value(0.25, 3.5, &p), red);
+        assert_eq!(texture.value(0.25, 0.5, &p), Vec3(0.5, 0.5, 0.));
+    }
+
+    #[test]
+    // Test the colors at and beyond the right edge of an image in every wrap mode.
+    fn test_image_texture_wrap_modes() {
+        let p = Vec3(0., 0., 0.);
+        let (red, green, blue) = (Vec3(1., 0., 0.), Vec3(0., 1., 0.), Vec3(0., 0., 1.));
+        let texture = |filter, wrap| {
+            image(3, 1, &[red, green, blue])
+                .with_filter(filter)
+                .with_wrap(wrap, WrapMode::Clamp)
+        };
+        let nearest = |wrap, u| texture(FilterMode::Nearest, wrap).value(u, 0.5, &p);
+        assert_eq!(nearest(WrapMode::Clamp, 1.), blue);
+        assert_eq!(nearest(WrapMode::Repeat, 1.), red);
+        assert_eq!(nearest(WrapMode::Mirror, 1.), blue);
+        // In the second tile to the right of the image.
+        assert_eq!(nearest(WrapMode::Clamp, 11. / 6.), blue);
+        assert_eq!(nearest(WrapMode::Repeat, 11. / 6.), blue);
+        assert_eq!(nearest(WrapMode::Mirror, 11. / 6.), red);
+        assert_eq!(nearest(WrapMode::Repeat, -0.1), blue);
+        assert_eq!(nearest(WrapMode::Mirror, -0.1), red);
+
+        let bilinear = |wrap, u| texture(FilterMode::Bilinear, wrap).value(u, 0.5, &p);
+        assert_eq!(bilinear(WrapMode::Clamp, 1.), blue);
+        assert_eq!(bilinear(WrapMode::Repeat, 1.), Vec3(0.5, 0., 0.5));
+        assert_eq!(bilinear(WrapMode::Mirror, 1.), blue);
+        assert_eq!(bilinear(WrapMode::Clamp, 5.), blue);
+        assert_eq!(bilinear(WrapMode::Clamp, -5.), red);
+    }
+
+    // A checker of black and white squares of size 0.5 in UV space.
+    struct UvChecker;
+
+    impl Texture for UvChecker {
+        fn value(&self, u: f32, v: f32, _p: &Vec3) -> Vec3 {
+            let parity = ((2. * u).floor() + (2. * v).floor()).rem_euclid(2.);
+            Vec3(parity, parity, parity)
+        }
+    }
+
+    // A texture whose color is the point it is looked up at.
+    struct Position;
+
+    impl Texture for Position {
+        fn value(&self, _u: f32, _v: f32, p: &Vec3) -> Vec3 {
+            *p
+        }
+    }
+
+    // A texture whose color is the surface coordinates it is looked up at.
+    struct Uv;
+
+    impl Texture for Uv {
+        fn value(&self, u: f32, v: f32, _p: &Vec3) -> Vec3 {
+            Vec3(u, v, 0.)
+        }
+    }
+
+    #[test]
+    // Test that tiling by 2 halves the period of a checker in UV space.
+    fn test_uv_transform_tile() {
+        let p = Vec3(0., 0., 0.);
+        let tiled = UvTransform::new(Arc::new(UvChecker)).tile(2., 2.);
+        for i in 0..16 {
+            for j in 0..16 {
+                let (u, v) = (0.03 + i as f32 / 16., 0.03 + j as f32 / 16.);
+                let color = tiled.value(u, v, &p);
+                assert_eq!(color, UvChecker.value(2. * u, 2. * v, &p));
+                // The period of the tiled checker is 0.5 instead of 1.
+                assert_eq!(color, tiled.value(u + 0.5, v, &p));
+                assert_eq!(color, tiled.value(u, v + 0.5, &p));
+                assert_ne!(color, tiled.value(u + 0.25, v, &p));
+            }
+        }
+    }
+
+    #[test]
+    // Test that transformations compose in order and keep the point.
+    fn test_uv_transform_compose() {
+        let p = Vec3(0.1, 0.2, 0.3);
+        let close = |a: Vec3, b: Vec3| (a - b).length() < 1e-5;
+        let uv = |texture: &dyn Texture, u, v| texture.value(u, v, &p);
+
+        let shifted = UvTransform::new(Arc::new(Uv)).tile(4., 2.).offset(0.5, 0.);
+        assert!(close(uv(&shifted, 0.25, 0.25), Vec3(1.5, 0.5, 0.)));
+        let rotated = UvTransform::new(Arc::new(Uv)).rotate(90.).offset(1., 0.);
+        assert!(close(uv(&rotated, 1., 0.), Vec3(1., 1., 0.)));
+
+        // A transform of a transform applies both, the outer one first.
+        let inner = UvTransform::new(Arc::new(Uv)).offset(0.5, 0.);
+        let outer = UvTransform::new(Arc::new(inner)).tile(4., 2.);
+        for &(u, v) in &[(0., 0.), (0.25, 0.25), (0.7, -0.3)] {
+            assert!(close(uv(&outer, u, v), uv(&shifted, u, v)));
+        }
+
+        let position = UvTransform::new(Arc::new(Position))
+            .tile(3., 3.)
+            .rotate(30.);
+        assert_eq!(position.value(0.4, 0.6, &p), p);
+    }
+
+    // A ramp from black at 0.2 to white at 0.6 with a hard edge to red at
+    // 0.6, driven by the height along y from 0 to 10.
+    fn height_ramp() -> RampTexture {
+        let stops = vec![
+            (0.2, Vec3(0., 0., 0.)),
+            (0.6, Vec3(1., 1., 1.)),
+            (0.6, Vec3(1., 0., 0.)),
+            (1., Vec3(0., 0., 1.)),
+        ];
+        let input = RampInput::Height {
+            axis: Vec3(0., 2., 0.),
+            min: 0.,
+            max: 10.,
+        };
+        RampTexture::new(stops, input).unwrap()
+    }
+
+    #[test]
+    // Test that ramps interpolate between their stops and keep the colors
+    // of the first and last stop outside of them.
+    fn test_ramp_between_stops() {
+        let ramp = height_ramp();
+        let at = |height: f32| ramp.value(0., 0., &Vec3(5., height, -3.));
+        let close = |a: Vec3, b: Vec3| (a - b).length() < 1e-5;
+        assert!(close(at(3.), Vec3(0.25, 0.25, 0.25)));
+        assert!(close(at(5.), Vec3(0.75, 0.75, 0.75)));
+        assert!(close(at(8.), Vec3(0.5, 0., 0.5)));
+        // The hard edge takes the later color at the stop.
+        assert!((at(5.999) - Vec3(1., 1., 1.)).length() < 1e-3);
+        assert_eq!(at(6.), Vec3(1., 0., 0.));
+        // Before the first stop and after the last stop.
+        assert_eq!(at(0.), Vec3(0., 0., 0.));
+        assert_eq!(at(1.), Vec3(0., 0., 0.));
+        assert_eq!(at(10.), Vec3(0., 0., 1.));
+        // Outside of 0-1, which is clamped by default.
+        assert_eq!(at(-4.), Vec3(0., 0., 0.));
+        assert_eq!(at(25.), Vec3(0., 0., 1.));
+
+        let single = RampTexture::new(vec![(0.5, Vec3(0.2, 0.3, 0.4))], *ramp.input()).unwrap();
+        for &height in &[-1., 2., 5., 7., 12.] {
+            assert_eq!(
+                single.value(0., 0., &Vec3(0., height, 0.)),
+                Vec3(0.2, 0.3, 0.4)
+            );
+        }
+    }
+
+    #[test]
+    // Test that positions outside of 0-1 are repeated or mirrored.
+    fn test_ramp_wrap() {
+        let close = |a: Vec3, b: Vec3| (a - b).length() < 1e-4;
+        let stops = vec![(0., Vec3(0., 0., 0.)), (1., Vec3(1., 1., 1.))];
+        let input = RampInput::Distance {
+            center: Vec3(1., 1., 1.),
+            radius: 2.,
+        };
+        let ramp = RampTexture::new(stops, input).unwrap();
+        let at =
+            |ramp: &RampTexture, distance: f32| ramp.value(0., 0., &Vec3(1. + distance, 1., 1.));
+        assert!(close(at(&ramp, 0.5), Vec3(0.25, 0.25, 0.25)));
+        assert_eq!(at(&ramp, 5.), Vec3(1., 1., 1.));
+
+        let repeat = ramp.clone().with_wrap(WrapMode::Repeat);
+        assert!(close(at(&repeat, 0.5), Vec3(0.25, 0.25, 0.25)));
+        assert!(close(at(&repeat, 2.5), Vec3(0.25, 0.25, 0.25)));
+        assert!(close(at(&repeat, 5.), Vec3(0.5, 0.5, 0.5)));
+
+        let mirror = ramp.with_wrap(WrapMode::Mirror);
+        assert!(close(at(&mirror, 2.5), Vec3(0.75, 0.75, 0.75)));
+        assert!(close(at(&mirror, 4.5), Vec3(0.25, 0.25, 0.25)));
+    }
+
+    #[test]
+    // Test that ramps are only created from sorted stops between 0 and 1.
+    fn test_ramp_validation() {
+        let input = RampInput::Facing {
+            up: Vec3(0., 1., 0.),
+        };
+        let color = Vec3(0.5, 0.5, 0.5);
+        let ramp = |positions: &[f32]| {
+            let stops = positions
+                .iter()
+                .map(|&position| (position, color))
+                .collect();
+            RampTexture::new(stops, input)
+        };
+        assert!(ramp(&[0., 0.5, 0.5, 1.]).is_ok());
+        assert_eq!(ramp(&[]).unwrap_err(), TextureError::NoStops);
+        assert_eq!(
+            ramp(&[0.2, 0.7, 0.3]).unwrap_err(),
+            TextureError::UnsortedStops { index: 2 }
+        );
+        assert_eq!(
+            ramp(&[0., 1.5]).unwrap_err(),
+            TextureError::StopOutOfRange { position: 1.5 }
+        );
+        assert!(matches!(
+            ramp(&[0., f32::NAN]),
+            Err(TextureError::StopOutOfRange { .. })
+        ));
+        let error = ramp(&[-0.1]).unwrap_err().to_string();
+        assert!(error.contains("-0.1"), "{}", error);
+    }
+
+    #[test]
+    // Test that a ramp facing up colors a sphere by its normal, also through
+    // a transform of the surface coordinates.
+    fn test_ramp_facing() {
+        let stops = vec![(0., Vec3(0., 0., 0.)), (1., Vec3(1., 1., 1.))];
+        let input = RampInput::Facing {
+            up: Vec3(0., 3., 0.),
+        };
+        let ramp = Arc::new(RampTexture::new(stops, input).unwrap());
+        let transformed = UvTransform::new(ramp.clone()).tile(2., 2.);
+        let sphere = Sphere::new(
+            Vec3(0., 0., 0.),
+            1.,
+            Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5))),
+        );
+        for &(origin, expected) in &[
+            (Vec3(0., 3., 0.), 1.),
+            (Vec3(0., -3., 0.), 0.),
+            (Vec3(3., 0., 0.), 0.5),
+            (Vec3(0., 3., 3.), 0.5 * (1. + 0.5f32.sqrt())),
+        ] {
+            let ray = Ray::new(origin, -origin);
+            let hit = sphere.intersect(&ray, 0.001, f32::MAX).unwrap();
+            for texture in [ramp.as_ref() as &dyn Texture, &transformed] {
+                let color = texture.value_at_hit(&hit);
+                assert!((color - Vec3(expected, expected, expected)).length() < 1e-5);
+            }
+        }
+        // Without a normal, the ramp reads as if it was perpendicular to up.
+        assert_eq!(ramp.value(0., 0., &Vec3(0., 1., 0.)), Vec3(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    // Test that cellular textures of F1 are black at the feature points,
+    // where F2 - F1 is not, stay between their colors, and repeat after the
+    // size of the lattice.
+    fn test_worley_texture() {
+        let worley = Worley::new(&mut StdRng::seed_from_u64(5));
+        let scale = 2.;
+        let cells = WorleyTexture::new(worley.clone()).with_scale(scale);
+        let cracks = cells.clone().with_feature(WorleyFeature::F2MinusF1);
+        for &(i, j, k) in &[(0, 0, 0), (-3, 1, 2), (4, -2, -1)] {
+            let point = worley.feature_point(i, j, k) / scale;
+            assert_eq!(cells.value(0., 0., &point), Vec3(0., 0., 0.));
+            assert!(cracks.value(0., 0., &point).x() > 0.);
+        }
+
+        let period = crate::perlin::POINT_COUNT as f32 / scale;
+        let features = [
+            WorleyFeature::F1,
+            WorleyFeature::F2,
+            WorleyFeature::F2MinusF1,
+        ];
+        for (index, &feature) in features.iter().enumerate() {
+            let texture = cells
+                .clone()
+                .with_feature(feature)
+                .with_colors(Vec3(0.2, 0.4, 0.), Vec3(0.8, 0.6, 1.));
+            for n in 0..50 {
+                let p = Vec3(0.13 * n as f32 - 3., 0.07 * n as f32, 1. - 0.11 * n as f32);
+                let color = texture.value(0., 0., &p);
+                assert!((0.2..=0.8).contains(&color.x()) && (0. ..=1.).contains(&color.z()));
+                for shift in [Vec3(period, 0., 0.), Vec3(-period, period, 2. * period)] {
+                    let shifted = texture.value(0., 0., &(p + shift));
+                    assert!((color - shifted).length() < 1e-3, "{} at {:?}", index, p);
+                }
+            }
+        }
+    }
+
+    #[test]
+    // Test that every face of a cube shows the texture projected along its
+    // normal, unstretched, and that curved surfaces blend the projections.
+    fn test_triplanar_cube() {
+        let material = Arc::new(Lambertian::new(Vec3(0.5, 0.5, 0.5)));
+        let cube = Cuboid::new(Vec3(-1., -1., -1.), Vec3(1., 1., 1.), material.clone());
+        let scale = 0.5;
+        let triplanar = Triplanar::new(Arc::new(Uv)).with_scale(scale);
+        let close = |a: Vec3, b: Vec3| (a - b).length() < 1e-5;
+        // The normal of each face, and the directions along which u and v
+        // grow on it.
+        let x = Vec3(1., 0., 0.);
+        let y = Vec3(0., 1., 0.);
+        let z = Vec3(0., 0., 1.);
+        for &(axis, along_u, along_v) in &[(x, z, y), (y, x, z), (z, x, y)] {
+            for &side in &[1., -1.] {
+                for &(a, b) in &[(0.3, -0.6), (-0.8, 0.1), (0.55, 0.75)] {
+                    // A point on the face, hit straight on.
+                    let target = side * axis + a * along_u + b * along_v;
+                    let ray = Ray::new(target + 3. * side * axis, -side * axis);
+                    let hit = cube.intersect(&ray, 0.001, f32::MAX).unwrap();
+                    assert!(close(hit.point_at_parameter, target));
+                    let color = triplanar.value_at_hit(&hit);
+                    assert!(
+                        close(color, Vec3(scale * a, scale * b, 0.)),
+                        "{:?} at {:?}",
+                        color,
+                        target
+                    );
+                }
+            }
+        }
+
+        // A sphere blends the projections by the normal, with weights
+        // summing to 1, as seen with a solid color.
+        let sphere = Sphere::new(Vec3(0., 0., 0.), 1., material);
+        let solid =
+            Triplanar::new(Arc::new(SolidColor::new(Vec3(0.2, 0.4, 0.6)))).with_sharpness(3.);
+        let ray = Ray::new(Vec3(3., 2., 1.), Vec3(-3., -2., -1.));
+        let hit = sphere.intersect(&ray, 0.001, f32::MAX).unwrap();
+        assert!(close(solid.value_at_hit(&hit), Vec3(0.2, 0.4, 0.6)));
+        let weights = solid.weights(&hit.normal);
+        assert!(weights.x() > weights.y() && weights.y() > weights.z() && weights.z() > 0.);
+        assert!((weights.x() + weights.y() + weights.z() - 1.).abs() < 1e-6);
+        // Without a normal, the projections are blended equally.
+        assert!(close(
+            solid.weights(&Vec3(0., 0., 0.)),
+            Vec3(1., 1., 1.) / 3.
+        ));
+        let p = Vec3(0.4, 0.8, 1.2);
+        assert!(close(
+            triplanar.value(0., 0., &p),
+            scale * Vec3(2., 2.8, 0.) / 3.
+        ));
+    }
+}
@@ -0,0 +1,303 @@
+//! Affine transformations of three-dimensional space.
+//!
+//! Transformations are represented by 4x4 matrices acting on homogeneous
+//! coordinates. Points have the homogeneous coordinate `w = 1` and are
+//! affected by translations, directions have `w = 0` and are not.
+//!
+//! Normals need special care: to stay perpendicular to the transformed
+//! surface they are transformed by the inverse transpose of the matrix.
+
+use crate::vec3::Vec3;
+use std::ops;
+
+/// A 4x4 matrix in row-major order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    rows: [[f32; 4]; 4],
+}
+
+impl Mat4 {
+    /// Create a matrix from its `rows`.
+    ///
+    /// ```
+    /// # use raytracer::transform::Mat4;
+    /// let m = Mat4::new([
+    ///     [1., 0., 0., 2.],
+    ///     [0., 1., 0., 0.],
+    ///     [0., 0., 1., 0.],
+    ///     [0., 0., 0., 1.],
+    /// ]);
+    /// assert_eq!(m.get(0, 3), 2.);
+    /// ```
+    pub fn new(rows: [[f32; 4]; 4]) -> Mat4 {
+        Mat4 { rows }
+    }
+
+    /// Return the identity matrix.
+    ///
+    /// ```
+    /// # use raytracer::transform::Mat4;
+    /// # use raytracer::vec3::Vec3;
+    /// let p = Vec3(1., 2., 3.);
+    /// assert_eq!(Mat4::identity().transform_point(&p), p);
+    /// ```
+    pub fn identity() -> Mat4 {
+        Mat4::scaling(Vec3(1., 1., 1.))
+    }
+
+    /// Return the matrix translating points by `offset`.
+    ///
+    /// ```
+    /// # use raytracer::transform::Mat4;
+    /// # use raytracer::vec3::Vec3;
+    /// let m = Mat4::translation(Vec3(1., 2., 3.));
+    /// assert_eq!(m.transform_point(&Vec3(0., 0., 0.)), Vec3(1., 2., 3.));
+    /// assert_eq!(m.transform_vector(&Vec3(1., 0., 0.)), Vec3(1., 0., 0.));
+    /// ```
+    pub fn translation(offset: Vec3) -> Mat4 {
+        Mat4::new([
+            [1., 0., 0., offset.x()],
+            [0., 1., 0., offset.y()],
+            [0., 0., 1., offset.z()],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    /// Return the matrix scaling each axis by the components of `factors`.
+    ///
+    /// ```
+    /// # use raytracer::transform::Mat4;
+    /// # use raytracer::vec3::Vec3;
+    /// let m = Mat4::scaling(Vec3(2., 1., 0.5));
+    /// assert_eq!(m.transform_point(&Vec3(1., 1., 1.)), Vec3(2., 1., 0.5));
+    /// ```
+    pub fn scaling(factors: Vec3) -> Mat4 {
+        Mat4::new([
+            [factors.x(), 0., 0., 0.],
+            [0., factors.y(), 0., 0.],
+            [0., 0., factors.z(), 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    /// Return the matrix rotating by `degrees` around the x-axis.
+    pub fn rotation_x(degrees: f32) -> Mat4 {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Mat4::new([
+            [1., 0., 0., 0.],
+            [0., cos, -sin, 0.],
+            [0., sin, cos, 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    /// Return the matrix rotating by `degrees` around the y-axis.
+    pub fn rotation_y(degrees: f32) -> Mat4 {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Mat4::new([
+            [cos, 0., sin, 0.],
+            [0., 1., 0., 0.],
+            [-sin, 0., cos, 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    /// Return the matrix rotating by `degrees` around the z-axis.
+    ///
+    /// Rotations follow the right-hand rule.
+    ///
+    /// ```
+    /// # use raytracer::transform::Mat4;
+    /// # use raytracer::vec3::Vec3;
+    /// let m = Mat4::rotation_z(90.);
+    /// let rotated = m.transform_vector(&Vec3(1., 0., 0.));
+    /// assert!((rotated - Vec3(0., 1., 0.)).length() < 1e-6);
+    /// ```
+    pub fn rotation_z(degrees: f32) -> Mat4 {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Mat4::new([
+            [cos, -sin, 0., 0.],
+            [sin, cos, 0., 0.],
+            [0., 0., 1., 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    /// Access the element in `row` and `column`.
+    pub fn get(&self, row: usize, column: usize) -> f32 {
+        self.rows[row][column]
+    }
+
+    /// Return the transpose of the matrix.
+    ///
+    /// ```
+    /// # use raytracer::transform::Mat4;
+    /// # use raytracer::vec3::Vec3;
+    /// let m = Mat4::translation(Vec3(1., 2., 3.));
+    /// assert_eq!(m.transpose().get(3, 1), 2.);
+    /// assert_eq!(m.transpose().transpose(), m);
+    /// ```
+    pub fn transpose(&self) -> Mat4 {
+        let mut rows = [[0.; 4]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, element) in row.iter_mut().enumerate() {
+                *element = self.rows[j][i];
+            }
+        }
+        Mat4::new(rows)
+    }
+
+    /// Return the inverse of the matrix, or `None` if it is singular.
+    ///
+    /// ```
+    /// # use raytracer::transform::Mat4;
+    /// # use raytracer::vec3::Vec3;
+    /// let m = Mat4::translation(Vec3(1., 2., 3.));
+    /// assert_eq!(m.inverse(), Some(Mat4::translation(Vec3(-1., -2., -3.))));
+    /// assert_eq!(Mat4::scaling(Vec3(1., 0., 1.)).inverse(), None);
+    /// ```
+    pub fn inverse(&self) -> Option<Mat4> {
+        // Gauss-Jordan elimination with partial pivoting, in double precision.
+        let mut a = [[0f64; 8]; 4];
+        for (i, row) in a.iter_mut().enumerate() {
+            for (j, element) in self.rows[i].iter().enumerate() {
+                row[j] = f64::from(*element);
+            }
+            row[4 + i] = 1.;
+        }
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|&i, &j| a[i][column].abs().partial_cmp(&a[j][column].abs()).unwrap())
+                .unwrap();
+            if a[pivot][column].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(column, pivot);
+            let scale = a[column][column];
+            for element in a[column].iter_mut() {
+                *element /= scale;
+            }
+            let pivot_row = a[column];
+            for (i, row) in a.iter_mut().enumerate() {
+                if i != column {
+                    let factor = row[column];
+                    for (element, pivot_element) in row.iter_mut().zip(pivot_row.iter()) {
+                        *element -= factor * pivot_element;
+                    }
+                }
+            }
+        }
+        let mut rows = [[0.; 4]; 4];
+        for (row, augmented) in rows.iter_mut().zip(a.iter()) {
+            for (element, value) in row.iter_mut().zip(augmented[4..].iter()) {
+                *element = *value as f32;
+            }
+        }
+        Some(Mat4::new(rows))
+    }
+
+    /// Transform a point, i.e. apply rotation, scaling and translation.
+    pub fn transform_point(&self, p: &Vec3) -> Vec3 {
+        let r = &self.rows;
+        Vec3(
+            r[0][0] * p.x() + r[0][1] * p.y() + r[0][2] * p.z() + r[0][3],
+            r[1][0] * p.x() + r[1][1] * p.y() + r[1][2] * p.z() + r[1][3],
+            r[2][0] * p.x() + r[2][1] * p.y() + r[2][2] * p.z() + r[2][3],
+        )
+    }
+
+    /// Transform a direction, i.e. apply rotation and scaling but no translation.
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let r = &self.rows;
+        Vec3(
+            r[0][0] * v.x() + r[0][1] * v.y() + r[0][2] * v.z(),
+            r[1][0] * v.x() + r[1][1] * v.y() + r[1][2] * v.z(),
+            r[2][0] * v.x() + r[2][1] * v.y() + r[2][2] * v.z(),
+        )
+    }
+
+    /// Transform a normal, given the inverse of the transformation as `self`.
+    ///
+    /// The result is the normal multiplied by the transpose of `self` and is
+    /// not normalized.
+    ///
+    /// ```
+    /// # use raytracer::transform::Mat4;
+    /// # use raytracer::vec3::Vec3;
+    /// // Stretching a 45° slope along x makes it flatter, so its normal steepens.
+    /// let m = Mat4::scaling(Vec3(2., 1., 1.));
+    /// let normal = m.inverse().unwrap().transform_normal(&Vec3(1., 1., 0.));
+    /// assert_eq!(normal, Vec3(0.5, 1., 0.));
+    /// ```
+    pub fn transform_normal(&self, n: &Vec3) -> Vec3 {
+        self.transpose().transform_vector(n)
+    }
+}
+
+impl Default for Mat4 {
+    fn default() -> Mat4 {
+        Mat4::identity()
+    }
+}
+
+impl ops::Mul<Mat4> for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, other: Mat4) -> Mat4 {
+        let mut rows = [[0.; 4]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, element) in row.iter_mut().enumerate() {
+                *element = (0..4).map(|k| self.rows[i][k] * other.rows[k][j]).sum();
+            }
+        }
+        Mat4::new(rows)
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: &Mat4, b: &Mat4) {
+        for i in 0..4 {
+            for j in 0..4 {
+                assert!(
+                    (a.get(i, j) - b.get(i, j)).abs() < 1e-5,
+                    "{:?} != {:?}",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    // Test that the product of a matrix and its inverse is the identity.
+    fn test_inverse() {
+        let m = Mat4::translation(Vec3(1., -2., 3.))
+            * Mat4::rotation_y(30.)
+            * Mat4::scaling(Vec3(2., 0.5, 3.))
+            * Mat4::new([
+                [1., 0.5, 0., 0.],
+                [0., 1., 0., 0.],
+                [0., 0., 1., 0.],
+                [0., 0., 0., 1.],
+            ]);
+        let inverse = m.inverse().unwrap();
+        assert_close(&(m * inverse), &Mat4::identity());
+        assert_close(&(inverse * m), &Mat4::identity());
+    }
+
+    #[test]
+    // Test that products apply the right-hand transformation first.
+    fn test_multiplication_order() {
+        let m = Mat4::translation(Vec3(1., 0., 0.)) * Mat4::scaling(Vec3(2., 2., 2.));
+        assert_eq!(m.transform_point(&Vec3(1., 1., 1.)), Vec3(3., 2., 2.));
+        let m = Mat4::rotation_x(90.) * Mat4::translation(Vec3(0., 1., 0.));
+        assert!((m.transform_point(&Vec3(0., 0., 0.)) - Vec3(0., 0., 1.)).length() < 1e-6);
+    }
+}
@@ -5,6 +5,20 @@
 //! the image library will be used) and vectors in
 //! three dimensional space.
 //!
+//! When a `Vec3` holds a color, its components are linear radiance
+//! values where `0.` is black and `1.` is full intensity, which is
+//! the convention assumed by all materials and the renderer. Colors
+//! given as 8-bit sRGB values can be converted with `Vec3::from_u8`
+//! or `Vec3::from_hex`.
+//!
+//! ```
+//! use raytracer::vec3::Vec3;
+//! let white = Vec3::from_u8(255, 255, 255);
+//! assert_eq!(white, Vec3(1., 1., 1.));
+//! let red = Vec3::from_hex("#ff0000").unwrap();
+//! assert_eq!(red, Vec3(1., 0., 0.));
+//! ```
+//!
 //! ```
 //! use raytracer::vec3::Vec3;
 //! let v = Vec3::new(1.,2.,3.);
@@ -59,11 +73,50 @@
 //! let cross_product = cross(&v1, &v2);
 //! ```
 
+use std::error::Error;
+use std::fmt;
+#[cfg(not(feature = "simd"))]
 use std::ops;
 
+/// Error returned when a hexadecimal color string cannot be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseColorError {
+    input: String,
+}
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid hex color {:?}, expected the form \"#rrggbb\"",
+            self.input
+        )
+    }
+}
+
+impl Error for ParseColorError {}
+
+/// Decode an 8-bit sRGB encoded channel into a linear value between 0 and 1.
+fn srgb_to_linear(value: u8) -> f32 {
+    let c = value as f32 / 255.;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(not(feature = "simd"))]
 #[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct Vec3(pub f32, pub f32, pub f32);
 
+#[cfg(all(feature = "simd", not(target_arch = "x86_64")))]
+compile_error!("the `simd` feature is only available on x86_64");
+#[cfg(feature = "simd")]
+mod simd;
+#[cfg(feature = "simd")]
+pub use self::simd::{cross, dot, Vec3};
+
 impl Vec3 {
     /// Create a new vector.
     ///
@@ -75,73 +128,81 @@ impl Vec3 {
         Vec3(x, y, z)
     }
 
-    /// Access the component red of the RGB code.
-    /// Only useful if the underlying data is color.
+    /// Create a linear color from 8-bit sRGB encoded components.
     ///
-    /// ```
-    /// # use raytracer::vec3::Vec3;
-    /// let red = Vec3::new(255., 0., 0.);
-    /// assert_eq!(red.r(), 255.);
-    /// ```
-    pub fn r(&self) -> f32 {
-        self.0
-    }
-
-    /// Access the component g of the RGB code.
-    /// Only useful if the underlying data is color.
+    /// This is the conversion to use for colors picked in an image editor,
+    /// since all materials expect linear components between 0 and 1.
     ///
     /// ```
     /// # use raytracer::vec3::Vec3;
-    /// let green = Vec3::new(0., 255., 0.);
-    /// assert_eq!(green.g(), 255.);
+    /// let color = Vec3::from_u8(255, 0, 0);
+    /// assert_eq!(color, Vec3(1., 0., 0.));
+    /// let grey = Vec3::from_u8(128, 128, 128);
+    /// assert!((grey.r() - 0.2158605).abs() < 1e-6);
     /// ```
-    pub fn g(&self) -> f32 {
-        self.1
+    pub fn from_u8(r: u8, g: u8, b: u8) -> Vec3 {
+        Vec3(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b))
     }
 
-    /// Access the component blue of the RGB code.
-    /// Only useful if the underlying data is color.
+    /// Create a linear color from an sRGB hex string like `"#c83232"`.
+    ///
+    /// The leading `#` is optional.
     ///
     /// ```
     /// # use raytracer::vec3::Vec3;
-    /// let blue = Vec3::new(0., 0., 255.);
-    /// assert_eq!(blue.b(), 255.);
+    /// let color = Vec3::from_hex("#c83232").unwrap();
+    /// assert_eq!(color, Vec3::from_u8(200, 50, 50));
+    /// assert!(Vec3::from_hex("#c8323").is_err());
     /// ```
-    pub fn b(&self) -> f32 {
-        self.2
+    pub fn from_hex(hex: &str) -> Result<Vec3, ParseColorError> {
+        let error = || ParseColorError {
+            input: hex.to_string(),
+        };
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        if digits.len() != 6 || !digits.is_ascii() {
+            return Err(error());
+        }
+        let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|_| error());
+        Ok(Vec3::from_u8(channel(0)?, channel(2)?, channel(4)?))
     }
 
-    /// Access the x-component of the vector.
+    /// Access the component red of the RGB code.
+    /// Only useful if the underlying data is color, in which
+    /// case the value is linear and lies between 0 and 1.
     ///
     /// ```
     /// # use raytracer::vec3::Vec3;
-    /// let v = Vec3::new(1., 2., 3.);
-    /// assert_eq!(v.x(), 1.);
+    /// let red = Vec3::new(1., 0., 0.);
+    /// assert_eq!(red.r(), 1.);
     /// ```
-    pub fn x(&self) -> f32 {
-        self.0
+    pub fn r(&self) -> f32 {
+        self.x()
     }
 
-    /// Access the y-component of the vector.
+    /// Access the component g of the RGB code.
+    /// Only useful if the underlying data is color, in which
+    /// case the value is linear and lies between 0 and 1.
     ///
     /// ```
     /// # use raytracer::vec3::Vec3;
-    /// let v = Vec3::new(1., 2., 3.);
-    /// assert_eq!(v.y(), 2.);
+    /// let green = Vec3::new(0., 1., 0.);
+    /// assert_eq!(green.g(), 1.);
     /// ```
-    pub fn y(&self) -> f32 {
-        self.1
+    pub fn g(&self) -> f32 {
+        self.y()
     }
 
-    /// Access the z-component of the vector.
+    /// Access the component blue of the RGB code.
+    /// Only useful if the underlying data is color, in which
+    /// case the value is linear and lies between 0 and 1.
     ///
     /// ```
     /// # use raytracer::vec3::Vec3;
-    /// let v = Vec3::new(1., 2., 3.);
-    /// assert_eq!(v.z(), 3.);
+    /// let blue = Vec3::new(0., 0., 1.);
+    /// assert_eq!(blue.b(), 1.);
     /// ```
-    pub fn z(&self) -> f32 {
-        self.2
+    pub fn b(&self) -> f32 {
+        self.z()
     }
 
     /// Compute the momentum squared of a vector.
@@ -154,7 +215,7 @@ impl Vec3 {
     /// assert_eq!(v.squared_length(), 25.);
     /// ```
     pub fn squared_length(&self) -> f32 {
-        self.0.powi(2) + self.1.powi(2) + self.2.powi(2)
+        self.x().powi(2) + self.y().powi(2) + self.z().powi(2)
     }
 
     /// Compute modulus of a vector.
@@ -178,11 +239,48 @@ impl Vec3 {
     /// assert_eq!(v.make_unit_vector().length(), 1.);
     /// ```
     pub fn make_unit_vector(&self) -> Vec3 {
-        Vec3::new(self.0, self.1, self.2) / self.length()
+        *self / self.length()
+    }
+}
+
+#[cfg(not(feature = "simd"))]
+impl Vec3 {
+    /// Access the x-component of the vector.
+    ///
+    /// ```
+    /// # use raytracer::vec3::Vec3;
+    /// let v = Vec3::new(1., 2., 3.);
+    /// assert_eq!(v.x(), 1.);
+    /// ```
+    pub fn x(&self) -> f32 {
+        self.0
+    }
+
+    /// Access the y-component of the vector.
+    ///
+    /// ```
+    /// # use raytracer::vec3::Vec3;
+    /// let v = Vec3::new(1., 2., 3.);
+    /// assert_eq!(v.y(), 2.);
+    /// ```
+    pub fn y(&self) -> f32 {
+        self.1
+    }
+
+    /// Access the z-component of the vector.
+    ///
+    /// ```
+    /// # use raytracer::vec3::Vec3;
+    /// let v = Vec3::new(1., 2., 3.);
+    /// assert_eq!(v.z(), 3.);
+    /// ```
+    pub fn z(&self) -> f32 {
+        self.2
     }
 }
 
 /// Negate a Vec3.
+#[cfg(not(feature = "simd"))]
 impl ops::Neg for Vec3 {
     type Output = Vec3;
     fn neg(self) -> Vec3 {
@@ -191,6 +289,7 @@ impl ops::Neg for Vec3 {
 }
 
 /// Add two vectors.
+#[cfg(not(feature = "simd"))]
 impl ops::Add<Vec3> for Vec3 {
     type Output = Vec3;
 
@@ -199,6 +298,7 @@ impl ops::Add<Vec3> for Vec3 {
     }
 }
 
+#[cfg(not(feature = "simd"))]
 impl ops::AddAssign for Vec3 {
     fn add_assign(&mut self, rhs: Self) {
         *self = Vec3(self.0 + rhs.0, self.1 + rhs.1, self.2 + rhs.2);
@@ -206,6 +306,7 @@ impl ops::AddAssign for Vec3 {
 }
 
 /// Subtract two vectors.
+#[cfg(not(feature = "simd"))]
 impl ops::Sub<Vec3> for Vec3 {
     type Output = Vec3;
 
@@ -214,6 +315,7 @@ impl ops::Sub<Vec3> for Vec3 {
     }
 }
 
+#[cfg(not(feature = "simd"))]
 impl ops::SubAssign for Vec3 {
     fn sub_assign(&mut self, rhs: Self) {
         *self = Vec3(self.0 - rhs.0, self.1 - rhs.1, self.2 - rhs.2);
@@ -221,6 +323,7 @@ impl ops::SubAssign for Vec3 {
 }
 
 /// Multiply two vectors component-wise
+#[cfg(not(feature = "simd"))]
 impl ops::Mul<Vec3> for Vec3 {
     type Output = Vec3;
 
@@ -230,6 +333,7 @@ impl ops::Mul<Vec3> for Vec3 {
 }
 
 /// Multiply the components by the vector by the components of rhs.
+#[cfg(not(feature = "simd"))]
 impl ops::MulAssign<Vec3> for Vec3 {
     fn mul_assign(&mut self, rhs: Vec3) {
         *self = Vec3(self.0 * rhs.0, self.1 * rhs.1, self.2 * rhs.2);
@@ -237,6 +341,7 @@ impl ops::MulAssign<Vec3> for Vec3 {
 }
 
 /// Multiply a vector by a number from the right.
+#[cfg(not(feature = "simd"))]
 impl ops::Mul<f32> for Vec3 {
     type Output = Vec3;
 
@@ -245,6 +350,7 @@ impl ops::Mul<f32> for Vec3 {
     }
 }
 
+#[cfg(not(feature = "simd"))]
 impl ops::MulAssign<f32> for Vec3 {
     fn mul_assign(&mut self, rhs: f32) {
         *self = Vec3(self.0 * rhs, self.1 * rhs, self.2 * rhs);
@@ -252,6 +358,7 @@ impl ops::MulAssign<f32> for Vec3 {
 }
 
 /// Multiply a vector by a number from the left.
+#[cfg(not(feature = "simd"))]
 impl ops::Mul<Vec3> for f32 {
     type Output = Vec3;
 
@@ -260,6 +367,17 @@ impl ops::Mul<Vec3> for f32 {
     }
 }
 
+/// Divide two vectors component-wise
+#[cfg(not(feature = "simd"))]
+impl ops::Div<Vec3> for Vec3 {
+    type Output = Vec3;
+
+    fn div(self, rhs: Vec3) -> Vec3 {
+        Vec3(self.x() / rhs.x(), self.y() / rhs.y(), self.z() / rhs.z())
+    }
+}
+
+#[cfg(not(feature = "simd"))]
 impl ops::Div<f32> for Vec3 {
     type Output = Vec3;
 
@@ -268,6 +386,7 @@ impl ops::Div<f32> for Vec3 {
     }
 }
 
+#[cfg(not(feature = "simd"))]
 impl ops::DivAssign<f32> for Vec3 {
     fn div_assign(&mut self, rhs: f32) {
         *self = Vec3(self.0 / rhs, self.1 / rhs, self.2 / rhs);
@@ -283,6 +402,7 @@ impl ops::DivAssign<f32> for Vec3 {
 /// let v2 = Vec3(4., 5., 6.);
 /// assert_eq!(dot(&v1, &v2), 32.);
 /// ```
+#[cfg(not(feature = "simd"))]
 pub fn dot(v1: &Vec3, v2: &Vec3) -> f32 {
     v1.0 * v2.0 + v1.1 * v2.1 + v1.2 * v2.2
 }
@@ -308,6 +428,7 @@ pub fn dot(v1: &Vec3, v2: &Vec3) -> f32 {
 /// assert_eq!(cross_product.y(), 0.);
 /// assert_eq!(cross_product.z(), 0.);
 /// ```
+#[cfg(not(feature = "simd"))]
 pub fn cross(v1: &Vec3, v2: &Vec3) -> Vec3 {
     Vec3(
         v1.1 * v2.2 - v1.2 * v2.1,
@@ -471,4 +592,30 @@ mod tests {
         assert_eq!(v1.y(), 1.);
         assert_eq!(v1.z(), 1.5);
     }
+
+    #[test]
+    // Test the sRGB decoding of 8-bit color components.
+    fn from_u8_decodes_srgb() {
+        assert_eq!(Vec3::from_u8(0, 0, 0), Vec3(0., 0., 0.));
+        assert_eq!(Vec3::from_u8(255, 255, 255), Vec3(1., 1., 1.));
+        // Below the linear threshold the curve is a straight line.
+        assert!((Vec3::from_u8(10, 0, 0).r() - 10. / 255. / 12.92).abs() < 1e-7);
+        let c = Vec3::from_u8(200, 50, 50);
+        assert!((c.r() - 0.5775804).abs() < 1e-6);
+        assert!((c.g() - 0.0318960).abs() < 1e-6);
+        assert!((c.b() - 0.0318960).abs() < 1e-6);
+    }
+
+    #[test]
+    // Test parsing of hexadecimal color strings.
+    fn from_hex_parses_colors() {
+        assert_eq!(Vec3::from_hex("#c83232"), Ok(Vec3::from_u8(200, 50, 50)));
+        assert_eq!(Vec3::from_hex("C83232"), Ok(Vec3::from_u8(200, 50, 50)));
+        assert_eq!(Vec3::from_hex("#000000"), Ok(Vec3(0., 0., 0.)));
+        assert!(Vec3::from_hex("").is_err());
+        assert!(Vec3::from_hex("#c8323").is_err());
+        assert!(Vec3::from_hex("#c832322").is_err());
+        assert!(Vec3::from_hex("#g83232").is_err());
+        assert!(Vec3::from_hex("#c832é").is_err());
+    }
 }
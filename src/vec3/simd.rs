@@ -0,0 +1,230 @@
+//! The representation of `Vec3` used with the `simd` feature.
+//!
+//! The components are held in the lanes of a 128-bit SSE register, with a
+//! fourth padding lane that is kept at zero. The public interface is the one
+//! of the tuple struct used without the feature, except that the components
+//! can only be accessed through `x()`, `y()` and `z()`.
+//!
+//! SSE and SSE2 are part of every x86_64 target, which is why the intrinsics
+//! can always be called here.
+
+use std::arch::x86_64::*;
+use std::fmt;
+use std::ops;
+
+/// A vector of three components, stored in the first lanes of a SSE register.
+#[derive(Clone, Copy)]
+pub struct Vec3 {
+    lanes: __m128,
+}
+
+/// Create a vector from its components.
+///
+/// This function takes the place of the constructor of the tuple struct,
+/// such that `Vec3(x, y, z)` reads the same with and without the feature.
+#[allow(non_snake_case)]
+#[inline]
+pub fn Vec3(x: f32, y: f32, z: f32) -> Vec3 {
+    Vec3 {
+        lanes: unsafe { _mm_set_ps(0., z, y, x) },
+    }
+}
+
+impl Vec3 {
+    #[inline]
+    fn from_lanes(lanes: __m128) -> Vec3 {
+        Vec3 { lanes }
+    }
+
+    #[inline]
+    fn to_array(self) -> [f32; 4] {
+        let mut array = [0.; 4];
+        unsafe { _mm_storeu_ps(array.as_mut_ptr(), self.lanes) };
+        array
+    }
+
+    /// Access the x-component of the vector.
+    #[inline]
+    pub fn x(&self) -> f32 {
+        unsafe { _mm_cvtss_f32(self.lanes) }
+    }
+
+    /// Access the y-component of the vector.
+    #[inline]
+    pub fn y(&self) -> f32 {
+        self.to_array()[1]
+    }
+
+    /// Access the z-component of the vector.
+    #[inline]
+    pub fn z(&self) -> f32 {
+        self.to_array()[2]
+    }
+}
+
+impl fmt::Debug for Vec3 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Vec3")
+            .field(&self.x())
+            .field(&self.y())
+            .field(&self.z())
+            .finish()
+    }
+}
+
+impl Default for Vec3 {
+    fn default() -> Vec3 {
+        Vec3(0., 0., 0.)
+    }
+}
+
+impl PartialEq for Vec3 {
+    fn eq(&self, other: &Vec3) -> bool {
+        self.x() == other.x() && self.y() == other.y() && self.z() == other.z()
+    }
+}
+
+/// Negate a Vec3.
+impl ops::Neg for Vec3 {
+    type Output = Vec3;
+
+    #[inline]
+    fn neg(self) -> Vec3 {
+        // Flip the sign bits, such that zeros are negated as well.
+        Vec3::from_lanes(unsafe { _mm_xor_ps(self.lanes, _mm_set1_ps(-0.)) })
+    }
+}
+
+/// Add two vectors.
+impl ops::Add<Vec3> for Vec3 {
+    type Output = Vec3;
+
+    #[inline]
+    fn add(self, rhs: Vec3) -> Vec3 {
+        Vec3::from_lanes(unsafe { _mm_add_ps(self.lanes, rhs.lanes) })
+    }
+}
+
+impl ops::AddAssign for Vec3 {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+/// Subtract two vectors.
+impl ops::Sub<Vec3> for Vec3 {
+    type Output = Vec3;
+
+    #[inline]
+    fn sub(self, rhs: Vec3) -> Vec3 {
+        Vec3::from_lanes(unsafe { _mm_sub_ps(self.lanes, rhs.lanes) })
+    }
+}
+
+impl ops::SubAssign for Vec3 {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+/// Multiply two vectors component-wise
+impl ops::Mul<Vec3> for Vec3 {
+    type Output = Vec3;
+
+    #[inline]
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        Vec3::from_lanes(unsafe { _mm_mul_ps(self.lanes, rhs.lanes) })
+    }
+}
+
+/// Multiply the components by the vector by the components of rhs.
+impl ops::MulAssign<Vec3> for Vec3 {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Vec3) {
+        *self = *self * rhs;
+    }
+}
+
+/// Multiply a vector by a number from the right.
+impl ops::Mul<f32> for Vec3 {
+    type Output = Vec3;
+
+    #[inline]
+    fn mul(self, rhs: f32) -> Vec3 {
+        Vec3::from_lanes(unsafe { _mm_mul_ps(self.lanes, _mm_set1_ps(rhs)) })
+    }
+}
+
+impl ops::MulAssign<f32> for Vec3 {
+    #[inline]
+    fn mul_assign(&mut self, rhs: f32) {
+        *self = *self * rhs;
+    }
+}
+
+/// Multiply a vector by a number from the left.
+impl ops::Mul<Vec3> for f32 {
+    type Output = Vec3;
+
+    #[inline]
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        rhs * self
+    }
+}
+
+/// Divide two vectors component-wise
+impl ops::Div<Vec3> for Vec3 {
+    type Output = Vec3;
+
+    #[inline]
+    fn div(self, rhs: Vec3) -> Vec3 {
+        // The padding lane becomes NaN, which is never read.
+        Vec3::from_lanes(unsafe { _mm_div_ps(self.lanes, rhs.lanes) })
+    }
+}
+
+impl ops::Div<f32> for Vec3 {
+    type Output = Vec3;
+
+    #[inline]
+    fn div(self, rhs: f32) -> Vec3 {
+        Vec3::from_lanes(unsafe { _mm_div_ps(self.lanes, _mm_set1_ps(rhs)) })
+    }
+}
+
+impl ops::DivAssign<f32> for Vec3 {
+    #[inline]
+    fn div_assign(&mut self, rhs: f32) {
+        *self = *self / rhs;
+    }
+}
+
+/// Compute the dot product between two vectors.
+///
+/// The products are computed in parallel and summed in the same order as
+/// without the `simd` feature, which gives the same result.
+#[inline]
+pub fn dot(v1: &Vec3, v2: &Vec3) -> f32 {
+    let products = (*v1 * *v2).to_array();
+    products[0] + products[1] + products[2]
+}
+
+/// Compute the cross product between two vectors.
+#[inline]
+pub fn cross(v1: &Vec3, v2: &Vec3) -> Vec3 {
+    // Rotate the lanes to (y, z, x) and (z, x, y), keeping the padding last.
+    const YZX: i32 = 0b11_00_10_01;
+    const ZXY: i32 = 0b11_01_00_10;
+    unsafe {
+        let a_yzx = _mm_shuffle_ps::<YZX>(v1.lanes, v1.lanes);
+        let a_zxy = _mm_shuffle_ps::<ZXY>(v1.lanes, v1.lanes);
+        let b_yzx = _mm_shuffle_ps::<YZX>(v2.lanes, v2.lanes);
+        let b_zxy = _mm_shuffle_ps::<ZXY>(v2.lanes, v2.lanes);
+        Vec3::from_lanes(_mm_sub_ps(
+            _mm_mul_ps(a_yzx, b_zxy),
+            _mm_mul_ps(a_zxy, b_yzx),
+        ))
+    }
+}
@@ -0,0 +1,213 @@
+//! Worley noise, also called cellular or Voronoi noise.
+//!
+//! Space is divided into the cells of an integer lattice, and every cell
+//! holds one feature point, jittered randomly within the cell. The noise at
+//! a point is given by the distances to the closest feature points: the
+//! distance `F1` to the nearest one is zero on the feature points and grows
+//! towards the borders of their Voronoi cells, while the difference between
+//! the distance `F2` to the second nearest one and `F1` vanishes on the
+//! borders, which gives cracks, cells and scales.
+//!
+//! As for Perlin noise, the jitters are picked through three permutation
+//! tables filled from a random number generator, such that two generators
+//! created from equally seeded random number generators give the exact same
+//! noise, which repeats after 256 cells along each axis.
+
+use rand::Rng;
+use rand::RngCore;
+
+use crate::perlin::permutation;
+use crate::perlin::wrap;
+use crate::perlin::POINT_COUNT;
+use crate::vec3::Vec3;
+
+/// A generator of Worley noise.
+///
+/// ```
+/// # use raytracer::worley::Worley;
+/// # use rand::rngs::StdRng;
+/// # use rand::SeedableRng;
+/// # use raytracer::vec3::Vec3;
+/// let worley = Worley::new(&mut StdRng::seed_from_u64(0));
+/// // The nearest feature point of a feature point is itself.
+/// let (f1, f2) = worley.distances(&worley.feature_point(1, 2, 3));
+/// assert_eq!(f1, 0.);
+/// assert!(f2 > 0.);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Worley {
+    jitters: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Worley {
+    /// Create a noise generator whose tables are drawn from `rng`.
+    pub fn new(rng: &mut dyn RngCore) -> Worley {
+        let jitters = (0..POINT_COUNT)
+            .map(|_| Vec3(rng.gen(), rng.gen(), rng.gen()))
+            .collect();
+        Worley {
+            jitters,
+            perm_x: permutation(rng),
+            perm_y: permutation(rng),
+            perm_z: permutation(rng),
+        }
+    }
+
+    /// Return the feature point of the lattice cell `(i, j, k)`, which lies
+    /// within the cell spanning from `(i, j, k)` to `(i + 1, j + 1, k + 1)`.
+    pub fn feature_point(&self, i: i64, j: i64, k: i64) -> Vec3 {
+        let jitter =
+            &self.jitters[self.perm_x[wrap(i)] ^ self.perm_y[wrap(j)] ^ self.perm_z[wrap(k)]];
+        Vec3(i as f32, j as f32, k as f32) + *jitter
+    }
+
+    /// Return the distances `(F1, F2)` from the point `p` to the nearest and
+    /// the second nearest feature point.
+    ///
+    /// The feature points are searched in the cell of the point and the 26
+    /// cells around it. These hold the nearest feature points in all but rare
+    /// arrangements of the jitters, such that the distances are continuous
+    /// across the cells. `F1` is at most `√3` and `F2` at most `√6`.
+    pub fn distances(&self, p: &Vec3) -> (f32, f32) {
+        let (i, j, k) = (
+            p.x().floor() as i64,
+            p.y().floor() as i64,
+            p.z().floor() as i64,
+        );
+        let (mut first, mut second) = (f32::MAX, f32::MAX);
+        for di in -1..=1 {
+            for dj in -1..=1 {
+                for dk in -1..=1 {
+                    let distance =
+                        (self.feature_point(i + di, j + dj, k + dk) - *p).squared_length();
+                    if distance < first {
+                        second = first;
+                        first = distance;
+                    } else if distance < second {
+                        second = distance;
+                    }
+                }
+            }
+        }
+        (first.sqrt(), second.sqrt())
+    }
+}
+
+// ------------------------------------------------------------
+// ----------------------- UNIT TESTS -------------------------
+// ------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::dot;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    // The points of a grid spanning several lattice cells, including
+    // negative coordinates.
+    fn grid() -> impl Iterator<Item = Vec3> {
+        (0..12).flat_map(|i| {
+            (0..12).flat_map(move |j| {
+                (0..12).map(move |k| {
+                    Vec3(
+                        -3.1 + 0.57 * i as f32,
+                        -2.9 + 0.61 * j as f32,
+                        -3.3 + 0.53 * k as f32,
+                    )
+                })
+            })
+        })
+    }
+
+    #[test]
+    // Test that the feature points lie in their cells, and that the distances
+    // are ordered and within their bounds.
+    fn test_worley_range() {
+        let worley = Worley::new(&mut StdRng::seed_from_u64(0));
+        for p in grid() {
+            let (i, j, k) = (
+                p.x().floor() as i64,
+                p.y().floor() as i64,
+                p.z().floor() as i64,
+            );
+            let point = worley.feature_point(i, j, k);
+            let cell = point - Vec3(i as f32, j as f32, k as f32);
+            assert!([cell.x(), cell.y(), cell.z()]
+                .iter()
+                .all(|c| (0. ..1.).contains(c)));
+            let (f1, f2) = worley.distances(&p);
+            assert!(0. <= f1 && f1 <= f2, "{} {} at {:?}", f1, f2, p);
+            assert!(f1 <= 3f32.sqrt() && f2 <= 6f32.sqrt());
+            assert!(f1 <= (point - p).length());
+        }
+    }
+
+    #[test]
+    // Test that the distances are those to the feature points at the feature
+    // points themselves.
+    fn test_worley_feature_points() {
+        let worley = Worley::new(&mut StdRng::seed_from_u64(1));
+        for &(i, j, k) in &[(0, 0, 0), (-1, 2, -3), (7, -5, 4)] {
+            let point = worley.feature_point(i, j, k);
+            let (f1, f2) = worley.distances(&point);
+            assert_eq!(f1, 0.);
+            let nearest_other = (-1..=1)
+                .flat_map(|di| (-1..=1).flat_map(move |dj| (-1..=1).map(move |dk| (di, dj, dk))))
+                .filter(|&offset| offset != (0, 0, 0))
+                .map(|(di, dj, dk)| (worley.feature_point(i + di, j + dj, k + dk) - point).length())
+                .fold(f32::MAX, f32::min);
+            assert_eq!(f2, nearest_other);
+        }
+    }
+
+    #[test]
+    // Test that the nearest distance has no discontinuity between cells.
+    fn test_worley_continuity() {
+        let worley = Worley::new(&mut StdRng::seed_from_u64(2));
+        let step = 1e-3;
+        for p in grid().step_by(7) {
+            for &axis in &[Vec3(1., 0., 0.), Vec3(0., 1., 0.), Vec3(0., 0., 1.)] {
+                // Move the point onto the lattice plane along the axis.
+                let height = dot(&p, &axis);
+                let on_plane = p + (height.round() - height) * axis;
+                let (before, _) = worley.distances(&(on_plane - 0.5 * step * axis));
+                let (after, _) = worley.distances(&(on_plane + 0.5 * step * axis));
+                assert!(
+                    (before - after).abs() <= step,
+                    "jump from {} to {} at {:?}",
+                    before,
+                    after,
+                    on_plane
+                );
+            }
+        }
+    }
+
+    #[test]
+    // Test that generators with the same seed agree exactly, others do not, and
+    // that the noise repeats after the size of the tables.
+    fn test_worley_seed() {
+        let first = Worley::new(&mut StdRng::seed_from_u64(42));
+        let second = Worley::new(&mut StdRng::seed_from_u64(42));
+        let other = Worley::new(&mut StdRng::seed_from_u64(43));
+        assert!(grid().all(|p| first.distances(&p) == second.distances(&p)));
+        assert!(grid().any(|p| first.distances(&p) != other.distances(&p)));
+
+        let period = POINT_COUNT as f32;
+        for p in grid().step_by(11) {
+            let (f1, f2) = first.distances(&p);
+            for &shift in &[
+                Vec3(period, 0., 0.),
+                Vec3(0., -period, period),
+                Vec3(period, period, -period),
+            ] {
+                let (g1, g2) = first.distances(&(p + shift));
+                assert!((f1 - g1).abs() < 1e-3 && (f2 - g2).abs() < 1e-3);
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Count the heap allocations of a render. The counting allocator is global,
+//! which is why this test lives in a binary of its own.
+
+use raytracer::camera::Camera;
+use raytracer::materials::{Dielectric, Lambertian, Metal};
+use raytracer::objects::sphere::Sphere;
+use raytracer::objects::HitableList;
+use raytracer::render::{render, RenderSettings};
+use raytracer::vec3::Vec3;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// Count the allocations and reallocations, and leave them to the system.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+// The number of allocations while running `f`.
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+#[test]
+// Test that the number of allocations of a render does not grow with its pixels and samples.
+fn test_render_allocations() {
+    let world = HitableList::new(vec![
+        Box::new(Sphere::new(
+            Vec3(0., -100.5, -1.),
+            100.,
+            Arc::new(Lambertian::new(Vec3(0.8, 0.8, 0.))),
+        )),
+        Box::new(Sphere::new(
+            Vec3(-1., 0., -1.),
+            0.5,
+            Arc::new(Dielectric::new(1.5)),
+        )),
+        Box::new(Sphere::new(
+            Vec3(1., 0., -1.),
+            0.5,
+            Arc::new(Metal::new(Vec3(0.8, 0.6, 0.2), 0.3)),
+        )),
+    ]);
+    let camera = Camera::new(
+        Vec3(0., 0., 1.),
+        Vec3(0., 0., -1.),
+        Vec3(0., 1., 0.),
+        60.,
+        1.,
+        0.1,
+        2.,
+    );
+    // Start the threads of the pool.
+    let warm_up = RenderSettings {
+        width: 8,
+        height: 8,
+        samples: 1,
+        ..RenderSettings::default()
+    };
+    render(&world, &camera, &warm_up);
+    for packets in [false, true] {
+        // The same number of tiles, with 64 times the samples.
+        let small = RenderSettings {
+            width: 32,
+            height: 32,
+            samples: 1,
+            tile_size: 8,
+            packets,
+            ..RenderSettings::default()
+        };
+        let large = RenderSettings {
+            width: 128,
+            height: 128,
+            samples: 4,
+            tile_size: 32,
+            ..small.clone()
+        };
+        let count = |settings: &RenderSettings| {
+            allocations(|| {
+                render(&world, &camera, settings);
+            })
+        };
+        let small = count(&small);
+        assert_eq!(count(&large), small);
+        // The image, and the rows of the 16 tiles.
+        assert!(small <= 2 * 16 + 8, "{} allocations", small);
+    }
+}